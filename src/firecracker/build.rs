@@ -3,9 +3,14 @@
 
 use std::path::Path;
 
+use serde_json::Value;
+
 const ADVANCED_BINARY_FILTER_FILE_NAME: &str = "seccomp_filter.bpf";
+const DEBUG_API_FILTER_FILE_NAME: &str = "seccomp_filter_debug_api.json";
 
 const JSON_DIR: &str = "../../resources/seccomp";
+// Rules added to the default filters of builds with the `debug-api` feature.
+const DEBUG_API_JSON_DIR: &str = "../../resources/seccomp/debug-api";
 const SECCOMPILER_SRC_DIR: &str = "../seccompiler/src";
 
 // This script is run on every modification in the target-specific JSON file in `resources/seccomp`.
@@ -23,6 +28,7 @@ fn main() {
     let target_arch = std::env::var("CARGO_CFG_TARGET_ARCH").expect("Missing target arch.");
 
     let seccomp_json_path = format!("{}/{}.json", JSON_DIR, target);
+    let debug_api_json_path = format!("{}/{}.json", DEBUG_API_JSON_DIR, target);
     // If the current target doesn't have a default filter, or if we're building a debug binary,
     // use a default, empty filter.
     // This is to make sure that Firecracker builds even with libc toolchains for which we don't
@@ -40,6 +46,16 @@ fn main() {
             target
         );
         format!("{}/unimplemented.json", JSON_DIR)
+    } else if std::env::var_os("CARGO_FEATURE_DEBUG_API").is_some()
+        && Path::new(&debug_api_json_path).exists()
+    {
+        // The debug API needs a few more syscalls, which only builds with the `debug-api`
+        // feature allow.
+        println!("cargo:rerun-if-changed={}", debug_api_json_path);
+        println!("cargo:rerun-if-changed={}", seccomp_json_path);
+        let merged_json_path = format!("{}/{}", out_dir, DEBUG_API_FILTER_FILE_NAME);
+        merge_filters(&seccomp_json_path, &debug_api_json_path, &merged_json_path);
+        merged_json_path
     } else {
         seccomp_json_path
     };
@@ -54,3 +70,26 @@ fn main() {
     seccompiler::compile_bpf(&seccomp_json_path, &target_arch, &out_path, false)
         .expect("Cannot compile seccomp filters");
 }
+
+// Appends the rules of each thread category of the JSON filters at `extra_path` to the filters of
+// the same category at `base_path`, and writes the result to `out_path`.
+fn merge_filters(base_path: &str, extra_path: &str, out_path: &str) {
+    let read_json = |path: &str| -> Value {
+        let content = std::fs::read_to_string(path).expect("Cannot read seccomp filters");
+        serde_json::from_str(&content).expect("Invalid seccomp filters")
+    };
+    let mut base = read_json(base_path);
+    let extra = read_json(extra_path);
+
+    for (thread, filter) in extra.as_object().expect("Invalid seccomp filters") {
+        let rules = filter["filter"]
+            .as_array()
+            .expect("Invalid seccomp filters");
+        base[thread]["filter"]
+            .as_array_mut()
+            .unwrap_or_else(|| panic!("No default seccomp filter for thread {thread}"))
+            .extend(rules.iter().cloned());
+    }
+
+    std::fs::write(out_path, base.to_string()).expect("Cannot write seccomp filters");
+}
@@ -0,0 +1,325 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deduplication of retried API requests.
+//!
+//! Clients may attach an `X-Idempotency-Key` header to a request. The response of a request
+//! which succeeded is remembered for a while, and a request carrying the same key is answered
+//! with that response instead of being applied again. Failed requests are not remembered, so that
+//! their retries are applied. A request whose method, path or body differs from the remembered
+//! request of its key is rejected, since it is not a retry.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use micro_http::{Body, MediaType, Method, Request, Response, StatusCode, Version};
+
+/// Name of the header carrying the idempotency key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "X-Idempotency-Key";
+/// Number of responses remembered by default.
+pub const DEFAULT_IDEMPOTENCY_CAPACITY: usize = 1000;
+/// Time, in seconds, during which a response is remembered by default.
+pub const DEFAULT_IDEMPOTENCY_TTL_S: u64 = 60;
+
+#[derive(Debug)]
+struct CachedResponse {
+    method: Method,
+    path: String,
+    // Hash of the request body, so that bodies of up to `HTTP_MAX_PAYLOAD_SIZE` bytes need not
+    // be remembered.
+    body_hash: u64,
+    http_version: Version,
+    status: StatusCode,
+    content_type: MediaType,
+    deprecation: bool,
+    allow: Vec<Method>,
+    body: Option<Body>,
+    completed_us: u64,
+}
+
+impl CachedResponse {
+    fn to_response(&self) -> Response {
+        let mut response = Response::new(self.http_version, self.status);
+        response.set_content_type(self.content_type);
+        if self.deprecation {
+            response.set_deprecation();
+        }
+        if !self.allow.is_empty() {
+            response.set_allow(self.allow.clone());
+        }
+        if let Some(body) = self.body.clone() {
+            response.set_body(body);
+        }
+        response
+    }
+}
+
+/// Remembers the responses of the most recent requests carrying an idempotency key.
+#[derive(Debug)]
+pub struct RequestDeduplicator {
+    capacity: usize,
+    ttl_us: u64,
+    responses: HashMap<String, CachedResponse>,
+    // Keys ordered from the least to the most recently used.
+    lru: VecDeque<String>,
+}
+
+impl Default for RequestDeduplicator {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_CAPACITY, DEFAULT_IDEMPOTENCY_TTL_S)
+    }
+}
+
+impl RequestDeduplicator {
+    /// Creates a deduplicator remembering up to `capacity` responses for `ttl_s` seconds.
+    pub fn new(capacity: usize, ttl_s: u64) -> Self {
+        RequestDeduplicator {
+            capacity,
+            ttl_us: ttl_s.saturating_mul(1_000_000),
+            responses: HashMap::new(),
+            lru: VecDeque::new(),
+        }
+    }
+
+    /// Returns the idempotency key of `request`, if any.
+    pub fn idempotency_key(request: &Request) -> Option<&str> {
+        // Header names are case insensitive.
+        request
+            .headers
+            .custom_entries()
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(IDEMPOTENCY_KEY_HEADER))
+            .map(|(_, value)| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Returns the response remembered for `key`, if it did not expire at `now_us`.
+    ///
+    /// A key reused for a different request, with another method, path or body, is answered with
+    /// an error, since the remembered response would not match the request.
+    pub fn lookup(&mut self, key: &str, request: &Request, now_us: u64) -> Option<Response> {
+        self.evict_expired(now_us);
+        let cached = self.responses.get(key)?;
+
+        let response = if cached.method == request.method()
+            && cached.path == request.uri().get_abs_path()
+            && cached.body_hash == Self::body_hash(request)
+        {
+            cached.to_response()
+        } else {
+            super::ApiServer::json_response(
+                StatusCode::BadRequest,
+                super::ApiServer::json_fault_message(format!(
+                    "Idempotency key {key} was already used for a different request."
+                )),
+            )
+        };
+        self.touch(key);
+        Some(response)
+    }
+
+    /// Remembers `response` as the outcome of `request`, completed at `now_us`, if the request
+    /// succeeded.
+    pub fn insert(&mut self, key: &str, request: &Request, response: &Response, now_us: u64) {
+        // A failed request may succeed when retried, e.g. once the microVM reached the state the
+        // request expects, so its response is not replayed.
+        let succeeded = matches!(response.status(), StatusCode::OK | StatusCode::NoContent);
+        if self.capacity == 0 || !succeeded {
+            return;
+        }
+        let cached = CachedResponse {
+            method: request.method(),
+            path: request.uri().get_abs_path().to_string(),
+            body_hash: Self::body_hash(request),
+            http_version: response.http_version(),
+            status: response.status(),
+            content_type: response.content_type(),
+            deprecation: response.deprecation(),
+            allow: response.allow(),
+            body: response.body(),
+            completed_us: now_us,
+        };
+        if self.responses.insert(key.to_string(), cached).is_some() {
+            self.touch(key);
+            return;
+        }
+        self.lru.push_back(key.to_string());
+        while self.lru.len() > self.capacity {
+            if let Some(oldest) = self.lru.pop_front() {
+                self.responses.remove(&oldest);
+            }
+        }
+    }
+
+    fn body_hash(request: &Request) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        request
+            .body
+            .as_ref()
+            .map(Body::raw)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.lru.iter().position(|k| k == key) {
+            if let Some(key) = self.lru.remove(pos) {
+                self.lru.push_back(key);
+            }
+        }
+    }
+
+    fn evict_expired(&mut self, now_us: u64) {
+        let ttl_us = self.ttl_us;
+        self.responses
+            .retain(|_, cached| now_us.saturating_sub(cached.completed_us) < ttl_us);
+        let responses = &self.responses;
+        self.lru.retain(|key| responses.contains_key(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str, key: Option<&str>) -> Request {
+        request_with_body(method, path, key, "")
+    }
+
+    fn request_with_body(method: &str, path: &str, key: Option<&str>, body: &str) -> Request {
+        let header = key
+            .map(|key| format!("x-idempotency-key: {key}\r\n"))
+            .unwrap_or_default();
+        let raw = format!(
+            "{method} {path} HTTP/1.1\r\n{header}Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        Request::try_from(raw.as_bytes(), None).unwrap()
+    }
+
+    fn response(status: StatusCode, body: &str) -> Response {
+        let mut response = Response::new(Version::Http11, status);
+        response.set_body(Body::new(body));
+        response
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let req = request(
+            "PUT",
+            "/actions",
+            Some("123e4567-e89b-12d3-a456-426614174000"),
+        );
+        assert_eq!(
+            RequestDeduplicator::idempotency_key(&req),
+            Some("123e4567-e89b-12d3-a456-426614174000")
+        );
+        let req = request("PUT", "/actions", None);
+        assert_eq!(RequestDeduplicator::idempotency_key(&req), None);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut dedup = RequestDeduplicator::new(10, 60);
+        let req = request("PUT", "/snapshot/create", Some("a"));
+        assert!(dedup.lookup("a", &req, 0).is_none());
+
+        let mut resp = response(StatusCode::NoContent, "done");
+        resp.set_deprecation();
+        dedup.insert("a", &req, &resp, 0);
+        let cached = dedup.lookup("a", &req, 59_999_999).unwrap();
+        // The headers are replayed along with the status and the body.
+        assert_eq!(cached.status(), StatusCode::NoContent);
+        assert_eq!(cached.body().unwrap(), Body::new("done"));
+        assert!(cached.deprecation());
+        assert_eq!(cached.content_type(), MediaType::ApplicationJson);
+
+        // The same key used for a different request is rejected.
+        let other = request("PUT", "/drives/rootfs", Some("a"));
+        let cached = dedup.lookup("a", &other, 1).unwrap();
+        assert_eq!(cached.status(), StatusCode::BadRequest);
+
+        // The response is forgotten once the TTL elapsed.
+        assert!(dedup.lookup("a", &req, 60_000_000).is_none());
+        assert!(dedup.responses.is_empty());
+        assert!(dedup.lru.is_empty());
+    }
+
+    #[test]
+    fn test_lookup_different_body() {
+        let mut dedup = RequestDeduplicator::new(10, 60);
+        let body = r#"{"vcpu_count": 2}"#;
+        let req = request_with_body("PATCH", "/machine-config", Some("a"), body);
+        dedup.insert("a", &req, &response(StatusCode::NoContent, ""), 0);
+
+        // A retry with the same body is answered with the remembered response.
+        let retry = request_with_body("PATCH", "/machine-config", Some("a"), body);
+        assert_eq!(
+            dedup.lookup("a", &retry, 0).unwrap().status(),
+            StatusCode::NoContent
+        );
+
+        // The same key used with another body is rejected, and does not replace the response.
+        let other = request_with_body(
+            "PATCH",
+            "/machine-config",
+            Some("a"),
+            r#"{"vcpu_count": 4}"#,
+        );
+        let rejected = dedup.lookup("a", &other, 0).unwrap();
+        assert_eq!(rejected.status(), StatusCode::BadRequest);
+        assert_eq!(
+            dedup.lookup("a", &retry, 0).unwrap().status(),
+            StatusCode::NoContent
+        );
+        let no_body = request("PATCH", "/machine-config", Some("a"));
+        assert_eq!(
+            dedup.lookup("a", &no_body, 0).unwrap().status(),
+            StatusCode::BadRequest
+        );
+    }
+
+    #[test]
+    fn test_failed_requests_not_cached() {
+        let mut dedup = RequestDeduplicator::new(10, 60);
+        let req = request("PUT", "/actions", Some("a"));
+
+        dedup.insert("a", &req, &response(StatusCode::BadRequest, "error"), 0);
+        assert!(dedup.lookup("a", &req, 0).is_none());
+        dedup.insert(
+            "a",
+            &req,
+            &response(StatusCode::InternalServerError, "error"),
+            0,
+        );
+        assert!(dedup.lookup("a", &req, 0).is_none());
+
+        // The retry which succeeds is remembered.
+        dedup.insert("a", &req, &response(StatusCode::NoContent, ""), 0);
+        assert_eq!(
+            dedup.lookup("a", &req, 0).unwrap().status(),
+            StatusCode::NoContent
+        );
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut dedup = RequestDeduplicator::new(2, 60);
+        let req = request("PATCH", "/vm", None);
+        let resp = response(StatusCode::NoContent, "");
+
+        dedup.insert("a", &req, &resp, 0);
+        dedup.insert("b", &req, &resp, 0);
+        // Using "a" makes "b" the least recently used key.
+        dedup.lookup("a", &req, 0).unwrap();
+        dedup.insert("c", &req, &resp, 0);
+        assert!(dedup.lookup("b", &req, 0).is_none());
+        assert!(dedup.lookup("a", &req, 0).is_some());
+        assert!(dedup.lookup("c", &req, 0).is_some());
+
+        let mut dedup = RequestDeduplicator::new(0, 60);
+        dedup.insert("a", &req, &resp, 0);
+        assert!(dedup.lookup("a", &req, 0).is_none());
+    }
+}
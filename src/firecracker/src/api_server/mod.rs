@@ -6,12 +6,14 @@
 //! It is constructed on top of an HTTP Server that uses Unix Domain Sockets and `EPOLL` to
 //! handle multiple connections on the same thread.
 
+pub mod deduplicator;
 pub mod parsed_request;
 pub mod request;
 
 use std::fmt::Debug;
 use std::sync::mpsc;
 
+use deduplicator::RequestDeduplicator;
 pub use micro_http::{Body, HttpServer, Request, Response, ServerError, StatusCode, Version};
 use parsed_request::{ParsedRequest, RequestAction};
 use serde_json::json;
@@ -34,6 +36,8 @@ pub struct ApiServer {
     /// FD on which we notify the VMM that we have sent at least one
     /// `VmmRequest`.
     to_vmm_fd: EventFd,
+    /// Responses of the recent requests carrying an idempotency key.
+    deduplicator: RequestDeduplicator,
 }
 
 impl ApiServer {
@@ -49,9 +53,18 @@ impl ApiServer {
             api_request_sender,
             vmm_response_receiver,
             to_vmm_fd,
+            deduplicator: RequestDeduplicator::default(),
         }
     }
 
+    /// Sets how long, in seconds, the responses of requests carrying an idempotency key are
+    /// remembered.
+    pub fn with_idempotency_ttl(mut self, ttl_s: u64) -> Self {
+        self.deduplicator =
+            RequestDeduplicator::new(deduplicator::DEFAULT_IDEMPOTENCY_CAPACITY, ttl_s);
+        self
+    }
+
     /// Runs the Api Server.
     ///
     /// # Arguments
@@ -118,11 +131,31 @@ impl ApiServer {
     }
 
     /// Handles an API request received through the associated socket.
+    ///
+    /// A request carrying an idempotency key which was already served is answered with the
+    /// response of the first request, without being applied again.
     pub fn handle_request(
         &mut self,
         request: &Request,
         request_processing_start_us: u64,
     ) -> Response {
+        let Some(key) = RequestDeduplicator::idempotency_key(request) else {
+            return self.serve_request(request, request_processing_start_us);
+        };
+        if let Some(response) = self
+            .deduplicator
+            .lookup(key, request, request_processing_start_us)
+        {
+            info!("Replaying the response of the request with idempotency key {key}.");
+            return response;
+        }
+        let response = self.serve_request(request, request_processing_start_us);
+        self.deduplicator
+            .insert(key, request, &response, get_time_us(ClockType::Monotonic));
+        response
+    }
+
+    fn serve_request(&mut self, request: &Request, request_processing_start_us: u64) -> Response {
         match ParsedRequest::try_from(request).map(|r| r.into_parts()) {
             Ok((req_action, mut parsing_info)) => {
                 let mut response = match req_action {
@@ -349,6 +382,37 @@ mod tests {
         assert_eq!(response.status(), StatusCode::BadRequest);
     }
 
+    #[test]
+    fn test_handle_request_idempotency_key() {
+        let to_vmm_fd = EventFd::new(libc::EFD_NONBLOCK).unwrap();
+        let (api_request_sender, from_api) = channel();
+        let (to_api, vmm_response_receiver) = channel();
+
+        let mut api_server = ApiServer::new(api_request_sender, vmm_response_receiver, to_vmm_fd);
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let request = b"PUT /actions HTTP/1.1\r\n\
+                        X-Idempotency-Key: 123e4567-e89b-12d3-a456-426614174000\r\n\
+                        Content-Type: application/json\r\n\
+                        Content-Length: 33\r\n\r\n{ \"action_type\": \"FlushMetrics\" }";
+
+        to_api.send(Box::new(Ok(VmmData::Empty))).unwrap();
+        sender.write_all(request).unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        let response = api_server.handle_request(&req, 0);
+        assert_eq!(response.status(), StatusCode::NoContent);
+        from_api.try_recv().unwrap();
+
+        // The retried request is answered without reaching the VMM.
+        sender.write_all(request).unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        let response = api_server.handle_request(&req, 0);
+        assert_eq!(response.status(), StatusCode::NoContent);
+        from_api.try_recv().unwrap_err();
+    }
+
     #[test]
     fn test_handle_request_logging() {
         let cpu_template_json = TEST_UNESCAPED_JSON_TEMPLATE;
@@ -13,10 +13,17 @@ use super::ApiServer;
 use super::request::actions::parse_put_actions;
 use super::request::balloon::{parse_get_balloon, parse_patch_balloon, parse_put_balloon};
 use super::request::boot_source::parse_put_boot_source;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use super::request::breakpoints::parse_put_breakpoints;
+#[cfg(target_arch = "x86_64")]
+use super::request::clock::{parse_get_vm_clock, parse_put_vm_clock};
+use super::request::config_snapshot::{parse_get_config_snapshot, parse_put_config_snapshot};
 use super::request::cpu_configuration::parse_put_cpu_config;
 use super::request::drive::{parse_patch_drive, parse_put_drive};
 use super::request::entropy::parse_put_entropy;
 use super::request::instance_info::parse_get_instance_info;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use super::request::interrupt::parse_put_inject_interrupt;
 use super::request::logger::parse_put_logger;
 use super::request::machine_configuration::{
     parse_get_machine_config, parse_patch_machine_config, parse_put_machine_config,
@@ -24,8 +31,21 @@ use super::request::machine_configuration::{
 use super::request::metrics::parse_put_metrics;
 use super::request::mmds::{parse_get_mmds, parse_patch_mmds, parse_put_mmds};
 use super::request::net::{parse_patch_net, parse_put_net};
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use super::request::page_table::parse_get_page_table;
+use super::request::pmem::parse_put_pmem;
 use super::request::snapshot::{parse_patch_vm_state, parse_put_snapshot};
+#[cfg(feature = "debug-api")]
+use super::request::trace_log::parse_get_trace_log;
+use super::request::transaction::parse_put_transaction;
+#[cfg(feature = "debug-api")]
+use super::request::vcpu::parse_patch_vcpu;
+#[cfg(target_arch = "x86_64")]
+use super::request::vcpu::parse_put_vcpu_pmu;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use super::request::vcpu::{parse_get_vcpu_state, parse_put_vcpu_state};
 use super::request::version::parse_get_version;
+use super::request::vmgenid::parse_put_vm_gen_id;
 use super::request::vsock::parse_put_vsock;
 
 #[derive(Debug)]
@@ -77,16 +97,36 @@ impl TryFrom<&Request> for ParsedRequest {
         match (request.method(), path, request.body.as_ref()) {
             (Method::Get, "", None) => parse_get_instance_info(),
             (Method::Get, "balloon", None) => parse_get_balloon(path_tokens.next()),
+            (Method::Get, "config-snapshot", None) => parse_get_config_snapshot(),
             (Method::Get, "version", None) => parse_get_version(),
-            (Method::Get, "vm", None) if path_tokens.next() == Some("config") => {
-                Ok(ParsedRequest::new_sync(VmmAction::GetFullVmConfig))
-            }
+            (Method::Get, "vm", None) => match path_tokens.next() {
+                Some("config") => Ok(ParsedRequest::new_sync(VmmAction::GetFullVmConfig)),
+                #[cfg(target_arch = "x86_64")]
+                Some("clock") => parse_get_vm_clock(),
+                #[cfg(feature = "debug-api")]
+                Some(token) if token == "trace-log" || token.starts_with("trace-log?") => {
+                    parse_get_trace_log(token)
+                }
+                #[cfg(feature = "perf-counters")]
+                Some("resource-usage") => Ok(ParsedRequest::new_sync(VmmAction::GetResourceUsage)),
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                Some(token) if token == "page-table" || token.starts_with("page-table?") => {
+                    parse_get_page_table(token)
+                }
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                Some("vcpu") => parse_get_vcpu_state(path_tokens.next(), path_tokens.next()),
+                _ => Err(RequestError::InvalidPathMethod(
+                    path.to_string(),
+                    Method::Get,
+                )),
+            },
             (Method::Get, "machine-config", None) => parse_get_machine_config(),
             (Method::Get, "mmds", None) => parse_get_mmds(),
             (Method::Get, _, Some(_)) => method_to_error(Method::Get),
             (Method::Put, "actions", Some(body)) => parse_put_actions(body),
             (Method::Put, "balloon", Some(body)) => parse_put_balloon(body),
             (Method::Put, "boot-source", Some(body)) => parse_put_boot_source(body),
+            (Method::Put, "config-snapshot", Some(body)) => parse_put_config_snapshot(body),
             (Method::Put, "cpu-config", Some(body)) => parse_put_cpu_config(body),
             (Method::Put, "drives", Some(body)) => parse_put_drive(body, path_tokens.next()),
             (Method::Put, "logger", Some(body)) => parse_put_logger(body),
@@ -97,8 +137,41 @@ impl TryFrom<&Request> for ParsedRequest {
                 parse_put_net(body, path_tokens.next())
             }
             (Method::Put, "snapshot", Some(body)) => parse_put_snapshot(body, path_tokens.next()),
+            (Method::Put, "vm", Some(body)) => match path_tokens.next() {
+                #[cfg(target_arch = "x86_64")]
+                Some("vcpu") => match (path_tokens.next(), path_tokens.next()) {
+                    (index, Some("pmu")) => parse_put_vcpu_pmu(body, index),
+                    #[cfg(feature = "debug-api")]
+                    (index, resource) => parse_put_vcpu_state(body, index, resource),
+                    #[cfg(not(feature = "debug-api"))]
+                    _ => Err(RequestError::InvalidPathMethod(
+                        path.to_string(),
+                        Method::Put,
+                    )),
+                },
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                Some("inject-interrupt") => parse_put_inject_interrupt(body),
+                #[cfg(target_arch = "x86_64")]
+                Some("clock") => parse_put_vm_clock(body),
+                Some("gen-id") => parse_put_vm_gen_id(body),
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                Some("debug") => match path_tokens.next() {
+                    Some("breakpoints") => parse_put_breakpoints(body),
+                    _ => Err(RequestError::InvalidPathMethod(
+                        path.to_string(),
+                        Method::Put,
+                    )),
+                },
+                _ => Err(RequestError::InvalidPathMethod(
+                    path.to_string(),
+                    Method::Put,
+                )),
+            },
             (Method::Put, "vsock", Some(body)) => parse_put_vsock(body),
             (Method::Put, "entropy", Some(body)) => parse_put_entropy(body),
+            (Method::Put, "pmem-drives", Some(body)) => parse_put_pmem(body, path_tokens.next()),
+            // Transaction operations do not take a body.
+            (Method::Put, "transaction", _) => parse_put_transaction(path_tokens.next()),
             (Method::Put, _, None) => method_to_error(Method::Put),
             (Method::Patch, "balloon", Some(body)) => parse_patch_balloon(body, path_tokens.next()),
             (Method::Patch, "drives", Some(body)) => parse_patch_drive(body, path_tokens.next()),
@@ -107,7 +180,11 @@ impl TryFrom<&Request> for ParsedRequest {
             (Method::Patch, "network-interfaces", Some(body)) => {
                 parse_patch_net(body, path_tokens.next())
             }
-            (Method::Patch, "vm", Some(body)) => parse_patch_vm_state(body),
+            (Method::Patch, "vm", Some(body)) => match path_tokens.next() {
+                #[cfg(feature = "debug-api")]
+                Some("vcpu") => parse_patch_vcpu(body, path_tokens.next()),
+                _ => parse_patch_vm_state(body),
+            },
             (Method::Patch, _, None) => method_to_error(Method::Patch),
             (method, unknown_uri, _) => Err(RequestError::InvalidPathMethod(
                 unknown_uri.to_string(),
@@ -171,11 +248,22 @@ impl ParsedRequest {
                     Self::success_response_with_data(balloon_config)
                 }
                 VmmData::BalloonStats(stats) => Self::success_response_with_data(stats),
+                VmmData::ConfigSnapshot(snapshot) => Self::success_response_with_data(snapshot),
                 VmmData::InstanceInformation(info) => Self::success_response_with_data(info),
                 VmmData::VmmVersion(version) => Self::success_response_with_data(
                     &serde_json::json!({ "firecracker_version": version.as_str() }),
                 ),
                 VmmData::FullVmConfig(config) => Self::success_response_with_data(config),
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                VmmData::GuestMappings(mappings) => Self::success_response_with_data(mappings),
+                #[cfg(feature = "debug-api")]
+                VmmData::TraceLog(entries) => Self::success_response_with_data(entries),
+                #[cfg(feature = "perf-counters")]
+                VmmData::ResourceUsage(counters) => Self::success_response_with_data(counters),
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                VmmData::VcpuState(snapshot) => Self::success_response_with_data(snapshot),
+                #[cfg(target_arch = "x86_64")]
+                VmmData::VmClock(clock) => Self::success_response_with_data(clock),
             },
             Err(vmm_action_error) => {
                 let mut response = match vmm_action_error {
@@ -319,11 +407,17 @@ pub mod tests {
     use std::str::FromStr;
 
     use micro_http::HttpConnection;
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    use vmm::arch::x86_64::page_table::GuestMapping;
     use vmm::builder::StartMicrovmError;
     use vmm::cpu_config::templates::test_utils::build_test_template;
-    use vmm::resources::VmmConfig;
+    #[cfg(feature = "debug-api")]
+    use vmm::logger::TraceEntry;
+    use vmm::resources::{ConfigSnapshot, VmmConfig};
     use vmm::rpc_interface::VmmActionError;
     use vmm::vmm_config::balloon::{BalloonDeviceConfig, BalloonStats};
+    #[cfg(target_arch = "x86_64")]
+    use vmm::vmm_config::clock::VmClockConfig;
     use vmm::vmm_config::instance_info::InstanceInfo;
     use vmm::vmm_config::machine_config::MachineConfig;
 
@@ -555,10 +649,17 @@ pub mod tests {
                 VmmData::BalloonStats(stats) => {
                     http_response(&serde_json::to_string(stats).unwrap(), 200)
                 }
+                VmmData::ConfigSnapshot(snapshot) => {
+                    http_response(&serde_json::to_string(snapshot).unwrap(), 200)
+                }
                 VmmData::Empty => http_response("", 204),
                 VmmData::FullVmConfig(cfg) => {
                     http_response(&serde_json::to_string(cfg).unwrap(), 200)
                 }
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                VmmData::GuestMappings(mappings) => {
+                    http_response(&serde_json::to_string(mappings).unwrap(), 200)
+                }
                 VmmData::MachineConfiguration(cfg) => {
                     http_response(&serde_json::to_string(cfg).unwrap(), 200)
                 }
@@ -572,6 +673,22 @@ pub mod tests {
                     &serde_json::json!({ "firecracker_version": version.as_str() }).to_string(),
                     200,
                 ),
+                #[cfg(feature = "debug-api")]
+                VmmData::TraceLog(entries) => {
+                    http_response(&serde_json::to_string(entries).unwrap(), 200)
+                }
+                #[cfg(feature = "perf-counters")]
+                VmmData::ResourceUsage(counters) => {
+                    http_response(&serde_json::to_string(counters).unwrap(), 200)
+                }
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                VmmData::VcpuState(snapshot) => {
+                    http_response(&serde_json::to_string(snapshot).unwrap(), 200)
+                }
+                #[cfg(target_arch = "x86_64")]
+                VmmData::VmClock(clock) => {
+                    http_response(&serde_json::to_string(clock).unwrap(), 200)
+                }
             };
             let response = ParsedRequest::convert_to_response(&data);
             response.write_all(&mut buf).unwrap();
@@ -584,12 +701,38 @@ pub mod tests {
             swap_out: Some(1),
             ..Default::default()
         }));
+        verify_ok_response_with(VmmData::ConfigSnapshot(ConfigSnapshot::default()));
         verify_ok_response_with(VmmData::Empty);
         verify_ok_response_with(VmmData::FullVmConfig(VmmConfig::default()));
         verify_ok_response_with(VmmData::MachineConfiguration(MachineConfig::default()));
         verify_ok_response_with(VmmData::MmdsValue(serde_json::from_str("{}").unwrap()));
         verify_ok_response_with(VmmData::InstanceInformation(InstanceInfo::default()));
         verify_ok_response_with(VmmData::VmmVersion(String::default()));
+        #[cfg(feature = "debug-api")]
+        verify_ok_response_with(VmmData::TraceLog(vec![TraceEntry {
+            timestamp_ns: 1,
+            thread_id: 2,
+            fn_name: "foo",
+        }]));
+        #[cfg(feature = "perf-counters")]
+        verify_ok_response_with(VmmData::ResourceUsage(
+            vmm::perf_counters::VmmPerfCounters {
+                instructions: 1,
+                cache_misses: 2,
+                branch_mispredictions: 3,
+            },
+        ));
+        #[cfg(target_arch = "x86_64")]
+        verify_ok_response_with(VmmData::VmClock(VmClockConfig {
+            realtime_ns: 1,
+            host_ns: 2,
+        }));
+        #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+        verify_ok_response_with(VmmData::GuestMappings(vec![GuestMapping {
+            virtual_addr: 0xffff_ffff_8000_0000,
+            physical_addr: 0x20_0000,
+            flags: 0x81,
+        }]));
 
         // Error.
         let error = VmmActionError::StartMicrovm(StartMicrovmError::MissingKernelConfig);
@@ -674,6 +817,169 @@ pub mod tests {
         ParsedRequest::try_from(&req).unwrap();
     }
 
+    #[test]
+    #[cfg(feature = "debug-api")]
+    fn test_try_from_get_trace_log() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        sender
+            .write_all(http_request("GET", "/vm/trace-log?last_n=5", None).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+
+        // Only the exact path is routed to the trace log.
+        sender
+            .write_all(http_request("GET", "/vm/trace-logs", None).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap_err();
+    }
+
+    #[test]
+    #[cfg(feature = "perf-counters")]
+    fn test_try_from_get_resource_usage() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        sender
+            .write_all(http_request("GET", "/vm/resource-usage", None).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        assert_eq!(
+            vmm_action_from_request(ParsedRequest::try_from(&req).unwrap()),
+            VmmAction::GetResourceUsage
+        );
+    }
+
+    #[cfg(feature = "debug-api")]
+    #[test]
+    fn test_try_from_patch_vcpu() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"state\": \"Paused\" }";
+        sender
+            .write_all(http_request("PATCH", "/vm/vcpu/0", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+    }
+
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    #[test]
+    fn test_try_from_get_vcpu_state() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        sender
+            .write_all(http_request("GET", "/vm/vcpu/0/state", None).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_try_from_put_vcpu_pmu() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"enabled\": true, \"allowed_events\": [60] }";
+        sender
+            .write_all(http_request("PUT", "/vm/vcpu/0/pmu", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_try_from_vm_clock() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        sender
+            .write_all(http_request("GET", "/vm/clock", None).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+
+        let body = "{ \"realtime_ns\": 1, \"host_ns\": 2 }";
+        sender
+            .write_all(http_request("PUT", "/vm/clock", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+    }
+
+    #[test]
+    fn test_try_from_put_vm_gen_id() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"uuid\": \"1b4e28ba-2fa1-11d2-883f-0016d3cca427\" }";
+        sender
+            .write_all(http_request("PUT", "/vm/gen-id", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+    }
+
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    #[test]
+    fn test_try_from_put_inject_interrupt() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"vcpu_id\": 0, \"vector\": 80, \"type\": \"fixed\" }";
+        sender
+            .write_all(http_request("PUT", "/vm/inject-interrupt", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+    }
+
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    #[test]
+    fn test_try_from_put_breakpoints() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "[{ \"address\": 4096, \"type\": \"write\" }]";
+        sender
+            .write_all(http_request("PUT", "/vm/debug/breakpoints", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+    }
+
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    #[test]
+    fn test_try_from_get_page_table() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        sender
+            .write_all(
+                http_request("GET", "/vm/page-table?cr3=0x1000&cr4=0x3606f0", None).as_bytes(),
+            )
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+
+        // Only the exact path is routed to the page table walk.
+        sender
+            .write_all(http_request("GET", "/vm/page-tables?cr3=0x1000&cr4=0", None).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap_err();
+    }
+
     #[test]
     fn test_try_from_put_actions() {
         let (mut sender, receiver) = UnixStream::pair().unwrap();
@@ -746,6 +1052,20 @@ pub mod tests {
         ParsedRequest::try_from(&req).unwrap();
     }
 
+    #[test]
+    fn test_try_from_put_pmem_drives() {
+        let (mut sender, receiver) = UnixStream::pair().unwrap();
+        let mut connection = HttpConnection::new(receiver);
+        let body = "{ \"drive_id\": \"string\", \"path_on_host\": \"string\", \"is_root_device\": \
+                    true, \"is_read_only\": true }";
+        sender
+            .write_all(http_request("PUT", "/pmem-drives/string", Some(body)).as_bytes())
+            .unwrap();
+        connection.try_read().unwrap();
+        let req = connection.pop_parsed_request().unwrap();
+        ParsedRequest::try_from(&req).unwrap();
+    }
+
     #[test]
     fn test_try_from_put_logger() {
         let (mut sender, receiver) = UnixStream::pair().unwrap();
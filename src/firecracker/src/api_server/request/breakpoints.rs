@@ -0,0 +1,60 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::rpc_interface::VmmAction;
+use vmm::vmm_config::breakpoints::BreakpointConfig;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+use super::Body;
+
+pub(crate) fn parse_put_breakpoints(body: &Body) -> Result<ParsedRequest, RequestError> {
+    let breakpoints = serde_json::from_slice::<Vec<BreakpointConfig>>(body.raw())?;
+    Ok(ParsedRequest::new_sync(VmmAction::SetBreakpoints(
+        breakpoints,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use vmm::vmm_config::breakpoints::BreakpointType;
+
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_put_breakpoints_request() {
+        parse_put_breakpoints(&Body::new("invalid_payload")).unwrap_err();
+
+        // PUT with an unknown breakpoint type.
+        let body = r#"[{ "address": 4096, "type": "read" }]"#;
+        parse_put_breakpoints(&Body::new(body)).unwrap_err();
+
+        // PUT with invalid fields.
+        let body = r#"[{ "address": 4096, "type": "write", "len": 8 }]"#;
+        parse_put_breakpoints(&Body::new(body)).unwrap_err();
+
+        let body = r#"[
+            { "address": 18446744071578845184, "type": "execute" },
+            { "address": 4096, "type": "access" }
+        ]"#;
+        assert_eq!(
+            vmm_action_from_request(parse_put_breakpoints(&Body::new(body)).unwrap()),
+            VmmAction::SetBreakpoints(vec![
+                BreakpointConfig {
+                    address: 0xffff_ffff_8100_0000,
+                    breakpoint_type: BreakpointType::Execute,
+                },
+                BreakpointConfig {
+                    address: 0x1000,
+                    breakpoint_type: BreakpointType::Access,
+                },
+            ])
+        );
+
+        // An empty list clears the breakpoints.
+        assert_eq!(
+            vmm_action_from_request(parse_put_breakpoints(&Body::new("[]")).unwrap()),
+            VmmAction::SetBreakpoints(vec![])
+        );
+    }
+}
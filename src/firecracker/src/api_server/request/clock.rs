@@ -0,0 +1,53 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::rpc_interface::VmmAction;
+use vmm::vmm_config::clock::VmClockConfig;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+use super::Body;
+
+pub(crate) fn parse_get_vm_clock() -> Result<ParsedRequest, RequestError> {
+    Ok(ParsedRequest::new_sync(VmmAction::GetVmClock))
+}
+
+pub(crate) fn parse_put_vm_clock(body: &Body) -> Result<ParsedRequest, RequestError> {
+    let clock = serde_json::from_slice::<VmClockConfig>(body.raw())?;
+    Ok(ParsedRequest::new_sync(VmmAction::SetVmClock(clock)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_get_vm_clock_request() {
+        assert_eq!(
+            vmm_action_from_request(parse_get_vm_clock().unwrap()),
+            VmmAction::GetVmClock
+        );
+    }
+
+    #[test]
+    fn test_parse_put_vm_clock_request() {
+        parse_put_vm_clock(&Body::new("invalid_payload")).unwrap_err();
+
+        // PUT with missing fields.
+        let body = r#"{ "realtime_ns": 1 }"#;
+        parse_put_vm_clock(&Body::new(body)).unwrap_err();
+
+        // PUT with invalid fields.
+        let body = r#"{ "realtime_ns": 1, "host_ns": 2, "tsc": 3 }"#;
+        parse_put_vm_clock(&Body::new(body)).unwrap_err();
+
+        let body = r#"{ "realtime_ns": 1, "host_ns": 2 }"#;
+        assert_eq!(
+            vmm_action_from_request(parse_put_vm_clock(&Body::new(body)).unwrap()),
+            VmmAction::SetVmClock(VmClockConfig {
+                realtime_ns: 1,
+                host_ns: 2,
+            })
+        );
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::resources::ConfigSnapshot;
+use vmm::rpc_interface::VmmAction;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+use super::Body;
+
+pub(crate) fn parse_get_config_snapshot() -> Result<ParsedRequest, RequestError> {
+    Ok(ParsedRequest::new_sync(VmmAction::GetConfigSnapshot))
+}
+
+pub(crate) fn parse_put_config_snapshot(body: &Body) -> Result<ParsedRequest, RequestError> {
+    let snapshot = serde_json::from_slice::<ConfigSnapshot>(body.raw())?;
+    Ok(ParsedRequest::new_sync(VmmAction::SetConfigSnapshot(
+        Box::new(snapshot),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_get_config_snapshot_request() {
+        assert_eq!(
+            vmm_action_from_request(parse_get_config_snapshot().unwrap()),
+            VmmAction::GetConfigSnapshot
+        );
+    }
+
+    #[test]
+    fn test_parse_put_config_snapshot_request() {
+        parse_put_config_snapshot(&Body::new("invalid_payload")).unwrap_err();
+
+        // The configuration must be wrapped in a `snapshot` field.
+        let body = r#"{ "boot-source": { "kernel_image_path": "vmlinux" }, "drives": [] }"#;
+        parse_put_config_snapshot(&Body::new(body)).unwrap_err();
+
+        let body = r#"{
+            "snapshot": {
+                "boot-source": { "kernel_image_path": "vmlinux" },
+                "drives": []
+            }
+        }"#;
+        let snapshot: ConfigSnapshot = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            vmm_action_from_request(parse_put_config_snapshot(&Body::new(body)).unwrap()),
+            VmmAction::SetConfigSnapshot(Box::new(snapshot))
+        );
+    }
+}
@@ -229,6 +229,12 @@ mod tests {
             "is_read_only": true,
             "cache_type": "Unsafe",
             "io_engine": "Sync",
+            "lifetime": {
+                "pre_eol_info": 1,
+                "device_lifetime_est_typ_a": 2,
+                "device_lifetime_est_typ_b": 3
+            },
+            "discard": false,
             "rate_limiter": {
                 "bandwidth": {
                     "size": 0,
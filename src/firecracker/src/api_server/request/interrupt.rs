@@ -0,0 +1,57 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::rpc_interface::VmmAction;
+use vmm::vmm_config::interrupt::InjectInterruptConfig;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+use super::Body;
+
+pub(crate) fn parse_put_inject_interrupt(body: &Body) -> Result<ParsedRequest, RequestError> {
+    let config = serde_json::from_slice::<InjectInterruptConfig>(body.raw())?;
+    Ok(ParsedRequest::new_sync(VmmAction::InjectInterrupt(config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use vmm::vmm_config::interrupt::InterruptType;
+
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_put_inject_interrupt_request() {
+        parse_put_inject_interrupt(&Body::new("invalid_payload")).unwrap_err();
+
+        // PUT with an unknown interrupt type.
+        let body = r#"{ "vcpu_id": 0, "vector": 80, "type": "extint" }"#;
+        parse_put_inject_interrupt(&Body::new(body)).unwrap_err();
+
+        // PUT with invalid fields.
+        let body = r#"{ "vcpu_id": 0, "vector": 80, "type": "fixed", "irq": 5 }"#;
+        parse_put_inject_interrupt(&Body::new(body)).unwrap_err();
+
+        let body = r#"{ "vcpu_id": 1, "vector": 80, "type": "fixed", "level": true }"#;
+        assert_eq!(
+            vmm_action_from_request(parse_put_inject_interrupt(&Body::new(body)).unwrap()),
+            VmmAction::InjectInterrupt(InjectInterruptConfig {
+                vcpu_id: 1,
+                vector: 80,
+                interrupt_type: InterruptType::Fixed,
+                level: true,
+            })
+        );
+
+        // The vector and the trigger mode are optional.
+        let body = r#"{ "vcpu_id": 0, "type": "nmi" }"#;
+        assert_eq!(
+            vmm_action_from_request(parse_put_inject_interrupt(&Body::new(body)).unwrap()),
+            VmmAction::InjectInterrupt(InjectInterruptConfig {
+                vcpu_id: 0,
+                vector: 0,
+                interrupt_type: InterruptType::Nmi,
+                level: false,
+            })
+        );
+    }
+}
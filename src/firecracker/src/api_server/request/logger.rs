@@ -40,6 +40,7 @@ mod tests {
             show_level: Some(false),
             show_log_origin: Some(false),
             module: None,
+            json: None,
         };
         assert_eq!(
             vmm_action_from_request(parse_put_logger(&Body::new(body)).unwrap()),
@@ -50,7 +51,8 @@ mod tests {
                 "log_path": "log",
                 "level": "DEBUG",
                 "show_level": false,
-                "show_log_origin": false
+                "show_log_origin": false,
+                "json": true
               }"#;
 
         let expected_config = LoggerConfig {
@@ -59,6 +61,7 @@ mod tests {
             show_level: Some(false),
             show_log_origin: Some(false),
             module: None,
+            json: Some(true),
         };
         assert_eq!(
             vmm_action_from_request(parse_put_logger(&Body::new(body)).unwrap()),
@@ -74,6 +74,7 @@ pub(crate) fn parse_patch_machine_config(body: &Body) -> Result<ParsedRequest, R
 #[cfg(test)]
 mod tests {
     use vmm::cpu_config::templates::StaticCpuTemplate;
+    use vmm::vmm_config::balloon::BalloonPolicy;
     use vmm::vmm_config::machine_config::HugePageConfig;
 
     use super::*;
@@ -123,6 +124,15 @@ mod tests {
                 cpu_template: None,
                 track_dirty_pages: Some(false),
                 huge_pages: Some(expected),
+                additional_memory_regions: Some(vec![]),
+                thp_mode: None,
+                cpu_weight: None,
+                io_weight: None,
+                memory_high_mb: None,
+                memory_swap_high_mb: None,
+                cpu_budget: None,
+                balloon_policy: Some(BalloonPolicy::None),
+                cpuid_blocklist: Some(vec![]),
                 #[cfg(feature = "gdb")]
                 gdb_socket_path: None,
             };
@@ -144,6 +154,15 @@ mod tests {
             cpu_template: Some(StaticCpuTemplate::None),
             track_dirty_pages: Some(false),
             huge_pages: Some(HugePageConfig::None),
+            additional_memory_regions: Some(vec![]),
+            thp_mode: None,
+            cpu_weight: None,
+            io_weight: None,
+            memory_high_mb: None,
+            memory_swap_high_mb: None,
+            cpu_budget: None,
+            balloon_policy: Some(BalloonPolicy::None),
+            cpuid_blocklist: Some(vec![]),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         };
@@ -165,6 +184,15 @@ mod tests {
             cpu_template: None,
             track_dirty_pages: Some(true),
             huge_pages: Some(HugePageConfig::None),
+            additional_memory_regions: Some(vec![]),
+            thp_mode: None,
+            cpu_weight: None,
+            io_weight: None,
+            memory_high_mb: None,
+            memory_swap_high_mb: None,
+            cpu_budget: None,
+            balloon_policy: Some(BalloonPolicy::None),
+            cpuid_blocklist: Some(vec![]),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         };
@@ -190,6 +218,15 @@ mod tests {
                 cpu_template: Some(StaticCpuTemplate::T2),
                 track_dirty_pages: Some(true),
                 huge_pages: Some(HugePageConfig::None),
+                additional_memory_regions: Some(vec![]),
+                thp_mode: None,
+                cpu_weight: None,
+                io_weight: None,
+                memory_high_mb: None,
+                memory_swap_high_mb: None,
+                cpu_budget: None,
+                balloon_policy: Some(BalloonPolicy::None),
+                cpuid_blocklist: Some(vec![]),
                 #[cfg(feature = "gdb")]
                 gdb_socket_path: None,
             };
@@ -217,6 +254,15 @@ mod tests {
             cpu_template: None,
             track_dirty_pages: Some(true),
             huge_pages: Some(HugePageConfig::None),
+            additional_memory_regions: Some(vec![]),
+            thp_mode: None,
+            cpu_weight: None,
+            io_weight: None,
+            memory_high_mb: None,
+            memory_swap_high_mb: None,
+            cpu_budget: None,
+            balloon_policy: Some(BalloonPolicy::None),
+            cpuid_blocklist: Some(vec![]),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         };
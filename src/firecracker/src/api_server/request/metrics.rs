@@ -1,7 +1,7 @@
 // Copyright 2020 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use vmm::logger::{IncMetric, METRICS};
+use vmm::logger::{IncMetric, METRICS, MetricsFormat};
 use vmm::rpc_interface::VmmAction;
 use vmm::vmm_config::metrics::MetricsConfig;
 
@@ -31,12 +31,32 @@ mod tests {
         }"#;
         let expected_config = MetricsConfig {
             metrics_path: PathBuf::from("metrics"),
+            format: MetricsFormat::Json,
         };
         assert_eq!(
             vmm_action_from_request(parse_put_metrics(&Body::new(body)).unwrap()),
             VmmAction::ConfigureMetrics(expected_config)
         );
 
+        let body = r#"{
+            "metrics_path": "metrics",
+            "format": "prometheus"
+        }"#;
+        let expected_config = MetricsConfig {
+            metrics_path: PathBuf::from("metrics"),
+            format: MetricsFormat::Prometheus,
+        };
+        assert_eq!(
+            vmm_action_from_request(parse_put_metrics(&Body::new(body)).unwrap()),
+            VmmAction::ConfigureMetrics(expected_config)
+        );
+
+        let invalid_body = r#"{
+            "metrics_path": "metrics",
+            "format": "xml"
+        }"#;
+        parse_put_metrics(&Body::new(invalid_body)).unwrap_err();
+
         let invalid_body = r#"{
             "invalid_field": "metrics"
         }"#;
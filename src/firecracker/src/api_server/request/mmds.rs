@@ -101,6 +101,24 @@ mod tests {
         }"#;
         parse_put_mmds(&Body::new(body), Some(config_path)).unwrap();
 
+        let body = r#"{
+            "network_interfaces": [],
+            "mmds_rate_limit_rps": 10
+        }"#;
+        parse_put_mmds(&Body::new(body), Some(config_path)).unwrap();
+
+        let body = r#"{
+            "network_interfaces": [],
+            "mmds_rate_limit_rps": -1
+        }"#;
+        parse_put_mmds(&Body::new(body), Some(config_path)).unwrap_err();
+
+        let body = r#"{
+            "network_interfaces": [],
+            "mmds_cache_ttl_ms": 1000
+        }"#;
+        parse_put_mmds(&Body::new(body), Some(config_path)).unwrap();
+
         let body = r#"{
             "version": "foo",
             "ipv4_address": "169.254.170.2",
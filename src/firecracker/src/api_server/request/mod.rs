@@ -4,16 +4,32 @@
 pub mod actions;
 pub mod balloon;
 pub mod boot_source;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+pub mod breakpoints;
+#[cfg(target_arch = "x86_64")]
+pub mod clock;
+pub mod config_snapshot;
 pub mod cpu_configuration;
 pub mod drive;
 pub mod entropy;
 pub mod instance_info;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+pub mod interrupt;
 pub mod logger;
 pub mod machine_configuration;
 pub mod metrics;
 pub mod mmds;
 pub mod net;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+pub mod page_table;
+pub mod pmem;
 pub mod snapshot;
+#[cfg(feature = "debug-api")]
+pub mod trace_log;
+pub mod transaction;
+#[cfg(any(target_arch = "x86_64", feature = "debug-api"))]
+pub mod vcpu;
 pub mod version;
+pub mod vmgenid;
 pub mod vsock;
 pub use micro_http::{Body, Method, StatusCode};
@@ -73,6 +73,8 @@ pub(crate) fn parse_patch_net(
 
 #[cfg(test)]
 mod tests {
+    use vmm::vmm_config::net::Duplex;
+
     use super::*;
     use crate::api_server::parsed_request::tests::vmm_action_from_request;
 
@@ -112,6 +114,42 @@ mod tests {
             }
         }"#;
         parse_put_net(&Body::new(body), Some("foo")).unwrap_err();
+
+        // 5. Success case with an MTU.
+        let body = r#"{
+            "iface_id": "foo",
+            "host_dev_name": "bar",
+            "mtu": 1400
+        }"#;
+        let expected_config = serde_json::from_str::<NetworkInterfaceConfig>(body).unwrap();
+        assert_eq!(expected_config.mtu, Some(1400));
+        assert_eq!(
+            vmm_action_from_request(parse_put_net(&Body::new(body), Some("foo")).unwrap()),
+            VmmAction::InsertNetworkDevice(expected_config)
+        );
+
+        // 6. Success case with a link speed and duplex mode.
+        let body = r#"{
+            "iface_id": "foo",
+            "host_dev_name": "bar",
+            "link_speed_mbps": 1000,
+            "duplex": "Half"
+        }"#;
+        let expected_config = serde_json::from_str::<NetworkInterfaceConfig>(body).unwrap();
+        assert_eq!(expected_config.link_speed_mbps, Some(1000));
+        assert_eq!(expected_config.duplex, Some(Duplex::Half));
+        assert_eq!(
+            vmm_action_from_request(parse_put_net(&Body::new(body), Some("foo")).unwrap()),
+            VmmAction::InsertNetworkDevice(expected_config)
+        );
+
+        // 7. Serde error for an unknown duplex mode.
+        let body = r#"{
+            "iface_id": "foo",
+            "host_dev_name": "bar",
+            "duplex": "Quarter"
+        }"#;
+        parse_put_net(&Body::new(body), Some("foo")).unwrap_err();
     }
 
     #[test]
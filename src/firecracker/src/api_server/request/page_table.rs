@@ -0,0 +1,98 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use micro_http::{Method, StatusCode};
+use vmm::arch::x86_64::page_table::PageTableRoot;
+use vmm::rpc_interface::VmmAction;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+
+// Parses the value of a register given in hexadecimal, with or without a `0x` prefix.
+fn parse_register(name: &str, value: &str) -> Result<u64, RequestError> {
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    u64::from_str_radix(digits, 16).map_err(|_| {
+        RequestError::Generic(
+            StatusCode::BadRequest,
+            format!("Invalid value for {name}: {value}."),
+        )
+    })
+}
+
+/// Parses `GET /vm/page-table?cr3=<hex>&cr4=<hex>`, where `token` is the last path segment
+/// together with the query string.
+pub(crate) fn parse_get_page_table(token: &str) -> Result<ParsedRequest, RequestError> {
+    let query = match token.split_once('?') {
+        Some(("page-table", query)) => query,
+        None if token == "page-table" => "",
+        _ => {
+            return Err(RequestError::InvalidPathMethod(
+                format!("/vm/{token}"),
+                Method::Get,
+            ));
+        }
+    };
+
+    let (mut cr3, mut cr4) = (None, None);
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        match param.split_once('=') {
+            Some(("cr3", value)) => cr3 = Some(parse_register("cr3", value)?),
+            Some(("cr4", value)) => cr4 = Some(parse_register("cr4", value)?),
+            _ => {
+                return Err(RequestError::Generic(
+                    StatusCode::BadRequest,
+                    format!("Unknown query parameter: {param}."),
+                ));
+            }
+        }
+    }
+
+    let missing = |name: &str| {
+        RequestError::Generic(
+            StatusCode::BadRequest,
+            format!("Missing query parameter: {name}."),
+        )
+    };
+    let root = PageTableRoot {
+        cr3: cr3.ok_or_else(|| missing("cr3"))?,
+        cr4: cr4.ok_or_else(|| missing("cr4"))?,
+    };
+    Ok(ParsedRequest::new_sync(VmmAction::GetGuestMappings(root)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_get_page_table_request() {
+        assert_eq!(
+            vmm_action_from_request(parse_get_page_table("page-table?cr3=1000&cr4=20").unwrap()),
+            VmmAction::GetGuestMappings(PageTableRoot {
+                cr3: 0x1000,
+                cr4: 0x20
+            })
+        );
+        assert_eq!(
+            vmm_action_from_request(
+                parse_get_page_table("page-table?cr4=0X3606f0&cr3=0x1a000").unwrap()
+            ),
+            VmmAction::GetGuestMappings(PageTableRoot {
+                cr3: 0x1a000,
+                cr4: 0x3606f0
+            })
+        );
+
+        parse_get_page_table("page-table").unwrap_err();
+        parse_get_page_table("page-table?").unwrap_err();
+        parse_get_page_table("page-table?cr3=1000").unwrap_err();
+        parse_get_page_table("page-table?cr4=20").unwrap_err();
+        parse_get_page_table("page-table?cr3=&cr4=20").unwrap_err();
+        parse_get_page_table("page-table?cr3=xyz&cr4=20").unwrap_err();
+        parse_get_page_table("page-table?cr3=1000&cr4=20&pid=1").unwrap_err();
+        parse_get_page_table("page-tables?cr3=1000&cr4=20").unwrap_err();
+    }
+}
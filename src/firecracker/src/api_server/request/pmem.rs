@@ -0,0 +1,94 @@
+// Copyright 2026 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::rpc_interface::VmmAction;
+use vmm::vmm_config::pmem::PmemConfig;
+
+use super::super::parsed_request::{ParsedRequest, RequestError, checked_id};
+use super::{Body, StatusCode};
+
+pub(crate) fn parse_put_pmem(
+    body: &Body,
+    id_from_path: Option<&str>,
+) -> Result<ParsedRequest, RequestError> {
+    let id = if let Some(id) = id_from_path {
+        checked_id(id)?
+    } else {
+        return Err(RequestError::EmptyID);
+    };
+
+    let device_cfg = serde_json::from_slice::<PmemConfig>(body.raw())?;
+
+    if id != device_cfg.drive_id {
+        Err(RequestError::Generic(
+            StatusCode::BadRequest,
+            "The id from the path does not match the id from the body!".to_string(),
+        ))
+    } else {
+        Ok(ParsedRequest::new_sync(VmmAction::InsertPmemDevice(
+            device_cfg,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_put_pmem_request() {
+        parse_put_pmem(&Body::new("invalid_payload"), None).unwrap_err();
+        parse_put_pmem(&Body::new("invalid_payload"), Some("id")).unwrap_err();
+
+        // PUT with invalid fields.
+        let body = r#"{
+            "drive_id": "bar",
+            "cache_type": "Unsafe"
+        }"#;
+        parse_put_pmem(&Body::new(body), Some("bar")).unwrap_err();
+
+        // PUT with missing path_on_host.
+        let body = r#"{
+            "drive_id": "bar"
+        }"#;
+        parse_put_pmem(&Body::new(body), Some("bar")).unwrap_err();
+
+        // PUT with missing all optional fields.
+        let body = r#"{
+            "drive_id": "1000",
+            "path_on_host": "dummy"
+        }"#;
+        let expected_config = PmemConfig {
+            drive_id: "1000".to_string(),
+            path_on_host: "dummy".to_string(),
+            is_root_device: false,
+            is_read_only: false,
+        };
+        assert_eq!(
+            vmm_action_from_request(parse_put_pmem(&Body::new(body), Some("1000")).unwrap()),
+            VmmAction::InsertPmemDevice(expected_config)
+        );
+
+        // Must fail since the drive id differs from id_from_path (1000 vs foo).
+        parse_put_pmem(&Body::new(body), Some("foo")).unwrap_err();
+
+        // PUT with the complete configuration.
+        let body = r#"{
+            "drive_id": "1000",
+            "path_on_host": "dummy",
+            "is_root_device": true,
+            "is_read_only": true
+        }"#;
+        let expected_config = PmemConfig {
+            drive_id: "1000".to_string(),
+            path_on_host: "dummy".to_string(),
+            is_root_device: true,
+            is_read_only: true,
+        };
+        assert_eq!(
+            vmm_action_from_request(parse_put_pmem(&Body::new(body), Some("1000")).unwrap()),
+            VmmAction::InsertPmemDevice(expected_config)
+        );
+    }
+}
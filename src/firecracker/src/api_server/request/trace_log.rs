@@ -0,0 +1,72 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use micro_http::{Method, StatusCode};
+use vmm::rpc_interface::VmmAction;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+
+/// Parses `GET /vm/trace-log[?last_n=<u32>]`, where `token` is the last path segment together
+/// with the query string.
+pub(crate) fn parse_get_trace_log(token: &str) -> Result<ParsedRequest, RequestError> {
+    let query = match token.split_once('?') {
+        Some(("trace-log", query)) => query,
+        None if token == "trace-log" => "",
+        _ => {
+            return Err(RequestError::InvalidPathMethod(
+                format!("/vm/{token}"),
+                Method::Get,
+            ));
+        }
+    };
+
+    // Without `last_n` every buffered entry is returned.
+    let mut last_n = u32::MAX;
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        match param.split_once('=') {
+            Some(("last_n", value)) => {
+                last_n = value.parse().map_err(|_| {
+                    RequestError::Generic(
+                        StatusCode::BadRequest,
+                        format!("Invalid value for last_n: {value}."),
+                    )
+                })?;
+            }
+            _ => {
+                return Err(RequestError::Generic(
+                    StatusCode::BadRequest,
+                    format!("Unknown query parameter: {param}."),
+                ));
+            }
+        }
+    }
+
+    Ok(ParsedRequest::new_sync(VmmAction::GetTraceLog(last_n)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_get_trace_log_request() {
+        assert_eq!(
+            vmm_action_from_request(parse_get_trace_log("trace-log").unwrap()),
+            VmmAction::GetTraceLog(u32::MAX)
+        );
+        assert_eq!(
+            vmm_action_from_request(parse_get_trace_log("trace-log?last_n=10").unwrap()),
+            VmmAction::GetTraceLog(10)
+        );
+        assert_eq!(
+            vmm_action_from_request(parse_get_trace_log("trace-log?").unwrap()),
+            VmmAction::GetTraceLog(u32::MAX)
+        );
+
+        parse_get_trace_log("trace-log?last_n=-1").unwrap_err();
+        parse_get_trace_log("trace-log?last_n=").unwrap_err();
+        parse_get_trace_log("trace-log?first_n=10").unwrap_err();
+        parse_get_trace_log("trace-logs").unwrap_err();
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::rpc_interface::VmmAction;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+use super::{Method, StatusCode};
+
+pub(crate) fn parse_put_transaction(
+    operation_from_path: Option<&str>,
+) -> Result<ParsedRequest, RequestError> {
+    match operation_from_path {
+        Some("begin") => Ok(ParsedRequest::new_sync(VmmAction::BeginTransaction)),
+        Some("commit") => Ok(ParsedRequest::new_sync(VmmAction::CommitTransaction)),
+        Some("rollback") => Ok(ParsedRequest::new_sync(VmmAction::RollbackTransaction)),
+        Some(operation) => Err(RequestError::InvalidPathMethod(
+            format!("/transaction/{}", operation),
+            Method::Put,
+        )),
+        None => Err(RequestError::Generic(
+            StatusCode::BadRequest,
+            "Missing transaction operation.".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_put_transaction_request() {
+        assert_eq!(
+            vmm_action_from_request(parse_put_transaction(Some("begin")).unwrap()),
+            VmmAction::BeginTransaction
+        );
+        assert_eq!(
+            vmm_action_from_request(parse_put_transaction(Some("commit")).unwrap()),
+            VmmAction::CommitTransaction
+        );
+        assert_eq!(
+            vmm_action_from_request(parse_put_transaction(Some("rollback")).unwrap()),
+            VmmAction::RollbackTransaction
+        );
+        parse_put_transaction(Some("abort")).unwrap_err();
+        parse_put_transaction(None).unwrap_err();
+    }
+}
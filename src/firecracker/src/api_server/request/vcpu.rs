@@ -0,0 +1,178 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use vmm::arch::VcpuStateSnapshot;
+use vmm::rpc_interface::VmmAction;
+#[cfg(target_arch = "x86_64")]
+use vmm::vmm_config::pmu::VcpuPmuConfig;
+#[cfg(feature = "debug-api")]
+use vmm::vmm_config::snapshot::{Vm, VmState};
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use super::Method;
+use super::{Body, StatusCode};
+
+fn parse_vcpu_index(index: Option<&str>) -> Result<u8, RequestError> {
+    let index = index.ok_or_else(|| {
+        RequestError::Generic(StatusCode::BadRequest, "Missing vCPU index.".to_string())
+    })?;
+    index.parse::<u8>().map_err(|_| {
+        RequestError::Generic(
+            StatusCode::BadRequest,
+            format!("Invalid vCPU index: {index}."),
+        )
+    })
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+fn check_state_resource(
+    method: Method,
+    index: u8,
+    resource: Option<&str>,
+) -> Result<(), RequestError> {
+    match resource {
+        Some("state") => Ok(()),
+        Some(resource) => Err(RequestError::InvalidPathMethod(
+            format!("/vm/vcpu/{index}/{resource}"),
+            method,
+        )),
+        None => Err(RequestError::InvalidPathMethod(
+            format!("/vm/vcpu/{index}"),
+            method,
+        )),
+    }
+}
+
+#[cfg(feature = "debug-api")]
+pub(crate) fn parse_patch_vcpu(
+    body: &Body,
+    index: Option<&str>,
+) -> Result<ParsedRequest, RequestError> {
+    let index = parse_vcpu_index(index)?;
+    let vcpu = serde_json::from_slice::<Vm>(body.raw())?;
+    match vcpu.state {
+        VmState::Paused => Ok(ParsedRequest::new_sync(VmmAction::PauseVcpu(index))),
+        VmState::Resumed => Ok(ParsedRequest::new_sync(VmmAction::ResumeVcpu(index))),
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+pub(crate) fn parse_get_vcpu_state(
+    index: Option<&str>,
+    resource: Option<&str>,
+) -> Result<ParsedRequest, RequestError> {
+    let index = parse_vcpu_index(index)?;
+    check_state_resource(Method::Get, index, resource)?;
+    Ok(ParsedRequest::new_sync(VmmAction::GetVcpuState(index)))
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+pub(crate) fn parse_put_vcpu_state(
+    body: &Body,
+    index: Option<&str>,
+    resource: Option<&str>,
+) -> Result<ParsedRequest, RequestError> {
+    let index = parse_vcpu_index(index)?;
+    check_state_resource(Method::Put, index, resource)?;
+    let snapshot = serde_json::from_slice::<VcpuStateSnapshot>(body.raw())?;
+    Ok(ParsedRequest::new_sync(VmmAction::SetVcpuState(
+        index,
+        Box::new(snapshot),
+    )))
+}
+
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn parse_put_vcpu_pmu(
+    body: &Body,
+    index: Option<&str>,
+) -> Result<ParsedRequest, RequestError> {
+    let index = parse_vcpu_index(index)?;
+    let config = serde_json::from_slice::<VcpuPmuConfig>(body.raw())?;
+    Ok(ParsedRequest::new_sync(VmmAction::SetVcpuPmu(
+        index, config,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[cfg(feature = "debug-api")]
+    #[test]
+    fn test_parse_patch_vcpu_request() {
+        let body = r#"{"state": "Paused"}"#;
+        assert_eq!(
+            vmm_action_from_request(parse_patch_vcpu(&Body::new(body), Some("1")).unwrap()),
+            VmmAction::PauseVcpu(1)
+        );
+        let body = r#"{"state": "Resumed"}"#;
+        assert_eq!(
+            vmm_action_from_request(parse_patch_vcpu(&Body::new(body), Some("1")).unwrap()),
+            VmmAction::ResumeVcpu(1)
+        );
+
+        parse_patch_vcpu(&Body::new(body), None).unwrap_err();
+        parse_patch_vcpu(&Body::new(body), Some("256")).unwrap_err();
+        parse_patch_vcpu(&Body::new(r#"{"state": "Stopped"}"#), Some("1")).unwrap_err();
+    }
+
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    #[test]
+    fn test_parse_vcpu_state_request() {
+        assert_eq!(
+            vmm_action_from_request(parse_get_vcpu_state(Some("0"), Some("state")).unwrap()),
+            VmmAction::GetVcpuState(0)
+        );
+        parse_get_vcpu_state(Some("0"), None).unwrap_err();
+        parse_get_vcpu_state(Some("0"), Some("regs")).unwrap_err();
+        parse_get_vcpu_state(Some("foo"), Some("state")).unwrap_err();
+
+        let snapshot = VcpuStateSnapshot {
+            regs: Default::default(),
+            sregs: Default::default(),
+            lapic: Default::default(),
+            msrs: vec![(0x10, 0x20)],
+        };
+        let body = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            vmm_action_from_request(
+                parse_put_vcpu_state(&Body::new(body.clone()), Some("0"), Some("state")).unwrap()
+            ),
+            VmmAction::SetVcpuState(0, Box::new(snapshot))
+        );
+        parse_put_vcpu_state(&Body::new(body), Some("0"), None).unwrap_err();
+        parse_put_vcpu_state(&Body::new("{}"), Some("0"), Some("state")).unwrap_err();
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_parse_put_vcpu_pmu_request() {
+        let body = r#"{"enabled": true, "allowed_events": [60, 192]}"#;
+        assert_eq!(
+            vmm_action_from_request(parse_put_vcpu_pmu(&Body::new(body), Some("1")).unwrap()),
+            VmmAction::SetVcpuPmu(
+                1,
+                VcpuPmuConfig {
+                    enabled: true,
+                    allowed_events: vec![60, 192],
+                }
+            )
+        );
+
+        // The allowed events are optional.
+        let body = r#"{"enabled": false}"#;
+        assert_eq!(
+            vmm_action_from_request(parse_put_vcpu_pmu(&Body::new(body), Some("0")).unwrap()),
+            VmmAction::SetVcpuPmu(0, VcpuPmuConfig::default())
+        );
+
+        parse_put_vcpu_pmu(&Body::new(body), None).unwrap_err();
+        parse_put_vcpu_pmu(&Body::new(body), Some("256")).unwrap_err();
+        parse_put_vcpu_pmu(&Body::new(r#"{"allowed_events": []}"#), Some("0")).unwrap_err();
+        parse_put_vcpu_pmu(&Body::new(r#"{"enabled": true, "events": []}"#), Some("0"))
+            .unwrap_err();
+    }
+}
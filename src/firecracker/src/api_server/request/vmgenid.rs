@@ -0,0 +1,39 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use vmm::rpc_interface::VmmAction;
+use vmm::vmm_config::vmgenid::VmGenIdConfig;
+
+use super::super::parsed_request::{ParsedRequest, RequestError};
+use super::Body;
+
+pub(crate) fn parse_put_vm_gen_id(body: &Body) -> Result<ParsedRequest, RequestError> {
+    let config = serde_json::from_slice::<VmGenIdConfig>(body.raw())?;
+    Ok(ParsedRequest::new_sync(VmmAction::SetVmGenId(config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::parsed_request::tests::vmm_action_from_request;
+
+    #[test]
+    fn test_parse_put_vm_gen_id_request() {
+        parse_put_vm_gen_id(&Body::new("invalid_payload")).unwrap_err();
+
+        // PUT with missing fields.
+        parse_put_vm_gen_id(&Body::new("{}")).unwrap_err();
+
+        // PUT with invalid fields.
+        let body = r#"{ "uuid": "1b4e28ba-2fa1-11d2-883f-0016d3cca427", "counter": 1 }"#;
+        parse_put_vm_gen_id(&Body::new(body)).unwrap_err();
+
+        let body = r#"{ "uuid": "1b4e28ba-2fa1-11d2-883f-0016d3cca427" }"#;
+        assert_eq!(
+            vmm_action_from_request(parse_put_vm_gen_id(&Body::new(body)).unwrap()),
+            VmmAction::SetVmGenId(VmGenIdConfig {
+                uuid: "1b4e28ba-2fa1-11d2-883f-0016d3cca427".to_string(),
+            })
+        );
+    }
+}
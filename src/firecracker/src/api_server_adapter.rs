@@ -145,6 +145,7 @@ pub(crate) fn run_with_api(
     boot_timer_enabled: bool,
     api_payload_limit: usize,
     mmds_size_limit: usize,
+    idempotency_ttl_s: u64,
     metadata_json: Option<&str>,
 ) -> Result<(), ApiServerError> {
     // FD to notify of API events. This is a blocking eventfd by design.
@@ -189,12 +190,14 @@ pub(crate) fn run_with_api(
     let api_thread = thread::Builder::new()
         .name("fc_api".to_owned())
         .spawn(move || {
-            ApiServer::new(to_vmm, from_vmm, to_vmm_event_fd).run(
-                server,
-                process_time_reporter,
-                &api_seccomp_filter,
-                api_payload_limit,
-            );
+            ApiServer::new(to_vmm, from_vmm, to_vmm_event_fd)
+                .with_idempotency_ttl(idempotency_ttl_s)
+                .run(
+                    server,
+                    process_time_reporter,
+                    &api_seccomp_filter,
+                    api_payload_limit,
+                );
         })
         .expect("API thread spawn failed.");
 
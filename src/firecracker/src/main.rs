@@ -8,21 +8,24 @@ mod metrics;
 mod seccomp;
 
 use std::fs::{self, File};
-use std::path::PathBuf;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::{io, panic};
 
+use api_server::deduplicator::DEFAULT_IDEMPOTENCY_TTL_S;
 use api_server_adapter::ApiServerError;
 use event_manager::SubscriberOps;
 use seccomp::FilterError;
 use utils::arg_parser::{ArgParser, Argument};
-use utils::validators::validate_instance_id;
+use utils::validators::{ValidatorError, validate_instance_id};
 use vmm::arch::host_page_size;
 use vmm::builder::StartMicrovmError;
 use vmm::logger::{
-    LOGGER, LoggerConfig, METRICS, ProcessTimeReporter, StoreMetric, debug, error, info,
+    LOGGER, LoggerConfig, METRICS, MetricsFormat, ProcessTimeReporter, StoreMetric, debug, error,
+    info, warn,
 };
 use vmm::persist::SNAPSHOT_VERSION;
 use vmm::resources::VmResources;
@@ -53,8 +56,12 @@ enum MainError {
     ParseArguments(#[from] utils::arg_parser::UtilsArgParserError),
     /// When printing Snapshot Data format: {0}
     PrintSnapshotDataFormat(#[from] SnapshotVersionError),
+    /// Invalid instance ID: {0}
+    InvalidInstanceId(ValidatorError),
     /// Invalid value for logger level: {0}.Possible values: [Error, Warning, Info, Debug]
     InvalidLogLevel(vmm::logger::LevelFilterFromStrError),
+    /// Invalid value for metrics format: {0}. Possible values: [json, prometheus]
+    InvalidMetricsFormat(vmm::logger::MetricsFormatFromStrError),
     /// Could not initialize logger: {0}
     LoggerInitialization(vmm::logger::LoggerUpdateError),
     /// Could not initialize metrics: {0}
@@ -63,6 +70,18 @@ enum MainError {
     SeccompFilter(FilterError),
     /// Failed to resize fd table: {0}
     ResizeFdtable(ResizeFdTableError),
+    /// Failed to set the OOM score adjustment: {0}
+    OomScoreAdj(OomScoreAdjError),
+    /// Failed to set the open files limit: {0}
+    MaxOpenFiles(MaxOpenFilesError),
+    /// Failed to set the cgroup of the microVM: {0}
+    Cgroup(vmm::cgroup::CgroupError),
+    /// Invalid value `{0}` for the vCPU pool size, expected an unsigned integer
+    InvalidVcpuPoolSize(String),
+    /// Failed to create the vCPU pool: {0}
+    VcpuPool(io::Error),
+    /// Failed to lock the instance ID: {0}
+    InstanceLock(InstanceLockError),
     /// RunWithApiError error: {0}
     RunWithApi(ApiServerError),
     /// RunWithoutApiError error: {0}
@@ -79,11 +98,44 @@ enum ResizeFdTableError {
     Close(io::Error),
 }
 
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+enum OomScoreAdjError {
+    /// Invalid value `{0}`, expected an integer between -1000 and 1000
+    InvalidValue(String),
+    /// Failed to write /proc/self/oom_score_adj: {0}
+    Write(io::Error),
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+enum MaxOpenFilesError {
+    /// Invalid value `{0}`, expected an unsigned integer
+    InvalidValue(String),
+    /// Failed to get RLIMIT_NOFILE: {0}
+    GetRlimit(io::Error),
+    /// Failed to set RLIMIT_NOFILE: {0}
+    SetRlimit(io::Error),
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+enum InstanceLockError {
+    /// Failed to open the lock file {0}: {1}
+    Open(PathBuf, io::Error),
+    /// Failed to lock {0}: {1}
+    Lock(PathBuf, io::Error),
+    /// Another Firecracker process already runs with the instance ID `{0}`
+    Duplicate(String),
+}
+
 impl From<MainError> for FcExitCode {
     fn from(value: MainError) -> Self {
         match value {
             MainError::ParseArguments(_) => FcExitCode::ArgParsing,
+            MainError::InvalidInstanceId(_) => FcExitCode::ArgParsing,
             MainError::InvalidLogLevel(_) => FcExitCode::BadConfiguration,
+            MainError::InvalidMetricsFormat(_) => FcExitCode::BadConfiguration,
+            MainError::OomScoreAdj(OomScoreAdjError::InvalidValue(_)) => FcExitCode::ArgParsing,
+            MainError::MaxOpenFiles(MaxOpenFilesError::InvalidValue(_)) => FcExitCode::ArgParsing,
+            MainError::InvalidVcpuPoolSize(_) => FcExitCode::ArgParsing,
             MainError::RunWithApi(ApiServerError::MicroVMStoppedWithError(code)) => code,
             MainError::RunWithoutApiError(RunWithoutApiError::Shutdown(code)) => code,
             _ => FcExitCode::GenericError,
@@ -140,6 +192,7 @@ fn main_exec() -> Result<(), MainError> {
     }));
 
     let http_max_payload_size_str = HTTP_MAX_PAYLOAD_SIZE.to_string();
+    let idempotency_ttl_str = DEFAULT_IDEMPOTENCY_TTL_S.to_string();
 
     let mut arg_parser =
         ArgParser::new()
@@ -153,8 +206,18 @@ fn main_exec() -> Result<(), MainError> {
                 Argument::new("id")
                     .takes_value(true)
                     .default_value(vmm::logger::DEFAULT_INSTANCE_ID)
+                    .forbids(vec!["instance-id"])
                     .help("MicroVM unique identifier."),
             )
+            .arg(
+                Argument::new("instance-id")
+                    .takes_value(true)
+                    .forbids(vec!["id"])
+                    .help(
+                        "Unique identifier of the instance, assigned by the orchestrator. Alias \
+                         of --id.",
+                    ),
+            )
             .arg(
                 Argument::new("seccomp-filter")
                     .takes_value(true)
@@ -226,11 +289,22 @@ fn main_exec() -> Result<(), MainError> {
             .arg(Argument::new("show-log-origin").takes_value(false).help(
                 "Whether or not to include the file path and line number of the log's origin.",
             ))
+            .arg(
+                Argument::new("log-json")
+                    .takes_value(false)
+                    .help("Whether or not to write each log line as a JSON object."),
+            )
             .arg(
                 Argument::new("metrics-path")
                     .takes_value(true)
                     .help("Path to a fifo or a file used for configuring the metrics on startup."),
             )
+            .arg(
+                Argument::new("metrics-format")
+                    .takes_value(true)
+                    .requires("metrics-path")
+                    .help("Format of the metrics written to --metrics-path: json or prometheus."),
+            )
             .arg(Argument::new("boot-timer").takes_value(false).help(
                 "Whether or not to load boot timer device for logging elapsed time since \
                  InstanceStart command.",
@@ -250,6 +324,14 @@ fn main_exec() -> Result<(), MainError> {
                     .takes_value(true)
                     .help("Print the data format version of the provided snapshot state file."),
             )
+            .arg(
+                Argument::new("verify-snapshot-integrity")
+                    .takes_value(false)
+                    .help(
+                        "Record the SHA-256 hashes of the files of the created snapshots, and \
+                         check them before loading a snapshot.",
+                    ),
+            )
             .arg(
                 Argument::new("http-api-max-payload-size")
                     .takes_value(true)
@@ -260,7 +342,39 @@ fn main_exec() -> Result<(), MainError> {
                 Argument::new("mmds-size-limit")
                     .takes_value(true)
                     .help("Mmds data store limit, in bytes."),
-            );
+            )
+            .arg(
+                Argument::new("http-api-idempotency-ttl")
+                    .takes_value(true)
+                    .default_value(&idempotency_ttl_str)
+                    .help(
+                        "Time, in seconds, during which the response of a successful API request \
+                         carrying an X-Idempotency-Key header is replayed to requests with the \
+                         same key.",
+                    ),
+            )
+            .arg(Argument::new("oom-score-adj").takes_value(true).help(
+                "OOM score adjustment of the Firecracker process, between -1000 and 1000. Lower \
+                 values make the OOM killer less likely to pick Firecracker. Lowering the value \
+                 requires CAP_SYS_RESOURCE.",
+            ))
+            .arg(Argument::new("max-open-files").takes_value(true).help(
+                "Soft limit on the number of files the Firecracker process can open \
+                 (RLIMIT_NOFILE). Capped to the hard limit.",
+            ))
+            .arg(Argument::new("lock-dir").takes_value(true).help(
+                "Directory holding a lock file for each instance ID, so that two Firecracker \
+                 processes sharing the directory cannot run with the same --instance-id.",
+            ))
+            .arg(Argument::new("cgroup-path").takes_value(true).help(
+                "Path of the cgroup v2 directory of the microVM, to which the resource controls \
+                 of the machine configuration are written.",
+            ))
+            .arg(Argument::new("vcpu-pool-size").takes_value(true).help(
+                "Number of vCPU threads to spawn while the microVM is configured and the host is \
+                 idle, ahead of its start. The vCPUs without a thread of the pool, or with a CPU \
+                 budget, spawn theirs when starting.",
+            ));
 
     arg_parser.parse_from_cmdline()?;
     let arguments = arg_parser.arguments();
@@ -286,14 +400,23 @@ fn main_exec() -> Result<(), MainError> {
         return Ok(());
     }
 
-    // It's safe to unwrap here because the field's been provided with a default value.
-    let instance_id = arguments.single_value("id").unwrap();
-    validate_instance_id(instance_id.as_str()).expect("Invalid instance ID");
+    // It's safe to unwrap here because the `id` field's been provided with a default value.
+    let instance_id = arguments
+        .single_value("instance-id")
+        .or(arguments.single_value("id"))
+        .unwrap();
+    validate_instance_id(instance_id.as_str()).map_err(MainError::InvalidInstanceId)?;
 
     // Apply the logger configuration.
     vmm::logger::INSTANCE_ID
         .set(String::from(instance_id))
         .unwrap();
+    // The lock is held until Firecracker exits.
+    let _instance_lock = arguments
+        .single_value("lock-dir")
+        .map(|lock_dir| lock_instance_id(Path::new(lock_dir), instance_id))
+        .transpose()
+        .map_err(MainError::InstanceLock)?;
     let log_path = arguments.single_value("log-path").map(PathBuf::from);
     let level = arguments
         .single_value("level")
@@ -303,6 +426,7 @@ fn main_exec() -> Result<(), MainError> {
     let show_level = arguments.flag_present("show-level").then_some(true);
     let show_log_origin = arguments.flag_present("show-log-origin").then_some(true);
     let module = arguments.single_value("module").cloned();
+    let json = arguments.flag_present("log-json").then_some(true);
     LOGGER
         .update(LoggerConfig {
             log_path,
@@ -310,6 +434,7 @@ fn main_exec() -> Result<(), MainError> {
             show_level,
             show_log_origin,
             module,
+            json,
         })
         .map_err(MainError::LoggerInitialization)?;
     info!("Running Firecracker v{FIRECRACKER_VERSION}");
@@ -319,6 +444,10 @@ fn main_exec() -> Result<(), MainError> {
     #[cfg(target_arch = "aarch64")]
     enable_ssbd_mitigation();
 
+    if let Some(max_open_files) = arguments.single_value("max-open-files") {
+        set_max_open_files(max_open_files).map_err(MainError::MaxOpenFiles)?;
+    }
+
     if let Err(err) = resize_fdtable() {
         match err {
             // These errors are non-critical: In the worst case we have worse snapshot restore
@@ -332,6 +461,14 @@ fn main_exec() -> Result<(), MainError> {
         }
     }
 
+    if let Some(oom_score_adj) = arguments.single_value("oom-score-adj") {
+        set_oom_score_adj(oom_score_adj).map_err(MainError::OomScoreAdj)?;
+    }
+
+    // The counters are inherited by the threads spawned afterwards.
+    #[cfg(feature = "perf-counters")]
+    vmm::perf_counters::init_perf_counters();
+
     // Display warnings for any used deprecated parameters.
     // Currently unused since there are no deprecated parameters. Uncomment the line when
     // deprecating one.
@@ -345,12 +482,27 @@ fn main_exec() -> Result<(), MainError> {
     };
 
     if let Some(metrics_path) = arguments.single_value("metrics-path") {
+        let format = arguments
+            .single_value("metrics-format")
+            .map(|s| MetricsFormat::from_str(s))
+            .transpose()
+            .map_err(MainError::InvalidMetricsFormat)?
+            .unwrap_or_default();
         let metrics_config = MetricsConfig {
             metrics_path: PathBuf::from(metrics_path),
+            format,
         };
         init_metrics(metrics_config).map_err(MainError::MetricsInitialization)?;
     }
 
+    if let Some(cgroup_path) = arguments.single_value("cgroup-path") {
+        vmm::cgroup::init_vm_cgroup(PathBuf::from(cgroup_path)).map_err(MainError::Cgroup)?;
+    }
+
+    if arguments.flag_present("verify-snapshot-integrity") {
+        vmm::snapshot::integrity::enable_snapshot_integrity();
+    }
+
     let mut seccomp_filters: BpfThreadMap = SeccompConfig::from_args(
         arguments.flag_present("no-seccomp"),
         arguments.single_value("seccomp-filter"),
@@ -358,6 +510,17 @@ fn main_exec() -> Result<(), MainError> {
     .and_then(seccomp::get_filters)
     .map_err(MainError::SeccompFilter)?;
 
+    if let Some(pool_size) = arguments.single_value("vcpu-pool-size") {
+        let pool_size = pool_size
+            .parse::<usize>()
+            .map_err(|_| MainError::InvalidVcpuPoolSize(pool_size.clone()))?;
+        // The pool threads must be spawned before the seccomp filters are applied, and install
+        // the vCPU filter themselves.
+        let vcpu_filter = seccomp_filters.get("vcpu").cloned().unwrap_or_default();
+        vmm::vstate::vcpu_pool::init_vcpu_pool(pool_size, vcpu_filter)
+            .map_err(MainError::VcpuPool)?;
+    }
+
     let vmm_config_json = arguments
         .single_value("config-file")
         .map(fs::read_to_string)
@@ -391,6 +554,16 @@ fn main_exec() -> Result<(), MainError> {
         })
         .unwrap_or_else(|| api_payload_limit);
 
+    let idempotency_ttl_s = arg_parser
+        .arguments()
+        .single_value("http-api-idempotency-ttl")
+        .map(|ttl| {
+            ttl.parse::<u64>()
+                .expect("'http-api-idempotency-ttl' parameter expected to be of 'u64' type.")
+        })
+        // Safe to unwrap as we provide a default value.
+        .unwrap();
+
     if api_enabled {
         let bind_path = arguments
             .single_value("api-sock")
@@ -424,6 +597,7 @@ fn main_exec() -> Result<(), MainError> {
             boot_timer_enabled,
             api_payload_limit,
             mmds_size_limit,
+            idempotency_ttl_s,
             metadata_json.as_deref(),
         )
         .map_err(MainError::RunWithApi)
@@ -492,6 +666,79 @@ fn resize_fdtable() -> Result<(), ResizeFdTableError> {
     Ok(())
 }
 
+/// Takes an exclusive lock on the `<instance_id>.lock` file of `lock_dir`. The kernel releases
+/// the lock when Firecracker exits, even if it crashes, so stale lock files do not block a later
+/// instance with the same ID.
+fn lock_instance_id(lock_dir: &Path, instance_id: &str) -> Result<File, InstanceLockError> {
+    let path = lock_dir.join(format!("{instance_id}.lock"));
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|err| InstanceLockError::Open(path.clone(), err))?;
+
+    // SAFETY: `file` is a valid file descriptor, and the call has no memory effects.
+    let ret = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if ret < 0 {
+        let err = io::Error::last_os_error();
+        return Err(match err.raw_os_error() {
+            Some(libc::EWOULDBLOCK) => InstanceLockError::Duplicate(instance_id.to_string()),
+            _ => InstanceLockError::Lock(path, err),
+        });
+    }
+    Ok(file)
+}
+
+/// Sets the soft limit on the number of open files to `value`, or to the hard limit if `value`
+/// exceeds it.
+fn set_max_open_files(value: &str) -> Result<(), MaxOpenFilesError> {
+    let requested = value
+        .parse::<u64>()
+        .map_err(|_| MaxOpenFilesError::InvalidValue(value.to_string()))?;
+
+    let mut rlimit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: We pass a pointer to a valid area of memory to which we have exclusive mutable access
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlimit as *mut libc::rlimit) } < 0 {
+        return Err(MaxOpenFilesError::GetRlimit(io::Error::last_os_error()));
+    }
+
+    rlimit.rlim_cur = if rlimit.rlim_max != libc::RLIM_INFINITY && requested > rlimit.rlim_max {
+        warn!(
+            "The requested open files limit of {requested} exceeds the hard limit of {}, using \
+             the hard limit instead",
+            rlimit.rlim_max
+        );
+        rlimit.rlim_max
+    } else {
+        requested
+    };
+    // SAFETY: We pass a pointer to a valid `rlimit`.
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &rlimit as *const libc::rlimit) } < 0 {
+        return Err(MaxOpenFilesError::SetRlimit(io::Error::last_os_error()));
+    }
+    info!("Set the open files limit to {}", rlimit.rlim_cur);
+    Ok(())
+}
+
+/// Sets the OOM score adjustment of the Firecracker process, which the OOM killer adds to the
+/// badness score it computes for the process.
+fn set_oom_score_adj(value: &str) -> Result<(), OomScoreAdjError> {
+    let oom_score_adj = value
+        .parse::<i16>()
+        .ok()
+        .filter(|adj| (-1000..=1000).contains(adj))
+        .ok_or_else(|| OomScoreAdjError::InvalidValue(value.to_string()))?;
+
+    fs::write("/proc/self/oom_score_adj", oom_score_adj.to_string())
+        .map_err(OomScoreAdjError::Write)?;
+    info!("Set the OOM score adjustment to {oom_score_adj}");
+    Ok(())
+}
+
 /// Enable SSBD mitigation through `prctl`.
 #[cfg(target_arch = "aarch64")]
 pub fn enable_ssbd_mitigation() {
@@ -0,0 +1,64 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Benchmarking cases:
+//   * Starting a vCPU thread by spawning it
+//   * Starting a vCPU thread from a `VcpuPool`
+
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::thread;
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use vmm::vstate::vcpu_pool::VcpuPool;
+
+pub fn bench_vcpu_thread_start(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vcpu_thread_start");
+
+    // Measures the time until the thread runs the vCPU, which is when the vCPU can boot.
+    group.bench_function("spawn", |b| {
+        b.iter_batched(
+            channel,
+            |(sender, receiver)| {
+                let handle = thread::Builder::new()
+                    .name("fc_vcpu 0".to_string())
+                    .spawn(move || sender.send(()).unwrap())
+                    .unwrap();
+                receiver.recv().unwrap();
+                handle
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    let pool = VcpuPool::new(1, Arc::new(vec![]));
+    group.bench_function("pool", |b| {
+        b.iter_batched(
+            || {
+                pool.fill().unwrap();
+                channel()
+            },
+            |(sender, receiver)| {
+                let handle = pool
+                    .acquire(0)
+                    .unwrap()
+                    .run(move || sender.send(()).unwrap());
+                receiver.recv().unwrap();
+                handle
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group! {
+    name = vcpu_pool_benches;
+    config = Criterion::default().noise_threshold(0.05);
+    targets = bench_vcpu_thread_start
+}
+
+criterion_main! {
+    vcpu_pool_benches
+}
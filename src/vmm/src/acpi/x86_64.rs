@@ -8,7 +8,7 @@ use acpi_tables::fadt::{
     IAPC_BOOT_ARG_FLAGS_VGA_NOT_PRESENT,
 };
 use acpi_tables::madt::{IoAPIC, LocalAPIC};
-use acpi_tables::{Fadt, aml};
+use acpi_tables::{Aml, Fadt, aml};
 use vm_memory::GuestAddress;
 use zerocopy::IntoBytes;
 
@@ -40,8 +40,72 @@ pub(crate) fn setup_arch_fadt(fadt: &mut Fadt) {
     );
 }
 
+// UUID of the platform-wide `_OSC` (0811B06E-4A27-44F9-8D60-3CBBC22E7B48), in the byte order of
+// the `ToUUID` ASL macro.
+const OSC_PLATFORM_UUID: [u8; 16] = [
+    0x6e, 0xb0, 0x11, 0x08, 0x27, 0x4a, 0xf9, 0x44, 0x8d, 0x60, 0x3c, 0xbb, 0xc2, 0x2e, 0x7b, 0x48,
+];
+// Revision of the platform-wide `_OSC` capabilities buffer.
+const OSC_PLATFORM_REVISION: u8 = 1;
+// Status bits of the first `_OSC` capabilities DWORD, reporting an unrecognized UUID, an
+// unrecognized revision, and capabilities requested by the guest but not granted.
+const OSC_UNRECOGNIZED_UUID: u32 = 1 << 2;
+const OSC_UNRECOGNIZED_REVISION: u32 = 1 << 3;
+const OSC_CAPABILITIES_MASKED: u32 = 1 << 4;
+// Insertion/Ejection `_OST` processing support bit of the platform-wide `_OSC` capabilities, which
+// the guest sets to report that it handles the hot-plug of CPUs.
+const OSC_SB_HOTPLUG_OST_SUPPORT: u32 = 1 << 3;
+
+/// Appends the platform-wide `\_SB._OSC` method, which the guest calls to negotiate the features
+/// it handles with the platform. The method only grants the `_OST` processing of hot-plug events,
+/// clearing the other capabilities and reporting them as masked. It neither enables the hot-plug
+/// of CPUs, which Firecracker does not support yet, nor raises a GPE, since the microVM uses
+/// hardware-reduced ACPI.
+fn append_osc_aml(dsdt_data: &mut Vec<u8>) -> Result<(), aml::AmlError> {
+    let cdw1: aml::Path = "CDW1".try_into()?;
+    let cdw2: aml::Path = "CDW2".try_into()?;
+    aml::Method::new(
+        "_SB_._OSC".try_into()?,
+        4,
+        false,
+        vec![
+            &aml::CreateField::<u32>::new(&aml::Arg(3), &0usize, "CDW1".try_into()?),
+            &aml::If::new(
+                &aml::Equal::new(&aml::Arg(0), &aml::Buffer::new(OSC_PLATFORM_UUID.to_vec())),
+                vec![
+                    &aml::If::new(
+                        &aml::Equal::new(&aml::Arg(1), &OSC_PLATFORM_REVISION),
+                        vec![
+                            &aml::CreateField::<u32>::new(
+                                &aml::Arg(3),
+                                &4usize,
+                                "CDW2".try_into()?,
+                            ),
+                            // Local0 keeps the capabilities requested by the guest.
+                            &aml::Store::new(&aml::Local(0), &cdw2),
+                            &aml::And::new(&cdw2, &cdw2, &OSC_SB_HOTPLUG_OST_SUPPORT),
+                            &aml::If::new(
+                                &aml::Equal::new(&aml::Local(0), &cdw2),
+                                vec![&aml::Return::new(&aml::Arg(3))],
+                            ),
+                            &aml::Or::new(&cdw1, &cdw1, &OSC_CAPABILITIES_MASKED),
+                            &aml::Return::new(&aml::Arg(3)),
+                        ],
+                    ),
+                    &aml::Or::new(&cdw1, &cdw1, &OSC_UNRECOGNIZED_REVISION),
+                    &aml::Return::new(&aml::Arg(3)),
+                ],
+            ),
+            &aml::Or::new(&cdw1, &cdw1, &OSC_UNRECOGNIZED_UUID),
+            &aml::Return::new(&aml::Arg(3)),
+        ],
+    )
+    .append_aml_bytes(dsdt_data)
+}
+
 #[inline(always)]
 pub(crate) fn setup_arch_dsdt(dsdt_data: &mut Vec<u8>) -> Result<(), aml::AmlError> {
+    append_osc_aml(dsdt_data)?;
     PortIODeviceManager::append_aml_bytes(dsdt_data)
 }
 
@@ -52,3 +116,41 @@ pub(crate) const fn apic_addr() -> u32 {
 pub(crate) const fn rsdp_addr() -> GuestAddress {
     GuestAddress(layout::RSDP_ADDR)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc_aml() {
+        let mut aml = Vec::new();
+        append_osc_aml(&mut aml).unwrap();
+
+        // MethodOp, with a two bytes PkgLength, named `\_SB._OSC`, with 4 arguments and not
+        // serialized.
+        assert_eq!(aml[0], 0x14);
+        assert_eq!(&aml[3..13], b"\x2e_SB__OSC\x04");
+
+        // The method matches the platform-wide UUID and only grants hot-plug `_OST` processing.
+        assert!(aml.windows(16).any(|w| w == OSC_PLATFORM_UUID));
+        // AndOp CDW2 OSC_SB_HOTPLUG_OST_SUPPORT CDW2
+        let granted = b"\x7bCDW2\x0c\x08\x00\x00\x00CDW2";
+        assert!(aml.windows(granted.len()).any(|w| w == granted));
+
+        // Only revision 1 of the capabilities buffer is recognized.
+        // LEqualOp Arg1 OSC_PLATFORM_REVISION
+        let revision = b"\x93\x69\x0a\x01";
+        assert!(aml.windows(revision.len()).any(|w| w == revision));
+        // OrOp CDW1 OSC_UNRECOGNIZED_REVISION CDW1
+        let unrecognized = b"\x7dCDW1\x0c\x08\x00\x00\x00CDW1";
+        assert!(aml.windows(unrecognized.len()).any(|w| w == unrecognized));
+
+        // The capabilities requested but not granted are reported as masked.
+        // StoreOp CDW2 Local0
+        let requested = b"\x70CDW2\x60";
+        assert!(aml.windows(requested.len()).any(|w| w == requested));
+        // OrOp CDW1 OSC_CAPABILITIES_MASKED CDW1
+        let masked = b"\x7dCDW1\x0c\x10\x00\x00\x00CDW1";
+        assert!(aml.windows(masked.len()).any(|w| w == masked));
+    }
+}
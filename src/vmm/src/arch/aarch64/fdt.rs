@@ -24,6 +24,8 @@ use crate::vstate::memory::{Address, GuestMemory, GuestMemoryMmap};
 const GIC_PHANDLE: u32 = 1;
 // This is a value for uniquely identifying the FDT node containing the clock definition.
 const CLOCK_PHANDLE: u32 = 2;
+// This is a value for uniquely identifying the FDT node declaring the MSI controller.
+const MSI_PHANDLE: u32 = 3;
 // You may be wondering why this big value?
 // This phandle is used to uniquely identify the FDT nodes containing cache information. Each cpu
 // can have a variable number of caches, some of these caches may be shared with other cpus.
@@ -297,6 +299,20 @@ fn create_gic_node(fdt: &mut FdtWriter, gic_device: &GICDevice) -> Result<(), Fd
     ];
 
     fdt.property_array_u32("interrupts", &gic_intr)?;
+
+    // The GICv3 ITS translates the MSIs of devices into LPIs.
+    // https://www.kernel.org/doc/Documentation/devicetree/bindings/interrupt-controller/arm%2Cgic-v3.txt
+    if let Some(msi_properties) = gic_device.msi_properties() {
+        let msic = fdt.begin_node("msic")?;
+        fdt.property_string("compatible", "arm,gic-v3-its")?;
+        fdt.property_null("msi-controller")?;
+        // The single MSI cell is the DeviceID of the device sending the MSI.
+        fdt.property_u32("#msi-cells", 1)?;
+        fdt.property_array_u64("reg", msi_properties)?;
+        fdt.property_u32("phandle", MSI_PHANDLE)?;
+        fdt.end_node(msic)?;
+    }
+
     fdt.end_node(interrupt)?;
 
     Ok(())
@@ -68,6 +68,8 @@ impl GICv2 {
                 GICv2::get_cpu_addr(),
                 GICv2::get_cpu_size(),
             ],
+            msi_properties: None,
+            its_device: None,
             vcpu_count,
         })
     }
@@ -6,6 +6,7 @@ mod regs;
 use kvm_ioctls::{DeviceFd, VmFd};
 
 use crate::arch::aarch64::gic::{GicError, GicState};
+use crate::logger::warn;
 
 #[derive(Debug)]
 pub struct GICv3(super::GIC);
@@ -24,6 +25,7 @@ impl GICv3 {
     const SZ_64K: u64 = 0x0001_0000;
     const KVM_VGIC_V3_DIST_SIZE: u64 = GICv3::SZ_64K;
     const KVM_VGIC_V3_REDIST_SIZE: u64 = (2 * GICv3::SZ_64K);
+    const KVM_VGIC_V3_ITS_SIZE: u64 = (2 * GICv3::SZ_64K);
 
     // Device trees specific constants
     const ARCH_GIC_V3_MAINT_IRQ: u32 = 9;
@@ -48,8 +50,20 @@ impl GICv3 {
         vcpu_count * GICv3::KVM_VGIC_V3_REDIST_SIZE
     }
 
+    /// Get the address of the ITS.
+    fn get_its_addr(vcpu_count: u64) -> u64 {
+        GICv3::get_redists_addr(vcpu_count) - GICv3::get_its_size()
+    }
+
+    /// Get the size of the ITS.
+    fn get_its_size() -> u64 {
+        GICv3::KVM_VGIC_V3_ITS_SIZE
+    }
+
     pub const VERSION: u32 = kvm_bindings::kvm_device_type_KVM_DEV_TYPE_ARM_VGIC_V3;
 
+    const ITS_VERSION: u32 = kvm_bindings::kvm_device_type_KVM_DEV_TYPE_ARM_VGIC_ITS;
+
     pub fn fdt_compatibility(&self) -> &str {
         "arm,gic-v3"
     }
@@ -58,8 +72,8 @@ impl GICv3 {
         GICv3::ARCH_GIC_V3_MAINT_IRQ
     }
 
-    /// Create the GIC device object
-    pub fn create_device(fd: DeviceFd, vcpu_count: u64) -> Self {
+    /// Create the GIC device object, with an ITS if `its_fd` is set.
+    pub fn create_device(fd: DeviceFd, its_fd: Option<DeviceFd>, vcpu_count: u64) -> Self {
         GICv3(super::GIC {
             fd,
             properties: [
@@ -68,16 +82,20 @@ impl GICv3 {
                 GICv3::get_redists_addr(vcpu_count),
                 GICv3::get_redists_size(vcpu_count),
             ],
+            msi_properties: its_fd
+                .as_ref()
+                .map(|_| [GICv3::get_its_addr(vcpu_count), GICv3::get_its_size()]),
+            its_device: its_fd,
             vcpu_count,
         })
     }
 
     pub fn save_device(&self, mpidrs: &[u64]) -> Result<GicState, GicError> {
-        regs::save_state(&self.fd, mpidrs)
+        regs::save_state(&self.fd, self.its_device(), mpidrs)
     }
 
     pub fn restore_device(&self, mpidrs: &[u64], state: &GicState) -> Result<(), GicError> {
-        regs::restore_state(&self.fd, mpidrs, state)
+        regs::restore_state(&self.fd, self.its_device(), mpidrs, state)
     }
 
     pub fn init_device_attributes(gic_device: &Self) -> Result<(), GicError> {
@@ -101,6 +119,17 @@ impl GICv3 {
             0,
         )?;
 
+        // Setting up the ITS attribute. The ITS frame sits right below the redistributors.
+        if let Some(its_fd) = gic_device.its_device() {
+            Self::set_device_attribute(
+                its_fd,
+                kvm_bindings::KVM_DEV_ARM_VGIC_GRP_ADDR,
+                u64::from(kvm_bindings::KVM_VGIC_ITS_ADDR_TYPE),
+                &GICv3::get_its_addr(gic_device.vcpu_count()) as *const u64 as u64,
+                0,
+            )?;
+        }
+
         Ok(())
     }
 
@@ -116,11 +145,29 @@ impl GICv3 {
             .map_err(GicError::CreateGIC)
     }
 
+    /// Initialize an ITS device. KVM emulates the ITS in-kernel, including the processing of its
+    /// command queue, so the guest can use MSIs without involving the VMM.
+    pub fn init_its_device(vm: &VmFd) -> Result<DeviceFd, GicError> {
+        let mut its_device = kvm_bindings::kvm_create_device {
+            type_: Self::ITS_VERSION,
+            fd: 0,
+            flags: 0,
+        };
+
+        vm.create_device(&mut its_device)
+            .map_err(GicError::CreateITS)
+    }
+
     /// Method to initialize the GIC device
     pub fn create(vm: &VmFd, vcpu_count: u64) -> Result<Self, GicError> {
         let vgic_fd = Self::init_device(vm)?;
+        // The ITS only provides MSIs, which no device needs yet. Hosts whose GIC lacks one, or
+        // whose kernel does not emulate it, still get a GICv3 without MSI support.
+        let its_fd = Self::init_its_device(vm)
+            .inspect_err(|err| warn!("Creating the GICv3 without an ITS: {err}"))
+            .ok();
 
-        let device = Self::create_device(vgic_fd, vcpu_count);
+        let device = Self::create_device(vgic_fd, its_fd, vcpu_count);
 
         Self::init_device_attributes(&device)?;
 
@@ -156,6 +203,17 @@ impl GICv3 {
             0,
         )?;
 
+        // Finalize the ITS.
+        if let Some(its_fd) = gic_device.its_device() {
+            Self::set_device_attribute(
+                its_fd,
+                kvm_bindings::KVM_DEV_ARM_VGIC_GRP_CTRL,
+                u64::from(kvm_bindings::KVM_DEV_ARM_VGIC_CTRL_INIT),
+                0,
+                0,
+            )?;
+        }
+
         Ok(())
     }
 
@@ -196,6 +254,30 @@ fn save_pending_tables(fd: &DeviceFd) -> Result<(), GicError> {
     })
 }
 
+/// Function that flushes
+/// the ITS tables (device, collection and interrupt translation tables) into guest RAM.
+fn save_its_tables(fd: &DeviceFd) -> Result<(), GicError> {
+    its_tables_ctrl(fd, kvm_bindings::KVM_DEV_ARM_ITS_SAVE_TABLES)
+}
+
+/// Function that loads
+/// the ITS tables (device, collection and interrupt translation tables) from guest RAM.
+fn restore_its_tables(fd: &DeviceFd) -> Result<(), GicError> {
+    its_tables_ctrl(fd, kvm_bindings::KVM_DEV_ARM_ITS_RESTORE_TABLES)
+}
+
+fn its_tables_ctrl(fd: &DeviceFd, ctrl: u32) -> Result<(), GicError> {
+    let its_tables_attr = kvm_bindings::kvm_device_attr {
+        group: kvm_bindings::KVM_DEV_ARM_VGIC_GRP_CTRL,
+        attr: u64::from(ctrl),
+        addr: 0,
+        flags: 0,
+    };
+    fd.set_device_attr(&its_tables_attr).map_err(|err| {
+        GicError::DeviceAttribute(err, true, kvm_bindings::KVM_DEV_ARM_VGIC_GRP_CTRL)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::undocumented_unsafe_blocks)]
@@ -224,4 +306,37 @@ mod tests {
         // dropping gic_fd would double close the gic fd, so leak it
         std::mem::forget(gic);
     }
+
+    #[test]
+    fn test_create_its() {
+        let kvm = Kvm::new().unwrap();
+        let vm = kvm.create_vm().unwrap();
+        let gic = create_gic(&vm, 1, Some(GICVersion::GICV3)).expect("Cannot create gic");
+
+        assert!(gic.its_device().is_some());
+        assert_eq!(
+            gic.msi_properties().unwrap(),
+            [
+                GICv3::get_redists_addr(1) - GICv3::KVM_VGIC_V3_ITS_SIZE,
+                GICv3::KVM_VGIC_V3_ITS_SIZE
+            ]
+        );
+        // The ITS frame sits right below the redistributors.
+        assert_eq!(
+            gic.msi_properties().unwrap()[0] + gic.msi_properties().unwrap()[1],
+            gic.device_properties()[2]
+        );
+    }
+
+    #[test]
+    fn test_create_without_its() {
+        let kvm = Kvm::new().unwrap();
+        let vm = kvm.create_vm().unwrap();
+        let gic = GICv3::create_device(GICv3::init_device(&vm).unwrap(), None, 1);
+        GICv3::init_device_attributes(&gic).unwrap();
+        GICv3::finalize_device(&gic).unwrap();
+
+        assert!(gic.its_device().is_none());
+        assert!(gic.msi_properties().is_none());
+    }
 }
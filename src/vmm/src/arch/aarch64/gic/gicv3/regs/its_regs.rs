@@ -0,0 +1,98 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use kvm_bindings::*;
+use kvm_ioctls::DeviceFd;
+
+use crate::arch::aarch64::gic::GicError;
+use crate::arch::aarch64::gic::regs::{GicRegState, SimpleReg, VgicRegEngine};
+
+// Relevant ITS registers that we want to save/restore. KVM accesses all of them as 64-bit values.
+const GITS_CTLR: SimpleReg = SimpleReg::new(0x0000, 8);
+const GITS_IIDR: SimpleReg = SimpleReg::new(0x0004, 8);
+const GITS_CBASER: SimpleReg = SimpleReg::new(0x0080, 8);
+const GITS_CWRITER: SimpleReg = SimpleReg::new(0x0088, 8);
+const GITS_CREADR: SimpleReg = SimpleReg::new(0x0090, 8);
+const GITS_BASER: SimpleReg = SimpleReg::new(0x0100, 64);
+
+// List with relevant ITS registers that we will be restoring, in the order KVM expects them to be
+// restored in. GITS_CTLR enables the ITS, so it is restored last, after the ITS tables.
+// See Documentation/virt/kvm/devices/arm-vgic-its.rst in the linux kernel.
+static VGIC_ITS_REGS: &[SimpleReg] = &[
+    GITS_IIDR,
+    GITS_CBASER,
+    GITS_CREADR,
+    GITS_CWRITER,
+    GITS_BASER,
+];
+
+struct ItsRegEngine {}
+
+impl VgicRegEngine for ItsRegEngine {
+    type Reg = SimpleReg;
+    type RegChunk = u64;
+
+    fn group() -> u32 {
+        KVM_DEV_ARM_VGIC_GRP_ITS_REGS
+    }
+}
+
+fn its_regs() -> Box<dyn Iterator<Item = &'static SimpleReg>> {
+    Box::new(VGIC_ITS_REGS.iter().chain(std::iter::once(&GITS_CTLR)))
+}
+
+/// Save the state of the ITS registers. The ITS tables need to be flushed to guest RAM before.
+pub(crate) fn get_its_regs(fd: &DeviceFd) -> Result<Vec<GicRegState<u64>>, GicError> {
+    ItsRegEngine::get_regs_data(fd, its_regs(), 0)
+}
+
+/// Restore the state of the ITS registers. `restore_tables` loads the ITS tables from guest RAM and
+/// is called right before GITS_CTLR is restored.
+pub(crate) fn set_its_regs(
+    fd: &DeviceFd,
+    data: &[GicRegState<u64>],
+    restore_tables: impl FnOnce(&DeviceFd) -> Result<(), GicError>,
+) -> Result<(), GicError> {
+    let (ctlr, regs) = data.split_last().ok_or(GicError::InvalidItsState)?;
+    if regs.len() != VGIC_ITS_REGS.len() {
+        return Err(GicError::InvalidItsState);
+    }
+
+    ItsRegEngine::set_regs_data(fd, Box::new(VGIC_ITS_REGS.iter()), regs, 0)?;
+    restore_tables(fd)?;
+    ItsRegEngine::set_reg_data(fd, &GITS_CTLR, ctlr, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::undocumented_unsafe_blocks)]
+    use kvm_ioctls::Kvm;
+
+    use super::*;
+    use crate::arch::aarch64::gic::{GICVersion, create_gic};
+
+    #[test]
+    fn test_access_its_regs() {
+        let kvm = Kvm::new().unwrap();
+        let vm = kvm.create_vm().unwrap();
+        let _ = vm.create_vcpu(0).unwrap();
+        let gic = create_gic(&vm, 1, Some(GICVersion::GICV3)).expect("Cannot create gic");
+        let its_fd = gic.its_device().unwrap();
+
+        let state = get_its_regs(its_fd).unwrap();
+        // GITS_IIDR, GITS_CBASER, GITS_CREADR, GITS_CWRITER, GITS_BASER and GITS_CTLR.
+        assert_eq!(state.len(), 6);
+        assert_eq!(state[4].chunks.len(), 8);
+
+        set_its_regs(its_fd, &state, |_| Ok(())).unwrap();
+
+        assert_eq!(
+            set_its_regs(its_fd, &state[1..], |_| Ok(())),
+            Err(GicError::InvalidItsState)
+        );
+        assert_eq!(
+            set_its_regs(its_fd, &[], |_| Ok(())),
+            Err(GicError::InvalidItsState)
+        );
+    }
+}
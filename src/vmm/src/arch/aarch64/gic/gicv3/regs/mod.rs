@@ -3,15 +3,20 @@
 
 mod dist_regs;
 mod icc_regs;
+mod its_regs;
 mod redist_regs;
 
 use kvm_ioctls::DeviceFd;
 
 use crate::arch::aarch64::gic::GicError;
-use crate::arch::aarch64::gic::regs::{GicState, GicVcpuState};
-
-/// Save the state of the GIC device.
-pub fn save_state(fd: &DeviceFd, mpidrs: &[u64]) -> Result<GicState, GicError> {
+use crate::arch::aarch64::gic::regs::{GicRegState, GicState, GicVcpuState};
+
+/// Save the state of the GIC device and of its ITS.
+pub fn save_state(
+    fd: &DeviceFd,
+    its_fd: Option<&DeviceFd>,
+    mpidrs: &[u64],
+) -> Result<GicState, GicError> {
     // Flush redistributors pending tables to guest RAM.
     super::save_pending_tables(fd)?;
 
@@ -26,11 +31,23 @@ pub fn save_state(fd: &DeviceFd, mpidrs: &[u64]) -> Result<GicState, GicError> {
     Ok(GicState {
         dist: dist_regs::get_dist_regs(fd)?,
         gic_vcpu_states: vcpu_states,
+        its: its_fd.map(save_its_state).transpose()?,
     })
 }
 
-/// Restore the state of the GIC device.
-pub fn restore_state(fd: &DeviceFd, mpidrs: &[u64], state: &GicState) -> Result<(), GicError> {
+fn save_its_state(its_fd: &DeviceFd) -> Result<Vec<GicRegState<u64>>, GicError> {
+    // Flush the ITS tables to guest RAM.
+    super::save_its_tables(its_fd)?;
+    its_regs::get_its_regs(its_fd)
+}
+
+/// Restore the state of the GIC device and of its ITS.
+pub fn restore_state(
+    fd: &DeviceFd,
+    its_fd: Option<&DeviceFd>,
+    mpidrs: &[u64],
+    state: &GicState,
+) -> Result<(), GicError> {
     dist_regs::set_dist_regs(fd, &state.dist)?;
 
     if mpidrs.len() != state.gic_vcpu_states.len() {
@@ -41,6 +58,16 @@ pub fn restore_state(fd: &DeviceFd, mpidrs: &[u64], state: &GicState) -> Result<
         icc_regs::set_icc_regs(fd, *mpidr, &vcpu_state.icc)?;
     }
 
+    // The ITS is restored after the redistributors. A state saved without an ITS can be
+    // restored on a host that has one: its guest does not know about the ITS and never uses it.
+    match (its_fd, &state.its) {
+        (Some(its_fd), Some(its_state)) => {
+            its_regs::set_its_regs(its_fd, its_state, super::restore_its_tables)?
+        }
+        (_, None) => (),
+        (None, Some(_)) => return Err(GicError::InvalidItsState),
+    }
+
     Ok(())
 }
 
@@ -59,9 +86,10 @@ mod tests {
         let vm = kvm.create_vm().unwrap();
         let gic = create_gic(&vm, 1, Some(GICVersion::GICV3)).expect("Cannot create gic");
         let gic_fd = gic.device_fd();
+        let its_fd = gic.its_device();
 
         let mpidr = vec![1];
-        let res = save_state(gic_fd, &mpidr);
+        let res = save_state(gic_fd, its_fd, &mpidr);
         // We will receive an error if trying to call before creating vcpu.
         assert_eq!(
             format!("{:?}", res.unwrap_err()),
@@ -73,8 +101,9 @@ mod tests {
         let _vcpu = vm.create_vcpu(0).unwrap();
         let gic = create_gic(&vm, 1, Some(GICVersion::GICV3)).expect("Cannot create gic");
         let gic_fd = gic.device_fd();
+        let its_fd = gic.its_device();
 
-        let vm_state = save_state(gic_fd, &mpidr).unwrap();
+        let vm_state = save_state(gic_fd, its_fd, &mpidr).unwrap();
         let val: u32 = 0;
         let gicd_statusr_off = 0x0010u64;
         let mut gic_dist_attr = kvm_bindings::kvm_device_attr {
@@ -94,7 +123,17 @@ mod tests {
 
         assert_eq!(gicd_statusr.chunks[0], val);
         assert_eq!(vm_state.dist.len(), 12);
-        restore_state(gic_fd, &mpidr, &vm_state).unwrap();
-        restore_state(gic_fd, &[1, 2], &vm_state).unwrap_err();
+        assert_eq!(vm_state.its.as_ref().unwrap().len(), 6);
+        restore_state(gic_fd, its_fd, &mpidr, &vm_state).unwrap();
+        restore_state(gic_fd, its_fd, &[1, 2], &vm_state).unwrap_err();
+        assert_eq!(
+            restore_state(gic_fd, None, &mpidr, &vm_state),
+            Err(GicError::InvalidItsState)
+        );
+
+        // A state saved without an ITS is restored on a GIC that has one.
+        let mut vm_state = vm_state;
+        vm_state.its = None;
+        restore_state(gic_fd, its_fd, &mpidr, &vm_state).unwrap();
     }
 }
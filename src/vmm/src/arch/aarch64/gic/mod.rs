@@ -21,6 +21,12 @@ pub struct GIC {
     /// GIC device properties, to be used for setting up the fdt entry
     properties: [u64; 4],
 
+    /// MSI controller properties, to be used for setting up the fdt entry
+    msi_properties: Option<[u64; 2]>,
+
+    /// The file descriptor for the KVM ITS device, if the GIC has one
+    its_device: Option<DeviceFd>,
+
     /// Number of CPUs handled by the device
     vcpu_count: u64,
 }
@@ -35,6 +41,16 @@ impl GIC {
         &self.properties
     }
 
+    /// Returns an array with MSI controller properties, if the GIC has one
+    pub fn msi_properties(&self) -> Option<&[u64]> {
+        self.msi_properties.as_ref().map(|props| props.as_slice())
+    }
+
+    /// Returns the file descriptor of the ITS device, if the GIC has one
+    pub fn its_device(&self) -> Option<&DeviceFd> {
+        self.its_device.as_ref()
+    }
+
     /// Returns the number of vCPUs this GIC handles
     pub fn vcpu_count(&self) -> u64 {
         self.vcpu_count
@@ -46,12 +62,16 @@ impl GIC {
 pub enum GicError {
     /// Error while calling KVM ioctl for setting up the global interrupt controller: {0}
     CreateGIC(kvm_ioctls::Error),
+    /// Error while calling KVM ioctl for setting up the interrupt translation service: {0}
+    CreateITS(kvm_ioctls::Error),
     /// Error while setting or getting device attributes for the GIC: {0}, {1}, {2}
     DeviceAttribute(kvm_ioctls::Error, bool, u32),
     /// The number of vCPUs in the GicState doesn't match the number of vCPUs on the system.
     InconsistentVcpuCount,
     /// The VgicSysRegsState is invalid.
     InvalidVgicSysRegState,
+    /// The ITS state is invalid.
+    InvalidItsState,
 }
 
 /// List of implemented GICs.
@@ -59,7 +79,7 @@ pub enum GicError {
 pub enum GICVersion {
     /// Legacy version.
     GICV2,
-    /// GICV3 without ITS.
+    /// GICV3 with ITS.
     GICV3,
 }
 
@@ -68,7 +88,7 @@ pub enum GICVersion {
 pub enum GICDevice {
     /// Legacy version.
     V2(GICv2),
-    /// GICV3 without ITS.
+    /// GICV3 with ITS.
     V3(GICv3),
 }
 impl GICDevice {
@@ -88,6 +108,22 @@ impl GICDevice {
         }
     }
 
+    /// Returns an array with MSI controller properties, if the GIC has one
+    pub fn msi_properties(&self) -> Option<&[u64]> {
+        match self {
+            Self::V2(x) => x.msi_properties(),
+            Self::V3(x) => x.msi_properties(),
+        }
+    }
+
+    /// Returns the file descriptor of the ITS device, if the GIC has one
+    pub fn its_device(&self) -> Option<&DeviceFd> {
+        match self {
+            Self::V2(x) => x.its_device(),
+            Self::V3(x) => x.its_device(),
+        }
+    }
+
     /// Returns the number of vCPUs this GIC handles
     pub fn vcpu_count(&self) -> u64 {
         match self {
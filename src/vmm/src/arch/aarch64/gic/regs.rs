@@ -30,6 +30,8 @@ pub struct GicState {
     pub dist: Vec<GicRegState<u32>>,
     /// The state of the vcpu interfaces.
     pub gic_vcpu_states: Vec<GicVcpuState>,
+    /// The state of the ITS registers, if the GIC has an ITS.
+    pub its: Option<Vec<GicRegState<u64>>>,
 }
 
 /// Structure used for serializing the state of the GIC registers for a specific vCPU.
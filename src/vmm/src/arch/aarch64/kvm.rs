@@ -10,6 +10,10 @@ use crate::cpu_config::templates::KvmCapability;
 /// ['Kvm'] initialization can't fail for Aarch64
 pub type KvmArchError = Infallible;
 
+// Guest physical address width (IPA size) of the VMs created with the default VM type, which is
+// the one Firecracker uses.
+const DEFAULT_IPA_BITS: u8 = 40;
+
 /// Optional capabilities.
 #[derive(Debug, Default)]
 pub struct OptionalCapabilities {
@@ -57,4 +61,9 @@ impl Kvm {
                 != 0,
         }
     }
+
+    /// Returns the guest physical address width of the VMs.
+    pub fn guest_phys_bits(&self) -> u8 {
+        DEFAULT_IPA_BITS
+    }
 }
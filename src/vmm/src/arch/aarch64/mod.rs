@@ -22,8 +22,10 @@ use std::fs::File;
 
 use linux_loader::loader::pe::PE as Loader;
 use linux_loader::loader::{Cmdline, KernelLoader};
+use vm_allocator::AllocPolicy;
 use vm_memory::GuestMemoryError;
 
+use self::vcpu::STEALTIME_STRUCT_MEM_SIZE;
 use crate::arch::{BootProtocol, EntryPoint};
 use crate::cpu_config::aarch64::{CpuConfiguration, CpuConfigurationError};
 use crate::cpu_config::templates::CustomCpuTemplate;
@@ -49,6 +51,8 @@ pub enum ConfigurationError {
     VcpuConfig(#[from] CpuConfigurationError),
     /// Error configuring the vcpu: {0}
     VcpuConfigure(#[from] KvmVcpuError),
+    /// Cannot allocate memory for the steal time structures: {0}
+    PvtimeAllocation(#[from] vm_allocator::Error),
 }
 
 /// The start of the memory area reserved for MMIO devices.
@@ -89,6 +93,11 @@ pub fn arch_memory_regions(offset: usize, size: usize) -> Vec<(GuestAddress, usi
     )]
 }
 
+/// Returns the first guest physical address free for device memory, right past guest memory.
+pub fn device_memory_start(guest_mem: &GuestMemoryMmap) -> GuestAddress {
+    guest_mem.last_addr().unchecked_add(1)
+}
+
 /// Configures the system for booting Linux.
 pub fn configure_system_for_boot(
     vmm: &mut Vmm,
@@ -111,6 +120,23 @@ pub fn configure_system_for_boot(
         cpu_config,
     };
 
+    // Report steal time to the guest, if supported, with one structure per vCPU in the system
+    // memory area, which the guest does not use as RAM.
+    if vcpus
+        .first()
+        .is_some_and(|vcpu| vcpu.kvm_vcpu.supports_pvtime())
+    {
+        let pvtime_base = vmm.resource_allocator.allocate_system_memory(
+            STEALTIME_STRUCT_MEM_SIZE * usize_to_u64(vcpus.len()),
+            STEALTIME_STRUCT_MEM_SIZE,
+            AllocPolicy::LastMatch,
+        )?;
+        for (index, vcpu) in vcpus.iter_mut().enumerate() {
+            let ipa = pvtime_base + usize_to_u64(index) * STEALTIME_STRUCT_MEM_SIZE;
+            vcpu.kvm_vcpu.enable_pvtime(GuestAddress(ipa))?;
+        }
+    }
+
     let optional_capabilities = vmm.kvm.optional_capabilities();
     // Configure vCPUs with normalizing and setting the generated CPU configuration.
     for vcpu in vcpus.iter_mut() {
@@ -270,6 +296,15 @@ mod tests {
         assert_eq!(super::layout::DRAM_MEM_MAX_SIZE, regions[0].1);
     }
 
+    #[test]
+    fn test_device_memory_start() {
+        let mem = arch_mem(0x1000);
+        assert_eq!(
+            device_memory_start(&mem),
+            GuestAddress(layout::DRAM_MEM_START + 0x1000)
+        );
+    }
+
     #[test]
     fn test_get_fdt_addr() {
         let mem = arch_mem(layout::FDT_MAX_SIZE - 0x1000);
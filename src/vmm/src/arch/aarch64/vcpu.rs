@@ -22,10 +22,14 @@ use crate::cpu_config::aarch64::custom_cpu_template::VcpuFeatures;
 use crate::cpu_config::templates::CpuConfiguration;
 use crate::logger::{IncMetric, METRICS, error};
 use crate::vcpu::{VcpuConfig, VcpuError};
-use crate::vstate::memory::{Address, GuestMemoryMmap};
+use crate::vstate::memory::{Address, GuestAddress, GuestMemoryMmap};
 use crate::vstate::vcpu::VcpuEmulation;
 use crate::vstate::vm::Vm;
 
+/// Size of the steal time structure of a vCPU, as defined by the Arm paravirtualized time
+/// specification (DEN0057A).
+pub const STEALTIME_STRUCT_MEM_SIZE: u64 = 64;
+
 /// Errors thrown while setting aarch64 registers.
 #[derive(Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
 pub enum VcpuArchError {
@@ -93,6 +97,8 @@ pub enum KvmVcpuError {
     CreateVcpu(kvm_ioctls::Error),
     /// Failed to dump CPU configuration: {0}
     DumpCpuConfig(VcpuArchError),
+    /// Error setting the address of the vcpu steal time structure: {0}
+    EnablePvtime(kvm_ioctls::Error),
     /// Error getting the vcpu preferred target: {0}
     GetPreferredTarget(kvm_ioctls::Error),
     /// Error initializing the vcpu: {0}
@@ -118,6 +124,8 @@ pub struct KvmVcpu {
     /// Vcpu peripherals, such as buses
     pub peripherals: Peripherals,
     kvi: kvm_vcpu_init,
+    /// Guest address of the steal time structure of the vcpu, if steal time is reported.
+    pub pvtime_ipa: Option<GuestAddress>,
 }
 
 /// Vcpu peripherals
@@ -151,6 +159,7 @@ impl KvmVcpu {
             fd: kvm_vcpu,
             peripherals: Default::default(),
             kvi,
+            pvtime_ipa: None,
         })
     }
 
@@ -234,6 +243,7 @@ impl KvmVcpu {
         self.get_all_registers(&mut state.regs)
             .map_err(KvmVcpuError::SaveState)?;
         state.mpidr = self.get_mpidr().map_err(KvmVcpuError::SaveState)?;
+        state.pvtime_ipa = self.pvtime_ipa.map(|ipa| ipa.raw_value());
 
         state.kvi = self.kvi;
         // We don't save power off state in a snapshot, because
@@ -275,6 +285,43 @@ impl KvmVcpu {
         }
         self.set_mpstate(state.mp_state)
             .map_err(KvmVcpuError::RestoreState)?;
+
+        // The guest looked up the address of its steal time structure at boot, so it must not
+        // change across snapshot restore.
+        if let Some(ipa) = state.pvtime_ipa {
+            self.enable_pvtime(GuestAddress(ipa))?;
+        }
+        Ok(())
+    }
+
+    /// Checks whether KVM supports reporting steal time to the guest.
+    pub fn supports_pvtime(&self) -> bool {
+        let pvtime_device_attr = kvm_device_attr {
+            group: KVM_ARM_VCPU_PVTIME_CTRL,
+            attr: u64::from(KVM_ARM_VCPU_PVTIME_IPA),
+            addr: 0,
+            flags: 0,
+        };
+        self.fd.has_device_attr(&pvtime_device_attr).is_ok()
+    }
+
+    /// Makes KVM report the steal time of the vcpu in the structure at guest address `ipa`,
+    /// which the guest discovers through the `PV_TIME_ST` hypercall.
+    ///
+    /// The structure must be [`STEALTIME_STRUCT_MEM_SIZE`] bytes long, 64 bytes aligned and
+    /// lie in guest memory not used as RAM by the guest.
+    pub fn enable_pvtime(&mut self, ipa: GuestAddress) -> Result<(), KvmVcpuError> {
+        let ipa_val = ipa.raw_value();
+        let pvtime_device_attr = kvm_device_attr {
+            group: KVM_ARM_VCPU_PVTIME_CTRL,
+            attr: u64::from(KVM_ARM_VCPU_PVTIME_IPA),
+            addr: &ipa_val as *const u64 as u64,
+            flags: 0,
+        };
+        self.fd
+            .set_device_attr(&pvtime_device_attr)
+            .map_err(KvmVcpuError::EnablePvtime)?;
+        self.pvtime_ipa = Some(ipa);
         Ok(())
     }
 
@@ -461,12 +508,17 @@ pub struct VcpuState {
     pub mpidr: u64,
     /// kvi states for vcpu initialization.
     pub kvi: kvm_vcpu_init,
+    /// Guest address of the steal time structure, if steal time is reported.
+    pub pvtime_ipa: Option<u64>,
 }
 
 impl Debug for VcpuState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "kvm_mp_state: {:#x}", self.mp_state.mp_state)?;
         writeln!(f, "mpidr: {:#x}", self.mpidr)?;
+        if let Some(pvtime_ipa) = self.pvtime_ipa {
+            writeln!(f, "pvtime_ipa: {:#x}", pvtime_ipa)?;
+        }
         for reg in self.regs.iter() {
             writeln!(
                 f,
@@ -758,6 +810,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pvtime() {
+        let (_, _, mut vcpu) = setup_vcpu(0x10000);
+        if !vcpu.supports_pvtime() {
+            return;
+        }
+
+        let ipa = GuestAddress(0x1000);
+        vcpu.enable_pvtime(ipa).unwrap();
+        assert_eq!(vcpu.pvtime_ipa, Some(ipa));
+        // The address can only be set once.
+        vcpu.enable_pvtime(ipa).unwrap_err();
+
+        // The address is saved along with the rest of the vcpu state.
+        let state = vcpu.save_state().unwrap();
+        assert_eq!(state.pvtime_ipa, Some(0x1000));
+    }
+
     #[test]
     fn test_mpstate() {
         use std::os::unix::io::AsRawFd;
@@ -1,6 +1,8 @@
 // Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::sync::OnceLock;
+
 use kvm_bindings::{CpuId, KVM_MAX_CPUID_ENTRIES, MsrList};
 use kvm_ioctls::Kvm as KvmFd;
 
@@ -16,6 +18,23 @@ pub enum KvmArchError {
     XstateFeatures(XstateError),
 }
 
+/// CPUID supported by KVM on this host, which is the same for all the VMs, queried when the first
+/// one is created.
+static SUPPORTED_CPUID: OnceLock<CpuId> = OnceLock::new();
+
+// Returns the CPUID cached in `cache`, filling it with the result of `query` the first time.
+fn cached_cpuid<E>(
+    cache: &OnceLock<CpuId>,
+    query: impl FnOnce() -> Result<CpuId, E>,
+) -> Result<CpuId, E> {
+    if let Some(cpuid) = cache.get() {
+        return Ok(cpuid.clone());
+    }
+    let cpuid = query()?;
+    // Another thread may have filled the cache in the meantime, with the same CPUID.
+    Ok(cache.get_or_init(|| cpuid).clone())
+}
+
 /// Struct with kvm fd and kvm associated parameters.
 #[derive(Debug)]
 pub struct Kvm {
@@ -52,9 +71,10 @@ impl Kvm {
     ) -> Result<Self, KvmArchError> {
         request_dynamic_xstate_features().map_err(KvmArchError::XstateFeatures)?;
 
-        let supported_cpuid = fd
-            .get_supported_cpuid(KVM_MAX_CPUID_ENTRIES)
-            .map_err(KvmArchError::GetSupportedCpuId)?;
+        let supported_cpuid = cached_cpuid(&SUPPORTED_CPUID, || {
+            fd.get_supported_cpuid(KVM_MAX_CPUID_ENTRIES)
+        })
+        .map_err(KvmArchError::GetSupportedCpuId)?;
 
         Ok(Kvm {
             fd,
@@ -67,4 +87,62 @@ impl Kvm {
     pub fn msrs_to_save(&self) -> Result<MsrList, crate::arch::x86_64::msr::MsrError> {
         crate::arch::x86_64::msr::get_msrs_to_save(&self.fd)
     }
+
+    /// Returns the guest physical address width, as reported by the supported CPUID.
+    pub fn guest_phys_bits(&self) -> u8 {
+        self.supported_cpuid
+            .as_slice()
+            .iter()
+            .find(|entry| entry.function == 0x8000_0008)
+            .map(|entry| entry.eax.to_le_bytes()[0])
+            .filter(|&bits| bits != 0)
+            .unwrap_or(crate::arch::x86_64::DEFAULT_PHYS_BITS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn test_cached_cpuid() {
+        let cache = OnceLock::new();
+        let queries = Cell::new(0);
+        let query = || {
+            queries.set(queries.get() + 1);
+            KvmFd::new()
+                .unwrap()
+                .get_supported_cpuid(KVM_MAX_CPUID_ENTRIES)
+        };
+
+        // The first VM queries KVM, the next ones reuse its result.
+        let first = cached_cpuid(&cache, query).unwrap();
+        let second = cached_cpuid(&cache, query).unwrap();
+        assert_eq!(queries.get(), 1);
+        assert_eq!(first, second);
+
+        // Failed queries are not cached.
+        let cache = OnceLock::new();
+        cached_cpuid(&cache, || Err(kvm_ioctls::Error::new(libc::EINVAL))).unwrap_err();
+        assert!(cache.get().is_none());
+        cached_cpuid(&cache, query).unwrap();
+        assert_eq!(queries.get(), 2);
+    }
+
+    #[test]
+    fn test_supported_cpuid_shared() {
+        let first = Kvm::new(vec![]).unwrap();
+        let second = Kvm::new(vec![]).unwrap();
+        assert_eq!(first.supported_cpuid, second.supported_cpuid);
+        assert_eq!(SUPPORTED_CPUID.get(), Some(&first.supported_cpuid));
+    }
+
+    #[test]
+    fn test_guest_phys_bits() {
+        let kvm = Kvm::new(vec![]).unwrap();
+        // The address width covers at least the 32-bit address space.
+        assert!((32..=52).contains(&kvm.guest_phys_bits()));
+    }
 }
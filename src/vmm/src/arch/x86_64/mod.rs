@@ -19,6 +19,9 @@ pub mod layout;
 mod mptable;
 /// Logic for configuring x86_64 model specific registers (MSRs).
 pub mod msr;
+/// Logic for walking the guest page tables.
+#[cfg(feature = "debug-api")]
+pub mod page_table;
 /// Logic for configuring x86_64 registers.
 pub mod regs;
 /// Architecture specific vCPU code
@@ -50,9 +53,10 @@ use crate::acpi::create_acpi_tables;
 use crate::arch::{BootProtocol, SYSTEM_MEM_SIZE, SYSTEM_MEM_START};
 use crate::cpu_config::templates::{CustomCpuTemplate, GuestConfigError};
 use crate::cpu_config::x86_64::CpuConfiguration;
+use crate::cpu_config::x86_64::cpuid::CpuidKey;
 use crate::initrd::InitrdConfig;
 use crate::utils::{align_down, mib_to_bytes, u64_to_usize, usize_to_u64};
-use crate::vmm_config::machine_config::MachineConfig;
+use crate::vmm_config::machine_config::{MachineConfig, MemoryRegionConfig, MemoryRegionType};
 use crate::vstate::memory::{
     Address, GuestAddress, GuestMemory, GuestMemoryMmap, GuestMemoryRegion,
 };
@@ -94,6 +98,8 @@ pub enum ConfigurationError {
     VcpuConfigure(#[from] KvmVcpuConfigureError),
     /// Error configuring ACPI: {0}
     Acpi(#[from] crate::acpi::AcpiError),
+    /// Error configuring the memory types of guest memory: {0}
+    Mtrr(msr::MsrError),
 }
 
 /// First address that cannot be addressed using 32 bit anymore.
@@ -140,6 +146,53 @@ pub fn arch_memory_regions(offset: usize, size: usize) -> Vec<(GuestAddress, usi
     }
 }
 
+/// Returns the first guest physical address free for device memory, past both guest memory and
+/// the MMIO gap at the top of the 32-bit address space.
+pub fn device_memory_start(guest_mem: &GuestMemoryMmap) -> GuestAddress {
+    GuestAddress(FIRST_ADDR_PAST_32BITS.max(guest_mem.last_addr().raw_value() + 1))
+}
+
+// Guest physical address width assumed when CPUID does not report it.
+pub(crate) const DEFAULT_PHYS_BITS: u8 = 36;
+
+/// Returns the guest physical ranges of the memory regions with a memory type other than
+/// write-back, as `(start, size, MTRR memory type)`. The memory regions are laid out in order from
+/// the start of guest memory.
+fn mtrr_ranges(
+    guest_mem: &GuestMemoryMmap,
+    regions: &[MemoryRegionConfig],
+) -> Vec<(u64, u64, u64)> {
+    let mut ranges = Vec::new();
+    let mut mem_regions = guest_mem
+        .iter()
+        .map(|region| (region.start_addr().raw_value(), region.len()));
+    let mut current = mem_regions.next();
+
+    for region in regions {
+        let mem_type = match region.region_type {
+            MemoryRegionType::Normal => None,
+            MemoryRegionType::WriteThrough => Some(msr::MTRR_TYPE_WRTHROUGH),
+            MemoryRegionType::WriteCombining => Some(msr::MTRR_TYPE_WRCOMB),
+            MemoryRegionType::Uncacheable => Some(msr::MTRR_TYPE_UNCACHEABLE),
+        };
+        let mut remaining = usize_to_u64(mib_to_bytes(region.size_mib));
+        // A region can span the MMIO gap, in which case it covers two guest memory regions.
+        while let Some((start, len)) = current.filter(|_| remaining > 0) {
+            let size = remaining.min(len);
+            if let Some(mem_type) = mem_type {
+                ranges.push((start, size, mem_type));
+            }
+            remaining -= size;
+            current = if size == len {
+                mem_regions.next()
+            } else {
+                Some((start + size, len - size))
+            };
+        }
+    }
+    ranges
+}
+
 /// Returns the memory address where the kernel could be loaded.
 pub fn get_kernel_start() -> u64 {
     layout::HIMEM_START
@@ -174,7 +227,28 @@ pub fn configure_system_for_boot(
     let cpu_config =
         CpuConfiguration::new(vmm.kvm.supported_cpuid.clone(), cpu_template, &vcpus[0])?;
     // Apply CPU template to the base CpuConfiguration.
-    let cpu_config = CpuConfiguration::apply_template(cpu_config, cpu_template)?;
+    let mut cpu_config = CpuConfiguration::apply_template(cpu_config, cpu_template)?;
+
+    // Give the memory regions their memory type through MTRRs. KVM memory slots have no flags
+    // for memory types, so the guest MTRRs are the only place to set them.
+    let phys_bits = cpu_config
+        .cpuid
+        .inner()
+        .get(&CpuidKey::leaf(0x8000_0008))
+        .map(|entry| entry.result.eax.to_le_bytes()[0])
+        .filter(|&bits| bits != 0)
+        .unwrap_or(DEFAULT_PHYS_BITS);
+    let mtrrs = msr::create_mtrr_msr_entries(
+        &mtrr_ranges(
+            vmm.vm.guest_memory(),
+            &machine_config.additional_memory_regions,
+        ),
+        phys_bits,
+    )
+    .map_err(ConfigurationError::Mtrr)?;
+    cpu_config
+        .msrs
+        .extend(mtrrs.into_iter().map(|entry| (entry.index, entry.data)));
 
     let vcpu_config = VcpuConfig {
         vcpu_count: machine_config.vcpu_count,
@@ -528,7 +602,7 @@ mod tests {
 
     use super::*;
     use crate::device_manager::resources::ResourceAllocator;
-    use crate::test_utils::{arch_mem, single_region_mem};
+    use crate::test_utils::{arch_mem, multi_region_mem, single_region_mem};
 
     #[test]
     fn regions_lt_4gb() {
@@ -569,6 +643,25 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_device_memory_start() {
+        // Device memory never goes into the MMIO gap.
+        let gm = arch_mem(1 << 29);
+        assert_eq!(
+            device_memory_start(&gm),
+            GuestAddress(FIRST_ADDR_PAST_32BITS)
+        );
+
+        let gm = multi_region_mem(&[
+            (GuestAddress(0), 0x1000),
+            (GuestAddress(FIRST_ADDR_PAST_32BITS), 0x1000),
+        ]);
+        assert_eq!(
+            device_memory_start(&gm),
+            GuestAddress(FIRST_ADDR_PAST_32BITS + 0x1000)
+        );
+    }
+
     #[test]
     fn test_system_configuration() {
         let no_vcpus = 4;
@@ -646,4 +739,40 @@ mod tests {
             .is_err()
         );
     }
+
+    #[test]
+    fn test_mtrr_ranges() {
+        const MIB: u64 = 1 << 20;
+        const FOUR_GIB: u64 = 1 << 32;
+        // Guest memory split around an MMIO gap.
+        let guest_mem = multi_region_mem(&[
+            (GuestAddress(0), mib_to_bytes(64)),
+            (GuestAddress(FOUR_GIB), mib_to_bytes(64)),
+        ]);
+
+        assert_eq!(mtrr_ranges(&guest_mem, &[]), vec![]);
+
+        let regions = [
+            MemoryRegionConfig {
+                size_mib: 48,
+                region_type: MemoryRegionType::Normal,
+            },
+            MemoryRegionConfig {
+                size_mib: 32,
+                region_type: MemoryRegionType::Uncacheable,
+            },
+            MemoryRegionConfig {
+                size_mib: 48,
+                region_type: MemoryRegionType::WriteThrough,
+            },
+        ];
+        assert_eq!(
+            mtrr_ranges(&guest_mem, &regions),
+            vec![
+                (48 * MIB, 16 * MIB, msr::MTRR_TYPE_UNCACHEABLE),
+                (FOUR_GIB, 16 * MIB, msr::MTRR_TYPE_UNCACHEABLE),
+                (FOUR_GIB + 16 * MIB, 48 * MIB, msr::MTRR_TYPE_WRTHROUGH),
+            ]
+        );
+    }
 }
@@ -25,6 +25,8 @@ pub enum MsrError {
     SetMsrs(kvm_ioctls::Error),
     /// Not all given MSRs were set.
     SetMsrsIncomplete,
+    /// Too many variable range MTRRs needed: {0}. At most 8 are available.
+    TooManyMtrrs(usize),
 }
 
 /// MSR range
@@ -422,6 +424,71 @@ pub fn create_boot_msr_entries() -> Vec<kvm_msr_entry> {
     ]
 }
 
+/// Number of variable range MTRRs KVM provides to guests (`KVM_NR_VAR_MTRR`).
+pub const NUM_VARIABLE_MTRRS: usize = 8;
+/// Index of `IA32_MTRR_PHYSBASE0`. `IA32_MTRR_PHYSMASKn` follows each `IA32_MTRR_PHYSBASEn`.
+const MSR_MTRR_PHYSBASE0: u32 = 0x200;
+// Enables MTRRs in `IA32_MTRR_DEF_TYPE`, and a variable range in `IA32_MTRR_PHYSMASKn`.
+const MTRR_ENABLE: u64 = 1 << 11;
+/// Uncacheable MTRR memory type.
+pub const MTRR_TYPE_UNCACHEABLE: u64 = 0;
+/// Write-combining MTRR memory type.
+pub const MTRR_TYPE_WRCOMB: u64 = 1;
+/// Write-through MTRR memory type.
+pub const MTRR_TYPE_WRTHROUGH: u64 = 4;
+/// Write-back MTRR memory type.
+pub const MTRR_TYPE_WRBACK: u64 = 6;
+// Largest physical address width of x86_64 CPUs.
+const MAX_PHYS_BITS: u8 = 52;
+
+/// Creates the MTRR entries giving guest physical ranges, as `(start, size, memory type)`, a
+/// memory type other than write-back, which is the default type of guest memory.
+///
+/// Variable range MTRRs only cover naturally aligned power of two ranges, so each range is split
+/// into as many of these as needed. `phys_bits` is the guest physical address width, clamped to
+/// the architectural maximum of 52 bits.
+pub fn create_mtrr_msr_entries(
+    ranges: &[(u64, u64, u64)],
+    phys_bits: u8,
+) -> Result<Vec<kvm_msr_entry>, MsrError> {
+    let mut mtrrs = Vec::new();
+    for &(mut start, mut size, mem_type) in ranges {
+        while size > 0 {
+            let max_aligned = 1u64.checked_shl(start.trailing_zeros()).unwrap_or(u64::MAX);
+            let chunk = max_aligned.min(1 << (u64::BITS - 1 - size.leading_zeros()));
+            mtrrs.push((start, chunk, mem_type));
+            start += chunk;
+            size -= chunk;
+        }
+    }
+    if mtrrs.is_empty() {
+        return Ok(Vec::new());
+    }
+    if mtrrs.len() > NUM_VARIABLE_MTRRS {
+        return Err(MsrError::TooManyMtrrs(mtrrs.len()));
+    }
+
+    let phys_mask = (1u64 << phys_bits.min(MAX_PHYS_BITS)) - 1;
+    let mut entries = vec![kvm_msr_entry {
+        index: MSR_MTRRdefType,
+        data: MTRR_ENABLE | MTRR_TYPE_WRBACK,
+        ..Default::default()
+    }];
+    for (index, (start, size, mem_type)) in (0u32..).zip(mtrrs) {
+        entries.push(kvm_msr_entry {
+            index: MSR_MTRR_PHYSBASE0 + 2 * index,
+            data: start | mem_type,
+            ..Default::default()
+        });
+        entries.push(kvm_msr_entry {
+            index: MSR_MTRR_PHYSBASE0 + 2 * index + 1,
+            data: (!(size - 1) & phys_mask) | MTRR_ENABLE,
+            ..Default::default()
+        });
+    }
+    Ok(entries)
+}
+
 /// Configure Model Specific Registers (MSRs) required to boot Linux for a given x86_64 vCPU.
 ///
 /// # Arguments
@@ -534,4 +601,38 @@ mod tests {
             MsrError::SetMsrsIncomplete
         );
     }
+
+    #[test]
+    fn test_create_mtrr_msr_entries() {
+        assert!(create_mtrr_msr_entries(&[], 40).unwrap().is_empty());
+
+        // 192 MiB starting at 64 MiB are covered by 64 MiB and 128 MiB ranges.
+        let entries =
+            create_mtrr_msr_entries(&[(0x400_0000, 0xc00_0000, MTRR_TYPE_WRCOMB)], 40).unwrap();
+        let entries = entries
+            .iter()
+            .map(|entry| (entry.index, entry.data))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                (MSR_MTRRdefType, 0x806),
+                (0x200, 0x400_0001),
+                (0x201, 0xff_fc00_0800),
+                (0x202, 0x800_0001),
+                (0x203, 0xff_f800_0800),
+            ]
+        );
+
+        // Physical address widths above the architectural maximum are clamped.
+        let entries =
+            create_mtrr_msr_entries(&[(0x400_0000, 0x400_0000, MTRR_TYPE_WRCOMB)], 64).unwrap();
+        assert_eq!(entries[2].data, 0xf_ffff_fc00_0800);
+
+        // Unaligned ranges are split in many MTRRs.
+        assert_eq!(
+            create_mtrr_msr_entries(&[(0x1000, 0x1f_e000, MTRR_TYPE_UNCACHEABLE)], 40).unwrap_err(),
+            MsrError::TooManyMtrrs(16)
+        );
+    }
 }
@@ -0,0 +1,298 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::Serialize;
+
+use crate::vstate::memory::{Bytes, GuestAddress, GuestMemory, GuestMemoryMmap};
+
+/// The entry maps a page or references a lower level table.
+const PTE_PRESENT: u64 = 1 << 0;
+/// In PDPT and PD entries, the entry maps a 1GiB or 2MiB page instead of referencing a table.
+const PTE_PAGE_SIZE: u64 = 1 << 7;
+/// Physical address bits of an entry.
+const PTE_ADDR_MASK: u64 = 0x000f_ffff_ffff_f000;
+/// Number of entries in a page table.
+const PTES_PER_TABLE: u64 = 512;
+/// Size in bytes of a page table entry.
+const PTE_SIZE: u64 = 8;
+/// Number of page table levels: PML4, PDPT, PD and PT.
+const PAGE_TABLE_LEVELS: usize = 4;
+/// Number of virtual address bits translated by each level.
+const LEVEL_SHIFTS: [u32; PAGE_TABLE_LEVELS] = [39, 30, 21, 12];
+/// CR4 bit enabling 5-level paging.
+const CR4_LA57: u64 = 1 << 12;
+/// Maximum number of page table entries read by a single walk.
+pub const MAX_PTES_READ: u64 = 1 << 24;
+
+/// Errors associated with walking the guest page tables.
+#[derive(Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+pub enum PageTableWalkError {
+    /// CR3 does not point to guest memory: {0:#x}
+    InvalidCr3(u64),
+    /// 5-level paging is not supported: CR4.LA57 is set
+    FiveLevelPaging,
+    /// The page tables have too many entries
+    TooManyEntries,
+}
+
+/// Registers locating the page tables of a vCPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageTableRoot {
+    /// Value of the CR3 register.
+    pub cr3: u64,
+    /// Value of the CR4 register.
+    pub cr4: u64,
+}
+
+/// A page mapped by the guest page tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct GuestMapping {
+    /// Canonical guest virtual address of the page.
+    pub virtual_addr: u64,
+    /// Guest physical address of the page.
+    pub physical_addr: u64,
+    /// Bits of the leaf page table entry other than the physical address.
+    pub flags: u64,
+}
+
+// Position of the walk within one page table.
+#[derive(Debug)]
+struct TableCursor {
+    table_addr: u64,
+    level: usize,
+    index: u64,
+    virtual_base: u64,
+}
+
+/// Iterator over the pages mapped by the 4-level page tables of an x86_64 guest.
+///
+/// The page tables are read from guest memory without involving the vCPUs, so the walk neither
+/// modifies the guest nor relies on its cooperation. Tables that do not lie in guest memory are
+/// skipped. A table referenced by several entries is walked once per entry, so that every
+/// virtual address mapping it is reported, and the walk fails after reading [`MAX_PTES_READ`]
+/// entries, which bounds it over malformed or malicious page tables.
+#[derive(Debug)]
+pub struct GuestPageTableWalker<'a> {
+    guest_memory: &'a GuestMemoryMmap,
+    stack: Vec<TableCursor>,
+    ptes_read: u64,
+}
+
+impl<'a> GuestPageTableWalker<'a> {
+    /// Creates a walker over the page tables rooted at the PML4 table referenced by `root.cr3`.
+    pub fn new(
+        guest_memory: &'a GuestMemoryMmap,
+        root: PageTableRoot,
+    ) -> Result<Self, PageTableWalkError> {
+        if root.cr4 & CR4_LA57 != 0 {
+            return Err(PageTableWalkError::FiveLevelPaging);
+        }
+        let pml4_addr = root.cr3 & PTE_ADDR_MASK;
+        if !guest_memory.address_in_range(GuestAddress(pml4_addr)) {
+            return Err(PageTableWalkError::InvalidCr3(root.cr3));
+        }
+
+        Ok(Self {
+            guest_memory,
+            stack: vec![TableCursor {
+                table_addr: pml4_addr,
+                level: 0,
+                index: 0,
+                virtual_base: 0,
+            }],
+            ptes_read: 0,
+        })
+    }
+}
+
+// Sign extends bit 47 of a virtual address.
+fn canonical(virtual_addr: u64) -> u64 {
+    if virtual_addr & (1 << 47) != 0 {
+        virtual_addr | 0xffff_0000_0000_0000
+    } else {
+        virtual_addr
+    }
+}
+
+impl Iterator for GuestPageTableWalker<'_> {
+    type Item = Result<GuestMapping, PageTableWalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let cursor = self.stack.last_mut()?;
+            if cursor.index == PTES_PER_TABLE {
+                self.stack.pop();
+                continue;
+            }
+
+            let level = cursor.level;
+            let virtual_addr = cursor.virtual_base | (cursor.index << LEVEL_SHIFTS[level]);
+            let entry_addr = GuestAddress(cursor.table_addr + cursor.index * PTE_SIZE);
+            cursor.index += 1;
+
+            if self.ptes_read == MAX_PTES_READ {
+                self.stack.clear();
+                return Some(Err(PageTableWalkError::TooManyEntries));
+            }
+            self.ptes_read += 1;
+
+            let Ok(entry) = self.guest_memory.read_obj::<u64>(entry_addr) else {
+                // The table does not lie in guest memory.
+                self.stack.pop();
+                continue;
+            };
+            if entry & PTE_PRESENT == 0 {
+                continue;
+            }
+
+            let is_leaf =
+                level == PAGE_TABLE_LEVELS - 1 || (level > 0 && entry & PTE_PAGE_SIZE != 0);
+            if is_leaf {
+                // Below the page size, the address bits of large pages hold flags (e.g. PAT).
+                let addr_mask = PTE_ADDR_MASK & !((1u64 << LEVEL_SHIFTS[level]) - 1);
+                return Some(Ok(GuestMapping {
+                    virtual_addr: canonical(virtual_addr),
+                    physical_addr: entry & addr_mask,
+                    flags: entry & !addr_mask,
+                }));
+            }
+
+            // The depth of the walk is bounded by the number of levels, so tables referencing
+            // themselves or their ancestors do not make it loop.
+            self.stack.push(TableCursor {
+                table_addr: entry & PTE_ADDR_MASK,
+                level: level + 1,
+                index: 0,
+                virtual_base: virtual_addr,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::single_region_mem;
+
+    const PTE_WRITABLE: u64 = 1 << 1;
+    const PTE_NX: u64 = 1 << 63;
+
+    fn write_pte(mem: &GuestMemoryMmap, table: u64, index: u64, entry: u64) {
+        mem.write_obj(entry, GuestAddress(table + index * PTE_SIZE))
+            .unwrap();
+    }
+
+    fn root(cr3: u64) -> PageTableRoot {
+        PageTableRoot { cr3, cr4: 0 }
+    }
+
+    #[test]
+    fn test_invalid_root() {
+        let mem = single_region_mem(0x10000);
+        assert_eq!(
+            GuestPageTableWalker::new(&mem, root(0x20000)).unwrap_err(),
+            PageTableWalkError::InvalidCr3(0x20000)
+        );
+        assert_eq!(
+            GuestPageTableWalker::new(
+                &mem,
+                PageTableRoot {
+                    cr3: 0x1000,
+                    cr4: CR4_LA57
+                }
+            )
+            .unwrap_err(),
+            PageTableWalkError::FiveLevelPaging
+        );
+    }
+
+    #[test]
+    fn test_too_many_entries() {
+        let mem = single_region_mem(0x10000);
+        let pml4 = 0x1000;
+        // Every entry references the PML4 table itself, which has 512^4 entries to walk.
+        for index in 0..PTES_PER_TABLE {
+            write_pte(&mem, pml4, index, pml4 | PTE_PRESENT);
+        }
+        let mut walker = GuestPageTableWalker::new(&mem, root(pml4)).unwrap();
+        assert_eq!(
+            walker.find(Result::is_err).unwrap(),
+            Err(PageTableWalkError::TooManyEntries)
+        );
+        assert_eq!(walker.next(), None);
+    }
+
+    #[test]
+    fn test_walk() {
+        let mem = single_region_mem(0x10_0000);
+        let (pml4, pdpt, pd, pt) = (0x1000, 0x2000, 0x3000, 0x4000);
+
+        // 0xffff_ffff_8000_0000: PML4 index 511, PDPT index 510.
+        write_pte(&mem, pml4, 511, pdpt | PTE_PRESENT);
+        write_pte(&mem, pdpt, 510, pd | PTE_PRESENT);
+        // A 2MiB page at 0xffff_ffff_8000_0000, with the PAT bit set.
+        write_pte(
+            &mem,
+            pd,
+            0,
+            0x20_0000 | (1 << 12) | PTE_PAGE_SIZE | PTE_PRESENT,
+        );
+        // Two 4KiB pages at 0xffff_ffff_8020_0000 and 0xffff_ffff_8020_2000.
+        write_pte(&mem, pd, 1, pt | PTE_PRESENT);
+        write_pte(&mem, pt, 0, 0x5000 | PTE_WRITABLE | PTE_PRESENT);
+        write_pte(&mem, pt, 1, 0x6000 | PTE_WRITABLE);
+        write_pte(&mem, pt, 2, 0x7000 | PTE_NX | PTE_PRESENT);
+        // A 1GiB page at 0x4000_0000.
+        write_pte(&mem, pml4, 0, 0x8000 | PTE_PRESENT);
+        write_pte(&mem, 0x8000, 1, 0x4000_0000 | PTE_PAGE_SIZE | PTE_PRESENT);
+        // A table outside guest memory is skipped.
+        write_pte(&mem, pml4, 1, 0x1000_0000 | PTE_PRESENT);
+        // A table referenced twice is walked for both virtual addresses.
+        write_pte(&mem, pdpt, 511, pd | PTE_PRESENT);
+
+        let mappings: Vec<_> = GuestPageTableWalker::new(&mem, root(pml4 | 0x18))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            mappings,
+            vec![
+                GuestMapping {
+                    virtual_addr: 0x4000_0000,
+                    physical_addr: 0x4000_0000,
+                    flags: PTE_PAGE_SIZE | PTE_PRESENT,
+                },
+                GuestMapping {
+                    virtual_addr: 0xffff_ffff_8000_0000,
+                    physical_addr: 0x20_0000,
+                    flags: (1 << 12) | PTE_PAGE_SIZE | PTE_PRESENT,
+                },
+                GuestMapping {
+                    virtual_addr: 0xffff_ffff_8020_0000,
+                    physical_addr: 0x5000,
+                    flags: PTE_WRITABLE | PTE_PRESENT,
+                },
+                GuestMapping {
+                    virtual_addr: 0xffff_ffff_8020_2000,
+                    physical_addr: 0x7000,
+                    flags: PTE_NX | PTE_PRESENT,
+                },
+                GuestMapping {
+                    virtual_addr: 0xffff_ffff_c000_0000,
+                    physical_addr: 0x20_0000,
+                    flags: (1 << 12) | PTE_PAGE_SIZE | PTE_PRESENT,
+                },
+                GuestMapping {
+                    virtual_addr: 0xffff_ffff_c020_0000,
+                    physical_addr: 0x5000,
+                    flags: PTE_WRITABLE | PTE_PRESENT,
+                },
+                GuestMapping {
+                    virtual_addr: 0xffff_ffff_c020_2000,
+                    physical_addr: 0x7000,
+                    flags: PTE_NX | PTE_PRESENT,
+                },
+            ]
+        );
+    }
+}
@@ -9,9 +9,12 @@ use std::collections::BTreeMap;
 use std::fmt::Debug;
 
 use kvm_bindings::{
-    CpuId, KVM_MAX_CPUID_ENTRIES, KVM_MAX_MSR_ENTRIES, Msrs, Xsave, kvm_debugregs, kvm_lapic_state,
-    kvm_mp_state, kvm_regs, kvm_sregs, kvm_vcpu_events, kvm_xcrs, kvm_xsave, kvm_xsave2,
+    CpuId, KVM_MAX_CPUID_ENTRIES, KVM_MAX_MSR_ENTRIES, Msrs, Xsave, kvm_debugregs, kvm_dtable,
+    kvm_lapic_state, kvm_mp_state, kvm_msr_entry, kvm_regs, kvm_segment, kvm_sregs,
+    kvm_vcpu_events, kvm_xcrs, kvm_xsave, kvm_xsave2,
 };
+#[cfg(feature = "debug-api")]
+use kvm_bindings::{KVM_GUESTDBG_ENABLE, KVM_GUESTDBG_USE_HW_BP, kvm_guest_debug};
 use kvm_ioctls::{VcpuExit, VcpuFd};
 use log::{error, warn};
 use serde::{Deserialize, Serialize};
@@ -24,6 +27,11 @@ use crate::arch::x86_64::msr::{MsrError, create_boot_msr_entries};
 use crate::arch::x86_64::regs::{SetupFpuError, SetupRegistersError, SetupSpecialRegistersError};
 use crate::cpu_config::x86_64::{CpuConfiguration, cpuid};
 use crate::logger::{IncMetric, METRICS};
+#[cfg(feature = "debug-api")]
+use crate::vmm_config::breakpoints::{
+    BreakpointConfig, BreakpointType, BreakpointsError, MAX_BREAKPOINTS,
+};
+use crate::vmm_config::machine_config::CpuidBlock;
 use crate::vstate::memory::GuestMemoryMmap;
 use crate::vstate::vcpu::{VcpuConfig, VcpuEmulation, VcpuError};
 use crate::vstate::vm::Vm;
@@ -35,6 +43,37 @@ use crate::vstate::vm::Vm;
 const TSC_KHZ_TOL_NUMERATOR: i64 = 250;
 const TSC_KHZ_TOL_DENOMINATOR: i64 = 1_000_000;
 
+/// Bits of CPUID leaf 0xA EDX describing the fixed counters of the PMU: their number in bits 4:0
+/// and their width in bits 12:5.
+const CPUID_LEAF_A_EDX_FIXED_COUNTERS: u32 = 0x1fff;
+
+/// Resume flag, which suppresses instruction breakpoints for the next instruction.
+#[cfg(feature = "debug-api")]
+const EFLAGS_RF: u64 = 1 << 16;
+
+/// DR7 bit enabling the first breakpoint, the bits of the following breakpoints are 2 bits apart.
+#[cfg(feature = "debug-api")]
+const DR7_GLOBAL_ENABLE: u64 = 1 << 1;
+/// DR7 bit enabling exact detection of data breakpoints.
+#[cfg(feature = "debug-api")]
+const DR7_GLOBAL_EXACT: u64 = 1 << 9;
+/// DR7 bit that always reads as 1.
+#[cfg(feature = "debug-api")]
+const DR7_RESERVED: u64 = 1 << 10;
+/// Offset of the R/W bits of the first breakpoint, the bits of the following breakpoints are 4
+/// bits apart.
+#[cfg(feature = "debug-api")]
+const DR7_RW_SHIFT: usize = 16;
+/// R/W bits of a breakpoint triggered by instruction execution.
+#[cfg(feature = "debug-api")]
+const DR7_RW_EXECUTE: u64 = 0b00;
+/// R/W bits of a breakpoint triggered by data writes.
+#[cfg(feature = "debug-api")]
+const DR7_RW_WRITE: u64 = 0b01;
+/// R/W bits of a breakpoint triggered by data reads and writes.
+#[cfg(feature = "debug-api")]
+const DR7_RW_ACCESS: u64 = 0b11;
+
 /// A set of MSRs that should be restored separately after all other MSRs have already been restored
 const DEFERRED_MSRS: [u32; 1] = [
     // MSR_IA32_TSC_DEADLINE must be restored after MSR_IA32_TSC, otherwise we risk "losing" timer
@@ -82,10 +121,18 @@ pub enum KvmVcpuError {
     VcpuGetCpuid(kvm_ioctls::Error),
     /// Failed to get KVM TSC frequency: {0}
     VcpuGetTsc(kvm_ioctls::Error),
+    /// Failed to inject NMI: {0}
+    #[cfg(feature = "debug-api")]
+    VcpuInjectNmi(kvm_ioctls::Error),
+    /// Failed to inject SMI: {0}
+    #[cfg(feature = "debug-api")]
+    VcpuInjectSmi(kvm_ioctls::Error),
     /// Failed to set KVM vcpu cpuid: {0}
     VcpuSetCpuid(kvm_ioctls::Error),
     /// Failed to set KVM vcpu debug regs: {0}
     VcpuSetDebugRegs(kvm_ioctls::Error),
+    /// Failed to set KVM vcpu guest debug: {0}
+    VcpuSetGuestDebug(kvm_ioctls::Error),
     /// Failed to set KVM vcpu lapic: {0}
     VcpuSetLapic(kvm_ioctls::Error),
     /// Failed to set KVM vcpu mp state: {0}
@@ -137,6 +184,63 @@ pub enum KvmVcpuConfigureError {
     SetLint(#[from] interrupts::InterruptError),
 }
 
+/// Details of the exception that caused a `KVM_EXIT_EXCEPTION`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExceptionInfo {
+    /// Exception vector.
+    pub vector: u32,
+    /// Error code pushed by the exception, if any.
+    pub error_code: u32,
+    /// Exception payload (the faulting address of a #PF, the DR6 bits of a #DB) as reported
+    /// through `KVM_CAP_EXCEPTION_PAYLOAD`, if any.
+    pub payload: Option<u64>,
+}
+
+impl ExceptionInfo {
+    /// Returns the mnemonic of the exception vector, as named in the Intel SDM.
+    pub fn mnemonic(&self) -> &'static str {
+        match self.vector {
+            0 => "#DE",
+            1 => "#DB",
+            2 => "NMI",
+            3 => "#BP",
+            4 => "#OF",
+            5 => "#BR",
+            6 => "#UD",
+            7 => "#NM",
+            8 => "#DF",
+            10 => "#TS",
+            11 => "#NP",
+            12 => "#SS",
+            13 => "#GP",
+            14 => "#PF",
+            16 => "#MF",
+            17 => "#AC",
+            18 => "#MC",
+            19 => "#XM",
+            20 => "#VE",
+            21 => "#CP",
+            _ => "unknown",
+        }
+    }
+}
+
+impl std::fmt::Display for ExceptionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (vector {}), error code: {:#x}",
+            self.mnemonic(),
+            self.vector,
+            self.error_code
+        )?;
+        match self.payload {
+            Some(payload) => write!(f, ", payload: {:#x}", payload),
+            None => write!(f, ", no payload"),
+        }
+    }
+}
+
 /// A wrapper around creating and using a kvm x86_64 vcpu.
 #[derive(Debug)]
 pub struct KvmVcpu {
@@ -153,6 +257,17 @@ pub struct KvmVcpu {
     ///
     /// `None` if `KVM_CAP_XSAVE2` not supported.
     xsave2_size: Option<usize>,
+    /// Breakpoints set through the API, applied again whenever the vCPU registers are
+    /// overwritten.
+    #[cfg(feature = "debug-api")]
+    persistent_debug: Option<PersistentDebugState>,
+    /// Whether the PMU is exposed to the guest through CPUID leaf 0xA.
+    pub pmu_enabled: bool,
+    /// Whether the fixed counters of the PMU are exposed to the guest. KVM cannot filter them,
+    /// so they are hidden while PMU events are filtered.
+    pub pmu_fixed_counters: bool,
+    /// CPUID bits hidden from the guest.
+    pub cpuid_blocklist: Vec<CpuidBlock>,
 }
 
 /// Vcpu peripherals
@@ -183,6 +298,11 @@ impl KvmVcpu {
             peripherals: Default::default(),
             msrs_to_save: vm.msrs_to_save().to_vec(),
             xsave2_size: vm.xsave2_size(),
+            #[cfg(feature = "debug-api")]
+            persistent_debug: None,
+            pmu_enabled: false,
+            pmu_fixed_counters: true,
+            cpuid_blocklist: Vec::new(),
         })
     }
 
@@ -202,6 +322,12 @@ impl KvmVcpu {
         vcpu_config: &VcpuConfig,
     ) -> Result<(), KvmVcpuConfigureError> {
         let mut cpuid = vcpu_config.cpu_config.cpuid.clone();
+        // Normalization hides the PMU from the guest, keep the leaf describing it if the PMU is
+        // exposed.
+        let pmu_leaf = self
+            .pmu_enabled
+            .then(|| cpuid.inner().get(&cpuid::CpuidKey::leaf(0xA)).cloned())
+            .flatten();
 
         // Apply machine specific changes to CPUID.
         cpuid.normalize(
@@ -212,6 +338,31 @@ impl KvmVcpu {
             // The number of bits needed to enumerate logical CPUs per core.
             u8::from(vcpu_config.vcpu_count > 1 && vcpu_config.smt),
         )?;
+        if let Some(pmu_leaf) = pmu_leaf {
+            cpuid
+                .inner_mut()
+                .insert(cpuid::CpuidKey::leaf(0xA), pmu_leaf);
+        }
+        // Normalization only clears the leaf on Intel, so hide the fixed counters explicitly
+        // whenever they are not exposed.
+        if !(self.pmu_enabled && self.pmu_fixed_counters) {
+            if let Some(leaf_a) = cpuid.inner_mut().get_mut(&cpuid::CpuidKey::leaf(0xA)) {
+                leaf_a.result.edx &= !CPUID_LEAF_A_EDX_FIXED_COUNTERS;
+            }
+        }
+        // Hide the blocked bits last, so that normalization cannot set them again.
+        for block in &self.cpuid_blocklist {
+            let key = cpuid::CpuidKey {
+                leaf: block.leaf,
+                subleaf: block.subleaf,
+            };
+            if let Some(entry) = cpuid.inner_mut().get_mut(&key) {
+                entry.result.eax &= !block.eax_mask;
+                entry.result.ebx &= !block.ebx_mask;
+                entry.result.ecx &= !block.ecx_mask;
+                entry.result.edx &= !block.edx_mask;
+            }
+        }
 
         // Set CPUID.
         let kvm_cpuid = kvm_bindings::CpuId::try_from(cpuid)?;
@@ -270,6 +421,29 @@ impl KvmVcpu {
         self.peripherals.pio_bus = Some(pio_bus);
     }
 
+    /// Returns the details of the exception that caused the last `KVM_EXIT_EXCEPTION`.
+    ///
+    /// The vector and error code come from `kvm_run`, while the payload of the (still pending)
+    /// exception is read through `KVM_GET_VCPU_EVENTS`, where `KVM_CAP_EXCEPTION_PAYLOAD`
+    /// makes KVM report it.
+    pub fn exception_info(&mut self) -> ExceptionInfo {
+        // SAFETY: `ex` is the active member of the `kvm_run` exit union after a
+        // `KVM_EXIT_EXCEPTION`. Reading it at any other time yields stale but valid integers.
+        let ex = unsafe { self.fd.get_kvm_run().__bindgen_anon_1.ex };
+        let payload = self
+            .fd
+            .get_vcpu_events()
+            .ok()
+            .filter(|events| events.exception_has_payload != 0)
+            .map(|events| events.exception_payload);
+
+        ExceptionInfo {
+            vector: ex.exception,
+            error_code: ex.error_code,
+            payload,
+        }
+    }
+
     /// Get the current XSAVE state for this vCPU.
     ///
     /// The C `kvm_xsave` struct was extended by adding a flexible array member (FAM) in the end
@@ -699,6 +873,41 @@ impl KvmVcpu {
             .map_err(KvmVcpuError::VcpuSetVcpuEvents)?;
         Ok(())
     }
+
+    /// Overwrites the architectural state of the vCPU, which must not be running, with
+    /// `snapshot`.
+    pub fn restore_state_snapshot(&self, snapshot: &VcpuStateSnapshot) -> Result<(), KvmVcpuError> {
+        snapshot.restore(&self.fd)?;
+        // Apply the breakpoints again so that they survive resetting the vCPU.
+        #[cfg(feature = "debug-api")]
+        if let Some(debug_state) = &self.persistent_debug {
+            debug_state.apply(&self.fd)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the breakpoints of the vCPU with those of `debug_state`.
+    ///
+    /// Unlike breakpoints set through GDB, these are applied again whenever the vCPU state is
+    /// overwritten through [`KvmVcpu::restore_state_snapshot`].
+    #[cfg(feature = "debug-api")]
+    pub fn set_persistent_debug(
+        &mut self,
+        debug_state: PersistentDebugState,
+    ) -> Result<(), KvmVcpuError> {
+        debug_state.apply(&self.fd)?;
+        self.persistent_debug = (!debug_state.is_empty()).then_some(debug_state);
+        Ok(())
+    }
+
+    /// Sets the resume flag of the vCPU, so that when it resumes it executes the instruction it
+    /// stopped at instead of hitting the breakpoint on that instruction again.
+    #[cfg(feature = "debug-api")]
+    pub fn set_resume_flag(&self) -> Result<(), KvmVcpuError> {
+        let mut regs = self.fd.get_regs().map_err(KvmVcpuError::VcpuGetRegs)?;
+        regs.rflags |= EFLAGS_RF;
+        self.fd.set_regs(&regs).map_err(KvmVcpuError::VcpuSetRegs)
+    }
 }
 
 impl Peripherals {
@@ -787,6 +996,244 @@ impl Debug for VcpuState {
     }
 }
 
+/// Architectural state of a single vCPU, used to inspect and modify a vCPU while it is suspended
+/// independently of the others.
+///
+/// Unlike [`VcpuState`] this is not meant for snapshotting the whole microVM: it only covers the
+/// general purpose, segment and control registers, the local APIC and the MSRs configured at boot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VcpuStateSnapshot {
+    /// General purpose registers.
+    #[serde(with = "KvmRegsDef")]
+    pub regs: kvm_regs,
+    /// Segment and control registers.
+    #[serde(with = "KvmSregsDef")]
+    pub sregs: kvm_sregs,
+    /// Local APIC registers.
+    pub lapic: kvm_lapic_state,
+    /// Values of the boot MSRs, as `(index, value)` pairs.
+    pub msrs: Vec<(u32, u64)>,
+}
+
+// The fields of `VcpuStateSnapshot` only hold integers, so equality is reflexive.
+impl Eq for VcpuStateSnapshot {}
+
+impl VcpuStateSnapshot {
+    /// Reads the state of the vCPU behind `vcpu_fd`, which must not be running.
+    pub fn capture(vcpu_fd: &VcpuFd) -> Result<Self, KvmVcpuError> {
+        let regs = vcpu_fd.get_regs().map_err(KvmVcpuError::VcpuGetRegs)?;
+        let sregs = vcpu_fd.get_sregs().map_err(KvmVcpuError::VcpuGetSregs)?;
+        let lapic = vcpu_fd.get_lapic().map_err(KvmVcpuError::VcpuGetLapic)?;
+
+        let mut msrs = Msrs::from_entries(&create_boot_msr_entries())?;
+        let nmsrs = vcpu_fd
+            .get_msrs(&mut msrs)
+            .map_err(KvmVcpuError::VcpuGetMsrs)?;
+        if let Some(msr) = msrs.as_slice().get(nmsrs) {
+            return Err(KvmVcpuError::VcpuGetMsr(msr.index));
+        }
+        let msrs = msrs
+            .as_slice()
+            .iter()
+            .map(|msr| (msr.index, msr.data))
+            .collect();
+
+        Ok(Self {
+            regs,
+            sregs,
+            lapic,
+            msrs,
+        })
+    }
+
+    /// Writes this state to the vCPU behind `vcpu_fd`, which must not be running.
+    pub fn restore(&self, vcpu_fd: &VcpuFd) -> Result<(), KvmVcpuError> {
+        vcpu_fd
+            .set_sregs(&self.sregs)
+            .map_err(KvmVcpuError::VcpuSetSregs)?;
+        vcpu_fd
+            .set_regs(&self.regs)
+            .map_err(KvmVcpuError::VcpuSetRegs)?;
+        vcpu_fd
+            .set_lapic(&self.lapic)
+            .map_err(KvmVcpuError::VcpuSetLapic)?;
+
+        let entries: Vec<kvm_msr_entry> = self
+            .msrs
+            .iter()
+            .map(|&(index, data)| kvm_msr_entry {
+                index,
+                data,
+                ..Default::default()
+            })
+            .collect();
+        let msrs = Msrs::from_entries(&entries)?;
+        let nmsrs = vcpu_fd.set_msrs(&msrs).map_err(KvmVcpuError::VcpuSetMsrs)?;
+        if nmsrs < entries.len() {
+            return Err(KvmVcpuError::VcpuSetMsrsIncomplete);
+        }
+        Ok(())
+    }
+}
+
+/// Hardware breakpoints set through the API, in the layout of the debug registers.
+#[cfg(feature = "debug-api")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PersistentDebugState {
+    /// Address of the first breakpoint.
+    pub dr0: u64,
+    /// Address of the second breakpoint.
+    pub dr1: u64,
+    /// Address of the third breakpoint.
+    pub dr2: u64,
+    /// Address of the fourth breakpoint.
+    pub dr3: u64,
+    /// Enables the breakpoints and selects the accesses that trigger them.
+    pub dr7: u64,
+}
+
+#[cfg(feature = "debug-api")]
+impl PersistentDebugState {
+    /// Builds the debug registers setting `breakpoints`, each one byte long.
+    pub fn new(breakpoints: &[BreakpointConfig]) -> Result<Self, BreakpointsError> {
+        if breakpoints.len() > MAX_BREAKPOINTS {
+            return Err(BreakpointsError::TooManyBreakpoints(breakpoints.len()));
+        }
+
+        let mut addrs = [0u64; MAX_BREAKPOINTS];
+        let mut dr7 = 0;
+        for (i, breakpoint) in breakpoints.iter().enumerate() {
+            let rw = match breakpoint.breakpoint_type {
+                BreakpointType::Execute => DR7_RW_EXECUTE,
+                BreakpointType::Write => DR7_RW_WRITE,
+                BreakpointType::Access => DR7_RW_ACCESS,
+            };
+            addrs[i] = breakpoint.address;
+            // The length bits are left cleared, which selects one byte and is the only length
+            // allowed for instruction breakpoints.
+            dr7 |= (DR7_GLOBAL_ENABLE << (i * 2)) | (rw << (DR7_RW_SHIFT + i * 4));
+        }
+        if dr7 != 0 {
+            dr7 |= DR7_GLOBAL_EXACT | DR7_RESERVED;
+        }
+
+        let [dr0, dr1, dr2, dr3] = addrs;
+        Ok(Self {
+            dr0,
+            dr1,
+            dr2,
+            dr3,
+            dr7,
+        })
+    }
+
+    /// Whether no breakpoint is set.
+    pub fn is_empty(&self) -> bool {
+        self.dr7 == 0
+    }
+
+    /// Configures the vCPU behind `vcpu_fd` to exit to the VMM when hitting the breakpoints, or
+    /// disables guest debugging if there are none.
+    pub fn apply(&self, vcpu_fd: &VcpuFd) -> Result<(), KvmVcpuError> {
+        let mut debug = kvm_guest_debug::default();
+        if !self.is_empty() {
+            debug.control = KVM_GUESTDBG_ENABLE | KVM_GUESTDBG_USE_HW_BP;
+            debug.arch.debugreg = [self.dr0, self.dr1, self.dr2, self.dr3, 0, 0, 0, self.dr7];
+        }
+        vcpu_fd
+            .set_guest_debug(&debug)
+            .map_err(KvmVcpuError::VcpuSetGuestDebug)
+    }
+}
+
+// Mirrors of the KVM register structures, used to (de)serialize them as named fields rather
+// than as opaque byte arrays, so that they can be read and edited through the API.
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "kvm_regs")]
+struct KvmRegsDef {
+    rax: u64,
+    rbx: u64,
+    rcx: u64,
+    rdx: u64,
+    rsi: u64,
+    rdi: u64,
+    rsp: u64,
+    rbp: u64,
+    r8: u64,
+    r9: u64,
+    r10: u64,
+    r11: u64,
+    r12: u64,
+    r13: u64,
+    r14: u64,
+    r15: u64,
+    rip: u64,
+    rflags: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "kvm_segment")]
+struct KvmSegmentDef {
+    base: u64,
+    limit: u32,
+    selector: u16,
+    #[serde(rename = "type")]
+    type_: u8,
+    present: u8,
+    dpl: u8,
+    db: u8,
+    s: u8,
+    l: u8,
+    g: u8,
+    avl: u8,
+    unusable: u8,
+    #[serde(skip)]
+    padding: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "kvm_dtable")]
+struct KvmDtableDef {
+    base: u64,
+    limit: u16,
+    #[serde(skip)]
+    padding: [u16; 3],
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "kvm_sregs")]
+struct KvmSregsDef {
+    #[serde(with = "KvmSegmentDef")]
+    cs: kvm_segment,
+    #[serde(with = "KvmSegmentDef")]
+    ds: kvm_segment,
+    #[serde(with = "KvmSegmentDef")]
+    es: kvm_segment,
+    #[serde(with = "KvmSegmentDef")]
+    fs: kvm_segment,
+    #[serde(with = "KvmSegmentDef")]
+    gs: kvm_segment,
+    #[serde(with = "KvmSegmentDef")]
+    ss: kvm_segment,
+    #[serde(with = "KvmSegmentDef")]
+    tr: kvm_segment,
+    #[serde(with = "KvmSegmentDef")]
+    ldt: kvm_segment,
+    #[serde(with = "KvmDtableDef")]
+    gdt: kvm_dtable,
+    #[serde(with = "KvmDtableDef")]
+    idt: kvm_dtable,
+    cr0: u64,
+    cr2: u64,
+    cr3: u64,
+    cr4: u64,
+    cr8: u64,
+    efer: u64,
+    apic_base: u64,
+    interrupt_bitmap: [u64; 4],
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::undocumented_unsafe_blocks)]
@@ -851,6 +1298,123 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_exception_payload() {
+        let (_, _, mut vcpu) = setup_vcpu(0x10000);
+
+        // Queue a #PF whose payload is the faulting address. Setting a payload is rejected by KVM
+        // unless `KVM_CAP_EXCEPTION_PAYLOAD` was enabled when creating the VM.
+        let mut events = vcpu.fd.get_vcpu_events().unwrap();
+        events.exception.pending = 1;
+        events.exception.nr = 14;
+        events.exception.has_error_code = 1;
+        events.exception.error_code = 0x2;
+        events.exception_has_payload = 1;
+        events.exception_payload = 0xdead_b000;
+        events.flags |= kvm_bindings::KVM_VCPUEVENT_VALID_PAYLOAD;
+        vcpu.fd.set_vcpu_events(&events).unwrap();
+
+        let info = vcpu.exception_info();
+        assert_eq!(info.payload, Some(0xdead_b000));
+    }
+
+    #[test]
+    fn test_vcpu_state_snapshot() {
+        let (_, _, vcpu) = setup_vcpu(0x10000);
+
+        let mut snapshot = VcpuStateSnapshot::capture(&vcpu.fd).unwrap();
+        assert_eq!(snapshot.msrs.len(), create_boot_msr_entries().len());
+
+        snapshot.regs.rip = 0x1000;
+        snapshot.regs.rax = 0xdead_beef;
+        snapshot.sregs.cr2 = 0x4000;
+        snapshot.restore(&vcpu.fd).unwrap();
+        assert_eq!(VcpuStateSnapshot::capture(&vcpu.fd).unwrap(), snapshot);
+
+        // Registers are exposed as named fields and survive a JSON round trip.
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["regs"]["rip"], 0x1000);
+        assert_eq!(json["sregs"]["cs"]["type"], snapshot.sregs.cs.type_);
+        let restored: VcpuStateSnapshot = serde_json::from_value(json).unwrap();
+        assert_eq!(restored, snapshot);
+    }
+
+    #[cfg(feature = "debug-api")]
+    #[test]
+    fn test_persistent_debug_state() {
+        let (_, _, mut vcpu) = setup_vcpu(0x10000);
+
+        let breakpoints = [
+            BreakpointConfig {
+                address: 0xffff_ffff_8100_0000,
+                breakpoint_type: BreakpointType::Execute,
+            },
+            BreakpointConfig {
+                address: 0x1000,
+                breakpoint_type: BreakpointType::Access,
+            },
+        ];
+        let debug_state = PersistentDebugState::new(&breakpoints).unwrap();
+        assert_eq!(
+            debug_state,
+            PersistentDebugState {
+                dr0: 0xffff_ffff_8100_0000,
+                dr1: 0x1000,
+                dr2: 0,
+                dr3: 0,
+                // G0, G1, GE, the reserved bit and R/W1 = 0b11.
+                dr7: 0b1010 | (0b11 << 9) | (0b11 << 20),
+            }
+        );
+        vcpu.set_persistent_debug(debug_state).unwrap();
+        assert_eq!(vcpu.persistent_debug, Some(debug_state));
+
+        // Overwriting the vCPU state applies the breakpoints again.
+        let snapshot = VcpuStateSnapshot::capture(&vcpu.fd).unwrap();
+        vcpu.restore_state_snapshot(&snapshot).unwrap();
+
+        // Clearing the breakpoints disables guest debugging.
+        let debug_state = PersistentDebugState::new(&[]).unwrap();
+        assert!(debug_state.is_empty());
+        vcpu.set_persistent_debug(debug_state).unwrap();
+        assert_eq!(vcpu.persistent_debug, None);
+
+        assert!(matches!(
+            PersistentDebugState::new(&[breakpoints[0]; MAX_BREAKPOINTS + 1]),
+            Err(BreakpointsError::TooManyBreakpoints(5))
+        ));
+    }
+
+    #[test]
+    fn test_exception_info_display() {
+        let info = ExceptionInfo {
+            vector: 14,
+            error_code: 0x2,
+            payload: Some(0xdead_b000),
+        };
+        assert_eq!(
+            info.to_string(),
+            "#PF (vector 14), error code: 0x2, payload: 0xdeadb000"
+        );
+
+        let info = ExceptionInfo {
+            vector: 13,
+            error_code: 0,
+            payload: None,
+        };
+        assert_eq!(
+            info.to_string(),
+            "#GP (vector 13), error code: 0x0, no payload"
+        );
+
+        let info = ExceptionInfo {
+            vector: 42,
+            error_code: 0,
+            payload: None,
+        };
+        assert_eq!(info.mnemonic(), "unknown");
+    }
+
     #[test]
     fn test_configure_vcpu() {
         let (kvm, vm, mut vcpu) = setup_vcpu(0x10000);
@@ -955,6 +1519,75 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_configure_vcpu_pmu() {
+        let (kvm, vm, mut vcpu) = setup_vcpu(0x10000);
+        let vcpu_config = create_vcpu_config(&kvm, &vcpu, &CustomCpuTemplate::default()).unwrap();
+        let leaf_a = |cpuid: &Cpuid| {
+            cpuid
+                .inner()
+                .get(&CpuidKey::leaf(0xA))
+                .map_or((0, 0), |entry| (entry.result.eax, entry.result.edx))
+        };
+        let host_leaf_a = leaf_a(&vcpu_config.cpu_config.cpuid);
+        let entry_point = EntryPoint {
+            entry_addr: GuestAddress(0),
+            protocol: BootProtocol::LinuxBoot,
+        };
+
+        // The PMU is hidden from the guest by default.
+        vcpu.configure(vm.guest_memory(), entry_point, &vcpu_config)
+            .unwrap();
+        let guest_cpuid = Cpuid::try_from(vcpu.save_state().unwrap().cpuid).unwrap();
+        assert_eq!(leaf_a(&guest_cpuid).0, 0);
+        assert_eq!(leaf_a(&guest_cpuid).1 & CPUID_LEAF_A_EDX_FIXED_COUNTERS, 0);
+
+        // The guest sees the PMU of the host once enabled.
+        vcpu.pmu_enabled = true;
+        vcpu.configure(vm.guest_memory(), entry_point, &vcpu_config)
+            .unwrap();
+        let guest_cpuid = Cpuid::try_from(vcpu.save_state().unwrap().cpuid).unwrap();
+        assert_eq!(leaf_a(&guest_cpuid), host_leaf_a);
+
+        // The fixed counters are hidden while events are filtered.
+        vcpu.pmu_fixed_counters = false;
+        vcpu.configure(vm.guest_memory(), entry_point, &vcpu_config)
+            .unwrap();
+        let guest_cpuid = Cpuid::try_from(vcpu.save_state().unwrap().cpuid).unwrap();
+        assert_eq!(leaf_a(&guest_cpuid).0, host_leaf_a.0);
+        assert_eq!(
+            leaf_a(&guest_cpuid).1,
+            host_leaf_a.1 & !CPUID_LEAF_A_EDX_FIXED_COUNTERS
+        );
+    }
+
+    #[test]
+    fn test_configure_vcpu_cpuid_blocklist() {
+        let (kvm, vm, mut vcpu) = setup_vcpu(0x10000);
+        let vcpu_config = create_vcpu_config(&kvm, &vcpu, &CustomCpuTemplate::default()).unwrap();
+        let entry_point = EntryPoint {
+            entry_addr: GuestAddress(0),
+            protocol: BootProtocol::LinuxBoot,
+        };
+
+        // Block the hypervisor bit, which normalization sets.
+        let hypervisor_bit = 1 << 31;
+        vcpu.cpuid_blocklist = vec![CpuidBlock {
+            leaf: 1,
+            subleaf: 0,
+            eax_mask: 0,
+            ebx_mask: 0,
+            ecx_mask: hypervisor_bit,
+            edx_mask: 0,
+        }];
+        vcpu.configure(vm.guest_memory(), entry_point, &vcpu_config)
+            .unwrap();
+        let guest_cpuid = Cpuid::try_from(vcpu.save_state().unwrap().cpuid).unwrap();
+        let leaf_1 = guest_cpuid.inner().get(&CpuidKey::leaf(1)).unwrap();
+        assert_eq!(leaf_1.result.ecx & hypervisor_bit, 0);
+        assert_ne!(leaf_1.result.eax, 0);
+    }
+
     #[test]
     fn test_vcpu_cpuid_restore() {
         let (kvm, _, vcpu) = setup_vcpu(0x10000);
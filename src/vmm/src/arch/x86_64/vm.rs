@@ -4,17 +4,26 @@
 use std::fmt;
 
 use kvm_bindings::{
-    KVM_CLOCK_TSC_STABLE, KVM_IRQCHIP_IOAPIC, KVM_IRQCHIP_PIC_MASTER, KVM_IRQCHIP_PIC_SLAVE,
-    KVM_PIT_SPEAKER_DUMMY, MsrList, kvm_clock_data, kvm_irqchip, kvm_pit_config, kvm_pit_state2,
+    KVM_CAP_EXCEPTION_PAYLOAD, KVM_CLOCK_REALTIME, KVM_CLOCK_TSC_STABLE, KVM_IRQCHIP_IOAPIC,
+    KVM_IRQCHIP_PIC_MASTER, KVM_IRQCHIP_PIC_SLAVE, KVM_PIT_SPEAKER_DUMMY, KVM_PMU_EVENT_ALLOW,
+    KVMIO, MsrList, kvm_clock_data, kvm_enable_cap, kvm_irqchip, kvm_pit_config, kvm_pit_state2,
+    kvm_pmu_event_filter,
 };
 use kvm_ioctls::Cap;
 use serde::{Deserialize, Serialize};
+use utils::time::{ClockType, get_time_ns};
+use vmm_sys_util::ioctl::ioctl_with_ptr;
+use vmm_sys_util::{ioctl_ioc_nr, ioctl_iow_nr};
 
 use crate::arch::x86_64::msr::MsrError;
 use crate::utils::u64_to_usize;
+use crate::vmm_config::pmu::MAX_PMU_EVENTS;
 use crate::vstate::memory::{GuestMemoryExtension, GuestMemoryState};
 use crate::vstate::vm::{VmCommon, VmError};
 
+// KVM_SET_PMU_EVENT_FILTER is not wrapped by kvm-ioctls.
+ioctl_iow_nr!(KVM_SET_PMU_EVENT_FILTER, KVMIO, 0xb2, kvm_pmu_event_filter);
+
 /// Error type for [`Vm::restore_state`]
 #[allow(missing_docs)]
 #[cfg(target_arch = "x86_64")]
@@ -44,6 +53,12 @@ pub enum ArchVmError {
     GetMsrsToSave(MsrError),
     /// Failed during KVM_SET_TSS_ADDRESS: {0}
     SetTssAddress(kvm_ioctls::Error),
+    /// Failed to enable KVM_CAP_EXCEPTION_PAYLOAD: {0}
+    EnableExceptionPayload(kvm_ioctls::Error),
+    /// Failed to set the PMU event filter: {0}
+    SetPmuEventFilter(kvm_ioctls::Error),
+    /// Too many events for the PMU event filter: {0}. At most {MAX_PMU_EVENTS:} are supported.
+    TooManyPmuEvents(usize),
 }
 
 /// Structure representing the current architecture's understand of what a "virtual machine" is.
@@ -56,6 +71,8 @@ pub struct ArchVm {
     ///
     /// `None` if `KVM_CAP_XSAVE2` not supported.
     xsave2_size: Option<usize>,
+    /// Events allowed by the PMU event filter, if one is set.
+    pmu_event_filter: Option<Vec<u64>>,
 }
 
 impl ArchVm {
@@ -90,10 +107,31 @@ impl ArchVm {
             .set_tss_address(u64_to_usize(crate::arch::x86_64::layout::KVM_TSS_ADDRESS))
             .map_err(ArchVmError::SetTssAddress)?;
 
+        // With `KVM_CAP_EXCEPTION_PAYLOAD` enabled, KVM reports the payload of a pending exception
+        // (e.g. the faulting address of a #PF) through `KVM_GET_VCPU_EVENTS` instead of eagerly
+        // writing it into CR2/DR6, which lets us report it on exception exits.
+        // https://docs.kernel.org/virt/kvm/api.html#kvm-cap-exception-payload
+        if kvm
+            .fd
+            .check_extension_raw(u64::from(KVM_CAP_EXCEPTION_PAYLOAD))
+            != 0
+        {
+            let mut cap = kvm_enable_cap {
+                cap: KVM_CAP_EXCEPTION_PAYLOAD,
+                ..Default::default()
+            };
+            cap.args[0] = 1;
+            common
+                .fd
+                .enable_cap(&cap)
+                .map_err(ArchVmError::EnableExceptionPayload)?;
+        }
+
         Ok(ArchVm {
             common,
             msrs_to_save,
             xsave2_size,
+            pmu_event_filter: None,
         })
     }
 
@@ -122,9 +160,16 @@ impl ArchVm {
         self.fd()
             .set_pit2(&state.pitstate)
             .map_err(ArchVmError::SetPit2)?;
-        self.fd()
-            .set_clock(&state.clock)
-            .map_err(ArchVmError::SetClock)?;
+        // KVM advances the clock by the wall clock time elapsed since it was saved on its own when
+        // it recorded that time. Otherwise, the clock is advanced here from the time recorded
+        // when saving it, if any.
+        if state.clock.flags & KVM_CLOCK_REALTIME == 0 && state.clock.realtime != 0 {
+            self.set_clock(state.clock.clock, state.clock.realtime)?;
+        } else {
+            self.fd()
+                .set_clock(&state.clock)
+                .map_err(ArchVmError::SetClock)?;
+        }
         self.fd()
             .set_irqchip(&state.pic_master)
             .map_err(ArchVmError::SetIrqChipPicMaster)?;
@@ -134,6 +179,39 @@ impl ArchVm {
         self.fd()
             .set_irqchip(&state.ioapic)
             .map_err(ArchVmError::SetIrqChipIoAPIC)?;
+        if let Some(events) = &state.pmu_event_filter {
+            self.set_pmu_event_filter(events)?;
+        }
+        Ok(())
+    }
+
+    /// Restricts the PMU events that the guest can count to `events`, encoded as event select
+    /// and unit mask. The fixed counters are not available to the guest while the filter is set.
+    ///
+    /// The filter applies to all vCPUs of the VM.
+    pub fn set_pmu_event_filter(&mut self, events: &[u64]) -> Result<(), ArchVmError> {
+        // The events of a restored filter come from the snapshot, not the validated API.
+        let nevents = u32::try_from(events.len())
+            .ok()
+            .filter(|_| events.len() <= MAX_PMU_EVENTS)
+            .ok_or(ArchVmError::TooManyPmuEvents(events.len()))?;
+        let header_len = std::mem::size_of::<kvm_pmu_event_filter>() / std::mem::size_of::<u64>();
+        // The events follow the header of the filter in the same buffer.
+        let mut buf = vec![0u64; header_len + events.len()];
+        {
+            // SAFETY: `buf` is aligned for `kvm_pmu_event_filter` and large enough to hold it.
+            let filter = unsafe { &mut *buf.as_mut_ptr().cast::<kvm_pmu_event_filter>() };
+            filter.action = KVM_PMU_EVENT_ALLOW;
+            filter.nevents = nevents;
+        }
+        buf[header_len..].copy_from_slice(events);
+
+        // SAFETY: The buffer holds a valid filter followed by `nevents` events.
+        let ret = unsafe { ioctl_with_ptr(self.fd(), KVM_SET_PMU_EVENT_FILTER(), buf.as_ptr()) };
+        if ret < 0 {
+            return Err(ArchVmError::SetPmuEventFilter(kvm_ioctls::Error::last()));
+        }
+        self.pmu_event_filter = Some(events.to_vec());
         Ok(())
     }
 
@@ -160,6 +238,10 @@ impl ArchVm {
         let mut clock = self.fd().get_clock().map_err(ArchVmError::VmGetClock)?;
         // This bit is not accepted in SET_CLOCK, clear it.
         clock.flags &= !KVM_CLOCK_TSC_STABLE;
+        // KVM only records the wall clock time of the clock if the host clocksource is the TSC.
+        if clock.flags & KVM_CLOCK_REALTIME == 0 {
+            clock.realtime = get_time_ns(ClockType::Real);
+        }
 
         let mut pic_master = kvm_irqchip {
             chip_id: KVM_IRQCHIP_PIC_MASTER,
@@ -192,9 +274,40 @@ impl ArchVm {
             pic_master,
             pic_slave,
             ioapic,
+            pmu_event_filter: self.pmu_event_filter.clone(),
         })
     }
 
+    /// Returns the KVM clock value in nanoseconds, together with the host wall clock time in
+    /// nanoseconds at which it was read.
+    pub fn clock(&self) -> Result<(u64, u64), ArchVmError> {
+        let clock = self.fd().get_clock().map_err(ArchVmError::VmGetClock)?;
+        // KVM only reports the matching wall clock time if the host clocksource is the TSC.
+        let realtime_ns = if clock.flags & KVM_CLOCK_REALTIME != 0 {
+            clock.realtime
+        } else {
+            get_time_ns(ClockType::Real)
+        };
+        Ok((clock.clock, realtime_ns))
+    }
+
+    /// Sets the KVM clock so that it read `clock_ns` at host wall clock time `realtime_ns`,
+    /// accounting for the wall clock time elapsed since then.
+    pub fn set_clock(&self, clock_ns: u64, realtime_ns: u64) -> Result<(), ArchVmError> {
+        let now_ns = get_time_ns(ClockType::Real);
+        let clock = if now_ns >= realtime_ns {
+            clock_ns.saturating_add(now_ns - realtime_ns)
+        } else {
+            clock_ns.saturating_sub(realtime_ns - now_ns)
+        };
+        self.fd()
+            .set_clock(&kvm_clock_data {
+                clock,
+                ..Default::default()
+            })
+            .map_err(ArchVmError::SetClock)
+    }
+
     /// Gets the list of MSRs to save when creating snapshots
     pub fn msrs_to_save(&self) -> &[u32] {
         self.msrs_to_save.as_slice()
@@ -218,6 +331,8 @@ pub struct VmState {
     // TODO: rename this field to adopt inclusive language once Linux updates it, too.
     pic_slave: kvm_irqchip,
     ioapic: kvm_irqchip,
+    /// Events allowed by the PMU event filter, if one is set.
+    pub pmu_event_filter: Option<Vec<u64>>,
 }
 
 impl fmt::Debug for VmState {
@@ -228,6 +343,7 @@ impl fmt::Debug for VmState {
             .field("pic_master", &"?")
             .field("pic_slave", &"?")
             .field("ioapic", &"?")
+            .field("pmu_event_filter", &self.pmu_event_filter)
             .finish()
     }
 }
@@ -235,11 +351,13 @@ impl fmt::Debug for VmState {
 #[cfg(test)]
 mod tests {
     use kvm_bindings::{
-        KVM_CLOCK_TSC_STABLE, KVM_IRQCHIP_IOAPIC, KVM_IRQCHIP_PIC_MASTER, KVM_IRQCHIP_PIC_SLAVE,
-        KVM_PIT_SPEAKER_DUMMY,
+        KVM_CLOCK_REALTIME, KVM_CLOCK_TSC_STABLE, KVM_IRQCHIP_IOAPIC, KVM_IRQCHIP_PIC_MASTER,
+        KVM_IRQCHIP_PIC_SLAVE, KVM_PIT_SPEAKER_DUMMY,
     };
 
+    use crate::arch::x86_64::vm::ArchVmError;
     use crate::snapshot::Snapshot;
+    use crate::vmm_config::pmu::MAX_PMU_EVENTS;
     use crate::vstate::vm::VmState;
     use crate::vstate::vm::tests::{setup_vm, setup_vm_with_memory};
 
@@ -269,6 +387,80 @@ mod tests {
         vm.restore_state(&vm_state).unwrap();
     }
 
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_vm_set_clock() {
+        let (_, vm) = setup_vm_with_memory(0x1000);
+        let (_, realtime_ns) = vm.clock().unwrap();
+
+        // The clock read 1s ten seconds ago, so it reads at least 11s now.
+        vm.set_clock(1_000_000_000, realtime_ns - 10_000_000_000)
+            .unwrap();
+        let (clock_ns, _) = vm.clock().unwrap();
+        assert!((11_000_000_000..12_000_000_000).contains(&clock_ns));
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_vm_restore_clock() {
+        let (_, vm) = setup_vm_with_memory(0x1000);
+        vm.setup_irqchip().unwrap();
+        let mut vm_state = vm.save_state().unwrap();
+        assert_ne!(vm_state.clock.realtime, 0);
+
+        // The clock was saved 100ms ago, by a host which did not record when, so the restored
+        // clock is advanced by Firecracker.
+        let (clock_ns, realtime_ns) = vm.clock().unwrap();
+        vm_state.clock.clock = clock_ns;
+        vm_state.clock.realtime = realtime_ns - 100_000_000;
+        vm_state.clock.flags &= !KVM_CLOCK_REALTIME;
+
+        let (_, mut vm) = setup_vm_with_memory(0x1000);
+        vm.setup_irqchip().unwrap();
+        vm.restore_state(&vm_state).unwrap();
+        let (restored_ns, _) = vm.clock().unwrap();
+        assert!((clock_ns + 100_000_000..clock_ns + 110_000_000).contains(&restored_ns));
+
+        // Snapshots which did not record the time are restored as they are.
+        vm_state.clock.realtime = 0;
+        let (_, mut vm) = setup_vm_with_memory(0x1000);
+        vm.setup_irqchip().unwrap();
+        vm.restore_state(&vm_state).unwrap();
+        let (restored_ns, _) = vm.clock().unwrap();
+        assert!((clock_ns..clock_ns + 10_000_000).contains(&restored_ns));
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_vm_pmu_event_filter() {
+        let (_, mut vm) = setup_vm_with_memory(0x1000);
+        vm.setup_irqchip().unwrap();
+        assert_eq!(vm.save_state().unwrap().pmu_event_filter, None);
+
+        vm.set_pmu_event_filter(&[0x3c, 0xc0]).unwrap();
+        let vm_state = vm.save_state().unwrap();
+        assert_eq!(vm_state.pmu_event_filter, Some(vec![0x3c, 0xc0]));
+
+        // The filter is applied again when restoring the state.
+        let (_, mut vm) = setup_vm_with_memory(0x1000);
+        vm.setup_irqchip().unwrap();
+        vm.restore_state(&vm_state).unwrap();
+        assert_eq!(
+            vm.save_state().unwrap().pmu_event_filter,
+            Some(vec![0x3c, 0xc0])
+        );
+
+        // Filters larger than KVM supports are refused before reaching KVM.
+        assert!(matches!(
+            vm.set_pmu_event_filter(&[0; MAX_PMU_EVENTS + 1]),
+            Err(ArchVmError::TooManyPmuEvents(301))
+        ));
+        assert_eq!(
+            vm.save_state().unwrap().pmu_event_filter,
+            Some(vec![0x3c, 0xc0])
+        );
+    }
+
     #[cfg(target_arch = "x86_64")]
     #[test]
     fn test_vm_save_restore_state_bad_irqchip() {
@@ -39,27 +39,34 @@ use crate::devices::acpi::vmgenid::{VmGenId, VmGenIdError};
 use crate::devices::legacy::RTCDevice;
 use crate::devices::legacy::serial::SerialOut;
 use crate::devices::legacy::{EventFdTrigger, SerialEventsWrapper, SerialWrapper};
+#[cfg(feature = "plugins")]
+use crate::devices::plugin::{PluginError, PluginRegion, load_plugin};
 use crate::devices::virtio::balloon::Balloon;
+use crate::devices::virtio::balloon::policy::BalloonPolicyHandler;
 use crate::devices::virtio::block::device::Block;
 use crate::devices::virtio::device::VirtioDevice;
 use crate::devices::virtio::mmio::MmioTransport;
 use crate::devices::virtio::net::Net;
+use crate::devices::virtio::pmem::{PMEM_ALIGNMENT, Pmem};
 use crate::devices::virtio::rng::Entropy;
 use crate::devices::virtio::vsock::{Vsock, VsockUnixBackend};
 #[cfg(feature = "gdb")]
 use crate::gdb;
 use crate::initrd::{InitrdConfig, InitrdError};
+#[cfg(feature = "plugins")]
+use crate::logger::info;
 use crate::logger::{debug, error};
 use crate::persist::{MicrovmState, MicrovmStateError};
 use crate::resources::VmResources;
 use crate::seccomp::BpfThreadMap;
 use crate::snapshot::Persist;
+use crate::utils::{align_up, usize_to_u64};
 use crate::vmm_config::instance_info::InstanceInfo;
-use crate::vmm_config::machine_config::MachineConfigError;
+use crate::vmm_config::machine_config::{MachineConfigError, ThpMode};
 use crate::vstate::kvm::Kvm;
-use crate::vstate::memory::GuestRegionMmap;
+use crate::vstate::memory::{GuestAddress, GuestRegionMmap, collapse_huge_pages};
 use crate::vstate::vcpu::{Vcpu, VcpuError};
-use crate::vstate::vm::Vm;
+use crate::vstate::vm::{Vm, VmError};
 use crate::{EventManager, Vmm, VmmError, device_manager};
 
 /// Errors associated with starting the instance.
@@ -69,6 +76,12 @@ pub enum StartMicrovmError {
     AttachBlockDevice(io::Error),
     /// Unable to attach the VMGenID device: {0}
     AttachVmgenidDevice(kvm_ioctls::Error),
+    /// Cannot start the balloon policy: {0}
+    BalloonPolicy(crate::devices::virtio::balloon::BalloonError),
+    /// A balloon policy is configured without a balloon device.
+    BalloonPolicyWithoutBalloon,
+    /// Cannot apply the cgroup resource controls: {0}
+    Cgroup(#[from] crate::cgroup::CgroupError),
     /// System configuration error: {0}
     ConfigureSystem(#[from] ConfigurationError),
     /// Failed to create guest config: {0}
@@ -104,8 +117,23 @@ pub enum StartMicrovmError {
     NetDeviceNotConfigured,
     /// Cannot open the block device backing file: {0}
     OpenBlockDevice(io::Error),
+    /// The open files limit of {limit} is below the {required} files needed by the configured vCPUs
+    /// and devices, raise it with --max-open-files.
+    OpenFilesLimit {
+        /// Soft limit on the number of open files.
+        limit: u64,
+        /// Estimated number of open files needed.
+        required: u64,
+    },
     /// Cannot initialize a MMIO Device or add a device to the MMIO Bus or cmdline: {0}
     RegisterMmioDevice(#[from] device_manager::mmio::MmioError),
+    /// Cannot map the memory of a pmem device into the guest: {0}
+    RegisterPmemMemory(VmError),
+    /// The memory of pmem device {0} ends past the guest physical address space
+    PmemAddressSpace(String),
+    /// Cannot load plugin device: {0}
+    #[cfg(feature = "plugins")]
+    Plugin(#[from] PluginError),
     /// Cannot restore microvm state: {0}
     RestoreMicrovmState(MicrovmStateError),
     /// Cannot set vm resources: {0}
@@ -191,6 +219,54 @@ fn create_vmm_and_vcpus(
     Ok((vmm, vcpus))
 }
 
+/// Files Firecracker keeps open regardless of the configured vCPUs and devices.
+const BASE_OPEN_FILES: u64 = 32;
+/// Files kept open by each vCPU.
+const OPEN_FILES_PER_VCPU: u64 = 2;
+/// Files kept open by each virtio device: its backend, queue and interrupt eventfds.
+const OPEN_FILES_PER_DEVICE: u64 = 4;
+
+/// Estimates the number of files the microVM needs to keep open. The estimate is a lower bound, so
+/// that it never rejects a configuration that would boot.
+fn required_open_files(vm_resources: &VmResources) -> u64 {
+    let devices = vm_resources.block.devices.len()
+        + vm_resources.net_builder.iter().count()
+        + usize::from(vm_resources.vsock.get().is_some())
+        + usize::from(vm_resources.balloon.get().is_some())
+        + usize::from(vm_resources.entropy.get().is_some())
+        + vm_resources.pmem.devices.len();
+
+    BASE_OPEN_FILES
+        + OPEN_FILES_PER_VCPU * u64::from(vm_resources.machine_config.vcpu_count)
+        + OPEN_FILES_PER_DEVICE * usize_to_u64(devices)
+}
+
+/// Fails early when the open files limit is too low for the microVM, which would otherwise fail
+/// creating a random file descriptor along the way.
+fn check_open_files_limit(vm_resources: &VmResources) -> Result<(), StartMicrovmError> {
+    let mut rlimit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: We pass a pointer to a valid area of memory to which we have exclusive mutable access
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlimit as *mut libc::rlimit) } < 0 {
+        debug!(
+            "Failed to get RLIMIT_NOFILE: {}",
+            io::Error::last_os_error()
+        );
+        return Ok(());
+    }
+
+    let required = required_open_files(vm_resources);
+    if rlimit.rlim_cur != libc::RLIM_INFINITY && rlimit.rlim_cur < required {
+        return Err(StartMicrovmError::OpenFilesLimit {
+            limit: rlimit.rlim_cur,
+            required,
+        });
+    }
+    Ok(())
+}
+
 /// Builds and starts a microVM based on the current Firecracker VmResources configuration.
 ///
 /// The built microVM and all the created vCPUs start off in the paused state.
@@ -213,6 +289,12 @@ pub fn build_microvm_for_boot(
         .as_ref()
         .ok_or(MissingKernelConfig)?;
 
+    check_open_files_limit(vm_resources)?;
+    crate::cgroup::apply_cgroup_limits(
+        &vm_resources.machine_config,
+        &vm_resources.block_device_paths(),
+    )?;
+
     let guest_memory = vm_resources
         .allocate_guest_memory()
         .map_err(StartMicrovmError::GuestMemory)?;
@@ -263,12 +345,33 @@ pub fn build_microvm_for_boot(
         attach_balloon_device(&mut vmm, &mut boot_cmdline, balloon, event_manager)?;
     }
 
+    let balloon_policy = vm_resources.machine_config.balloon_policy;
+    if !balloon_policy.is_none() {
+        let balloon = vm_resources
+            .balloon
+            .get()
+            .ok_or(StartMicrovmError::BalloonPolicyWithoutBalloon)?;
+        let handler = BalloonPolicyHandler::new(
+            balloon_policy,
+            balloon.clone(),
+            usize_to_u64(vm_resources.machine_config.mem_size_mib),
+        )
+        .map_err(StartMicrovmError::BalloonPolicy)?;
+        event_manager.add_subscriber(Arc::new(Mutex::new(handler)));
+    }
+
     attach_block_devices(
         &mut vmm,
         &mut boot_cmdline,
         vm_resources.block.devices.iter(),
         event_manager,
     )?;
+    attach_pmem_devices(
+        &mut vmm,
+        &mut boot_cmdline,
+        vm_resources.pmem.devices.iter(),
+        event_manager,
+    )?;
     attach_net_devices(
         &mut vmm,
         &mut boot_cmdline,
@@ -284,11 +387,33 @@ pub fn build_microvm_for_boot(
         attach_entropy_device(&mut vmm, &mut boot_cmdline, entropy, event_manager)?;
     }
 
+    #[cfg(feature = "plugins")]
+    attach_plugin_devices(&mut vmm, &vm_resources.plugins)?;
+
     #[cfg(target_arch = "aarch64")]
     attach_legacy_devices_aarch64(event_manager, &mut vmm, &mut boot_cmdline)?;
 
     attach_vmgenid_device(&mut vmm)?;
 
+    #[cfg(target_arch = "x86_64")]
+    {
+        let pmu_event_filter = vm_resources.pmu.event_filter();
+        for vcpu in vcpus.iter_mut() {
+            vcpu.kvm_vcpu.pmu_enabled = vm_resources.pmu.is_enabled(vcpu.kvm_vcpu.index);
+            vcpu.kvm_vcpu.pmu_fixed_counters = pmu_event_filter.is_none();
+            vcpu.kvm_vcpu.cpuid_blocklist = vm_resources.machine_config.cpuid_blocklist.clone();
+        }
+        if let Some(events) = pmu_event_filter {
+            vmm.vm
+                .set_pmu_event_filter(events)
+                .map_err(|err| VmmError::Vm(err.into()))?;
+        }
+    }
+
+    for vcpu in vcpus.iter_mut() {
+        vcpu.cpu_budget = vm_resources.machine_config.cpu_budget;
+    }
+
     configure_system_for_boot(
         &mut vmm,
         vcpus.as_mut(),
@@ -299,6 +424,15 @@ pub fn build_microvm_for_boot(
         boot_cmdline,
     )?;
 
+    // Collapse the guest memory into huge pages once the kernel and the boot data are written to
+    // it, before the vCPUs start.
+    if vm_resources.machine_config.thp_mode == Some(ThpMode::Madvise) {
+        if let Err(err) = collapse_huge_pages(vmm.vm.guest_memory()) {
+            // Collapsing is best effort: it needs Linux 6.1, and fails for unpopulated memory.
+            info!("Could not collapse all of the guest memory into huge pages: {err}");
+        }
+    }
+
     let vmm = Arc::new(Mutex::new(vmm));
 
     #[cfg(feature = "gdb")]
@@ -655,6 +789,25 @@ fn attach_entropy_device(
     )
 }
 
+#[cfg(feature = "plugins")]
+fn attach_plugin_devices(
+    vmm: &mut Vmm,
+    plugins: &[crate::vmm_config::plugin::PluginConfig],
+) -> Result<(), StartMicrovmError> {
+    for config in plugins {
+        let plugin = load_plugin(config)?;
+        let name = plugin.name().to_string();
+        let addrs = vmm
+            .mmio_device_manager
+            .register_mmio_plugin(&mut vmm.resource_allocator, PluginRegion::split(plugin)?)?;
+        info!(
+            "Plugin {name} loaded from {} with MMIO regions at {addrs:#x?}",
+            config.path.display()
+        );
+    }
+    Ok(())
+}
+
 fn attach_block_devices<'a, I: Iterator<Item = &'a Arc<Mutex<Block>>> + Debug>(
     vmm: &mut Vmm,
     cmdline: &mut LoaderKernelCmdline,
@@ -689,6 +842,49 @@ fn attach_block_devices<'a, I: Iterator<Item = &'a Arc<Mutex<Block>>> + Debug>(
     Ok(())
 }
 
+fn attach_pmem_devices<'a, I: Iterator<Item = &'a Arc<Mutex<Pmem>>> + Debug>(
+    vmm: &mut Vmm,
+    cmdline: &mut LoaderKernelCmdline,
+    pmem_devices: I,
+    event_manager: &mut EventManager,
+) -> Result<(), StartMicrovmError> {
+    // The memory of the pmem devices is laid out past guest memory, in the order of the devices,
+    // and must be addressable by the guest.
+    let mut next_addr = crate::arch::device_memory_start(vmm.vm.guest_memory());
+    let addr_limit = 1u64 << vmm.kvm.guest_phys_bits();
+    for pmem in pmem_devices {
+        let id = {
+            let mut locked = pmem.lock().expect("Poisoned lock");
+            let addr = GuestAddress(align_up(next_addr.0, PMEM_ALIGNMENT));
+            if addr
+                .0
+                .checked_add(locked.size())
+                .is_none_or(|end| end > addr_limit)
+            {
+                return Err(StartMicrovmError::PmemAddressSpace(locked.id().to_string()));
+            }
+            vmm.vm
+                .register_device_memory(addr, locked.mmap(), locked.read_only())
+                .map_err(StartMicrovmError::RegisterPmemMemory)?;
+            locked.set_start_addr(addr);
+            next_addr = GuestAddress(addr.0 + locked.size());
+
+            // The root device is the first pmem device, which the guest names /dev/pmem0.
+            if locked.root_device() {
+                cmdline.insert_str("root=/dev/pmem0")?;
+                match locked.read_only() {
+                    true => cmdline.insert_str("ro")?,
+                    false => cmdline.insert_str("rw")?,
+                }
+            }
+            locked.id().to_string()
+        };
+        // The device mutex mustn't be locked here otherwise it will deadlock.
+        attach_virtio_device(event_manager, vmm, id, pmem.clone(), cmdline, false)?;
+    }
+    Ok(())
+}
+
 fn attach_net_devices<'a, I: Iterator<Item = &'a Arc<Mutex<Net>>> + Debug>(
     vmm: &mut Vmm,
     cmdline: &mut LoaderKernelCmdline,
@@ -751,7 +947,7 @@ pub(crate) mod tests {
     use crate::devices::virtio::block::CacheType;
     use crate::devices::virtio::rng::device::ENTROPY_DEV_ID;
     use crate::devices::virtio::vsock::{TYPE_VSOCK, VSOCK_DEV_ID};
-    use crate::devices::virtio::{TYPE_BALLOON, TYPE_BLOCK, TYPE_RNG};
+    use crate::devices::virtio::{TYPE_BALLOON, TYPE_BLOCK, TYPE_PMEM, TYPE_RNG};
     use crate::mmds::data_store::{Mmds, MmdsVersion};
     use crate::mmds::ns::MmdsNetworkStack;
     use crate::utils::mib_to_bytes;
@@ -760,6 +956,7 @@ pub(crate) mod tests {
     use crate::vmm_config::drive::{BlockBuilder, BlockDeviceConfig};
     use crate::vmm_config::entropy::{EntropyDeviceBuilder, EntropyDeviceConfig};
     use crate::vmm_config::net::{NetBuilder, NetworkInterfaceConfig};
+    use crate::vmm_config::pmem::{PmemBuilder, PmemConfig};
     use crate::vmm_config::vsock::tests::default_config;
     use crate::vmm_config::vsock::{VsockBuilder, VsockDeviceConfig};
     use crate::vstate::vm::tests::setup_vm_with_memory;
@@ -886,6 +1083,8 @@ pub(crate) mod tests {
                 ),
                 rate_limiter: None,
                 file_engine_type: None,
+                lifetime: None,
+                discard: None,
 
                 socket: None,
             };
@@ -916,6 +1115,18 @@ pub(crate) mod tests {
         res.unwrap();
     }
 
+    pub(crate) fn insert_pmem_device(
+        vmm: &mut Vmm,
+        cmdline: &mut Cmdline,
+        event_manager: &mut EventManager,
+        pmem_config: PmemConfig,
+    ) {
+        let mut pmem_builder = PmemBuilder::new();
+        pmem_builder.insert(pmem_config).unwrap();
+
+        attach_pmem_devices(vmm, cmdline, pmem_builder.devices.iter(), event_manager).unwrap();
+    }
+
     pub(crate) fn insert_net_device_with_mmds(
         vmm: &mut Vmm,
         cmdline: &mut Cmdline,
@@ -930,6 +1141,7 @@ pub(crate) mod tests {
         mmds.set_version(mmds_version).unwrap();
         net.lock().unwrap().configure_mmds_network_stack(
             MmdsNetworkStack::default_ipv4_addr(),
+            MmdsNetworkStack::default_rate_limit_rps(),
             Arc::new(Mutex::new(mmds)),
         );
 
@@ -1009,6 +1221,9 @@ pub(crate) mod tests {
             guest_mac: None,
             rx_rate_limiter: None,
             tx_rate_limiter: None,
+            mtu: None,
+            link_speed_mbps: None,
+            duplex: None,
         };
 
         let mut cmdline = default_kernel_cmdline();
@@ -1246,6 +1461,63 @@ pub(crate) mod tests {
         ));
     }
 
+    #[test]
+    fn test_required_open_files() {
+        let mut vm_resources = VmResources::default();
+        vm_resources.machine_config.vcpu_count = 2;
+        assert_eq!(
+            required_open_files(&vm_resources),
+            BASE_OPEN_FILES + 2 * OPEN_FILES_PER_VCPU
+        );
+
+        vm_resources
+            .entropy
+            .insert(EntropyDeviceConfig::default())
+            .unwrap();
+        assert_eq!(
+            required_open_files(&vm_resources),
+            BASE_OPEN_FILES + 2 * OPEN_FILES_PER_VCPU + OPEN_FILES_PER_DEVICE
+        );
+        check_open_files_limit(&vm_resources).unwrap();
+    }
+
+    #[test]
+    fn test_open_files_limit_too_low() {
+        let mut vm_resources = VmResources::default();
+        vm_resources
+            .entropy
+            .insert(EntropyDeviceConfig::default())
+            .unwrap();
+        let required = required_open_files(&vm_resources);
+
+        // The open files limit is per process, so lower it in a child process, where it cannot
+        // starve the tests running in parallel.
+        // SAFETY: The child only makes syscalls and exits, without returning to the test harness.
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0, "{}", io::Error::last_os_error());
+        if pid == 0 {
+            let rlimit = libc::rlimit {
+                rlim_cur: required - 1,
+                rlim_max: required - 1,
+            };
+            // SAFETY: `rlimit` is a valid `rlimit` struct.
+            let limited = unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &rlimit) } == 0;
+            let rejected = matches!(
+                check_open_files_limit(&vm_resources),
+                Err(StartMicrovmError::OpenFilesLimit { limit, required: needed })
+                    if limit == required - 1 && needed == required
+            );
+            // SAFETY: Exiting the child process, without running the destructors of the parent.
+            unsafe { libc::_exit(i32::from(!(limited && rejected))) };
+        }
+
+        let mut status = 0;
+        // SAFETY: `pid` is our child process and `status` is a valid `c_int`.
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+    }
+
     #[test]
     fn test_attach_entropy_device() {
         let mut event_manager = EventManager::new().expect("Unable to create EventManager");
@@ -1263,6 +1535,67 @@ pub(crate) mod tests {
         ));
     }
 
+    #[test]
+    fn test_attach_pmem_devices() {
+        let mut event_manager = EventManager::new().expect("Unable to create EventManager");
+        let mut vmm = default_vmm();
+        let mut cmdline = default_kernel_cmdline();
+
+        let mut builder = PmemBuilder::new();
+        let mut files = Vec::new();
+        for (drive_id, size, is_root_device) in [
+            ("pmem1", PMEM_ALIGNMENT, false),
+            ("root", 2 * PMEM_ALIGNMENT, true),
+        ] {
+            let file = TempFile::new().unwrap();
+            file.as_file().set_len(size).unwrap();
+            builder
+                .insert(PmemConfig {
+                    drive_id: drive_id.to_string(),
+                    path_on_host: file.as_path().to_str().unwrap().to_string(),
+                    is_root_device,
+                    is_read_only: true,
+                })
+                .unwrap();
+            files.push(file);
+        }
+
+        attach_pmem_devices(
+            &mut vmm,
+            &mut cmdline,
+            builder.devices.iter(),
+            &mut event_manager,
+        )
+        .unwrap();
+        assert!(cmdline_contains(&cmdline, "root=/dev/pmem0 ro"));
+        for drive_id in ["pmem1", "root"] {
+            assert!(
+                vmm.mmio_device_manager
+                    .get_device(DeviceType::Virtio(TYPE_PMEM), drive_id)
+                    .is_some()
+            );
+        }
+
+        // The memory of the devices is laid out past guest memory, starting with the root device.
+        let start = align_up(
+            crate::arch::device_memory_start(vmm.vm.guest_memory()).0,
+            PMEM_ALIGNMENT,
+        );
+        let mut config = [0u8; 8];
+        builder.devices[0]
+            .lock()
+            .unwrap()
+            .read_config(0, &mut config);
+        assert_eq!(u64::from_le_bytes(config), start);
+        builder.devices[1]
+            .lock()
+            .unwrap()
+            .read_config(0, &mut config);
+        assert_eq!(u64::from_le_bytes(config), start + 2 * PMEM_ALIGNMENT);
+        // Guest memory does not include the memory of the devices.
+        assert_eq!(vmm.vm.guest_memory().num_regions(), 1);
+    }
+
     #[test]
     fn test_attach_vsock_device() {
         let mut event_manager = EventManager::new().expect("Unable to create EventManager");
@@ -0,0 +1,559 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Resource controls of the cgroup v2 the microVM runs in.
+//!
+//! The cgroup is created by the orchestrator, or by the jailer under `--parent-cgroup`, and is
+//! passed to Firecracker with `--cgroup-path`. The limits set in the machine configuration are
+//! written to its interface files when the microVM starts, and again whenever they are updated.
+//!
+//! The vCPU threads are moved to a threaded child cgroup, which is frozen when pausing the
+//! microVM so that all vCPUs stop at once.
+
+use std::ffi::CString;
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::FromRawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+use vmm_sys_util::epoll::{ControlOperation, Epoll, EpollEvent, EventSet};
+
+use crate::logger::{info, warn};
+use crate::vmm_config::machine_config::MachineConfig;
+
+/// cgroup v2 interface file of the CPU weight.
+const CPU_WEIGHT_FILE: &str = "cpu.weight";
+/// cgroup v2 interface file of the block I/O weight.
+const IO_WEIGHT_FILE: &str = "io.weight";
+/// cgroup v2 interface file of the memory throttling limit.
+const MEMORY_HIGH_FILE: &str = "memory.high";
+/// cgroup v2 interface file of the swap throttling limit.
+const MEMORY_SWAP_HIGH_FILE: &str = "memory.swap.high";
+/// Directory listing the block devices of the host, and their partitions.
+const SYS_BLOCK_PATH: &str = "/sys/block";
+/// Threaded child cgroup holding the vCPU threads, so that they can be frozen together.
+const VCPU_CGROUP: &str = "vcpus";
+/// cgroup v2 interface file freezing and thawing the threads of a cgroup.
+const FREEZE_FILE: &str = "cgroup.freeze";
+/// cgroup v2 interface file reporting, among others, whether a cgroup is frozen.
+const EVENTS_FILE: &str = "cgroup.events";
+/// How long the vCPU threads can take to freeze.
+const FREEZE_TIMEOUT: Duration = Duration::from_secs(1);
+
+static VM_CGROUP: OnceLock<VmCgroup> = OnceLock::new();
+
+/// Errors associated with the cgroup of the microVM.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum CgroupError {
+    /// `{0}` is not a cgroup v2 directory
+    NotACgroup(PathBuf),
+    /// The cgroup of the microVM is already set
+    AlreadySet,
+    /// Resource controls require the cgroup of the microVM, set with `--cgroup-path`
+    NotConfigured,
+    /// Failed to write `{0}`: {1}
+    Write(PathBuf, io::Error),
+    /// Failed to find the block device backing `{0}`: {1}
+    BackingDevice(PathBuf, io::Error),
+    /// Failed to create the vCPU cgroup: {0}
+    CreateVcpuCgroup(io::Error),
+    /// Failed to read `{0}`: {1}
+    Read(PathBuf, io::Error),
+    /// Failed to watch `{0}`: {1}
+    Watch(PathBuf, io::Error),
+    /// The vCPU threads did not freeze within {FREEZE_TIMEOUT:?}
+    FreezeTimeout,
+}
+
+/// cgroup v2 directory of the microVM.
+#[derive(Debug)]
+pub struct VmCgroup {
+    path: PathBuf,
+    vcpus: OnceLock<VcpuCgroup>,
+}
+
+impl VmCgroup {
+    /// Opens the cgroup v2 directory at `path`.
+    pub fn new(path: PathBuf) -> Result<Self, CgroupError> {
+        // Every cgroup v2 directory has a `cgroup.procs` file, which regular directories lack.
+        if !path.join("cgroup.procs").is_file() {
+            return Err(CgroupError::NotACgroup(path));
+        }
+        Ok(VmCgroup {
+            path,
+            vcpus: OnceLock::new(),
+        })
+    }
+
+    /// Returns the path of the cgroup directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `value` to the interface file `file` of the cgroup. The kernel applies the value
+    /// of each write atomically.
+    pub fn write(&self, file: &str, value: &str) -> Result<(), CgroupError> {
+        write_file(&self.path, file, value)
+    }
+
+    /// Creates the threaded child cgroup of the vCPU threads, or returns the existing one. The
+    /// creation fails if controllers that do not support threads, like `memory` or `io`, are
+    /// enabled in the `cgroup.subtree_control` of the microVM cgroup.
+    pub fn create_vcpu_cgroup(&self) -> Result<&VcpuCgroup, CgroupError> {
+        if let Some(vcpus) = self.vcpus.get() {
+            return Ok(vcpus);
+        }
+        let path = self.path.join(VCPU_CGROUP);
+        match fs::create_dir(&path) {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => (),
+            Err(err) => return Err(CgroupError::CreateVcpuCgroup(err)),
+        }
+        write_file(&path, "cgroup.type", "threaded")?;
+        let vcpus = VcpuCgroup::new(path)?;
+        Ok(self.vcpus.get_or_init(|| vcpus))
+    }
+
+    /// Writes the resource controls set in `config` to the cgroup. The I/O weight applies to the
+    /// host disks backing `drive_paths`.
+    pub fn apply(
+        &self,
+        config: &MachineConfig,
+        drive_paths: &[PathBuf],
+    ) -> Result<(), CgroupError> {
+        if let Some(cpu_weight) = config.cpu_weight {
+            self.write(CPU_WEIGHT_FILE, &cpu_weight.to_string())?;
+        }
+        if let Some(io_weight) = config.io_weight {
+            self.write_io_weight(Path::new(SYS_BLOCK_PATH), io_weight, drive_paths)?;
+        }
+        if let Some(memory_high_mb) = config.memory_high_mb {
+            self.write(MEMORY_HIGH_FILE, &memory_high_mb.to_cgroup_value())?;
+        }
+        if let Some(memory_swap_high_mb) = config.memory_swap_high_mb {
+            self.write(
+                MEMORY_SWAP_HIGH_FILE,
+                &memory_swap_high_mb.to_cgroup_value(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_io_weight(
+        &self,
+        sys_block: &Path,
+        io_weight: u16,
+        drive_paths: &[PathBuf],
+    ) -> Result<(), CgroupError> {
+        for disk in backing_disks(sys_block, drive_paths)? {
+            // `io.weight` takes one `<major>:<minor> <weight>` line per write.
+            self.write(IO_WEIGHT_FILE, &format!("{disk} {io_weight}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Threaded cgroup v2 holding the vCPU threads of the microVM.
+#[derive(Debug)]
+pub struct VcpuCgroup {
+    path: PathBuf,
+    // inotify instance watching `cgroup.events`, which the kernel modifies when the cgroup
+    // becomes frozen.
+    events_watch: File,
+    // Waits for `events_watch` to become readable.
+    epoll: Epoll,
+}
+
+impl VcpuCgroup {
+    /// Opens the cgroup v2 directory at `path`, and starts watching its `cgroup.events`.
+    ///
+    /// The watch is set up here, before the seccomp filters are installed, so that freezing only
+    /// needs to wait on it.
+    fn new(path: PathBuf) -> Result<Self, CgroupError> {
+        let events_path = path.join(EVENTS_FILE);
+        let watch = || -> io::Result<(File, Epoll)> {
+            // SAFETY: The call has no pointer arguments, and its result is checked.
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: `fd` is a valid file descriptor which nothing else owns.
+            let events_watch = unsafe { File::from_raw_fd(fd) };
+            let c_path = CString::new(events_path.as_os_str().as_bytes())?;
+            // SAFETY: `fd` is a valid inotify instance and `c_path` is a NUL terminated string
+            // which outlives the call.
+            let wd = unsafe { libc::inotify_add_watch(fd, c_path.as_ptr(), libc::IN_MODIFY) };
+            if wd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let epoll = Epoll::new()?;
+            epoll.ctl(ControlOperation::Add, fd, EpollEvent::new(EventSet::IN, 0))?;
+            Ok((events_watch, epoll))
+        };
+        let (events_watch, epoll) = watch().map_err(|err| CgroupError::Watch(events_path, err))?;
+        Ok(VcpuCgroup {
+            path,
+            events_watch,
+            epoll,
+        })
+    }
+
+    /// Moves the calling thread to the cgroup.
+    pub fn join(&self) -> Result<(), CgroupError> {
+        // Writing 0 to `cgroup.threads` moves the writing thread.
+        write_file(&self.path, "cgroup.threads", "0")
+    }
+
+    /// Freezes the threads of the cgroup, and waits until all of them are stopped.
+    pub fn freeze(&self) -> Result<(), CgroupError> {
+        write_file(&self.path, FREEZE_FILE, "1")?;
+        // The kernel stops the threads asynchronously, and modifies `cgroup.events` once all of
+        // them are stopped. The watch is set before freezing, so the modification cannot be
+        // missed between reading the file and waiting.
+        let deadline = Instant::now() + FREEZE_TIMEOUT;
+        while !self.is_frozen()? {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                self.thaw()?;
+                return Err(CgroupError::FreezeTimeout);
+            }
+            self.wait_events(timeout)?;
+        }
+        Ok(())
+    }
+
+    /// Lets the threads of the cgroup run again.
+    pub fn thaw(&self) -> Result<(), CgroupError> {
+        write_file(&self.path, FREEZE_FILE, "0")
+    }
+
+    fn is_frozen(&self) -> Result<bool, CgroupError> {
+        let path = self.path.join(EVENTS_FILE);
+        let events = fs::read_to_string(&path).map_err(|err| CgroupError::Read(path, err))?;
+        Ok(events.lines().any(|line| line == "frozen 1"))
+    }
+
+    /// Waits at most `timeout` for `cgroup.events` to be modified.
+    fn wait_events(&self, timeout: Duration) -> Result<(), CgroupError> {
+        let read_error = |err| CgroupError::Read(self.path.join(EVENTS_FILE), err);
+        // Round up, so that a sub-millisecond timeout does not turn into a busy loop.
+        let timeout_ms = i32::try_from(timeout.as_micros().div_ceil(1000)).unwrap_or(i32::MAX);
+        let mut events = [EpollEvent::default()];
+        match self.epoll.wait(timeout_ms, &mut events) {
+            Ok(_) => (),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(read_error(err)),
+        }
+        // Drain the pending inotify events. They only wake the waiter up, the state is always
+        // read from `cgroup.events`.
+        let mut buf = [0u8; 1024];
+        loop {
+            match (&self.events_watch).read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(_) => (),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(read_error(err)),
+            }
+        }
+    }
+}
+
+fn write_file(dir: &Path, file: &str, value: &str) -> Result<(), CgroupError> {
+    let path = dir.join(file);
+    fs::write(&path, value).map_err(|err| CgroupError::Write(path, err))
+}
+
+/// Returns the `<major>:<minor>` numbers of the host disks backing `paths`, without duplicates.
+/// Files that are not stored on a disk, e.g. on a tmpfs, are skipped.
+fn backing_disks(sys_block: &Path, paths: &[PathBuf]) -> Result<Vec<String>, CgroupError> {
+    let mut disks = Vec::new();
+    for path in paths {
+        match backing_disk(sys_block, path)? {
+            Some(disk) if !disks.contains(&disk) => disks.push(disk),
+            Some(_) => (),
+            None => warn!(
+                "No host disk backs {}, skipping its I/O weight",
+                path.display()
+            ),
+        }
+    }
+    Ok(disks)
+}
+
+/// Returns the `<major>:<minor>` numbers of the disk holding `path`. I/O weights only apply to
+/// whole disks, so a file on a partition resolves to the disk of the partition.
+fn backing_disk(sys_block: &Path, path: &Path) -> Result<Option<String>, CgroupError> {
+    let backing_device_err = |err| CgroupError::BackingDevice(path.to_path_buf(), err);
+    let metadata = fs::metadata(path).map_err(backing_device_err)?;
+    let dev = if metadata.file_type().is_block_device() {
+        metadata.rdev()
+    } else {
+        metadata.dev()
+    };
+    let dev = format!("{}:{}", libc::major(dev), libc::minor(dev));
+
+    let read_dev =
+        |dir: &Path| fs::read_to_string(dir.join("dev")).map(|dev| dev.trim().to_string());
+    for disk in fs::read_dir(sys_block).map_err(backing_device_err)? {
+        let disk = disk.map_err(backing_device_err)?.path();
+        let disk_dev = read_dev(&disk).map_err(backing_device_err)?;
+        if disk_dev == dev {
+            return Ok(Some(disk_dev));
+        }
+        // Partitions are the subdirectories with a `partition` file.
+        for part in fs::read_dir(&disk).map_err(backing_device_err)? {
+            let part = part.map_err(backing_device_err)?.path();
+            if part.join("partition").is_file() && read_dev(&part).is_ok_and(|d| d == dev) {
+                return Ok(Some(disk_dev));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Sets the cgroup of the microVM, once, when Firecracker starts.
+pub fn init_vm_cgroup(path: PathBuf) -> Result<(), CgroupError> {
+    let cgroup = VmCgroup::new(path)?;
+    info!("Using cgroup {}", cgroup.path().display());
+    VM_CGROUP.set(cgroup).map_err(|_| CgroupError::AlreadySet)
+}
+
+/// Returns the cgroup of the microVM, if Firecracker was given one.
+pub fn vm_cgroup() -> Option<&'static VmCgroup> {
+    VM_CGROUP.get()
+}
+
+/// Returns the cgroup of the vCPU threads, if it was created.
+pub fn vcpu_cgroup() -> Option<&'static VcpuCgroup> {
+    vm_cgroup()?.vcpus.get()
+}
+
+/// Writes the resource controls set in `config` to the cgroup of the microVM. Fails if any is set
+/// but Firecracker has no cgroup.
+pub fn apply_cgroup_limits(
+    config: &MachineConfig,
+    drive_paths: &[PathBuf],
+) -> Result<(), CgroupError> {
+    match vm_cgroup() {
+        Some(cgroup) => cgroup.apply(config, drive_paths),
+        None if config.has_cgroup_limits() => Err(CgroupError::NotConfigured),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vmm_sys_util::tempdir::TempDir;
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+    use crate::vmm_config::machine_config::CgroupMemoryLimit;
+
+    fn mock_cgroup() -> (TempDir, VmCgroup) {
+        let dir = TempDir::new().unwrap();
+        for file in [
+            "cgroup.procs",
+            CPU_WEIGHT_FILE,
+            IO_WEIGHT_FILE,
+            MEMORY_HIGH_FILE,
+            MEMORY_SWAP_HIGH_FILE,
+        ] {
+            fs::write(dir.as_path().join(file), "").unwrap();
+        }
+        let cgroup = VmCgroup::new(dir.as_path().to_path_buf()).unwrap();
+        (dir, cgroup)
+    }
+
+    #[test]
+    fn test_not_a_cgroup() {
+        let dir = TempDir::new().unwrap();
+        assert!(matches!(
+            VmCgroup::new(dir.as_path().to_path_buf()),
+            Err(CgroupError::NotACgroup(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply_cpu_weight() {
+        let (dir, cgroup) = mock_cgroup();
+        let cpu_weight = dir.as_path().join(CPU_WEIGHT_FILE);
+
+        // Unset controls leave the cgroup untouched.
+        cgroup.apply(&MachineConfig::default(), &[]).unwrap();
+        assert_eq!(fs::read_to_string(&cpu_weight).unwrap(), "");
+
+        let config = MachineConfig {
+            cpu_weight: Some(500),
+            ..Default::default()
+        };
+        cgroup.apply(&config, &[]).unwrap();
+        assert_eq!(fs::read_to_string(&cpu_weight).unwrap(), "500");
+    }
+
+    #[test]
+    fn test_apply_memory_high() {
+        let (dir, cgroup) = mock_cgroup();
+        // The limits may be below the guest memory size, which throttles the microVM.
+        let config = MachineConfig {
+            mem_size_mib: 256,
+            memory_high_mb: Some(CgroupMemoryLimit::Mib(128)),
+            memory_swap_high_mb: Some(CgroupMemoryLimit::Mib(16)),
+            ..Default::default()
+        };
+        cgroup.apply(&config, &[]).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.as_path().join(MEMORY_HIGH_FILE)).unwrap(),
+            (128u64 << 20).to_string()
+        );
+        assert_eq!(
+            fs::read_to_string(dir.as_path().join(MEMORY_SWAP_HIGH_FILE)).unwrap(),
+            (16u64 << 20).to_string()
+        );
+
+        // The limits can be lifted.
+        let config = MachineConfig {
+            memory_high_mb: Some(CgroupMemoryLimit::Max),
+            ..config
+        };
+        cgroup.apply(&config, &[]).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.as_path().join(MEMORY_HIGH_FILE)).unwrap(),
+            "max"
+        );
+    }
+
+    #[test]
+    fn test_backing_disks() {
+        let drive = TempFile::new().unwrap();
+        let dev = fs::metadata(drive.as_path()).unwrap().dev();
+        let dev = format!("{}:{}", libc::major(dev), libc::minor(dev));
+
+        // A mock `/sys/block`, where the drive is on the first partition of `vdb`.
+        let sys_block = TempDir::new().unwrap();
+        let mock_device = |dir: &str, dev: &str| {
+            let dir = sys_block.as_path().join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("dev"), format!("{dev}\n")).unwrap();
+            dir
+        };
+        mock_device("vda", "250:0");
+        mock_device("vdb", "250:16");
+        let part = mock_device("vdb/vdb1", &dev);
+        fs::write(part.join("partition"), "1\n").unwrap();
+
+        let drives = [drive.as_path().to_path_buf(), drive.as_path().to_path_buf()];
+        assert_eq!(
+            backing_disks(sys_block.as_path(), &drives).unwrap(),
+            vec!["250:16".to_string()]
+        );
+
+        let (dir, cgroup) = mock_cgroup();
+        cgroup
+            .write_io_weight(sys_block.as_path(), 300, &drives)
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.as_path().join(IO_WEIGHT_FILE)).unwrap(),
+            "250:16 300"
+        );
+
+        // Without the `partition` file, `vdb1` is not a partition of `vdb`.
+        fs::remove_file(part.join("partition")).unwrap();
+        assert!(
+            backing_disks(sys_block.as_path(), &drives)
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(matches!(
+            backing_disk(sys_block.as_path(), Path::new("/nonexistent")),
+            Err(CgroupError::BackingDevice(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_vcpu_cgroup() {
+        let (dir, cgroup) = mock_cgroup();
+        let vcpus_dir = dir.as_path().join(VCPU_CGROUP);
+        // The kernel populates the interface files of a new cgroup.
+        fs::create_dir(&vcpus_dir).unwrap();
+        fs::write(vcpus_dir.join(EVENTS_FILE), "populated 1\nfrozen 1\n").unwrap();
+
+        let vcpus = cgroup.create_vcpu_cgroup().unwrap();
+        assert_eq!(
+            fs::read_to_string(vcpus_dir.join("cgroup.type")).unwrap(),
+            "threaded"
+        );
+        assert!(std::ptr::eq(vcpus, cgroup.create_vcpu_cgroup().unwrap()));
+
+        vcpus.join().unwrap();
+        assert_eq!(
+            fs::read_to_string(vcpus_dir.join("cgroup.threads")).unwrap(),
+            "0"
+        );
+
+        vcpus.freeze().unwrap();
+        assert_eq!(
+            fs::read_to_string(vcpus_dir.join(FREEZE_FILE)).unwrap(),
+            "1"
+        );
+        vcpus.thaw().unwrap();
+        assert_eq!(
+            fs::read_to_string(vcpus_dir.join(FREEZE_FILE)).unwrap(),
+            "0"
+        );
+    }
+
+    #[test]
+    fn test_freeze_timeout() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.as_path().join(EVENTS_FILE), "populated 1\nfrozen 0\n").unwrap();
+        let vcpus = VcpuCgroup::new(dir.as_path().to_path_buf()).unwrap();
+
+        let start = Instant::now();
+        assert!(matches!(vcpus.freeze(), Err(CgroupError::FreezeTimeout)));
+        assert!(start.elapsed() >= FREEZE_TIMEOUT);
+        // The threads are thawed when they do not all freeze in time.
+        assert_eq!(
+            fs::read_to_string(dir.as_path().join(FREEZE_FILE)).unwrap(),
+            "0"
+        );
+    }
+
+    #[test]
+    fn test_freeze_wakeup() {
+        let dir = TempDir::new().unwrap();
+        let events_path = dir.as_path().join(EVENTS_FILE);
+        fs::write(&events_path, "populated 1\nfrozen 0\n").unwrap();
+        let vcpus = VcpuCgroup::new(dir.as_path().to_path_buf()).unwrap();
+
+        // The freeze completes as soon as `cgroup.events` reports it.
+        let kernel = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            fs::write(&events_path, "populated 1\nfrozen 1\n").unwrap();
+        });
+        let start = Instant::now();
+        vcpus.freeze().unwrap();
+        assert!(start.elapsed() < FREEZE_TIMEOUT);
+        kernel.join().unwrap();
+    }
+
+    #[test]
+    fn test_apply_without_cgroup() {
+        // `init_vm_cgroup` is never called by the unit tests.
+        apply_cgroup_limits(&MachineConfig::default(), &[]).unwrap();
+        let config = MachineConfig {
+            cpu_weight: Some(100),
+            ..Default::default()
+        };
+        assert!(matches!(
+            apply_cgroup_limits(&config, &[]),
+            Err(CgroupError::NotConfigured)
+        ));
+    }
+}
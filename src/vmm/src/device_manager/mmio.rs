@@ -26,6 +26,8 @@ use crate::arch::DeviceType::Virtio;
 use crate::devices::BusDevice;
 #[cfg(target_arch = "aarch64")]
 use crate::devices::legacy::RTCDevice;
+#[cfg(feature = "plugins")]
+use crate::devices::plugin::PluginRegion;
 use crate::devices::pseudo::BootTimer;
 use crate::devices::virtio::balloon::Balloon;
 use crate::devices::virtio::block::device::Block;
@@ -358,6 +360,38 @@ impl MMIODeviceManager {
         )
     }
 
+    /// Register the MMIO regions of a plugin device, returning their addresses.
+    ///
+    /// Plugin devices do not appear in the device tree nor in the ACPI tables: the guest driver
+    /// is expected to know where they are, for example through the kernel command line.
+    #[cfg(feature = "plugins")]
+    pub fn register_mmio_plugin(
+        &mut self,
+        resource_allocator: &mut ResourceAllocator,
+        regions: Vec<(PluginRegion, u64)>,
+    ) -> Result<Vec<u64>, MmioError> {
+        let addrs = regions
+            .iter()
+            .map(|(_, len)| {
+                resource_allocator.allocate_mmio_memory(
+                    len.next_multiple_of(MMIO_LEN),
+                    MMIO_LEN,
+                    AllocPolicy::FirstMatch,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some((region, _)) = regions.first() {
+            region.notify_mapped(&addrs);
+        }
+        for ((region, len), addr) in regions.into_iter().zip(addrs.iter()) {
+            self.bus
+                .insert(Arc::new(Mutex::new(BusDevice::Plugin(region))), *addr, len)
+                .map_err(MmioError::BusInsert)?;
+        }
+        Ok(addrs)
+    }
+
     /// Gets the information of the devices registered up to some point in time.
     pub fn get_device_info(&self) -> &HashMap<(DeviceType, String), MMIODeviceInfo> {
         &self.id_to_dev_info
@@ -192,6 +192,8 @@ pub struct DeviceStates {
     pub balloon_device: Option<ConnectedBalloonState>,
     /// Mmds version.
     pub mmds_version: Option<MmdsVersionState>,
+    /// Mmds response cache TTL.
+    pub mmds_cache_ttl_ms: u64,
     /// Entropy device state.
     pub entropy_device: Option<ConnectedEntropyState>,
 }
@@ -348,8 +350,9 @@ impl<'a> Persist<'a> for MMIODeviceManager {
                     if let (Some(mmds_ns), None) =
                         (net.mmds_ns.as_ref(), states.mmds_version.as_ref())
                     {
-                        states.mmds_version =
-                            Some(mmds_ns.mmds.lock().expect("Poisoned lock").version().into());
+                        let mmds = mmds_ns.mmds.lock().expect("Poisoned lock");
+                        states.mmds_version = Some(mmds.version().into());
+                        states.mmds_cache_ttl_ms = mmds.response_cache_ttl_ms();
                     }
 
                     states.net_devices.push(ConnectedNetState {
@@ -561,6 +564,10 @@ impl<'a> Persist<'a> for MMIODeviceManager {
             constructor_args
                 .vm_resources
                 .set_mmds_version(mmds_version.clone().into(), constructor_args.instance_id)?;
+            constructor_args
+                .vm_resources
+                .locked_mmds_or_default()
+                .set_response_cache_ttl_ms(state.mmds_cache_ttl_ms);
         } else if state
             .net_devices
             .iter()
@@ -773,6 +780,9 @@ mod tests {
                 guest_mac: None,
                 rx_rate_limiter: None,
                 tx_rate_limiter: None,
+                mtu: None,
+                link_speed_mbps: None,
+                duplex: None,
             };
             insert_net_device_with_mmds(
                 &mut vmm,
@@ -107,6 +107,16 @@ impl VmGenId {
         Ok(u128::from_le_bytes(gen_id_bytes))
     }
 
+    /// Replace the generation ID in guest memory and notify the guest about it.
+    pub fn set_gen_id(&mut self, gen_id: u128, mem: &GuestMemoryMmap) -> Result<(), VmGenIdError> {
+        debug!("vmgenid: writing new generation ID to guest: {gen_id:#034x}");
+        mem.write_slice(&gen_id.to_le_bytes(), self.guest_address)
+            .inspect_err(|err| error!("vmgenid: could not write generation ID to guest: {err}"))?;
+        self.gen_id = gen_id;
+        self.notify_guest()?;
+        Ok(())
+    }
+
     /// Send an ACPI notification to guest device.
     ///
     /// This will only have effect if we have updated the generation ID in guest memory, i.e. when
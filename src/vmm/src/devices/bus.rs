@@ -55,6 +55,8 @@ use event_manager::{EventOps, Events, MutEventSubscriber};
 #[cfg(target_arch = "aarch64")]
 use super::legacy::RTCDevice;
 use super::legacy::{I8042Device, SerialDevice};
+#[cfg(feature = "plugins")]
+use super::plugin::PluginRegion;
 use super::pseudo::BootTimer;
 use super::virtio::mmio::MmioTransport;
 
@@ -66,6 +68,8 @@ pub enum BusDevice {
     BootTimer(BootTimer),
     MmioTransport(MmioTransport),
     Serial(SerialDevice<std::io::Stdin>),
+    #[cfg(feature = "plugins")]
+    Plugin(PluginRegion),
     #[cfg(test)]
     Dummy(DummyDevice),
     #[cfg(test)]
@@ -174,6 +178,8 @@ impl BusDevice {
             Self::BootTimer(x) => x.bus_read(offset, data),
             Self::MmioTransport(x) => x.bus_read(offset, data),
             Self::Serial(x) => x.bus_read(offset, data),
+            #[cfg(feature = "plugins")]
+            Self::Plugin(x) => x.bus_read(offset, data),
             #[cfg(test)]
             Self::Dummy(x) => x.bus_read(offset, data),
             #[cfg(test)]
@@ -189,6 +195,8 @@ impl BusDevice {
             Self::BootTimer(x) => x.bus_write(offset, data),
             Self::MmioTransport(x) => x.bus_write(offset, data),
             Self::Serial(x) => x.bus_write(offset, data),
+            #[cfg(feature = "plugins")]
+            Self::Plugin(x) => x.bus_write(offset, data),
             #[cfg(test)]
             Self::Dummy(x) => x.bus_write(offset, data),
             #[cfg(test)]
@@ -280,6 +288,7 @@ impl Bus {
     ///
     /// Returns true on success, otherwise `data` is untouched.
     pub fn read(&self, addr: u64, data: &mut [u8]) -> bool {
+        crate::ftrace!("Bus::read");
         if let Some((offset, dev)) = self.get_device(addr) {
             // OK to unwrap as lock() failing is a serious error condition and should panic.
             dev.lock()
@@ -295,6 +304,7 @@ impl Bus {
     ///
     /// Returns true on success, otherwise `data` is untouched.
     pub fn write(&self, addr: u64, data: &[u8]) -> bool {
+        crate::ftrace!("Bus::write");
         if let Some((offset, dev)) = self.get_device(addr) {
             // OK to unwrap as lock() failing is a serious error condition and should panic.
             dev.lock()
@@ -12,6 +12,8 @@ use std::io;
 pub mod acpi;
 pub mod bus;
 pub mod legacy;
+#[cfg(feature = "plugins")]
+pub mod plugin;
 pub mod pseudo;
 pub mod virtio;
 
@@ -20,6 +22,7 @@ use log::error;
 
 use crate::devices::virtio::net::metrics::NetDeviceMetrics;
 use crate::devices::virtio::queue::QueueError;
+use crate::devices::virtio::transport::TransportError;
 use crate::devices::virtio::vsock::VsockError;
 use crate::logger::IncMetric;
 
@@ -46,6 +49,8 @@ pub enum DeviceError {
     MalformedDescriptor,
     /// Error during queue processing: {0}
     QueueError(QueueError),
+    /// Virtio transport error: {0}
+    Transport(TransportError),
     /// Vsock device error: {0}
     VsockError(VsockError),
 }
@@ -0,0 +1,251 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Devices emulated by plugins loaded from shared libraries.
+//!
+//! A plugin is a shared library exporting a [`PLUGIN_INIT_SYMBOL`] function of type
+//! [`PluginInitFn`], which returns the [`FirecrackerPlugin`] emulating the device. Firecracker
+//! maps the MMIO regions of the device in the guest physical address space and forwards the guest
+//! accesses to them to the plugin, on the vCPU threads.
+//!
+//! The plugin interface uses the Rust ABI, which is not stable: a plugin must be built with the
+//! same compiler and against the same Firecracker sources as the Firecracker binary loading it.
+//! The interface itself may change in any release.
+//!
+//! ```ignore
+//! #[unsafe(no_mangle)]
+//! pub fn firecracker_plugin_init(
+//!     config: &serde_json::Value,
+//! ) -> Result<Box<dyn FirecrackerPlugin>, String> {
+//!     Ok(Box::new(MyDevice::new(config)?))
+//! }
+//! ```
+
+use std::ffi::{CStr, CString};
+use std::fmt::{self, Debug};
+use std::os::unix::ffi::OsStrExt;
+use std::sync::{Arc, Mutex};
+
+use crate::vmm_config::plugin::PluginConfig;
+
+/// Name of the function a plugin library must export.
+pub const PLUGIN_INIT_SYMBOL: &CStr = c"firecracker_plugin_init";
+
+/// Type of the [`PLUGIN_INIT_SYMBOL`] function, which creates the device of a plugin from the
+/// `config` of its [`PluginConfig`].
+pub type PluginInitFn =
+    fn(config: &serde_json::Value) -> Result<Box<dyn FirecrackerPlugin>, String>;
+
+/// Errors associated with plugin devices.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum PluginError {
+    /// Invalid plugin path: {0}
+    InvalidPath(std::ffi::NulError),
+    /// Failed to load the plugin library: {0}
+    Load(String),
+    /// The plugin library does not export firecracker_plugin_init: {0}
+    MissingInit(String),
+    /// Failed to initialize the plugin: {0}
+    Init(String),
+    /// The plugin {0} has an empty MMIO region.
+    EmptyRegion(String),
+}
+
+/// Device emulated by a plugin.
+pub trait FirecrackerPlugin: Send {
+    /// Returns the name of the device, used in the logs.
+    fn name(&self) -> &str;
+
+    /// Returns the sizes, in bytes, of the MMIO regions of the device.
+    fn mmio_regions(&self) -> Vec<u64>;
+
+    /// Called once the MMIO regions are mapped in the guest, with their guest physical addresses
+    /// in the order of [`FirecrackerPlugin::mmio_regions`].
+    fn mmio_mapped(&mut self, _addrs: &[u64]) {}
+
+    /// Handles a guest read of `data.len()` bytes at `offset` in the MMIO region `region`.
+    fn mmio_read(&mut self, region: usize, offset: u64, data: &mut [u8]);
+
+    /// Handles a guest write of `data` at `offset` in the MMIO region `region`.
+    fn mmio_write(&mut self, region: usize, offset: u64, data: &[u8]);
+}
+
+fn dlerror() -> String {
+    // SAFETY: `dlerror` returns either NULL or a valid C string describing the last error.
+    let err = unsafe { libc::dlerror() };
+    if err.is_null() {
+        return "unknown error".to_string();
+    }
+    // SAFETY: `err` is a valid C string, which we copy before any other `dl*` call.
+    unsafe { CStr::from_ptr(err) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Loads the plugin described by `config` and creates its device.
+///
+/// The library stays loaded until Firecracker exits.
+pub fn load_plugin(config: &PluginConfig) -> Result<Box<dyn FirecrackerPlugin>, PluginError> {
+    let path =
+        CString::new(config.path.as_os_str().as_bytes()).map_err(PluginError::InvalidPath)?;
+
+    // SAFETY: `path` is a valid C string. Loading a library runs its initializers, which is the
+    // point of plugins.
+    let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if handle.is_null() {
+        return Err(PluginError::Load(dlerror()));
+    }
+    // SAFETY: `handle` is a valid library handle and the symbol name is a valid C string.
+    let init = unsafe { libc::dlsym(handle, PLUGIN_INIT_SYMBOL.as_ptr()) };
+    if init.is_null() {
+        return Err(PluginError::MissingInit(dlerror()));
+    }
+    // SAFETY: The symbol exported by the plugin has the type documented by `PluginInitFn`. Since
+    // the library is never unloaded, the function stays valid.
+    let init = unsafe { std::mem::transmute::<*mut libc::c_void, PluginInitFn>(init) };
+
+    init(&config.config).map_err(PluginError::Init)
+}
+
+/// MMIO region of a plugin device, registered on the MMIO bus.
+pub struct PluginRegion {
+    plugin: Arc<Mutex<Box<dyn FirecrackerPlugin>>>,
+    region: usize,
+}
+
+impl Debug for PluginRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginRegion")
+            .field("plugin", &self.plugin.lock().expect("Poisoned lock").name())
+            .field("region", &self.region)
+            .finish()
+    }
+}
+
+impl PluginRegion {
+    /// Splits a plugin device into its MMIO regions, returned with their sizes.
+    pub fn split(plugin: Box<dyn FirecrackerPlugin>) -> Result<Vec<(Self, u64)>, PluginError> {
+        let sizes = plugin.mmio_regions();
+        if sizes.contains(&0) {
+            return Err(PluginError::EmptyRegion(plugin.name().to_string()));
+        }
+        let plugin = Arc::new(Mutex::new(plugin));
+        Ok(sizes
+            .into_iter()
+            .enumerate()
+            .map(|(region, size)| {
+                (
+                    PluginRegion {
+                        plugin: plugin.clone(),
+                        region,
+                    },
+                    size,
+                )
+            })
+            .collect())
+    }
+
+    /// Notifies the plugin of the guest physical addresses of its MMIO regions.
+    pub fn notify_mapped(&self, addrs: &[u64]) {
+        self.plugin
+            .lock()
+            .expect("Poisoned lock")
+            .mmio_mapped(addrs);
+    }
+
+    pub(crate) fn bus_read(&mut self, offset: u64, data: &mut [u8]) {
+        self.plugin
+            .lock()
+            .expect("Poisoned lock")
+            .mmio_read(self.region, offset, data);
+    }
+
+    pub(crate) fn bus_write(&mut self, offset: u64, data: &[u8]) {
+        self.plugin
+            .lock()
+            .expect("Poisoned lock")
+            .mmio_write(self.region, offset, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct RegisterPlugin {
+        regs: Vec<Vec<u8>>,
+        addrs: Vec<u64>,
+    }
+
+    impl FirecrackerPlugin for RegisterPlugin {
+        fn name(&self) -> &str {
+            "registers"
+        }
+
+        fn mmio_regions(&self) -> Vec<u64> {
+            self.regs.iter().map(|regs| regs.len() as u64).collect()
+        }
+
+        fn mmio_mapped(&mut self, addrs: &[u64]) {
+            self.addrs = addrs.to_vec();
+        }
+
+        fn mmio_read(&mut self, region: usize, offset: u64, data: &mut [u8]) {
+            let offset = usize::try_from(offset).unwrap();
+            data.copy_from_slice(&self.regs[region][offset..offset + data.len()]);
+        }
+
+        fn mmio_write(&mut self, region: usize, offset: u64, data: &[u8]) {
+            let offset = usize::try_from(offset).unwrap();
+            self.regs[region][offset..offset + data.len()].copy_from_slice(data);
+        }
+    }
+
+    #[test]
+    fn test_plugin_regions() {
+        let plugin = RegisterPlugin {
+            regs: vec![vec![0; 8], vec![0; 16]],
+            addrs: vec![],
+        };
+        let mut regions = PluginRegion::split(Box::new(plugin)).unwrap();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].1, 8);
+        assert_eq!(regions[1].1, 16);
+
+        regions[1].0.bus_write(4, &[1, 2, 3, 4]);
+        let mut data = [0u8; 4];
+        regions[1].0.bus_read(4, &mut data);
+        assert_eq!(data, [1, 2, 3, 4]);
+        regions[0].0.bus_read(4, &mut data);
+        assert_eq!(data, [0; 4]);
+
+        let plugin = RegisterPlugin {
+            regs: vec![vec![]],
+            addrs: vec![],
+        };
+        assert!(matches!(
+            PluginRegion::split(Box::new(plugin)),
+            Err(PluginError::EmptyRegion(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_plugin() {
+        let config = PluginConfig {
+            path: "/nonexistent/libplugin.so".into(),
+            config: serde_json::Value::Null,
+        };
+        assert!(matches!(load_plugin(&config), Err(PluginError::Load(_))));
+
+        // The C library is loaded, but does not export the init function.
+        let config = PluginConfig {
+            path: "libc.so.6".into(),
+            config: serde_json::Value::Null,
+        };
+        assert!(matches!(
+            load_plugin(&config),
+            Err(PluginError::Load(_) | PluginError::MissingInit(_))
+        ));
+    }
+}
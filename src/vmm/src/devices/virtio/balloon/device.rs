@@ -505,6 +505,11 @@ impl Balloon {
         pages_to_mib(self.config_space.num_pages)
     }
 
+    /// Obtain the size of 4K pages the guest driver actually gave to the device in MIB.
+    pub fn actual_mb(&self) -> u32 {
+        pages_to_mib(self.config_space.actual_pages)
+    }
+
     pub fn deflate_on_oom(&self) -> bool {
         self.avail_features & (1u64 << VIRTIO_BALLOON_F_DEFLATE_ON_OOM) != 0
     }
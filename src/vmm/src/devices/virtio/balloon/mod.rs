@@ -7,6 +7,7 @@ pub mod device;
 mod event_handler;
 pub mod metrics;
 pub mod persist;
+pub mod policy;
 pub mod test_utils;
 mod util;
 
@@ -78,12 +79,18 @@ pub enum BalloonError {
     EventFd(std::io::Error),
     /// Guest gave us bad memory addresses: {0}
     GuestMemory(GuestMemoryError),
+    /// Error reading the host memory usage: {0}
+    HostMemInfo(std::io::Error),
+    /// Cannot open /proc/meminfo, which balloon policies need to be available in the jail: {0}
+    OpenHostMemInfo(std::io::Error),
     /// Received error while sending an interrupt: {0}
     InterruptError(std::io::Error),
     /// Guest gave us a malformed descriptor.
     MalformedDescriptor,
     /// Guest gave us a malformed payload.
     MalformedPayload,
+    /// The host memory usage does not report the total and available memory.
+    MalformedHostMemInfo,
     /// Error restoring the balloon device queues.
     QueueRestoreError,
     /// Received stats querry when stats are disabled.
@@ -0,0 +1,300 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Automatic management of the balloon size.
+//!
+//! A [`BalloonPolicy`] computes the balloon target size from the host memory usage, so that
+//! the balloon does not have to be resized through `PATCH /balloon` requests. The policy is
+//! evaluated periodically by a [`BalloonPolicyHandler`] registered with the VMM event loop.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use event_manager::{EventOps, Events, MutEventSubscriber};
+use serde::{Deserialize, Serialize};
+use timerfd::{ClockId, SetTimeFlags, TimerFd, TimerState};
+use vmm_sys_util::epoll::EventSet;
+
+use super::{Balloon, BalloonError};
+use crate::logger::{error, warn};
+
+/// Interval between two evaluations of the balloon policy.
+pub const BALLOON_POLICY_INTERVAL: Duration = Duration::from_secs(5);
+/// Smallest difference between the available and the wanted host memory, in MiB, for which the
+/// balloon is resized.
+pub const BALLOON_POLICY_DEADBAND_MIB: u64 = 32;
+/// Host file reporting the memory usage.
+const HOST_MEMINFO_PATH: &str = "/proc/meminfo";
+
+/// Policy deciding the target size of the balloon.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BalloonPolicy {
+    /// The balloon is only resized through the API.
+    #[default]
+    None,
+    /// Keeps the balloon at a fixed size.
+    Static {
+        /// Target size of the balloon, in MiB.
+        target_mib: u64,
+    },
+    /// Resizes the balloon to keep a share of the host memory available.
+    ProportionalToFreeHost {
+        /// Share of the host memory to keep available, in percent.
+        target_free_pct: u8,
+    },
+}
+
+impl BalloonPolicy {
+    /// Returns `true` if the policy leaves the balloon size to the API.
+    pub fn is_none(&self) -> bool {
+        *self == BalloonPolicy::None
+    }
+
+    /// Returns `true` if the policy parameters are valid.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            BalloonPolicy::ProportionalToFreeHost { target_free_pct } => *target_free_pct <= 100,
+            _ => true,
+        }
+    }
+
+    /// Computes the balloon target size, in MiB, given the host memory usage and the memory
+    /// the guest actually gave to the balloon. The target never exceeds the guest memory size.
+    /// Returns `None` if the balloon keeps its current target.
+    pub fn target_mib(
+        &self,
+        host: &HostMemInfo,
+        actual_mib: u64,
+        guest_mem_mib: u64,
+    ) -> Option<u64> {
+        let target_mib = match *self {
+            BalloonPolicy::None => return None,
+            BalloonPolicy::Static { target_mib } => target_mib,
+            BalloonPolicy::ProportionalToFreeHost { target_free_pct } => {
+                let wanted_mib = host.total_mib * u64::from(target_free_pct) / 100;
+                // Small differences are ignored, so that the balloon does not follow every
+                // fluctuation of the host memory usage.
+                if wanted_mib.abs_diff(host.available_mib) < BALLOON_POLICY_DEADBAND_MIB {
+                    return None;
+                }
+                // The memory reclaimed by the balloon is already accounted as available on the
+                // host, so the balloon only grows by the shortfall, or shrinks by the excess.
+                // Starting from the memory the guest actually gave back, rather than from the
+                // current target, keeps the balloon from overshooting while the guest catches up.
+                if wanted_mib > host.available_mib {
+                    actual_mib + (wanted_mib - host.available_mib)
+                } else {
+                    actual_mib.saturating_sub(host.available_mib - wanted_mib)
+                }
+            }
+        };
+        Some(target_mib.min(guest_mem_mib))
+    }
+}
+
+/// Host memory usage, as reported by `/proc/meminfo`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HostMemInfo {
+    /// Total usable memory, in MiB.
+    pub total_mib: u64,
+    /// Memory available for new allocations without swapping, in MiB.
+    pub available_mib: u64,
+}
+
+impl HostMemInfo {
+    /// Parses the contents of `/proc/meminfo`.
+    pub fn parse(meminfo: &str) -> Option<Self> {
+        let field_mib = |name: &str| {
+            meminfo.lines().find_map(|line| {
+                let value = line.strip_prefix(name)?.strip_prefix(':')?;
+                let kib = value
+                    .trim()
+                    .strip_suffix("kB")?
+                    .trim()
+                    .parse::<u64>()
+                    .ok()?;
+                Some(kib / 1024)
+            })
+        };
+
+        Some(HostMemInfo {
+            total_mib: field_mib("MemTotal")?,
+            available_mib: field_mib("MemAvailable")?,
+        })
+    }
+}
+
+/// Applies a [`BalloonPolicy`] every [`BALLOON_POLICY_INTERVAL`].
+#[derive(Debug)]
+pub struct BalloonPolicyHandler {
+    policy: BalloonPolicy,
+    balloon: Arc<Mutex<Balloon>>,
+    guest_mem_mib: u64,
+    // Kept open so that it can be read again once the seccomp filters are installed.
+    meminfo: File,
+    timer: TimerFd,
+}
+
+impl BalloonPolicyHandler {
+    /// Creates a handler applying `policy` to `balloon`. Fails if the host memory usage cannot be
+    /// read, e.g. because `/proc` is not mounted in the jail.
+    pub fn new(
+        policy: BalloonPolicy,
+        balloon: Arc<Mutex<Balloon>>,
+        guest_mem_mib: u64,
+    ) -> Result<Self, BalloonError> {
+        let meminfo = File::open(HOST_MEMINFO_PATH).map_err(BalloonError::OpenHostMemInfo)?;
+        let mut timer =
+            TimerFd::new_custom(ClockId::Monotonic, true, true).map_err(BalloonError::Timer)?;
+        timer.set_state(
+            TimerState::Periodic {
+                current: BALLOON_POLICY_INTERVAL,
+                interval: BALLOON_POLICY_INTERVAL,
+            },
+            SetTimeFlags::Default,
+        );
+
+        let mut handler = BalloonPolicyHandler {
+            policy,
+            balloon,
+            guest_mem_mib,
+            meminfo,
+            timer,
+        };
+        handler.read_host_meminfo()?;
+        Ok(handler)
+    }
+
+    fn read_host_meminfo(&mut self) -> Result<HostMemInfo, BalloonError> {
+        let mut meminfo = String::new();
+        self.meminfo
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| self.meminfo.read_to_string(&mut meminfo))
+            .map_err(BalloonError::HostMemInfo)?;
+        HostMemInfo::parse(&meminfo).ok_or(BalloonError::MalformedHostMemInfo)
+    }
+
+    fn apply_policy(&mut self) -> Result<(), BalloonError> {
+        let host = self.read_host_meminfo()?;
+        let mut balloon = self.balloon.lock().expect("Poisoned lock");
+        let actual_mib = u64::from(balloon.actual_mb());
+        let Some(target_mib) = self
+            .policy
+            .target_mib(&host, actual_mib, self.guest_mem_mib)
+        else {
+            return Ok(());
+        };
+        if target_mib == u64::from(balloon.size_mb()) {
+            return Ok(());
+        }
+        let target_mib =
+            u32::try_from(target_mib).map_err(|_| BalloonError::TooManyPagesRequested)?;
+        balloon.update_size(target_mib)
+    }
+}
+
+impl MutEventSubscriber for BalloonPolicyHandler {
+    fn process(&mut self, event: Events, _: &mut EventOps) {
+        if event.fd() != self.timer.as_raw_fd() || event.event_set() != EventSet::IN {
+            warn!("Balloon policy: spurious event received: {:?}", event);
+            return;
+        }
+        self.timer.read();
+
+        match self.apply_policy() {
+            // The guest driver did not initialize the balloon yet.
+            Ok(()) | Err(BalloonError::DeviceNotActive) => (),
+            Err(err) => warn!("Balloon policy: failed to resize the balloon: {}", err),
+        }
+    }
+
+    fn init(&mut self, ops: &mut EventOps) {
+        if let Err(err) = ops.add(Events::new(&self.timer, EventSet::IN)) {
+            error!("Failed to register balloon policy timer event: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMINFO: &str = "MemTotal:        8388608 kB\nMemFree:         1048576 \
+                           kB\nMemAvailable:    2097152 kB\nBuffers:          102400 kB\n";
+
+    #[test]
+    fn test_parse_host_meminfo() {
+        assert_eq!(
+            HostMemInfo::parse(MEMINFO).unwrap(),
+            HostMemInfo {
+                total_mib: 8192,
+                available_mib: 2048,
+            }
+        );
+        assert!(HostMemInfo::parse("MemTotal: 1024 kB\n").is_none());
+        assert!(HostMemInfo::parse("MemTotal: 1024 kB\nMemAvailable: x kB\n").is_none());
+    }
+
+    #[test]
+    fn test_policy_target() {
+        let host = HostMemInfo {
+            total_mib: 8192,
+            available_mib: 2048,
+        };
+
+        assert_eq!(BalloonPolicy::None.target_mib(&host, 64, 1024), None);
+
+        let policy = BalloonPolicy::Static { target_mib: 256 };
+        assert_eq!(policy.target_mib(&host, 0, 1024), Some(256));
+        assert_eq!(policy.target_mib(&host, 0, 128), Some(128));
+
+        // 25% of the host memory is already available.
+        let policy = BalloonPolicy::ProportionalToFreeHost {
+            target_free_pct: 25,
+        };
+        assert_eq!(policy.target_mib(&host, 128, 1024), None);
+        // Differences within the deadband are ignored.
+        let close_host = HostMemInfo {
+            total_mib: 8192,
+            available_mib: 2048 - BALLOON_POLICY_DEADBAND_MIB + 1,
+        };
+        assert_eq!(policy.target_mib(&close_host, 128, 1024), None);
+        // The balloon grows by the missing 5% of the host memory.
+        let policy = BalloonPolicy::ProportionalToFreeHost {
+            target_free_pct: 30,
+        };
+        assert_eq!(policy.target_mib(&host, 128, 1024), Some(128 + 409));
+        assert_eq!(policy.target_mib(&host, 128, 512), Some(512));
+        // The balloon shrinks once the host has more memory available than needed.
+        let policy = BalloonPolicy::ProportionalToFreeHost {
+            target_free_pct: 20,
+        };
+        assert_eq!(policy.target_mib(&host, 512, 1024), Some(512 - 410));
+        assert_eq!(policy.target_mib(&host, 128, 1024), Some(0));
+
+        assert!(policy.is_valid());
+        assert!(
+            !BalloonPolicy::ProportionalToFreeHost {
+                target_free_pct: 101
+            }
+            .is_valid()
+        );
+    }
+
+    #[test]
+    fn test_policy_serde() {
+        let policy: BalloonPolicy =
+            serde_json::from_str(r#"{"ProportionalToFreeHost": {"target_free_pct": 20}}"#).unwrap();
+        assert_eq!(
+            policy,
+            BalloonPolicy::ProportionalToFreeHost {
+                target_free_pct: 20
+            }
+        );
+        let policy: BalloonPolicy = serde_json::from_str(r#""None""#).unwrap();
+        assert!(policy.is_none());
+    }
+}
@@ -70,6 +70,8 @@ impl TryFrom<&BlockDeviceConfig> for VhostUserBlockConfig {
             && value.path_on_host.is_none()
             && value.rate_limiter.is_none()
             && value.file_engine_type.is_none()
+            && value.lifetime.is_none()
+            && value.discard.is_none()
         {
             Ok(Self {
                 drive_id: value.drive_id.clone(),
@@ -97,6 +99,8 @@ impl From<VhostUserBlockConfig> for BlockDeviceConfig {
             path_on_host: None,
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: Some(value.socket),
         }
@@ -392,6 +396,8 @@ mod tests {
             path_on_host: None,
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: Some("sock".to_string()),
         };
@@ -407,6 +413,8 @@ mod tests {
             path_on_host: Some("path".to_string()),
             rate_limiter: None,
             file_engine_type: Some(FileEngineType::Sync),
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -422,6 +430,8 @@ mod tests {
             path_on_host: Some("path".to_string()),
             rate_limiter: None,
             file_engine_type: Some(FileEngineType::Sync),
+            lifetime: None,
+            discard: None,
 
             socket: Some("sock".to_string()),
         };
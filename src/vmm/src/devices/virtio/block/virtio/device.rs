@@ -20,12 +20,17 @@ use vmm_sys_util::eventfd::EventFd;
 
 use super::io::async_io;
 use super::request::*;
-use super::{BLOCK_QUEUE_SIZES, SECTOR_SHIFT, SECTOR_SIZE, VirtioBlockError, io as block_io};
+use super::{
+    BLOCK_QUEUE_SIZES, DISCARD_SECTOR_ALIGNMENT, MAX_DISCARD_SECTORS, MAX_DISCARD_SEG,
+    MAX_WRITE_ZEROES_SECTORS, MAX_WRITE_ZEROES_SEG, SECTOR_SHIFT, SECTOR_SIZE,
+    VIRTIO_BLK_F_LIFETIME, VirtioBlockError, io as block_io,
+};
 use crate::devices::virtio::block::CacheType;
 use crate::devices::virtio::block::virtio::metrics::{BlockDeviceMetrics, BlockMetricsPerDevice};
 use crate::devices::virtio::device::{DeviceState, IrqTrigger, IrqType, VirtioDevice};
 use crate::devices::virtio::generated::virtio_blk::{
-    VIRTIO_BLK_F_FLUSH, VIRTIO_BLK_F_RO, VIRTIO_BLK_ID_BYTES,
+    VIRTIO_BLK_F_DISCARD, VIRTIO_BLK_F_FLUSH, VIRTIO_BLK_F_RO, VIRTIO_BLK_F_WRITE_ZEROES,
+    VIRTIO_BLK_ID_BYTES,
 };
 use crate::devices::virtio::generated::virtio_config::VIRTIO_F_VERSION_1;
 use crate::devices::virtio::generated::virtio_ring::VIRTIO_RING_F_EVENT_IDX;
@@ -156,15 +161,101 @@ impl DiskProperties {
     }
 }
 
+/// The configuration space of the block device, laid out as `struct virtio_blk_config` up to
+/// the write zeroes fields. Fields of features which are not offered are left zeroed.
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
 #[repr(C)]
 pub struct ConfigSpace {
     pub capacity: u64,
+    pub size_max: u32,
+    pub seg_max: u32,
+    pub cylinders: u16,
+    pub heads: u8,
+    pub sectors: u8,
+    pub blk_size: u32,
+    pub physical_block_exp: u8,
+    pub alignment_offset: u8,
+    pub min_io_size: u16,
+    pub opt_io_size: u32,
+    pub writeback: u8,
+    pub unused0: u8,
+    pub num_queues: u16,
+    pub max_discard_sectors: u32,
+    pub max_discard_seg: u32,
+    pub discard_sector_alignment: u32,
+    pub max_write_zeroes_sectors: u32,
+    pub max_write_zeroes_seg: u32,
+    pub write_zeroes_may_unmap: u8,
+    pub unused1: [u8; 3],
+    // Overlaps `max_secure_erase_sectors`, which is not offered, so that the structure has no
+    // padding.
+    pub unused2: u32,
+}
+
+impl ConfigSpace {
+    /// Creates the configuration space of a disk of `nsectors` sectors, offering the
+    /// `avail_features` features.
+    pub fn new(nsectors: u64, avail_features: u64) -> Self {
+        let mut config_space = ConfigSpace {
+            capacity: nsectors.to_le(),
+            ..Default::default()
+        };
+        if avail_features & (1u64 << VIRTIO_BLK_F_DISCARD) != 0 {
+            config_space.max_discard_sectors = MAX_DISCARD_SECTORS.to_le();
+            config_space.max_discard_seg = MAX_DISCARD_SEG.to_le();
+            config_space.discard_sector_alignment = DISCARD_SECTOR_ALIGNMENT.to_le();
+        }
+        if avail_features & (1u64 << VIRTIO_BLK_F_WRITE_ZEROES) != 0 {
+            config_space.max_write_zeroes_sectors = MAX_WRITE_ZEROES_SECTORS.to_le();
+            config_space.max_write_zeroes_seg = MAX_WRITE_ZEROES_SEG.to_le();
+            config_space.write_zeroes_may_unmap = 1;
+        }
+        config_space
+    }
 }
 
 // SAFETY: `ConfigSpace` contains only PODs in `repr(C)` or `repr(transparent)`, without padding.
 unsafe impl ByteValued for ConfigSpace {}
 
+/// Wear information of the drive, laid out as `struct virtio_blk_lifetime`. It is reported to the
+/// guest in response to `VIRTIO_BLK_T_GET_LIFETIME` requests.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[repr(C)]
+pub struct DriveLifetime {
+    /// Consumption of the reserved blocks: 0 is undefined, 1 is normal, 2 is a warning (80%
+    /// consumed) and 3 is urgent (90% consumed).
+    pub pre_eol_info: u16,
+    /// Estimated lifetime consumed by the SLC cells, in steps of 10%: 0 is undefined, 1 is
+    /// 0-10%, up to 10 for 90-100%, and 11 is beyond the estimated lifetime.
+    pub device_lifetime_est_typ_a: u16,
+    /// Estimated lifetime consumed by the MLC cells, in the same steps as the SLC cells.
+    pub device_lifetime_est_typ_b: u16,
+}
+
+// SAFETY: `DriveLifetime` contains only PODs in `repr(C)`, without padding.
+unsafe impl ByteValued for DriveLifetime {}
+
+impl DriveLifetime {
+    const MAX_PRE_EOL_INFO: u16 = 3;
+    const MAX_LIFETIME_EST: u16 = 11;
+
+    fn is_valid(&self) -> bool {
+        self.pre_eol_info <= Self::MAX_PRE_EOL_INFO
+            && self.device_lifetime_est_typ_a <= Self::MAX_LIFETIME_EST
+            && self.device_lifetime_est_typ_b <= Self::MAX_LIFETIME_EST
+    }
+
+    /// Returns the lifetime in the little endian byte order of the guest.
+    pub fn to_le(self) -> Self {
+        DriveLifetime {
+            pre_eol_info: self.pre_eol_info.to_le(),
+            device_lifetime_est_typ_a: self.device_lifetime_est_typ_a.to_le(),
+            device_lifetime_est_typ_b: self.device_lifetime_est_typ_b.to_le(),
+        }
+    }
+}
+
 /// Use this structure to set up the Block Device before booting the kernel.
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
@@ -194,6 +285,12 @@ pub struct VirtioBlockConfig {
     #[serde(default)]
     #[serde(rename = "io_engine")]
     pub file_engine_type: FileEngineType,
+    /// Wear information reported to the guest, which is offered the lifetime feature if set.
+    pub lifetime: Option<DriveLifetime>,
+    /// Whether the guest is offered the discard and write zeroes features, if the drive is
+    /// writable.
+    #[serde(default)]
+    pub discard: bool,
 }
 
 impl TryFrom<&BlockDeviceConfig> for VirtioBlockConfig {
@@ -211,6 +308,8 @@ impl TryFrom<&BlockDeviceConfig> for VirtioBlockConfig {
                 path_on_host: value.path_on_host.as_ref().unwrap().clone(),
                 rate_limiter: value.rate_limiter,
                 file_engine_type: value.file_engine_type.unwrap_or_default(),
+                lifetime: value.lifetime,
+                discard: value.discard.unwrap_or(false),
             })
         } else {
             Err(VirtioBlockError::Config)
@@ -230,6 +329,8 @@ impl From<VirtioBlockConfig> for BlockDeviceConfig {
             path_on_host: Some(value.path_on_host),
             rate_limiter: value.rate_limiter,
             file_engine_type: Some(value.file_engine_type),
+            lifetime: value.lifetime,
+            discard: Some(value.discard),
 
             socket: None,
         }
@@ -257,6 +358,8 @@ pub struct VirtioBlock {
     pub cache_type: CacheType,
     pub root_device: bool,
     pub read_only: bool,
+    pub lifetime: Option<DriveLifetime>,
+    pub discard: bool,
 
     // Host file and properties.
     pub disk: DiskProperties,
@@ -282,6 +385,10 @@ impl VirtioBlock {
     ///
     /// The given file must be seekable and sizable.
     pub fn new(config: VirtioBlockConfig) -> Result<VirtioBlock, VirtioBlockError> {
+        if let Some(lifetime) = config.lifetime.filter(|lifetime| !lifetime.is_valid()) {
+            return Err(VirtioBlockError::InvalidLifetime(lifetime));
+        }
+
         let disk_properties = DiskProperties::new(
             config.path_on_host,
             config.is_read_only,
@@ -303,15 +410,19 @@ impl VirtioBlock {
 
         if config.is_read_only {
             avail_features |= 1u64 << VIRTIO_BLK_F_RO;
+        } else if config.discard {
+            avail_features |= (1u64 << VIRTIO_BLK_F_DISCARD) | (1u64 << VIRTIO_BLK_F_WRITE_ZEROES);
         };
 
+        if config.lifetime.is_some() {
+            avail_features |= 1u64 << VIRTIO_BLK_F_LIFETIME;
+        }
+
         let queue_evts = [EventFd::new(libc::EFD_NONBLOCK).map_err(VirtioBlockError::EventFd)?];
 
         let queues = BLOCK_QUEUE_SIZES.iter().map(|&s| Queue::new(s)).collect();
 
-        let config_space = ConfigSpace {
-            capacity: disk_properties.nsectors.to_le(),
-        };
+        let config_space = ConfigSpace::new(disk_properties.nsectors, avail_features);
 
         Ok(VirtioBlock {
             avail_features,
@@ -329,6 +440,8 @@ impl VirtioBlock {
             cache_type: config.cache_type,
             root_device: config.is_root_device,
             read_only: config.is_read_only,
+            lifetime: config.lifetime,
+            discard: config.discard,
 
             disk: disk_properties,
             rate_limiter,
@@ -349,6 +462,8 @@ impl VirtioBlock {
             cache_type: self.cache_type,
             rate_limiter: rl.into_option(),
             file_engine_type: self.file_engine_type(),
+            lifetime: self.lifetime,
+            discard: self.discard,
         }
     }
 
@@ -423,7 +538,14 @@ impl VirtioBlock {
                     }
 
                     used_any = true;
-                    request.process(&mut self.disk, head.index, mem, &self.metrics)
+                    request.process(
+                        &mut self.disk,
+                        self.lifetime,
+                        self.discard,
+                        head.index,
+                        mem,
+                        &self.metrics,
+                    )
                 }
                 Err(err) => {
                     error!("Failed to parse available descriptor chain: {:?}", err);
@@ -677,12 +799,12 @@ mod tests {
 
     use super::*;
     use crate::check_metric_after_block;
-    use crate::devices::virtio::block::virtio::IO_URING_NUM_ENTRIES;
     use crate::devices::virtio::block::virtio::test_utils::{
         default_block, read_blk_req_descriptors, set_queue, set_rate_limiter,
         simulate_async_completion_event, simulate_queue_and_async_completion_events,
         simulate_queue_event,
     };
+    use crate::devices::virtio::block::virtio::{IO_URING_NUM_ENTRIES, VIRTIO_BLK_T_GET_LIFETIME};
     use crate::devices::virtio::queue::{VIRTQ_DESC_F_NEXT, VIRTQ_DESC_F_WRITE};
     use crate::devices::virtio::test_utils::{VirtQueue, default_mem};
     use crate::rate_limiter::TokenType;
@@ -700,6 +822,8 @@ mod tests {
             path_on_host: Some("path".to_string()),
             rate_limiter: None,
             file_engine_type: Default::default(),
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -715,6 +839,8 @@ mod tests {
             path_on_host: None,
             rate_limiter: None,
             file_engine_type: Default::default(),
+            lifetime: None,
+            discard: None,
 
             socket: Some("sock".to_string()),
         };
@@ -730,6 +856,8 @@ mod tests {
             path_on_host: Some("path".to_string()),
             rate_limiter: None,
             file_engine_type: Default::default(),
+            lifetime: None,
+            discard: None,
 
             socket: Some("sock".to_string()),
         };
@@ -769,7 +897,10 @@ mod tests {
 
             assert_eq!(block.device_type(), TYPE_BLOCK);
 
-            let features: u64 = (1u64 << VIRTIO_F_VERSION_1) | (1u64 << VIRTIO_RING_F_EVENT_IDX);
+            let features: u64 = (1u64 << VIRTIO_F_VERSION_1)
+                | (1u64 << VIRTIO_RING_F_EVENT_IDX)
+                | (1u64 << VIRTIO_BLK_F_DISCARD)
+                | (1u64 << VIRTIO_BLK_F_WRITE_ZEROES);
 
             assert_eq!(
                 block.avail_features_by_page(0),
@@ -785,6 +916,20 @@ mod tests {
                 block.ack_features_by_page(i, u32::MAX);
             }
             assert_eq!(block.acked_features, features);
+
+            // Discard and write zeroes are offered only if the drive allows them.
+            let mut config = block.config();
+            config.discard = false;
+            let block_without_discard = VirtioBlock::new(config).unwrap();
+            assert_eq!(
+                block_without_discard.avail_features,
+                features & !((1u64 << VIRTIO_BLK_F_DISCARD) | (1u64 << VIRTIO_BLK_F_WRITE_ZEROES))
+            );
+            assert_eq!(block_without_discard.config_space.max_discard_sectors, 0);
+            assert_eq!(
+                block_without_discard.config_space.max_write_zeroes_sectors,
+                0
+            );
         }
     }
 
@@ -798,11 +943,24 @@ mod tests {
             // This will read the number of sectors.
             // The block's backing file size is 0x1000, so there are 8 (4096/512) sectors.
             // The config space is little endian.
-            let expected_config_space = ConfigSpace { capacity: 8 };
+            // The block is writable, so the discard and write zeroes limits are advertised as well.
+            let expected_config_space = ConfigSpace {
+                capacity: 8,
+                max_discard_sectors: MAX_DISCARD_SECTORS,
+                max_discard_seg: MAX_DISCARD_SEG,
+                discard_sector_alignment: DISCARD_SECTOR_ALIGNMENT,
+                max_write_zeroes_sectors: MAX_WRITE_ZEROES_SECTORS,
+                max_write_zeroes_seg: MAX_WRITE_ZEROES_SEG,
+                write_zeroes_may_unmap: 1,
+                ..Default::default()
+            };
             assert_eq!(actual_config_space, expected_config_space);
 
             // Invalid read.
-            let expected_config_space = ConfigSpace { capacity: 696969 };
+            let expected_config_space = ConfigSpace {
+                capacity: 696969,
+                ..Default::default()
+            };
             actual_config_space = expected_config_space;
             block.read_config(
                 std::mem::size_of::<ConfigSpace>() as u64 + 1,
@@ -819,7 +977,10 @@ mod tests {
         for engine in [FileEngineType::Sync, FileEngineType::Async] {
             let mut block = default_block(engine);
 
-            let expected_config_space = ConfigSpace { capacity: 696969 };
+            let expected_config_space = ConfigSpace {
+                capacity: 696969,
+                ..Default::default()
+            };
             block.write_config(0, expected_config_space.as_slice());
 
             let mut actual_config_space = ConfigSpace::default();
@@ -829,6 +990,7 @@ mod tests {
             // If priviledged user writes to `/dev/mem`, in block config space - byte by byte.
             let expected_config_space = ConfigSpace {
                 capacity: 0x1122334455667788,
+                ..Default::default()
             };
             let expected_config_space_slice = expected_config_space.as_slice();
             for (i, b) in expected_config_space_slice.iter().enumerate() {
@@ -840,6 +1002,7 @@ mod tests {
             // Invalid write.
             let new_config_space = ConfigSpace {
                 capacity: 0xDEADBEEF,
+                ..Default::default()
             };
             block.write_config(5, new_config_space.as_slice());
             // Make sure nothing got written.
@@ -1009,8 +1172,9 @@ mod tests {
             let request_type_addr = GuestAddress(vq.dtable[0].addr.get());
             let status_addr = GuestAddress(vq.dtable[2].addr.get());
 
-            // Currently only VIRTIO_BLK_T_IN, VIRTIO_BLK_T_OUT,
-            // VIRTIO_BLK_T_FLUSH and VIRTIO_BLK_T_GET_ID  are supported.
+            // Currently only VIRTIO_BLK_T_IN, VIRTIO_BLK_T_OUT, VIRTIO_BLK_T_FLUSH,
+            // VIRTIO_BLK_T_GET_ID, VIRTIO_BLK_T_DISCARD and VIRTIO_BLK_T_WRITE_ZEROES are
+            // supported.
             // Generate an unsupported request.
             let request_header = RequestHeader::new(42, 0);
             mem.write_obj::<RequestHeader>(request_header, request_type_addr)
@@ -1437,6 +1601,291 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_discard() {
+        for engine in [FileEngineType::Sync, FileEngineType::Async] {
+            let mut block = default_block(engine);
+            let mem = default_mem();
+            let vq = VirtQueue::new(GuestAddress(0), &mem, 16);
+            set_queue(&mut block, 0, vq.create_queue());
+            block.activate(mem.clone()).unwrap();
+            read_blk_req_descriptors(&vq);
+
+            let request_type_addr = GuestAddress(vq.dtable[0].addr.get());
+            let data_addr = GuestAddress(vq.dtable[1].addr.get());
+            let status_addr = GuestAddress(vq.dtable[2].addr.get());
+            let segment_len = u32::try_from(size_of::<DiscardWriteZeroesSegment>()).unwrap();
+
+            block
+                .disk
+                .file_engine
+                .file()
+                .write_all(&[0xab; 0x1000])
+                .unwrap();
+            mem.write_obj::<u32>(VIRTIO_BLK_T_DISCARD, request_type_addr)
+                .unwrap();
+            vq.dtable[1].set(data_addr.0, segment_len, VIRTQ_DESC_F_NEXT, 2);
+
+            // Discard the second half of the disk.
+            {
+                mem.write_obj(DiscardWriteZeroesSegment::new(4, 4, 0), data_addr)
+                    .unwrap();
+
+                check_metric_after_block!(
+                    &block.metrics.discard_count,
+                    1,
+                    simulate_queue_and_async_completion_events(&mut block, true)
+                );
+                assert_eq!(vq.used.idx.get(), 1);
+                assert_eq!(vq.used.ring[0].get().id, 0);
+                assert_eq!(vq.used.ring[0].get().len, 1);
+                assert_eq!(mem.read_obj::<u32>(status_addr).unwrap(), VIRTIO_BLK_S_OK);
+
+                let mut buf = vec![];
+                let mut file = block.disk.file_engine.file();
+                file.seek(SeekFrom::Start(0)).unwrap();
+                file.read_to_end(&mut buf).unwrap();
+                // The size of the disk does not change.
+                assert_eq!(buf.len(), 0x1000);
+                assert!(buf[..0x800].iter().all(|b| *b == 0xab));
+                assert!(buf[0x800..].iter().all(|b| *b == 0));
+            }
+
+            // Discard past the end of the disk.
+            {
+                vq.used.idx.set(0);
+                set_queue(&mut block, 0, vq.create_queue());
+                mem.write_obj(DiscardWriteZeroesSegment::new(4, 5, 0), data_addr)
+                    .unwrap();
+
+                simulate_queue_and_async_completion_events(&mut block, true);
+                assert_eq!(vq.used.idx.get(), 1);
+                assert_eq!(vq.used.ring[0].get().len, 1);
+                assert_eq!(
+                    mem.read_obj::<u32>(status_addr).unwrap(),
+                    VIRTIO_BLK_S_IOERR
+                );
+            }
+
+            // Discard with flags, which are not supported.
+            {
+                vq.used.idx.set(0);
+                set_queue(&mut block, 0, vq.create_queue());
+                mem.write_obj(DiscardWriteZeroesSegment::new(0, 4, 1), data_addr)
+                    .unwrap();
+
+                simulate_queue_and_async_completion_events(&mut block, true);
+                assert_eq!(vq.used.idx.get(), 1);
+                assert_eq!(vq.used.ring[0].get().len, 1);
+                assert_eq!(
+                    mem.read_obj::<u32>(status_addr).unwrap(),
+                    VIRTIO_BLK_S_UNSUPP
+                );
+            }
+
+            // Discarding a range consumes as much bandwidth as writing it.
+            {
+                vq.used.idx.set(0);
+                set_queue(&mut block, 0, vq.create_queue());
+                mem.write_obj(DiscardWriteZeroesSegment::new(0, 4, 0), data_addr)
+                    .unwrap();
+                // The bucket holds less than the 2 KiB of the range.
+                let mut rl = RateLimiter::new(0x1000, 0, 100, 0, 0, 0).unwrap();
+                assert!(rl.consume(0x1000 - 0x400, TokenType::Bytes));
+                set_rate_limiter(&mut block, rl);
+
+                check_metric_after_block!(
+                    &block.metrics.rate_limiter_throttled_events,
+                    1,
+                    simulate_queue_event(&mut block, None)
+                );
+                assert!(block.rate_limiter.is_blocked());
+                assert_eq!(vq.used.idx.get(), 0);
+                set_rate_limiter(&mut block, RateLimiter::default());
+            }
+
+            // Drives that do not allow discard reject the requests.
+            {
+                block.discard = false;
+                vq.used.idx.set(0);
+                set_queue(&mut block, 0, vq.create_queue());
+
+                simulate_queue_and_async_completion_events(&mut block, true);
+                assert_eq!(vq.used.idx.get(), 1);
+                assert_eq!(vq.used.ring[0].get().len, 1);
+                assert_eq!(
+                    mem.read_obj::<u32>(status_addr).unwrap(),
+                    VIRTIO_BLK_S_UNSUPP
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_zeroes() {
+        for engine in [FileEngineType::Sync, FileEngineType::Async] {
+            let mut block = default_block(engine);
+            let mem = default_mem();
+            let vq = VirtQueue::new(GuestAddress(0), &mem, 16);
+            set_queue(&mut block, 0, vq.create_queue());
+            block.activate(mem.clone()).unwrap();
+            read_blk_req_descriptors(&vq);
+
+            let request_type_addr = GuestAddress(vq.dtable[0].addr.get());
+            let data_addr = GuestAddress(vq.dtable[1].addr.get());
+            let status_addr = GuestAddress(vq.dtable[2].addr.get());
+            let segment_len = u32::try_from(size_of::<DiscardWriteZeroesSegment>()).unwrap();
+
+            block
+                .disk
+                .file_engine
+                .file()
+                .write_all(&[0xab; 0x1000])
+                .unwrap();
+            mem.write_obj::<u32>(VIRTIO_BLK_T_WRITE_ZEROES, request_type_addr)
+                .unwrap();
+            vq.dtable[1].set(data_addr.0, segment_len, VIRTQ_DESC_F_NEXT, 2);
+
+            // Zero the last quarter of the disk, then the quarter before it allowing the
+            // sectors to be deallocated.
+            for (sector, flags) in [(6, 0), (4, VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP)] {
+                vq.used.idx.set(0);
+                set_queue(&mut block, 0, vq.create_queue());
+                mem.write_obj(DiscardWriteZeroesSegment::new(sector, 2, flags), data_addr)
+                    .unwrap();
+
+                check_metric_after_block!(
+                    &block.metrics.write_zeroes_count,
+                    1,
+                    simulate_queue_and_async_completion_events(&mut block, true)
+                );
+                assert_eq!(vq.used.idx.get(), 1);
+                assert_eq!(vq.used.ring[0].get().id, 0);
+                assert_eq!(vq.used.ring[0].get().len, 1);
+                assert_eq!(mem.read_obj::<u32>(status_addr).unwrap(), VIRTIO_BLK_S_OK);
+            }
+
+            let mut buf = vec![];
+            let mut file = block.disk.file_engine.file();
+            file.seek(SeekFrom::Start(0)).unwrap();
+            file.read_to_end(&mut buf).unwrap();
+            // The size of the disk does not change.
+            assert_eq!(buf.len(), 0x1000);
+            assert!(buf[..0x800].iter().all(|b| *b == 0xab));
+            assert!(buf[0x800..].iter().all(|b| *b == 0));
+
+            // Zero past the end of the disk.
+            {
+                vq.used.idx.set(0);
+                set_queue(&mut block, 0, vq.create_queue());
+                mem.write_obj(DiscardWriteZeroesSegment::new(0, 9, 0), data_addr)
+                    .unwrap();
+
+                simulate_queue_and_async_completion_events(&mut block, true);
+                assert_eq!(vq.used.idx.get(), 1);
+                assert_eq!(vq.used.ring[0].get().len, 1);
+                assert_eq!(
+                    mem.read_obj::<u32>(status_addr).unwrap(),
+                    VIRTIO_BLK_S_IOERR
+                );
+            }
+
+            // Zero with unknown flags.
+            {
+                vq.used.idx.set(0);
+                set_queue(&mut block, 0, vq.create_queue());
+                mem.write_obj(DiscardWriteZeroesSegment::new(0, 4, 2), data_addr)
+                    .unwrap();
+
+                simulate_queue_and_async_completion_events(&mut block, true);
+                assert_eq!(vq.used.idx.get(), 1);
+                assert_eq!(vq.used.ring[0].get().len, 1);
+                assert_eq!(
+                    mem.read_obj::<u32>(status_addr).unwrap(),
+                    VIRTIO_BLK_S_UNSUPP
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_lifetime() {
+        let lifetime = DriveLifetime {
+            pre_eol_info: 2,
+            device_lifetime_est_typ_a: 9,
+            device_lifetime_est_typ_b: 11,
+        };
+
+        for engine in [FileEngineType::Sync, FileEngineType::Async] {
+            let mut block = default_block(engine);
+            assert_eq!(block.avail_features & (1u64 << VIRTIO_BLK_F_LIFETIME), 0);
+
+            // The feature is offered only for drives with a valid lifetime.
+            let mut config = block.config();
+            config.lifetime = Some(DriveLifetime {
+                pre_eol_info: 4,
+                ..lifetime
+            });
+            assert!(matches!(
+                VirtioBlock::new(config),
+                Err(VirtioBlockError::InvalidLifetime(_))
+            ));
+            let mut config = block.config();
+            config.lifetime = Some(lifetime);
+            let block_with_lifetime = VirtioBlock::new(config).unwrap();
+            assert_ne!(
+                block_with_lifetime.avail_features & (1u64 << VIRTIO_BLK_F_LIFETIME),
+                0
+            );
+            assert_eq!(block_with_lifetime.config().lifetime, Some(lifetime));
+
+            let mem = default_mem();
+            let vq = VirtQueue::new(GuestAddress(0), &mem, 16);
+            set_queue(&mut block, 0, vq.create_queue());
+            block.activate(mem.clone()).unwrap();
+            read_blk_req_descriptors(&vq);
+
+            let request_type_addr = GuestAddress(vq.dtable[0].addr.get());
+            let data_addr = GuestAddress(vq.dtable[1].addr.get());
+            let status_addr = GuestAddress(vq.dtable[2].addr.get());
+            let lifetime_len = u32::try_from(size_of::<DriveLifetime>()).unwrap();
+
+            mem.write_obj::<u32>(VIRTIO_BLK_T_GET_LIFETIME, request_type_addr)
+                .unwrap();
+            vq.dtable[1].set(
+                data_addr.0,
+                lifetime_len,
+                VIRTQ_DESC_F_NEXT | VIRTQ_DESC_F_WRITE,
+                2,
+            );
+
+            // The drive has no lifetime.
+            {
+                simulate_queue_and_async_completion_events(&mut block, true);
+                assert_eq!(vq.used.idx.get(), 1);
+                assert_eq!(vq.used.ring[0].get().len, 1);
+                assert_eq!(
+                    mem.read_obj::<u32>(status_addr).unwrap(),
+                    VIRTIO_BLK_S_UNSUPP
+                );
+            }
+
+            // The driver receives the lifetime of the drive.
+            {
+                block.lifetime = Some(lifetime);
+                vq.used.idx.set(0);
+                set_queue(&mut block, 0, vq.create_queue());
+
+                simulate_queue_and_async_completion_events(&mut block, true);
+                assert_eq!(vq.used.idx.get(), 1);
+                assert_eq!(vq.used.ring[0].get().id, 0);
+                assert_eq!(vq.used.ring[0].get().len, lifetime_len + 1);
+                assert_eq!(mem.read_obj::<u32>(status_addr).unwrap(), VIRTIO_BLK_S_OK);
+                assert_eq!(mem.read_obj::<DriveLifetime>(data_addr).unwrap(), lifetime);
+            }
+        }
+    }
+
     #[test]
     fn test_get_device_id() {
         for engine in [FileEngineType::Sync, FileEngineType::Async] {
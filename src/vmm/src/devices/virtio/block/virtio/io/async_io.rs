@@ -9,7 +9,7 @@ use std::os::unix::io::AsRawFd;
 use vm_memory::GuestMemoryError;
 use vmm_sys_util::eventfd::EventFd;
 
-use crate::devices::virtio::block::virtio::io::RequestError;
+use crate::devices::virtio::block::virtio::io::{RequestError, punch_hole, write_zeroes};
 use crate::devices::virtio::block::virtio::{IO_URING_NUM_ENTRIES, PendingRequest};
 use crate::io_uring::operation::{Cqe, OpCode, Operation};
 use crate::io_uring::restriction::Restriction;
@@ -19,6 +19,10 @@ use crate::vstate::memory::{GuestAddress, GuestMemory, GuestMemoryExtension, Gue
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
 pub enum AsyncIoError {
+    /// Discard: {0}
+    Discard(std::io::Error),
+    /// Write zeroes: {0}
+    WriteZeroes(std::io::Error),
     /// IO: {0}
     IO(std::io::Error),
     /// IoUring: {0}
@@ -198,6 +202,14 @@ impl AsyncFileEngine {
             })
     }
 
+    pub fn discard(&mut self, offset: u64, len: u64) -> Result<(), AsyncIoError> {
+        punch_hole(&self.file, offset, len).map_err(AsyncIoError::Discard)
+    }
+
+    pub fn write_zeroes(&mut self, offset: u64, len: u64, unmap: bool) -> Result<(), AsyncIoError> {
+        write_zeroes(&self.file, offset, len, unmap).map_err(AsyncIoError::WriteZeroes)
+    }
+
     pub fn kick_submission_queue(&mut self) -> Result<(), AsyncIoError> {
         self.ring
             .submit()
@@ -6,6 +6,8 @@ pub mod sync_io;
 
 use std::fmt::Debug;
 use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
 
 pub use self::async_io::{AsyncFileEngine, AsyncIoError};
 pub use self::sync_io::{SyncFileEngine, SyncIoError};
@@ -40,6 +42,55 @@ impl BlockIoError {
             _ => false,
         }
     }
+
+    /// Whether the backing file does not support deallocating or zeroing a range.
+    pub fn is_unsupported_err(&self) -> bool {
+        match self {
+            BlockIoError::Sync(SyncIoError::Discard(err) | SyncIoError::WriteZeroes(err))
+            | BlockIoError::Async(AsyncIoError::Discard(err) | AsyncIoError::WriteZeroes(err)) => {
+                err.raw_os_error() == Some(libc::EOPNOTSUPP)
+            }
+            _ => false,
+        }
+    }
+}
+
+// Manipulates the allocated space of `len` bytes of `file` starting at `offset`, as selected by
+// the fallocate `mode`. The size of the file is left unchanged.
+fn fallocate(file: &File, mode: libc::c_int, offset: u64, len: u64) -> Result<(), io::Error> {
+    let offset = i64::try_from(offset).map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?;
+    let len = i64::try_from(len).map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?;
+    // SAFETY: `file` is a valid file descriptor, and `fallocate` does not access our memory.
+    let ret = unsafe {
+        libc::fallocate(
+            file.as_raw_fd(),
+            mode | libc::FALLOC_FL_KEEP_SIZE,
+            offset,
+            len,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Deallocates `len` bytes of `file` starting at `offset`, which read back as zeroes afterwards.
+/// The size of the file is left unchanged.
+pub fn punch_hole(file: &File, offset: u64, len: u64) -> Result<(), io::Error> {
+    fallocate(file, libc::FALLOC_FL_PUNCH_HOLE, offset, len)
+}
+
+/// Zeroes `len` bytes of `file` starting at `offset`, deallocating them if `unmap` is set. Fails
+/// with `EOPNOTSUPP` when the filesystem does not support it. The size of the file is left
+/// unchanged.
+pub fn write_zeroes(file: &File, offset: u64, len: u64, unmap: bool) -> Result<(), io::Error> {
+    let mode = if unmap {
+        libc::FALLOC_FL_PUNCH_HOLE
+    } else {
+        libc::FALLOC_FL_ZERO_RANGE
+    };
+    fallocate(file, mode, offset, len)
 }
 
 #[derive(Debug)]
@@ -157,6 +208,29 @@ impl FileEngine {
         }
     }
 
+    /// Deallocates `len` bytes of the backing file starting at `offset`. Both engines do it with
+    /// a blocking system call, as the io_uring of the async engine is restricted to reads, writes
+    /// and fsyncs.
+    pub fn discard(&mut self, offset: u64, len: u64) -> Result<(), BlockIoError> {
+        match self {
+            FileEngine::Async(engine) => engine.discard(offset, len).map_err(BlockIoError::Async),
+            FileEngine::Sync(engine) => engine.discard(offset, len).map_err(BlockIoError::Sync),
+        }
+    }
+
+    /// Zeroes `len` bytes of the backing file starting at `offset`, deallocating them if `unmap`
+    /// is set. Like discards, this is a blocking system call for both engines.
+    pub fn write_zeroes(&mut self, offset: u64, len: u64, unmap: bool) -> Result<(), BlockIoError> {
+        match self {
+            FileEngine::Async(engine) => engine
+                .write_zeroes(offset, len, unmap)
+                .map_err(BlockIoError::Async),
+            FileEngine::Sync(engine) => engine
+                .write_zeroes(offset, len, unmap)
+                .map_err(BlockIoError::Sync),
+        }
+    }
+
     pub fn drain(&mut self, discard: bool) -> Result<(), BlockIoError> {
         match self {
             FileEngine::Async(engine) => engine.drain(discard).map_err(BlockIoError::Async),
@@ -178,6 +252,7 @@ impl FileEngine {
 pub mod tests {
     #![allow(clippy::undocumented_unsafe_blocks)]
     use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::FileExt;
 
     use vmm_sys_util::tempfile::TempFile;
 
@@ -325,6 +400,25 @@ pub mod tests {
         mem.read_slice(&mut buf, GuestAddress(0)).unwrap();
         assert_eq!(buf, data.as_slice());
 
+        // Discard the first half of the file, which then reads back as zeroes.
+        let half_len = FILE_LEN as usize / 2;
+        engine.discard(0, half_len as u64).unwrap();
+        let mem = create_mem();
+        assert_sync_execution!(
+            engine.read(
+                0,
+                &mem,
+                GuestAddress(0),
+                FILE_LEN,
+                PendingRequest::default()
+            ),
+            FILE_LEN
+        );
+        mem.read_slice(&mut buf, GuestAddress(0)).unwrap();
+        assert!(buf[..half_len].iter().all(|&b| b == 0));
+        assert_eq!(buf[half_len..], data[half_len..]);
+        assert_eq!(engine.file().metadata().unwrap().len(), u64::from(FILE_LEN));
+
         // Check other ops
         engine.flush(PendingRequest::default()).unwrap();
         engine.drain(true).unwrap();
@@ -381,6 +475,17 @@ pub mod tests {
         check_dirty_mem(&mem, addr, FILE_LEN);
         check_clean_mem(&mem, GuestAddress(4096), 4096);
 
+        // Discard the first half of the file, which then reads back as zeroes.
+        let half_len = FILE_LEN as usize / 2;
+        engine.discard(0, half_len as u64).unwrap();
+        let mem = create_mem();
+        assert_queued!(engine.read(0, &mem, addr, FILE_LEN, PendingRequest::default()));
+        assert_async_execution(&mem, &mut engine, FILE_LEN);
+        mem.read_slice(&mut buf, GuestAddress(0)).unwrap();
+        assert!(buf[..half_len].iter().all(|&b| b == 0));
+        assert_eq!(buf[half_len..], data[half_len..]);
+        assert_eq!(engine.file().metadata().unwrap().len(), u64::from(FILE_LEN));
+
         // Check other ops
         assert_queued!(engine.flush(PendingRequest::default()));
         assert_async_execution(&mem, &mut engine, 0);
@@ -388,4 +493,25 @@ pub mod tests {
         engine.drain(true).unwrap();
         engine.drain_and_flush(true).unwrap();
     }
+
+    #[test]
+    fn test_write_zeroes() {
+        let file = TempFile::new().unwrap().into_file();
+        let data = vmm_sys_util::rand::rand_alphanumerics(FILE_LEN as usize)
+            .as_bytes()
+            .to_vec();
+        file.write_all_at(&data, 0).unwrap();
+
+        // Zero the first quarter of the file and deallocate the second one.
+        let quarter_len = u64::from(FILE_LEN) / 4;
+        write_zeroes(&file, 0, quarter_len, false).unwrap();
+        write_zeroes(&file, quarter_len, quarter_len, true).unwrap();
+
+        let mut buf = vec![0u8; FILE_LEN as usize];
+        file.read_exact_at(&mut buf, 0).unwrap();
+        let zeroed_len = u64_to_usize(2 * quarter_len);
+        assert!(buf[..zeroed_len].iter().all(|&b| b == 0));
+        assert_eq!(buf[zeroed_len..], data[zeroed_len..]);
+        assert_eq!(file.metadata().unwrap().len(), u64::from(FILE_LEN));
+    }
 }
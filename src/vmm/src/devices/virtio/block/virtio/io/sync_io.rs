@@ -6,10 +6,15 @@ use std::io::{Seek, SeekFrom, Write};
 
 use vm_memory::{GuestMemoryError, ReadVolatile, WriteVolatile};
 
+use super::{punch_hole, write_zeroes};
 use crate::vstate::memory::{GuestAddress, GuestMemory, GuestMemoryMmap};
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
 pub enum SyncIoError {
+    /// Discard: {0}
+    Discard(std::io::Error),
+    /// Write zeroes: {0}
+    WriteZeroes(std::io::Error),
     /// Flush: {0}
     Flush(std::io::Error),
     /// Seek: {0}
@@ -69,12 +74,22 @@ impl SyncFileEngine {
         self.file
             .seek(SeekFrom::Start(offset))
             .map_err(SyncIoError::Seek)?;
+        // The guest memory is mapped in this process, so the data is written to the file
+        // straight from the guest buffer, without an intermediate copy.
         mem.get_slice(addr, count as usize)
             .and_then(|slice| Ok(self.file.write_all_volatile(&slice)?))
             .map_err(SyncIoError::Transfer)?;
         Ok(count)
     }
 
+    pub fn discard(&mut self, offset: u64, len: u64) -> Result<(), SyncIoError> {
+        punch_hole(&self.file, offset, len).map_err(SyncIoError::Discard)
+    }
+
+    pub fn write_zeroes(&mut self, offset: u64, len: u64, unmap: bool) -> Result<(), SyncIoError> {
+        write_zeroes(&self.file, offset, len, unmap).map_err(SyncIoError::WriteZeroes)
+    }
+
     pub fn flush(&mut self) -> Result<(), SyncIoError> {
         // flush() first to force any cached data out of rust buffers.
         self.file.flush().map_err(SyncIoError::Flush)?;
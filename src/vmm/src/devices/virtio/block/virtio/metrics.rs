@@ -84,7 +84,7 @@ use std::sync::{Arc, RwLock};
 use serde::ser::SerializeMap;
 use serde::{Serialize, Serializer};
 
-use crate::logger::{IncMetric, LatencyAggregateMetrics, SharedIncMetric};
+use crate::logger::{IncMetric, LatencyAggregateMetrics, LatencyHistogramMetrics, SharedIncMetric};
 
 /// map of block drive id and metrics
 /// this should be protected by a lock before accessing.
@@ -157,6 +157,10 @@ pub struct BlockDeviceMetrics {
     pub invalid_reqs_count: SharedIncMetric,
     /// Number of flushes operation triggered on this block device.
     pub flush_count: SharedIncMetric,
+    /// Number of discard operations triggered on this block device.
+    pub discard_count: SharedIncMetric,
+    /// Number of write zeroes operations triggered on this block device.
+    pub write_zeroes_count: SharedIncMetric,
     /// Number of events triggered on the queue of this block device.
     pub queue_event_count: SharedIncMetric,
     /// Number of events ratelimiter-related.
@@ -177,6 +181,10 @@ pub struct BlockDeviceMetrics {
     pub read_agg: LatencyAggregateMetrics,
     /// Duration of all write operations.
     pub write_agg: LatencyAggregateMetrics,
+    /// Distribution of read request latencies, from the virtqueue to the IO engine completion.
+    pub read_latency_hist: LatencyHistogramMetrics,
+    /// Distribution of write request latencies, from the virtqueue to the IO engine completion.
+    pub write_latency_hist: LatencyHistogramMetrics,
     /// Number of rate limiter throttling events.
     pub rate_limiter_throttled_events: SharedIncMetric,
     /// Number of virtio events throttled because of the IO engine.
@@ -192,6 +200,8 @@ impl BlockDeviceMetrics {
         Self {
             read_agg: LatencyAggregateMetrics::new(),
             write_agg: LatencyAggregateMetrics::new(),
+            read_latency_hist: LatencyHistogramMetrics::new(),
+            write_latency_hist: LatencyHistogramMetrics::new(),
             ..Default::default()
         }
     }
@@ -210,6 +220,9 @@ impl BlockDeviceMetrics {
         self.invalid_reqs_count
             .add(other.invalid_reqs_count.fetch_diff());
         self.flush_count.add(other.flush_count.fetch_diff());
+        self.discard_count.add(other.discard_count.fetch_diff());
+        self.write_zeroes_count
+            .add(other.write_zeroes_count.fetch_diff());
         self.queue_event_count
             .add(other.queue_event_count.fetch_diff());
         self.rate_limiter_event_count
@@ -224,6 +237,8 @@ impl BlockDeviceMetrics {
         self.write_agg
             .sum_us
             .add(other.write_agg.sum_us.fetch_diff());
+        self.read_latency_hist.aggregate(&other.read_latency_hist);
+        self.write_latency_hist.aggregate(&other.write_latency_hist);
         self.rate_limiter_throttled_events
             .add(other.rate_limiter_throttled_events.fetch_diff());
         self.io_engine_throttled_events
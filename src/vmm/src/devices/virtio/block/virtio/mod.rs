@@ -22,6 +22,21 @@ use crate::devices::virtio::queue::FIRECRACKER_MAX_QUEUE_SIZE;
 pub const SECTOR_SHIFT: u8 = 9;
 /// Size of block sector.
 pub const SECTOR_SIZE: u32 = (0x01_u32) << SECTOR_SHIFT;
+/// Maximum number of sectors of a discard request, 32 MiB.
+pub const MAX_DISCARD_SECTORS: u32 = (32 << 20) >> SECTOR_SHIFT;
+/// Maximum number of segments of a discard request.
+pub const MAX_DISCARD_SEG: u32 = 1;
+/// Alignment of the sectors of discard requests, so that they cover whole 4 KiB host pages.
+pub const DISCARD_SECTOR_ALIGNMENT: u32 = 4096 >> SECTOR_SHIFT;
+/// Maximum number of sectors of a write zeroes request, 32 MiB.
+pub const MAX_WRITE_ZEROES_SECTORS: u32 = (32 << 20) >> SECTOR_SHIFT;
+/// Maximum number of segments of a write zeroes request.
+pub const MAX_WRITE_ZEROES_SEG: u32 = 1;
+/// Feature bit of the drive lifetime. Not part of the generated bindings, as the Linux UAPI
+/// headers do not define it.
+pub const VIRTIO_BLK_F_LIFETIME: u32 = 15;
+/// Request type to get the lifetime of the drive.
+pub const VIRTIO_BLK_T_GET_LIFETIME: u32 = 10;
 /// The number of queues of block device.
 pub const BLOCK_NUM_QUEUES: usize = 1;
 pub const BLOCK_QUEUE_SIZES: [u16; BLOCK_NUM_QUEUES] = [FIRECRACKER_MAX_QUEUE_SIZE];
@@ -47,6 +62,8 @@ pub enum VirtioBlockError {
     InvalidDataLength,
     /// The requested operation would cause a seek beyond disk end.
     InvalidOffset,
+    /// Invalid drive lifetime: {0:?}
+    InvalidLifetime(device::DriveLifetime),
     /// Guest gave us a read only descriptor that protocol says to write to.
     UnexpectedReadOnlyDescriptor,
     /// Guest gave us a write only descriptor that protocol says to read from.
@@ -10,14 +10,14 @@ use device::ConfigSpace;
 use serde::{Deserialize, Serialize};
 use vmm_sys_util::eventfd::EventFd;
 
-use super::device::DiskProperties;
+use super::device::{DiskProperties, DriveLifetime};
 use super::*;
 use crate::devices::virtio::TYPE_BLOCK;
 use crate::devices::virtio::block::persist::BlockConstructorArgs;
 use crate::devices::virtio::block::virtio::device::FileEngineType;
 use crate::devices::virtio::block::virtio::metrics::BlockMetricsPerDevice;
 use crate::devices::virtio::device::{DeviceState, IrqTrigger};
-use crate::devices::virtio::generated::virtio_blk::VIRTIO_BLK_F_RO;
+use crate::devices::virtio::generated::virtio_blk::{VIRTIO_BLK_F_DISCARD, VIRTIO_BLK_F_RO};
 use crate::devices::virtio::persist::VirtioDeviceState;
 use crate::rate_limiter::RateLimiter;
 use crate::rate_limiter::persist::RateLimiterState;
@@ -64,6 +64,7 @@ pub struct VirtioBlockState {
     virtio_state: VirtioDeviceState,
     rate_limiter_state: RateLimiterState,
     file_engine_type: FileEngineTypeState,
+    lifetime: Option<DriveLifetime>,
 }
 
 impl Persist<'_> for VirtioBlock {
@@ -82,6 +83,7 @@ impl Persist<'_> for VirtioBlock {
             virtio_state: VirtioDeviceState::from_device(self),
             rate_limiter_state: self.rate_limiter.save(),
             file_engine_type: FileEngineTypeState::from(self.file_engine_type()),
+            lifetime: self.lifetime,
         }
     }
 
@@ -90,6 +92,7 @@ impl Persist<'_> for VirtioBlock {
         state: &Self::State,
     ) -> Result<Self, Self::Error> {
         let is_read_only = state.virtio_state.avail_features & (1u64 << VIRTIO_BLK_F_RO) != 0;
+        let discard = state.virtio_state.avail_features & (1u64 << VIRTIO_BLK_F_DISCARD) != 0;
         let rate_limiter = RateLimiter::restore((), &state.rate_limiter_state)
             .map_err(VirtioBlockError::RateLimiter)?;
 
@@ -123,9 +126,7 @@ impl Persist<'_> for VirtioBlock {
             DeviceState::Inactive
         };
 
-        let config_space = ConfigSpace {
-            capacity: disk_properties.nsectors.to_le(),
-        };
+        let config_space = ConfigSpace::new(disk_properties.nsectors, avail_features);
 
         Ok(VirtioBlock {
             avail_features,
@@ -143,6 +144,8 @@ impl Persist<'_> for VirtioBlock {
             cache_type: state.cache_type,
             root_device: state.root_device,
             read_only: is_read_only,
+            lifetime: state.lifetime,
+            discard,
 
             disk: disk_properties,
             rate_limiter,
@@ -179,6 +182,8 @@ mod tests {
             cache_type: CacheType::Writeback,
             rate_limiter: None,
             file_engine_type: FileEngineType::default(),
+            lifetime: None,
+            discard: false,
         };
 
         let block = VirtioBlock::new(config).unwrap();
@@ -221,6 +226,12 @@ mod tests {
             cache_type: CacheType::Unsafe,
             rate_limiter: None,
             file_engine_type: FileEngineType::default(),
+            lifetime: Some(DriveLifetime {
+                pre_eol_info: 1,
+                device_lifetime_est_typ_a: 2,
+                device_lifetime_est_typ_b: 3,
+            }),
+            discard: true,
         };
 
         let block = VirtioBlock::new(config).unwrap();
@@ -251,5 +262,7 @@ mod tests {
 
         // Test that block specific fields are the same.
         assert_eq!(restored_block.disk.file_path, block.disk.file_path);
+        assert_eq!(restored_block.lifetime, block.lifetime);
+        assert_eq!(restored_block.discard, block.discard);
     }
 }
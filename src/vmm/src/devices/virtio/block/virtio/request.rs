@@ -7,14 +7,19 @@
 
 use std::convert::From;
 
+use utils::time::{ClockType, get_time_us};
 use vm_memory::GuestMemoryError;
 
-use super::{SECTOR_SHIFT, SECTOR_SIZE, VirtioBlockError, io as block_io};
-use crate::devices::virtio::block::virtio::device::DiskProperties;
+use super::{
+    MAX_DISCARD_SECTORS, MAX_DISCARD_SEG, MAX_WRITE_ZEROES_SECTORS, MAX_WRITE_ZEROES_SEG,
+    SECTOR_SHIFT, SECTOR_SIZE, VIRTIO_BLK_T_GET_LIFETIME, VirtioBlockError, io as block_io,
+};
+use crate::devices::virtio::block::virtio::device::{DiskProperties, DriveLifetime};
 use crate::devices::virtio::block::virtio::metrics::BlockDeviceMetrics;
 pub use crate::devices::virtio::generated::virtio_blk::{
     VIRTIO_BLK_ID_BYTES, VIRTIO_BLK_S_IOERR, VIRTIO_BLK_S_OK, VIRTIO_BLK_S_UNSUPP,
-    VIRTIO_BLK_T_FLUSH, VIRTIO_BLK_T_GET_ID, VIRTIO_BLK_T_IN, VIRTIO_BLK_T_OUT,
+    VIRTIO_BLK_T_DISCARD, VIRTIO_BLK_T_FLUSH, VIRTIO_BLK_T_GET_ID, VIRTIO_BLK_T_IN,
+    VIRTIO_BLK_T_OUT, VIRTIO_BLK_T_WRITE_ZEROES, VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP,
 };
 use crate::devices::virtio::queue::DescriptorChain;
 use crate::logger::{IncMetric, error};
@@ -24,8 +29,33 @@ use crate::vstate::memory::{ByteValued, Bytes, GuestAddress, GuestMemoryMmap};
 #[derive(Debug, derive_more::From)]
 pub enum IoErr {
     GetId(GuestMemoryError),
-    PartialTransfer { completed: u32, expected: u32 },
+    #[from(skip)]
+    GetLifetime(GuestMemoryError),
+    PartialTransfer {
+        completed: u32,
+        expected: u32,
+    },
     FileEngine(block_io::BlockIoError),
+    InvalidSegment {
+        sector: u64,
+        num_sectors: u32,
+    },
+    #[from(skip)]
+    UnsupportedFlags(u32),
+    UnsupportedLifetime,
+    UnsupportedDiscard,
+}
+
+impl IoErr {
+    // Whether a discard or write zeroes request is not supported by the drive or by its backing
+    // file, in which case the driver can fall back to writing zeroes.
+    fn is_unsupported(&self) -> bool {
+        match self {
+            IoErr::UnsupportedFlags(_) | IoErr::UnsupportedDiscard => true,
+            IoErr::FileEngine(err) => err.is_unsupported_err(),
+            _ => false,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -34,6 +64,9 @@ pub enum RequestType {
     Out,
     Flush,
     GetDeviceID,
+    Discard,
+    WriteZeroes,
+    GetLifetime,
     Unsupported(u32),
 }
 
@@ -44,6 +77,9 @@ impl From<u32> for RequestType {
             VIRTIO_BLK_T_OUT => RequestType::Out,
             VIRTIO_BLK_T_FLUSH => RequestType::Flush,
             VIRTIO_BLK_T_GET_ID => RequestType::GetDeviceID,
+            VIRTIO_BLK_T_DISCARD => RequestType::Discard,
+            VIRTIO_BLK_T_WRITE_ZEROES => RequestType::WriteZeroes,
+            VIRTIO_BLK_T_GET_LIFETIME => RequestType::GetLifetime,
             t => RequestType::Unsupported(t),
         }
     }
@@ -95,6 +131,8 @@ pub struct PendingRequest {
     data_len: u32,
     status_addr: GuestAddress,
     desc_idx: u16,
+    /// Time at which the request was taken off the virtqueue, in microseconds.
+    start_us: u64,
 }
 
 impl PendingRequest {
@@ -150,6 +188,13 @@ impl PendingRequest {
         res: Result<u32, IoErr>,
         block_metrics: &BlockDeviceMetrics,
     ) -> FinishedRequest {
+        let latency_us = get_time_us(ClockType::Monotonic) - self.start_us;
+        match self.r#type {
+            RequestType::In => block_metrics.read_latency_hist.record(latency_us),
+            RequestType::Out => block_metrics.write_latency_hist.record(latency_us),
+            _ => (),
+        }
+
         let status = match (res, self.r#type) {
             (Ok(transferred_data_len), RequestType::In) => {
                 let status = Status::from_data(self.data_len, transferred_data_len, true);
@@ -173,10 +218,31 @@ impl PendingRequest {
                     num_bytes_to_mem: 0,
                 }
             }
-            (Ok(transferred_data_len), RequestType::GetDeviceID) => {
+            (Ok(transferred_data_len), RequestType::GetDeviceID | RequestType::GetLifetime) => {
                 Status::from_data(self.data_len, transferred_data_len, true)
             }
+            (Ok(_), RequestType::Discard) => {
+                block_metrics.discard_count.inc();
+                Status::Ok {
+                    num_bytes_to_mem: 0,
+                }
+            }
+            (Ok(_), RequestType::WriteZeroes) => {
+                block_metrics.write_zeroes_count.inc();
+                Status::Ok {
+                    num_bytes_to_mem: 0,
+                }
+            }
             (_, RequestType::Unsupported(op)) => Status::Unsupported { op },
+            (Err(err), RequestType::Discard) if err.is_unsupported() => Status::Unsupported {
+                op: VIRTIO_BLK_T_DISCARD,
+            },
+            (Err(err), RequestType::WriteZeroes) if err.is_unsupported() => Status::Unsupported {
+                op: VIRTIO_BLK_T_WRITE_ZEROES,
+            },
+            (Err(IoErr::UnsupportedLifetime), RequestType::GetLifetime) => Status::Unsupported {
+                op: VIRTIO_BLK_T_GET_LIFETIME,
+            },
             (Err(err), _) => Status::IoErr {
                 num_bytes_to_mem: 0,
                 err,
@@ -231,6 +297,34 @@ impl RequestHeader {
     }
 }
 
+/// A segment of a discard or write zeroes request, which is the data of the request.
+///
+/// A segment contains the following fields:
+///   * sector: an u64 value representing the first sector to discard or zero.
+///   * num_sectors: an u32 value representing the number of sectors to discard or zero.
+///   * flags: 32 bits of flags, of which only `VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP` is supported,
+///     for write zeroes requests.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct DiscardWriteZeroesSegment {
+    sector: u64,
+    num_sectors: u32,
+    flags: u32,
+}
+
+// SAFETY: Safe because DiscardWriteZeroesSegment only contains plain data.
+unsafe impl ByteValued for DiscardWriteZeroesSegment {}
+
+impl DiscardWriteZeroesSegment {
+    pub fn new(sector: u64, num_sectors: u32, flags: u32) -> DiscardWriteZeroesSegment {
+        DiscardWriteZeroesSegment {
+            sector,
+            num_sectors,
+            flags,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Request {
     pub r#type: RequestType,
@@ -238,6 +332,9 @@ pub struct Request {
     pub status_addr: GuestAddress,
     sector: u64,
     data_addr: GuestAddress,
+    // Segment of a discard or write zeroes request, read from the data once when parsing the
+    // request, so that the guest cannot change it between rate limiting and processing.
+    segment: DiscardWriteZeroesSegment,
 }
 
 impl Request {
@@ -258,6 +355,7 @@ impl Request {
             data_addr: GuestAddress(0),
             data_len: 0,
             status_addr: GuestAddress(0),
+            segment: DiscardWriteZeroesSegment::default(),
         };
 
         let data_desc;
@@ -278,13 +376,23 @@ impl Request {
                 .next_descriptor()
                 .ok_or(VirtioBlockError::DescriptorChainTooShort)?;
 
-            if data_desc.is_write_only() && req.r#type == RequestType::Out {
+            if data_desc.is_write_only()
+                && matches!(
+                    req.r#type,
+                    RequestType::Out | RequestType::Discard | RequestType::WriteZeroes
+                )
+            {
                 return Err(VirtioBlockError::UnexpectedWriteOnlyDescriptor);
             }
             if !data_desc.is_write_only() && req.r#type == RequestType::In {
                 return Err(VirtioBlockError::UnexpectedReadOnlyDescriptor);
             }
-            if !data_desc.is_write_only() && req.r#type == RequestType::GetDeviceID {
+            if !data_desc.is_write_only()
+                && matches!(
+                    req.r#type,
+                    RequestType::GetDeviceID | RequestType::GetLifetime
+                )
+            {
                 return Err(VirtioBlockError::UnexpectedReadOnlyDescriptor);
             }
 
@@ -313,6 +421,33 @@ impl Request {
                     return Err(VirtioBlockError::InvalidDataLength);
                 }
             }
+            RequestType::Discard => {
+                // The data holds as many segments as advertised in `max_discard_seg`.
+                if req.data_len as usize
+                    != MAX_DISCARD_SEG as usize * size_of::<DiscardWriteZeroesSegment>()
+                {
+                    return Err(VirtioBlockError::InvalidDataLength);
+                }
+                req.segment = mem
+                    .read_obj(req.data_addr)
+                    .map_err(VirtioBlockError::GuestMemory)?;
+            }
+            RequestType::GetLifetime => {
+                if req.data_len as usize != size_of::<DriveLifetime>() {
+                    return Err(VirtioBlockError::InvalidDataLength);
+                }
+            }
+            RequestType::WriteZeroes => {
+                // The data holds as many segments as advertised in `max_write_zeroes_seg`.
+                if req.data_len as usize
+                    != MAX_WRITE_ZEROES_SEG as usize * size_of::<DiscardWriteZeroesSegment>()
+                {
+                    return Err(VirtioBlockError::InvalidDataLength);
+                }
+                req.segment = mem
+                    .read_obj(req.data_addr)
+                    .map_err(VirtioBlockError::GuestMemory)?;
+            }
             _ => {}
         }
 
@@ -336,11 +471,19 @@ impl Request {
         if !rate_limiter.consume(1, TokenType::Ops) {
             return true;
         }
-        // Exercise the rate limiter only if this request is of data transfer type.
-        if self.r#type == RequestType::In || self.r#type == RequestType::Out {
+        // Exercise the rate limiter only if this request is of data transfer type. Discarding
+        // or zeroing a range costs as much bandwidth as writing it.
+        let bytes = match self.r#type {
+            RequestType::In | RequestType::Out => Some(u64::from(self.data_len)),
+            RequestType::Discard | RequestType::WriteZeroes => {
+                Some(u64::from(self.segment.num_sectors) << SECTOR_SHIFT)
+            }
+            _ => None,
+        };
+        if let Some(bytes) = bytes {
             // If limiter.consume() fails it means there is no more TokenType::Bytes
             // budget and rate limiting is in effect.
-            if !rate_limiter.consume(u64::from(self.data_len), TokenType::Bytes) {
+            if !rate_limiter.consume(bytes, TokenType::Bytes) {
                 // Revert the OPS consume().
                 rate_limiter.manual_replenish(1, TokenType::Ops);
                 return true;
@@ -354,18 +497,75 @@ impl Request {
         self.sector << SECTOR_SHIFT
     }
 
+    // Checks that the segment of a discard or write zeroes request covers at most `max_sectors`
+    // sectors within the disk and sets no flags but `allowed_flags`.
+    fn check_segment(
+        &self,
+        disk: &DiskProperties,
+        max_sectors: u32,
+        allowed_flags: u32,
+    ) -> Result<DiscardWriteZeroesSegment, IoErr> {
+        let segment = self.segment;
+        if segment.flags & !allowed_flags != 0 {
+            return Err(IoErr::UnsupportedFlags(segment.flags));
+        }
+        let top_sector = segment.sector.checked_add(u64::from(segment.num_sectors));
+        if segment.num_sectors > max_sectors
+            || top_sector.is_none_or(|top_sector| top_sector > disk.nsectors)
+        {
+            return Err(IoErr::InvalidSegment {
+                sector: segment.sector,
+                num_sectors: segment.num_sectors,
+            });
+        }
+        Ok(segment)
+    }
+
+    // Deallocates the sectors covered by the segment of a discard request in the backing file.
+    fn discard(&self, disk: &mut DiskProperties) -> Result<u32, IoErr> {
+        let segment = self.check_segment(disk, MAX_DISCARD_SECTORS, 0)?;
+        disk.file_engine
+            .discard(
+                segment.sector << SECTOR_SHIFT,
+                u64::from(segment.num_sectors) << SECTOR_SHIFT,
+            )
+            .map_err(IoErr::FileEngine)?;
+        Ok(0)
+    }
+
+    // Zeroes the sectors covered by the segment of a write zeroes request in the backing file,
+    // deallocating them if the driver allows it.
+    fn write_zeroes(&self, disk: &mut DiskProperties) -> Result<u32, IoErr> {
+        let segment = self.check_segment(
+            disk,
+            MAX_WRITE_ZEROES_SECTORS,
+            VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP,
+        )?;
+        disk.file_engine
+            .write_zeroes(
+                segment.sector << SECTOR_SHIFT,
+                u64::from(segment.num_sectors) << SECTOR_SHIFT,
+                segment.flags & VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP != 0,
+            )
+            .map_err(IoErr::FileEngine)?;
+        Ok(0)
+    }
+
     fn to_pending_request(&self, desc_idx: u16) -> PendingRequest {
         PendingRequest {
             r#type: self.r#type,
             data_len: self.data_len,
             status_addr: self.status_addr,
             desc_idx,
+            start_us: get_time_us(ClockType::Monotonic),
         }
     }
 
     pub(crate) fn process(
         self,
         disk: &mut DiskProperties,
+        lifetime: Option<DriveLifetime>,
+        discard: bool,
         desc_idx: u16,
         mem: &GuestMemoryMmap,
         block_metrics: &BlockDeviceMetrics,
@@ -390,6 +590,30 @@ impl Request {
                     .map_err(IoErr::GetId);
                 return ProcessingResult::Executed(pending.finish(mem, res, block_metrics));
             }
+            // Drives that do not offer the discard and write zeroes features reject the requests.
+            RequestType::Discard | RequestType::WriteZeroes if !discard => {
+                let res = Err(IoErr::UnsupportedDiscard);
+                return ProcessingResult::Executed(pending.finish(mem, res, block_metrics));
+            }
+            RequestType::Discard => {
+                let res = self.discard(disk);
+                return ProcessingResult::Executed(pending.finish(mem, res, block_metrics));
+            }
+            RequestType::WriteZeroes => {
+                let res = self.write_zeroes(disk);
+                return ProcessingResult::Executed(pending.finish(mem, res, block_metrics));
+            }
+            RequestType::GetLifetime => {
+                // Drives without a configured lifetime do not offer the feature.
+                let res = lifetime
+                    .ok_or(IoErr::UnsupportedLifetime)
+                    .and_then(|lifetime| {
+                        mem.write_obj(lifetime.to_le(), self.data_addr)
+                            .map_err(IoErr::GetLifetime)
+                    })
+                    .map(|_| self.data_len);
+                return ProcessingResult::Executed(pending.finish(mem, res, block_metrics));
+            }
             RequestType::Unsupported(_) => {
                 return ProcessingResult::Executed(pending.finish(mem, Ok(0), block_metrics));
             }
@@ -422,6 +646,7 @@ mod tests {
     use super::*;
     use crate::devices::virtio::queue::{Queue, VIRTQ_DESC_F_NEXT, VIRTQ_DESC_F_WRITE};
     use crate::devices::virtio::test_utils::{VirtQueue, default_mem};
+    use crate::logger::LatencyHistogramMetrics;
     use crate::vstate::memory::{Address, GuestAddress, GuestMemory};
 
     const NUM_DISK_SECTORS: u64 = 1024;
@@ -433,6 +658,7 @@ mod tests {
                 data_len: 0,
                 status_addr: Default::default(),
                 desc_idx: 0,
+                start_us: 0,
             }
         }
     }
@@ -449,6 +675,9 @@ mod tests {
             VIRTIO_BLK_T_OUT,
             VIRTIO_BLK_T_FLUSH,
             VIRTIO_BLK_T_GET_ID,
+            VIRTIO_BLK_T_DISCARD,
+            VIRTIO_BLK_T_WRITE_ZEROES,
+            VIRTIO_BLK_T_GET_LIFETIME,
         ];
 
         for request_type in supported_request_types {
@@ -475,6 +704,18 @@ mod tests {
             RequestType::from(VIRTIO_BLK_T_GET_ID),
             RequestType::GetDeviceID
         );
+        assert_eq!(
+            RequestType::from(VIRTIO_BLK_T_DISCARD),
+            RequestType::Discard
+        );
+        assert_eq!(
+            RequestType::from(VIRTIO_BLK_T_WRITE_ZEROES),
+            RequestType::WriteZeroes
+        );
+        assert_eq!(
+            RequestType::from(VIRTIO_BLK_T_GET_LIFETIME),
+            RequestType::GetLifetime
+        );
         assert_eq!(RequestType::from(42), RequestType::Unsupported(42));
     }
 
@@ -669,6 +910,129 @@ mod tests {
         chain.check_parse(true);
     }
 
+    #[test]
+    fn test_parse_get_lifetime() {
+        let mem = &default_mem();
+        let queue = VirtQueue::new(GuestAddress(0), mem, 16);
+        let chain = RequestDescriptorChain::new(&queue);
+
+        let request_header = RequestHeader::new(VIRTIO_BLK_T_GET_LIFETIME, 0);
+        chain.set_header(request_header);
+
+        // Read only data descriptor for GetLifetime.
+        chain.data_desc.flags.set(VIRTQ_DESC_F_NEXT);
+        chain.check_parse_err(VirtioBlockError::UnexpectedReadOnlyDescriptor);
+
+        // data_len does not match struct virtio_blk_lifetime.
+        let lifetime_len = u32::try_from(size_of::<DriveLifetime>()).unwrap();
+        chain
+            .data_desc
+            .flags
+            .set(VIRTQ_DESC_F_NEXT | VIRTQ_DESC_F_WRITE);
+        chain.data_desc.len.set(lifetime_len + 1);
+        chain.check_parse_err(VirtioBlockError::InvalidDataLength);
+
+        chain.data_desc.len.set(lifetime_len);
+        chain.check_parse(true);
+    }
+
+    #[test]
+    fn test_parse_discard_write_zeroes() {
+        let mem = &default_mem();
+        let queue = VirtQueue::new(GuestAddress(0), mem, 16);
+        let chain = RequestDescriptorChain::new(&queue);
+        let segment_len = u32::try_from(size_of::<DiscardWriteZeroesSegment>()).unwrap();
+
+        for request_type in [VIRTIO_BLK_T_DISCARD, VIRTIO_BLK_T_WRITE_ZEROES] {
+            let request_header = RequestHeader::new(request_type, 0);
+            chain.set_header(request_header);
+
+            // Write only data descriptor for Discard and WriteZeroes.
+            chain
+                .data_desc
+                .flags
+                .set(VIRTQ_DESC_F_NEXT | VIRTQ_DESC_F_WRITE);
+            chain.check_parse_err(VirtioBlockError::UnexpectedWriteOnlyDescriptor);
+
+            // data_len does not match a single segment.
+            chain.data_desc.flags.set(VIRTQ_DESC_F_NEXT);
+            chain.data_desc.len.set(2 * segment_len);
+            chain.check_parse_err(VirtioBlockError::InvalidDataLength);
+
+            chain.data_desc.len.set(segment_len);
+            chain.check_parse(true);
+
+            // The segment is read once, when parsing the request.
+            let segment = DiscardWriteZeroesSegment::new(8, 16, 0);
+            let data_addr = GuestAddress(chain.data_desc.addr.get());
+            mem.write_obj(segment, data_addr).unwrap();
+            let mut q = queue.create_queue();
+            let request = Request::parse(&q.pop().unwrap(), mem, NUM_DISK_SECTORS).unwrap();
+            assert_eq!(request.segment, segment);
+
+            // The segment cannot be read from an invalid guest address.
+            chain.data_desc.addr.set(mem.last_addr().raw_value());
+            let mut q = queue.create_queue();
+            assert!(matches!(
+                Request::parse(&q.pop().unwrap(), mem, NUM_DISK_SECTORS),
+                Err(VirtioBlockError::GuestMemory(_))
+            ));
+            chain.data_desc.addr.set(data_addr.raw_value());
+        }
+    }
+
+    #[test]
+    fn test_finish_unsupported_backing_file() {
+        let mem = default_mem();
+        let metrics = BlockDeviceMetrics::default();
+        let status_addr = GuestAddress(0x1000);
+        let write_zeroes_err = |errno| {
+            IoErr::FileEngine(block_io::BlockIoError::Sync(
+                block_io::SyncIoError::WriteZeroes(std::io::Error::from_raw_os_error(errno)),
+            ))
+        };
+
+        // The driver is told that the request is not supported, so that it writes the zeroes.
+        for (errno, status) in [
+            (libc::EOPNOTSUPP, VIRTIO_BLK_S_UNSUPP),
+            (libc::EIO, VIRTIO_BLK_S_IOERR),
+        ] {
+            let pending = PendingRequest {
+                r#type: RequestType::WriteZeroes,
+                status_addr,
+                ..Default::default()
+            };
+            let finished = pending.finish(&mem, Err(write_zeroes_err(errno)), &metrics);
+            assert_eq!(finished.num_bytes_to_mem, 1);
+            assert_eq!(u32::from(mem.read_obj::<u8>(status_addr).unwrap()), status);
+        }
+    }
+
+    #[test]
+    fn test_finish_latency_histogram() {
+        let mem = default_mem();
+        let metrics = BlockDeviceMetrics::default();
+        let count = |hist: &LatencyHistogramMetrics| {
+            hist.buckets
+                .iter()
+                .map(|bucket| bucket.count())
+                .sum::<u64>()
+        };
+
+        for r#type in [RequestType::In, RequestType::Out, RequestType::Flush] {
+            let pending = PendingRequest {
+                r#type,
+                start_us: get_time_us(ClockType::Monotonic),
+                ..Default::default()
+            };
+            pending.finish(&mem, Ok(0), &metrics);
+        }
+
+        // Only reads and writes are accounted for.
+        assert_eq!(count(&metrics.read_latency_hist), 1);
+        assert_eq!(count(&metrics.write_latency_hist), 1);
+    }
+
     use std::convert::TryInto;
 
     /// -------------------------------------
@@ -716,7 +1080,15 @@ mod tests {
                         // Random unsupported requests for our implementation start at
                         // VIRTIO_BLK_T_GET_ID + 1 = 9.
                         // This can be further refined to include unsupported requests ids < 9.
-                        RequestType::Unsupported(id.checked_add(9).unwrap_or(9))
+                        // Ids of supported requests above 8, like VIRTIO_BLK_T_DISCARD and
+                        // VIRTIO_BLK_T_WRITE_ZEROES, are mapped to 9.
+                        RequestType::Unsupported(
+                            id.checked_add(9)
+                                .filter(|id| {
+                                    RequestType::from(*id) == RequestType::Unsupported(*id)
+                                })
+                                .unwrap_or(9),
+                        )
                     })),
                 ),
             ))
@@ -730,6 +1102,9 @@ mod tests {
                 RequestType::Out => VIRTIO_BLK_T_OUT,
                 RequestType::Flush => VIRTIO_BLK_T_FLUSH,
                 RequestType::GetDeviceID => VIRTIO_BLK_T_GET_ID,
+                RequestType::Discard => VIRTIO_BLK_T_DISCARD,
+                RequestType::WriteZeroes => VIRTIO_BLK_T_WRITE_ZEROES,
+                RequestType::GetLifetime => VIRTIO_BLK_T_GET_LIFETIME,
                 RequestType::Unsupported(id) => id,
             }
         }
@@ -742,6 +1117,9 @@ mod tests {
             RequestType::Out => VIRTQ_DESC_F_NEXT,
             RequestType::Flush => VIRTQ_DESC_F_NEXT,
             RequestType::GetDeviceID => VIRTQ_DESC_F_NEXT | VIRTQ_DESC_F_WRITE,
+            RequestType::Discard => VIRTQ_DESC_F_NEXT,
+            RequestType::WriteZeroes => VIRTQ_DESC_F_NEXT,
+            RequestType::GetLifetime => VIRTQ_DESC_F_NEXT | VIRTQ_DESC_F_WRITE,
             RequestType::Unsupported(_) => VIRTQ_DESC_F_NEXT,
         }
     }
@@ -833,6 +1211,7 @@ mod tests {
             status_addr,
             sector: sector & (NUM_DISK_SECTORS - sectors_len),
             data_addr,
+            segment: DiscardWriteZeroesSegment::default(),
         };
         let mut request_header = RequestHeader::new(virtio_request_id, request.sector);
 
@@ -56,6 +56,8 @@ pub fn default_block_with_path(path: String, file_engine_type: FileEngineType) -
             }),
         }),
         file_engine_type,
+        lifetime: None,
+        discard: true,
     };
 
     // The default block device is read-write and non-root.
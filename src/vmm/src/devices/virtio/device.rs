@@ -126,6 +126,12 @@ pub trait VirtioDevice: AsAny + Send {
 
     fn interrupt_trigger(&self) -> &IrqTrigger;
 
+    /// Returns the device status, shared with the transport. Devices that do not keep track of
+    /// their status get a new one, owned by the transport.
+    fn device_status(&self) -> Arc<AtomicU32> {
+        Arc::new(AtomicU32::new(super::device_status::INIT))
+    }
+
     /// The set of feature bits shifted by `page * 32`.
     fn avail_features_by_page(&self, page: u32) -> u32 {
         let avail_features = self.avail_features();
@@ -52,7 +52,7 @@ pub struct MmioTransport {
     // The register where features page is selected.
     pub(crate) acked_features_select: u32,
     pub(crate) queue_select: u32,
-    pub(crate) device_status: u32,
+    pub(crate) device_status: Arc<AtomicU32>,
     pub(crate) config_generation: u32,
     mem: GuestMemoryMmap,
     pub(crate) interrupt_status: Arc<AtomicU32>,
@@ -66,14 +66,21 @@ impl MmioTransport {
         device: Arc<Mutex<dyn VirtioDevice>>,
         is_vhost_user: bool,
     ) -> MmioTransport {
-        let interrupt_status = device.lock().expect("Poisoned lock").interrupt_status();
+        let (interrupt_status, device_status) = {
+            let locked_device = device.lock().expect("Poisoned lock");
+            (
+                locked_device.interrupt_status(),
+                locked_device.device_status(),
+            )
+        };
+        device_status.store(device_status::INIT, Ordering::SeqCst);
 
         MmioTransport {
             device,
             features_select: 0,
             acked_features_select: 0,
             queue_select: 0,
-            device_status: device_status::INIT,
+            device_status,
             config_generation: 0,
             mem,
             interrupt_status,
@@ -91,8 +98,13 @@ impl MmioTransport {
         self.device.clone()
     }
 
+    /// Gets the device status.
+    pub(crate) fn device_status(&self) -> u32 {
+        self.device_status.load(Ordering::SeqCst)
+    }
+
     fn check_device_status(&self, set: u32, clr: u32) -> bool {
-        self.device_status & (set | clr) == set
+        self.device_status() & (set | clr) == set
     }
 
     fn are_queues_valid(&self) -> bool {
@@ -139,7 +151,7 @@ impl MmioTransport {
         } else {
             warn!(
                 "update virtio queue in invalid state {:#x}",
-                self.device_status
+                self.device_status()
             );
         }
     }
@@ -152,7 +164,8 @@ impl MmioTransport {
         self.acked_features_select = 0;
         self.queue_select = 0;
         self.interrupt_status.store(0, Ordering::SeqCst);
-        self.device_status = device_status::INIT;
+        self.device_status
+            .store(device_status::INIT, Ordering::SeqCst);
         // . Keep interrupt_evt and queue_evts as is. There may be pending notifications in those
         //   eventfds, but nothing will happen other than supurious wakeups.
         // . Do not reset config_generation and keep it monotonically increasing
@@ -171,25 +184,18 @@ impl MmioTransport {
     #[allow(unused_assignments)]
     fn set_device_status(&mut self, status: u32) {
         use device_status::*;
+        let current_status = self.device_status();
         // match changed bits
-        match !self.device_status & status {
-            ACKNOWLEDGE if self.device_status == INIT => {
-                self.device_status = status;
-            }
-            DRIVER if self.device_status == ACKNOWLEDGE => {
-                self.device_status = status;
-            }
-            FEATURES_OK if self.device_status == (ACKNOWLEDGE | DRIVER) => {
-                self.device_status = status;
-            }
-            DRIVER_OK if self.device_status == (ACKNOWLEDGE | DRIVER | FEATURES_OK) => {
-                self.device_status = status;
+        match !current_status & status {
+            DRIVER_OK if is_next_init_step(current_status, status) => {
+                self.device_status.store(status, Ordering::SeqCst);
                 let device_activated = self.locked_device().is_activated();
                 if !device_activated && self.are_queues_valid() {
                     // temporary variable needed for borrow checker
                     let activate_result = self.locked_device().activate(self.mem.clone());
                     if let Err(err) = activate_result {
-                        self.device_status |= DEVICE_NEEDS_RESET;
+                        self.device_status
+                            .fetch_or(DEVICE_NEEDS_RESET, Ordering::SeqCst);
 
                         // Section 2.1.2 of the specification states that we need to send a device
                         // configuration change interrupt
@@ -202,33 +208,34 @@ impl MmioTransport {
                     }
                 }
             }
+            _ if is_next_init_step(current_status, status) => {
+                self.device_status.store(status, Ordering::SeqCst);
+            }
             _ if (status & FAILED) != 0 => {
                 // TODO: notify backend driver to stop the device
-                self.device_status |= FAILED;
+                self.device_status.fetch_or(FAILED, Ordering::SeqCst);
             }
             _ if status == 0 => {
                 if self.locked_device().is_activated() {
-                    let mut device_status = self.device_status;
                     let reset_result = self.locked_device().reset();
                     match reset_result {
                         Some((_interrupt_evt, mut _queue_evts)) => {}
                         None => {
-                            device_status |= FAILED;
+                            self.device_status.fetch_or(FAILED, Ordering::SeqCst);
                         }
                     }
-                    self.device_status = device_status;
                 }
 
                 // If the backend device driver doesn't support reset,
                 // just leave the device marked as FAILED.
-                if self.device_status & FAILED == 0 {
+                if self.device_status() & FAILED == 0 {
                     self.reset();
                 }
             }
             _ => {
                 warn!(
                     "invalid virtio driver status transition: {:#x} -> {:#x}",
-                    self.device_status, status
+                    current_status, status
                 );
             }
         }
@@ -279,7 +286,7 @@ impl MmioTransport {
                             VIRTIO_MMIO_INT_VRING
                         }
                     }
-                    0x70 => self.device_status,
+                    0x70 => self.device_status(),
                     0xfc => self.config_generation,
                     _ => {
                         warn!("unknown virtio mmio register read: {:#x}", offset);
@@ -321,7 +328,7 @@ impl MmioTransport {
                         } else {
                             warn!(
                                 "ack virtio features in invalid state {:#x}",
-                                self.device_status
+                                self.device_status()
                             );
                         }
                     }
@@ -609,7 +616,7 @@ pub(crate) mod tests {
 
         buf.pop();
 
-        assert_eq!(d.device_status, device_status::INIT);
+        assert_eq!(d.device_status(), device_status::INIT);
         set_device_status(&mut d, device_status::ACKNOWLEDGE);
 
         // Acking features in invalid state shouldn't take effect.
@@ -635,7 +642,7 @@ pub(crate) mod tests {
 
         set_device_status(&mut d, device_status::ACKNOWLEDGE | device_status::DRIVER);
         assert_eq!(
-            d.device_status,
+            d.device_status(),
             device_status::ACKNOWLEDGE | device_status::DRIVER
         );
 
@@ -763,12 +770,12 @@ pub(crate) mod tests {
 
         assert!(!d.are_queues_valid());
         assert!(!d.locked_device().is_activated());
-        assert_eq!(d.device_status, device_status::INIT);
+        assert_eq!(d.device_status(), device_status::INIT);
 
         set_device_status(&mut d, device_status::ACKNOWLEDGE);
         set_device_status(&mut d, device_status::ACKNOWLEDGE | device_status::DRIVER);
         assert_eq!(
-            d.device_status,
+            d.device_status(),
             device_status::ACKNOWLEDGE | device_status::DRIVER
         );
 
@@ -778,7 +785,7 @@ pub(crate) mod tests {
             device_status::ACKNOWLEDGE | device_status::DRIVER | device_status::DRIVER_OK,
         );
         assert_eq!(
-            d.device_status,
+            d.device_status(),
             device_status::ACKNOWLEDGE | device_status::DRIVER
         );
 
@@ -787,7 +794,7 @@ pub(crate) mod tests {
             device_status::ACKNOWLEDGE | device_status::DRIVER | device_status::FEATURES_OK,
         );
         assert_eq!(
-            d.device_status,
+            d.device_status(),
             device_status::ACKNOWLEDGE | device_status::DRIVER | device_status::FEATURES_OK
         );
 
@@ -818,7 +825,7 @@ pub(crate) mod tests {
                 | device_status::DRIVER_OK,
         );
         assert_eq!(
-            d.device_status,
+            d.device_status(),
             device_status::ACKNOWLEDGE
                 | device_status::DRIVER
                 | device_status::FEATURES_OK
@@ -875,7 +882,7 @@ pub(crate) mod tests {
         );
 
         // Failure in activate results in `DEVICE_NEEDS_RESET` status being set
-        assert_ne!(d.device_status & DEVICE_NEEDS_RESET, 0);
+        assert_ne!(d.device_status() & DEVICE_NEEDS_RESET, 0);
         // We injected an interrupt of type "configuration change"
         assert_eq!(
             d.locked_device().interrupt_status().load(Ordering::SeqCst),
@@ -922,7 +929,7 @@ pub(crate) mod tests {
                 | device_status::DRIVER_OK,
         );
         assert_eq!(
-            d.device_status,
+            d.device_status(),
             device_status::ACKNOWLEDGE
                 | device_status::DRIVER
                 | device_status::FEATURES_OK
@@ -939,19 +946,19 @@ pub(crate) mod tests {
 
         assert!(!d.are_queues_valid());
         assert!(!d.locked_device().is_activated());
-        assert_eq!(d.device_status, 0);
+        assert_eq!(d.device_status(), 0);
         activate_device(&mut d);
 
         // Marking device as FAILED should not affect device_activated state
         write_le_u32(&mut buf[..], 0x8f);
         d.bus_write(0x70, &buf[..]);
-        assert_eq!(d.device_status, 0x8f);
+        assert_eq!(d.device_status(), 0x8f);
         assert!(d.locked_device().is_activated());
 
         // Nothing happens when backend driver doesn't support reset
         write_le_u32(&mut buf[..], 0x0);
         d.bus_write(0x70, &buf[..]);
-        assert_eq!(d.device_status, 0x8f);
+        assert_eq!(d.device_status(), 0x8f);
         assert!(d.locked_device().is_activated());
     }
 
@@ -21,9 +21,11 @@ pub mod iovec;
 pub mod mmio;
 pub mod net;
 pub mod persist;
+pub mod pmem;
 pub mod queue;
 pub mod rng;
 pub mod test_utils;
+pub mod transport;
 pub mod vhost_user;
 pub mod vhost_user_metrics;
 pub mod vsock;
@@ -44,6 +46,18 @@ mod device_status {
     pub const FEATURES_OK: u32 = 8;
     pub const DRIVER_OK: u32 = 4;
     pub const DEVICE_NEEDS_RESET: u32 = 64;
+
+    /// Returns whether going from the `current` to the `new` device status completes the next
+    /// step of the driver initialization sequence. Please refer to VirtIO Spec 1.0, section 3.1.1.
+    pub fn is_next_init_step(current: u32, new: u32) -> bool {
+        match !current & new {
+            ACKNOWLEDGE => current == INIT,
+            DRIVER => current == ACKNOWLEDGE,
+            FEATURES_OK => current == (ACKNOWLEDGE | DRIVER),
+            DRIVER_OK => current == (ACKNOWLEDGE | DRIVER | FEATURES_OK),
+            _ => false,
+        }
+    }
 }
 
 /// Types taken from linux/virtio_ids.h.
@@ -56,6 +70,8 @@ pub const TYPE_BLOCK: u32 = 2;
 pub const TYPE_RNG: u32 = 4;
 /// Virtio balloon device ID.
 pub const TYPE_BALLOON: u32 = 5;
+/// Virtio pmem device ID.
+pub const TYPE_PMEM: u32 = 27;
 
 /// Offset from the base MMIO address of a virtio device used by the guest to notify the device of
 /// queue events.
@@ -8,19 +8,21 @@
 use std::collections::VecDeque;
 use std::mem::{self};
 use std::net::Ipv4Addr;
+use std::sync::atomic::AtomicU32;
 use std::sync::{Arc, Mutex};
 
 use libc::{EAGAIN, iovec};
-use log::error;
+use log::{error, warn};
 use vmm_sys_util::eventfd::EventFd;
 
 use super::NET_QUEUE_MAX_SIZE;
-use crate::devices::virtio::device::{DeviceState, IrqTrigger, IrqType, VirtioDevice};
+use crate::devices::virtio::device::{DeviceState, IrqTrigger, VirtioDevice};
 use crate::devices::virtio::generated::virtio_config::VIRTIO_F_VERSION_1;
 use crate::devices::virtio::generated::virtio_net::{
     VIRTIO_NET_F_CSUM, VIRTIO_NET_F_GUEST_CSUM, VIRTIO_NET_F_GUEST_TSO4, VIRTIO_NET_F_GUEST_TSO6,
     VIRTIO_NET_F_GUEST_UFO, VIRTIO_NET_F_HOST_TSO4, VIRTIO_NET_F_HOST_TSO6, VIRTIO_NET_F_HOST_UFO,
-    VIRTIO_NET_F_MAC, VIRTIO_NET_F_MRG_RXBUF, virtio_net_hdr_v1,
+    VIRTIO_NET_F_MAC, VIRTIO_NET_F_MRG_RXBUF, VIRTIO_NET_F_MTU, VIRTIO_NET_F_SPEED_DUPLEX,
+    virtio_net_hdr_v1,
 };
 use crate::devices::virtio::generated::virtio_ring::VIRTIO_RING_F_EVENT_IDX;
 use crate::devices::virtio::iovec::{
@@ -29,10 +31,11 @@ use crate::devices::virtio::iovec::{
 use crate::devices::virtio::net::metrics::{NetDeviceMetrics, NetMetricsPerDevice};
 use crate::devices::virtio::net::tap::Tap;
 use crate::devices::virtio::net::{
-    MAX_BUFFER_SIZE, NET_QUEUE_SIZES, NetError, NetQueue, RX_INDEX, TX_INDEX, generated,
+    Duplex, MAX_BUFFER_SIZE, NET_QUEUE_SIZES, NetError, NetQueue, RX_INDEX, TX_INDEX, generated,
 };
 use crate::devices::virtio::queue::{DescriptorChain, Queue};
-use crate::devices::virtio::{ActivateError, TYPE_NET};
+use crate::devices::virtio::transport::{VirtioMmioTransport, VirtioTransport};
+use crate::devices::virtio::{ActivateError, TYPE_NET, device_status};
 use crate::devices::{DeviceError, report_net_event_fail};
 use crate::dumbo::pdu::arp::ETH_IPV4_FRAME_LEN;
 use crate::dumbo::pdu::ethernet::{EthernetFrame, PAYLOAD_OFFSET};
@@ -40,7 +43,7 @@ use crate::logger::{IncMetric, METRICS};
 use crate::mmds::data_store::Mmds;
 use crate::mmds::ns::MmdsNetworkStack;
 use crate::rate_limiter::{BucketUpdate, RateLimiter, TokenType};
-use crate::utils::net::mac::MacAddr;
+use crate::utils::net::mac::{MAC_ADDR_LEN, MacAddr};
 use crate::utils::u64_to_usize;
 use crate::vstate::memory::{ByteValued, GuestMemoryMmap};
 
@@ -81,10 +84,26 @@ fn init_vnet_hdr(buf: &mut [u8]) {
     buf[0..vnet_hdr_len()].fill(0);
 }
 
+// Values of the `duplex` field of the configuration space.
+const VIRTIO_NET_DUPLEX_HALF: u8 = 0x00;
+const VIRTIO_NET_DUPLEX_FULL: u8 = 0x01;
+
+/// The virtio-net configuration space, up to the `rss_max_indirection_table_length` field.
+///
+/// The `status` and `max_virtqueue_pairs` fields are always 0, because the device offers neither
+/// `VIRTIO_NET_F_STATUS` nor `VIRTIO_NET_F_MQ`. The same goes for the `rss_max_key_size` and
+/// `rss_max_indirection_table_length` fields, as the device does not offer `VIRTIO_NET_F_RSS`.
 #[derive(Debug, Default, Clone, Copy)]
 #[repr(C)]
 pub struct ConfigSpace {
     pub guest_mac: MacAddr,
+    pub status: u16,
+    pub max_virtqueue_pairs: u16,
+    pub mtu: u16,
+    pub speed: u32,
+    pub duplex: u8,
+    pub rss_max_key_size: u8,
+    pub rss_max_indirection_table_length: u16,
 }
 
 // SAFETY: `ConfigSpace` contains only PODs in `repr(C)` or `repr(transparent)`, without padding.
@@ -250,6 +269,9 @@ pub struct Net {
     tx_frame_headers: [u8; frame_hdr_len()],
 
     pub(crate) irq_trigger: IrqTrigger,
+    pub(crate) device_status: Arc<AtomicU32>,
+    // Device side of the transport, sharing the interrupt and device status above.
+    transport: Box<dyn VirtioTransport>,
 
     pub(crate) config_space: ConfigSpace,
     pub(crate) guest_mac: Option<MacAddr>,
@@ -264,6 +286,13 @@ pub struct Net {
 
     tx_buffer: IoVecBuffer,
     pub(crate) rx_buffer: RxBuffers,
+
+    // The device stops reading the tap while it has no RX buffers or while the RX rate limiter
+    // is blocked, so the frames exceeding the tap queue are dropped by the kernel. These are
+    // the drops already accounted for in `rx_drops`, or `None` if they can't be queried.
+    rx_tap_dropped: Option<u64>,
+    // Whether the device stopped reading the tap since the drops were last accounted for.
+    rx_stalled: bool,
 }
 
 impl Net {
@@ -302,6 +331,22 @@ impl Net {
             queues.push(Queue::new(size));
         }
 
+        let irq_trigger = IrqTrigger::new().map_err(NetError::EventFd)?;
+        let device_status = Arc::new(AtomicU32::new(device_status::INIT));
+        let transport = VirtioMmioTransport::new(&irq_trigger, Arc::clone(&device_status))
+            .map_err(NetError::EventFd)?;
+
+        let rx_tap_dropped = tap
+            .tx_dropped()
+            .inspect_err(|err| {
+                warn!(
+                    "Failed to query the frames dropped by {}, they won't be counted: {:?}",
+                    tap.if_name_as_str(),
+                    err
+                )
+            })
+            .ok();
+
         Ok(Net {
             id: id.clone(),
             tap,
@@ -313,7 +358,9 @@ impl Net {
             tx_rate_limiter,
             rx_frame_buf: [0u8; MAX_BUFFER_SIZE],
             tx_frame_headers: [0u8; frame_hdr_len()],
-            irq_trigger: IrqTrigger::new().map_err(NetError::EventFd)?,
+            irq_trigger,
+            device_status,
+            transport: Box::new(transport),
             config_space,
             guest_mac,
             device_state: DeviceState::Inactive,
@@ -322,6 +369,8 @@ impl Net {
             metrics: NetMetricsPerDevice::alloc(id),
             tx_buffer: Default::default(),
             rx_buffer: RxBuffers::new()?,
+            rx_tap_dropped,
+            rx_stalled: true,
         })
     }
 
@@ -352,6 +401,41 @@ impl Net {
         self.guest_mac.as_ref()
     }
 
+    /// Provides the MTU advertised to the guest, if any.
+    pub fn mtu(&self) -> Option<u16> {
+        (self.avail_features & (1 << VIRTIO_NET_F_MTU) != 0)
+            .then_some(u16::from_le(self.config_space.mtu))
+    }
+
+    /// Advertises `mtu` to the guest driver, which uses it as the MTU of the interface. Only takes
+    /// effect if called before the driver negotiates the features of the device.
+    pub fn set_mtu(&mut self, mtu: u16) {
+        self.config_space.mtu = mtu.to_le();
+        self.avail_features |= 1 << VIRTIO_NET_F_MTU;
+    }
+
+    /// Provides the link speed, in Mbps, and duplex mode reported to the guest, if any.
+    pub fn speed_duplex(&self) -> Option<(u32, Duplex)> {
+        let duplex = match self.config_space.duplex {
+            VIRTIO_NET_DUPLEX_HALF => Duplex::Half,
+            _ => Duplex::Full,
+        };
+        (self.avail_features & (1 << VIRTIO_NET_F_SPEED_DUPLEX) != 0)
+            .then_some((u32::from_le(self.config_space.speed), duplex))
+    }
+
+    /// Reports a link of `speed` Mbps in `duplex` mode to the guest driver, which shows it to
+    /// tools such as `ethtool`. Only takes effect if called before the driver negotiates the
+    /// features of the device.
+    pub fn set_speed_duplex(&mut self, speed: u32, duplex: Duplex) {
+        self.config_space.speed = speed.to_le();
+        self.config_space.duplex = match duplex {
+            Duplex::Half => VIRTIO_NET_DUPLEX_HALF,
+            Duplex::Full => VIRTIO_NET_DUPLEX_FULL,
+        };
+        self.avail_features |= 1 << VIRTIO_NET_F_SPEED_DUPLEX;
+    }
+
     /// Provides the host IFACE name of this net device.
     pub fn iface_name(&self) -> String {
         self.tap.if_name_as_str().to_string()
@@ -363,13 +447,18 @@ impl Net {
     }
 
     /// Configures the `MmdsNetworkStack` to allow device to forward MMDS requests.
-    /// If the device already supports MMDS, updates the IPv4 address.
-    pub fn configure_mmds_network_stack(&mut self, ipv4_addr: Ipv4Addr, mmds: Arc<Mutex<Mmds>>) {
-        if let Some(mmds_ns) = self.mmds_ns.as_mut() {
-            mmds_ns.set_ipv4_addr(ipv4_addr);
-        } else {
-            self.mmds_ns = Some(MmdsNetworkStack::new_with_defaults(Some(ipv4_addr), mmds))
-        }
+    /// If the device already supports MMDS, updates the IPv4 address and connection rate limit.
+    pub fn configure_mmds_network_stack(
+        &mut self,
+        ipv4_addr: Ipv4Addr,
+        rate_limit_rps: u32,
+        mmds: Arc<Mutex<Mmds>>,
+    ) {
+        let mmds_ns = self
+            .mmds_ns
+            .get_or_insert_with(|| MmdsNetworkStack::new_with_defaults(Some(ipv4_addr), mmds));
+        mmds_ns.set_ipv4_addr(ipv4_addr);
+        mmds_ns.set_rate_limit_rps(rate_limit_rps);
     }
 
     /// Disables the `MmdsNetworkStack` to prevent device to forward MMDS requests.
@@ -392,18 +481,16 @@ impl Net {
     /// https://docs.oasis-open.org/virtio/virtio/v1.1/csprd01/virtio-v1.1-csprd01.html#x1-320005
     /// 2.6.7.1 Driver Requirements: Used Buffer Notification Suppression
     fn try_signal_queue(&mut self, queue_type: NetQueue) -> Result<(), DeviceError> {
-        let queue = match queue_type {
-            NetQueue::Rx => &mut self.queues[RX_INDEX],
-            NetQueue::Tx => &mut self.queues[TX_INDEX],
+        let queue_index = match queue_type {
+            NetQueue::Rx => RX_INDEX,
+            NetQueue::Tx => TX_INDEX,
         };
 
-        if queue.prepare_kick() {
-            self.irq_trigger
-                .trigger_irq(IrqType::Vring)
-                .map_err(|err| {
-                    self.metrics.event_fails.inc();
-                    DeviceError::FailedSignalingIrq(err)
-                })?;
+        if self.queues[queue_index].prepare_kick() {
+            self.transport.notify_queue(queue_index).map_err(|err| {
+                self.metrics.event_fails.inc();
+                DeviceError::Transport(err)
+            })?;
         }
 
         Ok(())
@@ -436,6 +523,7 @@ impl Net {
         let rx_queue = &mut self.queues[RX_INDEX];
         if !Self::rate_limiter_consume_op(&mut self.rx_rate_limiter, frame_size as u64) {
             self.metrics.rx_rate_limiter_throttled.inc();
+            self.rx_stalled = true;
             return false;
         }
 
@@ -512,12 +600,14 @@ impl Net {
             .map_err(|err| {
                 error!("Received malformed TX buffer: {:?}", err);
                 net_metrics.tx_malformed_frames.inc();
+                net_metrics.tx_drops.inc();
                 NetError::VnetHeaderMissing
             })?;
 
         let headers = frame_bytes_from_buf(&headers[..header_len]).inspect_err(|_| {
             error!("VNET headers missing in TX frame");
             net_metrics.tx_malformed_frames.inc();
+            net_metrics.tx_drops.inc();
         })?;
 
         if let Some(ns) = mmds_ns {
@@ -561,6 +651,7 @@ impl Net {
             Err(err) => {
                 error!("Failed to write to tap: {:?}", err);
                 net_metrics.tap_write_fails.inc();
+                net_metrics.tx_drops.inc();
             }
         };
         Ok(false)
@@ -627,15 +718,34 @@ impl Net {
         Ok(Some(len))
     }
 
+    // Adds to `rx_drops` the frames the tap dropped while the device wasn't reading it.
+    fn count_tap_drops(&mut self) {
+        self.rx_stalled = false;
+        let Some(counted) = self.rx_tap_dropped else {
+            return;
+        };
+        match self.tap.tx_dropped() {
+            Ok(dropped) => {
+                self.metrics.rx_drops.add(dropped.saturating_sub(counted));
+                self.rx_tap_dropped = Some(dropped);
+            }
+            Err(err) => error!("Failed to query the frames dropped by the tap: {:?}", err),
+        }
+    }
+
     /// Read as many frames as possible.
     fn process_rx(&mut self) -> Result<(), DeviceError> {
         loop {
             match self.read_from_mmds_or_tap() {
                 Ok(None) => {
                     self.metrics.no_rx_avail_buffer.inc();
+                    self.rx_stalled = true;
                     break;
                 }
                 Ok(Some(bytes)) => {
+                    if self.rx_stalled {
+                        self.count_tap_drops();
+                    }
                     self.metrics.rx_count.inc();
                     self.metrics.rx_bytes_count.add(bytes as u64);
                     self.metrics.rx_packets_count.inc();
@@ -658,6 +768,7 @@ impl Net {
                 }
                 Err(err) => {
                     error!("Spurious error in network RX: {:?}", err);
+                    self.metrics.rx_drops.inc();
                 }
             }
         }
@@ -701,6 +812,7 @@ impl Net {
             // are live at the same time, meaning this has exclusive ownership over the memory
             if unsafe { self.tx_buffer.load_descriptor_chain(mem, head).is_err() } {
                 self.metrics.tx_fails.inc();
+                self.metrics.tx_drops.inc();
                 tx_queue
                     .add_used(head_index, 0)
                     .map_err(DeviceError::QueueError)?;
@@ -711,6 +823,7 @@ impl Net {
             if self.tx_buffer.len() as usize > MAX_BUFFER_SIZE {
                 error!("net: received too big frame from driver");
                 self.metrics.tx_malformed_frames.inc();
+                self.metrics.tx_drops.inc();
                 tx_queue
                     .add_used(head_index, 0)
                     .map_err(DeviceError::QueueError)?;
@@ -965,6 +1078,10 @@ impl VirtioDevice for Net {
     fn interrupt_trigger(&self) -> &IrqTrigger {
         &self.irq_trigger
     }
+
+    fn device_status(&self) -> Arc<AtomicU32> {
+        Arc::clone(&self.device_status)
+    }
     fn read_config(&self, offset: u64, data: &mut [u8]) {
         if let Some(config_space_bytes) = self.config_space.as_slice().get(u64_to_usize(offset)..) {
             let len = config_space_bytes.len().min(data.len());
@@ -976,7 +1093,8 @@ impl VirtioDevice for Net {
     }
 
     fn write_config(&mut self, offset: u64, data: &[u8]) {
-        let config_space_bytes = self.config_space.as_mut_slice();
+        // Only the MAC address is writable by the driver.
+        let config_space_bytes = &mut self.config_space.as_mut_slice()[..MAC_ADDR_LEN as usize];
         let start = usize::try_from(offset).ok();
         let end = start.and_then(|s| s.checked_add(data.len()));
         let Some(dst) = start
@@ -1040,6 +1158,7 @@ pub mod tests {
 
     use super::*;
     use crate::check_metric_after_block;
+    use crate::devices::virtio::device::IrqType;
     use crate::devices::virtio::generated::virtio_ring::VIRTIO_RING_F_EVENT_IDX;
     use crate::devices::virtio::iovec::IoVecBuffer;
     use crate::devices::virtio::net::NET_QUEUE_SIZES;
@@ -1059,7 +1178,6 @@ pub mod tests {
     use crate::logger::IncMetric;
     use crate::rate_limiter::{BucketUpdate, RateLimiter, TokenBucket, TokenType};
     use crate::test_utils::single_region_mem;
-    use crate::utils::net::mac::{MAC_ADDR_LEN, MacAddr};
     use crate::vstate::memory::{Address, GuestMemory};
 
     impl Net {
@@ -1232,6 +1350,58 @@ pub mod tests {
         assert_eq!(new_config, new_config_read);
     }
 
+    #[test]
+    fn test_virtio_device_mtu() {
+        let mut net = default_net();
+        assert_eq!(net.mtu(), None);
+        assert_eq!(net.avail_features() & (1 << VIRTIO_NET_F_MTU), 0);
+
+        net.set_mtu(1400);
+        assert_eq!(net.mtu(), Some(1400));
+        assert_ne!(net.avail_features() & (1 << VIRTIO_NET_F_MTU), 0);
+
+        // The MTU follows the MAC address, status and max_virtqueue_pairs fields.
+        let mut config_mtu = [0u8; 2];
+        net.read_config(10, &mut config_mtu);
+        assert_eq!(u16::from_le_bytes(config_mtu), 1400);
+
+        // The driver cannot change it.
+        net.write_config(10, &[0, 0]);
+        net.read_config(10, &mut config_mtu);
+        assert_eq!(u16::from_le_bytes(config_mtu), 1400);
+        assert_eq!(net.mtu(), Some(1400));
+    }
+
+    #[test]
+    fn test_virtio_device_speed_duplex() {
+        let mut net = default_net();
+        assert_eq!(net.speed_duplex(), None);
+        assert_eq!(net.avail_features() & (1 << VIRTIO_NET_F_SPEED_DUPLEX), 0);
+
+        net.set_speed_duplex(1000, Duplex::Half);
+        assert_eq!(net.speed_duplex(), Some((1000, Duplex::Half)));
+        assert_ne!(net.avail_features() & (1 << VIRTIO_NET_F_SPEED_DUPLEX), 0);
+
+        // The speed and duplex follow the MTU field.
+        let mut config_speed_duplex = [0u8; 5];
+        net.read_config(12, &mut config_speed_duplex);
+        assert_eq!(
+            config_speed_duplex,
+            [0xe8, 0x03, 0, 0, VIRTIO_NET_DUPLEX_HALF]
+        );
+
+        net.set_speed_duplex(10000, Duplex::Full);
+        net.read_config(12, &mut config_speed_duplex);
+        assert_eq!(
+            config_speed_duplex,
+            [0x10, 0x27, 0, 0, VIRTIO_NET_DUPLEX_FULL]
+        );
+
+        // The driver cannot change them.
+        net.write_config(12, &[0; 5]);
+        assert_eq!(net.speed_duplex(), Some((10000, Duplex::Full)));
+    }
+
     #[test]
     fn test_rx_missing_queue_signal() {
         let mem = single_region_mem(2 * MAX_BUFFER_SIZE);
@@ -1680,9 +1850,13 @@ pub mod tests {
             &[(0, (MAX_BUFFER_SIZE + 1).try_into().unwrap(), 0)],
         );
         check_metric_after_block!(
-            th.net().metrics.tx_malformed_frames,
+            th.net().metrics.tx_drops,
             1,
-            th.event_manager.run_with_timeout(100)
+            check_metric_after_block!(
+                th.net().metrics.tx_malformed_frames,
+                1,
+                th.event_manager.run_with_timeout(100)
+            )
         );
 
         // Check that the used queue advanced.
@@ -1740,11 +1914,16 @@ pub mod tests {
         let frame = th.write_tx_frame(&desc_list, 1000);
 
         // One frame is valid, one will not be handled because it includes write-only memory
-        // so that leaves us with 2 malformed (no vnet header) frames.
+        // so that leaves us with 2 malformed (no vnet header) frames. All 3 invalid frames are
+        // dropped.
         check_metric_after_block!(
-            th.net().metrics.tx_malformed_frames,
-            2,
-            th.event_manager.run_with_timeout(100)
+            th.net().metrics.tx_drops,
+            3,
+            check_metric_after_block!(
+                th.net().metrics.tx_malformed_frames,
+                2,
+                th.event_manager.run_with_timeout(100)
+            )
         );
 
         // Check that the used queue advanced.
@@ -1802,9 +1981,13 @@ pub mod tests {
         let _ = th.write_tx_frame(&desc_list, 1000);
 
         check_metric_after_block!(
-            th.net().metrics.tap_write_fails,
+            th.net().metrics.tx_drops,
             1,
-            th.event_manager.run_with_timeout(100).unwrap()
+            check_metric_after_block!(
+                th.net().metrics.tap_write_fails,
+                1,
+                th.event_manager.run_with_timeout(100).unwrap()
+            )
         );
 
         // Check that the used queue advanced.
@@ -2069,6 +2252,82 @@ pub mod tests {
         );
     }
 
+    // Sends more frames than the queue of the tap holds, so that the kernel drops some of them.
+    fn overflow_tap_queue(net: &Net) {
+        let tap_traffic_simulator = TapTrafficSimulator::new(if_index(&net.tap));
+        for _ in 0..1000 {
+            tap_traffic_simulator.push_tx_packet(&[0u8; 100]);
+        }
+    }
+
+    #[test]
+    fn test_rx_drops_no_rx_buffers() {
+        let mem = single_region_mem(2 * MAX_BUFFER_SIZE);
+        let mut th = TestHelper::get_default(&mem);
+        th.activate_net();
+
+        // The guest provides no RX buffers, so the device doesn't read the tap.
+        overflow_tap_queue(&th.net());
+        check_metric_after_block!(
+            th.net().metrics.no_rx_avail_buffer,
+            1,
+            th.simulate_event(NetEvent::Tap)
+        );
+        let dropped = th.net().tap.tx_dropped().unwrap();
+        assert!(dropped > 0);
+        assert_eq!(th.net().metrics.rx_drops.count(), 0);
+
+        // The drops are counted once the device reads the tap again.
+        th.add_desc_chain(
+            NetQueue::Rx,
+            0,
+            &[(0, MAX_BUFFER_SIZE as u32, VIRTQ_DESC_F_WRITE)],
+        );
+        th.simulate_event(NetEvent::RxQueue);
+        assert_eq!(th.rxq.used.idx.get(), 1);
+        assert_eq!(th.net().metrics.rx_drops.count(), dropped);
+    }
+
+    #[test]
+    fn test_rx_drops_rate_limiter() {
+        let mem = single_region_mem(2 * MAX_BUFFER_SIZE);
+        let mut th = TestHelper::get_default(&mem);
+        th.activate_net();
+
+        // create ops rate limiter that allows 10 ops/s with bucket size 1 ops
+        let mut rl = RateLimiter::new(0, 0, 0, 1, 0, 100).unwrap();
+        // use up the initial budget
+        assert!(rl.consume(1, TokenType::Ops));
+        th.net().rx_rate_limiter = rl;
+
+        th.add_desc_chain(NetQueue::Rx, 0, &[(0, 4096, VIRTQ_DESC_F_WRITE)]);
+        th.add_desc_chain(NetQueue::Rx, 4096, &[(1, 4096, VIRTQ_DESC_F_WRITE)]);
+        inject_tap_tx_frame(&th.net(), 1000);
+        // The frame is deferred and the device stops reading the tap.
+        check_metric_after_block!(
+            th.net().metrics.rx_rate_limiter_throttled,
+            1,
+            th.simulate_event(NetEvent::Tap)
+        );
+        assert!(th.net().rx_rate_limiter.is_blocked());
+
+        overflow_tap_queue(&th.net());
+        th.simulate_event(NetEvent::Tap);
+        let dropped = th.net().tap.tx_dropped().unwrap();
+        assert!(dropped > 0);
+        assert_eq!(th.net().metrics.rx_drops.count(), 0);
+
+        // wait for 100ms to give the rate-limiter timer a chance to replenish
+        // wait for an extra 100ms to make sure the timerfd event makes its way from the kernel
+        thread::sleep(Duration::from_millis(200));
+
+        // The deferred frame is delivered, and the drops are counted once the device reads the
+        // tap again.
+        th.simulate_event(NetEvent::RxRateLimiter);
+        assert_eq!(th.rxq.used.idx.get(), 1);
+        assert_eq!(th.net().metrics.rx_drops.count(), dropped);
+    }
+
     #[test]
     fn test_tx_rate_limiter_handling() {
         let mem = single_region_mem(2 * MAX_BUFFER_SIZE);
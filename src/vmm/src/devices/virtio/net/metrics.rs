@@ -175,6 +175,9 @@ pub struct NetDeviceMetrics {
     pub rx_fails: SharedIncMetric,
     /// Number of successful read operations while receiving data.
     pub rx_count: SharedIncMetric,
+    /// Number of frames destined to the guest that were dropped, including those the tap dropped
+    /// while the device wasn't reading it.
+    pub rx_drops: SharedIncMetric,
     /// Number of times reading from TAP failed.
     pub tap_read_fails: SharedIncMetric,
     /// Number of times writing to TAP failed.
@@ -189,6 +192,8 @@ pub struct NetDeviceMetrics {
     pub tx_fails: SharedIncMetric,
     /// Number of successful write operations while transmitting data.
     pub tx_count: SharedIncMetric,
+    /// Number of frames sent by the guest that were dropped.
+    pub tx_drops: SharedIncMetric,
     /// Number of transmitted packets.
     pub tx_packets_count: SharedIncMetric,
     /// Number of TX partial reads from guest.
@@ -244,6 +249,7 @@ impl NetDeviceMetrics {
             .add(other.rx_packets_count.fetch_diff());
         self.rx_fails.add(other.rx_fails.fetch_diff());
         self.rx_count.add(other.rx_count.fetch_diff());
+        self.rx_drops.add(other.rx_drops.fetch_diff());
         self.tap_read_fails.add(other.tap_read_fails.fetch_diff());
         self.tap_write_fails.add(other.tap_write_fails.fetch_diff());
         self.tap_write_agg
@@ -254,6 +260,7 @@ impl NetDeviceMetrics {
             .add(other.tx_malformed_frames.fetch_diff());
         self.tx_fails.add(other.tx_fails.fetch_diff());
         self.tx_count.add(other.tx_count.fetch_diff());
+        self.tx_drops.add(other.tx_drops.fetch_diff());
         self.tx_packets_count
             .add(other.tx_packets_count.fetch_diff());
         self.tx_partial_reads
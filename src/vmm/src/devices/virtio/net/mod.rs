@@ -5,6 +5,8 @@
 
 use std::io;
 
+use serde::{Deserialize, Serialize};
+
 /// Maximum size of the queue for network device.
 pub const NET_QUEUE_MAX_SIZE: u16 = 256;
 /// Maximum size of the frame buffers handled by this device.
@@ -41,6 +43,16 @@ pub enum NetQueue {
     Tx,
 }
 
+/// Duplex mode of the link reported to the guest driver.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Duplex {
+    /// The link is half-duplex.
+    Half,
+    /// The link is full-duplex.
+    #[default]
+    Full,
+}
+
 /// Errors the network device can trigger.
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
 pub enum NetError {
@@ -4,13 +4,13 @@
 //! Defines the structures needed for saving/restoring net devices.
 
 use std::io;
-use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
 
 use serde::{Deserialize, Serialize};
 
 use super::device::{Net, RxBuffers};
-use super::{NET_NUM_QUEUES, NET_QUEUE_MAX_SIZE, RX_INDEX, TapError};
+use super::{Duplex, NET_NUM_QUEUES, NET_QUEUE_MAX_SIZE, RX_INDEX, TapError};
 use crate::devices::virtio::TYPE_NET;
 use crate::devices::virtio::device::DeviceState;
 use crate::devices::virtio::persist::{PersistError as VirtioStateError, VirtioDeviceState};
@@ -28,6 +28,8 @@ use crate::vstate::memory::GuestMemoryMmap;
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct NetConfigSpaceState {
     guest_mac: Option<MacAddr>,
+    mtu: Option<u16>,
+    speed_duplex: Option<(u32, Duplex)>,
 }
 
 /// Information about the parsed RX buffers
@@ -104,6 +106,8 @@ impl Persist<'_> for Net {
             mmds_ns: self.mmds_ns.as_ref().map(|mmds| mmds.save()),
             config_space: NetConfigSpaceState {
                 guest_mac: self.guest_mac,
+                mtu: self.mtu(),
+                speed_duplex: self.speed_duplex(),
             },
             virtio_state: VirtioDeviceState::from_device(self),
             rx_buffers_state: RxBufferState::from_rx_buffers(&self.rx_buffer),
@@ -124,6 +128,12 @@ impl Persist<'_> for Net {
             rx_rate_limiter,
             tx_rate_limiter,
         )?;
+        if let Some(mtu) = state.config_space.mtu {
+            net.set_mtu(mtu);
+        }
+        if let Some((speed, duplex)) = state.config_space.speed_duplex {
+            net.set_speed_duplex(speed, duplex);
+        }
 
         // We trust the MMIODeviceManager::restore to pass us an MMDS data store reference if
         // there is at least one net device having the MMDS NS present and/or the mmds version was
@@ -148,7 +158,10 @@ impl Persist<'_> for Net {
             NET_NUM_QUEUES,
             NET_QUEUE_MAX_SIZE,
         )?;
-        net.irq_trigger.irq_status = Arc::new(AtomicU32::new(state.virtio_state.interrupt_status));
+        // The interrupt status is shared with the transport, so it is restored in place.
+        net.irq_trigger
+            .irq_status
+            .store(state.virtio_state.interrupt_status, Ordering::SeqCst);
         net.avail_features = state.virtio_state.avail_features;
         net.acked_features = state.virtio_state.acked_features;
 
@@ -174,13 +187,12 @@ impl Persist<'_> for Net {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::atomic::Ordering;
-
     use super::*;
     use crate::devices::virtio::device::VirtioDevice;
     use crate::devices::virtio::net::test_utils::{default_net, default_net_no_mmds};
     use crate::devices::virtio::test_utils::default_mem;
     use crate::snapshot::Snapshot;
+    use crate::utils::net::mac::MAC_ADDR_LEN;
 
     fn validate_save_and_restore(net: Net, mmds_ds: Option<Arc<Mutex<Mmds>>>) {
         let guest_mem = default_mem();
@@ -190,6 +202,9 @@ mod tests {
         let tap_if_name;
         let has_mmds_ns;
         let allow_mmds_requests;
+        let mtu;
+        let speed_duplex;
+        let guest_mac;
         let virtio_state;
 
         // Create and save the net device.
@@ -201,6 +216,9 @@ mod tests {
             tap_if_name = net.iface_name();
             has_mmds_ns = net.mmds_ns.is_some();
             allow_mmds_requests = has_mmds_ns && mmds_ds.is_some();
+            mtu = net.mtu();
+            speed_duplex = net.speed_duplex();
+            guest_mac = net.guest_mac;
             virtio_state = VirtioDeviceState::from_device(&net);
         }
 
@@ -231,6 +249,14 @@ mod tests {
                     assert_eq!(&restored_net.id, &id);
                     assert_eq!(&restored_net.iface_name(), &tap_if_name);
                     assert_eq!(restored_net.mmds_ns.is_some(), allow_mmds_requests);
+                    assert_eq!(restored_net.mtu(), mtu);
+                    assert_eq!(restored_net.speed_duplex(), speed_duplex);
+                    assert_eq!(restored_net.guest_mac, guest_mac);
+                    if let Some(mac) = guest_mac {
+                        let mut config_mac = [0u8; MAC_ADDR_LEN as usize];
+                        restored_net.read_config(0, &mut config_mac);
+                        assert_eq!(&config_mac, mac.get_bytes());
+                    }
                     assert_eq!(restored_net.rx_rate_limiter, RateLimiter::default());
                     assert_eq!(restored_net.tx_rate_limiter, RateLimiter::default());
                 }
@@ -248,6 +274,19 @@ mod tests {
         validate_save_and_restore(default_net(), mmds.as_ref().cloned());
         validate_save_and_restore(default_net_no_mmds(), None);
 
+        let mut net = default_net_no_mmds();
+        net.set_mtu(1400);
+        validate_save_and_restore(net, None);
+
+        let mut net = default_net_no_mmds();
+        net.set_speed_duplex(1000, Duplex::Half);
+        validate_save_and_restore(net, None);
+
+        // The MAC address set by the driver is the one restored.
+        let mut net = default_net_no_mmds();
+        net.write_config(0, &[0x06, 0x00, 0x00, 0x00, 0x00, 0x02]);
+        validate_save_and_restore(net, None);
+
         // Check what happens if the MMIODeviceManager gives us the reference to the MMDS
         // data store even if this device does not have mmds ns configured.
         // The restore should be conservative and not configure the mmds ns.
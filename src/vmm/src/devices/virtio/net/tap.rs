@@ -7,7 +7,7 @@
 
 use std::fmt::{self, Debug};
 use std::fs::File;
-use std::io::Error as IoError;
+use std::io::{Error as IoError, ErrorKind, Read, Write};
 use std::os::raw::*;
 use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 
@@ -35,6 +35,10 @@ pub enum TapError {
     SetOffloadFlags(IoError),
     /// Error while setting size of the vnet header: {0}
     SetSizeOfVnetHdr(IoError),
+    /// Couldn't open the netlink socket querying the statistics of the interface: {0}
+    OpenNetlink(IoError),
+    /// Couldn't get the index of the interface: {0}
+    GetIfIndex(IoError),
 }
 
 const TUNTAP: ::std::os::raw::c_uint = 84;
@@ -42,6 +46,24 @@ ioctl_iow_nr!(TUNSETIFF, TUNTAP, 202, ::std::os::raw::c_int);
 ioctl_iow_nr!(TUNSETOFFLOAD, TUNTAP, 208, ::std::os::raw::c_uint);
 ioctl_iow_nr!(TUNSETVNETHDRSZ, TUNTAP, 216, ::std::os::raw::c_int);
 
+// As defined in the Linux UAPI:
+// https://elixir.bootlin.com/linux/v6.1/source/include/uapi/linux/netlink.h
+// https://elixir.bootlin.com/linux/v6.1/source/include/uapi/linux/rtnetlink.h
+// https://elixir.bootlin.com/linux/v6.1/source/include/uapi/linux/if_link.h
+const NLMSG_ERROR: u16 = 2;
+const NLM_F_REQUEST: u16 = 1;
+const RTM_NEWLINK: u16 = 16;
+const RTM_GETLINK: u16 = 18;
+const IFLA_STATS64: u16 = 23;
+// Sizes of `struct nlmsghdr`, `struct ifinfomsg` and `struct rtattr`.
+const NLMSG_HDR_LEN: usize = 16;
+const IFINFOMSG_LEN: usize = 16;
+const RTATTR_HDR_LEN: usize = 4;
+// Offset of `tx_dropped` in `struct rtnl_link_stats64`.
+const RTNL_LINK_STATS64_TX_DROPPED: usize = 56;
+// Large enough for the `RTM_NEWLINK` message describing a tap interface.
+const NETLINK_RECV_BUF_LEN: usize = 32 * 1024;
+
 /// Handle for a network tap interface.
 ///
 /// For now, this simply wraps the file descriptor for the tap device so methods
@@ -51,6 +73,10 @@ ioctl_iow_nr!(TUNSETVNETHDRSZ, TUNTAP, 216, ::std::os::raw::c_int);
 pub struct Tap {
     tap_file: File,
     pub(crate) if_name: [u8; IFACE_NAME_MAX_LEN],
+    // Route netlink socket used to query the statistics of the interface. It is opened along with
+    // the tap, since the seccomp filters forbid creating it later on.
+    netlink: File,
+    if_index: c_int,
 }
 
 // Returns a byte vector representing the contents of a null terminated C string which
@@ -142,11 +168,35 @@ impl Tap {
             )
             .execute(&tuntap, TUNSETIFF())
             .map_err(|io_error| TapError::IfreqExecuteError(io_error, if_name.to_owned()))?;
+        // SAFETY: Safe since only the name is accessed, and it's cloned out.
+        let if_name = unsafe { ifreq.ifr_ifrn.ifrn_name };
+
+        // SAFETY: Socket calls are safe because we pass constant arguments and verify the result.
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd < 0 {
+            return Err(TapError::OpenNetlink(IoError::last_os_error()));
+        }
+        // SAFETY: We just checked that the fd is valid.
+        let netlink = unsafe { File::from_raw_fd(fd) };
+
+        // Any socket can get the index of an interface.
+        let ifreq = IfReqBuilder::new()
+            .if_name(&if_name)
+            .execute(&netlink, c_ulong::from(generated::sockios::SIOCGIFINDEX))
+            .map_err(TapError::GetIfIndex)?;
 
         Ok(Tap {
             tap_file: tuntap,
-            // SAFETY: Safe since only the name is accessed, and it's cloned out.
-            if_name: unsafe { ifreq.ifr_ifrn.ifrn_name },
+            if_name,
+            netlink,
+            // SAFETY: Using this union variant is safe since `SIOCGIFINDEX` returns an integer.
+            if_index: unsafe { ifreq.ifr_ifru.ifru_ivalue },
         })
     }
 
@@ -180,6 +230,24 @@ impl Tap {
         Ok(())
     }
 
+    /// Returns the number of frames the kernel dropped, instead of queueing them for Firecracker to
+    /// read from the tap, since the interface was created.
+    pub fn tx_dropped(&self) -> Result<u64, IoError> {
+        // An `RTM_GETLINK` request for the interface, without attributes.
+        let mut request = [0u8; NLMSG_HDR_LEN + IFINFOMSG_LEN];
+        request[0..4].copy_from_slice(&u32::try_from(request.len()).unwrap().to_ne_bytes());
+        request[4..6].copy_from_slice(&RTM_GETLINK.to_ne_bytes());
+        request[6..8].copy_from_slice(&NLM_F_REQUEST.to_ne_bytes());
+        // `ifi_index` follows `ifi_family`, its padding and `ifi_type`.
+        request[NLMSG_HDR_LEN + 4..NLMSG_HDR_LEN + 8].copy_from_slice(&self.if_index.to_ne_bytes());
+        (&self.netlink).write_all(&request)?;
+
+        // The kernel answers the request synchronously, before `write` returns.
+        let mut response = vec![0u8; NETLINK_RECV_BUF_LEN];
+        let len = (&self.netlink).read(&mut response)?;
+        parse_tx_dropped(&response[..len])
+    }
+
     /// Write an `IoVecBuffer` to tap
     pub(crate) fn write_iovec(&mut self, buffer: &IoVecBuffer) -> Result<usize, IoError> {
         let iovcnt = i32::try_from(buffer.iovec_count()).unwrap();
@@ -209,6 +277,57 @@ impl Tap {
     }
 }
 
+fn invalid_netlink_response() -> IoError {
+    IoError::new(
+        ErrorKind::InvalidData,
+        "invalid RTM_NEWLINK netlink response",
+    )
+}
+
+// Returns `tx_dropped` from the `IFLA_STATS64` attribute of an `RTM_NEWLINK` netlink message.
+fn parse_tx_dropped(msg: &[u8]) -> Result<u64, IoError> {
+    let u16_at = |bytes: &[u8], offset: usize| -> Option<u16> {
+        Some(u16::from_ne_bytes(
+            bytes.get(offset..offset + 2)?.try_into().ok()?,
+        ))
+    };
+    let msg_type = u16_at(msg, 4).ok_or_else(invalid_netlink_response)?;
+    if msg_type == NLMSG_ERROR {
+        // `struct nlmsgerr` starts with the negated errno.
+        let error = msg
+            .get(NLMSG_HDR_LEN..NLMSG_HDR_LEN + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(i32::from_ne_bytes)
+            .ok_or_else(invalid_netlink_response)?;
+        return Err(IoError::from_raw_os_error(-error));
+    }
+    if msg_type != RTM_NEWLINK {
+        return Err(invalid_netlink_response());
+    }
+
+    let mut attrs = msg
+        .get(NLMSG_HDR_LEN + IFINFOMSG_LEN..)
+        .ok_or_else(invalid_netlink_response)?;
+    while let (Some(attr_len), Some(attr_type)) = (u16_at(attrs, 0), u16_at(attrs, 2)) {
+        let attr_len = usize::from(attr_len);
+        let attr = attrs
+            .get(RTATTR_HDR_LEN..attr_len)
+            .ok_or_else(invalid_netlink_response)?;
+        if attr_type == IFLA_STATS64 {
+            return attr
+                .get(RTNL_LINK_STATS64_TX_DROPPED..RTNL_LINK_STATS64_TX_DROPPED + 8)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(u64::from_ne_bytes)
+                .ok_or_else(invalid_netlink_response);
+        }
+        // Attributes are aligned to 4 bytes.
+        attrs = attrs
+            .get(attr_len.next_multiple_of(4)..)
+            .unwrap_or_default();
+    }
+    Err(invalid_netlink_response())
+}
+
 impl AsRawFd for Tap {
     fn as_raw_fd(&self) -> RawFd {
         self.tap_file.as_raw_fd()
@@ -288,6 +407,49 @@ pub mod tests {
         assert_eq!(tap.as_raw_fd(), tap.tap_file.as_raw_fd());
     }
 
+    #[test]
+    fn test_tx_dropped() {
+        let tap = Tap::open_named("").unwrap();
+        enable(&tap);
+        assert_eq!(tap.if_index, if_index(&tap));
+        assert_eq!(tap.tx_dropped().unwrap(), 0);
+
+        // Nothing reads the tap, so the frames exceeding its queue are dropped.
+        let tap_traffic_simulator = TapTrafficSimulator::new(if_index(&tap));
+        for _ in 0..1000 {
+            tap_traffic_simulator.push_tx_packet(&[0u8; 100]);
+        }
+        assert!(tap.tx_dropped().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_parse_tx_dropped() {
+        let mut msg = [0u8; NLMSG_HDR_LEN + IFINFOMSG_LEN + RTATTR_HDR_LEN + 64];
+        parse_tx_dropped(&msg[..2]).unwrap_err();
+        parse_tx_dropped(&msg).unwrap_err();
+
+        // An error message.
+        msg[4..6].copy_from_slice(&NLMSG_ERROR.to_ne_bytes());
+        msg[NLMSG_HDR_LEN..NLMSG_HDR_LEN + 4].copy_from_slice(&(-libc::ENODEV).to_ne_bytes());
+        let err = parse_tx_dropped(&msg).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENODEV));
+
+        // A message without the statistics.
+        msg[4..6].copy_from_slice(&RTM_NEWLINK.to_ne_bytes());
+        parse_tx_dropped(&msg[..NLMSG_HDR_LEN + IFINFOMSG_LEN]).unwrap_err();
+        // An attribute with an invalid length.
+        let attr = NLMSG_HDR_LEN + IFINFOMSG_LEN;
+        msg[attr..attr + 2].copy_from_slice(&2u16.to_ne_bytes());
+        parse_tx_dropped(&msg).unwrap_err();
+
+        msg[attr..attr + 2]
+            .copy_from_slice(&u16::try_from(RTATTR_HDR_LEN + 64).unwrap().to_ne_bytes());
+        msg[attr + 2..attr + 4].copy_from_slice(&IFLA_STATS64.to_ne_bytes());
+        let tx_dropped = attr + RTATTR_HDR_LEN + RTNL_LINK_STATS64_TX_DROPPED;
+        msg[tx_dropped..tx_dropped + 8].copy_from_slice(&42u64.to_ne_bytes());
+        assert_eq!(parse_tx_dropped(&msg).unwrap(), 42);
+    }
+
     #[test]
     fn test_write_iovec() {
         let mut tap = Tap::open_named("").unwrap();
@@ -49,6 +49,7 @@ pub fn default_net() -> Net {
     .unwrap();
     net.configure_mmds_network_stack(
         MmdsNetworkStack::default_ipv4_addr(),
+        MmdsNetworkStack::default_rate_limit_rps(),
         Arc::new(Mutex::new(Mmds::default())),
     );
     enable(&net.tap);
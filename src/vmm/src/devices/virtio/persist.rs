@@ -230,7 +230,7 @@ impl Persist<'_> for MmioTransport {
             features_select: self.features_select,
             acked_features_select: self.acked_features_select,
             queue_select: self.queue_select,
-            device_status: self.device_status,
+            device_status: self.device_status(),
             config_generation: self.config_generation,
         }
     }
@@ -247,7 +247,9 @@ impl Persist<'_> for MmioTransport {
         transport.features_select = state.features_select;
         transport.acked_features_select = state.acked_features_select;
         transport.queue_select = state.queue_select;
-        transport.device_status = state.device_status;
+        transport
+            .device_status
+            .store(state.device_status, Ordering::SeqCst);
         transport.config_generation = state.config_generation;
         Ok(transport)
     }
@@ -383,7 +385,7 @@ mod tests {
             self.acked_features_select == other.acked_features_select &&
                 self.features_select == other.features_select &&
                 self.queue_select == other.queue_select &&
-                self.device_status == other.device_status &&
+                self.device_status() == other.device_status() &&
                 self.config_generation == other.config_generation &&
                 self.interrupt_status.load(Ordering::SeqCst) == other.interrupt_status.load(Ordering::SeqCst) &&
                 // Only checking equality of device type, actual device (de)ser is tested by that
@@ -0,0 +1,534 @@
+// Copyright 2026 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::sync::Arc;
+
+use vm_memory::GuestMemoryError;
+use vm_memory::mmap::MmapRegionError;
+use vmm_sys_util::eventfd::EventFd;
+
+use super::metrics::METRICS;
+use super::{PMEM_ALIGNMENT, PMEM_NUM_QUEUES, PMEM_QUEUE};
+use crate::devices::DeviceError;
+use crate::devices::virtio::device::{DeviceState, IrqTrigger, IrqType, VirtioDevice};
+use crate::devices::virtio::generated::virtio_config::VIRTIO_F_VERSION_1;
+use crate::devices::virtio::queue::{DescriptorChain, FIRECRACKER_MAX_QUEUE_SIZE, Queue};
+use crate::devices::virtio::{ActivateError, TYPE_PMEM};
+use crate::logger::{IncMetric, debug, error};
+use crate::utils::u64_to_usize;
+use crate::vmm_config::pmem::PmemConfig;
+use crate::vstate::memory::{
+    ByteValued, Bytes, FileOffset, GuestAddress, GuestMemoryMmap, GuestMmapRegion,
+    MmapRegionBuilder,
+};
+
+/// The only request type of virtio-pmem, which flushes the guest writes to the backing file.
+const VIRTIO_PMEM_REQ_TYPE_FLUSH: u32 = 0;
+/// Response to a request that succeeded.
+const VIRTIO_PMEM_RESP_OK: u32 = 0;
+/// Response to a request that failed.
+const VIRTIO_PMEM_RESP_EIO: u32 = 1;
+/// Size of both the requests and the responses, which are a little-endian `u32`.
+const VIRTIO_PMEM_MSG_SIZE: u32 = 4;
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum PmemError {
+    /// Error while handling an Event file descriptor: {0}
+    EventFd(#[from] io::Error),
+    /// Cannot open the backing file: {0}
+    BackingFile(io::Error),
+    /// The size of the backing file ({0} bytes) is not a non-zero multiple of 2 MiB
+    BackingFileSize(u64),
+    /// Cannot map the backing file: {0}
+    Mmap(MmapRegionError),
+    /// Bad guest memory buffer: {0}
+    GuestMemory(#[from] GuestMemoryError),
+    /// The request descriptor chain is too short
+    DescriptorChainTooShort,
+    /// The request descriptor is write-only
+    UnexpectedWriteOnlyDescriptor,
+    /// The response descriptor is read-only
+    UnexpectedReadOnlyDescriptor,
+    /// Cannot flush the backing file: {0}
+    Flush(io::Error),
+}
+
+/// Layout of the configuration space of virtio-pmem devices.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub(crate) struct ConfigSpace {
+    /// Guest physical address of the memory of the device.
+    pub start: u64,
+    /// Size of the memory of the device.
+    pub size: u64,
+}
+
+// SAFETY: Safe because ConfigSpace only contains plain data.
+unsafe impl ByteValued for ConfigSpace {}
+
+#[derive(Debug)]
+pub struct Pmem {
+    // VirtIO fields
+    avail_features: u64,
+    acked_features: u64,
+    activate_event: EventFd,
+
+    // Transport fields
+    device_state: DeviceState,
+    pub(crate) queues: Vec<Queue>,
+    queue_events: Vec<EventFd>,
+    irq_trigger: IrqTrigger,
+
+    // Device specific fields
+    config: PmemConfig,
+    config_space: ConfigSpace,
+    file: Arc<File>,
+    mmap: GuestMmapRegion,
+}
+
+impl Pmem {
+    /// Creates a virtio-pmem device and maps its backing file, which is mapped in the guest
+    /// physical address space once the guest address of the device is set.
+    pub fn new(config: PmemConfig) -> Result<Self, PmemError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(!config.is_read_only)
+            .open(&config.path_on_host)
+            .map_err(PmemError::BackingFile)?;
+        let size = file.metadata().map_err(PmemError::BackingFile)?.len();
+        if size == 0 || size % PMEM_ALIGNMENT != 0 {
+            return Err(PmemError::BackingFileSize(size));
+        }
+
+        // Guest writes go straight to the page cache of the backing file, from where flush
+        // requests write them back.
+        let file = Arc::new(file);
+        let prot = if config.is_read_only {
+            libc::PROT_READ
+        } else {
+            libc::PROT_READ | libc::PROT_WRITE
+        };
+        let mmap = MmapRegionBuilder::new_with_bitmap(u64_to_usize(size), None)
+            .with_file_offset(FileOffset::from_arc(Arc::clone(&file), 0))
+            .with_mmap_prot(prot)
+            .with_mmap_flags(libc::MAP_SHARED | libc::MAP_NORESERVE)
+            .build()
+            .map_err(PmemError::Mmap)?;
+
+        let queue_events = (0..PMEM_NUM_QUEUES)
+            .map(|_| EventFd::new(libc::EFD_NONBLOCK))
+            .collect::<Result<Vec<EventFd>, io::Error>>()?;
+
+        Ok(Self {
+            avail_features: 1 << VIRTIO_F_VERSION_1,
+            acked_features: 0u64,
+            activate_event: EventFd::new(libc::EFD_NONBLOCK)?,
+            device_state: DeviceState::Inactive,
+            queues: vec![Queue::new(FIRECRACKER_MAX_QUEUE_SIZE); PMEM_NUM_QUEUES],
+            queue_events,
+            irq_trigger: IrqTrigger::new()?,
+            config,
+            config_space: ConfigSpace { start: 0, size },
+            file,
+            mmap,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.config.drive_id
+    }
+
+    /// Returns the configuration the device was created with.
+    pub fn config(&self) -> PmemConfig {
+        self.config.clone()
+    }
+
+    pub fn root_device(&self) -> bool {
+        self.config.is_root_device
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.config.is_read_only
+    }
+
+    /// Returns the size of the memory of the device, which is the size of its backing file.
+    pub fn size(&self) -> u64 {
+        self.config_space.size
+    }
+
+    /// Returns the mapping of the backing file.
+    pub fn mmap(&self) -> &GuestMmapRegion {
+        &self.mmap
+    }
+
+    /// Sets the guest physical address of the memory of the device, which must be set before the
+    /// device is attached.
+    pub fn set_start_addr(&mut self, addr: GuestAddress) {
+        self.config_space.start = addr.0;
+    }
+
+    fn signal_used_queue(&self) -> Result<(), DeviceError> {
+        self.irq_trigger
+            .trigger_irq(IrqType::Vring)
+            .map_err(DeviceError::FailedSignalingIrq)
+    }
+
+    fn flush(&self) -> Result<(), PmemError> {
+        METRICS.flush_count.inc();
+        // The backing file is mapped shared, so syncing the file also writes back the guest
+        // writes to its mapping.
+        self.file.sync_all().map_err(PmemError::Flush)
+    }
+
+    /// Handles a request, returning the number of bytes written to the response descriptor.
+    fn handle_request(
+        &self,
+        mem: &GuestMemoryMmap,
+        head: DescriptorChain,
+    ) -> Result<u32, PmemError> {
+        if head.is_write_only() {
+            return Err(PmemError::UnexpectedWriteOnlyDescriptor);
+        }
+        if head.len < VIRTIO_PMEM_MSG_SIZE {
+            return Err(PmemError::DescriptorChainTooShort);
+        }
+        let resp_desc = head
+            .next_descriptor()
+            .ok_or(PmemError::DescriptorChainTooShort)?;
+        if !resp_desc.is_write_only() {
+            return Err(PmemError::UnexpectedReadOnlyDescriptor);
+        }
+        if resp_desc.len < VIRTIO_PMEM_MSG_SIZE {
+            return Err(PmemError::DescriptorChainTooShort);
+        }
+
+        let req_type = u32::from_le(mem.read_obj::<u32>(head.addr)?);
+        let resp = match req_type {
+            VIRTIO_PMEM_REQ_TYPE_FLUSH => match self.flush() {
+                Ok(()) => VIRTIO_PMEM_RESP_OK,
+                Err(err) => {
+                    error!("pmem: {err}");
+                    METRICS.flush_fails.inc();
+                    VIRTIO_PMEM_RESP_EIO
+                }
+            },
+            _ => {
+                error!("pmem: Unknown request type: {req_type}");
+                METRICS.event_fails.inc();
+                VIRTIO_PMEM_RESP_EIO
+            }
+        };
+        mem.write_obj(resp.to_le(), resp_desc.addr)?;
+
+        Ok(VIRTIO_PMEM_MSG_SIZE)
+    }
+
+    fn process_pmem_queue(&mut self) {
+        // This is safe since we checked in the event handler that the device is activated.
+        let mem = self.device_state.mem().unwrap();
+        let mut used_any = false;
+
+        while let Some(head) = self.queues[PMEM_QUEUE].pop() {
+            let index = head.index;
+            METRICS.event_count.inc();
+
+            let len = self.handle_request(mem, head).unwrap_or_else(|err| {
+                error!("pmem: Invalid request: {err}");
+                METRICS.event_fails.inc();
+                0
+            });
+            debug!("pmem: Handled request {index}");
+
+            if let Err(err) = self.queues[PMEM_QUEUE].add_used(index, len) {
+                error!("pmem: Could not add used descriptor to queue: {err}");
+                METRICS.event_fails.inc();
+                // If we are not able to add a buffer to the used queue, something
+                // is probably seriously wrong, so just stop processing additional
+                // buffers
+                break;
+            }
+            used_any = true;
+        }
+
+        if used_any {
+            self.signal_used_queue().unwrap_or_else(|err| {
+                error!("pmem: {err:?}");
+                METRICS.event_fails.inc()
+            });
+        }
+    }
+
+    pub(crate) fn process_pmem_queue_event(&mut self) {
+        if let Err(err) = self.queue_events[PMEM_QUEUE].read() {
+            error!("Failed to read pmem queue event: {err}");
+            METRICS.event_fails.inc();
+        } else {
+            self.process_pmem_queue();
+        }
+    }
+
+    pub fn process_virtio_queues(&mut self) {
+        self.process_pmem_queue();
+    }
+
+    pub(crate) fn activate_event(&self) -> &EventFd {
+        &self.activate_event
+    }
+}
+
+impl VirtioDevice for Pmem {
+    fn device_type(&self) -> u32 {
+        TYPE_PMEM
+    }
+
+    fn queues(&self) -> &[Queue] {
+        &self.queues
+    }
+
+    fn queues_mut(&mut self) -> &mut [Queue] {
+        &mut self.queues
+    }
+
+    fn queue_events(&self) -> &[EventFd] {
+        &self.queue_events
+    }
+
+    fn interrupt_trigger(&self) -> &IrqTrigger {
+        &self.irq_trigger
+    }
+
+    fn avail_features(&self) -> u64 {
+        self.avail_features
+    }
+
+    fn acked_features(&self) -> u64 {
+        self.acked_features
+    }
+
+    fn set_acked_features(&mut self, acked_features: u64) {
+        self.acked_features = acked_features;
+    }
+
+    fn read_config(&self, offset: u64, data: &mut [u8]) {
+        if let Some(config_space_bytes) = self.config_space.as_slice().get(u64_to_usize(offset)..) {
+            let len = config_space_bytes.len().min(data.len());
+            data[..len].copy_from_slice(&config_space_bytes[..len]);
+        } else {
+            error!("Failed to read config space");
+        }
+    }
+
+    fn write_config(&mut self, _offset: u64, _data: &[u8]) {
+        // The configuration space of virtio-pmem devices is read-only.
+        METRICS.cfg_fails.inc();
+    }
+
+    fn is_activated(&self) -> bool {
+        self.device_state.is_activated()
+    }
+
+    fn activate(&mut self, mem: GuestMemoryMmap) -> Result<(), ActivateError> {
+        for q in self.queues.iter_mut() {
+            q.initialize(&mem)
+                .map_err(ActivateError::QueueMemoryError)?;
+        }
+
+        self.activate_event.write(1).map_err(|_| {
+            METRICS.activate_fails.inc();
+            ActivateError::EventFd
+        })?;
+        self.device_state = DeviceState::Activated(mem);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+    use crate::check_metric_after_block;
+    use crate::devices::virtio::queue::VIRTQ_DESC_F_WRITE;
+    use crate::devices::virtio::test_utils::test::{
+        VirtioTestDevice, VirtioTestHelper, create_virtio_mem,
+    };
+
+    impl VirtioTestDevice for Pmem {
+        fn set_queues(&mut self, queues: Vec<Queue>) {
+            self.queues = queues;
+        }
+
+        fn num_queues() -> usize {
+            PMEM_NUM_QUEUES
+        }
+    }
+
+    fn backing_file(size: u64) -> TempFile {
+        let file = TempFile::new().unwrap();
+        file.as_file().set_len(size).unwrap();
+        file
+    }
+
+    fn default_config(file: &TempFile) -> PmemConfig {
+        PmemConfig {
+            drive_id: "pmem0".to_string(),
+            path_on_host: file.as_path().to_str().unwrap().to_string(),
+            is_root_device: false,
+            is_read_only: false,
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        let file = backing_file(PMEM_ALIGNMENT);
+        let pmem = Pmem::new(default_config(&file)).unwrap();
+
+        assert_eq!(pmem.id(), "pmem0");
+        assert_eq!(pmem.config(), default_config(&file));
+        assert_eq!(pmem.device_type(), TYPE_PMEM);
+        assert_eq!(pmem.avail_features(), 1 << VIRTIO_F_VERSION_1);
+        assert_eq!(pmem.size(), PMEM_ALIGNMENT);
+        assert_eq!(pmem.mmap().size(), u64_to_usize(PMEM_ALIGNMENT));
+        assert!(!pmem.is_activated());
+
+        for size in [0, PMEM_ALIGNMENT + 0x1000] {
+            let file = backing_file(size);
+            assert!(matches!(
+                Pmem::new(default_config(&file)),
+                Err(PmemError::BackingFileSize(s)) if s == size
+            ));
+        }
+
+        let mut config = default_config(&file);
+        config.path_on_host = "/invalid/path".to_string();
+        assert!(matches!(Pmem::new(config), Err(PmemError::BackingFile(_))));
+    }
+
+    #[test]
+    fn test_mmap() {
+        let file = backing_file(PMEM_ALIGNMENT);
+        let pmem = Pmem::new(default_config(&file)).unwrap();
+
+        // Writes to the mapping land in the backing file.
+        pmem.mmap()
+            .as_volatile_slice()
+            .unwrap()
+            .write_obj(0xabu8, 0x1000)
+            .unwrap();
+        let contents = std::fs::read(file.as_path()).unwrap();
+        assert_eq!(contents[0x1000], 0xab);
+    }
+
+    #[test]
+    fn test_config_space() {
+        let file = backing_file(2 * PMEM_ALIGNMENT);
+        let mut pmem = Pmem::new(default_config(&file)).unwrap();
+        pmem.set_start_addr(GuestAddress(0x1_0000_0000));
+
+        let mut data = [0u8; 16];
+        pmem.read_config(0, &mut data);
+        assert_eq!(data[..8], 0x1_0000_0000u64.to_le_bytes());
+        assert_eq!(data[8..], (2 * PMEM_ALIGNMENT).to_le_bytes());
+
+        let mut data = [0u8; 8];
+        pmem.read_config(8, &mut data);
+        assert_eq!(data, (2 * PMEM_ALIGNMENT).to_le_bytes());
+
+        // Writes are ignored.
+        check_metric_after_block!(&METRICS.cfg_fails, 1, pmem.write_config(0, &[0; 8]));
+        let mut data = [0u8; 8];
+        pmem.read_config(0, &mut data);
+        assert_eq!(data, 0x1_0000_0000u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_handle_request() {
+        let mem = create_virtio_mem();
+        let file = backing_file(PMEM_ALIGNMENT);
+        let mut th = VirtioTestHelper::<Pmem>::new(&mem, Pmem::new(default_config(&file)).unwrap());
+        th.activate_device(&mem);
+
+        // A flush request.
+        th.add_desc_chain(PMEM_QUEUE, 0, &[(0, 4, 0), (1, 4, VIRTQ_DESC_F_WRITE)]);
+        let head = th.device().queues_mut()[PMEM_QUEUE].pop().unwrap();
+        let resp_addr = head.next_descriptor().unwrap().addr;
+        mem.write_obj(VIRTIO_PMEM_REQ_TYPE_FLUSH, head.addr)
+            .unwrap();
+        mem.write_obj(0xffu32, resp_addr).unwrap();
+        assert_eq!(th.device().handle_request(&mem, head).unwrap(), 4);
+        assert_eq!(mem.read_obj::<u32>(resp_addr).unwrap(), VIRTIO_PMEM_RESP_OK);
+
+        // Malformed requests.
+        th.add_desc_chain(PMEM_QUEUE, 0, &[(0, 4, 0)]);
+        let head = th.device().queues_mut()[PMEM_QUEUE].pop().unwrap();
+        assert!(matches!(
+            th.device().handle_request(&mem, head),
+            Err(PmemError::DescriptorChainTooShort)
+        ));
+        th.add_desc_chain(PMEM_QUEUE, 0, &[(0, 2, 0), (1, 4, VIRTQ_DESC_F_WRITE)]);
+        let head = th.device().queues_mut()[PMEM_QUEUE].pop().unwrap();
+        assert!(matches!(
+            th.device().handle_request(&mem, head),
+            Err(PmemError::DescriptorChainTooShort)
+        ));
+        th.add_desc_chain(PMEM_QUEUE, 0, &[(0, 4, VIRTQ_DESC_F_WRITE)]);
+        let head = th.device().queues_mut()[PMEM_QUEUE].pop().unwrap();
+        assert!(matches!(
+            th.device().handle_request(&mem, head),
+            Err(PmemError::UnexpectedWriteOnlyDescriptor)
+        ));
+        th.add_desc_chain(PMEM_QUEUE, 0, &[(0, 4, 0), (1, 4, 0)]);
+        let head = th.device().queues_mut()[PMEM_QUEUE].pop().unwrap();
+        assert!(matches!(
+            th.device().handle_request(&mem, head),
+            Err(PmemError::UnexpectedReadOnlyDescriptor)
+        ));
+    }
+
+    #[test]
+    fn test_pmem_event() {
+        let mem = create_virtio_mem();
+        let file = backing_file(PMEM_ALIGNMENT);
+        let mut th = VirtioTestHelper::<Pmem>::new(&mem, Pmem::new(default_config(&file)).unwrap());
+        th.activate_device(&mem);
+
+        // Guest memory is zeroed, so both requests are flush requests.
+        th.add_desc_chain(PMEM_QUEUE, 0, &[(0, 4, 0), (1, 4, VIRTQ_DESC_F_WRITE)]);
+        th.add_desc_chain(PMEM_QUEUE, 0x100, &[(2, 4, 0), (3, 4, VIRTQ_DESC_F_WRITE)]);
+        let event_count = METRICS.event_count.count();
+        let event_fails = METRICS.event_fails.count();
+        check_metric_after_block!(
+            &METRICS.flush_count,
+            2,
+            assert_eq!(th.emulate_for_msec(100).unwrap(), 1)
+        );
+        assert_eq!(METRICS.event_count.count(), event_count + 2);
+        assert_eq!(METRICS.event_fails.count(), event_fails);
+
+        // An unknown request fails, but still gets a response.
+        th.add_desc_chain(PMEM_QUEUE, 0, &[(0, 4, 0), (1, 4, VIRTQ_DESC_F_WRITE)]);
+        let head = th.device().queues_mut()[PMEM_QUEUE].pop().unwrap();
+        let resp_addr = head.next_descriptor().unwrap().addr;
+        mem.write_obj(5u32, head.addr).unwrap();
+        th.device().queues_mut()[PMEM_QUEUE].undo_pop();
+        check_metric_after_block!(
+            &METRICS.event_fails,
+            1,
+            assert_eq!(th.emulate_for_msec(100).unwrap(), 1)
+        );
+        assert_eq!(
+            mem.read_obj::<u32>(resp_addr).unwrap(),
+            VIRTIO_PMEM_RESP_EIO
+        );
+
+        // A malformed request is completed without a response.
+        th.add_desc_chain(PMEM_QUEUE, 0, &[(0, 4, 0)]);
+        check_metric_after_block!(
+            &METRICS.event_fails,
+            1,
+            assert_eq!(th.emulate_for_msec(100).unwrap(), 1)
+        );
+    }
+}
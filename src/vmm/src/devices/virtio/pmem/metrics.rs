@@ -0,0 +1,94 @@
+// Copyright 2026 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines the metrics system for pmem devices.
+//!
+//! # Metrics format
+//! The metrics are flushed in JSON when requested by vmm::logger::metrics::METRICS.write().
+//!
+//! ## JSON example with metrics:
+//! ```json
+//!  "pmem": {
+//!     "activate_fails": "SharedIncMetric",
+//!     "cfg_fails": "SharedIncMetric",
+//!     "event_count": "SharedIncMetric",
+//!     ...
+//!  }
+//! }
+//! ```
+//! The `pmem` field in the example above is a serializable `PmemDeviceMetrics` structure
+//! collecting metrics such as `activate_fails`, `flush_count` etc. for the pmem devices.
+//! Pmem devices have no per device metrics, `pmem` aggregates the metrics of all of them.
+//!
+//! # Design
+//! The main design goals of this system are:
+//! * Have a consistent approach of keeping device related metrics in the individual devices
+//!   modules.
+//! * Rely on `serde` to provide the actual serialization for writing the metrics.
+//!
+//! The system implements 1 type of metrics:
+//! * Shared Incremental Metrics (SharedIncMetrics) - dedicated for the metrics which need a counter
+//!   (i.e the number of times an API request failed). These metrics are reset upon flush.
+
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+use crate::logger::SharedIncMetric;
+
+/// Stores aggregated pmem metrics
+pub(super) static METRICS: PmemDeviceMetrics = PmemDeviceMetrics::new();
+
+/// Called by METRICS.flush(), this function facilitates serialization of pmem device metrics.
+pub fn flush_metrics<S: Serializer>(serializer: S) -> Result<S::Ok, S::Error> {
+    let mut seq = serializer.serialize_map(Some(1))?;
+    seq.serialize_entry("pmem", &METRICS)?;
+    seq.end()
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct PmemDeviceMetrics {
+    /// Number of device activation failures
+    pub activate_fails: SharedIncMetric,
+    /// Number of guest writes to the read-only configuration space
+    pub cfg_fails: SharedIncMetric,
+    /// Number of requests handled
+    pub event_count: SharedIncMetric,
+    /// Number of invalid requests and queue event handling failures
+    pub event_fails: SharedIncMetric,
+    /// Number of flush requests
+    pub flush_count: SharedIncMetric,
+    /// Number of flush requests that failed to sync the backing file
+    pub flush_fails: SharedIncMetric,
+}
+impl PmemDeviceMetrics {
+    /// Const default construction.
+    const fn new() -> Self {
+        Self {
+            activate_fails: SharedIncMetric::new(),
+            cfg_fails: SharedIncMetric::new(),
+            event_count: SharedIncMetric::new(),
+            event_fails: SharedIncMetric::new(),
+            flush_count: SharedIncMetric::new(),
+            flush_fails: SharedIncMetric::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::logger::IncMetric;
+
+    #[test]
+    fn test_pmem_dev_metrics() {
+        let pmem_metrics: PmemDeviceMetrics = PmemDeviceMetrics::new();
+        let pmem_metrics_local: String = serde_json::to_string(&pmem_metrics).unwrap();
+        // the 1st serialize flushes the metrics and resets values to 0 so that
+        // we can compare the values with local metrics.
+        serde_json::to_string(&METRICS).unwrap();
+        let pmem_metrics_global: String = serde_json::to_string(&METRICS).unwrap();
+        assert_eq!(pmem_metrics_local, pmem_metrics_global);
+        pmem_metrics.flush_count.inc();
+        assert_eq!(pmem_metrics.flush_count.count(), 1);
+    }
+}
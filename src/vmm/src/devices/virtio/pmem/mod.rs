@@ -0,0 +1,18 @@
+// Copyright 2026 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements a virtio-pmem device, which maps a host file into the guest physical address space
+//! so that the guest can access it directly, bypassing its page cache (DAX).
+
+pub mod device;
+mod event_handler;
+pub mod metrics;
+
+pub use self::device::{Pmem, PmemError};
+
+pub(crate) const PMEM_NUM_QUEUES: usize = 1;
+
+pub(crate) const PMEM_QUEUE: usize = 0;
+
+/// Alignment of the size of the backing files and of their guest physical addresses.
+pub const PMEM_ALIGNMENT: u64 = 0x20_0000;
@@ -0,0 +1,209 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Device side of the virtio transports.
+//!
+//! Devices signal the driver and inspect the transport state through [`VirtioTransport`], so that
+//! they do not depend on the transport they are plugged into.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::device::{IrqTrigger, IrqType};
+use super::device_status;
+
+/// Errors triggered by a virtio transport.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum TransportError {
+    /// The operation is not implemented by the transport.
+    Unimplemented,
+    /// Failed to signal the interrupt: {0}
+    Interrupt(std::io::Error),
+    /// Invalid device status transition: {0:#x} -> {1:#x}
+    InvalidStatus(u32, u32),
+}
+
+/// Operations a virtio device performs on the transport it is plugged into.
+pub trait VirtioTransport: Debug + Send {
+    /// Notifies the driver that the device used buffers of the queue at `queue_index`.
+    fn notify_queue(&self, queue_index: usize) -> Result<(), TransportError>;
+
+    /// Returns the pending interrupt status bits.
+    fn read_isr(&self) -> Result<u32, TransportError>;
+
+    /// Acknowledges the interrupt status bits set in `isr`.
+    fn write_isr(&self, isr: u32) -> Result<(), TransportError>;
+
+    /// Returns the device status.
+    fn read_status(&self) -> Result<u32, TransportError>;
+
+    /// Sets the device status, if the transition from the current status is valid.
+    fn write_status(&self, status: u32) -> Result<(), TransportError>;
+}
+
+/// Device side of the [MMIO transport](super::mmio::MmioTransport).
+///
+/// The interrupt and the device status are shared with the device, which hands them to the MMIO
+/// transport through [`VirtioDevice`](super::device::VirtioDevice), so that the driver sees them
+/// through the MMIO registers.
+#[derive(Debug)]
+pub struct VirtioMmioTransport {
+    irq_trigger: IrqTrigger,
+    device_status: Arc<AtomicU32>,
+}
+
+impl VirtioMmioTransport {
+    /// Creates the device side of an MMIO transport, sharing the interrupt and the device status
+    /// of a device.
+    pub fn new(irq_trigger: &IrqTrigger, device_status: Arc<AtomicU32>) -> std::io::Result<Self> {
+        Ok(Self {
+            irq_trigger: IrqTrigger {
+                irq_status: Arc::clone(&irq_trigger.irq_status),
+                irq_evt: irq_trigger.irq_evt.try_clone()?,
+            },
+            device_status,
+        })
+    }
+}
+
+impl VirtioTransport for VirtioMmioTransport {
+    fn notify_queue(&self, _queue_index: usize) -> Result<(), TransportError> {
+        // The MMIO transport has a single interrupt shared by all the queues.
+        self.irq_trigger
+            .trigger_irq(IrqType::Vring)
+            .map_err(TransportError::Interrupt)
+    }
+
+    fn read_isr(&self) -> Result<u32, TransportError> {
+        Ok(self.irq_trigger.irq_status.load(Ordering::SeqCst))
+    }
+
+    fn write_isr(&self, isr: u32) -> Result<(), TransportError> {
+        self.irq_trigger
+            .irq_status
+            .fetch_and(!isr, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn read_status(&self) -> Result<u32, TransportError> {
+        Ok(self.device_status.load(Ordering::SeqCst))
+    }
+
+    fn write_status(&self, status: u32) -> Result<(), TransportError> {
+        // Besides the steps of the driver initialization sequence, the device can only ask the
+        // driver to reset it.
+        self.device_status
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                (device_status::is_next_init_step(current, status)
+                    || status == current | device_status::DEVICE_NEEDS_RESET)
+                    .then_some(status)
+            })
+            .map(|_| ())
+            .map_err(|current| TransportError::InvalidStatus(current, status))
+    }
+}
+
+/// Device side of the PCI transport.
+///
+/// Firecracker does not support the PCI transport yet, so none of the operations are implemented.
+#[derive(Debug, Default)]
+pub struct VirtioPciTransport;
+
+impl VirtioTransport for VirtioPciTransport {
+    fn notify_queue(&self, _queue_index: usize) -> Result<(), TransportError> {
+        Err(TransportError::Unimplemented)
+    }
+
+    fn read_isr(&self) -> Result<u32, TransportError> {
+        Err(TransportError::Unimplemented)
+    }
+
+    fn write_isr(&self, _isr: u32) -> Result<(), TransportError> {
+        Err(TransportError::Unimplemented)
+    }
+
+    fn read_status(&self) -> Result<u32, TransportError> {
+        Err(TransportError::Unimplemented)
+    }
+
+    fn write_status(&self, _status: u32) -> Result<(), TransportError> {
+        Err(TransportError::Unimplemented)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::virtio::mmio::{VIRTIO_MMIO_INT_CONFIG, VIRTIO_MMIO_INT_VRING};
+
+    #[test]
+    fn test_mmio_transport() {
+        let irq_trigger = IrqTrigger::new().unwrap();
+        let device_status = Arc::new(AtomicU32::new(device_status::INIT));
+        let transport = VirtioMmioTransport::new(&irq_trigger, Arc::clone(&device_status)).unwrap();
+        assert_eq!(transport.read_isr().unwrap(), 0);
+        assert_eq!(transport.read_status().unwrap(), device_status::INIT);
+
+        transport.notify_queue(1).unwrap();
+        assert!(irq_trigger.has_pending_irq(IrqType::Vring));
+        irq_trigger.trigger_irq(IrqType::Config).unwrap();
+        assert_eq!(
+            transport.read_isr().unwrap(),
+            VIRTIO_MMIO_INT_VRING | VIRTIO_MMIO_INT_CONFIG
+        );
+        transport.write_isr(VIRTIO_MMIO_INT_VRING).unwrap();
+        assert_eq!(transport.read_isr().unwrap(), VIRTIO_MMIO_INT_CONFIG);
+
+        transport.write_status(device_status::ACKNOWLEDGE).unwrap();
+        assert_eq!(
+            device_status.load(Ordering::SeqCst),
+            device_status::ACKNOWLEDGE
+        );
+        // Steps of the initialization sequence cannot be skipped.
+        let skipped = device_status::ACKNOWLEDGE | device_status::FEATURES_OK;
+        assert!(matches!(
+            transport.write_status(skipped),
+            Err(TransportError::InvalidStatus(device_status::ACKNOWLEDGE, status)) if status == skipped
+        ));
+        transport
+            .write_status(device_status::ACKNOWLEDGE | device_status::DEVICE_NEEDS_RESET)
+            .unwrap();
+        assert_eq!(
+            transport.read_status().unwrap(),
+            device_status::ACKNOWLEDGE | device_status::DEVICE_NEEDS_RESET
+        );
+
+        // Signaling fails once the interrupt eventfd is full.
+        irq_trigger.irq_evt.write(u64::MAX - 1).unwrap();
+        assert!(matches!(
+            transport.notify_queue(0),
+            Err(TransportError::Interrupt(_))
+        ));
+    }
+
+    #[test]
+    fn test_pci_transport() {
+        let transport = VirtioPciTransport;
+        assert!(matches!(
+            transport.notify_queue(0),
+            Err(TransportError::Unimplemented)
+        ));
+        assert!(matches!(
+            transport.read_isr(),
+            Err(TransportError::Unimplemented)
+        ));
+        assert!(matches!(
+            transport.write_isr(0),
+            Err(TransportError::Unimplemented)
+        ));
+        assert!(matches!(
+            transport.read_status(),
+            Err(TransportError::Unimplemented)
+        ));
+        assert!(matches!(
+            transport.write_status(0),
+            Err(TransportError::Unimplemented)
+        ));
+    }
+}
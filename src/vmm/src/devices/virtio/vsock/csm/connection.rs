@@ -89,7 +89,7 @@ use vmm_sys_util::epoll::EventSet;
 
 use super::super::defs::uapi;
 use super::super::{VsockChannel, VsockEpollListener, VsockError};
-use super::txbuf::TxBuf;
+use super::txbuf::{TxBuf, TxMsgBuf};
 use super::{ConnState, PendingRx, PendingRxSet, VsockCsmError, defs};
 use crate::devices::virtio::vsock::metrics::METRICS;
 use crate::devices::virtio::vsock::packet::{VsockPacketHeader, VsockPacketRx, VsockPacketTx};
@@ -98,10 +98,18 @@ use crate::utils::wrap_usize_to_u32;
 
 /// Trait that vsock connection backends need to implement.
 ///
-/// Used as an alias for `ReadVolatile + Write + WriteVolatile + AsRawFd`
+/// Besides acting as an alias for `ReadVolatile + Write + WriteVolatile + AsRawFd`
 /// (sadly, trait aliases are not supported,
-/// <https://github.com/rust-lang/rfcs/pull/1733#issuecomment-243840014>).
-pub trait VsockConnectionBackend: ReadVolatile + Write + WriteVolatile + AsRawFd {}
+/// <https://github.com/rust-lang/rfcs/pull/1733#issuecomment-243840014>), it provides the
+/// record-oriented I/O used by seqpacket connections.
+pub trait VsockConnectionBackend: ReadVolatile + Write + WriteVolatile + AsRawFd {
+    /// Write `buf` to the stream as a single record.
+    fn send_record(&mut self, buf: &[u8]) -> std::io::Result<usize>;
+
+    /// Read a single, whole record from the stream. An empty record means the stream was
+    /// closed down.
+    fn recv_record(&mut self) -> std::io::Result<Vec<u8>>;
+}
 
 /// A self-managing connection object, that handles communication between a guest-side AF_VSOCK
 /// socket and a host-side `ReadVolatile + Write + WriteVolatile + AsRawFd` stream.
@@ -117,10 +125,19 @@ pub struct VsockConnection<S: VsockConnectionBackend> {
     local_port: u32,
     /// The peer (guest) port.
     peer_port: u32,
+    /// The socket type (`uapi::VSOCK_TYPE_STREAM` or `uapi::VSOCK_TYPE_SEQPACKET`).
+    pkt_type: u16,
     /// The (connected) host-side stream.
     stream: S,
-    /// The TX buffer for this connection.
+    /// The TX buffer for this connection, used by stream connections.
     tx_buf: TxBuf,
+    /// The TX buffer for this connection, used by seqpacket connections.
+    tx_msg_buf: TxMsgBuf,
+    /// The record last read from a seqpacket host stream, that is yet to be fully delivered
+    /// to the peer.
+    rx_msg: Vec<u8>,
+    /// How much of `self.rx_msg` has already been delivered to the peer.
+    rx_msg_offset: usize,
     /// Total number of bytes that have been successfully written to `self.stream`, either
     /// directly, or flushed from `self.tx_buf`.
     fwd_cnt: Wrapping<u32>,
@@ -219,7 +236,13 @@ where
             let max_len = std::cmp::min(pkt.buf_size(), self.peer_avail_credit());
 
             // Read data from the stream straight to the RX buffer, for maximum throughput.
-            match pkt.read_at_offset_from(&mut self.stream, 0, max_len) {
+            // Seqpacket connections go through a record at a time, instead.
+            let read_res = if self.is_seqpacket() {
+                self.read_msg_bytes(pkt, max_len)
+            } else {
+                pkt.read_at_offset_from(&mut self.stream, 0, max_len)
+            };
+            match read_res {
                 Ok(read_cnt) => {
                     if read_cnt == 0 {
                         // A 0-length read means the host stream was closed down. In that case,
@@ -313,8 +336,12 @@ where
                     return Ok(());
                 }
 
-                // Unwrapping here is safe, since we just checked `pkt.buf()` above.
-                if let Err(err) = self.send_bytes(pkt) {
+                let send_res = if self.is_seqpacket() {
+                    self.send_msg_bytes(pkt)
+                } else {
+                    self.send_bytes(pkt)
+                };
+                if let Err(err) = send_res {
                     // If we can't write to the host stream, that's an unrecoverable error, so
                     // we'll terminate this connection.
                     warn!(
@@ -350,7 +377,7 @@ where
                 let send_off = pkt.hdr.flags() & uapi::VSOCK_FLAGS_SHUTDOWN_SEND != 0;
                 self.state = ConnState::PeerClosed(recv_off, send_off);
                 if recv_off && send_off {
-                    if self.tx_buf.is_empty() {
+                    if !self.tx_pending() {
                         self.pending_rx.insert(PendingRx::Rst);
                     } else {
                         self.expiry = Some(
@@ -367,7 +394,7 @@ where
             {
                 *recv_off = *recv_off || (pkt.hdr.flags() & uapi::VSOCK_FLAGS_SHUTDOWN_RCV != 0);
                 *send_off = *send_off || (pkt.hdr.flags() & uapi::VSOCK_FLAGS_SHUTDOWN_SEND != 0);
-                if *recv_off && *send_off && self.tx_buf.is_empty() {
+                if *recv_off && *send_off && !self.tx_pending() {
                     self.pending_rx.insert(PendingRx::Rst);
                 }
             }
@@ -396,6 +423,18 @@ where
             }
         };
 
+        // A seqpacket record that was only partially delivered, for lack of peer credit, can
+        // carry on as soon as the peer has freed up some buffer space.
+        if self.rx_msg_pending()
+            && !self.need_credit_update_from_peer()
+            && matches!(
+                self.state,
+                ConnState::Established | ConnState::PeerClosed(false, _)
+            )
+        {
+            self.pending_rx.insert(PendingRx::Rw);
+        }
+
         Ok(())
     }
 
@@ -430,7 +469,7 @@ where
     /// - data can be written to the host stream, and the TX buffer needs to be flushed.
     fn get_polled_evset(&self) -> EventSet {
         let mut evset = EventSet::empty();
-        if !self.tx_buf.is_empty() {
+        if self.tx_pending() {
             // There's data waiting in the TX buffer, so we are interested in being notified
             // when writing to the host stream wouldn't block.
             evset.insert(EventSet::OUT);
@@ -440,6 +479,9 @@ where
         match self.state {
             ConnState::Killed | ConnState::LocalClosed | ConnState::PeerClosed(true, _) => (),
             _ if self.need_credit_update_from_peer() => (),
+            // A seqpacket connection reads the next record only after the current one has
+            // been fully delivered to the peer.
+            _ if self.rx_msg_pending() => (),
             _ => evset.insert(EventSet::IN),
         }
         evset
@@ -456,37 +498,16 @@ where
         if evset.contains(EventSet::OUT) {
             // Data can be written to the host stream. Time to flush out the TX buffer.
             //
-            if self.tx_buf.is_empty() {
+            if !self.tx_pending() {
                 METRICS.conn_event_fails.inc();
                 info!("vsock: connection received unexpected EPOLLOUT event");
                 return;
             }
-            let flushed = self
-                .tx_buf
-                .flush_to(&mut self.stream)
-                .unwrap_or_else(|err| {
-                    METRICS.tx_flush_fails.inc();
-                    warn!(
-                        "vsock: error flushing TX buf for (lp={}, pp={}): {:?}",
-                        self.local_port, self.peer_port, err
-                    );
-                    match err {
-                        VsockCsmError::TxBufFlush(inner)
-                            if inner.kind() == ErrorKind::WouldBlock =>
-                        {
-                            // This should never happen (EWOULDBLOCK after EPOLLOUT), but
-                            // it does, so let's absorb it.
-                        }
-                        _ => self.kill(),
-                    };
-                    0
-                });
-            self.fwd_cnt += wrap_usize_to_u32(flushed);
-            METRICS.tx_bytes_count.add(flushed as u64);
+            self.flush_tx();
 
             // If this connection was shutting down, but is waiting to drain the TX buffer
             // before forceful termination, the wait might be over.
-            if self.state == ConnState::PeerClosed(true, true) && self.tx_buf.is_empty() {
+            if self.state == ConnState::PeerClosed(true, true) && !self.tx_pending() {
                 self.pending_rx.insert(PendingRx::Rst);
             } else if self.peer_needs_credit_update() {
                 // If we've freed up some more buffer space, we may need to let the peer know it
@@ -501,7 +522,7 @@ impl<S> VsockConnection<S>
 where
     S: VsockConnectionBackend + Debug,
 {
-    /// Create a new guest-initiated connection object.
+    /// Create a new guest-initiated connection object, of socket type `pkt_type`.
     pub fn new_peer_init(
         stream: S,
         local_cid: u64,
@@ -509,15 +530,20 @@ where
         local_port: u32,
         peer_port: u32,
         peer_buf_alloc: u32,
+        pkt_type: u16,
     ) -> Self {
         Self {
             local_cid,
             peer_cid,
             local_port,
             peer_port,
+            pkt_type,
             stream,
             state: ConnState::PeerInit,
             tx_buf: TxBuf::new(),
+            tx_msg_buf: TxMsgBuf::new(),
+            rx_msg: Vec::new(),
+            rx_msg_offset: 0,
             fwd_cnt: Wrapping(0),
             peer_buf_alloc,
             peer_fwd_cnt: Wrapping(0),
@@ -541,9 +567,13 @@ where
             peer_cid,
             local_port,
             peer_port,
+            pkt_type: uapi::VSOCK_TYPE_STREAM,
             stream,
             state: ConnState::LocalInit,
             tx_buf: TxBuf::new(),
+            tx_msg_buf: TxMsgBuf::new(),
+            rx_msg: Vec::new(),
+            rx_msg_offset: 0,
             fwd_cnt: Wrapping(0),
             peer_buf_alloc: 0,
             peer_fwd_cnt: Wrapping(0),
@@ -646,6 +676,106 @@ where
         Ok(())
     }
 
+    /// Send the data in a guest packet to the host stream of a seqpacket connection.
+    ///
+    /// Data is gathered in the TX buffer until the packet marking the end of a message
+    /// arrives. The whole message is then written to the host stream as a single record.
+    fn send_msg_bytes(&mut self, pkt: &VsockPacketTx) -> Result<(), VsockError> {
+        pkt.write_from_offset_to(&mut self.tx_msg_buf, 0, pkt.hdr.len())?;
+        if pkt.hdr.flags() & uapi::VSOCK_FLAGS_SEQ_EOM != 0 {
+            self.tx_msg_buf.end_msg();
+            self.flush_tx();
+        }
+        Ok(())
+    }
+
+    /// Fill in an RX packet with data from the record read from a seqpacket host stream.
+    ///
+    /// A new record is read only once the previous one has been fully delivered. Records
+    /// larger than a packet are split over several packets, the last of which carries the
+    /// end-of-message flags.
+    ///
+    /// Returns the number of bytes written to the packet, where 0 means the host stream was
+    /// closed down.
+    fn read_msg_bytes(&mut self, pkt: &mut VsockPacketRx, max_len: u32) -> Result<u32, VsockError> {
+        if !self.rx_msg_pending() {
+            let msg = self
+                .stream
+                .recv_record()
+                .map_err(|err| VsockError::GuestMemoryMmap(GuestMemoryError::IOError(err)))?;
+            // The peer could never fit in the whole message, so it could never read it.
+            if msg.len() > self.peer_buf_alloc as usize {
+                return Err(VsockError::GuestMemoryMmap(GuestMemoryError::IOError(
+                    std::io::Error::from_raw_os_error(libc::EMSGSIZE),
+                )));
+            }
+            self.rx_msg = msg;
+            self.rx_msg_offset = 0;
+            if self.rx_msg.is_empty() {
+                return Ok(0);
+            }
+        }
+
+        let mut remaining = &self.rx_msg[self.rx_msg_offset..];
+        let count = std::cmp::min(wrap_usize_to_u32(remaining.len()), max_len);
+        let read_cnt = pkt.read_at_offset_from(&mut remaining, 0, count)?;
+        self.rx_msg_offset += read_cnt as usize;
+
+        if self.rx_msg_pending() {
+            // More data to go, as soon as another RX buffer is available.
+            self.pending_rx.insert(PendingRx::Rw);
+        } else {
+            self.rx_msg = Vec::new();
+            self.rx_msg_offset = 0;
+            pkt.hdr
+                .set_flag(uapi::VSOCK_FLAGS_SEQ_EOM)
+                .set_flag(uapi::VSOCK_FLAGS_SEQ_EOR);
+        }
+        Ok(read_cnt)
+    }
+
+    /// Flush as much of the TX buffer as possible to the host stream, killing the connection
+    /// if that fails for any reason other than the stream being full.
+    fn flush_tx(&mut self) {
+        let flush_res = if self.is_seqpacket() {
+            self.tx_msg_buf.flush_to(&mut self.stream)
+        } else {
+            self.tx_buf.flush_to(&mut self.stream)
+        };
+        let flushed = flush_res.unwrap_or_else(|err| {
+            METRICS.tx_flush_fails.inc();
+            warn!(
+                "vsock: error flushing TX buf for (lp={}, pp={}): {:?}",
+                self.local_port, self.peer_port, err
+            );
+            match err {
+                VsockCsmError::TxBufFlush(inner) if inner.kind() == ErrorKind::WouldBlock => {
+                    // Absorb any would-block errors, since we can always try again on the
+                    // next EPOLLOUT.
+                }
+                _ => self.kill(),
+            };
+            0
+        });
+        self.fwd_cnt += wrap_usize_to_u32(flushed);
+        METRICS.tx_bytes_count.add(flushed as u64);
+    }
+
+    /// Check if this is a seqpacket connection.
+    fn is_seqpacket(&self) -> bool {
+        self.pkt_type == uapi::VSOCK_TYPE_SEQPACKET
+    }
+
+    /// Check if there is TX data waiting to be flushed to the host stream.
+    fn tx_pending(&self) -> bool {
+        !self.tx_buf.is_empty() || !self.tx_msg_buf.is_empty()
+    }
+
+    /// Check if part of a seqpacket record is still waiting to be delivered to the peer.
+    fn rx_msg_pending(&self) -> bool {
+        self.rx_msg_offset < self.rx_msg.len()
+    }
+
     /// Check if the credit information the peer has last received from us is outdated.
     fn peer_needs_credit_update(&self) -> bool {
         let peer_seen_free_buf =
@@ -671,7 +801,7 @@ where
             .set_dst_cid(self.peer_cid)
             .set_src_port(self.local_port)
             .set_dst_port(self.peer_port)
-            .set_type(uapi::VSOCK_TYPE_STREAM)
+            .set_type(self.pkt_type)
             .set_buf_alloc(defs::CONN_TX_BUF_SIZE)
             .set_fwd_cnt(self.fwd_cnt.0);
     }
@@ -712,8 +842,10 @@ mod tests {
     struct TestStream {
         fd: EventFd,
         read_buf: Vec<u8>,
+        read_records: Vec<Vec<u8>>,
         read_state: StreamState,
         write_buf: Vec<u8>,
+        write_records: Vec<Vec<u8>>,
         write_state: StreamState,
     }
     impl TestStream {
@@ -723,7 +855,9 @@ mod tests {
                 read_state: StreamState::Ready,
                 write_state: StreamState::Ready,
                 read_buf: Vec::new(),
+                read_records: Vec::new(),
                 write_buf: Vec::new(),
+                write_records: Vec::new(),
             }
         }
         fn new_with_read_buf(buf: &[u8]) -> Self {
@@ -805,7 +939,32 @@ mod tests {
         }
     }
 
-    impl VsockConnectionBackend for TestStream {}
+    impl VsockConnectionBackend for TestStream {
+        fn send_record(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+            match self.write_state {
+                StreamState::Closed => Err(IoError::new(ErrorKind::BrokenPipe, "EPIPE")),
+                StreamState::Error(kind) => Err(IoError::new(kind, "whatevs")),
+                StreamState::Ready => {
+                    self.write_records.push(buf.to_vec());
+                    Ok(buf.len())
+                }
+                StreamState::WouldBlock => Err(IoError::new(ErrorKind::WouldBlock, "EAGAIN")),
+            }
+        }
+
+        fn recv_record(&mut self) -> Result<Vec<u8>, IoError> {
+            match self.read_state {
+                StreamState::Closed => Ok(Vec::new()),
+                StreamState::Error(kind) => Err(IoError::new(kind, "whatevs")),
+                StreamState::Ready if !self.read_records.is_empty() => {
+                    Ok(self.read_records.remove(0))
+                }
+                StreamState::Ready | StreamState::WouldBlock => {
+                    Err(IoError::new(ErrorKind::WouldBlock, "EAGAIN"))
+                }
+            }
+        }
+    }
 
     impl<S> VsockConnection<S>
     where
@@ -856,7 +1015,15 @@ mod tests {
             Self::new(ConnState::Established)
         }
 
+        fn new_established_seqpacket() -> Self {
+            Self::new_with_type(ConnState::Established, uapi::VSOCK_TYPE_SEQPACKET)
+        }
+
         fn new(conn_state: ConnState) -> Self {
+            Self::new_with_type(conn_state, uapi::VSOCK_TYPE_STREAM)
+        }
+
+        fn new_with_type(conn_state: ConnState, pkt_type: u16) -> Self {
             let vsock_test_ctx = TestContext::new();
             let mut handler_ctx = vsock_test_ctx.create_event_handler_context();
             let stream = TestStream::new();
@@ -882,6 +1049,7 @@ mod tests {
                     LOCAL_PORT,
                     PEER_PORT,
                     PEER_BUF_ALLOC,
+                    pkt_type,
                 ),
                 ConnState::LocalInit => VsockConnection::<TestStream>::new_local_init(
                     stream, LOCAL_CID, PEER_CID, LOCAL_PORT, PEER_PORT,
@@ -894,6 +1062,7 @@ mod tests {
                         LOCAL_PORT,
                         PEER_PORT,
                         PEER_BUF_ALLOC,
+                        pkt_type,
                     );
                     assert!(conn.has_pending_rx());
                     conn.recv_pkt(&mut rx_pkt).unwrap();
@@ -1306,4 +1475,94 @@ mod tests {
         ctx.recv();
         assert_eq!(ctx.rx_pkt.hdr.op(), uapi::VSOCK_OP_RST);
     }
+
+    #[test]
+    fn test_seqpacket_tx() {
+        let mut ctx = CsmTestContext::new_established_seqpacket();
+
+        // Data is held back until the end of the message.
+        ctx.init_data_tx_pkt(&[1, 2, 3]);
+        ctx.tx_pkt.hdr.set_flags(0);
+        ctx.send();
+        assert!(ctx.conn.stream.write_records.is_empty());
+        assert!(!ctx.conn.get_polled_evset().contains(EventSet::OUT));
+
+        // The whole message is then written out as a single record.
+        ctx.init_data_tx_pkt(&[4, 5]);
+        ctx.tx_pkt.hdr.set_flags(uapi::VSOCK_FLAGS_SEQ_EOM);
+        ctx.send();
+        assert_eq!(ctx.conn.stream.write_records, vec![vec![1, 2, 3, 4, 5]]);
+        assert!(ctx.conn.stream.write_buf.is_empty());
+        assert_eq!(ctx.conn.fwd_cnt(), Wrapping(5));
+
+        // When the record can't be written, it is buffered until the next EPOLLOUT.
+        let mut stream = TestStream::new();
+        stream.write_state = StreamState::WouldBlock;
+        ctx.set_stream(stream);
+        ctx.init_data_tx_pkt(&[6, 7]);
+        ctx.tx_pkt.hdr.set_flags(uapi::VSOCK_FLAGS_SEQ_EOM);
+        ctx.send();
+        assert!(ctx.conn.get_polled_evset().contains(EventSet::OUT));
+        ctx.set_stream(TestStream::new());
+        ctx.notify_epollout();
+        assert!(!ctx.conn.get_polled_evset().contains(EventSet::OUT));
+        assert_eq!(ctx.conn.stream.write_records, vec![vec![6, 7]]);
+        assert_eq!(ctx.conn.fwd_cnt(), Wrapping(7));
+    }
+
+    #[test]
+    fn test_seqpacket_rx() {
+        let mut ctx = CsmTestContext::new_established_seqpacket();
+        let buf_size = ctx.rx_pkt.buf_size();
+        let data: Vec<u8> = (0..buf_size + 3)
+            .map(|i| u8::try_from(i % 256).unwrap())
+            .collect();
+        let mut stream = TestStream::new();
+        stream.read_records.push(data.clone());
+        ctx.set_stream(stream);
+
+        // A record larger than the RX buffer is split over two packets.
+        ctx.notify_epollin();
+        ctx.recv();
+        assert_eq!(ctx.rx_pkt.hdr.op(), uapi::VSOCK_OP_RW);
+        assert_eq!(ctx.rx_pkt.hdr.type_(), uapi::VSOCK_TYPE_SEQPACKET);
+        assert_eq!(ctx.rx_pkt.hdr.len(), buf_size);
+        assert_eq!(ctx.rx_pkt.hdr.flags(), 0);
+        assert_eq!(
+            test_utils::read_packet_data(&ctx.tx_pkt, buf_size),
+            data[..buf_size as usize]
+        );
+
+        // The rest of the record is due without waiting for the host stream.
+        assert!(ctx.conn.has_pending_rx());
+        assert!(!ctx.conn.get_polled_evset().contains(EventSet::IN));
+        ctx.recv();
+        assert_eq!(ctx.rx_pkt.hdr.op(), uapi::VSOCK_OP_RW);
+        assert_eq!(ctx.rx_pkt.hdr.len(), 3);
+        assert_eq!(
+            ctx.rx_pkt.hdr.flags(),
+            uapi::VSOCK_FLAGS_SEQ_EOM | uapi::VSOCK_FLAGS_SEQ_EOR
+        );
+        assert_eq!(
+            test_utils::read_packet_data(&ctx.tx_pkt, 3),
+            data[buf_size as usize..]
+        );
+        assert!(!ctx.conn.has_pending_rx());
+        assert!(ctx.conn.get_polled_evset().contains(EventSet::IN));
+    }
+
+    #[test]
+    fn test_seqpacket_rx_too_big() {
+        // A record the peer can't fit in its buffer would never be read, so the connection is
+        // reset instead.
+        let mut ctx = CsmTestContext::new_established_seqpacket();
+        let mut stream = TestStream::new();
+        stream
+            .read_records
+            .push(vec![0u8; PEER_BUF_ALLOC as usize + 1]);
+        ctx.set_stream(stream);
+        ctx.notify_epollin();
+        ctx.recv();
+        assert_eq!(ctx.rx_pkt.hdr.op(), uapi::VSOCK_OP_RST);
+    }
 }
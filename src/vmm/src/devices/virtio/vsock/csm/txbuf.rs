@@ -2,13 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
+use std::collections::VecDeque;
 use std::fmt::Debug;
-use std::io::Write;
+use std::io::{ErrorKind, Write};
 use std::num::Wrapping;
 
 use vm_memory::{VolatileMemoryError, VolatileSlice, WriteVolatile};
 
-use super::{VsockCsmError, defs};
+use super::{VsockConnectionBackend, VsockCsmError, defs};
 use crate::utils::wrap_usize_to_u32;
 use crate::vstate::memory::{BitmapSlice, Bytes};
 
@@ -148,11 +149,103 @@ impl WriteVolatile for TxBuf {
     }
 }
 
+/// The TX buffer of seqpacket connections. Unlike `TxBuf`, it keeps track of message
+/// boundaries: data is gathered until the guest sends the last packet of a message, and every
+/// message is then written to the host stream as a single record.
+#[derive(Debug, Default)]
+pub struct TxMsgBuf {
+    /// The message currently being received from the guest.
+    partial: Vec<u8>,
+    /// Complete messages, that are yet to be written to the host stream.
+    msgs: VecDeque<Vec<u8>>,
+    /// Total number of bytes held, across `partial` and `msgs`.
+    len: usize,
+}
+
+impl TxMsgBuf {
+    /// Total buffer size, in bytes.
+    const SIZE: usize = defs::CONN_TX_BUF_SIZE as usize;
+
+    /// Buffer constructor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the used length of this buffer - number of bytes that have been pushed in, but not
+    /// yet flushed out.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Append a byte slice to the message currently being received.
+    ///
+    /// Either the entire source slice will be pushed to the buffer, or none of it, if there
+    /// isn't enough room, in which case `Err(Error::TxBufFull)` is returned.
+    pub fn push(&mut self, src: &VolatileSlice<impl BitmapSlice>) -> Result<(), VsockCsmError> {
+        if self.len + src.len() > Self::SIZE {
+            return Err(VsockCsmError::TxBufFull);
+        }
+
+        let start = self.partial.len();
+        self.partial.resize(start + src.len(), 0);
+        let _ = src.read(&mut self.partial[start..], 0);
+        self.len += src.len();
+
+        Ok(())
+    }
+
+    /// Mark the end of the message currently being received, making it ready to be flushed.
+    pub fn end_msg(&mut self) {
+        self.msgs.push_back(std::mem::take(&mut self.partial));
+    }
+
+    /// Flush complete messages, in order, to a host stream, each one as a single record.
+    ///
+    /// Flushing stops at the first message that can't be written without blocking. Return the
+    /// number of bytes that have been transferred out of the buffer.
+    pub fn flush_to<W: VsockConnectionBackend + Debug>(
+        &mut self,
+        sink: &mut W,
+    ) -> Result<usize, VsockCsmError> {
+        let mut flushed = 0;
+        while let Some(msg) = self.msgs.front() {
+            match sink.send_record(msg) {
+                Ok(_) => {
+                    flushed += msg.len();
+                    self.len -= msg.len();
+                    self.msgs.pop_front();
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(VsockCsmError::TxBufFlush(err)),
+            }
+        }
+        Ok(flushed)
+    }
+
+    /// Check if the buffer holds any complete message that hasn't yet been flushed out.
+    pub fn is_empty(&self) -> bool {
+        self.msgs.is_empty()
+    }
+}
+
+impl WriteVolatile for TxMsgBuf {
+    fn write_volatile<B: BitmapSlice>(
+        &mut self,
+        buf: &VolatileSlice<B>,
+    ) -> Result<usize, VolatileMemoryError> {
+        self.push(buf).map(|()| buf.len()).map_err(|err| {
+            VolatileMemoryError::IOError(std::io::Error::new(std::io::ErrorKind::Other, err))
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::io::{Error as IoError, ErrorKind, Write};
+    use std::io::{Error as IoError, ErrorKind, Read, Write};
+    use std::os::unix::net::UnixStream;
 
     use super::*;
+    use crate::devices::virtio::vsock::test_utils::seqpacket_pair;
 
     #[derive(Debug)]
     struct TestSink {
@@ -319,4 +412,80 @@ mod tests {
             other => panic!("Unexpected result: {:?}", other),
         }
     }
+
+    #[test]
+    fn test_msg_push_flush() {
+        let mut txbuf = TxMsgBuf::new();
+        let (mut sink, mut peer): (UnixStream, UnixStream) = seqpacket_pair();
+        assert!(txbuf.is_empty());
+
+        // A message split over several pushes is only flushed once complete.
+        txbuf
+            .push(&VolatileSlice::from([1, 2, 3].as_mut_slice()))
+            .unwrap();
+        txbuf
+            .write_all_volatile(&VolatileSlice::from([4, 5].as_mut_slice()))
+            .unwrap();
+        assert!(txbuf.is_empty());
+        assert_eq!(txbuf.len(), 5);
+        assert_eq!(txbuf.flush_to(&mut sink).unwrap(), 0);
+
+        txbuf.end_msg();
+        txbuf
+            .push(&VolatileSlice::from([6, 7].as_mut_slice()))
+            .unwrap();
+        txbuf.end_msg();
+        assert!(!txbuf.is_empty());
+        assert_eq!(txbuf.flush_to(&mut sink).unwrap(), 7);
+        assert!(txbuf.is_empty());
+        assert_eq!(txbuf.len(), 0);
+
+        // Each message makes up a single record.
+        let mut buf = [0u8; 16];
+        assert_eq!(peer.read(&mut buf).unwrap(), 5);
+        assert_eq!(buf[..5], [1, 2, 3, 4, 5]);
+        assert_eq!(peer.read(&mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [6, 7]);
+    }
+
+    #[test]
+    fn test_msg_push_error() {
+        let mut txbuf = TxMsgBuf::new();
+        let mut tmp = vec![0u8; TxMsgBuf::SIZE - 1];
+
+        txbuf
+            .push(&VolatileSlice::from(tmp.as_mut_slice()))
+            .unwrap();
+        txbuf.end_msg();
+        match txbuf.push(&VolatileSlice::from([1, 2].as_mut_slice())) {
+            Err(VsockCsmError::TxBufFull) => (),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        match txbuf.write_volatile(&VolatileSlice::from([1, 2].as_mut_slice())) {
+            Err(err) => {
+                assert_eq!(
+                    format!("{}", err),
+                    "Attempted to push data to a full TX buffer"
+                );
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_msg_flush_error() {
+        let mut txbuf = TxMsgBuf::new();
+        let (mut sink, peer) = seqpacket_pair();
+
+        txbuf
+            .push(&VolatileSlice::from([1, 2, 3, 4].as_mut_slice()))
+            .unwrap();
+        txbuf.end_msg();
+        drop(peer);
+        match txbuf.flush_to(&mut sink) {
+            Err(VsockCsmError::TxBufFlush(ref err)) if err.kind() == ErrorKind::BrokenPipe => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert!(!txbuf.is_empty());
+    }
 }
@@ -61,6 +61,12 @@ mod defs {
         /// Defined in `include/uapi/linux/virtio_ids.h`.
         pub const VIRTIO_ID_VSOCK: u32 = 19;
 
+        /// Vsock feature bits.
+        /// Defined in `/include/uapi/linux/virtio_vsock.h`.
+        ///
+        /// The device supports seqpacket sockets (VSOCK_TYPE_SEQPACKET).
+        pub const VIRTIO_VSOCK_F_SEQPACKET: u32 = 1;
+
         /// Vsock packet operation IDs.
         /// Defined in `/include/uapi/linux/virtio_vsock.h`.
         ///
@@ -86,12 +92,20 @@ mod defs {
         pub const VSOCK_FLAGS_SHUTDOWN_RCV: u32 = 1;
         /// Valid with a VSOCK_OP_SHUTDOWN packet: the packet sender will send no more data.
         pub const VSOCK_FLAGS_SHUTDOWN_SEND: u32 = 2;
+        /// Valid with a VSOCK_OP_RW packet of a seqpacket connection: the packet carries the
+        /// last bytes of a message.
+        pub const VSOCK_FLAGS_SEQ_EOM: u32 = 1;
+        /// Valid with a VSOCK_OP_RW packet of a seqpacket connection: the message it ends is
+        /// also the end of a record (i.e. it was sent with MSG_EOR).
+        pub const VSOCK_FLAGS_SEQ_EOR: u32 = 2;
 
         /// Vsock packet type.
         /// Defined in `/include/uapi/linux/virtio_vsock.h`.
         ///
-        /// Stream / connection-oriented packet (the only currently valid type).
+        /// Stream / connection-oriented packet.
         pub const VSOCK_TYPE_STREAM: u16 = 1;
+        /// Seqpacket / connection-oriented packet, preserving message boundaries.
+        pub const VSOCK_TYPE_SEQPACKET: u16 = 2;
 
         pub const VSOCK_HOST_CID: u64 = 2;
     }
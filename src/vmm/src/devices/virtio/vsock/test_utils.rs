@@ -4,7 +4,8 @@
 #![cfg(test)]
 #![doc(hidden)]
 
-use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::UnixStream;
 
 use vmm_sys_util::epoll::EventSet;
 use vmm_sys_util::eventfd::EventFd;
@@ -214,6 +215,28 @@ pub fn read_packet_data(pkt: &VsockPacketTx, how_much: u32) -> Vec<u8> {
     buf
 }
 
+/// Create a pair of connected, non-blocking, seqpacket Unix sockets.
+pub fn seqpacket_pair() -> (UnixStream, UnixStream) {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` is valid for writes of two file descriptors.
+    let ret = unsafe {
+        libc::socketpair(
+            libc::AF_UNIX,
+            libc::SOCK_SEQPACKET | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            0,
+            fds.as_mut_ptr(),
+        )
+    };
+    assert_eq!(ret, 0);
+    // SAFETY: both file descriptors were just created, and are owned by nobody else.
+    unsafe {
+        (
+            UnixStream::from_raw_fd(fds[0]),
+            UnixStream::from_raw_fd(fds[1]),
+        )
+    }
+}
+
 impl<B> Vsock<B>
 where
     B: VsockBackend,
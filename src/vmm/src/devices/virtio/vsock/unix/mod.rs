@@ -11,6 +11,11 @@ mod muxer;
 mod muxer_killq;
 mod muxer_rxq;
 
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
 pub use muxer::VsockMuxer as VsockUnixBackend;
 
 use crate::devices::virtio::vsock::csm::VsockConnectionBackend;
@@ -47,6 +52,91 @@ pub enum VsockUnixBackendError {
     TooManyConnections,
 }
 
-type MuxerConnection = super::csm::VsockConnection<std::os::unix::net::UnixStream>;
+type MuxerConnection = super::csm::VsockConnection<UnixStream>;
+
+impl VsockConnectionBackend for UnixStream {
+    fn send_record(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut iov = libc::iovec {
+            iov_base: buf.as_ptr().cast_mut().cast(),
+            iov_len: buf.len(),
+        };
+        // SAFETY: all-zeroes is a valid `msghdr`.
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        // SAFETY: `msg` points to a single iovec describing `buf`, which is valid for reads of
+        // `buf.len()` bytes. `sendmsg()` doesn't write through the iovec base pointer.
+        let ret =
+            unsafe { libc::sendmsg(self.as_raw_fd(), &msg, libc::MSG_EOR | libc::MSG_NOSIGNAL) };
+        // A negative return value signals an error.
+        usize::try_from(ret).map_err(|_| std::io::Error::last_os_error())
+    }
+
+    fn recv_record(&mut self) -> std::io::Result<Vec<u8>> {
+        // With `MSG_TRUNC`, seqpacket sockets report the full length of the next record, even
+        // though nothing is copied out.
+        // SAFETY: a zero-length read never writes through the (null) buffer pointer.
+        let len = unsafe {
+            libc::recv(
+                self.as_raw_fd(),
+                std::ptr::null_mut(),
+                0,
+                libc::MSG_PEEK | libc::MSG_TRUNC,
+            )
+        };
+        let len = usize::try_from(len).map_err(|_| std::io::Error::last_os_error())?;
+        let mut buf = vec![0u8; len];
+        // SAFETY: `buf` is valid for writes of `buf.len()` bytes.
+        let len = unsafe { libc::recv(self.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+        let len = usize::try_from(len).map_err(|_| std::io::Error::last_os_error())?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+}
+
+/// Build the address of the Unix socket at `path`.
+fn unix_sockaddr<P: AsRef<Path>>(path: P) -> std::io::Result<libc::sockaddr_un> {
+    let path = path.as_ref().as_os_str().as_bytes();
 
-impl VsockConnectionBackend for std::os::unix::net::UnixStream {}
+    // SAFETY: all-zeroes is a valid `sockaddr_un`.
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    // Leave room for the terminating null byte.
+    if path.len() >= addr.sun_path.len() {
+        return Err(std::io::Error::from_raw_os_error(libc::ENAMETOOLONG));
+    }
+    addr.sun_family = libc::sa_family_t::try_from(libc::AF_UNIX).unwrap();
+    for (dst, src) in addr.sun_path.iter_mut().zip(path) {
+        *dst = libc::c_char::from_ne_bytes([*src]);
+    }
+    Ok(addr)
+}
+
+/// Connect to the host-side seqpacket Unix socket listening at `path`.
+///
+/// `std` only offers stream Unix sockets, so the socket is set up by hand. The resulting
+/// `UnixStream` should only be used for record-oriented I/O, via `VsockConnectionBackend`.
+fn connect_seqpacket<P: AsRef<Path>>(path: P) -> std::io::Result<UnixStream> {
+    let addr = unix_sockaddr(path)?;
+
+    // SAFETY: `socket()` has no memory safety preconditions.
+    let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: `fd` is a freshly created socket, owned by nobody else.
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: `addr` is a valid, null-terminated `sockaddr_un`, of the size we pass in.
+    let ret = unsafe {
+        libc::connect(
+            fd.as_raw_fd(),
+            std::ptr::from_ref(&addr).cast(),
+            u32::try_from(std::mem::size_of::<libc::sockaddr_un>()).unwrap(),
+        )
+    };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(UnixStream::from(fd))
+}
@@ -44,7 +44,7 @@ use super::super::defs::uapi;
 use super::super::{VsockBackend, VsockChannel, VsockEpollListener, VsockError};
 use super::muxer_killq::MuxerKillQ;
 use super::muxer_rxq::MuxerRxQ;
-use super::{MuxerConnection, VsockUnixBackendError, defs};
+use super::{MuxerConnection, VsockUnixBackendError, connect_seqpacket, defs};
 use crate::devices::virtio::vsock::metrics::METRICS;
 use crate::devices::virtio::vsock::packet::{VsockPacketRx, VsockPacketTx};
 use crate::logger::IncMetric;
@@ -62,8 +62,12 @@ pub struct ConnMapKey {
 pub enum MuxerRx {
     /// The packet must be fetched from the connection identified by `ConnMapKey`.
     ConnRx(ConnMapKey),
-    /// The muxer must produce an RST packet.
-    RstPkt { local_port: u32, peer_port: u32 },
+    /// The muxer must produce an RST packet, of socket type `pkt_type`.
+    RstPkt {
+        local_port: u32,
+        peer_port: u32,
+        pkt_type: u16,
+    },
 }
 
 /// An epoll listener, registered under the muxer's nested epoll FD.
@@ -131,6 +135,7 @@ impl VsockChannel for VsockMuxer {
                 MuxerRx::RstPkt {
                     local_port,
                     peer_port,
+                    pkt_type,
                 } => {
                     pkt.hdr
                         .set_op(uapi::VSOCK_OP_RST)
@@ -139,7 +144,7 @@ impl VsockChannel for VsockMuxer {
                         .set_src_port(local_port)
                         .set_dst_port(peer_port)
                         .set_len(0)
-                        .set_type(uapi::VSOCK_TYPE_STREAM)
+                        .set_type(pkt_type)
                         .set_flags(0)
                         .set_buf_alloc(0)
                         .set_fwd_cnt(0);
@@ -202,10 +207,17 @@ impl VsockChannel for VsockMuxer {
             pkt.hdr
         );
 
-        // If this packet has an unsupported type (!=stream), we must send back an RST.
+        // If this packet has an unsupported type (neither stream nor seqpacket), we must send
+        // back an RST.
         //
-        if pkt.hdr.type_() != uapi::VSOCK_TYPE_STREAM {
-            self.enq_rst(pkt.hdr.dst_port(), pkt.hdr.src_port());
+        if pkt.hdr.type_() != uapi::VSOCK_TYPE_STREAM
+            && pkt.hdr.type_() != uapi::VSOCK_TYPE_SEQPACKET
+        {
+            self.enq_rst(
+                pkt.hdr.dst_port(),
+                pkt.hdr.src_port(),
+                uapi::VSOCK_TYPE_STREAM,
+            );
             return Ok(());
         }
 
@@ -228,7 +240,7 @@ impl VsockChannel for VsockMuxer {
                 self.handle_peer_request_pkt(pkt);
             } else {
                 // Send back an RST, to let the drive know we weren't expecting this packet.
-                self.enq_rst(pkt.hdr.dst_port(), pkt.hdr.src_port());
+                self.enq_rst(pkt.hdr.dst_port(), pkt.hdr.src_port(), pkt.hdr.type_());
             }
             return Ok(());
         }
@@ -612,8 +624,14 @@ impl VsockMuxer {
     /// RST packet will be scheduled for delivery to the guest.
     fn handle_peer_request_pkt(&mut self, pkt: &VsockPacketTx) {
         let port_path = format!("{}_{}", self.host_sock_path, pkt.hdr.dst_port());
+        let pkt_type = pkt.hdr.type_();
+        let stream = if pkt_type == uapi::VSOCK_TYPE_SEQPACKET {
+            connect_seqpacket(port_path)
+        } else {
+            UnixStream::connect(port_path)
+        };
 
-        UnixStream::connect(port_path)
+        stream
             .and_then(|stream| stream.set_nonblocking(true).map(|_| stream))
             .map_err(VsockUnixBackendError::UnixConnect)
             .and_then(|stream| {
@@ -629,10 +647,11 @@ impl VsockMuxer {
                         pkt.hdr.dst_port(),
                         pkt.hdr.src_port(),
                         pkt.hdr.buf_alloc(),
+                        pkt_type,
                     ),
                 )
             })
-            .unwrap_or_else(|_| self.enq_rst(pkt.hdr.dst_port(), pkt.hdr.src_port()));
+            .unwrap_or_else(|_| self.enq_rst(pkt.hdr.dst_port(), pkt.hdr.src_port(), pkt_type));
     }
 
     /// Perform an action that might mutate a connection's state.
@@ -772,10 +791,11 @@ impl VsockMuxer {
     /// Enqueue errors aren't propagated up the call chain, since there is nothing we can do to
     /// handle them. We do, however, log a warning, since not being able to enqueue an RST
     /// packet means we have to drop it, which is not normal operation.
-    fn enq_rst(&mut self, local_port: u32, peer_port: u32) {
+    fn enq_rst(&mut self, local_port: u32, peer_port: u32, pkt_type: u16) {
         let pushed = self.rxq.push(MuxerRx::RstPkt {
             local_port,
             peer_port,
+            pkt_type,
         });
         if !pushed {
             warn!(
@@ -790,6 +810,7 @@ impl VsockMuxer {
 mod tests {
     use std::io::{Read, Write};
     use std::ops::Drop;
+    use std::os::unix::io::FromRawFd;
     use std::os::unix::net::{UnixListener, UnixStream};
     use std::path::{Path, PathBuf};
 
@@ -917,6 +938,10 @@ mod tests {
             LocalListener::new(format!("{}_{}", self.muxer.host_sock_path, port))
         }
 
+        fn create_local_seqpacket_listener(&self, port: u32) -> LocalListener {
+            LocalListener::new_seqpacket(format!("{}_{}", self.muxer.host_sock_path, port))
+        }
+
         fn local_connect(&mut self, peer_port: u32) -> (UnixStream, u32) {
             let (init_local_lsn_count, init_conn_lsn_count) = self.count_epoll_listeners();
 
@@ -986,6 +1011,36 @@ mod tests {
                 sock,
             }
         }
+        fn new_seqpacket<P: AsRef<Path> + Clone + Debug>(path: P) -> Self {
+            let path_buf = path.as_ref().to_path_buf();
+            let addr = super::super::unix_sockaddr(path).unwrap();
+            // SAFETY: `socket()` has no memory safety preconditions.
+            let fd = unsafe {
+                libc::socket(
+                    libc::AF_UNIX,
+                    libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                    0,
+                )
+            };
+            assert!(fd >= 0);
+            // SAFETY: `fd` is a freshly created socket, owned by nobody else.
+            let sock = unsafe { UnixListener::from_raw_fd(fd) };
+            // SAFETY: `addr` is a valid `sockaddr_un`, of the size we pass in.
+            let ret = unsafe {
+                libc::bind(
+                    sock.as_raw_fd(),
+                    std::ptr::from_ref(&addr).cast(),
+                    u32::try_from(std::mem::size_of::<libc::sockaddr_un>()).unwrap(),
+                )
+            };
+            assert_eq!(ret, 0);
+            // SAFETY: `listen()` has no memory safety preconditions.
+            assert_eq!(unsafe { libc::listen(sock.as_raw_fd(), 1) }, 0);
+            Self {
+                path: path_buf,
+                sock,
+            }
+        }
         fn accept(&mut self) -> UnixStream {
             let (stream, _) = self.sock.accept().unwrap();
             stream.set_nonblocking(true).unwrap();
@@ -1027,15 +1082,15 @@ mod tests {
     fn test_bad_peer_pkt() {
         const LOCAL_PORT: u32 = 1026;
         const PEER_PORT: u32 = 1025;
-        const SOCK_DGRAM: u16 = 2;
+        const VSOCK_TYPE_DGRAM: u16 = 3;
 
         let mut ctx = MuxerTestContext::new("bad_peer_pkt");
         let tx_pkt = ctx.init_tx_pkt(LOCAL_PORT, PEER_PORT, uapi::VSOCK_OP_REQUEST);
-        tx_pkt.hdr.set_type(SOCK_DGRAM);
+        tx_pkt.hdr.set_type(VSOCK_TYPE_DGRAM);
         ctx.send();
 
-        // The guest sent a SOCK_DGRAM packet. Per the vsock spec, we need to reply with an RST
-        // packet, since vsock only supports stream sockets.
+        // The guest sent a datagram packet. Per the vsock spec, we need to reply with an RST
+        // packet, since we only support stream and seqpacket sockets.
         assert!(ctx.muxer.has_pending_rx());
         ctx.recv();
         assert_eq!(ctx.rx_pkt.hdr.op(), uapi::VSOCK_OP_RST);
@@ -1138,6 +1193,63 @@ mod tests {
         assert!(!ctx.muxer.has_pending_rx());
     }
 
+    #[test]
+    fn test_peer_seqpacket_connection() {
+        const LOCAL_PORT: u32 = 1026;
+        const PEER_PORT: u32 = 1025;
+
+        let mut ctx = MuxerTestContext::new("peer_seqpacket_connection");
+
+        // Test peer connection refused. The RST packet should match the request type.
+        ctx.init_tx_pkt(LOCAL_PORT, PEER_PORT, uapi::VSOCK_OP_REQUEST)
+            .hdr
+            .set_type(uapi::VSOCK_TYPE_SEQPACKET);
+        ctx.send();
+        ctx.recv();
+        assert_eq!(ctx.rx_pkt.hdr.op(), uapi::VSOCK_OP_RST);
+        assert_eq!(ctx.rx_pkt.hdr.type_(), uapi::VSOCK_TYPE_SEQPACKET);
+
+        // Test peer connection accepted.
+        let mut listener = ctx.create_local_seqpacket_listener(LOCAL_PORT);
+        ctx.init_tx_pkt(LOCAL_PORT, PEER_PORT, uapi::VSOCK_OP_REQUEST)
+            .hdr
+            .set_type(uapi::VSOCK_TYPE_SEQPACKET);
+        ctx.send();
+        assert_eq!(ctx.muxer.conn_map.len(), 1);
+        let mut stream = listener.accept();
+        ctx.recv();
+        assert_eq!(ctx.rx_pkt.hdr.op(), uapi::VSOCK_OP_RESPONSE);
+        assert_eq!(ctx.rx_pkt.hdr.type_(), uapi::VSOCK_TYPE_SEQPACKET);
+
+        // Test guest -> host data flow. A message split over two packets makes up a single
+        // record.
+        ctx.init_data_tx_pkt(LOCAL_PORT, PEER_PORT, &[1, 2, 3])
+            .hdr
+            .set_type(uapi::VSOCK_TYPE_SEQPACKET)
+            .set_flags(0);
+        ctx.send();
+        ctx.init_data_tx_pkt(LOCAL_PORT, PEER_PORT, &[4])
+            .hdr
+            .set_type(uapi::VSOCK_TYPE_SEQPACKET)
+            .set_flags(uapi::VSOCK_FLAGS_SEQ_EOM);
+        ctx.send();
+        let mut buf = [0u8; 16];
+        assert_eq!(stream.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf[..4], [1, 2, 3, 4]);
+
+        // Test host -> guest data flow. Each record makes up a message.
+        stream.write_all(&[5, 6]).unwrap();
+        stream.write_all(&[7]).unwrap();
+        ctx.notify_muxer();
+        assert!(ctx.muxer.has_pending_rx());
+        ctx.recv();
+        assert_eq!(ctx.rx_pkt.hdr.op(), uapi::VSOCK_OP_RW);
+        assert_eq!(ctx.rx_pkt.hdr.type_(), uapi::VSOCK_TYPE_SEQPACKET);
+        assert_eq!(ctx.rx_pkt.hdr.len(), 2);
+        assert_ne!(ctx.rx_pkt.hdr.flags() & uapi::VSOCK_FLAGS_SEQ_EOM, 0);
+        assert_eq!(test_utils::read_packet_data(&ctx.tx_pkt, 2), [5, 6]);
+    }
+
     #[test]
     fn test_local_connection() {
         // Test guest -> host data flow.
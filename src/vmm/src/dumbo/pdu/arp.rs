@@ -8,12 +8,18 @@
 //!
 //! [here]: https://en.wikipedia.org/wiki/Address_Resolution_Protocol
 use std::convert::From;
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display};
 use std::net::Ipv4Addr;
 use std::result::Result;
 
 use super::bytes::{InnerBytes, NetworkBytes, NetworkBytesMut};
 use super::ethernet::{self, ETHERTYPE_IPV4};
+// ARP frames are logged at trace level, or at debug level with the `arp-verbose` feature, to
+// follow them in builds which filter out trace logs.
+#[cfg(feature = "arp-verbose")]
+use crate::logger::debug as arp_log;
+#[cfg(not(feature = "arp-verbose"))]
+use crate::logger::trace as arp_log;
 use crate::utils::net::mac::{MAC_ADDR_LEN, MacAddr};
 
 /// ARP Request operation
@@ -70,16 +76,25 @@ pub struct EthIPv4ArpFrame<'a, T: 'a> {
     bytes: InnerBytes<'a, T>,
 }
 
+// Logs the frame in `bytes`, which failed validation with `err`.
+fn invalid_frame(bytes: &[u8], err: ArpError) -> ArpError {
+    arp_log!("Invalid ARP frame ({err:?}): {bytes:02x?}");
+    err
+}
+
 #[allow(clippy::len_without_is_empty)]
 impl<T: NetworkBytes + Debug> EthIPv4ArpFrame<'_, T> {
     /// Interprets the given bytes as an ARP frame, without doing any validity checks beforehand.
     ///
     ///  # Panics
     ///
-    /// This method does not panic, but further method calls on the resulting object may panic if
-    /// `bytes` contains invalid input.
+    /// The accessor methods of the resulting object read and write at fixed offsets, up to
+    /// `ETH_IPV4_FRAME_LEN`, so they panic if `bytes` is any shorter. Callers must check the length
+    /// of `bytes` beforehand, which debug builds assert.
     #[inline]
+    #[must_use = "the frame must be used or parsing is wasted"]
     pub fn from_bytes_unchecked(bytes: T) -> Self {
+        debug_assert!(bytes.len() >= ETH_IPV4_FRAME_LEN);
         EthIPv4ArpFrame {
             bytes: InnerBytes::new(bytes),
         }
@@ -89,33 +104,46 @@ impl<T: NetworkBytes + Debug> EthIPv4ArpFrame<'_, T> {
     ///
     /// If no error occurs, it guarantees accessor methods (which make use of various `_unchecked`
     /// functions) are safe to call on the result, because all predefined offsets will be valid.
+    #[inline]
     pub fn request_from_bytes(bytes: T) -> Result<Self, ArpError> {
+        Self::from_bytes(bytes, OPER_REQUEST)
+    }
+
+    /// Tries to interpret a byte slice as a valid IPv4 over Ethernet ARP reply.
+    ///
+    /// Offers the same guarantees as `request_from_bytes()` when no error occurs.
+    #[inline]
+    pub fn reply_from_bytes(bytes: T) -> Result<Self, ArpError> {
+        Self::from_bytes(bytes, OPER_REPLY)
+    }
+
+    fn from_bytes(bytes: T, operation: u16) -> Result<Self, ArpError> {
         // This kind of frame has a fixed length, so we know what to expect.
         if bytes.len() != ETH_IPV4_FRAME_LEN {
-            return Err(ArpError::SliceExactLen);
+            return Err(invalid_frame(&bytes, ArpError::SliceExactLen));
         }
 
         let maybe = EthIPv4ArpFrame::from_bytes_unchecked(bytes);
 
         if maybe.htype() != HTYPE_ETHERNET {
-            return Err(ArpError::HType);
+            return Err(invalid_frame(&maybe.bytes, ArpError::HType));
         }
 
         if maybe.ptype() != ETHERTYPE_IPV4 {
-            return Err(ArpError::PType);
+            return Err(invalid_frame(&maybe.bytes, ArpError::PType));
         }
 
         // We could theoretically skip the hlen and plen checks, since they are kinda implicit.
         if maybe.hlen() != MAC_ADDR_LEN {
-            return Err(ArpError::HLen);
+            return Err(invalid_frame(&maybe.bytes, ArpError::HLen));
         }
 
         if maybe.plen() != IPV4_ADDR_LEN {
-            return Err(ArpError::PLen);
+            return Err(invalid_frame(&maybe.bytes, ArpError::PLen));
         }
 
-        if maybe.operation() != OPER_REQUEST {
-            return Err(ArpError::Operation);
+        if maybe.operation() != operation {
+            return Err(invalid_frame(&maybe.bytes, ArpError::Operation));
         }
 
         Ok(maybe)
@@ -179,7 +207,7 @@ impl<T: NetworkBytes + Debug> EthIPv4ArpFrame<'_, T> {
     #[inline]
     pub fn len(&self) -> usize {
         // This might as well return ETH_IPV4_FRAME_LEN directly, since we check this is the actual
-        // length in request_from_bytes(). For some reason it seems nicer leaving it as is.
+        // length in from_bytes(). For some reason it seems nicer leaving it as is.
         self.bytes.len()
     }
 }
@@ -252,7 +280,7 @@ impl<T: NetworkBytesMut + Debug> EthIPv4ArpFrame<'_, T> {
         tha: MacAddr,
         tpa: Ipv4Addr,
     ) -> Result<Self, ArpError> {
-        Self::write_raw(
+        let frame = Self::write_raw(
             buf,
             HTYPE_ETHERNET,
             ETHERTYPE_IPV4,
@@ -263,7 +291,9 @@ impl<T: NetworkBytesMut + Debug> EthIPv4ArpFrame<'_, T> {
             spa,
             tha,
             tpa,
-        )
+        )?;
+        arp_log!("Wrote {frame}");
+        Ok(frame)
     }
 
     /// Sets the hardware type of the frame.
@@ -323,13 +353,32 @@ impl<T: NetworkBytesMut + Debug> EthIPv4ArpFrame<'_, T> {
     }
 }
 
+impl<T: NetworkBytes + Debug> Display for EthIPv4ArpFrame<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.operation() {
+            OPER_REQUEST => write!(f, "ARP REQUEST")?,
+            OPER_REPLY => write!(f, "ARP REPLY")?,
+            operation => write!(f, "ARP {operation:#06x}")?,
+        }
+        write!(
+            f,
+            " sha={} spa={} tha={} tpa={}",
+            self.sha(),
+            self.spa(),
+            self.tha(),
+            self.tpa()
+        )
+    }
+}
+
 /// This function checks if `buf` may hold an Ethernet frame which encapsulates an
 /// `EthIPv4ArpRequest` for the given address. Cannot produce false negatives.
 #[inline]
 pub fn test_speculative_tpa(buf: &[u8], addr: Ipv4Addr) -> bool {
     // The unchecked methods are safe because we actually check the buffer length beforehand.
-    if buf.len() >= ethernet::PAYLOAD_OFFSET + ETH_IPV4_FRAME_LEN {
-        let bytes = &buf[ethernet::PAYLOAD_OFFSET..];
+    let offset = ethernet::speculative_payload_offset(buf);
+    if buf.len() >= offset + ETH_IPV4_FRAME_LEN {
+        let bytes = &buf[offset..];
         if EthIPv4ArpFrame::from_bytes_unchecked(bytes).tpa() == addr {
             return true;
         }
@@ -337,6 +386,21 @@ pub fn test_speculative_tpa(buf: &[u8], addr: Ipv4Addr) -> bool {
     false
 }
 
+/// This function checks if `buf` may hold an Ethernet frame which encapsulates an
+/// `EthIPv4ArpRequest` from the given hardware address. Cannot produce false negatives.
+#[inline]
+pub fn test_speculative_sha(buf: &[u8], addr: MacAddr) -> bool {
+    // The unchecked methods are safe because we actually check the buffer length beforehand.
+    let offset = ethernet::speculative_payload_offset(buf);
+    if buf.len() >= offset + ETH_IPV4_FRAME_LEN {
+        let bytes = &buf[offset..];
+        if EthIPv4ArpFrame::from_bytes_unchecked(bytes).sha() == addr {
+            return true;
+        }
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -358,6 +422,10 @@ mod tests {
             EthIPv4ArpFrame::request_from_bytes(bad_array.as_ref()).unwrap_err(),
             ArpError::SliceExactLen
         );
+        assert_eq!(
+            EthIPv4ArpFrame::reply_from_bytes(bad_array.as_ref()).unwrap_err(),
+            ArpError::SliceExactLen
+        );
 
         // Slice is too short.
         assert_eq!(
@@ -388,6 +456,15 @@ mod tests {
             assert_eq!(f.tpa(), tpa);
         }
 
+        // The reply we wrote parses back as one.
+        {
+            let f = EthIPv4ArpFrame::reply_from_bytes(&a[..ETH_IPV4_FRAME_LEN]).unwrap();
+            assert_eq!(f.sha(), sha);
+            assert_eq!(f.spa(), spa);
+            assert_eq!(f.tha(), tha);
+            assert_eq!(f.tpa(), tpa);
+        }
+
         // Now let's try to parse a request.
 
         // Slice is too long.
@@ -395,6 +472,10 @@ mod tests {
             EthIPv4ArpFrame::request_from_bytes(a.as_ref()).unwrap_err(),
             ArpError::SliceExactLen
         );
+        assert_eq!(
+            EthIPv4ArpFrame::reply_from_bytes(a.as_ref()).unwrap_err(),
+            ArpError::SliceExactLen
+        );
 
         // The length is fine now, but the operation is a reply instead of request.
         assert_eq!(
@@ -465,6 +546,72 @@ mod tests {
                 ),
             }
         }
+
+        // The same frames, as replies. The operation is checked last, so a valid reply parsed as
+        // a request only fails on it.
+        for (htype, ptype, hlen, plen, err) in requests.iter() {
+            EthIPv4ArpFrame::write_raw(
+                &mut a[..ETH_IPV4_FRAME_LEN],
+                *htype,
+                *ptype,
+                *hlen,
+                *plen,
+                OPER_REPLY,
+                sha,
+                spa,
+                tha,
+                tpa,
+            )
+            .unwrap();
+            match err {
+                None => {
+                    EthIPv4ArpFrame::reply_from_bytes(&a[..ETH_IPV4_FRAME_LEN]).unwrap();
+                    assert_eq!(
+                        EthIPv4ArpFrame::request_from_bytes(&a[..ETH_IPV4_FRAME_LEN]).unwrap_err(),
+                        ArpError::Operation
+                    );
+                }
+                Some(arp_error) => assert_eq!(
+                    EthIPv4ArpFrame::reply_from_bytes(&a[..ETH_IPV4_FRAME_LEN]).unwrap_err(),
+                    *arp_error
+                ),
+            }
+        }
+
+        // A request is not a reply.
+        EthIPv4ArpFrame::write_request(&mut a[..ETH_IPV4_FRAME_LEN], sha, spa, tha, tpa).unwrap();
+        assert_eq!(
+            EthIPv4ArpFrame::reply_from_bytes(&a[..ETH_IPV4_FRAME_LEN]).unwrap_err(),
+            ArpError::Operation
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let mut a = [0u8; ETH_IPV4_FRAME_LEN];
+        let sha = MacAddr::from_str("01:23:45:67:89:ab").unwrap();
+        let tha = MacAddr::from_str("ff:ff:ff:ff:ff:ff").unwrap();
+        let spa = Ipv4Addr::new(10, 0, 0, 1);
+        let tpa = Ipv4Addr::new(10, 0, 0, 2);
+
+        let f = EthIPv4ArpFrame::write_request(a.as_mut(), sha, spa, tha, tpa).unwrap();
+        assert_eq!(
+            f.to_string(),
+            "ARP REQUEST sha=01:23:45:67:89:ab spa=10.0.0.1 tha=ff:ff:ff:ff:ff:ff tpa=10.0.0.2"
+        );
+
+        let f = EthIPv4ArpFrame::write_reply(a.as_mut(), sha, spa, tha, tpa).unwrap();
+        assert_eq!(
+            f.to_string(),
+            "ARP REPLY sha=01:23:45:67:89:ab spa=10.0.0.1 tha=ff:ff:ff:ff:ff:ff tpa=10.0.0.2"
+        );
+
+        let mut f = EthIPv4ArpFrame::write_reply(a.as_mut(), sha, spa, tha, tpa).unwrap();
+        f.set_operation(0x1a);
+        assert_eq!(
+            f.to_string(),
+            "ARP 0x001a sha=01:23:45:67:89:ab spa=10.0.0.1 tha=ff:ff:ff:ff:ff:ff tpa=10.0.0.2"
+        );
     }
 
     #[test]
@@ -489,8 +636,65 @@ mod tests {
 
         assert!(test_speculative_tpa(a.as_ref(), addr));
 
+        // The ARP frame follows the tag of 802.1Q-tagged frames.
+        let mut tagged = [0u8; 1000];
+        {
+            let mac = MacAddr::from_bytes_unchecked(&[0; 6]);
+            let mut eth = crate::dumbo::pdu::ethernet::EthernetFrame::write_incomplete(
+                tagged.as_mut(),
+                mac,
+                mac,
+                ethernet::ETHERTYPE_VLAN,
+            )
+            .unwrap();
+            let mut arp = EthIPv4ArpFrame::from_bytes_unchecked(eth.inner_mut().payload_mut());
+            arp.set_tpa(addr);
+        }
+
+        assert!(test_speculative_tpa(tagged.as_ref(), addr));
+        let len = ethernet::VLAN_PAYLOAD_OFFSET + ETH_IPV4_FRAME_LEN;
+        assert!(test_speculative_tpa(&tagged[..len], addr));
+        assert!(!test_speculative_tpa(&tagged[..len - 1], addr));
+
         // Let's also test for a very small buffer.
         let small = [0u8; 1];
         assert!(!test_speculative_tpa(small.as_ref(), addr));
     }
+
+    #[test]
+    fn test_speculative_sender() {
+        let mut a = [0u8; 1000];
+        let sha = MacAddr::from_str("01:23:45:67:89:ab").unwrap();
+
+        assert!(!test_speculative_sha(a.as_ref(), sha));
+
+        {
+            // The sender hardware address of the Ethernet frame does not matter.
+            let mac = MacAddr::from_bytes_unchecked(&[0; 6]);
+            let mut eth = crate::dumbo::pdu::ethernet::EthernetFrame::write_incomplete(
+                a.as_mut(),
+                mac,
+                mac,
+                0,
+            )
+            .unwrap();
+            let mut arp = EthIPv4ArpFrame::from_bytes_unchecked(eth.inner_mut().payload_mut());
+            arp.set_sha(sha);
+        }
+
+        assert!(test_speculative_sha(a.as_ref(), sha));
+        assert!(!test_speculative_sha(
+            a.as_ref(),
+            MacAddr::from_str("01:23:45:67:89:ac").unwrap()
+        ));
+
+        // The buffer must hold a whole frame.
+        let len = ethernet::PAYLOAD_OFFSET + ETH_IPV4_FRAME_LEN;
+        assert!(test_speculative_sha(&a[..len], sha));
+        assert!(!test_speculative_sha(&a[..len - 1], sha));
+
+        // Let's also test for a very small buffer.
+        let small = [0u8; 1];
+        assert!(!test_speculative_sha(small.as_ref(), sha));
+    }
 }
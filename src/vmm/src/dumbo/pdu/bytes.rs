@@ -134,6 +134,37 @@ pub trait NetworkBytesMut: NetworkBytes + DerefMut<Target = [u8]> {
     }
 }
 
+/// Adds the 16 bit words of `data` to `sum` using ones' complement arithmetic, as the RFC 1071
+/// Internet checksum does, and returns the new sum.
+///
+/// This allows computing the checksum of data split across several slices, by passing the sum
+/// returned for each slice to the call for the next one. A slice with an odd length is padded with
+/// a zero byte, so only the last slice can have an odd length.
+#[inline]
+pub fn partial_checksum(data: &[u8], sum: u16) -> u16 {
+    let mut sum = u64::from(sum);
+    let mut words = data.chunks_exact(2);
+    for word in &mut words {
+        sum += u64::from(u16::from_be_bytes([word[0], word[1]]));
+    }
+    if let [last] = words.remainder() {
+        sum += u64::from(*last) << 8;
+    }
+
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    // Safe to unwrap due to the while loop above.
+    u16::try_from(sum).unwrap()
+}
+
+/// Computes the RFC 1071 Internet checksum of `data`.
+#[inline]
+pub fn checksum(data: &[u8]) -> u16 {
+    !partial_checksum(data, 0)
+}
+
 impl NetworkBytes for &[u8] {
     #[inline]
     fn shrink_unchecked(&mut self, len: usize) {
@@ -214,6 +245,47 @@ mod tests {
         a.htonl_unchecked(1, u32::default());
     }
 
+    #[test]
+    fn test_checksum() {
+        // The example of RFC 1071.
+        let data = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        assert_eq!(partial_checksum(&data, 0), 0xddf2);
+        assert_eq!(checksum(&data), 0x220d);
+
+        // An IPv4 header, with its checksum field set to 0.
+        let header = [
+            0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
+        ];
+        assert_eq!(checksum(&header), 0xb861);
+
+        // The checksum of data including its own checksum is 0.
+        let mut header_with_checksum = header;
+        header_with_checksum[10..12].copy_from_slice(&0xb861u16.to_be_bytes());
+        assert_eq!(checksum(&header_with_checksum), 0);
+
+        // Odd lengths are padded with a zero byte.
+        assert_eq!(checksum(&[0x12]), !0x1200);
+        assert_eq!(checksum(&[0x12, 0x34, 0x56]), !(0x1234 + 0x5600));
+
+        // Carries wrap around.
+        assert_eq!(partial_checksum(&[0xff, 0xff, 0x00, 0x01], 0), 0x0001);
+        assert_eq!(partial_checksum(&[0x80, 0x00], 0x8000), 0x0001);
+
+        // Empty data.
+        assert_eq!(partial_checksum(&[], 0x1234), 0x1234);
+        assert_eq!(checksum(&[]), 0xffff);
+
+        // Partial sums over slices with even lengths add up to the sum over the whole data.
+        for split in (0..=header.len()).step_by(2) {
+            let (a, b) = header.split_at(split);
+            assert_eq!(
+                !partial_checksum(b, partial_checksum(a, 0)),
+                checksum(&header)
+            );
+        }
+    }
+
     #[test]
     fn test_network_bytes() {
         let mut buf = [0u8; 1000];
@@ -1,8 +1,8 @@
 // Copyright 2018 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-//! Contains support for parsing and writing Ethernet frames. Does not currently offer support for
-//! 802.1Q tags.
+//! Contains support for parsing and writing Ethernet frames. Frames carrying a single 802.1Q tag
+//! can be parsed, but only untagged frames are written.
 
 use std::fmt::Debug;
 use std::result::Result;
@@ -14,17 +14,20 @@ use crate::dumbo::MacAddr;
 const DST_MAC_OFFSET: usize = 0;
 const SRC_MAC_OFFSET: usize = 6;
 const ETHERTYPE_OFFSET: usize = 12;
+const VLAN_TCI_OFFSET: usize = 14;
+const VLAN_ETHERTYPE_OFFSET: usize = 16;
 
-// We don't support 802.1Q tags.
-// TODO: support 802.1Q tags?! If so, don't forget to change the speculative_test_* functions
-// for ARP and IPv4.
-/// Payload offset in an ethernet frame
+/// Payload offset in an untagged ethernet frame
 pub const PAYLOAD_OFFSET: usize = 14;
+/// Payload offset in an 802.1Q-tagged ethernet frame
+pub const VLAN_PAYLOAD_OFFSET: usize = 18;
 
 /// Ethertype value for ARP frames.
 pub const ETHERTYPE_ARP: u16 = 0x0806;
 /// Ethertype value for IPv4 packets.
 pub const ETHERTYPE_IPV4: u16 = 0x0800;
+/// Ethertype value (TPID) announcing an 802.1Q tag.
+pub const ETHERTYPE_VLAN: u16 = 0x8100;
 
 /// Describes the errors which may occur when handling Ethernet frames.
 #[derive(Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
@@ -33,6 +36,19 @@ pub enum EthernetError {
     SliceTooShort,
 }
 
+/// The 802.1Q tag of an Ethernet frame, along with the ethertype of the payload it encapsulates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VlanTag {
+    /// Priority code point.
+    pub pcp: u8,
+    /// Drop eligible indicator.
+    pub dei: bool,
+    /// VLAN identifier.
+    pub vid: u16,
+    /// Ethertype of the encapsulated payload.
+    pub ethertype: u16,
+}
+
 /// Interprets the inner bytes as an Ethernet frame.
 #[derive(Debug)]
 pub struct EthernetFrame<'a, T: 'a> {
@@ -61,7 +77,12 @@ impl<T: NetworkBytes + Debug> EthernetFrame<'_, T> {
             return Err(EthernetError::SliceTooShort);
         }
 
-        Ok(EthernetFrame::from_bytes_unchecked(bytes))
+        let frame = EthernetFrame::from_bytes_unchecked(bytes);
+        if frame.len() < frame.payload_offset() {
+            return Err(EthernetError::SliceTooShort);
+        }
+
+        Ok(frame)
     }
 
     /// Returns the destination MAC address.
@@ -76,16 +97,37 @@ impl<T: NetworkBytes + Debug> EthernetFrame<'_, T> {
         MacAddr::from_bytes_unchecked(&self.bytes[SRC_MAC_OFFSET..ETHERTYPE_OFFSET])
     }
 
-    /// Returns the ethertype of the frame.
+    /// Returns the ethertype of the frame. This is `ETHERTYPE_VLAN` for 802.1Q-tagged frames, in
+    /// which case the ethertype of the payload is part of the `VlanTag`.
     #[inline]
     pub fn ethertype(&self) -> u16 {
         self.bytes.ntohs_unchecked(ETHERTYPE_OFFSET)
     }
 
-    /// Returns the offset of the payload within the frame.
+    /// Returns the 802.1Q tag of the frame, if it has one.
+    #[inline]
+    pub fn vlan_tag(&self) -> Option<VlanTag> {
+        if self.ethertype() != ETHERTYPE_VLAN {
+            return None;
+        }
+
+        let tci = self.bytes.ntohs_unchecked(VLAN_TCI_OFFSET);
+        Some(VlanTag {
+            pcp: self.bytes[VLAN_TCI_OFFSET] >> 5,
+            dei: tci & 0x1000 != 0,
+            vid: tci & 0x0fff,
+            ethertype: self.bytes.ntohs_unchecked(VLAN_ETHERTYPE_OFFSET),
+        })
+    }
+
+    /// Returns the offset of the payload within the frame, past the 802.1Q tag if there is one.
     #[inline]
     pub fn payload_offset(&self) -> usize {
-        PAYLOAD_OFFSET
+        if self.ethertype() == ETHERTYPE_VLAN {
+            VLAN_PAYLOAD_OFFSET
+        } else {
+            PAYLOAD_OFFSET
+        }
     }
 
     /// Returns the payload of the frame as an `[&u8]` slice.
@@ -109,7 +151,9 @@ impl<T: NetworkBytesMut + Debug> EthernetFrame<'_, T> {
         src_mac: MacAddr,
         ethertype: u16,
     ) -> Result<Self, EthernetError> {
-        if buf.len() < PAYLOAD_OFFSET {
+        if buf.len() < PAYLOAD_OFFSET
+            || (ethertype == ETHERTYPE_VLAN && buf.len() < VLAN_PAYLOAD_OFFSET)
+        {
             return Err(EthernetError::SliceTooShort);
         }
 
@@ -183,6 +227,20 @@ impl<'a, T: NetworkBytes + Debug> Incomplete<EthernetFrame<'a, T>> {
     }
 }
 
+/// Returns the offset of the payload of the Ethernet frame `buf` may hold, past the 802.1Q tag if
+/// there is one. Frames too short to hold an ethertype are treated as untagged.
+#[inline]
+pub fn speculative_payload_offset(buf: &[u8]) -> usize {
+    // The unchecked method is safe because we actually check the buffer length beforehand.
+    if buf.len() >= PAYLOAD_OFFSET
+        && EthernetFrame::from_bytes_unchecked(buf).ethertype() == ETHERTYPE_VLAN
+    {
+        VLAN_PAYLOAD_OFFSET
+    } else {
+        PAYLOAD_OFFSET
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -235,6 +293,70 @@ mod tests {
             assert_eq!(f3_complete.len(), f3_complete.payload_offset() + 123);
         }
     }
+
+    #[test]
+    fn test_vlan_tag() {
+        let mut a = [0u8; 100];
+
+        let dst_mac = MacAddr::from_str("01:23:45:67:89:ab").unwrap();
+        let src_mac = MacAddr::from_str("cd:ef:01:23:45:67").unwrap();
+
+        // Untagged frames have no tag.
+        {
+            EthernetFrame::new_with_header(a.as_mut(), dst_mac, src_mac, ETHERTYPE_IPV4).unwrap();
+            let f = EthernetFrame::from_bytes(a.as_ref()).unwrap();
+            assert_eq!(f.vlan_tag(), None);
+            assert_eq!(f.payload_offset(), PAYLOAD_OFFSET);
+            assert_eq!(speculative_payload_offset(a.as_ref()), PAYLOAD_OFFSET);
+        }
+
+        // PCP 5, DEI set, VID 0x123, followed by an ARP payload.
+        {
+            let mut f =
+                EthernetFrame::new_with_header(a.as_mut(), dst_mac, src_mac, ETHERTYPE_VLAN)
+                    .unwrap();
+            f.bytes.htons_unchecked(VLAN_TCI_OFFSET, 0xb123);
+            f.bytes
+                .htons_unchecked(VLAN_ETHERTYPE_OFFSET, ETHERTYPE_ARP);
+            f.payload_mut()[0] = 42;
+        }
+
+        {
+            let f = EthernetFrame::from_bytes(a.as_ref()).unwrap();
+            assert_eq!(f.ethertype(), ETHERTYPE_VLAN);
+            assert_eq!(
+                f.vlan_tag(),
+                Some(VlanTag {
+                    pcp: 5,
+                    dei: true,
+                    vid: 0x123,
+                    ethertype: ETHERTYPE_ARP,
+                })
+            );
+            assert_eq!(f.payload_offset(), VLAN_PAYLOAD_OFFSET);
+            assert_eq!(f.payload()[0], 42);
+            assert_eq!(f.payload().len(), a.len() - VLAN_PAYLOAD_OFFSET);
+            assert_eq!(speculative_payload_offset(a.as_ref()), VLAN_PAYLOAD_OFFSET);
+        }
+
+        // A tagged frame must be long enough to hold the whole tag.
+        assert_eq!(
+            EthernetFrame::from_bytes(&a[..VLAN_PAYLOAD_OFFSET - 1]).unwrap_err(),
+            EthernetError::SliceTooShort
+        );
+        EthernetFrame::from_bytes(&a[..VLAN_PAYLOAD_OFFSET]).unwrap();
+        assert_eq!(
+            EthernetFrame::new_with_header(
+                &mut a[..VLAN_PAYLOAD_OFFSET - 1],
+                dst_mac,
+                src_mac,
+                ETHERTYPE_VLAN
+            )
+            .unwrap_err(),
+            EthernetError::SliceTooShort
+        );
+        assert_eq!(speculative_payload_offset(&a[..1]), PAYLOAD_OFFSET);
+    }
 }
 
 #[cfg(kani)]
@@ -251,7 +373,7 @@ mod kani_proofs {
 
     impl<'a, T: NetworkBytesMut + Debug> EthernetFrame<'a, T> {
         fn is_valid(&self) -> bool {
-            self.len() >= PAYLOAD_OFFSET
+            self.len() >= PAYLOAD_OFFSET && self.len() >= self.payload_offset()
         }
     }
 
@@ -270,7 +392,8 @@ mod kani_proofs {
         // Check for post-conditions
         assert_eq!(ethernet.len(), slice_length);
         assert!(
-            !(ethernet.is_valid()) || (ethernet.payload().len() == slice_length - PAYLOAD_OFFSET)
+            !(ethernet.is_valid())
+                || (ethernet.payload().len() == slice_length - ethernet.payload_offset())
         );
     }
 
@@ -288,7 +411,10 @@ mod kani_proofs {
             let ethernet = ethernet.unwrap();
             assert!(ethernet.is_valid());
             assert_eq!(ethernet.len(), slice_length);
-            assert_eq!(ethernet.payload().len(), slice_length - PAYLOAD_OFFSET);
+            assert_eq!(
+                ethernet.payload().len(),
+                slice_length - ethernet.payload_offset()
+            );
         } else {
             ethernet.unwrap_err();
         }
@@ -442,7 +568,11 @@ mod kani_proofs {
         // Check for post-conditions
 
         // Check payload_offset value
-        assert_eq!(payload_offset, PAYLOAD_OFFSET);
+        if ethernet.ethertype() == ETHERTYPE_VLAN {
+            assert_eq!(payload_offset, VLAN_PAYLOAD_OFFSET);
+        } else {
+            assert_eq!(payload_offset, PAYLOAD_OFFSET);
+        }
 
         // Check equivalence
         assert_eq!(payload, payload_mut);
@@ -476,7 +606,9 @@ mod kani_proofs {
         assert_eq!(frame.src_mac(), src_mac);
         assert_eq!(frame.ethertype(), ethertype);
         assert_eq!(frame.len(), bytes_length);
-        assert!(frame.is_valid() && (frame.payload().len() == bytes_length - PAYLOAD_OFFSET));
+        assert!(
+            frame.is_valid() && (frame.payload().len() == bytes_length - frame.payload_offset())
+        );
     }
 
     #[kani::proof]
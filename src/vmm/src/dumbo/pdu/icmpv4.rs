@@ -0,0 +1,310 @@
+// Copyright 2026 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Contains support for parsing and writing ICMPv4 echo request and echo reply messages, which is
+//! all it takes to answer pings. Other ICMP messages are not supported.
+//!
+//! Details of the ICMP message formats can be found at [1].
+//!
+//! [1]: https://tools.ietf.org/html/rfc792
+
+use std::fmt::Debug;
+use std::result::Result;
+
+use super::bytes::{InnerBytes, NetworkBytes, NetworkBytesMut, checksum};
+
+const TYPE_OFFSET: usize = 0;
+const CODE_OFFSET: usize = 1;
+const CHECKSUM_OFFSET: usize = 2;
+const IDENTIFIER_OFFSET: usize = 4;
+const SEQUENCE_NUMBER_OFFSET: usize = 6;
+const PAYLOAD_OFFSET: usize = 8;
+
+/// The length of the header of ICMP echo messages.
+pub const ICMP_ECHO_HEADER_LEN: usize = 8;
+
+/// The type of ICMP echo reply messages.
+pub const ICMP_TYPE_ECHO_REPLY: u8 = 0;
+/// The type of ICMP echo request messages.
+pub const ICMP_TYPE_ECHO_REQUEST: u8 = 8;
+
+/// Describes the errors which may occur while handling ICMP echo messages.
+#[derive(Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+pub enum IcmpError {
+    /// Invalid checksum.
+    Checksum,
+    /// Invalid code field.
+    Code,
+    /// The specified byte sequence is shorter than the echo message header.
+    SliceTooShort,
+    /// Invalid type field.
+    Type,
+}
+
+/// Interprets the inner bytes as an ICMP echo request or echo reply message.
+#[derive(Debug)]
+pub struct IcmpEchoFrame<'a, T: 'a> {
+    bytes: InnerBytes<'a, T>,
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl<T: NetworkBytes + Debug> IcmpEchoFrame<'_, T> {
+    /// Interprets `bytes` as an ICMP echo message without any validity checks.
+    ///
+    /// # Panics
+    ///
+    /// This method does not panic, but further method calls on the resulting object may panic if
+    /// `bytes` contains invalid input.
+    #[inline]
+    pub fn from_bytes_unchecked(bytes: T) -> Self {
+        IcmpEchoFrame {
+            bytes: InnerBytes::new(bytes),
+        }
+    }
+
+    fn from_bytes(bytes: T, msg_type: u8, verify_checksum: bool) -> Result<Self, IcmpError> {
+        if bytes.len() < ICMP_ECHO_HEADER_LEN {
+            return Err(IcmpError::SliceTooShort);
+        }
+
+        let frame = Self::from_bytes_unchecked(bytes);
+
+        if frame.msg_type() != msg_type {
+            return Err(IcmpError::Type);
+        }
+
+        if frame.code() != 0 {
+            return Err(IcmpError::Code);
+        }
+
+        if verify_checksum && frame.compute_checksum() != 0 {
+            return Err(IcmpError::Checksum);
+        }
+
+        Ok(frame)
+    }
+
+    /// Attempts to interpret `bytes` as an ICMP echo request, optionally verifying its checksum.
+    #[inline]
+    pub fn request_from_bytes(bytes: T, verify_checksum: bool) -> Result<Self, IcmpError> {
+        Self::from_bytes(bytes, ICMP_TYPE_ECHO_REQUEST, verify_checksum)
+    }
+
+    /// Attempts to interpret `bytes` as an ICMP echo reply, optionally verifying its checksum.
+    #[inline]
+    pub fn reply_from_bytes(bytes: T, verify_checksum: bool) -> Result<Self, IcmpError> {
+        Self::from_bytes(bytes, ICMP_TYPE_ECHO_REPLY, verify_checksum)
+    }
+
+    /// Returns the value of the `type` field.
+    #[inline]
+    pub fn msg_type(&self) -> u8 {
+        self.bytes[TYPE_OFFSET]
+    }
+
+    /// Returns the value of the `code` field.
+    #[inline]
+    pub fn code(&self) -> u8 {
+        self.bytes[CODE_OFFSET]
+    }
+
+    /// Returns the value of the `checksum` field.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        self.bytes.ntohs_unchecked(CHECKSUM_OFFSET)
+    }
+
+    /// Returns the value of the `identifier` field.
+    #[inline]
+    pub fn identifier(&self) -> u16 {
+        self.bytes.ntohs_unchecked(IDENTIFIER_OFFSET)
+    }
+
+    /// Returns the value of the `sequence number` field.
+    #[inline]
+    pub fn sequence_number(&self) -> u16 {
+        self.bytes.ntohs_unchecked(SEQUENCE_NUMBER_OFFSET)
+    }
+
+    /// Returns the data carried by the message, which replies echo back.
+    #[inline]
+    pub fn payload(&self) -> &[u8] {
+        self.bytes.split_at(PAYLOAD_OFFSET).1
+    }
+
+    /// Returns the length of the message.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Computes the checksum of the message, which is 0 for messages with a valid `checksum`
+    /// field.
+    #[inline]
+    pub fn compute_checksum(&self) -> u16 {
+        checksum(&self.bytes)
+    }
+}
+
+impl<T: NetworkBytesMut + Debug> IcmpEchoFrame<'_, T> {
+    /// Attempts to write an ICMP echo reply to `buf`, echoing back the `identifier`,
+    /// `sequence_number` and `payload` of the request it answers. The inner byte sequence is
+    /// shrunk to the length of the reply.
+    pub fn write_reply(
+        buf: T,
+        identifier: u16,
+        sequence_number: u16,
+        payload: &[u8],
+    ) -> Result<Self, IcmpError> {
+        let len = ICMP_ECHO_HEADER_LEN + payload.len();
+        if buf.len() < len {
+            return Err(IcmpError::SliceTooShort);
+        }
+
+        let mut frame = Self::from_bytes_unchecked(buf);
+        frame.bytes.shrink_unchecked(len);
+        frame.payload_mut().copy_from_slice(payload);
+        frame
+            .set_msg_type(ICMP_TYPE_ECHO_REPLY)
+            .set_code(0)
+            .set_identifier(identifier)
+            .set_sequence_number(sequence_number)
+            .set_checksum(0);
+        let checksum = frame.compute_checksum();
+        frame.set_checksum(checksum);
+
+        Ok(frame)
+    }
+
+    /// Sets the value of the `type` field.
+    #[inline]
+    pub fn set_msg_type(&mut self, value: u8) -> &mut Self {
+        self.bytes[TYPE_OFFSET] = value;
+        self
+    }
+
+    /// Sets the value of the `code` field.
+    #[inline]
+    pub fn set_code(&mut self, value: u8) -> &mut Self {
+        self.bytes[CODE_OFFSET] = value;
+        self
+    }
+
+    /// Sets the value of the `checksum` field.
+    #[inline]
+    pub fn set_checksum(&mut self, value: u16) -> &mut Self {
+        self.bytes.htons_unchecked(CHECKSUM_OFFSET, value);
+        self
+    }
+
+    /// Sets the value of the `identifier` field.
+    #[inline]
+    pub fn set_identifier(&mut self, value: u16) -> &mut Self {
+        self.bytes.htons_unchecked(IDENTIFIER_OFFSET, value);
+        self
+    }
+
+    /// Sets the value of the `sequence number` field.
+    #[inline]
+    pub fn set_sequence_number(&mut self, value: u16) -> &mut Self {
+        self.bytes.htons_unchecked(SEQUENCE_NUMBER_OFFSET, value);
+        self
+    }
+
+    /// Returns the data carried by the message as a `&mut [u8]` slice.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes[PAYLOAD_OFFSET..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_get() {
+        let mut a = [0u8; 20];
+        let mut f = IcmpEchoFrame::from_bytes_unchecked(a.as_mut());
+
+        f.set_msg_type(ICMP_TYPE_ECHO_REQUEST)
+            .set_code(3)
+            .set_checksum(0x1234)
+            .set_identifier(0xabcd)
+            .set_sequence_number(7);
+        f.payload_mut()[0] = 42;
+
+        assert_eq!(f.msg_type(), ICMP_TYPE_ECHO_REQUEST);
+        assert_eq!(f.code(), 3);
+        assert_eq!(f.checksum(), 0x1234);
+        assert_eq!(f.identifier(), 0xabcd);
+        assert_eq!(f.sequence_number(), 7);
+        assert_eq!(f.payload().len(), 20 - ICMP_ECHO_HEADER_LEN);
+        assert_eq!(f.payload()[0], 42);
+        assert_eq!(f.len(), 20);
+    }
+
+    #[test]
+    fn test_echo() {
+        let mut a = [0u8; 100];
+        let payload = b"ping";
+
+        assert_eq!(
+            IcmpEchoFrame::write_reply(&mut a[..ICMP_ECHO_HEADER_LEN + 3], 1, 2, payload)
+                .unwrap_err(),
+            IcmpError::SliceTooShort
+        );
+
+        let len = {
+            let f = IcmpEchoFrame::write_reply(a.as_mut(), 0x1234, 5, payload).unwrap();
+            assert_eq!(f.len(), ICMP_ECHO_HEADER_LEN + payload.len());
+            f.len()
+        };
+
+        let f = IcmpEchoFrame::reply_from_bytes(&a[..len], true).unwrap();
+        assert_eq!(f.msg_type(), ICMP_TYPE_ECHO_REPLY);
+        assert_eq!(f.code(), 0);
+        assert_eq!(f.identifier(), 0x1234);
+        assert_eq!(f.sequence_number(), 5);
+        assert_eq!(f.payload(), payload);
+        // Checksum computed by hand.
+        assert_eq!(f.checksum(), 0x0ef6);
+
+        // A reply is not a request.
+        assert_eq!(
+            IcmpEchoFrame::request_from_bytes(&a[..len], true).unwrap_err(),
+            IcmpError::Type
+        );
+
+        // Turn the reply into a request, keeping the checksum valid.
+        {
+            let mut f = IcmpEchoFrame::from_bytes_unchecked(&mut a[..len]);
+            f.set_msg_type(ICMP_TYPE_ECHO_REQUEST).set_checksum(0);
+            let checksum = f.compute_checksum();
+            f.set_checksum(checksum);
+        }
+        let f = IcmpEchoFrame::request_from_bytes(&a[..len], true).unwrap();
+        assert_eq!(f.identifier(), 0x1234);
+        assert_eq!(f.payload(), payload);
+
+        // Corrupt the checksum.
+        IcmpEchoFrame::from_bytes_unchecked(&mut a[..len]).set_checksum(0);
+        assert_eq!(
+            IcmpEchoFrame::request_from_bytes(&a[..len], true).unwrap_err(),
+            IcmpError::Checksum
+        );
+        IcmpEchoFrame::request_from_bytes(&a[..len], false).unwrap();
+
+        // Only code 0 is valid.
+        IcmpEchoFrame::from_bytes_unchecked(&mut a[..len]).set_code(1);
+        assert_eq!(
+            IcmpEchoFrame::request_from_bytes(&a[..len], false).unwrap_err(),
+            IcmpError::Code
+        );
+
+        assert_eq!(
+            IcmpEchoFrame::request_from_bytes(&a[..ICMP_ECHO_HEADER_LEN - 1], false).unwrap_err(),
+            IcmpError::SliceTooShort
+        );
+    }
+}
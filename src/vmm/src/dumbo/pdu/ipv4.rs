@@ -12,7 +12,7 @@ use std::fmt::Debug;
 use std::net::Ipv4Addr;
 use std::result::Result;
 
-use crate::dumbo::pdu::bytes::{InnerBytes, NetworkBytes, NetworkBytesMut};
+use crate::dumbo::pdu::bytes::{InnerBytes, NetworkBytes, NetworkBytesMut, checksum};
 use crate::dumbo::pdu::{Incomplete, ethernet};
 
 const VERSION_AND_IHL_OFFSET: usize = 0;
@@ -32,6 +32,9 @@ pub const IPV4_VERSION: u8 = 0x04;
 /// Default TTL value
 pub const DEFAULT_TTL: u8 = 1;
 
+/// The IP protocol number associated with ICMP.
+pub const PROTOCOL_ICMP: u8 = 0x01;
+
 /// The IP protocol number associated with TCP.
 pub const PROTOCOL_TCP: u8 = 0x06;
 
@@ -230,17 +233,7 @@ impl<T: NetworkBytes + Debug> IPv4Packet<'_, T> {
     ///
     /// [here]: https://en.wikipedia.org/wiki/IPv4_header_checksum
     pub fn compute_checksum_unchecked(&self, header_len: usize) -> u16 {
-        let mut sum = 0u32;
-        for i in 0..header_len / 2 {
-            sum += u32::from(self.bytes.ntohs_unchecked(i * 2));
-        }
-
-        while sum >> 16 != 0 {
-            sum = (sum & 0xffff) + (sum >> 16);
-        }
-
-        // Safe to unwrap due to the while loop.
-        !u16::try_from(sum).unwrap()
+        checksum(&self.bytes[..header_len])
     }
 
     /// Computes and returns the packet header checksum.
@@ -458,8 +451,9 @@ impl<'a, T: NetworkBytesMut + Debug> Incomplete<IPv4Packet<'a, T>> {
 #[inline]
 pub fn test_speculative_dst_addr(buf: &[u8], addr: Ipv4Addr) -> bool {
     // The unchecked methods are safe because we actually check the buffer length beforehand.
-    if buf.len() >= ethernet::PAYLOAD_OFFSET + usize::from(OPTIONS_OFFSET) {
-        let bytes = &buf[ethernet::PAYLOAD_OFFSET..];
+    let offset = ethernet::speculative_payload_offset(buf);
+    if buf.len() >= offset + usize::from(OPTIONS_OFFSET) {
+        let bytes = &buf[offset..];
         if IPv4Packet::from_bytes_unchecked(bytes).destination_address() == addr {
             return true;
         }
@@ -686,6 +680,20 @@ mod tests {
         }
         assert!(!test_speculative_dst_addr(buf.as_ref(), ip));
 
+        // The packet follows the tag of 802.1Q-tagged frames.
+        {
+            let mut eth = crate::dumbo::pdu::ethernet::EthernetFrame::write_incomplete(
+                buf.as_mut(),
+                mac,
+                mac,
+                ethernet::ETHERTYPE_VLAN,
+            )
+            .unwrap();
+            IPv4Packet::from_bytes_unchecked(eth.inner_mut().payload_mut())
+                .set_destination_address(ip);
+        }
+        assert!(test_speculative_dst_addr(buf.as_ref(), ip));
+
         let small = [0u8; 1];
         assert!(!test_speculative_dst_addr(small.as_ref(), ip));
     }
@@ -10,12 +10,13 @@
 use std::fmt::Debug;
 use std::net::Ipv4Addr;
 
-use crate::dumbo::pdu::bytes::NetworkBytes;
+use crate::dumbo::pdu::bytes::{NetworkBytes, partial_checksum};
 use crate::dumbo::pdu::ipv4::{PROTOCOL_TCP, PROTOCOL_UDP};
 
 pub mod arp;
 pub mod bytes;
 pub mod ethernet;
+pub mod icmpv4;
 pub mod ipv4;
 pub mod tcp;
 pub mod udp;
@@ -78,34 +79,14 @@ fn compute_checksum<T: NetworkBytes + Debug>(
     dst_addr: Ipv4Addr,
     protocol: ChecksumProto,
 ) -> u16 {
-    let mut sum = 0usize;
-
-    let a = u32::from(src_addr) as usize;
-    sum += a & 0xffff;
-    sum += a >> 16;
-
-    let b = u32::from(dst_addr) as usize;
-    sum += b & 0xffff;
-    sum += b >> 16;
-
-    let len = bytes.len();
-    sum += protocol as usize;
-    sum += len;
-
-    for i in 0..len / 2 {
-        sum += usize::from(bytes.ntohs_unchecked(i * 2));
-    }
-
-    if len % 2 != 0 {
-        sum += usize::from(bytes[len - 1]) << 8;
-    }
-
-    while sum >> 16 != 0 {
-        sum = (sum & 0xffff) + (sum >> 16);
-    }
-
-    // Safe to unwrap due to the while loop above
-    let mut csum = !u16::try_from(sum).unwrap();
+    // The pseudo header holds the addresses, the protocol and the length of the packet.
+    let mut sum = partial_checksum(&src_addr.octets(), 0);
+    sum = partial_checksum(&dst_addr.octets(), sum);
+    sum = partial_checksum(&[0, protocol as u8], sum);
+    sum = partial_checksum(&bytes.len().to_be_bytes(), sum);
+    sum = partial_checksum(bytes, sum);
+
+    let mut csum = !sum;
     // If a UDP packet checksum is 0, an all ones value is transmitted
     if protocol == ChecksumProto::Udp && csum == 0x0 {
         csum = !csum;
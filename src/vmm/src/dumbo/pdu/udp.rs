@@ -35,6 +35,8 @@ pub enum UdpError {
     Checksum,
     /// The specified byte sequence is shorter than the Ethernet header length.
     DatagramTooShort,
+    /// The length of the given slice does not match the length of the datagram.
+    SliceExactLen,
     /// The payload to be added to the UDP packet exceeds the size allowed by the used IP version.
     PayloadTooBig,
 }
@@ -81,6 +83,12 @@ impl<T: NetworkBytes + Debug> UdpDatagram<'_, T> {
             }
         }
 
+        // The length field is covered by the checksum, so a corrupted one is reported as a
+        // checksum error when verifying it.
+        if usize::from(datagram.len()) != datagram.bytes.len() {
+            return Err(UdpError::SliceExactLen);
+        }
+
         Ok(datagram)
     }
 
@@ -132,7 +140,6 @@ impl<T: NetworkBytesMut + Debug> UdpDatagram<'_, T> {
     /// * `payload` - Datagram payload.
     #[inline]
     pub fn write_incomplete_datagram(buf: T, payload: &[u8]) -> Result<Incomplete<Self>, UdpError> {
-        let mut packet = UdpDatagram::from_bytes(buf, None)?;
         let len = payload.len() + UDP_HEADER_SIZE;
 
         let len = match u16::try_from(len) {
@@ -140,6 +147,13 @@ impl<T: NetworkBytesMut + Debug> UdpDatagram<'_, T> {
             _ => return Err(UdpError::PayloadTooBig),
         };
 
+        if buf.len() < usize::from(len) {
+            return Err(UdpError::DatagramTooShort);
+        }
+
+        // The length field of `buf` is not valid yet, so `from_bytes()` cannot be used.
+        let mut packet = UdpDatagram::from_bytes_unchecked(buf);
+
         packet.bytes.shrink_unchecked(len.into());
         packet.payload_mut().copy_from_slice(payload);
         packet.set_len(len);
@@ -303,4 +317,57 @@ mod tests {
         let p = p.finalize(41103, 9876, Some((src_ip, dst_ip)));
         assert_eq!(p.checksum(), correct_checksum);
     }
+
+    #[test]
+    fn test_from_bytes() {
+        let src_ip = Ipv4Addr::new(152, 1, 51, 27);
+        let dst_ip = Ipv4Addr::new(152, 14, 94, 75);
+        let addrs = Some((src_ip, dst_ip));
+        // The datagram of `test_checksum()`.
+        let mut bytes = [0xa0, 0x8f, 0x26, 0x94, 0x00, 0x0a, 0x14, 0xde, b'b', b'b'];
+
+        let p = UdpDatagram::from_bytes(bytes.as_ref(), addrs).unwrap();
+        assert_eq!(p.source_port(), 41103);
+        assert_eq!(p.destination_port(), 9876);
+        assert_eq!(p.len(), 10);
+        assert_eq!(p.checksum(), 0x14de);
+        assert_eq!(p.payload(), b"bb");
+
+        // The checksum covers the addresses.
+        assert_eq!(
+            UdpDatagram::from_bytes(bytes.as_ref(), Some((src_ip, Ipv4Addr::new(10, 0, 0, 1))))
+                .unwrap_err(),
+            UdpError::Checksum
+        );
+
+        // The length field must match the length of the slice.
+        assert_eq!(
+            UdpDatagram::from_bytes(&bytes[..9], None).unwrap_err(),
+            UdpError::SliceExactLen
+        );
+        let mut padded = [0u8; 11];
+        padded[..10].copy_from_slice(&bytes);
+        assert_eq!(
+            UdpDatagram::from_bytes(padded.as_ref(), None).unwrap_err(),
+            UdpError::SliceExactLen
+        );
+        // A corrupted length field is caught by the checksum first.
+        bytes[5] = 0x0b;
+        assert_eq!(
+            UdpDatagram::from_bytes(bytes.as_ref(), addrs).unwrap_err(),
+            UdpError::Checksum
+        );
+
+        // A zero checksum means the sender did not compute one.
+        bytes[5] = 0x0a;
+        bytes[6..8].copy_from_slice(&[0, 0]);
+        UdpDatagram::from_bytes(bytes.as_ref(), addrs).unwrap();
+
+        // Buffers too short for the whole datagram cannot be written to.
+        let mut short = [0u8; UDP_HEADER_SIZE + 1];
+        assert_eq!(
+            UdpDatagram::write_incomplete_datagram(short.as_mut(), b"bb").unwrap_err(),
+            UdpError::DatagramTooShort
+        );
+    }
 }
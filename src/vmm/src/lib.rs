@@ -75,6 +75,8 @@ pub mod rate_limiter;
 pub mod acpi;
 /// Handles setup and initialization a `Vmm` object.
 pub mod builder;
+/// Resource controls of the cgroup the microVM runs in.
+pub mod cgroup;
 /// Types for guest configuration.
 pub mod cpu_config;
 pub(crate) mod device_manager;
@@ -90,6 +92,9 @@ pub mod gdb;
 pub mod logger;
 /// microVM Metadata Service MMDS
 pub mod mmds;
+/// Hardware performance counters of the VMM process.
+#[cfg(feature = "perf-counters")]
+pub mod perf_counters;
 /// Save/restore utilities.
 pub mod persist;
 /// Resource store for configured microVM resources.
@@ -134,6 +139,10 @@ use vstate::kvm::Kvm;
 use vstate::vcpu::{self, StartThreadedError, VcpuSendEventError};
 
 use crate::arch::DeviceType;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use crate::arch::x86_64::page_table::{
+    GuestMapping, GuestPageTableWalker, PageTableRoot, PageTableWalkError,
+};
 use crate::cpu_config::templates::CpuConfiguration;
 #[cfg(target_arch = "x86_64")]
 use crate::device_manager::legacy::PortIODeviceManager;
@@ -149,7 +158,14 @@ use crate::logger::{METRICS, MetricsError, error, info, warn};
 use crate::persist::{MicrovmState, MicrovmStateError, VmInfo};
 use crate::rate_limiter::BucketUpdate;
 use crate::snapshot::Persist;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use crate::vmm_config::breakpoints::{BreakpointConfig, BreakpointsError};
+#[cfg(target_arch = "x86_64")]
+use crate::vmm_config::clock::VmClockConfig;
 use crate::vmm_config::instance_info::{InstanceInfo, VmState};
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use crate::vmm_config::interrupt::{InjectInterruptConfig, InjectInterruptError, InterruptType};
+use crate::vmm_config::vmgenid::VmGenIdConfigError;
 use crate::vstate::memory::{GuestMemory, GuestMemoryMmap, GuestMemoryRegion};
 use crate::vstate::vcpu::VcpuState;
 pub use crate::vstate::vcpu::{Vcpu, VcpuConfig, VcpuEvent, VcpuHandle, VcpuResponse};
@@ -200,6 +216,10 @@ pub const RECV_TIMEOUT_SEC: Duration = Duration::from_secs(30);
 /// Default byte limit of accepted http requests on API and MMDS servers.
 pub const HTTP_MAX_PAYLOAD_SIZE: usize = 51200;
 
+/// Maximum number of guest mappings returned by a single page table walk.
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+pub const MAX_GUEST_MAPPINGS: usize = 10_000;
+
 /// Errors associated with the VMM internal logic. These errors cannot be generated by direct user
 /// input, but can result from bad configuration of the host (for example if Firecracker doesn't
 /// have permissions to open the KVM fd).
@@ -207,6 +227,8 @@ pub const HTTP_MAX_PAYLOAD_SIZE: usize = 51200;
 pub enum VmmError {
     /// Failed to allocate guest resource: {0}
     AllocateResources(#[from] vm_allocator::Error),
+    /// Failed to freeze or thaw the vCPU cgroup: {0}
+    Cgroup(cgroup::CgroupError),
     #[cfg(target_arch = "aarch64")]
     /// Invalid command line error.
     Cmdline,
@@ -296,6 +318,22 @@ pub enum DumpCpuConfigError {
     NotAllowed(String),
 }
 
+/// Error type for the [`Vmm`] operations acting on a single vCPU.
+#[cfg(feature = "debug-api")]
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum SingleVcpuError {
+    /// Invalid vCPU index: {0}
+    InvalidIndex(u8),
+    /// Failed to send event to vcpu thread: {0}
+    SendEvent(#[from] VcpuSendEventError),
+    /// Got unexpected response from vcpu thread.
+    UnexpectedResponse,
+    /// Failed to access the vCPU state: {0}
+    Vcpu(#[from] vcpu::VcpuError),
+    /// Operation not allowed: {0}
+    NotAllowed(String),
+}
+
 /// Contains the state and associated methods required for the Firecracker VMM.
 #[derive(Debug)]
 pub struct Vmm {
@@ -377,9 +415,24 @@ impl Vmm {
 
         Vcpu::register_kick_signal_handler();
 
+        // The vCPU threads join a cgroup of their own, so that pausing can freeze all of them at
+        // once.
+        let vcpu_cgroup = cgroup::vm_cgroup().and_then(|cgroup| {
+            cgroup
+                .create_vcpu_cgroup()
+                .inspect_err(|err| {
+                    warn!(
+                        "Cannot create the vCPU cgroup, vCPUs pause one by one: {}",
+                        err
+                    )
+                })
+                .ok()
+        });
+
         self.vcpus_handles.reserve(vcpu_count);
 
         for mut vcpu in vcpus.drain(..) {
+            vcpu.vcpu_cgroup = vcpu_cgroup;
             vcpu.set_mmio_bus(self.mmio_device_manager.bus.clone());
             #[cfg(target_arch = "x86_64")]
             vcpu.kvm_vcpu
@@ -391,6 +444,10 @@ impl Vmm {
         self.instance_info.state = VmState::Paused;
         // Wait for vCPUs to initialize their TLS before moving forward.
         barrier.wait();
+        // The pool threads no vCPU took are not needed anymore.
+        if let Some(pool) = vstate::vcpu_pool::vcpu_pool() {
+            pool.close();
+        }
 
         Ok(())
     }
@@ -421,11 +478,32 @@ impl Vmm {
 
     /// Sends a pause command to the vCPUs.
     pub fn pause_vm(&mut self) -> Result<(), VmmError> {
+        // Freezing the vCPU threads first stops all of them at once. Once thawed, they find the
+        // pause event before getting back to the guest, so that no vCPU runs while the others
+        // are paused.
+        let mut vcpu_cgroup = cgroup::vcpu_cgroup();
+        if let Some(cgroup) = vcpu_cgroup {
+            match cgroup.freeze() {
+                Ok(()) => (),
+                // A vCPU thread blocked in the host kernel can keep the cgroup from freezing.
+                // The pause events still stop every vCPU, one by one.
+                Err(cgroup::CgroupError::FreezeTimeout) => {
+                    warn!("The vCPU threads did not freeze in time, vCPUs pause one by one");
+                    vcpu_cgroup = None;
+                }
+                Err(err) => return Err(VmmError::Cgroup(err)),
+            }
+        }
+
         // Send the events.
-        self.vcpus_handles
+        let sent = self
+            .vcpus_handles
             .iter()
-            .try_for_each(|handle| handle.send_event(VcpuEvent::Pause))
-            .map_err(|_| VmmError::VcpuMessage)?;
+            .try_for_each(|handle| handle.send_event(VcpuEvent::Pause));
+        if let Some(vcpu_cgroup) = vcpu_cgroup {
+            vcpu_cgroup.thaw().map_err(VmmError::Cgroup)?;
+        }
+        sent.map_err(|_| VmmError::VcpuMessage)?;
 
         // Check the responses.
         if self
@@ -441,6 +519,36 @@ impl Vmm {
         Ok(())
     }
 
+    /// Reads the KVM clock of the microVM.
+    #[cfg(target_arch = "x86_64")]
+    pub fn vm_clock(&self) -> Result<VmClockConfig, VmmError> {
+        let (host_ns, realtime_ns) = self.vm.clock().map_err(vstate::vm::VmError::Arch)?;
+        Ok(VmClockConfig {
+            realtime_ns,
+            host_ns,
+        })
+    }
+
+    /// Sets the KVM clock of the microVM, so that the guest clock advances by the wall clock
+    /// time elapsed since the clock was read.
+    #[cfg(target_arch = "x86_64")]
+    pub fn set_vm_clock(&self, clock: VmClockConfig) -> Result<(), VmmError> {
+        self.vm
+            .set_clock(clock.host_ns, clock.realtime_ns)
+            .map_err(vstate::vm::VmError::Arch)?;
+        Ok(())
+    }
+
+    /// Replaces the generation ID exposed by the VMGenID device and notifies the guest about it.
+    pub fn set_vm_gen_id(&mut self, gen_id: u128) -> Result<(), VmGenIdConfigError> {
+        self.acpi_device_manager
+            .vmgenid
+            .as_mut()
+            .ok_or(VmGenIdConfigError::DeviceNotFound)?
+            .set_gen_id(gen_id, self.vm.guest_memory())?;
+        Ok(())
+    }
+
     /// Sets RDA bit in serial console
     pub fn emulate_serial_init(&self) -> Result<(), EmulateSerialInitError> {
         // When restoring from a previously saved state, there is no serial
@@ -586,6 +694,142 @@ impl Vmm {
         Ok(cpu_configs)
     }
 
+    // Sends `event` to the vCPU with the given index and waits for its response.
+    #[cfg(feature = "debug-api")]
+    fn single_vcpu_request(
+        &self,
+        index: u8,
+        event: VcpuEvent,
+    ) -> Result<VcpuResponse, SingleVcpuError> {
+        let handle = self
+            .vcpus_handles
+            .get(usize::from(index))
+            .ok_or(SingleVcpuError::InvalidIndex(index))?;
+        handle.send_event(event)?;
+
+        match handle.response_receiver().recv_timeout(RECV_TIMEOUT_SEC) {
+            Ok(VcpuResponse::Error(err)) => Err(SingleVcpuError::Vcpu(err)),
+            Ok(VcpuResponse::NotAllowed(reason)) => Err(SingleVcpuError::NotAllowed(reason)),
+            Ok(response) => Ok(response),
+            Err(_) => Err(SingleVcpuError::UnexpectedResponse),
+        }
+    }
+
+    /// Pauses the vCPU with the given index, leaving the other vCPUs running.
+    #[cfg(feature = "debug-api")]
+    pub fn pause_vcpu(&mut self, index: u8) -> Result<(), SingleVcpuError> {
+        match self.single_vcpu_request(index, VcpuEvent::Pause)? {
+            VcpuResponse::Paused => Ok(()),
+            _ => Err(SingleVcpuError::UnexpectedResponse),
+        }
+    }
+
+    /// Resumes the paused vCPU with the given index.
+    #[cfg(feature = "debug-api")]
+    pub fn resume_vcpu(&mut self, index: u8) -> Result<(), SingleVcpuError> {
+        match self.single_vcpu_request(index, VcpuEvent::ResumePaused)? {
+            VcpuResponse::Resumed => Ok(()),
+            _ => Err(SingleVcpuError::UnexpectedResponse),
+        }
+    }
+
+    /// Captures the architectural state of the paused vCPU with the given index.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    pub fn vcpu_state(&mut self, index: u8) -> Result<arch::VcpuStateSnapshot, SingleVcpuError> {
+        match self.single_vcpu_request(index, VcpuEvent::CaptureState)? {
+            VcpuResponse::CapturedState(snapshot) => Ok(*snapshot),
+            _ => Err(SingleVcpuError::UnexpectedResponse),
+        }
+    }
+
+    /// Overwrites the architectural state of the paused vCPU with the given index.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    pub fn restore_vcpu_state(
+        &mut self,
+        index: u8,
+        snapshot: arch::VcpuStateSnapshot,
+    ) -> Result<(), SingleVcpuError> {
+        match self.single_vcpu_request(index, VcpuEvent::RestoreState(Box::new(snapshot)))? {
+            VcpuResponse::RestoredState => Ok(()),
+            _ => Err(SingleVcpuError::UnexpectedResponse),
+        }
+    }
+
+    /// Injects an interrupt into the vCPU with the given index. Fixed interrupts are signaled to
+    /// the local APIC of the vCPU as an MSI, while NMIs and SMIs are injected by the vCPU thread.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    pub fn inject_interrupt(
+        &mut self,
+        config: InjectInterruptConfig,
+    ) -> Result<(), InjectInterruptError> {
+        // MSI address of the local APICs, to which the destination APIC ID is added.
+        const MSI_ADDRESS_BASE: u32 = 0xfee0_0000;
+        const MSI_ADDRESS_DEST_ID_SHIFT: u32 = 12;
+        // MSI data bits selecting level triggered delivery, with the interrupt asserted.
+        const MSI_DATA_LEVEL_ASSERT: u32 = 1 << 14;
+        const MSI_DATA_TRIGGER_LEVEL: u32 = 1 << 15;
+
+        config.validate()?;
+        let event = match config.interrupt_type {
+            InterruptType::Fixed => {
+                if usize::from(config.vcpu_id) >= self.vcpus_handles.len() {
+                    return Err(SingleVcpuError::InvalidIndex(config.vcpu_id).into());
+                }
+                // The APIC ID of each vCPU is its index.
+                let mut data = u32::from(config.vector);
+                if config.level {
+                    data |= MSI_DATA_TRIGGER_LEVEL | MSI_DATA_LEVEL_ASSERT;
+                }
+                let msi = kvm_bindings::kvm_msi {
+                    address_lo: MSI_ADDRESS_BASE
+                        | (u32::from(config.vcpu_id) << MSI_ADDRESS_DEST_ID_SHIFT),
+                    data,
+                    ..Default::default()
+                };
+                self.vm
+                    .fd()
+                    .signal_msi(msi)
+                    .map_err(InjectInterruptError::SignalMsi)?;
+                return Ok(());
+            }
+            InterruptType::Nmi => VcpuEvent::InjectNmi,
+            InterruptType::Smi => VcpuEvent::InjectSmi,
+        };
+
+        match self.single_vcpu_request(config.vcpu_id, event)? {
+            VcpuResponse::InjectedInterrupt => Ok(()),
+            _ => Err(SingleVcpuError::UnexpectedResponse.into()),
+        }
+    }
+
+    /// Replaces the breakpoints of all vCPUs with `breakpoints`.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    pub fn set_breakpoints(
+        &mut self,
+        breakpoints: &[BreakpointConfig],
+    ) -> Result<(), BreakpointsError> {
+        let debug_state = arch::PersistentDebugState::new(breakpoints)?;
+        for (index, _) in (0u8..).zip(&self.vcpus_handles) {
+            match self.single_vcpu_request(index, VcpuEvent::SetDebugState(debug_state))? {
+                VcpuResponse::SetDebugState => (),
+                _ => return Err(SingleVcpuError::UnexpectedResponse.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Walks the guest page tables located by `root`, returning at most [`MAX_GUEST_MAPPINGS`]
+    /// mapped pages.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    pub fn guest_mappings(
+        &self,
+        root: PageTableRoot,
+    ) -> Result<Vec<GuestMapping>, PageTableWalkError> {
+        GuestPageTableWalker::new(self.vm.guest_memory(), root)?
+            .take(MAX_GUEST_MAPPINGS)
+            .collect()
+    }
+
     /// Updates the path of the host file backing the emulated block device with id `drive_id`.
     /// We update the disk image on the device and its virtio configuration.
     pub fn update_block_device_path(
@@ -0,0 +1,202 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-memory function call trace log.
+//!
+//! Instrumented functions record an entry in a fixed size ring buffer through the [`ftrace!`]
+//! macro. Recording never allocates and only holds the lock of the buffer while copying the entry
+//! in, so it can be used on the vCPU and device emulation paths. The buffer is drained by the API
+//! thread.
+//!
+//! [`ftrace!`]: crate::ftrace
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use serde::Serialize;
+use utils::time::{ClockType, get_time_ns};
+
+/// Number of entries kept in the trace log. Older entries are overwritten.
+pub const TRACE_LOG_CAPACITY: usize = 4096;
+
+/// Records entry into a function in the VMM trace log.
+///
+/// Expands to nothing unless Firecracker is built with the `tracing` feature.
+#[cfg(feature = "tracing")]
+#[macro_export]
+macro_rules! ftrace {
+    ($fn_name:expr) => {
+        $crate::logger::TRACE_LOG.push($fn_name)
+    };
+}
+
+/// Records entry into a function in the VMM trace log.
+///
+/// Expands to nothing unless Firecracker is built with the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+#[macro_export]
+macro_rules! ftrace {
+    ($fn_name:expr) => {};
+}
+
+/// The global trace log.
+pub static TRACE_LOG: TraceLog = TraceLog::new();
+
+// Source of the per-thread identifiers recorded in the trace log. Identifiers are handed out
+// instead of querying the kernel thread id so that recording does not issue syscalls, which the
+// seccomp filters of the vCPU and API threads would reject.
+static NEXT_THREAD_ID: AtomicU32 = AtomicU32::new(1);
+
+thread_local! {
+    static THREAD_ID: u32 = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+fn current_thread_id() -> u32 {
+    THREAD_ID.with(|id| *id)
+}
+
+/// A single record of the trace log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TraceEntry {
+    /// Monotonic timestamp of the call, in nanoseconds.
+    pub timestamp_ns: u64,
+    /// Identifier of the thread that made the call, assigned in order of first use.
+    pub thread_id: u32,
+    /// Name of the called function.
+    pub fn_name: &'static str,
+}
+
+// Ring buffer of the recorded entries.
+#[derive(Debug)]
+struct Ring {
+    // Position of the next entry to be written.
+    head: u64,
+    // Position of the first entry that has not been drained yet.
+    tail: u64,
+    entries: [Option<TraceEntry>; TRACE_LOG_CAPACITY],
+}
+
+impl Ring {
+    // The index is smaller than the capacity, so the cast cannot truncate.
+    #[allow(clippy::cast_possible_truncation)]
+    fn index(pos: u64) -> usize {
+        (pos % TRACE_LOG_CAPACITY as u64) as usize
+    }
+}
+
+/// Ring buffer of [`TraceEntry`] records.
+#[derive(Debug)]
+pub struct TraceLog {
+    ring: Mutex<Ring>,
+}
+
+impl TraceLog {
+    const fn new() -> Self {
+        Self {
+            ring: Mutex::new(Ring {
+                head: 0,
+                tail: 0,
+                entries: [None; TRACE_LOG_CAPACITY],
+            }),
+        }
+    }
+
+    /// Records a call to `fn_name`, overwriting the oldest entry if the log is full.
+    pub fn push(&self, fn_name: &'static str) {
+        let entry = TraceEntry {
+            timestamp_ns: get_time_ns(ClockType::Monotonic),
+            thread_id: current_thread_id(),
+            fn_name,
+        };
+        let mut ring = self.ring.lock().expect("Poisoned lock");
+        let pos = ring.head;
+        ring.entries[Ring::index(pos)] = Some(entry);
+        ring.head = pos + 1;
+    }
+
+    /// Drains the log, returning at most `last_n` of the most recent entries, oldest first.
+    pub fn drain(&self, last_n: u32) -> Vec<TraceEntry> {
+        let mut ring = self.ring.lock().expect("Poisoned lock");
+        let head = ring.head;
+        let start = ring
+            .tail
+            .max(head.saturating_sub(TRACE_LOG_CAPACITY as u64))
+            .max(head.saturating_sub(u64::from(last_n)));
+        ring.tail = head;
+
+        (start..head)
+            .filter_map(|pos| ring.entries[Ring::index(pos)])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_and_drain() {
+        let log = TraceLog::new();
+        assert!(log.drain(u32::MAX).is_empty());
+
+        log.push("first");
+        log.push("second");
+        log.push("third");
+
+        let entries = log.drain(2);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].fn_name, "second");
+        assert_eq!(entries[1].fn_name, "third");
+        assert!(entries[0].timestamp_ns <= entries[1].timestamp_ns);
+        assert_eq!(entries[0].thread_id, current_thread_id());
+
+        // Drained entries are not returned again.
+        assert!(log.drain(u32::MAX).is_empty());
+        log.push("fourth");
+        let entries = log.drain(u32::MAX);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].fn_name, "fourth");
+    }
+
+    #[test]
+    fn test_wrap_around() {
+        let log = TraceLog::new();
+        for _ in 0..TRACE_LOG_CAPACITY {
+            log.push("old");
+        }
+        log.push("new");
+
+        let entries = log.drain(u32::MAX);
+        assert_eq!(entries.len(), TRACE_LOG_CAPACITY);
+        assert_eq!(entries.last().unwrap().fn_name, "new");
+        assert_eq!(
+            entries
+                .iter()
+                .filter(|entry| entry.fn_name == "old")
+                .count(),
+            TRACE_LOG_CAPACITY - 1
+        );
+    }
+
+    #[test]
+    fn test_concurrent_push() {
+        let log = std::sync::Arc::new(TraceLog::new());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let log = log.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        log.push("worker");
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let entries = log.drain(u32::MAX);
+        assert_eq!(entries.len(), 400);
+        assert!(entries.iter().all(|entry| entry.fn_name == "worker"));
+    }
+}
@@ -32,6 +32,7 @@ pub static LOGGER: Logger = Logger(Mutex::new(LoggerConfiguration {
     format: LogFormat {
         show_level: false,
         show_log_origin: false,
+        json: false,
     },
 }));
 
@@ -80,6 +81,10 @@ impl Logger {
             guard.format.show_log_origin = show_log_origin;
         }
 
+        if let Some(json) = config.json {
+            guard.format.json = json;
+        }
+
         if let Some(module) = config.module {
             guard.filter.module = Some(module);
         }
@@ -100,6 +105,7 @@ pub struct LogFilter {
 pub struct LogFormat {
     pub show_level: bool,
     pub show_log_origin: bool,
+    pub json: bool,
 }
 #[derive(Debug)]
 pub struct LoggerConfiguration {
@@ -136,33 +142,16 @@ impl Log for Logger {
         // Prints log message
         {
             let thread = thread::current().name().unwrap_or("-").to_string();
-            let level = match guard.format.show_level {
-                true => format!(":{}", record.level()),
-                false => String::new(),
+            let instance_id = INSTANCE_ID
+                .get()
+                .map(|s| s.as_str())
+                .unwrap_or(DEFAULT_INSTANCE_ID);
+
+            let message = match guard.format.json {
+                true => json_log_line(&guard.format, instance_id, &thread, record),
+                false => text_log_line(&guard.format, instance_id, &thread, record),
             };
 
-            let origin = match guard.format.show_log_origin {
-                true => {
-                    let file = record.file().unwrap_or("?");
-                    let line = match record.line() {
-                        Some(x) => x.to_string(),
-                        None => String::from("?"),
-                    };
-                    format!(":{file}:{line}")
-                }
-                false => String::new(),
-            };
-
-            let message = format!(
-                "{} [{}:{thread}{level}{origin}] {}\n",
-                LocalTime::now(),
-                INSTANCE_ID
-                    .get()
-                    .map(|s| s.as_str())
-                    .unwrap_or(DEFAULT_INSTANCE_ID),
-                record.args()
-            );
-
             let result = if let Some(file) = &mut guard.target {
                 file.write_all(message.as_bytes())
             } else {
@@ -180,6 +169,47 @@ impl Log for Logger {
     fn flush(&self) {}
 }
 
+fn text_log_line(format: &LogFormat, instance_id: &str, thread: &str, record: &Record) -> String {
+    let level = match format.show_level {
+        true => format!(":{}", record.level()),
+        false => String::new(),
+    };
+
+    let origin = match format.show_log_origin {
+        true => {
+            let file = record.file().unwrap_or("?");
+            let line = match record.line() {
+                Some(x) => x.to_string(),
+                None => String::from("?"),
+            };
+            format!(":{file}:{line}")
+        }
+        false => String::new(),
+    };
+
+    format!(
+        "{} [{instance_id}:{thread}{level}{origin}] {}\n",
+        LocalTime::now(),
+        record.args()
+    )
+}
+
+fn json_log_line(format: &LogFormat, instance_id: &str, thread: &str, record: &Record) -> String {
+    let mut line = serde_json::Map::new();
+    line.insert("timestamp".into(), LocalTime::now().to_string().into());
+    line.insert("instance_id".into(), instance_id.into());
+    line.insert("thread".into(), thread.into());
+    if format.show_level {
+        line.insert("level".into(), record.level().as_str().into());
+    }
+    if format.show_log_origin {
+        line.insert("file".into(), record.file().into());
+        line.insert("line".into(), record.line().into());
+    }
+    line.insert("message".into(), record.args().to_string().into());
+    format!("{}\n", serde_json::Value::Object(line))
+}
+
 /// Strongly typed structure used to describe the logger.
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
@@ -194,6 +224,8 @@ pub struct LoggerConfig {
     pub show_log_origin: Option<bool>,
     /// The module to filter logs by.
     pub module: Option<String>,
+    /// Whether to write each log line as a JSON object.
+    pub json: Option<bool>,
 }
 
 /// This is required since we originally supported `Warning` and uppercase variants being used as
@@ -372,6 +404,7 @@ mod tests {
             format: LogFormat {
                 show_level: true,
                 show_log_origin: true,
+                json: false,
             },
         }));
 
@@ -404,4 +437,46 @@ mod tests {
 
         std::fs::remove_file(path).unwrap();
     }
+
+    #[test]
+    fn logger_json() {
+        let file = vmm_sys_util::tempfile::TempFile::new().unwrap();
+        let logger = Logger(Mutex::new(LoggerConfiguration {
+            target: Some(file.as_file().try_clone().unwrap()),
+            filter: LogFilter { module: None },
+            format: LogFormat {
+                show_level: true,
+                show_log_origin: true,
+                json: true,
+            },
+        }));
+
+        let metadata = Metadata::builder().level(Level::Warn).build();
+        let record = Record::builder()
+            .args(format_args!("Warning \"{}\"", 1))
+            .metadata(metadata)
+            .file(Some("dir/app.rs"))
+            .line(Some(200))
+            .module_path(Some("module::server"))
+            .build();
+        logger.log(&record);
+
+        let contents = std::fs::read_to_string(file.as_path()).unwrap();
+        assert!(contents.ends_with('\n'));
+        let mut line: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert!(line["timestamp"].is_string());
+        line.as_object_mut().unwrap().remove("timestamp");
+        let thread = thread::current().name().unwrap_or("-").to_string();
+        assert_eq!(
+            line,
+            serde_json::json!({
+                "instance_id": DEFAULT_INSTANCE_ID,
+                "thread": thread,
+                "level": "WARN",
+                "file": "dir/app.rs",
+                "line": 200,
+                "message": "Warning \"1\"",
+            })
+        );
+    }
 }
@@ -4,14 +4,23 @@
 //! Defines the metrics system.
 //!
 //! # Metrics format
-//! The metrics are flushed in JSON format each 60 seconds. The first field will always be the
-//! timestamp followed by the JSON representation of the structures representing each component on
-//! which we are capturing specific metrics.
+//! The metrics are flushed in JSON format each 60 seconds. The first fields will always be the
+//! timestamp and the instance ID, followed by the JSON representation of the structures
+//! representing each component on which we are capturing specific metrics.
+//!
+//! The metrics can also be flushed in the Prometheus text format, with a sample per metric. The
+//! name of a sample is the path to the metric in the JSON representation, joined with `_` and
+//! prefixed with the instance ID, and its timestamp is `utc_timestamp_ms`:
+//! ```text
+//! anonymous_instance_api_server_process_startup_time_us 0 1541591155180
+//! anonymous_instance_block_activate_fails 0 1541591155180
+//! ```
 //!
 //! ## JSON example with metrics:
 //! ```json
 //! {
 //!  "utc_timestamp_ms": 1541591155180,
+//!  "instance_id": "anonymous-instance",
 //!  "api_server": {
 //!    "process_startup_time_us": 0,
 //!    "process_startup_time_cpu_us": 0
@@ -64,17 +73,19 @@
 use std::fmt::Debug;
 use std::io::Write;
 use std::ops::Deref;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Mutex, OnceLock};
 
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 use utils::time::{ClockType, get_time_ns, get_time_us};
 
-use super::FcLineWriter;
+use super::{DEFAULT_INSTANCE_ID, FcLineWriter, INSTANCE_ID};
 use crate::devices::legacy;
 use crate::devices::virtio::balloon::metrics as balloon_metrics;
 use crate::devices::virtio::block::virtio::metrics as block_metrics;
 use crate::devices::virtio::net::metrics as net_metrics;
+use crate::devices::virtio::pmem::metrics as pmem_metrics;
 use crate::devices::virtio::rng::metrics as entropy_metrics;
 use crate::devices::virtio::vhost_user_metrics;
 use crate::devices::virtio::vsock::metrics as vsock_metrics;
@@ -90,6 +101,8 @@ pub static METRICS: Metrics<FirecrackerMetrics, FcLineWriter> =
 pub struct Metrics<T: Serialize, M: Write + Send> {
     // Metrics will get flushed here.
     metrics_buf: OnceLock<Mutex<M>>,
+    // Format of the flushed metrics, set along with `metrics_buf`.
+    format: OnceLock<MetricsFormat>,
     pub app_metrics: T,
 }
 
@@ -98,6 +111,7 @@ impl<T: Serialize + Debug, M: Write + Send + Debug> Metrics<T, M> {
     pub const fn new(app_metrics: T) -> Metrics<T, M> {
         Metrics {
             metrics_buf: OnceLock::new(),
+            format: OnceLock::new(),
             app_metrics,
         }
     }
@@ -113,10 +127,15 @@ impl<T: Serialize + Debug, M: Write + Send + Debug> Metrics<T, M> {
     ///
     /// # Arguments
     ///
-    /// * `metrics_dest` - Buffer for JSON formatted metrics. Needs to implement `Write` and `Send`.
-    pub fn init(&self, metrics_dest: M) -> Result<(), MetricsError> {
+    /// * `metrics_dest` - Buffer for the formatted metrics. Needs to implement `Write` and `Send`.
+    /// * `format` - Format in which the metrics are written to `metrics_dest`.
+    pub fn init(&self, metrics_dest: M, format: MetricsFormat) -> Result<(), MetricsError> {
         self.metrics_buf
             .set(Mutex::new(metrics_dest))
+            .map_err(|_| MetricsError::AlreadyInitialized)?;
+        // The format is only ever set here, after `metrics_buf`, so it cannot be set already.
+        self.format
+            .set(format)
             .map_err(|_| MetricsError::AlreadyInitialized)
     }
 
@@ -141,7 +160,17 @@ impl<T: Serialize + Debug, M: Write + Send + Debug> Metrics<T, M> {
     /// known deadlock potential.
     pub fn write(&self) -> Result<bool, MetricsError> {
         if let Some(lock) = self.metrics_buf.get() {
-            match serde_json::to_string(&self.app_metrics) {
+            let msg = match self.format.get().copied().unwrap_or_default() {
+                MetricsFormat::Json => serde_json::to_string(&self.app_metrics),
+                MetricsFormat::Prometheus => serde_json::to_value(&self.app_metrics).map(|value| {
+                    let instance_id = INSTANCE_ID
+                        .get()
+                        .map(String::as_str)
+                        .unwrap_or(DEFAULT_INSTANCE_ID);
+                    prometheus_text(instance_id, &value)
+                }),
+            };
+            match msg {
                 Ok(msg) => {
                     if let Ok(mut guard) = lock.lock() {
                         // No need to explicitly call flush because the underlying LineWriter
@@ -171,6 +200,89 @@ impl<T: Serialize + Debug, M: Write + Send + Debug> Metrics<T, M> {
     }
 }
 
+/// Format in which the metrics are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsFormat {
+    /// A JSON object per flush.
+    #[default]
+    Json,
+    /// The Prometheus text format, with the instance ID as prefix of the names of the samples.
+    Prometheus,
+}
+
+/// Error type for [`<MetricsFormat as FromStr>::from_str`].
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("Failed to parse string to metrics format: {0}")]
+pub struct MetricsFormatFromStrError(String);
+
+impl FromStr for MetricsFormat {
+    type Err = MetricsFormatFromStrError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "prometheus" => Ok(Self::Prometheus),
+            _ => Err(MetricsFormatFromStrError(String::from(s))),
+        }
+    }
+}
+
+/// Renders the JSON representation of the metrics in the Prometheus text format.
+fn prometheus_text(instance_id: &str, metrics: &serde_json::Value) -> String {
+    // Prometheus timestamps are in milliseconds too.
+    let timestamp = metrics
+        .get("utc_timestamp_ms")
+        .map(|timestamp| format!(" {timestamp}"))
+        .unwrap_or_default();
+    let mut samples = Vec::new();
+    if let serde_json::Value::Object(map) = metrics {
+        for (name, value) in map {
+            if name != "utc_timestamp_ms" {
+                push_prometheus_samples(
+                    &format!("{instance_id}_{name}"),
+                    value,
+                    &timestamp,
+                    &mut samples,
+                );
+            }
+        }
+    }
+    samples.join("\n")
+}
+
+fn push_prometheus_samples(
+    name: &str,
+    value: &serde_json::Value,
+    timestamp: &str,
+    samples: &mut Vec<String>,
+) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (child, value) in map {
+                push_prometheus_samples(&format!("{name}_{child}"), value, timestamp, samples);
+            }
+        }
+        serde_json::Value::Number(number) => {
+            samples.push(format!("{} {number}{timestamp}", prometheus_name(name)))
+        }
+        // The instance ID is already in the name of every sample.
+        _ => (),
+    }
+}
+
+/// Replaces the characters not allowed in Prometheus metric names (the hyphens of instance IDs,
+/// for instance) with `_`.
+fn prometheus_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
 impl<T: Serialize + Debug, M: Write + Send + Debug> Deref for Metrics<T, M> {
     type Target = T;
 
@@ -570,6 +682,8 @@ pub struct MmdsMetrics {
     pub connections_created: SharedIncMetric,
     /// The number of connections cleaned up by the MMDS TCP handler.
     pub connections_destroyed: SharedIncMetric,
+    /// The number of connection attempts dropped because their source address was over its rate.
+    pub connections_rate_limited: SharedIncMetric,
 }
 impl MmdsMetrics {
     /// Const default construction.
@@ -586,6 +700,7 @@ impl MmdsMetrics {
             tx_frames: SharedIncMetric::new(),
             connections_created: SharedIncMetric::new(),
             connections_destroyed: SharedIncMetric::new(),
+            connections_rate_limited: SharedIncMetric::new(),
         }
     }
 }
@@ -756,6 +871,43 @@ impl LatencyAggregateMetrics {
     }
 }
 
+/// Upper bounds, in microseconds, of all but the last bucket of `LatencyHistogramMetrics`.
+pub const LATENCY_HISTOGRAM_BOUNDS_US: [u64; 4] = [50, 200, 1_000, 10_000];
+
+/// Used to record the distribution of latency metrics over fixed buckets:
+/// under 50us, 50-200us, 200us-1ms, 1-10ms and over 10ms.
+/// Serialized as an array holding the number of samples in each bucket.
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct LatencyHistogramMetrics {
+    /// Number of samples in each bucket, from the fastest to the slowest.
+    pub buckets: [SharedIncMetric; LATENCY_HISTOGRAM_BOUNDS_US.len() + 1],
+}
+impl LatencyHistogramMetrics {
+    /// Const default construction.
+    pub const fn new() -> Self {
+        Self {
+            buckets: [const { SharedIncMetric::new() }; LATENCY_HISTOGRAM_BOUNDS_US.len() + 1],
+        }
+    }
+
+    /// Counts a sample of `latency_us` microseconds in the bucket it falls into.
+    pub fn record(&self, latency_us: u64) {
+        let bucket = LATENCY_HISTOGRAM_BOUNDS_US
+            .iter()
+            .position(|&bound| latency_us < bound)
+            .unwrap_or(LATENCY_HISTOGRAM_BOUNDS_US.len());
+        self.buckets[bucket].inc();
+    }
+
+    /// Adds the samples recorded by `other` since its last flush, bucket by bucket.
+    pub fn aggregate(&self, other: &Self) {
+        for (bucket, other_bucket) in self.buckets.iter().zip(other.buckets.iter()) {
+            bucket.add(other_bucket.fetch_diff());
+        }
+    }
+}
+
 /// Structure provides Metrics specific to VCPUs' mode of functioning.
 /// Sample_count or number of kvm exits for IO and MMIO VM exits are covered by:
 /// `exit_io_in`, `exit_io_out`, `exit_mmio_read` and , `exit_mmio_write`.
@@ -777,6 +929,8 @@ pub struct VcpuMetrics {
     pub exit_mmio_read: SharedIncMetric,
     /// Number of KVM exits for handling MMIO writes.
     pub exit_mmio_write: SharedIncMetric,
+    /// Number of KVM exits for guest memory faults KVM could not resolve.
+    pub exit_memory_fault: SharedIncMetric,
     /// Number of errors during this VCPU's run.
     pub failures: SharedIncMetric,
     /// Number of times that the `KVM_KVMCLOCK_CTRL` ioctl failed.
@@ -798,6 +952,7 @@ impl VcpuMetrics {
             exit_io_out: SharedIncMetric::new(),
             exit_mmio_read: SharedIncMetric::new(),
             exit_mmio_write: SharedIncMetric::new(),
+            exit_memory_fault: SharedIncMetric::new(),
             failures: SharedIncMetric::new(),
             kvmclock_ctrl_fails: SharedIncMetric::new(),
             exit_io_in_agg: LatencyAggregateMetrics::new(),
@@ -842,6 +997,28 @@ impl Serialize for SerializeToUtcTimestampMs {
     }
 }
 
+// The sole purpose of this struct is to record the instance ID in every metrics line, so that
+// metrics can be correlated with the logs of the same instance.
+#[derive(Debug, Default)]
+struct SerializeInstanceId;
+impl SerializeInstanceId {
+    /// Const default construction.
+    pub const fn new() -> Self {
+        SerializeInstanceId
+    }
+}
+
+impl Serialize for SerializeInstanceId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(
+            INSTANCE_ID
+                .get()
+                .map(String::as_str)
+                .unwrap_or(DEFAULT_INSTANCE_ID),
+        )
+    }
+}
+
 macro_rules! create_serialize_proxy {
     // By using the below structure in FirecrackerMetrics it is easy
     // to serialise Firecracker app_metrics as a single json object which
@@ -867,6 +1044,7 @@ create_serialize_proxy!(NetMetricsSerializeProxy, net_metrics);
 create_serialize_proxy!(VhostUserMetricsSerializeProxy, vhost_user_metrics);
 create_serialize_proxy!(BalloonMetricsSerializeProxy, balloon_metrics);
 create_serialize_proxy!(EntropyMetricsSerializeProxy, entropy_metrics);
+create_serialize_proxy!(PmemMetricsSerializeProxy, pmem_metrics);
 create_serialize_proxy!(VsockMetricsSerializeProxy, vsock_metrics);
 create_serialize_proxy!(LegacyDevMetricsSerializeProxy, legacy);
 
@@ -874,6 +1052,7 @@ create_serialize_proxy!(LegacyDevMetricsSerializeProxy, legacy);
 #[derive(Debug, Default, Serialize)]
 pub struct FirecrackerMetrics {
     utc_timestamp_ms: SerializeToUtcTimestampMs,
+    instance_id: SerializeInstanceId,
     /// API Server related metrics.
     pub api_server: ApiServerMetrics,
     #[serde(flatten)]
@@ -917,6 +1096,9 @@ pub struct FirecrackerMetrics {
     /// Metrics related to virtio-rng entropy device.
     pub entropy_ser: EntropyMetricsSerializeProxy,
     #[serde(flatten)]
+    /// Metrics related to virtio-pmem devices.
+    pub pmem_ser: PmemMetricsSerializeProxy,
+    #[serde(flatten)]
     /// Vhost-user device related metrics.
     pub vhost_user_ser: VhostUserMetricsSerializeProxy,
 }
@@ -925,6 +1107,7 @@ impl FirecrackerMetrics {
     pub const fn new() -> Self {
         Self {
             utc_timestamp_ms: SerializeToUtcTimestampMs::new(),
+            instance_id: SerializeInstanceId::new(),
             api_server: ApiServerMetrics::new(),
             balloon_ser: BalloonMetricsSerializeProxy {},
             block_ser: BlockMetricsSerializeProxy {},
@@ -943,6 +1126,7 @@ impl FirecrackerMetrics {
             signals: SignalMetrics::new(),
             vsock_ser: VsockMetricsSerializeProxy {},
             entropy_ser: EntropyMetricsSerializeProxy {},
+            pmem_ser: PmemMetricsSerializeProxy {},
             vhost_user_ser: VhostUserMetricsSerializeProxy {},
         }
     }
@@ -975,13 +1159,81 @@ mod tests {
         assert!(res.is_ok() && !res.unwrap());
 
         let f = TempFile::new().expect("Failed to create temporary metrics file");
-        m.init(LineWriter::new(f.into_file())).unwrap();
+        m.init(LineWriter::new(f.into_file()), MetricsFormat::Json)
+            .unwrap();
 
         m.write().unwrap();
 
         let f = TempFile::new().expect("Failed to create temporary metrics file");
 
-        m.init(LineWriter::new(f.into_file())).unwrap_err();
+        m.init(LineWriter::new(f.into_file()), MetricsFormat::Json)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_prometheus_format() {
+        #[derive(Debug, Serialize)]
+        struct TestMetrics {
+            utc_timestamp_ms: u64,
+            instance_id: &'static str,
+            api_server: ApiServerMetrics,
+            vmm: VmmMetrics,
+        }
+        let m = &Metrics::<_, FcLineWriter>::new(TestMetrics {
+            utc_timestamp_ms: 1541591155180,
+            instance_id: "some-instance",
+            api_server: ApiServerMetrics::new(),
+            vmm: VmmMetrics::new(),
+        });
+        m.app_metrics.vmm.device_events.inc();
+        m.app_metrics.vmm.panic_count.store(2);
+
+        let value = serde_json::to_value(&m.app_metrics).unwrap();
+        assert_eq!(
+            prometheus_text("some-instance", &value),
+            "some_instance_api_server_process_startup_time_cpu_us 0 \
+             1541591155180\nsome_instance_api_server_process_startup_time_us 0 \
+             1541591155180\nsome_instance_api_server_sync_response_fails 0 \
+             1541591155180\nsome_instance_api_server_sync_vmm_send_timeout_count 0 \
+             1541591155180\nsome_instance_vmm_device_events 1 \
+             1541591155180\nsome_instance_vmm_panic_count 2 1541591155180"
+        );
+
+        let f = TempFile::new().unwrap();
+        m.init(
+            LineWriter::new(f.as_file().try_clone().unwrap()),
+            MetricsFormat::Prometheus,
+        )
+        .unwrap();
+        assert!(m.write().unwrap());
+        let contents = std::fs::read_to_string(f.as_path()).unwrap();
+        assert_eq!(contents.lines().count(), 6, "{contents}");
+        assert!(
+            contents
+                .lines()
+                .all(|sample| sample.ends_with(" 1541591155180")),
+            "{contents}"
+        );
+    }
+
+    #[test]
+    fn test_prometheus_name() {
+        assert_eq!(prometheus_name("a-b_c9"), "a_b_c9");
+        assert_eq!(prometheus_name("1-instance_vmm"), "_1_instance_vmm");
+        assert_eq!(prometheus_name("anonymous-instance"), "anonymous_instance");
+    }
+
+    #[test]
+    fn test_metrics_format_from_str() {
+        assert_eq!(MetricsFormat::from_str("json"), Ok(MetricsFormat::Json));
+        assert_eq!(
+            MetricsFormat::from_str("Prometheus"),
+            Ok(MetricsFormat::Prometheus)
+        );
+        assert_eq!(
+            MetricsFormat::from_str("xml"),
+            Err(MetricsFormatFromStrError(String::from("xml")))
+        );
     }
 
     #[test]
@@ -1027,6 +1279,24 @@ mod tests {
         assert_eq!(1, m1.fetch());
     }
 
+    #[test]
+    fn test_latency_histogram_metrics() {
+        let hist = LatencyHistogramMetrics::new();
+        for latency_us in [0, 49, 50, 199, 200, 999, 1_000, 9_999, 10_000, u64::MAX] {
+            hist.record(latency_us);
+        }
+        hist.record(10);
+        assert_eq!(serde_json::to_string(&hist).unwrap(), "[3,2,2,2,2]");
+        // Serializing flushes the buckets.
+        assert_eq!(serde_json::to_string(&hist).unwrap(), "[0,0,0,0,0]");
+
+        let other = LatencyHistogramMetrics::default();
+        other.record(100);
+        other.record(20_000);
+        hist.aggregate(&other);
+        assert_eq!(serde_json::to_string(&hist).unwrap(), "[0,1,0,0,1]");
+    }
+
     #[test]
     fn test_serialize() {
         let s = serde_json::to_string(&FirecrackerMetrics::default());
@@ -4,17 +4,20 @@
 //! Crate that implements Firecracker specific functionality as far as logging and metrics
 //! collecting.
 
+mod ftrace;
 mod logging;
 mod metrics;
 
+pub use ftrace::{TRACE_LOG, TRACE_LOG_CAPACITY, TraceEntry, TraceLog};
 pub use log::{Level, debug, error, info, log_enabled, trace, warn};
 pub use logging::{
     DEFAULT_INSTANCE_ID, DEFAULT_LEVEL, INSTANCE_ID, LOGGER, LevelFilter, LevelFilterFromStrError,
     LoggerConfig, LoggerInitError, LoggerUpdateError,
 };
 pub use metrics::{
-    IncMetric, LatencyAggregateMetrics, METRICS, MetricsError, ProcessTimeReporter,
-    SharedIncMetric, SharedStoreMetric, StoreMetric,
+    IncMetric, LatencyAggregateMetrics, LatencyHistogramMetrics, METRICS, MetricsError,
+    MetricsFormat, MetricsFormatFromStrError, ProcessTimeReporter, SharedIncMetric,
+    SharedStoreMetric, StoreMetric,
 };
 use utils::time::{ClockType, get_time_us};
 
@@ -1,8 +1,10 @@
 // Copyright 2018 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, to_vec};
@@ -17,6 +19,57 @@ pub struct Mmds {
     token_authority: Option<TokenAuthority>,
     is_initialized: bool,
     data_store_limit: usize,
+    response_cache: ResponseCache,
+}
+
+// Path of a value of the data store, and the format it is output in.
+type ResponseCacheKey = (String, OutputFormat);
+
+// Formatted values of the data store, so that paths read repeatedly are not formatted again on
+// every request.
+#[derive(Debug, Default)]
+struct ResponseCache {
+    entries: HashMap<ResponseCacheKey, (String, Instant)>,
+    // How long the entries are served for, 0 if the cache is disabled.
+    ttl_ms: u64,
+    // Size of the cached values.
+    size: usize,
+}
+
+impl ResponseCache {
+    fn get(&self, key: &ResponseCacheKey) -> Option<&String> {
+        let ttl = Duration::from_millis(self.ttl_ms);
+        self.entries
+            .get(key)
+            .filter(|(_, inserted)| inserted.elapsed() < ttl)
+            .map(|(value, _)| value)
+    }
+
+    // Caches `value`, unless the cache would grow over `size_limit`.
+    fn insert(&mut self, key: ResponseCacheKey, value: String, size_limit: usize) {
+        if self.ttl_ms == 0 {
+            return;
+        }
+        // Expired entries are only dropped when replaced, so make room by dropping them all.
+        if self.size + value.len() > size_limit {
+            let ttl = Duration::from_millis(self.ttl_ms);
+            self.entries
+                .retain(|_, (_, inserted)| inserted.elapsed() < ttl);
+            self.size = self.entries.values().map(|(value, _)| value.len()).sum();
+            if self.size + value.len() > size_limit {
+                return;
+            }
+        }
+        self.size += value.len();
+        if let Some((old_value, _)) = self.entries.insert(key, (value, Instant::now())) {
+            self.size -= old_value.len();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.size = 0;
+    }
 }
 
 /// MMDS version.
@@ -39,7 +92,7 @@ impl Display for MmdsVersion {
 }
 
 /// MMDS possible outputs.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum OutputFormat {
     /// MMDS output format as Json
     Json,
@@ -77,6 +130,7 @@ impl Mmds {
             token_authority: None,
             is_initialized: false,
             data_store_limit,
+            response_cache: ResponseCache::default(),
         }
     }
 
@@ -145,6 +199,17 @@ impl Mmds {
         self.data_store_limit = data_store_limit;
     }
 
+    /// Sets how long the responses to guest requests are cached for, 0 to disable the cache.
+    pub fn set_response_cache_ttl_ms(&mut self, ttl_ms: u64) {
+        self.response_cache.clear();
+        self.response_cache.ttl_ms = ttl_ms;
+    }
+
+    /// Returns how long the responses to guest requests are cached for.
+    pub fn response_cache_ttl_ms(&self) -> u64 {
+        self.response_cache.ttl_ms
+    }
+
     /// put `data` in MMDS data store
     pub fn put_data(&mut self, data: Value) -> Result<(), MmdsDatastoreError> {
         // It is safe to unwrap because any map keys are all strings and
@@ -154,6 +219,7 @@ impl Mmds {
         } else {
             self.data_store = data;
             self.is_initialized = true;
+            self.response_cache.clear();
 
             Ok(())
         }
@@ -171,6 +237,7 @@ impl Mmds {
             return Err(MmdsDatastoreError::DataStoreLimitExceeded);
         }
         self.data_store = data_store_clone;
+        self.response_cache.clear();
         Ok(())
     }
 
@@ -274,6 +341,25 @@ impl Mmds {
             Err(MmdsDatastoreError::NotFound)
         }
     }
+
+    /// Same as `get_value`, but serves the values cached by previous calls until they expire or
+    /// the data store is updated.
+    pub fn get_cached_value(
+        &mut self,
+        path: String,
+        format: OutputFormat,
+    ) -> Result<String, MmdsDatastoreError> {
+        let key = (path, format);
+        if let Some(value) = self.response_cache.get(&key) {
+            return Ok(value.clone());
+        }
+        let value = self.get_value(key.0.clone(), format)?;
+        // Caching the values of as many paths as the guest wants could take any amount of memory,
+        // so the cache takes at most as much as the data store.
+        self.response_cache
+            .insert(key, value.clone(), self.data_store_limit);
+        Ok(value)
+    }
 }
 
 #[cfg(test)]
@@ -581,6 +667,89 @@ mod tests {
         assert_eq!(mmds.get_data_str().len(), 2);
     }
 
+    #[test]
+    fn test_response_cache() {
+        let mut mmds = Mmds::default();
+        mmds.put_data(serde_json::json!({"age": "43", "name": {"first": "John"}}))
+            .unwrap();
+
+        // The cache is disabled by default.
+        assert_eq!(mmds.response_cache_ttl_ms(), 0);
+        assert_eq!(
+            mmds.get_cached_value("/age".to_string(), OutputFormat::Json)
+                .unwrap(),
+            "\"43\""
+        );
+        assert!(mmds.response_cache.entries.is_empty());
+
+        mmds.set_response_cache_ttl_ms(60_000);
+        assert_eq!(mmds.response_cache_ttl_ms(), 60_000);
+        assert_eq!(
+            mmds.get_cached_value("/age".to_string(), OutputFormat::Json)
+                .unwrap(),
+            "\"43\""
+        );
+        assert_eq!(
+            mmds.get_cached_value("/age".to_string(), OutputFormat::Imds)
+                .unwrap(),
+            "43"
+        );
+        assert_eq!(
+            mmds.get_cached_value("/name".to_string(), OutputFormat::Imds)
+                .unwrap(),
+            "first"
+        );
+        // Missing values are not cached.
+        mmds.get_cached_value("/invalid".to_string(), OutputFormat::Json)
+            .unwrap_err();
+        assert_eq!(mmds.response_cache.entries.len(), 3);
+        assert_eq!(mmds.response_cache.size, 11);
+
+        // Updating the data store invalidates the cache.
+        mmds.patch_data(serde_json::json!({"age": "44"})).unwrap();
+        assert!(mmds.response_cache.entries.is_empty());
+        assert_eq!(
+            mmds.get_cached_value("/age".to_string(), OutputFormat::Imds)
+                .unwrap(),
+            "44"
+        );
+        mmds.put_data(serde_json::json!({"age": "45"})).unwrap();
+        assert!(mmds.response_cache.entries.is_empty());
+        assert_eq!(
+            mmds.get_cached_value("/age".to_string(), OutputFormat::Imds)
+                .unwrap(),
+            "45"
+        );
+
+        // Cached values are served until they expire.
+        let key = ("/age".to_string(), OutputFormat::Imds);
+        mmds.response_cache.entries.get_mut(&key).unwrap().0 = "46".to_string();
+        assert_eq!(
+            mmds.get_cached_value("/age".to_string(), OutputFormat::Imds)
+                .unwrap(),
+            "46"
+        );
+        mmds.response_cache.entries.get_mut(&key).unwrap().1 =
+            Instant::now() - Duration::from_millis(60_000);
+        assert_eq!(
+            mmds.get_cached_value("/age".to_string(), OutputFormat::Imds)
+                .unwrap(),
+            "45"
+        );
+
+        // The cache does not grow over the data store limit.
+        mmds.set_data_store_limit(10);
+        mmds.set_response_cache_ttl_ms(60_000);
+        mmds.get_cached_value("/age".to_string(), OutputFormat::Json)
+            .unwrap();
+        mmds.get_cached_value("/age".to_string(), OutputFormat::Imds)
+            .unwrap();
+        mmds.get_cached_value("/".to_string(), OutputFormat::Json)
+            .unwrap();
+        assert_eq!(mmds.response_cache.entries.len(), 2);
+        assert_eq!(mmds.response_cache.size, 6);
+    }
+
     #[test]
     fn test_is_valid() {
         let mut mmds = Mmds::default();
@@ -112,12 +112,12 @@ pub fn convert_to_response(mmds: Arc<Mutex<Mmds>>, request: Request) -> Response
     let mut mmds_guard = mmds.lock().expect("Poisoned lock");
 
     match mmds_guard.version() {
-        MmdsVersion::V1 => respond_to_request_mmdsv1(&mmds_guard, request),
+        MmdsVersion::V1 => respond_to_request_mmdsv1(&mut mmds_guard, request),
         MmdsVersion::V2 => respond_to_request_mmdsv2(&mut mmds_guard, request),
     }
 }
 
-fn respond_to_request_mmdsv1(mmds: &Mmds, request: Request) -> Response {
+fn respond_to_request_mmdsv1(mmds: &mut Mmds, request: Request) -> Response {
     // Allow only GET requests.
     match request.method() {
         Method::Get => respond_to_get_request_unchecked(mmds, request),
@@ -164,7 +164,7 @@ fn respond_to_request_mmdsv2(mmds: &mut Mmds, request: Request) -> Response {
 }
 
 fn respond_to_get_request_checked(
-    mmds: &Mmds,
+    mmds: &mut Mmds,
     request: Request,
     token_headers: TokenHeaders,
 ) -> Response {
@@ -193,14 +193,14 @@ fn respond_to_get_request_checked(
     }
 }
 
-fn respond_to_get_request_unchecked(mmds: &Mmds, request: Request) -> Response {
+fn respond_to_get_request_unchecked(mmds: &mut Mmds, request: Request) -> Response {
     let uri = request.uri().get_abs_path();
 
     // The data store expects a strict json path, so we need to
     // sanitize the URI.
     let json_path = sanitize_uri(uri.to_string());
 
-    match mmds.get_value(json_path, request.headers.accept().into()) {
+    match mmds.get_cached_value(json_path, request.headers.accept().into()) {
         Ok(response_body) => build_response(
             request.http_version(),
             StatusCode::OK,
@@ -675,6 +675,22 @@ mod tests {
             data["phones"]["mobile"]["UK"],
             patch["phones"]["mobile"]["UK"]
         );
+
+        // Test patching a non-object target document, which is replaced by an object.
+        let mut data = serde_json::json!("scalar");
+        let patch = serde_json::json!({
+            "name": {
+                "first": "John",
+                "second": null
+            }
+        });
+        json_patch(&mut data, &patch);
+        assert_eq!(data, serde_json::json!({"name": {"first": "John"}}));
+
+        // Test patching with a non-object patch, which replaces the target document.
+        let patch = serde_json::json!(["array"]);
+        json_patch(&mut data, &patch);
+        assert_eq!(data, patch);
     }
 
     #[test]
@@ -4,12 +4,14 @@
 // TODO: get rid of this when splitting dumbo into public and internal parts.
 #![allow(missing_docs)]
 
+use std::collections::HashMap;
 use std::convert::From;
 use std::net::Ipv4Addr;
 use std::num::NonZeroUsize;
 use std::result::Result;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use utils::time::timestamp_cycles;
 
@@ -20,14 +22,17 @@ use crate::dumbo::pdu::arp::{
 use crate::dumbo::pdu::ethernet::{
     ETHERTYPE_ARP, ETHERTYPE_IPV4, EthernetError as EthernetFrameError, EthernetFrame,
 };
+use crate::dumbo::pdu::icmpv4::{IcmpEchoFrame, IcmpError};
 use crate::dumbo::pdu::ipv4::{
-    IPv4Packet, Ipv4Error as IPv4PacketError, PROTOCOL_TCP, test_speculative_dst_addr,
+    IPv4Packet, Ipv4Error as IPv4PacketError, PROTOCOL_ICMP, PROTOCOL_TCP,
+    test_speculative_dst_addr,
 };
-use crate::dumbo::pdu::tcp::TcpError as TcpSegmentError;
+use crate::dumbo::pdu::tcp::{Flags as TcpFlags, TcpError as TcpSegmentError, TcpSegment};
 use crate::dumbo::tcp::NextSegmentStatus;
 use crate::dumbo::tcp::handler::{RecvEvent, TcpIPv4Handler, WriteEvent, WriteNextError};
 use crate::logger::{IncMetric, METRICS};
 use crate::mmds::data_store::Mmds;
+use crate::rate_limiter::{BucketReduction, TokenBucket};
 use crate::utils::net::mac::MacAddr;
 
 const DEFAULT_MAC_ADDR: &str = "06:01:23:45:67:01";
@@ -35,6 +40,11 @@ const DEFAULT_IPV4_ADDR: [u8; 4] = [169, 254, 169, 254];
 const DEFAULT_TCP_PORT: u16 = 80;
 const DEFAULT_MAX_CONNECTIONS: usize = 30;
 const DEFAULT_MAX_PENDING_RESETS: usize = 100;
+const DEFAULT_RATE_LIMIT_RPS: u32 = 100;
+// Each source address can open up to two seconds worth of connections at once.
+const RATE_LIMIT_BURST_MS: u64 = 2000;
+// Bounds the number of source addresses tracked, which the guest can spoof.
+const MAX_RATE_LIMITED_ADDRS: usize = 64;
 
 #[derive(Debug, PartialEq, thiserror::Error, displaydoc::Display)]
 enum WriteArpFrameError {
@@ -56,6 +66,61 @@ enum WritePacketError {
     TcpSegment(#[from] TcpSegmentError),
     /// WriteNext error: {0}
     WriteNext(#[from] WriteNextError),
+    /// ICMP error: {0}
+    Icmp(#[from] IcmpError),
+}
+
+// An ICMP echo reply to a ping from the guest.
+#[derive(Debug)]
+struct PendingEchoReply {
+    dest: Ipv4Addr,
+    identifier: u16,
+    sequence_number: u16,
+    payload: Vec<u8>,
+}
+
+// Limits the rate at which each source address opens connections to MMDS.
+#[derive(Debug)]
+struct ConnectionRateLimiter {
+    // Connections per second allowed for each source address, 0 if unlimited.
+    rps: u32,
+    // Bucket of each source address, with the last time it was used.
+    buckets: HashMap<Ipv4Addr, (TokenBucket, Instant)>,
+}
+
+impl ConnectionRateLimiter {
+    fn new(rps: u32) -> Self {
+        ConnectionRateLimiter {
+            rps,
+            buckets: HashMap::new(),
+        }
+    }
+
+    // Returns whether `addr` may open a new connection.
+    fn allow(&mut self, addr: Ipv4Addr) -> bool {
+        if self.rps == 0 {
+            return true;
+        }
+        let now = Instant::now();
+        if !self.buckets.contains_key(&addr) && self.buckets.len() >= MAX_RATE_LIMITED_ADDRS {
+            // Buckets unused for a whole refill period are full again, forgetting them changes
+            // nothing.
+            let refill_time = Duration::from_millis(RATE_LIMIT_BURST_MS);
+            self.buckets
+                .retain(|_, (_, last_used)| now.duration_since(*last_used) < refill_time);
+            if self.buckets.len() >= MAX_RATE_LIMITED_ADDRS {
+                return false;
+            }
+        }
+        let size = u64::from(self.rps) * RATE_LIMIT_BURST_MS / 1000;
+        let (bucket, last_used) = self.buckets.entry(addr).or_insert_with(|| {
+            let bucket = TokenBucket::new(size, 0, RATE_LIMIT_BURST_MS)
+                .expect("Rate limit bucket has a non zero size and refill time");
+            (bucket, now)
+        });
+        *last_used = now;
+        bucket.reduce(1) == BucketReduction::Success
+    }
 }
 
 #[derive(Debug)]
@@ -70,10 +135,14 @@ pub struct MmdsNetworkStack {
     // It is the Ipv4Addr of the network interface for which the MmdsNetworkStack
     // routes the packets.
     pending_arp_reply_dest: Option<Ipv4Addr>,
+    // Echo reply to the last ping received, if it was not answered yet.
+    pending_echo_reply: Option<PendingEchoReply>,
     // This handles MMDS<->guest interaction at the TCP level.
     pub(crate) tcp_handler: TcpIPv4Handler,
     // Data store reference shared across all MmdsNetworkStack instances.
     pub mmds: Arc<Mutex<Mmds>>,
+    // Limits the connections opened by each guest address.
+    rate_limiter: ConnectionRateLimiter,
 }
 
 impl MmdsNetworkStack {
@@ -88,6 +157,7 @@ impl MmdsNetworkStack {
             mac_addr,
             ipv4_addr,
             pending_arp_reply_dest: None,
+            pending_echo_reply: None,
             tcp_handler: TcpIPv4Handler::new(
                 ipv4_addr,
                 tcp_port,
@@ -95,6 +165,7 @@ impl MmdsNetworkStack {
                 NonZeroUsize::new(DEFAULT_MAX_PENDING_RESETS).unwrap(),
             ),
             mmds,
+            rate_limiter: ConnectionRateLimiter::new(DEFAULT_RATE_LIMIT_RPS),
         }
     }
 
@@ -119,10 +190,24 @@ impl MmdsNetworkStack {
         Ipv4Addr::from(DEFAULT_IPV4_ADDR)
     }
 
+    /// Sets the connections per second each guest address can open, 0 for no limit.
+    pub fn set_rate_limit_rps(&mut self, rps: u32) {
+        self.rate_limiter = ConnectionRateLimiter::new(rps);
+    }
+
+    pub fn rate_limit_rps(&self) -> u32 {
+        self.rate_limiter.rps
+    }
+
+    pub fn default_rate_limit_rps() -> u32 {
+        DEFAULT_RATE_LIMIT_RPS
+    }
+
     /// Check if a frame is destined for `mmds`
     ///
     /// This returns `true` if the frame is an ARP or IPv4 frame destined for
     /// the `mmds` service, or `false` otherwise. It does not consume the frame.
+    /// 802.1Q-tagged frames are never destined for `mmds`, which only writes untagged frames.
     pub fn is_mmds_frame(&self, src: &[u8]) -> bool {
         if let Ok(eth) = EthernetFrame::from_bytes(src) {
             match eth.ethertype() {
@@ -178,6 +263,14 @@ impl MmdsNetworkStack {
                 // Note-2: For every routed packet we will have a single source MAC address, because
                 // each MmdsNetworkStack routes packets for only one network device.
                 self.remote_mac_addr = eth.src_mac();
+                // Drop the connection attempts of addresses over their rate, the guest retries
+                // them later.
+                let is_syn = TcpSegment::from_bytes(ip.payload(), None)
+                    .is_ok_and(|segment| segment.flags_after_ns() == TcpFlags::SYN);
+                if is_syn && !self.rate_limiter.allow(ip.source_address()) {
+                    METRICS.mmds.connections_rate_limited.inc();
+                    return true;
+                }
                 let mmds_instance = self.mmds.clone();
                 match &mut self.tcp_handler.receive_packet(&ip, move |request| {
                     super::convert_to_response(mmds_instance, request)
@@ -200,6 +293,8 @@ impl MmdsNetworkStack {
                     }
                     Err(_) => METRICS.mmds.rx_accepted_err.inc(),
                 }
+            } else if ip.protocol() == PROTOCOL_ICMP {
+                self.detour_icmp(&ip, eth.src_mac());
             } else {
                 // A non-TCP IPv4 packet heading towards the MMDS; we consider it unusual.
                 METRICS.mmds.rx_accepted_unusual.inc();
@@ -210,12 +305,31 @@ impl MmdsNetworkStack {
         false
     }
 
+    fn detour_icmp(&mut self, ip: &IPv4Packet<&[u8]>, src_mac: MacAddr) {
+        match IcmpEchoFrame::request_from_bytes(ip.payload(), true) {
+            Ok(request) => {
+                METRICS.mmds.rx_count.inc();
+                self.remote_mac_addr = src_mac;
+                // Only the last ping is answered if several arrive before the reply is sent, like
+                // for ARP requests.
+                self.pending_echo_reply = Some(PendingEchoReply {
+                    dest: ip.source_address(),
+                    identifier: request.identifier(),
+                    sequence_number: request.sequence_number(),
+                    payload: request.payload().to_vec(),
+                });
+            }
+            // Other ICMP messages heading towards the MMDS are unusual.
+            Err(_) => METRICS.mmds.rx_accepted_unusual.inc(),
+        }
+    }
+
     // Allows the MMDS network stack to write a frame to the specified buffer. Will return:
     // - None, if the MMDS network stack has no frame to send at this point. The buffer can be
     // used for something else by the device model.
     // - Some(len), if a frame of the given length has been written to the specified buffer.
     pub fn write_next_frame(&mut self, buf: &mut [u8]) -> Option<NonZeroUsize> {
-        // We try to send ARP replies first.
+        // We try to send ARP replies first, then echo replies.
         if self.pending_arp_reply_dest.is_some() {
             return match self.write_arp_reply(buf) {
                 Ok(something) => {
@@ -228,6 +342,18 @@ impl MmdsNetworkStack {
                     None
                 }
             };
+        } else if let Some(reply) = self.pending_echo_reply.take() {
+            return match self.write_echo_reply(buf, &reply) {
+                Ok(something) => {
+                    METRICS.mmds.tx_count.inc();
+                    something
+                }
+                Err(_) => {
+                    // The reply is dropped, pings are retried anyway.
+                    METRICS.mmds.tx_errors.inc();
+                    None
+                }
+            };
         } else {
             let call_write = match self.tcp_handler.next_segment_status() {
                 NextSegmentStatus::Available => true,
@@ -285,6 +411,40 @@ impl MmdsNetworkStack {
         ))
     }
 
+    fn write_echo_reply(
+        &self,
+        buf: &mut [u8],
+        reply: &PendingEchoReply,
+    ) -> Result<Option<NonZeroUsize>, WritePacketError> {
+        let mut eth_unsized = self.prepare_eth_unsized(buf, ETHERTYPE_IPV4)?;
+
+        let packet_len = {
+            let mut packet = IPv4Packet::write_header(
+                eth_unsized.inner_mut().payload_mut(),
+                PROTOCOL_ICMP,
+                self.ipv4_addr,
+                reply.dest,
+            )?;
+
+            let icmp_len = IcmpEchoFrame::write_reply(
+                packet.inner_mut().payload_mut(),
+                reply.identifier,
+                reply.sequence_number,
+                &reply.payload,
+            )?
+            .len();
+
+            // The echo request came in an IPv4 packet, so the reply fits in one as well.
+            let icmp_len = u16::try_from(icmp_len).unwrap();
+            packet.with_payload_len_unchecked(icmp_len, true).len()
+        };
+
+        Ok(Some(
+            // The unwrap() is safe because packet_len > 0.
+            NonZeroUsize::new(eth_unsized.with_payload_len_unchecked(packet_len).len()).unwrap(),
+        ))
+    }
+
     fn write_packet(&mut self, buf: &mut [u8]) -> Result<Option<NonZeroUsize>, WritePacketError> {
         let mut eth_unsized = self.prepare_eth_unsized(buf, ETHERTYPE_IPV4)?;
 
@@ -317,6 +477,8 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
+    use crate::dumbo::pdu::ethernet::ETHERTYPE_VLAN;
+    use crate::dumbo::pdu::icmpv4::ICMP_TYPE_ECHO_REQUEST;
     use crate::dumbo::pdu::tcp::{Flags as TcpFlags, TcpSegment};
 
     // We use LOCALHOST here because const new() is not stable yet, so just reuse this const, since
@@ -386,6 +548,34 @@ mod tests {
             eth_unsized.with_payload_len_unchecked(packet_len).len()
         }
 
+        fn write_incoming_echo_request(&self, buf: &mut [u8], addr: Ipv4Addr) -> usize {
+            let mut eth_unsized = self.prepare_eth_unsized(buf, ETHERTYPE_IPV4).unwrap();
+            eth_unsized
+                .inner_mut()
+                .set_src_mac(MacAddr::from_str(REMOTE_MAC_STR).unwrap());
+            let packet_len = {
+                let mut packet = IPv4Packet::write_header(
+                    eth_unsized.inner_mut().payload_mut(),
+                    PROTOCOL_ICMP,
+                    REMOTE_ADDR,
+                    addr,
+                )
+                .unwrap();
+
+                // Write a reply and then modify it into a request.
+                let mut icmp =
+                    IcmpEchoFrame::write_reply(packet.inner_mut().payload_mut(), 7, 1, b"ping")
+                        .unwrap();
+                icmp.set_msg_type(ICMP_TYPE_ECHO_REQUEST).set_checksum(0);
+                let checksum = icmp.compute_checksum();
+                let icmp_len = u16::try_from(icmp.set_checksum(checksum).len()).unwrap();
+
+                packet.with_payload_len_unchecked(icmp_len, true).len()
+            };
+
+            eth_unsized.with_payload_len_unchecked(packet_len).len()
+        }
+
         fn next_frame_as_ipv4_packet<'a>(&mut self, buf: &'a mut [u8]) -> IPv4Packet<&'a [u8]> {
             let len = self.write_next_frame(buf).unwrap().get();
             let eth = EthernetFrame::from_bytes(&buf[..len]).unwrap();
@@ -426,6 +616,22 @@ mod tests {
         // There's nothing to send right now.
         assert!(ns.write_next_frame(buf.as_mut()).is_none());
 
+        // Tagged frames are left alone, even if they ask for the MMDS MAC address.
+        {
+            let mut eth = EthernetFrame::write_incomplete(
+                buf.as_mut(),
+                remote_mac,
+                remote_mac,
+                ETHERTYPE_VLAN,
+            )
+            .unwrap();
+            let mut arp = EthIPv4ArpFrame::from_bytes_unchecked(eth.inner_mut().payload_mut());
+            arp.set_tpa(mmds_addr);
+            let len = eth.inner().payload_offset() + ETH_IPV4_FRAME_LEN;
+            assert!(!ns.is_mmds_frame(&buf[..len]));
+            assert!(!ns.detour_frame(&buf[..len]));
+        }
+
         {
             let len = ns.write_arp_request(buf.as_mut(), false);
             // Not asking for MMDS MAC address.
@@ -450,7 +656,7 @@ mod tests {
             let len = ns.write_next_frame(buf.as_mut()).unwrap().get();
             assert_eq!(curr_tx_count + 1, METRICS.mmds.tx_count.count());
             let eth = EthernetFrame::from_bytes(&buf[..len]).unwrap();
-            let arp_reply = EthIPv4ArpFrame::from_bytes_unchecked(eth.payload());
+            let arp_reply = EthIPv4ArpFrame::reply_from_bytes(eth.payload()).unwrap();
 
             // REPLY = 2
             assert_eq!(arp_reply.operation(), 2);
@@ -529,6 +735,32 @@ mod tests {
         assert!(ns.write_next_frame(buf.as_mut()).is_none());
     }
 
+    #[test]
+    fn test_arp_probe() {
+        let mut ns =
+            MmdsNetworkStack::new_with_defaults(None, Arc::new(Mutex::new(Mmds::default())));
+        let mut buf = [0u8; 2000];
+
+        // A probe of the guest for the MMDS address (RFC 5227) has an unspecified sender address.
+        let len = ns.write_arp_request(buf.as_mut(), true);
+        {
+            let mut eth = EthernetFrame::from_bytes_unchecked(&mut buf[..len]);
+            let mut arp = EthIPv4ArpFrame::from_bytes_unchecked(eth.payload_mut());
+            arp.set_spa(Ipv4Addr::UNSPECIFIED);
+            arp.set_tha(MacAddr::from_bytes_unchecked(&[0; 6]));
+        }
+        assert!(ns.detour_frame(&buf[..len]));
+
+        // The reply claims the address, so the guest detects the conflict.
+        let len = ns.write_next_frame(buf.as_mut()).unwrap().get();
+        let eth = EthernetFrame::from_bytes(&buf[..len]).unwrap();
+        let arp_reply = EthIPv4ArpFrame::reply_from_bytes(eth.payload()).unwrap();
+        assert_eq!(arp_reply.sha(), ns.mac_addr);
+        assert_eq!(arp_reply.spa(), ns.ipv4_addr);
+        assert_eq!(arp_reply.tha(), MacAddr::from_str(REMOTE_MAC_STR).unwrap());
+        assert_eq!(arp_reply.tpa(), Ipv4Addr::UNSPECIFIED);
+    }
+
     #[test]
     fn test_set_ipv4_addr() {
         let mut ns =
@@ -547,6 +779,133 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_icmp_echo() {
+        let mut ns =
+            MmdsNetworkStack::new_with_defaults(None, Arc::new(Mutex::new(Mmds::default())));
+        let mut buf = [0u8; 2000];
+        let remote_mac = MacAddr::from_str(REMOTE_MAC_STR).unwrap();
+        let mmds_addr = ns.ipv4_addr;
+
+        // Pings to other addresses are not for the MMDS.
+        let len = ns.write_incoming_echo_request(buf.as_mut(), REMOTE_ADDR);
+        assert!(!ns.is_mmds_frame(&buf[..len]));
+
+        let len = ns.write_incoming_echo_request(buf.as_mut(), mmds_addr);
+        assert!(ns.is_mmds_frame(&buf[..len]));
+        assert!(ns.detour_frame(&buf[..len]));
+        assert_eq!(ns.remote_mac_addr, remote_mac);
+
+        // A reply heads back to the sender of the request.
+        let curr_tx_count = METRICS.mmds.tx_count.count();
+        let len = ns.write_next_frame(buf.as_mut()).unwrap().get();
+        assert_eq!(curr_tx_count + 1, METRICS.mmds.tx_count.count());
+        let eth = EthernetFrame::from_bytes(&buf[..len]).unwrap();
+        assert_eq!(eth.dst_mac(), remote_mac);
+        assert_eq!(eth.src_mac(), ns.mac_addr);
+        assert_eq!(eth.ethertype(), ETHERTYPE_IPV4);
+        let ip = IPv4Packet::from_bytes(eth.payload(), true).unwrap();
+        assert_eq!(ip.protocol(), PROTOCOL_ICMP);
+        assert_eq!(ip.source_address(), mmds_addr);
+        assert_eq!(ip.destination_address(), REMOTE_ADDR);
+        let icmp = IcmpEchoFrame::reply_from_bytes(ip.payload(), true).unwrap();
+        assert_eq!(icmp.identifier(), 7);
+        assert_eq!(icmp.sequence_number(), 1);
+        assert_eq!(icmp.payload(), b"ping");
+
+        // Nothing to send anymore.
+        assert!(ns.write_next_frame(buf.as_mut()).is_none());
+
+        // Requests with an invalid checksum are not answered.
+        let len = ns.write_incoming_echo_request(buf.as_mut(), mmds_addr);
+        {
+            let mut eth = EthernetFrame::from_bytes_unchecked(&mut buf[..len]);
+            let mut ip = IPv4Packet::from_bytes_unchecked(eth.payload_mut());
+            IcmpEchoFrame::from_bytes_unchecked(ip.payload_mut()).set_checksum(0);
+        }
+        let curr_unusual = METRICS.mmds.rx_accepted_unusual.count();
+        assert!(ns.detour_frame(&buf[..len]));
+        assert_eq!(curr_unusual + 1, METRICS.mmds.rx_accepted_unusual.count());
+        assert!(ns.write_next_frame(buf.as_mut()).is_none());
+
+        // Replies which do not fit the buffer are dropped.
+        let len = ns.write_incoming_echo_request(buf.as_mut(), mmds_addr);
+        assert!(ns.detour_frame(&buf[..len]));
+        let curr_tx_errors = METRICS.mmds.tx_errors.count();
+        assert!(ns.write_next_frame(&mut buf[..len - 1]).is_none());
+        assert_eq!(curr_tx_errors + 1, METRICS.mmds.tx_errors.count());
+        assert!(ns.write_next_frame(buf.as_mut()).is_none());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut ns =
+            MmdsNetworkStack::new_with_defaults(None, Arc::new(Mutex::new(Mmds::default())));
+        assert_eq!(
+            ns.rate_limit_rps(),
+            MmdsNetworkStack::default_rate_limit_rps()
+        );
+        let mut buf = [0u8; 2000];
+        let mmds_addr = ns.ipv4_addr;
+
+        // A limit of 1 connection per second allows a burst of 2 connections.
+        ns.set_rate_limit_rps(1);
+        let rate_limited = METRICS.mmds.connections_rate_limited.count();
+        for _ in 0..2 {
+            let len = ns.write_incoming_tcp_segment(buf.as_mut(), mmds_addr, TcpFlags::SYN);
+            assert!(ns.detour_frame(&buf[..len]));
+            assert_eq!(rate_limited, METRICS.mmds.connections_rate_limited.count());
+        }
+        let len = ns.write_incoming_tcp_segment(buf.as_mut(), mmds_addr, TcpFlags::SYN);
+        assert!(ns.detour_frame(&buf[..len]));
+        assert_eq!(
+            rate_limited + 1,
+            METRICS.mmds.connections_rate_limited.count()
+        );
+
+        // Segments of established connections are not limited.
+        let len = ns.write_incoming_tcp_segment(buf.as_mut(), mmds_addr, TcpFlags::ACK);
+        assert!(ns.detour_frame(&buf[..len]));
+        assert_eq!(
+            rate_limited + 1,
+            METRICS.mmds.connections_rate_limited.count()
+        );
+
+        // A limit of 0 disables rate limiting.
+        ns.set_rate_limit_rps(0);
+        for _ in 0..10 {
+            let len = ns.write_incoming_tcp_segment(buf.as_mut(), mmds_addr, TcpFlags::SYN);
+            assert!(ns.detour_frame(&buf[..len]));
+        }
+        assert_eq!(
+            rate_limited + 1,
+            METRICS.mmds.connections_rate_limited.count()
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_addrs() {
+        let mut limiter = ConnectionRateLimiter::new(1);
+        for i in 0..MAX_RATE_LIMITED_ADDRS {
+            let addr = Ipv4Addr::from(u32::try_from(i).unwrap());
+            assert!(limiter.allow(addr));
+        }
+        // Tracked addresses keep their buckets, new ones are dropped while the table is full.
+        assert!(limiter.allow(Ipv4Addr::from(0u32)));
+        assert!(!limiter.allow(Ipv4Addr::from(0u32)));
+        let new_addr = Ipv4Addr::from(u32::try_from(MAX_RATE_LIMITED_ADDRS).unwrap());
+        assert!(!limiter.allow(new_addr));
+        assert_eq!(limiter.buckets.len(), MAX_RATE_LIMITED_ADDRS);
+
+        // Addresses unused for a whole refill period are forgotten.
+        let unused = Instant::now() - Duration::from_millis(RATE_LIMIT_BURST_MS);
+        for (_, last_used) in limiter.buckets.values_mut() {
+            *last_used = unused;
+        }
+        assert!(limiter.allow(new_addr));
+        assert_eq!(limiter.buckets.len(), 1);
+    }
+
     #[test]
     fn test_break_speculative_check_detour_arp() {
         let mut buf = [0u8; 2000];
@@ -0,0 +1,200 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hardware performance counters of the VMM process.
+//!
+//! The counters are opened with `perf_event_open(2)` when Firecracker starts, before it spawns any
+//! other thread, and are inherited by every thread created afterwards. They only count the user
+//! space execution of Firecracker, neither the host kernel nor the guest.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::fd::FromRawFd;
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+use crate::logger::{info, warn};
+
+// `perf_event_attr.type` of the generalized hardware events.
+const PERF_TYPE_HARDWARE: u32 = 0;
+// Generalized hardware events, from `enum perf_hw_id`.
+const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+const PERF_COUNT_HW_BRANCH_MISSES: u64 = 5;
+// Bits of the `perf_event_attr` bitfield.
+const PERF_ATTR_INHERIT: u64 = 1 << 1;
+const PERF_ATTR_EXCLUDE_KERNEL: u64 = 1 << 5;
+const PERF_ATTR_EXCLUDE_HV: u64 = 1 << 6;
+const PERF_ATTR_EXCLUDE_GUEST: u64 = 1 << 20;
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+/// First published layout of `struct perf_event_attr` (`PERF_ATTR_SIZE_VER0`), which every
+/// kernel accepts.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+const PERF_ATTR_SIZE_VER0: u32 = 64;
+const _: () = assert!(std::mem::size_of::<PerfEventAttr>() == PERF_ATTR_SIZE_VER0 as usize);
+
+static PERF_COUNTERS: OnceLock<Option<PerfCounters>> = OnceLock::new();
+
+/// Errors associated with the VMM performance counters.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum PerfCountersError {
+    /// The performance counters of the VMM are not available on this host.
+    Unavailable,
+    /// Failed to open a performance counter: {0}
+    Open(io::Error),
+    /// Failed to read a performance counter: {0}
+    Read(io::Error),
+}
+
+/// Values of the VMM performance counters, since Firecracker started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct VmmPerfCounters {
+    /// Retired instructions.
+    pub instructions: u64,
+    /// Last level cache misses.
+    pub cache_misses: u64,
+    /// Mispredicted branch instructions.
+    pub branch_mispredictions: u64,
+}
+
+#[derive(Debug)]
+struct PerfCounters {
+    instructions: File,
+    cache_misses: File,
+    branch_mispredictions: File,
+}
+
+impl PerfCounters {
+    fn open() -> Result<Self, PerfCountersError> {
+        Ok(PerfCounters {
+            instructions: open_counter(PERF_COUNT_HW_INSTRUCTIONS)?,
+            cache_misses: open_counter(PERF_COUNT_HW_CACHE_MISSES)?,
+            branch_mispredictions: open_counter(PERF_COUNT_HW_BRANCH_MISSES)?,
+        })
+    }
+
+    fn read(&self) -> Result<VmmPerfCounters, PerfCountersError> {
+        Ok(VmmPerfCounters {
+            instructions: read_counter(&self.instructions)?,
+            cache_misses: read_counter(&self.cache_misses)?,
+            branch_mispredictions: read_counter(&self.branch_mispredictions)?,
+        })
+    }
+}
+
+fn open_counter(config: u64) -> Result<File, PerfCountersError> {
+    let attr = PerfEventAttr {
+        type_: PERF_TYPE_HARDWARE,
+        size: PERF_ATTR_SIZE_VER0,
+        config,
+        flags: PERF_ATTR_INHERIT
+            | PERF_ATTR_EXCLUDE_KERNEL
+            | PERF_ATTR_EXCLUDE_HV
+            | PERF_ATTR_EXCLUDE_GUEST,
+        ..Default::default()
+    };
+
+    // SAFETY: `attr` is a valid `perf_event_attr` of the advertised size. The counter measures the
+    // calling thread (pid 0) on any CPU (-1), outside of any group (-1).
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_perf_event_open,
+            &attr as *const PerfEventAttr,
+            0,
+            -1,
+            -1,
+            PERF_FLAG_FD_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(PerfCountersError::Open(io::Error::last_os_error()));
+    }
+    let fd = i32::try_from(fd).expect("perf_event_open returned an invalid fd");
+    // SAFETY: `fd` is a valid file descriptor we exclusively own.
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+fn read_counter(mut counter: &File) -> Result<u64, PerfCountersError> {
+    // Without a `read_format`, reading a counter returns its value, which includes the values of
+    // the inherited counters of the other threads.
+    let mut value = [0u8; 8];
+    counter
+        .read_exact(&mut value)
+        .map_err(PerfCountersError::Read)?;
+    Ok(u64::from_ne_bytes(value))
+}
+
+/// Opens the performance counters of the VMM. Must be called before Firecracker spawns any other
+/// thread, so that the counters are inherited by every thread. Hosts that do not allow
+/// unprivileged access to the PMU, or do not have one, only get a warning.
+pub fn init_perf_counters() {
+    PERF_COUNTERS.get_or_init(|| match PerfCounters::open() {
+        Ok(counters) => {
+            info!("Opened the VMM performance counters");
+            Some(counters)
+        }
+        Err(err) => {
+            warn!("VMM performance counters are not available: {err}");
+            None
+        }
+    });
+}
+
+/// Returns the current values of the VMM performance counters.
+pub fn read_perf_counters() -> Result<VmmPerfCounters, PerfCountersError> {
+    PERF_COUNTERS
+        .get()
+        .and_then(Option::as_ref)
+        .ok_or(PerfCountersError::Unavailable)?
+        .read()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perf_counters() {
+        // The host running the tests may not expose a PMU, or may forbid unprivileged access to it.
+        let counters = match PerfCounters::open() {
+            Ok(counters) => counters,
+            Err(PerfCountersError::Open(_)) => return,
+            Err(err) => panic!("{err}"),
+        };
+
+        let first = counters.read().unwrap();
+        let mut sum = 0u64;
+        for i in 0..100_000u64 {
+            sum = std::hint::black_box(sum.wrapping_add(i));
+        }
+        let second = counters.read().unwrap();
+        assert!(second.instructions > first.instructions);
+        assert!(second.cache_misses >= first.cache_misses);
+        assert!(second.branch_mispredictions >= first.branch_mispredictions);
+    }
+
+    #[test]
+    fn test_read_uninitialized() {
+        // `init_perf_counters` is never called by the unit tests.
+        assert!(matches!(
+            read_perf_counters(),
+            Err(PerfCountersError::Unavailable)
+        ));
+    }
+}
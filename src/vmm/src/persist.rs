@@ -5,7 +5,7 @@
 
 use std::fmt::Debug;
 use std::fs::{File, OpenOptions};
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
 use std::mem::forget;
 use std::os::unix::io::AsRawFd;
 use std::os::unix::net::UnixStream;
@@ -17,6 +17,7 @@ use serde::{Deserialize, Serialize};
 use userfaultfd::{FeatureFlags, Uffd, UffdBuilder};
 use vmm_sys_util::sock_ctrl_msg::ScmSocket;
 
+use crate::arch::DeviceType;
 #[cfg(target_arch = "aarch64")]
 use crate::arch::aarch64::vcpu::get_manufacturer_id_from_host;
 use crate::builder::{self, BuildMicrovmFromSnapshotError};
@@ -26,10 +27,14 @@ use crate::cpu_config::x86_64::cpuid::CpuidTrait;
 #[cfg(target_arch = "x86_64")]
 use crate::cpu_config::x86_64::cpuid::common::get_vendor_id_from_host;
 use crate::device_manager::persist::{ACPIDeviceManagerState, DevicePersistError, DeviceStates};
+use crate::devices::virtio::TYPE_PMEM;
 use crate::logger::{info, warn};
 use crate::resources::VmResources;
 use crate::seccomp::BpfThreadMap;
 use crate::snapshot::Snapshot;
+use crate::snapshot::integrity::{
+    SnapshotIntegrity, SnapshotIntegrityError, snapshot_integrity_enabled,
+};
 use crate::utils::u64_to_usize;
 use crate::vmm_config::boot_source::BootSourceConfig;
 use crate::vmm_config::instance_info::InstanceInfo;
@@ -145,10 +150,14 @@ pub enum CreateSnapshotError {
     SerializeMicrovmState(#[from] crate::snapshot::SnapshotError),
     /// Cannot perform {0} on the snapshot backing file: {1}
     SnapshotBackingFile(&'static str, io::Error),
+    /// Cannot record the integrity of the snapshot: {0}
+    Integrity(#[from] SnapshotIntegrityError),
+    /// Snapshotting is not supported with {0} devices attached
+    UnsupportedDevice(&'static str),
 }
 
 /// Snapshot version
-pub const SNAPSHOT_VERSION: Version = Version::new(6, 0, 0);
+pub const SNAPSHOT_VERSION: Version = Version::new(7, 0, 0);
 
 /// Creates a Microvm snapshot.
 pub fn create_snapshot(
@@ -156,6 +165,17 @@ pub fn create_snapshot(
     vm_info: &VmInfo,
     params: &CreateSnapshotParams,
 ) -> Result<(), CreateSnapshotError> {
+    // The state of pmem devices is not saved, so the restored microVM would lose them.
+    let pmem_type = DeviceType::Virtio(TYPE_PMEM);
+    if vmm
+        .mmio_device_manager
+        .get_device_info()
+        .keys()
+        .any(|(device_type, _)| *device_type == pmem_type)
+    {
+        return Err(CreateSnapshotError::UnsupportedDevice("pmem"));
+    }
+
     let microvm_state = vmm
         .save_state(vm_info)
         .map_err(CreateSnapshotError::MicrovmState)?;
@@ -165,6 +185,11 @@ pub fn create_snapshot(
     vmm.vm
         .snapshot_memory_to_file(&params.mem_file_path, params.snapshot_type)?;
 
+    if snapshot_integrity_enabled() {
+        SnapshotIntegrity::new(&params.snapshot_path, &params.mem_file_path)?
+            .save(&params.snapshot_path)?;
+    }
+
     // We need to mark queues as dirty again for all activated devices. The reason we
     // do it here is because we don't mark pages as dirty during runtime
     // for queue objects.
@@ -305,6 +330,8 @@ pub fn snapshot_state_sanity_check(
 /// Error type for [`restore_from_snapshot`].
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
 pub enum RestoreFromSnapshotError {
+    /// Snapshot integrity check failed: {0}
+    IntegrityCheckFailed(#[from] SnapshotIntegrityError),
     /// Failed to get snapshot state from file: {0}
     File(#[from] SnapshotStateFromFileError),
     /// Invalid snapshot state: {0}
@@ -332,7 +359,12 @@ pub fn restore_from_snapshot(
     params: &LoadSnapshotParams,
     vm_resources: &mut VmResources,
 ) -> Result<Arc<Mutex<Vmm>>, RestoreFromSnapshotError> {
-    let mut microvm_state = snapshot_state_from_file(&params.snapshot_path)?;
+    let integrity = if snapshot_integrity_enabled() {
+        Some(SnapshotIntegrity::load(&params.snapshot_path)?)
+    } else {
+        None
+    };
+    let mut microvm_state = snapshot_state_from_file(&params.snapshot_path, integrity.as_ref())?;
     for entry in &params.network_overrides {
         let net_devices = &mut microvm_state.device_states.net_devices;
         if let Some(device) = net_devices
@@ -364,6 +396,15 @@ pub fn restore_from_snapshot(
             cpu_template: Some(microvm_state.vm_info.cpu_template),
             track_dirty_pages: Some(track_dirty_pages),
             huge_pages: Some(microvm_state.vm_info.huge_pages),
+            additional_memory_regions: None,
+            thp_mode: None,
+            cpu_weight: None,
+            io_weight: None,
+            memory_high_mb: None,
+            memory_swap_high_mb: None,
+            cpu_budget: None,
+            balloon_policy: None,
+            cpuid_blocklist: None,
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         })
@@ -383,12 +424,21 @@ pub fn restore_from_snapshot(
                 )
                 .into());
             }
+            let mem_file = File::open(mem_backend_path).map_err(|err| {
+                RestoreFromSnapshotGuestMemoryError::File(GuestMemoryFromFileError::File(err))
+            })?;
+            // The memory is mapped from the same file descriptor whose contents are checked.
+            if let Some(integrity) = &integrity {
+                integrity.verify_mem(mem_backend_path, &mem_file)?;
+                info!("Verified the integrity of the guest memory file");
+            }
             (
-                guest_memory_from_file(mem_backend_path, mem_state, track_dirty_pages)
+                guest_memory_from_file(mem_file, mem_state, track_dirty_pages)
                     .map_err(RestoreFromSnapshotGuestMemoryError::File)?,
                 None,
             )
         }
+        // With uffd, the memory file is only read by the page fault handler.
         MemBackendType::Uffd => guest_memory_from_uffd(
             mem_backend_path,
             mem_state,
@@ -414,8 +464,10 @@ pub fn restore_from_snapshot(
 pub enum SnapshotStateFromFileError {
     /// Failed to open snapshot file: {0}
     Open(std::io::Error),
-    /// Failed to read snapshot file metadata: {0}
-    Meta(std::io::Error),
+    /// Failed to read snapshot file: {0}
+    Read(std::io::Error),
+    /// Snapshot integrity check failed: {0}
+    Integrity(#[from] SnapshotIntegrityError),
     /// Failed to load snapshot state from file: {0}
     Load(#[from] crate::snapshot::SnapshotError),
     /// Unknown Network Device.
@@ -424,14 +476,21 @@ pub enum SnapshotStateFromFileError {
 
 fn snapshot_state_from_file(
     snapshot_path: &Path,
+    integrity: Option<&SnapshotIntegrity>,
 ) -> Result<MicrovmState, SnapshotStateFromFileError> {
     let snapshot = Snapshot::new(SNAPSHOT_VERSION);
-    let mut snapshot_reader =
-        File::open(snapshot_path).map_err(SnapshotStateFromFileError::Open)?;
-    let metadata = std::fs::metadata(snapshot_path).map_err(SnapshotStateFromFileError::Meta)?;
-    let snapshot_len = u64_to_usize(metadata.len());
+    let mut snapshot_file = File::open(snapshot_path).map_err(SnapshotStateFromFileError::Open)?;
+    // The state is deserialized from the same buffer whose contents are checked.
+    let mut snapshot_bytes = Vec::new();
+    snapshot_file
+        .read_to_end(&mut snapshot_bytes)
+        .map_err(SnapshotStateFromFileError::Read)?;
+    if let Some(integrity) = integrity {
+        integrity.verify_state(snapshot_path, &snapshot_bytes)?;
+        info!("Verified the integrity of the microVM state file");
+    }
     let state: MicrovmState = snapshot
-        .load_with_version_check(&mut snapshot_reader, snapshot_len)
+        .load_with_version_check(&mut snapshot_bytes.as_slice(), snapshot_bytes.len())
         .map_err(SnapshotStateFromFileError::Load)?;
     Ok(state)
 }
@@ -448,11 +507,10 @@ pub enum GuestMemoryFromFileError {
 }
 
 fn guest_memory_from_file(
-    mem_file_path: &Path,
+    mem_file: File,
     mem_state: &GuestMemoryState,
     track_dirty_pages: bool,
 ) -> Result<Vec<GuestRegionMmap>, GuestMemoryFromFileError> {
-    let mem_file = File::open(mem_file_path)?;
     let guest_mem = memory::snapshot_file(mem_file, mem_state.regions(), track_dirty_pages)?;
     Ok(guest_mem)
 }
@@ -594,14 +652,17 @@ mod tests {
     use crate::builder::tests::insert_vmgenid_device;
     use crate::builder::tests::{
         CustomBlockConfig, default_kernel_cmdline, default_vmm, insert_balloon_device,
-        insert_block_devices, insert_net_device, insert_vsock_device,
+        insert_block_devices, insert_net_device, insert_pmem_device, insert_vsock_device,
     };
     #[cfg(target_arch = "aarch64")]
     use crate::construct_kvm_mpidrs;
     use crate::devices::virtio::block::CacheType;
+    use crate::devices::virtio::pmem::PMEM_ALIGNMENT;
     use crate::snapshot::Persist;
     use crate::vmm_config::balloon::BalloonDeviceConfig;
     use crate::vmm_config::net::NetworkInterfaceConfig;
+    use crate::vmm_config::pmem::PmemConfig;
+    use crate::vmm_config::snapshot::SnapshotType;
     use crate::vmm_config::vsock::tests::default_config;
     use crate::vstate::memory::GuestMemoryRegionState;
 
@@ -636,6 +697,9 @@ mod tests {
             guest_mac: None,
             rx_rate_limiter: None,
             tx_rate_limiter: None,
+            mtu: None,
+            link_speed_mbps: None,
+            duplex: None,
         };
         insert_net_device(
             &mut vmm,
@@ -700,6 +764,35 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_create_snapshot_with_pmem() {
+        let mut event_manager = EventManager::new().expect("Cannot create EventManager");
+        let mut vmm = default_vmm();
+        let mut cmdline = default_kernel_cmdline();
+        let pmem_file = TempFile::new().unwrap();
+        pmem_file.as_file().set_len(PMEM_ALIGNMENT).unwrap();
+        let pmem_config = PmemConfig {
+            drive_id: String::from("pmem"),
+            path_on_host: pmem_file.as_path().to_str().unwrap().to_string(),
+            is_root_device: false,
+            is_read_only: false,
+        };
+        insert_pmem_device(&mut vmm, &mut cmdline, &mut event_manager, pmem_config);
+
+        // Snapshots would lose the pmem devices, so they are refused.
+        let snapshot_file = TempFile::new().unwrap();
+        let mem_file = TempFile::new().unwrap();
+        let params = CreateSnapshotParams {
+            snapshot_type: SnapshotType::Full,
+            snapshot_path: snapshot_file.as_path().to_path_buf(),
+            mem_file_path: mem_file.as_path().to_path_buf(),
+        };
+        assert!(matches!(
+            create_snapshot(&mut vmm, &VmInfo::default(), &params),
+            Err(CreateSnapshotError::UnsupportedDevice("pmem"))
+        ));
+    }
+
     #[test]
     fn test_create_guest_memory() {
         let mem_state = GuestMemoryState {
@@ -7,7 +7,7 @@ use std::sync::{Arc, Mutex, MutexGuard};
 
 use serde::{Deserialize, Serialize};
 
-use crate::cpu_config::templates::CustomCpuTemplate;
+use crate::cpu_config::templates::{CpuTemplateType, CustomCpuTemplate};
 use crate::device_manager::persist::SharedDeviceType;
 use crate::logger::info;
 use crate::mmds;
@@ -28,6 +28,11 @@ use crate::vmm_config::machine_config::{
 use crate::vmm_config::metrics::{MetricsConfig, MetricsConfigError, init_metrics};
 use crate::vmm_config::mmds::{MmdsConfig, MmdsConfigError};
 use crate::vmm_config::net::*;
+#[cfg(feature = "plugins")]
+use crate::vmm_config::plugin::PluginConfig;
+use crate::vmm_config::pmem::*;
+#[cfg(target_arch = "x86_64")]
+use crate::vmm_config::pmu::{PmuConfig, VcpuPmuConfig, VcpuPmuConfigError};
 use crate::vmm_config::vsock::*;
 use crate::vstate::memory;
 use crate::vstate::memory::{GuestRegionMmap, MemoryError};
@@ -61,6 +66,8 @@ pub enum ResourcesError {
     VsockDevice(#[from] VsockConfigError),
     /// Entropy device error: {0}
     EntropyDevice(#[from] EntropyDeviceError),
+    /// Pmem device error: {0}
+    PmemDevice(#[from] PmemConfigError),
 }
 
 /// Used for configuring a vmm from one single json passed to the Firecracker process.
@@ -79,6 +86,19 @@ pub struct VmmConfig {
     network_interfaces: Vec<NetworkInterfaceConfig>,
     vsock: Option<VsockDeviceConfig>,
     entropy: Option<EntropyDeviceConfig>,
+    #[cfg(feature = "plugins")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    plugins: Vec<PluginConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pmem_drives: Vec<PmemConfig>,
+}
+
+/// Configuration of a microVM saved through the API, which can be restored before boot.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigSnapshot {
+    /// The saved configuration.
+    pub snapshot: VmmConfig,
 }
 
 /// A data structure that encapsulates the device configurations
@@ -99,6 +119,14 @@ pub struct VmResources {
     pub net_builder: NetBuilder,
     /// The entropy device builder.
     pub entropy: EntropyDeviceBuilder,
+    /// The pmem devices.
+    pub pmem: PmemBuilder,
+    /// The PMU configuration of the vCPUs.
+    #[cfg(target_arch = "x86_64")]
+    pub pmu: PmuConfig,
+    /// The devices emulated by plugins.
+    #[cfg(feature = "plugins")]
+    pub plugins: Vec<PluginConfig>,
     /// The optional Mmds data store.
     // This is initialised on demand (if ever used), so that we don't allocate it unless it's
     // actually used.
@@ -131,53 +159,102 @@ impl VmResources {
             mmds_size_limit,
             ..Default::default()
         };
+
+        // Init the data store from file, if present.
+        if let Some(data) = metadata_json {
+            resources.locked_mmds_or_default().put_data(
+                serde_json::from_str(data).expect("MMDS error: metadata provided not valid json"),
+            )?;
+            info!("Successfully added metadata to mmds from file");
+        }
+
+        resources.apply_vmm_config(vmm_config, &instance_info.id)?;
+        Ok(resources)
+    }
+
+    /// Replaces the configuration of the microVM with `vmm_config`.
+    ///
+    /// The devices of the current configuration are released before the new ones are created,
+    /// so that the new devices can use the same host resources, e.g. the same tap devices. The
+    /// MMDS data store, the custom CPU template and the PMU configuration of the vCPUs are kept.
+    pub fn replace_config(
+        &mut self,
+        vmm_config: VmmConfig,
+        instance_id: &str,
+    ) -> Result<(), ResourcesError> {
+        let mut resources = Self {
+            mmds: self.mmds.take(),
+            mmds_size_limit: self.mmds_size_limit,
+            boot_timer: self.boot_timer,
+            #[cfg(target_arch = "x86_64")]
+            pmu: std::mem::take(&mut self.pmu),
+            ..Default::default()
+        };
+        if let Some(CpuTemplateType::Custom(cpu_template)) = self.machine_config.cpu_template.take()
+        {
+            resources.set_custom_cpu_template(cpu_template);
+        }
+        *self = resources;
+
+        self.apply_vmm_config(vmm_config, instance_id)
+    }
+
+    fn apply_vmm_config(
+        &mut self,
+        vmm_config: VmmConfig,
+        instance_id: &str,
+    ) -> Result<(), ResourcesError> {
         if let Some(machine_config) = vmm_config.machine_config {
             let machine_config = MachineConfigUpdate::from(machine_config);
-            resources.update_machine_config(&machine_config)?;
+            self.update_machine_config(&machine_config)?;
         }
 
         if let Some(cpu_config) = vmm_config.cpu_config {
             let cpu_config_json =
                 std::fs::read_to_string(cpu_config).map_err(ResourcesError::File)?;
             let cpu_template = CustomCpuTemplate::try_from(cpu_config_json.as_str())?;
-            resources.set_custom_cpu_template(cpu_template);
+            self.set_custom_cpu_template(cpu_template);
         }
 
-        resources.build_boot_source(vmm_config.boot_source)?;
+        // A configuration saved before the boot source was set has a default one.
+        if vmm_config.boot_source != BootSourceConfig::default() {
+            self.build_boot_source(vmm_config.boot_source)?;
+        }
 
         for drive_config in vmm_config.drives.into_iter() {
-            resources.set_block_device(drive_config)?;
+            self.set_block_device(drive_config)?;
         }
 
         for net_config in vmm_config.network_interfaces.into_iter() {
-            resources.build_net_device(net_config)?;
+            self.build_net_device(net_config)?;
         }
 
         if let Some(vsock_config) = vmm_config.vsock {
-            resources.set_vsock_device(vsock_config)?;
+            self.set_vsock_device(vsock_config)?;
         }
 
         if let Some(balloon_config) = vmm_config.balloon {
-            resources.set_balloon_device(balloon_config)?;
-        }
-
-        // Init the data store from file, if present.
-        if let Some(data) = metadata_json {
-            resources.locked_mmds_or_default().put_data(
-                serde_json::from_str(data).expect("MMDS error: metadata provided not valid json"),
-            )?;
-            info!("Successfully added metadata to mmds from file");
+            self.set_balloon_device(balloon_config)?;
         }
 
         if let Some(mmds_config) = vmm_config.mmds_config {
-            resources.set_mmds_config(mmds_config, &instance_info.id)?;
+            self.set_mmds_config(mmds_config, instance_id)?;
         }
 
         if let Some(entropy_device_config) = vmm_config.entropy {
-            resources.build_entropy_device(entropy_device_config)?;
+            self.build_entropy_device(entropy_device_config)?;
         }
 
-        Ok(resources)
+        for pmem_config in vmm_config.pmem_drives.into_iter() {
+            self.build_pmem_device(pmem_config)?;
+        }
+
+        #[cfg(feature = "plugins")]
+        {
+            self.plugins = vmm_config.plugins;
+        }
+
+        Ok(())
     }
 
     /// If not initialised, create the mmds data store with the default config.
@@ -277,10 +354,17 @@ impl VmResources {
             .collect();
 
         if !net_devs_with_mmds.is_empty() {
+            let (version, cache_ttl_ms) = {
+                let mmds = mmds.lock().expect("Poisoned lock");
+                (mmds.version(), mmds.response_cache_ttl_ms())
+            };
             let mut inner_mmds_config = MmdsConfig {
-                version: mmds.lock().expect("Poisoned lock").version(),
+                version,
                 network_interfaces: vec![],
                 ipv4_address: None,
+                mmds_rate_limit_rps: None,
+                // The cache TTL is left out of the config when the cache is disabled.
+                mmds_cache_ttl_ms: Some(cache_ttl_ms).filter(|&ttl| ttl != 0),
             };
 
             for net_dev in net_devs_with_mmds {
@@ -291,6 +375,11 @@ impl VmResources {
                     // Safe to unwrap the mmds_ns as the filter() explicitly checks for
                     // its existence.
                     inner_mmds_config.ipv4_address = Some(net.mmds_ns().unwrap().ipv4_addr());
+                    // The rate limit is left out of the config when it was not changed.
+                    let rate_limit_rps = net.mmds_ns().unwrap().rate_limit_rps();
+                    if rate_limit_rps != MmdsNetworkStack::default_rate_limit_rps() {
+                        inner_mmds_config.mmds_rate_limit_rps = Some(rate_limit_rps);
+                    }
                 }
             }
 
@@ -338,6 +427,10 @@ impl VmResources {
         &mut self,
         block_device_config: BlockDeviceConfig,
     ) -> Result<(), DriveError> {
+        // The root file system is mounted either from a block or from a pmem device.
+        if block_device_config.is_root_device && self.pmem.has_root_device() {
+            return Err(DriveError::RootPmemDeviceAlreadyAdded);
+        }
         self.block.insert(block_device_config)
     }
 
@@ -363,6 +456,31 @@ impl VmResources {
         self.entropy.insert(body)
     }
 
+    /// Builds a pmem device to be attached when the VM starts.
+    // If the drive_id does not exist, a new pmem device is added to the list.
+    pub fn build_pmem_device(&mut self, config: PmemConfig) -> Result<(), PmemConfigError> {
+        if config.is_root_device && self.block.has_root_device() {
+            return Err(PmemConfigError::RootBlockDeviceAlreadyAdded);
+        }
+        self.pmem.insert(config)
+    }
+
+    /// Sets the PMU configuration of the vCPU with index `index`.
+    #[cfg(target_arch = "x86_64")]
+    pub fn set_vcpu_pmu(
+        &mut self,
+        index: u8,
+        config: VcpuPmuConfig,
+    ) -> Result<(), VcpuPmuConfigError> {
+        if index >= self.machine_config.vcpu_count {
+            return Err(VcpuPmuConfigError::InvalidVcpuIndex(
+                index,
+                self.machine_config.vcpu_count,
+            ));
+        }
+        self.pmu.set_vcpu(index, config)
+    }
+
     /// Setter for mmds config.
     pub fn set_mmds_config(
         &mut self,
@@ -371,6 +489,8 @@ impl VmResources {
     ) -> Result<(), MmdsConfigError> {
         self.set_mmds_network_stack_config(&config)?;
         self.set_mmds_version(config.version, instance_id)?;
+        self.locked_mmds_or_default()
+            .set_response_cache_ttl_ms(config.cache_ttl_ms().unwrap_or(0));
 
         Ok(())
     }
@@ -402,6 +522,9 @@ impl VmResources {
             None => Ok(MmdsNetworkStack::default_ipv4_addr()),
             _ => Err(MmdsConfigError::InvalidIpv4Addr),
         }?;
+        let rate_limit_rps = config
+            .rate_limit_rps()
+            .unwrap_or_else(MmdsNetworkStack::default_rate_limit_rps);
 
         let network_interfaces = config.network_interfaces();
         // Ensure that at least one network ID is specified.
@@ -428,7 +551,11 @@ impl VmResources {
         for net_device in self.net_builder.iter_mut() {
             let mut net_device_lock = net_device.lock().expect("Poisoned lock");
             if network_interfaces.contains(net_device_lock.id()) {
-                net_device_lock.configure_mmds_network_stack(ipv4_addr, mmds.clone());
+                net_device_lock.configure_mmds_network_stack(
+                    ipv4_addr,
+                    rate_limit_rps,
+                    mmds.clone(),
+                );
             } else {
                 net_device_lock.disable_mmds_network_stack();
             }
@@ -437,11 +564,29 @@ impl VmResources {
         Ok(())
     }
 
+    /// Returns the host paths of the files backing the block devices, leaving out vhost-user
+    /// devices.
+    pub fn block_device_paths(&self) -> Vec<PathBuf> {
+        self.block
+            .configs()
+            .into_iter()
+            .filter_map(|config| config.path_on_host.map(PathBuf::from))
+            .collect()
+    }
+
     /// Allocates guest memory in a configuration most appropriate for these [`VmResources`].
     ///
     /// If vhost-user-blk devices are in use, allocates memfd-backed shared memory, otherwise
     /// prefers anonymous memory for performance reasons.
     pub fn allocate_guest_memory(&self) -> Result<Vec<GuestRegionMmap>, MemoryError> {
+        let guest_memory = self.allocate_guest_memory_regions()?;
+        if let Some(thp_mode) = self.machine_config.thp_mode {
+            memory::apply_thp_mode(&guest_memory, thp_mode)?;
+        }
+        Ok(guest_memory)
+    }
+
+    fn allocate_guest_memory_regions(&self) -> Result<Vec<GuestRegionMmap>, MemoryError> {
         let vhost_user_device_used = self
             .block
             .devices
@@ -489,6 +634,9 @@ impl From<&VmResources> for VmmConfig {
             network_interfaces: resources.net_builder.configs(),
             vsock: resources.vsock.config(),
             entropy: resources.entropy.config(),
+            #[cfg(feature = "plugins")]
+            plugins: resources.plugins.clone(),
+            pmem_drives: resources.pmem.configs(),
         }
     }
 }
@@ -505,10 +653,11 @@ mod tests {
 
     use super::*;
     use crate::HTTP_MAX_PAYLOAD_SIZE;
-    use crate::cpu_config::templates::{CpuTemplateType, StaticCpuTemplate};
+    use crate::cpu_config::templates::StaticCpuTemplate;
     use crate::devices::virtio::balloon::Balloon;
     use crate::devices::virtio::block::virtio::VirtioBlockError;
     use crate::devices::virtio::block::{BlockError, CacheType};
+    use crate::devices::virtio::pmem::PMEM_ALIGNMENT;
     use crate::devices::virtio::vsock::VSOCK_DEV_ID;
     use crate::resources::VmResources;
     use crate::utils::net::mac::MacAddr;
@@ -535,6 +684,9 @@ mod tests {
             guest_mac: Some(MacAddr::from_str("01:23:45:67:89:0a").unwrap()),
             rx_rate_limiter: Some(RateLimiterConfig::default()),
             tx_rate_limiter: Some(RateLimiterConfig::default()),
+            mtu: None,
+            link_speed_mbps: None,
+            duplex: None,
         }
     }
 
@@ -558,6 +710,8 @@ mod tests {
                 path_on_host: Some(tmp_file.as_path().to_str().unwrap().to_string()),
                 rate_limiter: Some(RateLimiterConfig::default()),
                 file_engine_type: None,
+                lifetime: None,
+                discard: None,
 
                 socket: None,
             },
@@ -598,6 +752,11 @@ mod tests {
             boot_timer: false,
             mmds_size_limit: HTTP_MAX_PAYLOAD_SIZE,
             entropy: Default::default(),
+            pmem: Default::default(),
+            #[cfg(target_arch = "x86_64")]
+            pmu: Default::default(),
+            #[cfg(feature = "plugins")]
+            plugins: vec![],
         }
     }
 
@@ -1276,7 +1435,9 @@ mod tests {
                     }},
                     "mmds-config": {{
                         "network_interfaces": ["netif1", "netif2"],
-                        "ipv4_address": "169.254.1.1"
+                        "ipv4_address": "169.254.1.1",
+                        "mmds_rate_limit_rps": 10,
+                        "mmds_cache_ttl_ms": 1000
                     }}
             }}"#,
                 kernel_file.as_path().to_str().unwrap(),
@@ -1309,6 +1470,15 @@ mod tests {
             cpu_template: Some(StaticCpuTemplate::V1N1),
             track_dirty_pages: Some(false),
             huge_pages: Some(HugePageConfig::None),
+            additional_memory_regions: Some(vec![]),
+            thp_mode: None,
+            cpu_weight: None,
+            io_weight: None,
+            memory_high_mb: None,
+            memory_swap_high_mb: None,
+            cpu_budget: None,
+            balloon_policy: Some(BalloonPolicy::None),
+            cpuid_blocklist: Some(vec![]),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         };
@@ -1546,6 +1716,44 @@ mod tests {
         assert_eq!(vm_resources.block.devices.len(), 2);
     }
 
+    #[test]
+    fn test_set_pmem_device() {
+        let mut vm_resources = default_vm_resources();
+        let tmp_file = TempFile::new().unwrap();
+        tmp_file.as_file().set_len(PMEM_ALIGNMENT).unwrap();
+        let pmem_cfg = PmemConfig {
+            drive_id: "pmem0".to_string(),
+            path_on_host: tmp_file.as_path().to_str().unwrap().to_string(),
+            is_root_device: true,
+            is_read_only: true,
+        };
+
+        vm_resources.build_pmem_device(pmem_cfg.clone()).unwrap();
+        assert_eq!(vm_resources.pmem.configs(), [pmem_cfg.clone()]);
+        assert_eq!(
+            VmmConfig::from(&vm_resources).pmem_drives,
+            [pmem_cfg.clone()]
+        );
+
+        // There is a single root device, be it a block or a pmem device.
+        let (mut block_cfg, _file) = default_block_cfg();
+        block_cfg.drive_id = "root".to_string();
+        block_cfg.is_root_device = true;
+        assert!(matches!(
+            vm_resources.set_block_device(block_cfg),
+            Err(DriveError::RootPmemDeviceAlreadyAdded)
+        ));
+
+        let mut vm_resources = default_vm_resources();
+        let (mut block_cfg, _file) = default_block_cfg();
+        block_cfg.is_root_device = true;
+        vm_resources.set_block_device(block_cfg).unwrap();
+        assert!(matches!(
+            vm_resources.build_pmem_device(pmem_cfg),
+            Err(PmemConfigError::RootBlockDeviceAlreadyAdded)
+        ));
+    }
+
     #[test]
     fn test_set_vsock_device() {
         let mut vm_resources = default_vm_resources();
@@ -1572,4 +1780,91 @@ mod tests {
         vm_resources.build_net_device(new_net_device_cfg).unwrap();
         assert_eq!(vm_resources.net_builder.len(), 2);
     }
+
+    #[test]
+    fn test_replace_config() {
+        let kernel_file = TempFile::new().unwrap();
+        let rootfs_file = TempFile::new().unwrap();
+        let data_file = TempFile::new().unwrap();
+        let config_json = |drives: &[(&str, &TempFile)]| {
+            let drives: Vec<String> = drives
+                .iter()
+                .map(|(id, file)| {
+                    format!(
+                        r#"{{"drive_id": "{}", "path_on_host": "{}", "is_root_device": {}}}"#,
+                        id,
+                        file.as_path().to_str().unwrap(),
+                        *id == "rootfs"
+                    )
+                })
+                .collect();
+            format!(
+                r#"{{
+                    "boot-source": {{
+                        "kernel_image_path": "{}"
+                    }},
+                    "drives": [{}],
+                    "machine-config": {{
+                        "vcpu_count": 2,
+                        "mem_size_mib": 256
+                    }}
+                }}"#,
+                kernel_file.as_path().to_str().unwrap(),
+                drives.join(",")
+            )
+        };
+
+        let mut vm_resources = VmResources::from_json(
+            &config_json(&[("rootfs", &rootfs_file)]),
+            &InstanceInfo::default(),
+            HTTP_MAX_PAYLOAD_SIZE,
+            Some(r#"{"key": "value"}"#),
+        )
+        .unwrap();
+        vm_resources.set_custom_cpu_template(CustomCpuTemplate::default());
+        let config: VmmConfig = serde_json::from_str(&config_json(&[
+            ("rootfs", &rootfs_file),
+            ("data", &data_file),
+        ]))
+        .unwrap();
+
+        vm_resources.replace_config(config, "").unwrap();
+        assert_eq!(vm_resources.block.configs().len(), 2);
+        assert_eq!(vm_resources.machine_config.vcpu_count, 2);
+        // The custom CPU template and the MMDS data store are kept.
+        assert!(matches!(
+            vm_resources.machine_config.cpu_template,
+            Some(CpuTemplateType::Custom(_))
+        ));
+        assert_eq!(
+            vm_resources.locked_mmds_or_default().data_store_value(),
+            serde_json::json!({"key": "value"})
+        );
+
+        let mut config: VmmConfig =
+            serde_json::from_str(&config_json(&[("rootfs", &rootfs_file)])).unwrap();
+        config.boot_source.kernel_image_path = "/invalid/path".to_string();
+        assert!(matches!(
+            vm_resources.replace_config(config, ""),
+            Err(ResourcesError::BootSource(
+                BootSourceConfigError::InvalidKernelPath(_)
+            ))
+        ));
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_set_vcpu_pmu() {
+        let mut vm_resources = default_vm_resources();
+        let config = VcpuPmuConfig {
+            enabled: true,
+            allowed_events: vec![],
+        };
+        assert!(matches!(
+            vm_resources.set_vcpu_pmu(1, config.clone()),
+            Err(VcpuPmuConfigError::InvalidVcpuIndex(1, 1))
+        ));
+        vm_resources.set_vcpu_pmu(0, config).unwrap();
+        assert!(vm_resources.pmu.is_enabled(0));
+    }
 }
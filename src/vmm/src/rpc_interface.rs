@@ -7,33 +7,54 @@ use std::sync::{Arc, Mutex, MutexGuard};
 use serde_json::Value;
 use utils::time::{ClockType, get_time_us};
 
+#[cfg(feature = "debug-api")]
+use super::SingleVcpuError;
 use super::builder::build_and_boot_microvm;
 use super::persist::{create_snapshot, restore_from_snapshot};
 use super::resources::VmResources;
 use super::{Vmm, VmmError};
 use crate::EventManager;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use crate::arch::VcpuStateSnapshot;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use crate::arch::x86_64::page_table::{GuestMapping, PageTableRoot, PageTableWalkError};
 use crate::builder::StartMicrovmError;
-use crate::cpu_config::templates::{CustomCpuTemplate, GuestConfigError};
+use crate::cgroup::{CgroupError, apply_cgroup_limits};
+use crate::cpu_config::templates::{CpuTemplateType, CustomCpuTemplate, GuestConfigError};
 use crate::logger::{LoggerConfig, info, warn, *};
+#[cfg(feature = "debug-api")]
+use crate::logger::{TRACE_LOG, TraceEntry};
 use crate::mmds::data_store::{self, Mmds};
+#[cfg(feature = "perf-counters")]
+use crate::perf_counters::{PerfCountersError, VmmPerfCounters, read_perf_counters};
 use crate::persist::{CreateSnapshotError, RestoreFromSnapshotError, VmInfo};
-use crate::resources::VmmConfig;
+use crate::resources::{ConfigSnapshot, ResourcesError, VmmConfig};
 use crate::seccomp::BpfThreadMap;
 use crate::vmm_config::balloon::{
     BalloonConfigError, BalloonDeviceConfig, BalloonStats, BalloonUpdateConfig,
     BalloonUpdateStatsConfig,
 };
 use crate::vmm_config::boot_source::{BootSourceConfig, BootSourceConfigError};
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use crate::vmm_config::breakpoints::{BreakpointConfig, BreakpointsError};
+#[cfg(target_arch = "x86_64")]
+use crate::vmm_config::clock::VmClockConfig;
 use crate::vmm_config::drive::{BlockDeviceConfig, BlockDeviceUpdateConfig, DriveError};
 use crate::vmm_config::entropy::{EntropyDeviceConfig, EntropyDeviceError};
 use crate::vmm_config::instance_info::InstanceInfo;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use crate::vmm_config::interrupt::{InjectInterruptConfig, InjectInterruptError};
 use crate::vmm_config::machine_config::{MachineConfig, MachineConfigError, MachineConfigUpdate};
 use crate::vmm_config::metrics::{MetricsConfig, MetricsConfigError};
 use crate::vmm_config::mmds::{MmdsConfig, MmdsConfigError};
 use crate::vmm_config::net::{
     NetworkInterfaceConfig, NetworkInterfaceError, NetworkInterfaceUpdateConfig,
 };
+use crate::vmm_config::pmem::{PmemConfig, PmemConfigError};
+#[cfg(target_arch = "x86_64")]
+use crate::vmm_config::pmu::{PmuConfig, VcpuPmuConfig, VcpuPmuConfigError};
 use crate::vmm_config::snapshot::{CreateSnapshotParams, LoadSnapshotParams, SnapshotType};
+use crate::vmm_config::vmgenid::{VmGenIdConfig, VmGenIdConfigError};
 use crate::vmm_config::vsock::{VsockConfigError, VsockDeviceConfig};
 use crate::vmm_config::{self, RateLimiterUpdate};
 
@@ -41,6 +62,12 @@ use crate::vmm_config::{self, RateLimiterUpdate};
 /// bits of information (ids, paths, etc.).
 #[derive(Debug, PartialEq, Eq)]
 pub enum VmmAction {
+    /// Start buffering the configuration changes until the transaction is committed or rolled
+    /// back. This action can only be called before the microVM has booted.
+    BeginTransaction,
+    /// Apply the configuration changes buffered since the transaction began, reverting all of
+    /// them if one fails. This action can only be called before the microVM has booted.
+    CommitTransaction,
     /// Configure the boot source of the microVM using as input the `ConfigureBootSource`. This
     /// action can only be called before the microVM has booted.
     ConfigureBootSource(BootSourceConfig),
@@ -57,18 +84,42 @@ pub enum VmmAction {
     GetBalloonConfig,
     /// Get the ballon device latest statistics.
     GetBalloonStats,
+    /// Get the microVM configuration, in a form which can be restored with `SetConfigSnapshot`.
+    GetConfigSnapshot,
     /// Get complete microVM configuration in JSON format.
     GetFullVmConfig,
+    /// Get the pages mapped by the guest page tables located by the given CR3 and CR4 values.
+    /// This action can only be called after the microVM has booted.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    GetGuestMappings(PageTableRoot),
     /// Get MMDS contents.
     GetMMDS,
     /// Get the machine configuration of the microVM.
     GetVmMachineConfig,
+    /// Get the KVM clock of the microVM. This action can only be called after the microVM has
+    /// booted.
+    #[cfg(target_arch = "x86_64")]
+    GetVmClock,
+    /// Drain the VMM trace log, returning at most the given number of most recent entries.
+    #[cfg(feature = "debug-api")]
+    GetTraceLog(u32),
+    /// Get the hardware performance counters of the VMM process.
+    #[cfg(feature = "perf-counters")]
+    GetResourceUsage,
+    /// Get the architectural state of the given vCPU, which must be paused. This action can only
+    /// be called after the microVM has booted.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    GetVcpuState(u8),
     /// Get microVM instance information.
     GetVmInstanceInfo,
     /// Get microVM version.
     GetVmmVersion,
     /// Flush the metrics. This action can only be called after the logger has been configured.
     FlushMetrics,
+    /// Inject an interrupt into a vCPU using the `InjectInterruptConfig` as input. This action
+    /// can only be called after the microVM has booted.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    InjectInterrupt(InjectInterruptConfig),
     /// Add a new block device or update one that already exists using the `BlockDeviceConfig` as
     /// input. This action can only be called before the microVM has booted.
     InsertBlockDevice(BlockDeviceConfig),
@@ -76,6 +127,9 @@ pub enum VmmAction {
     /// `NetworkInterfaceConfig` as input. This action can only be called before the microVM has
     /// booted.
     InsertNetworkDevice(NetworkInterfaceConfig),
+    /// Add a new pmem device or update one that already exists using the `PmemConfig` as input.
+    /// This action can only be called before the microVM has booted.
+    InsertPmemDevice(PmemConfig),
     /// Load the microVM state using as input the `LoadSnapshotParams`. This action can only be
     /// called before the microVM has booted. If this action is successful, the loaded microVM will
     /// be in `Paused` state. Should change this state to `Resumed` for the microVM to run.
@@ -84,16 +138,32 @@ pub enum VmmAction {
     PatchMMDS(Value),
     /// Pause the guest, by pausing the microVM VCPUs.
     Pause,
+    /// Pause a single vCPU of the guest, leaving the others running.
+    #[cfg(feature = "debug-api")]
+    PauseVcpu(u8),
     /// Repopulate the MMDS contents.
     PutMMDS(Value),
     /// Configure the guest vCPU features.
     PutCpuConfiguration(CustomCpuTemplate),
     /// Resume the guest, by resuming the microVM VCPUs.
     Resume,
+    /// Resume a single paused vCPU of the guest.
+    #[cfg(feature = "debug-api")]
+    ResumeVcpu(u8),
+    /// Discard the configuration changes buffered since the transaction began. This action can
+    /// only be called before the microVM has booted.
+    RollbackTransaction,
     /// Set the balloon device or update the one that already exists using the
     /// `BalloonDeviceConfig` as input. This action can only be called before the microVM
     /// has booted.
     SetBalloonDevice(BalloonDeviceConfig),
+    /// Replace the breakpoints of all vCPUs with the given ones. This action can only be called
+    /// after the microVM has booted.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    SetBreakpoints(Vec<BreakpointConfig>),
+    /// Replace the microVM configuration with the one saved by `GetConfigSnapshot`. This action
+    /// can only be called before the microVM has booted.
+    SetConfigSnapshot(Box<ConfigSnapshot>),
     /// Set the MMDS configuration.
     SetMmdsConfiguration(MmdsConfig),
     /// Set the vsock device or update the one that already exists using the
@@ -103,6 +173,21 @@ pub enum VmmAction {
     /// Set the entropy device using `EntropyDeviceConfig` as input. This action can only be called
     /// before the microVM has booted.
     SetEntropyDevice(EntropyDeviceConfig),
+    /// Overwrite the architectural state of the given vCPU, which must be paused. This action can
+    /// only be called after the microVM has booted.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    SetVcpuState(u8, Box<VcpuStateSnapshot>),
+    /// Configure the PMU exposed to the guest by the given vCPU using the `VcpuPmuConfig` as
+    /// input. This action can only be called before the microVM has booted.
+    #[cfg(target_arch = "x86_64")]
+    SetVcpuPmu(u8, VcpuPmuConfig),
+    /// Set the KVM clock of the microVM using the `VmClockConfig` as input. This action can only
+    /// be called after the microVM has booted.
+    #[cfg(target_arch = "x86_64")]
+    SetVmClock(VmClockConfig),
+    /// Set the generation ID exposed by the VMGenID device using the `VmGenIdConfig` as input.
+    /// This action can only be called after the microVM has booted.
+    SetVmGenId(VmGenIdConfig),
     /// Launch the microVM. This action can only be called before the microVM has booted.
     StartMicroVm,
     /// Send CTRL+ALT+DEL to the microVM, using the i8042 keyboard function. If an AT-keyboard
@@ -130,6 +215,15 @@ pub enum VmmActionError {
     BalloonConfig(#[from] BalloonConfigError),
     /// Boot source error: {0}
     BootSource(#[from] BootSourceConfigError),
+    /// Breakpoints error: {0}
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    Breakpoints(#[from] BreakpointsError),
+    /// Cgroup error: {0}
+    Cgroup(#[from] CgroupError),
+    /// Config snapshot error: {0}
+    ConfigSnapshot(ResourcesError),
+    /// Config snapshot error: {0}. Reverting the configuration failed too: {1}
+    ConfigSnapshotRevert(ResourcesError, Box<ResourcesError>),
     /// Create snapshot error: {0}
     CreateSnapshot(#[from] CreateSnapshotError),
     /// Configure CPU error: {0}
@@ -138,6 +232,9 @@ pub enum VmmActionError {
     DriveConfig(#[from] DriveError),
     /// Entropy device error: {0}
     EntropyDevice(#[from] EntropyDeviceError),
+    /// Inject interrupt error: {0}
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    InjectInterrupt(#[from] InjectInterruptError),
     /// Internal VMM error: {0}
     InternalVmm(#[from] VmmError),
     /// Load snapshot error: {0}
@@ -158,14 +255,32 @@ pub enum VmmActionError {
     MmdsLimitExceeded(data_store::MmdsDatastoreError),
     /// Network config error: {0}
     NetworkConfig(#[from] NetworkInterfaceError),
+    /// Pmem config error: {0}
+    PmemConfig(#[from] PmemConfigError),
     /// The requested operation is not supported: {0}
     NotSupported(String),
     /// The requested operation is not supported after starting the microVM.
     OperationNotSupportedPostBoot,
     /// The requested operation is not supported before starting the microVM.
     OperationNotSupportedPreBoot,
+    /// Page table walk error: {0}
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    PageTableWalk(#[from] PageTableWalkError),
+    /// Performance counters error: {0}
+    #[cfg(feature = "perf-counters")]
+    PerfCounters(#[from] PerfCountersError),
+    /// Single vCPU error: {0}
+    #[cfg(feature = "debug-api")]
+    SingleVcpu(#[from] SingleVcpuError),
     /// Start microvm error: {0}
     StartMicrovm(#[from] StartMicrovmError),
+    /// Transaction error: {0}
+    Transaction(#[from] TransactionError),
+    /// vCPU PMU config error: {0}
+    #[cfg(target_arch = "x86_64")]
+    VcpuPmuConfig(#[from] VcpuPmuConfigError),
+    /// VM generation ID error: {0}
+    VmGenId(#[from] VmGenIdConfigError),
     /// Vsock config error: {0}
     VsockConfig(#[from] VsockConfigError),
 }
@@ -179,16 +294,33 @@ pub enum VmmData {
     BalloonConfig(BalloonDeviceConfig),
     /// The latest balloon device statistics.
     BalloonStats(BalloonStats),
+    /// The microVM configuration, which can be restored.
+    ConfigSnapshot(ConfigSnapshot),
     /// No data is sent on the channel.
     Empty,
     /// The complete microVM configuration in JSON format.
     FullVmConfig(VmmConfig),
+    /// The pages mapped by the guest page tables.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    GuestMappings(Vec<GuestMapping>),
     /// The microVM configuration represented by `VmConfig`.
     MachineConfiguration(MachineConfig),
     /// Mmds contents.
     MmdsValue(serde_json::Value),
     /// The microVM instance information.
     InstanceInformation(InstanceInfo),
+    /// The hardware performance counters of the VMM process.
+    #[cfg(feature = "perf-counters")]
+    ResourceUsage(VmmPerfCounters),
+    /// Entries drained from the VMM trace log.
+    #[cfg(feature = "debug-api")]
+    TraceLog(Vec<TraceEntry>),
+    /// The architectural state of a vCPU.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    VcpuState(Box<VcpuStateSnapshot>),
+    /// The KVM clock of the microVM.
+    #[cfg(target_arch = "x86_64")]
+    VmClock(VmClockConfig),
     /// The microVM version.
     VmmVersion(String),
 }
@@ -246,6 +378,8 @@ pub struct PrebootApiController<'a> {
     // Some PrebootApiRequest errors are irrecoverable and Firecracker
     // should cleanly teardown if they occur.
     fatal_error: Option<BuildMicrovmFromRequestsError>,
+    // Configuration changes buffered by the transaction in progress, if any.
+    transaction: Option<Vec<VmmAction>>,
 }
 
 // TODO Remove when `EventManager` implements `std::fmt::Debug`.
@@ -259,6 +393,7 @@ impl fmt::Debug for PrebootApiController<'_> {
             .field("built_vmm", &self.built_vmm)
             .field("boot_path", &self.boot_path)
             .field("fatal_error", &self.fatal_error)
+            .field("transaction", &self.transaction)
             .finish()
     }
 }
@@ -280,6 +415,54 @@ pub enum LoadSnapshotError {
     ResumeMicrovm(#[from] VmmError),
 }
 
+/// Errors associated with configuration transactions.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum TransactionError {
+    /// A transaction is already in progress.
+    AlreadyStarted,
+    /// No transaction is in progress.
+    NotStarted,
+    /// The operation is not allowed while a transaction is in progress.
+    InProgress,
+    /// Step {0} of the transaction failed, the configuration was reverted: {1}
+    Failed(usize, Box<VmmActionError>),
+    /// Step {0} of the transaction failed: {1}. Reverting the configuration failed too: {2}
+    RevertFailed(usize, Box<VmmActionError>, ResourcesError),
+}
+
+impl VmmAction {
+    // Configuration changes which are buffered while a transaction is in progress.
+    fn is_config_change(&self) -> bool {
+        use self::VmmAction::*;
+
+        match self {
+            ConfigureBootSource(_)
+            | InsertBlockDevice(_)
+            | InsertNetworkDevice(_)
+            | InsertPmemDevice(_)
+            | PutCpuConfiguration(_)
+            | SetBalloonDevice(_)
+            | SetConfigSnapshot(_)
+            | SetEntropyDevice(_)
+            | SetMmdsConfiguration(_)
+            | SetVsockDevice(_)
+            | UpdateMachineConfiguration(_) => true,
+            #[cfg(target_arch = "x86_64")]
+            SetVcpuPmu(..) => true,
+            _ => false,
+        }
+    }
+}
+
+// Configuration of the microVM saved before a change which may have to be reverted.
+#[derive(Debug)]
+struct SavedConfig {
+    config: VmmConfig,
+    cpu_template: Option<CpuTemplateType>,
+    #[cfg(target_arch = "x86_64")]
+    pmu: PmuConfig,
+}
+
 /// Shorthand type for a request containing a boxed VmmAction.
 pub type ApiRequest = Box<VmmAction>;
 /// Shorthand type for a response containing a boxed Result.
@@ -312,6 +495,7 @@ impl<'a> PrebootApiController<'a> {
             built_vmm: None,
             boot_path: false,
             fatal_error: None,
+            transaction: None,
         }
     }
 
@@ -396,8 +580,22 @@ impl<'a> PrebootApiController<'a> {
     ) -> Result<VmmData, VmmActionError> {
         use self::VmmAction::*;
 
+        crate::ftrace!("handle_preboot_request");
+        if let Some(actions) = self.transaction.as_mut() {
+            if request.is_config_change() {
+                actions.push(request);
+                return Ok(VmmData::Empty);
+            }
+            if matches!(request, LoadSnapshot(_) | StartMicroVm) {
+                return Err(TransactionError::InProgress.into());
+            }
+        }
+
         match request {
             // Supported operations allowed pre-boot.
+            BeginTransaction => self.begin_transaction(),
+            CommitTransaction => self.commit_transaction(),
+            RollbackTransaction => self.rollback_transaction(),
             ConfigureBootSource(config) => self.set_boot_source(config),
             ConfigureLogger(logger_cfg) => crate::logger::LOGGER
                 .update(logger_cfg)
@@ -407,6 +605,9 @@ impl<'a> PrebootApiController<'a> {
                 .map(|()| VmmData::Empty)
                 .map_err(VmmActionError::Metrics),
             GetBalloonConfig => self.balloon_config(),
+            GetConfigSnapshot => Ok(VmmData::ConfigSnapshot(ConfigSnapshot {
+                snapshot: (&*self.vm_resources).into(),
+            })),
             GetFullVmConfig => {
                 warn!(
                     "If the VM was restored from snapshot, boot-source, machine-config.smt, and \
@@ -419,9 +620,14 @@ impl<'a> PrebootApiController<'a> {
                 self.vm_resources.machine_config.clone(),
             )),
             GetVmInstanceInfo => Ok(VmmData::InstanceInformation(self.instance_info.clone())),
+            #[cfg(feature = "debug-api")]
+            GetTraceLog(last_n) => Ok(VmmData::TraceLog(TRACE_LOG.drain(last_n))),
+            #[cfg(feature = "perf-counters")]
+            GetResourceUsage => Ok(VmmData::ResourceUsage(read_perf_counters()?)),
             GetVmmVersion => Ok(VmmData::VmmVersion(self.instance_info.vmm_version.clone())),
             InsertBlockDevice(config) => self.insert_block_device(config),
             InsertNetworkDevice(config) => self.insert_net_device(config),
+            InsertPmemDevice(config) => self.insert_pmem_device(config),
             LoadSnapshot(config) => self
                 .load_snapshot(&config)
                 .map_err(VmmActionError::LoadSnapshot),
@@ -431,11 +637,14 @@ impl<'a> PrebootApiController<'a> {
             }
             PutMMDS(value) => self.put_mmds(value),
             SetBalloonDevice(config) => self.set_balloon_device(config),
+            SetConfigSnapshot(snapshot) => self.set_config_snapshot(*snapshot),
             SetVsockDevice(config) => self.set_vsock_device(config),
             SetMmdsConfiguration(config) => self.set_mmds_config(config),
             StartMicroVm => self.start_microvm(),
             UpdateMachineConfiguration(config) => self.update_machine_config(config),
             SetEntropyDevice(config) => self.set_entropy_device(config),
+            #[cfg(target_arch = "x86_64")]
+            SetVcpuPmu(index, config) => self.set_vcpu_pmu(index, config),
             // Operations not allowed pre-boot.
             CreateSnapshot(_)
             | FlushMetrics
@@ -444,11 +653,96 @@ impl<'a> PrebootApiController<'a> {
             | GetBalloonStats
             | UpdateBalloon(_)
             | UpdateBalloonStatistics(_)
+            | SetVmGenId(_)
             | UpdateBlockDevice(_)
             | UpdateNetworkInterface(_) => Err(VmmActionError::OperationNotSupportedPreBoot),
             #[cfg(target_arch = "x86_64")]
-            SendCtrlAltDel => Err(VmmActionError::OperationNotSupportedPreBoot),
+            SendCtrlAltDel | GetVmClock | SetVmClock(_) => {
+                Err(VmmActionError::OperationNotSupportedPreBoot)
+            }
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            GetGuestMappings(_) | InjectInterrupt(_) => {
+                Err(VmmActionError::OperationNotSupportedPreBoot)
+            }
+            #[cfg(feature = "debug-api")]
+            PauseVcpu(_) | ResumeVcpu(_) => Err(VmmActionError::OperationNotSupportedPreBoot),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            GetVcpuState(_) | SetVcpuState(..) => Err(VmmActionError::OperationNotSupportedPreBoot),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            SetBreakpoints(_) => Err(VmmActionError::OperationNotSupportedPreBoot),
+        }
+    }
+
+    fn begin_transaction(&mut self) -> Result<VmmData, VmmActionError> {
+        if self.transaction.is_some() {
+            return Err(TransactionError::AlreadyStarted.into());
+        }
+        self.transaction = Some(Vec::new());
+        Ok(VmmData::Empty)
+    }
+
+    fn commit_transaction(&mut self) -> Result<VmmData, VmmActionError> {
+        let actions = self
+            .transaction
+            .take()
+            .ok_or(TransactionError::NotStarted)?;
+        let saved_config = self.save_config();
+
+        for (step, action) in actions.into_iter().enumerate() {
+            if let Err(err) = self.handle_preboot_request(action) {
+                let err = match self.restore_config(saved_config) {
+                    Ok(()) => TransactionError::Failed(step, Box::new(err)),
+                    Err(revert_err) => {
+                        TransactionError::RevertFailed(step, Box::new(err), revert_err)
+                    }
+                };
+                return Err(err.into());
+            }
+        }
+        Ok(VmmData::Empty)
+    }
+
+    fn save_config(&self) -> SavedConfig {
+        SavedConfig {
+            config: VmmConfig::from(&*self.vm_resources),
+            cpu_template: self.vm_resources.machine_config.cpu_template.clone(),
+            #[cfg(target_arch = "x86_64")]
+            pmu: self.vm_resources.pmu.clone(),
+        }
+    }
+
+    fn restore_config(&mut self, saved: SavedConfig) -> Result<(), ResourcesError> {
+        let restored = self
+            .vm_resources
+            .replace_config(saved.config, &self.instance_info.id);
+        self.vm_resources.machine_config.cpu_template = saved.cpu_template;
+        #[cfg(target_arch = "x86_64")]
+        {
+            self.vm_resources.pmu = saved.pmu;
         }
+        restored
+    }
+
+    fn set_config_snapshot(&mut self, snapshot: ConfigSnapshot) -> Result<VmmData, VmmActionError> {
+        self.boot_path = true;
+        let saved_config = self.save_config();
+        if let Err(err) = self
+            .vm_resources
+            .replace_config(snapshot.snapshot, &self.instance_info.id)
+        {
+            return Err(match self.restore_config(saved_config) {
+                Ok(()) => VmmActionError::ConfigSnapshot(err),
+                Err(revert_err) => VmmActionError::ConfigSnapshotRevert(err, Box::new(revert_err)),
+            });
+        }
+        Ok(VmmData::Empty)
+    }
+
+    fn rollback_transaction(&mut self) -> Result<VmmData, VmmActionError> {
+        self.transaction
+            .take()
+            .ok_or(TransactionError::NotStarted)?;
+        Ok(VmmData::Empty)
     }
 
     fn balloon_config(&mut self) -> Result<VmmData, VmmActionError> {
@@ -478,6 +772,14 @@ impl<'a> PrebootApiController<'a> {
             .map_err(VmmActionError::NetworkConfig)
     }
 
+    fn insert_pmem_device(&mut self, cfg: PmemConfig) -> Result<VmmData, VmmActionError> {
+        self.boot_path = true;
+        self.vm_resources
+            .build_pmem_device(cfg)
+            .map(|()| VmmData::Empty)
+            .map_err(VmmActionError::PmemConfig)
+    }
+
     fn set_balloon_device(&mut self, cfg: BalloonDeviceConfig) -> Result<VmmData, VmmActionError> {
         self.boot_path = true;
         self.vm_resources
@@ -535,6 +837,13 @@ impl<'a> PrebootApiController<'a> {
         Ok(VmmData::Empty)
     }
 
+    #[cfg(target_arch = "x86_64")]
+    fn set_vcpu_pmu(&mut self, index: u8, cfg: VcpuPmuConfig) -> Result<VmmData, VmmActionError> {
+        self.boot_path = true;
+        self.vm_resources.set_vcpu_pmu(index, cfg)?;
+        Ok(VmmData::Empty)
+    }
+
     // On success, this command will end the pre-boot stage and this controller
     // will be replaced by a runtime controller.
     fn start_microvm(&mut self) -> Result<VmmData, VmmActionError> {
@@ -624,6 +933,8 @@ impl RuntimeApiController {
     /// Handles the incoming runtime `VmmAction` request and provides a response for it.
     pub fn handle_request(&mut self, request: VmmAction) -> Result<VmmData, VmmActionError> {
         use self::VmmAction::*;
+
+        crate::ftrace!("handle_request");
         match request {
             // Supported operations allowed post-boot.
             CreateSnapshot(snapshot_create_cfg) => self.create_snapshot(&snapshot_create_cfg),
@@ -642,6 +953,9 @@ impl RuntimeApiController {
                 .latest_balloon_stats()
                 .map(VmmData::BalloonStats)
                 .map_err(|err| VmmActionError::BalloonConfig(BalloonConfigError::from(err))),
+            GetConfigSnapshot => Ok(VmmData::ConfigSnapshot(ConfigSnapshot {
+                snapshot: (&self.vm_resources).into(),
+            })),
             GetFullVmConfig => Ok(VmmData::FullVmConfig((&self.vm_resources).into())),
             GetMMDS => self.get_mmds(),
             GetVmMachineConfig => Ok(VmmData::MachineConfiguration(
@@ -650,13 +964,90 @@ impl RuntimeApiController {
             GetVmInstanceInfo => Ok(VmmData::InstanceInformation(
                 self.vmm.lock().expect("Poisoned lock").instance_info(),
             )),
+            #[cfg(feature = "debug-api")]
+            GetTraceLog(last_n) => Ok(VmmData::TraceLog(TRACE_LOG.drain(last_n))),
+            #[cfg(feature = "perf-counters")]
+            GetResourceUsage => Ok(VmmData::ResourceUsage(read_perf_counters()?)),
             GetVmmVersion => Ok(VmmData::VmmVersion(
                 self.vmm.lock().expect("Poisoned lock").version(),
             )),
             PatchMMDS(value) => self.patch_mmds(value),
             Pause => self.pause(),
+            #[cfg(feature = "debug-api")]
+            PauseVcpu(index) => self
+                .vmm
+                .lock()
+                .expect("Poisoned lock")
+                .pause_vcpu(index)
+                .map(|()| VmmData::Empty)
+                .map_err(VmmActionError::SingleVcpu),
             PutMMDS(value) => self.put_mmds(value),
             Resume => self.resume(),
+            #[cfg(feature = "debug-api")]
+            ResumeVcpu(index) => self
+                .vmm
+                .lock()
+                .expect("Poisoned lock")
+                .resume_vcpu(index)
+                .map(|()| VmmData::Empty)
+                .map_err(VmmActionError::SingleVcpu),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            InjectInterrupt(config) => self
+                .vmm
+                .lock()
+                .expect("Poisoned lock")
+                .inject_interrupt(config)
+                .map(|()| VmmData::Empty)
+                .map_err(VmmActionError::InjectInterrupt),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            GetGuestMappings(root) => self
+                .vmm
+                .lock()
+                .expect("Poisoned lock")
+                .guest_mappings(root)
+                .map(VmmData::GuestMappings)
+                .map_err(VmmActionError::PageTableWalk),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            GetVcpuState(index) => self
+                .vmm
+                .lock()
+                .expect("Poisoned lock")
+                .vcpu_state(index)
+                .map(|snapshot| VmmData::VcpuState(Box::new(snapshot)))
+                .map_err(VmmActionError::SingleVcpu),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            SetVcpuState(index, snapshot) => self
+                .vmm
+                .lock()
+                .expect("Poisoned lock")
+                .restore_vcpu_state(index, *snapshot)
+                .map(|()| VmmData::Empty)
+                .map_err(VmmActionError::SingleVcpu),
+            #[cfg(target_arch = "x86_64")]
+            GetVmClock => self
+                .vmm
+                .lock()
+                .expect("Poisoned lock")
+                .vm_clock()
+                .map(VmmData::VmClock)
+                .map_err(VmmActionError::InternalVmm),
+            #[cfg(target_arch = "x86_64")]
+            SetVmClock(clock) => self
+                .vmm
+                .lock()
+                .expect("Poisoned lock")
+                .set_vm_clock(clock)
+                .map(|()| VmmData::Empty)
+                .map_err(VmmActionError::InternalVmm),
+            SetVmGenId(config) => self.set_vm_gen_id(&config),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            SetBreakpoints(breakpoints) => self
+                .vmm
+                .lock()
+                .expect("Poisoned lock")
+                .set_breakpoints(&breakpoints)
+                .map(|()| VmmData::Empty)
+                .map_err(VmmActionError::Breakpoints),
             #[cfg(target_arch = "x86_64")]
             SendCtrlAltDel => self.send_ctrl_alt_del(),
             UpdateBalloon(balloon_update) => self
@@ -675,21 +1066,31 @@ impl RuntimeApiController {
                 .map_err(|err| VmmActionError::BalloonConfig(BalloonConfigError::from(err))),
             UpdateBlockDevice(new_cfg) => self.update_block_device(new_cfg),
             UpdateNetworkInterface(netif_update) => self.update_net_rate_limiters(netif_update),
+            UpdateMachineConfiguration(update) if update.is_cgroup_only() => {
+                self.update_cgroup_limits(update)
+            }
 
             // Operations not allowed post-boot.
-            ConfigureBootSource(_)
+            BeginTransaction
+            | CommitTransaction
+            | RollbackTransaction
+            | ConfigureBootSource(_)
             | ConfigureLogger(_)
             | ConfigureMetrics(_)
             | InsertBlockDevice(_)
             | InsertNetworkDevice(_)
+            | InsertPmemDevice(_)
             | LoadSnapshot(_)
             | PutCpuConfiguration(_)
             | SetBalloonDevice(_)
+            | SetConfigSnapshot(_)
             | SetVsockDevice(_)
             | SetMmdsConfiguration(_)
             | SetEntropyDevice(_)
             | StartMicroVm
             | UpdateMachineConfiguration(_) => Err(VmmActionError::OperationNotSupportedPostBoot),
+            #[cfg(target_arch = "x86_64")]
+            SetVcpuPmu(..) => Err(VmmActionError::OperationNotSupportedPostBoot),
         }
     }
 
@@ -764,6 +1165,14 @@ impl RuntimeApiController {
             ));
         }
 
+        // The state of plugin devices is opaque to Firecracker.
+        #[cfg(feature = "plugins")]
+        if !self.vm_resources.plugins.is_empty() {
+            return Err(VmmActionError::NotSupported(
+                "Snapshots are not allowed on uVMs with plugin devices.".to_string(),
+            ));
+        }
+
         let mut locked_vmm = self.vmm.lock().unwrap();
         let vm_info = VmInfo::from(&self.vm_resources);
         let create_start_us = get_time_us(ClockType::Monotonic);
@@ -846,16 +1255,40 @@ impl RuntimeApiController {
             .map_err(NetworkInterfaceError::DeviceUpdate)
             .map_err(VmmActionError::NetworkConfig)
     }
+
+    /// Updates the cgroup resource controls of the running microVM.
+    fn update_cgroup_limits(
+        &mut self,
+        update: MachineConfigUpdate,
+    ) -> Result<VmmData, VmmActionError> {
+        let machine_config = self.vm_resources.machine_config.update(&update)?;
+        apply_cgroup_limits(&machine_config, &self.vm_resources.block_device_paths())?;
+        self.vm_resources.machine_config = machine_config;
+        Ok(VmmData::Empty)
+    }
+
+    /// Sets the generation ID exposed to the guest by the VMGenID device.
+    fn set_vm_gen_id(&mut self, config: &VmGenIdConfig) -> Result<VmmData, VmmActionError> {
+        let gen_id = config.gen_id()?;
+        self.vmm
+            .lock()
+            .expect("Poisoned lock")
+            .set_vm_gen_id(gen_id)?;
+        Ok(VmmData::Empty)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
+    use vmm_sys_util::tempfile::TempFile;
+
     use super::*;
     use crate::HTTP_MAX_PAYLOAD_SIZE;
     use crate::builder::tests::default_vmm;
     use crate::devices::virtio::block::CacheType;
+    use crate::logger::MetricsFormat;
     use crate::mmds::data_store::MmdsVersion;
     use crate::seccomp::BpfThreadMap;
     use crate::vmm_config::snapshot::{MemBackendConfig, MemBackendType};
@@ -1143,6 +1576,9 @@ mod tests {
                 tx_rate_limiter: None,
             },
         )));
+        check_unsupported(preboot_request(VmmAction::SetVmGenId(VmGenIdConfig {
+            uuid: String::new(),
+        })));
         check_unsupported(preboot_request(VmmAction::CreateSnapshot(
             CreateSnapshotParams {
                 snapshot_type: SnapshotType::Full,
@@ -1150,8 +1586,170 @@ mod tests {
                 mem_file_path: PathBuf::new(),
             },
         )));
+        #[cfg(feature = "debug-api")]
+        check_unsupported(preboot_request(VmmAction::PauseVcpu(0)));
+        #[cfg(feature = "debug-api")]
+        check_unsupported(preboot_request(VmmAction::ResumeVcpu(0)));
         #[cfg(target_arch = "x86_64")]
         check_unsupported(preboot_request(VmmAction::SendCtrlAltDel));
+        #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+        check_unsupported(preboot_request(VmmAction::GetVcpuState(0)));
+        #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+        check_unsupported(preboot_request(VmmAction::GetGuestMappings(
+            PageTableRoot { cr3: 0, cr4: 0 },
+        )));
+        #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+        check_unsupported(preboot_request(VmmAction::InjectInterrupt(
+            InjectInterruptConfig {
+                vcpu_id: 0,
+                vector: 0x50,
+                interrupt_type: crate::vmm_config::interrupt::InterruptType::Fixed,
+                level: false,
+            },
+        )));
+        #[cfg(target_arch = "x86_64")]
+        check_unsupported(preboot_request(VmmAction::GetVmClock));
+        #[cfg(target_arch = "x86_64")]
+        check_unsupported(preboot_request(VmmAction::SetVmClock(VmClockConfig {
+            realtime_ns: 0,
+            host_ns: 0,
+        })));
+        #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+        check_unsupported(preboot_request(VmmAction::SetBreakpoints(vec![])));
+    }
+
+    #[test]
+    fn test_preboot_transaction() {
+        let mut vm_resources = VmResources::default();
+        let mut evmgr = EventManager::new().unwrap();
+        let seccomp_filters = BpfThreadMap::new();
+        let mut preboot = default_preboot(&mut vm_resources, &mut evmgr, &seccomp_filters);
+        let vcpu_count = |vcpu_count| {
+            VmmAction::UpdateMachineConfiguration(MachineConfigUpdate {
+                vcpu_count: Some(vcpu_count),
+                ..Default::default()
+            })
+        };
+
+        assert!(matches!(
+            preboot.handle_preboot_request(VmmAction::CommitTransaction),
+            Err(VmmActionError::Transaction(TransactionError::NotStarted))
+        ));
+        assert!(matches!(
+            preboot.handle_preboot_request(VmmAction::RollbackTransaction),
+            Err(VmmActionError::Transaction(TransactionError::NotStarted))
+        ));
+
+        // The changes are only applied once the transaction is committed.
+        preboot
+            .handle_preboot_request(VmmAction::BeginTransaction)
+            .unwrap();
+        assert!(matches!(
+            preboot.handle_preboot_request(VmmAction::BeginTransaction),
+            Err(VmmActionError::Transaction(
+                TransactionError::AlreadyStarted
+            ))
+        ));
+        preboot.handle_preboot_request(vcpu_count(2)).unwrap();
+        assert_eq!(preboot.vm_resources.machine_config.vcpu_count, 1);
+        assert!(matches!(
+            preboot.handle_preboot_request(VmmAction::StartMicroVm),
+            Err(VmmActionError::Transaction(TransactionError::InProgress))
+        ));
+        preboot
+            .handle_preboot_request(VmmAction::CommitTransaction)
+            .unwrap();
+        assert_eq!(preboot.vm_resources.machine_config.vcpu_count, 2);
+
+        // A rolled back transaction leaves the configuration untouched.
+        preboot
+            .handle_preboot_request(VmmAction::BeginTransaction)
+            .unwrap();
+        preboot.handle_preboot_request(vcpu_count(4)).unwrap();
+        preboot
+            .handle_preboot_request(VmmAction::RollbackTransaction)
+            .unwrap();
+        assert_eq!(preboot.vm_resources.machine_config.vcpu_count, 2);
+
+        // A failing change reverts the changes which preceded it.
+        preboot
+            .handle_preboot_request(VmmAction::BeginTransaction)
+            .unwrap();
+        preboot.handle_preboot_request(vcpu_count(4)).unwrap();
+        preboot.handle_preboot_request(vcpu_count(0)).unwrap();
+        assert!(matches!(
+            preboot.handle_preboot_request(VmmAction::CommitTransaction),
+            Err(VmmActionError::Transaction(TransactionError::Failed(1, _)))
+        ));
+        assert_eq!(preboot.vm_resources.machine_config.vcpu_count, 2);
+        assert!(preboot.transaction.is_none());
+
+        // A configuration which cannot be reverted, e.g. because its kernel image was removed in
+        // the meantime, is reported.
+        let kernel_file = TempFile::new().unwrap();
+        preboot
+            .handle_preboot_request(VmmAction::ConfigureBootSource(BootSourceConfig {
+                kernel_image_path: kernel_file.as_path().to_str().unwrap().to_string(),
+                ..Default::default()
+            }))
+            .unwrap();
+        preboot
+            .handle_preboot_request(VmmAction::BeginTransaction)
+            .unwrap();
+        preboot.handle_preboot_request(vcpu_count(0)).unwrap();
+        drop(kernel_file);
+        assert!(matches!(
+            preboot.handle_preboot_request(VmmAction::CommitTransaction),
+            Err(VmmActionError::Transaction(TransactionError::RevertFailed(
+                0,
+                _,
+                ResourcesError::BootSource(BootSourceConfigError::InvalidKernelPath(_))
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_preboot_config_snapshot() {
+        let mut vm_resources = VmResources::default();
+        let mut evmgr = EventManager::new().unwrap();
+        let seccomp_filters = BpfThreadMap::new();
+        let mut preboot = default_preboot(&mut vm_resources, &mut evmgr, &seccomp_filters);
+        let vcpu_count = |vcpu_count| {
+            VmmAction::UpdateMachineConfiguration(MachineConfigUpdate {
+                vcpu_count: Some(vcpu_count),
+                ..Default::default()
+            })
+        };
+
+        preboot.handle_preboot_request(vcpu_count(2)).unwrap();
+        let VmmData::ConfigSnapshot(snapshot) = preboot
+            .handle_preboot_request(VmmAction::GetConfigSnapshot)
+            .unwrap()
+        else {
+            panic!("Unexpected response");
+        };
+        preboot.handle_preboot_request(vcpu_count(4)).unwrap();
+        preboot
+            .handle_preboot_request(VmmAction::SetConfigSnapshot(Box::new(snapshot)))
+            .unwrap();
+        assert_eq!(preboot.vm_resources.machine_config.vcpu_count, 2);
+
+        // A configuration which cannot be applied leaves the current one untouched.
+        let snapshot: ConfigSnapshot = serde_json::from_str(
+            r#"{
+                "snapshot": {
+                    "boot-source": { "kernel_image_path": "/invalid/path" },
+                    "drives": [],
+                    "machine-config": { "vcpu_count": 4, "mem_size_mib": 128 }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            preboot.handle_preboot_request(VmmAction::SetConfigSnapshot(Box::new(snapshot))),
+            Err(VmmActionError::ConfigSnapshot(_))
+        ));
+        assert_eq!(preboot.vm_resources.machine_config.vcpu_count, 2);
     }
 
     fn runtime_request(request: VmmAction) -> Result<VmmData, VmmActionError> {
@@ -1181,6 +1779,103 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_runtime_update_cgroup_limits() {
+        // Without a cgroup, the resource controls cannot be applied.
+        let res = runtime_request(VmmAction::UpdateMachineConfiguration(MachineConfigUpdate {
+            cpu_weight: Some(200),
+            ..Default::default()
+        }));
+        assert!(
+            matches!(res, Err(VmmActionError::Cgroup(CgroupError::NotConfigured))),
+            "{:?}",
+            res
+        );
+
+        let res = runtime_request(VmmAction::UpdateMachineConfiguration(MachineConfigUpdate {
+            cpu_weight: Some(0),
+            ..Default::default()
+        }));
+        assert!(
+            matches!(
+                res,
+                Err(VmmActionError::MachineConfig(
+                    MachineConfigError::InvalidCpuWeight
+                ))
+            ),
+            "{:?}",
+            res
+        );
+
+        // An update without any field is rejected.
+        let res = runtime_request(VmmAction::UpdateMachineConfiguration(
+            MachineConfigUpdate::default(),
+        ));
+        assert!(
+            matches!(res, Err(VmmActionError::OperationNotSupportedPostBoot)),
+            "{:?}",
+            res
+        );
+    }
+
+    #[test]
+    fn test_runtime_set_vm_gen_id() {
+        let config = |uuid: &str| {
+            VmmAction::SetVmGenId(VmGenIdConfig {
+                uuid: uuid.to_string(),
+            })
+        };
+        let res = runtime_request(config("not-a-uuid"));
+        assert!(
+            matches!(
+                res,
+                Err(VmmActionError::VmGenId(VmGenIdConfigError::InvalidUuid(_)))
+            ),
+            "{:?}",
+            res
+        );
+        let res = runtime_request(config("1b4e28ba-2fa1-11d2-883f-0016d3cca427"));
+        assert!(
+            matches!(
+                res,
+                Err(VmmActionError::VmGenId(VmGenIdConfigError::DeviceNotFound))
+            ),
+            "{:?}",
+            res
+        );
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            use crate::builder::tests::insert_vmgenid_device;
+            use crate::vstate::memory::Bytes;
+
+            let mut vmm = default_vmm();
+            insert_vmgenid_device(&mut vmm);
+            let vmm = Arc::new(Mutex::new(vmm));
+            let mut runtime = RuntimeApiController::new(VmResources::default(), vmm.clone());
+            runtime
+                .handle_request(config("1b4e28ba-2fa1-11d2-883f-0016d3cca427"))
+                .unwrap();
+
+            let locked_vmm = vmm.lock().unwrap();
+            let vmgenid = locked_vmm.acpi_device_manager.vmgenid.as_ref().unwrap();
+            let mut gen_id = [0u8; 16];
+            locked_vmm
+                .vm
+                .guest_memory()
+                .read_slice(&mut gen_id, vmgenid.guest_address)
+                .unwrap();
+            assert_eq!(
+                gen_id,
+                [
+                    0x1b, 0x4e, 0x28, 0xba, 0x2f, 0xa1, 0x11, 0xd2, 0x88, 0x3f, 0x00, 0x16, 0xd3,
+                    0xcc, 0xa4, 0x27
+                ]
+            );
+            assert_eq!(vmgenid.gen_id, u128::from_le_bytes(gen_id));
+        }
+    }
+
     #[test]
     fn test_runtime_disallowed() {
         fn check_unsupported(res: Result<VmmData, VmmActionError>) {
@@ -1191,6 +1886,12 @@ mod tests {
             );
         }
 
+        check_unsupported(runtime_request(VmmAction::BeginTransaction));
+        check_unsupported(runtime_request(VmmAction::CommitTransaction));
+        check_unsupported(runtime_request(VmmAction::RollbackTransaction));
+        check_unsupported(runtime_request(
+            VmmAction::SetConfigSnapshot(Box::default()),
+        ));
         check_unsupported(runtime_request(VmmAction::ConfigureBootSource(
             BootSourceConfig::default(),
         )));
@@ -1200,10 +1901,12 @@ mod tests {
             show_level: Some(false),
             show_log_origin: Some(false),
             module: None,
+            json: None,
         })));
         check_unsupported(runtime_request(VmmAction::ConfigureMetrics(
             MetricsConfig {
                 metrics_path: PathBuf::new(),
+                format: MetricsFormat::Json,
             },
         )));
         check_unsupported(runtime_request(VmmAction::InsertBlockDevice(
@@ -1217,6 +1920,8 @@ mod tests {
                 path_on_host: Some(String::new()),
                 rate_limiter: None,
                 file_engine_type: None,
+                lifetime: None,
+                discard: None,
 
                 socket: None,
             },
@@ -1228,6 +1933,9 @@ mod tests {
                 guest_mac: None,
                 rx_rate_limiter: None,
                 tx_rate_limiter: None,
+                mtu: None,
+                link_speed_mbps: None,
+                duplex: None,
             },
         )));
         check_unsupported(runtime_request(VmmAction::SetVsockDevice(
@@ -1252,6 +1960,8 @@ mod tests {
                 ipv4_address: None,
                 version: MmdsVersion::default(),
                 network_interfaces: Vec::new(),
+                mmds_rate_limit_rps: None,
+                mmds_cache_ttl_ms: None,
             },
         )));
         check_unsupported(runtime_request(VmmAction::UpdateMachineConfiguration(
@@ -1272,5 +1982,13 @@ mod tests {
         check_unsupported(runtime_request(VmmAction::SetEntropyDevice(
             EntropyDeviceConfig::default(),
         )));
+        check_unsupported(runtime_request(VmmAction::InsertPmemDevice(
+            PmemConfig::default(),
+        )));
+        #[cfg(target_arch = "x86_64")]
+        check_unsupported(runtime_request(VmmAction::SetVcpuPmu(
+            0,
+            VcpuPmuConfig::default(),
+        )));
     }
 }
@@ -0,0 +1,276 @@
+// Copyright 2026 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Detects corrupted snapshot files.
+//!
+//! When enabled, creating a snapshot records the SHA-256 hashes of the microVM state file and of
+//! the guest memory file in a manifest, next to the state file, and loading a snapshot checks
+//! against the manifest exactly what it loads: the buffer the state is deserialized from and the
+//! file descriptor the guest memory is mapped from.
+
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use aws_lc_rs::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+
+/// Suffix appended to the path of the microVM state file to get the path of its manifest.
+pub const MANIFEST_SUFFIX: &str = ".sha256";
+
+// Size of the reads when hashing a file.
+const HASH_BUFFER_SIZE: usize = 1 << 20;
+
+/// Whether the Firecracker process records and checks the integrity of snapshots.
+static SNAPSHOT_INTEGRITY: AtomicBool = AtomicBool::new(false);
+
+/// Makes the Firecracker process record the integrity of the snapshots it creates and check the
+/// integrity of the snapshots it loads.
+pub fn enable_snapshot_integrity() {
+    SNAPSHOT_INTEGRITY.store(true, Ordering::Relaxed);
+}
+
+/// Returns whether the Firecracker process records and checks the integrity of snapshots.
+pub fn snapshot_integrity_enabled() -> bool {
+    SNAPSHOT_INTEGRITY.load(Ordering::Relaxed)
+}
+
+/// Errors associated with the integrity of snapshots.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum SnapshotIntegrityError {
+    /// Cannot hash {0}: {1}
+    Hash(PathBuf, io::Error),
+    /// Cannot read the integrity manifest {0}: {1}
+    ReadManifest(PathBuf, io::Error),
+    /// Cannot write the integrity manifest {0}: {1}
+    WriteManifest(PathBuf, io::Error),
+    /// Invalid integrity manifest {0}: {1}
+    ParseManifest(PathBuf, serde_json::Error),
+    /// The SHA-256 hash of {0} does not match the integrity manifest
+    Mismatch(PathBuf),
+}
+
+/// SHA-256 hashes of the files of a snapshot, hex encoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotIntegrity {
+    /// Hash of the microVM state file.
+    pub state_sha256: String,
+    /// Hash of the guest memory file.
+    pub mem_sha256: String,
+}
+
+impl SnapshotIntegrity {
+    /// Hashes the microVM state file and the guest memory file of a snapshot.
+    pub fn new(snapshot_path: &Path, mem_file_path: &Path) -> Result<Self, SnapshotIntegrityError> {
+        Ok(SnapshotIntegrity {
+            state_sha256: file_sha256(snapshot_path)?,
+            mem_sha256: file_sha256(mem_file_path)?,
+        })
+    }
+
+    /// Writes the manifest of the snapshot whose microVM state file is `snapshot_path`.
+    pub fn save(&self, snapshot_path: &Path) -> Result<(), SnapshotIntegrityError> {
+        let path = manifest_path(snapshot_path);
+        // Serializing two strings cannot fail.
+        let manifest = serde_json::to_vec(self).unwrap();
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .and_then(|mut file| {
+                file.write_all(&manifest)?;
+                file.sync_all()
+            })
+            .map_err(|err| SnapshotIntegrityError::WriteManifest(path, err))
+    }
+
+    /// Reads the manifest of the snapshot whose microVM state file is `snapshot_path`.
+    pub fn load(snapshot_path: &Path) -> Result<Self, SnapshotIntegrityError> {
+        let path = manifest_path(snapshot_path);
+        let manifest = std::fs::read(&path)
+            .map_err(|err| SnapshotIntegrityError::ReadManifest(path.clone(), err))?;
+        serde_json::from_slice(&manifest)
+            .map_err(|err| SnapshotIntegrityError::ParseManifest(path, err))
+    }
+
+    /// Checks the contents of the microVM state file, read from `snapshot_path`, against the hash
+    /// of the manifest.
+    pub fn verify_state(
+        &self,
+        snapshot_path: &Path,
+        state: &[u8],
+    ) -> Result<(), SnapshotIntegrityError> {
+        if bytes_sha256(state) != self.state_sha256 {
+            return Err(SnapshotIntegrityError::Mismatch(
+                snapshot_path.to_path_buf(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the guest memory file, opened from `mem_file_path`, against the hash of the
+    /// manifest. The file is read from its start, regardless of its offset.
+    pub fn verify_mem(
+        &self,
+        mem_file_path: &Path,
+        mem_file: &File,
+    ) -> Result<(), SnapshotIntegrityError> {
+        let hash = reader_sha256(FileReader {
+            file: mem_file,
+            offset: 0,
+        })
+        .map_err(|err| SnapshotIntegrityError::Hash(mem_file_path.to_path_buf(), err))?;
+        if hash != self.mem_sha256 {
+            return Err(SnapshotIntegrityError::Mismatch(
+                mem_file_path.to_path_buf(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Reads a file with positional reads, leaving its offset untouched.
+#[derive(Debug)]
+struct FileReader<'a> {
+    file: &'a File,
+    offset: u64,
+}
+
+impl Read for FileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.file.read_at(buf, self.offset)?;
+        self.offset += len as u64;
+        Ok(len)
+    }
+}
+
+/// Returns the path of the manifest of the snapshot whose microVM state file is `snapshot_path`.
+pub fn manifest_path(snapshot_path: &Path) -> PathBuf {
+    let mut path = OsString::from(snapshot_path);
+    path.push(MANIFEST_SUFFIX);
+    PathBuf::from(path)
+}
+
+fn file_sha256(path: &Path) -> Result<String, SnapshotIntegrityError> {
+    File::open(path)
+        .and_then(reader_sha256)
+        .map_err(|err| SnapshotIntegrityError::Hash(path.to_path_buf(), err))
+}
+
+fn reader_sha256<R: Read>(reader: R) -> io::Result<String> {
+    let mut reader = BufReader::with_capacity(HASH_BUFFER_SIZE, reader);
+    let mut context = Context::new(&SHA256);
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        context.update(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+    Ok(hex_digest(context))
+}
+
+fn bytes_sha256(bytes: &[u8]) -> String {
+    let mut context = Context::new(&SHA256);
+    context.update(bytes);
+    hex_digest(context)
+}
+
+fn hex_digest(context: Context) -> String {
+    context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+
+    #[test]
+    fn test_file_sha256() {
+        let file = TempFile::new().unwrap();
+        assert_eq!(
+            file_sha256(file.as_path()).unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        file.as_file().write_all(b"abc").unwrap();
+        assert_eq!(
+            file_sha256(file.as_path()).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(matches!(
+            file_sha256(Path::new("/invalid/path")),
+            Err(SnapshotIntegrityError::Hash(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_manifest_path() {
+        assert_eq!(
+            manifest_path(Path::new("/snapshots/vm.snap")),
+            PathBuf::from("/snapshots/vm.snap.sha256")
+        );
+    }
+
+    #[test]
+    fn test_integrity() {
+        let state_file = TempFile::new().unwrap();
+        let mem_file = TempFile::new().unwrap();
+        state_file.as_file().write_all(b"state").unwrap();
+        mem_file.as_file().write_all(&[0u8; 4096]).unwrap();
+        let state_path = state_file.as_path();
+        let mem_path = mem_file.as_path();
+
+        // Loading a snapshot without a manifest fails.
+        assert!(matches!(
+            SnapshotIntegrity::load(state_path),
+            Err(SnapshotIntegrityError::ReadManifest(_, _))
+        ));
+
+        let integrity = SnapshotIntegrity::new(state_path, mem_path).unwrap();
+        integrity.save(state_path).unwrap();
+        let manifest = SnapshotIntegrity::load(state_path).unwrap();
+        assert_eq!(manifest, integrity);
+        manifest
+            .verify_state(state_path, &std::fs::read(state_path).unwrap())
+            .unwrap();
+        // The memory file is hashed from its start, whatever its offset.
+        let mem = File::open(mem_path).unwrap();
+        (&mem).read_exact(&mut [0u8; 100]).unwrap();
+        manifest.verify_mem(mem_path, &mem).unwrap();
+        manifest.verify_mem(mem_path, &mem).unwrap();
+
+        // A corrupted memory file is detected.
+        mem_file.as_file().write_all(&[1u8]).unwrap();
+        assert!(matches!(
+            manifest.verify_mem(mem_path, &mem),
+            Err(SnapshotIntegrityError::Mismatch(path)) if path == mem_path
+        ));
+
+        // A corrupted state is detected.
+        assert!(matches!(
+            manifest.verify_state(state_path, b"corrupted"),
+            Err(SnapshotIntegrityError::Mismatch(path)) if path == state_path
+        ));
+
+        // A corrupted manifest is detected.
+        std::fs::write(manifest_path(state_path), b"{\"state_sha256\": \"\"}").unwrap();
+        assert!(matches!(
+            SnapshotIntegrity::load(state_path),
+            Err(SnapshotIntegrityError::ParseManifest(_, _))
+        ));
+        std::fs::remove_file(manifest_path(state_path)).unwrap();
+    }
+}
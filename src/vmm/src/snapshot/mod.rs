@@ -24,6 +24,7 @@
 //! The snapshot format uses a version value in the form of `MAJOR.MINOR.PATCH`. The version is
 //! provided by the library clients (it is not tied to this crate).
 pub mod crc;
+pub mod integrity;
 mod persist;
 use std::fmt::Debug;
 use std::io::{Read, Write};
@@ -58,8 +59,8 @@ const SNAPSHOT_MAGIC_ID: u64 = 0x0710_1984_AAAA_0000u64;
 pub enum SnapshotError {
     /// CRC64 validation failed: {0}
     Crc64(u64),
-    /// Invalid data version: {0}
-    InvalidFormatVersion(Version),
+    /// Invalid data version: {0}, the supported version is {1}
+    InvalidFormatVersion(Version, Version),
     /// Magic value does not match arch: {0}
     InvalidMagic(u64),
     /// Snapshot file is smaller than CRC length.
@@ -195,7 +196,10 @@ impl Snapshot {
     {
         let (data, version) = Snapshot::load::<_, O>(reader, snapshot_len)?;
         if version.major != self.version.major || version.minor > self.version.minor {
-            Err(SnapshotError::InvalidFormatVersion(version))
+            Err(SnapshotError::InvalidFormatVersion(
+                version,
+                self.version.clone(),
+            ))
         } else {
             Ok(data)
         }
@@ -340,34 +344,22 @@ mod tests {
         let snapshot = Snapshot::new(Version::new(2, 3, 12));
         assert!(matches!(
             snapshot.load_with_version_check::<_, u8>(&mut data.as_slice(), data.len()),
-            Err(SnapshotError::InvalidFormatVersion(Version {
-                major: 1,
-                minor: 3,
-                patch: 12,
-                ..
-            }))
+            Err(SnapshotError::InvalidFormatVersion(version, supported))
+                if version == Version::new(1, 3, 12) && supported == Version::new(2, 3, 12)
         ));
         let snapshot = Snapshot::new(Version::new(0, 3, 12));
         assert!(matches!(
             snapshot.load_with_version_check::<_, u8>(&mut data.as_slice(), data.len()),
-            Err(SnapshotError::InvalidFormatVersion(Version {
-                major: 1,
-                minor: 3,
-                patch: 12,
-                ..
-            }))
+            Err(SnapshotError::InvalidFormatVersion(version, supported))
+                if version == Version::new(1, 3, 12) && supported == Version::new(0, 3, 12)
         ));
 
         // We can't support minor versions bigger than ours
         let snapshot = Snapshot::new(Version::new(1, 2, 12));
         assert!(matches!(
             snapshot.load_with_version_check::<_, u8>(&mut data.as_slice(), data.len()),
-            Err(SnapshotError::InvalidFormatVersion(Version {
-                major: 1,
-                minor: 3,
-                patch: 12,
-                ..
-            }))
+            Err(SnapshotError::InvalidFormatVersion(version, supported))
+                if version == Version::new(1, 3, 12) && supported == Version::new(1, 2, 12)
         ));
 
         // But we can support minor versions smaller or equeal to ours. We also support
@@ -21,7 +21,10 @@ use serde::ser::{Serialize, Serializer};
 pub const MAC_ADDR_LEN: u8 = 6;
 
 /// Represents a MAC address
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+///
+/// MAC addresses are ordered lexicographically by their bytes, the first byte being the most
+/// significant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
 /// Representation of a MAC address.
 pub struct MacAddr {
@@ -29,6 +32,12 @@ pub struct MacAddr {
 }
 
 impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+impl fmt::LowerHex for MacAddr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let b = &self.bytes;
         write!(
@@ -39,6 +48,17 @@ impl fmt::Display for MacAddr {
     }
 }
 
+impl fmt::UpperHex for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let b = &self.bytes;
+        write!(
+            f,
+            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+            b[0], b[1], b[2], b[3], b[4], b[5]
+        )
+    }
+}
+
 impl From<[u8; 6]> for MacAddr {
     fn from(bytes: [u8; 6]) -> Self {
         Self { bytes }
@@ -98,6 +118,19 @@ impl MacAddr {
     pub fn get_bytes(&self) -> &[u8] {
         &self.bytes
     }
+
+    /// Returns whether this is the broadcast address, `ff:ff:ff:ff:ff:ff`.
+    #[inline]
+    pub fn is_broadcast(&self) -> bool {
+        self.bytes == [0xff; MAC_ADDR_LEN as usize]
+    }
+
+    /// Returns whether this is a group address, which has the least significant bit of its first
+    /// byte set. The broadcast address is a multicast address as well.
+    #[inline]
+    pub fn is_multicast(&self) -> bool {
+        self.bytes[0] & 0x01 != 0
+    }
 }
 
 impl Serialize for MacAddr {
@@ -156,4 +189,82 @@ mod tests {
         let s = serde_json::to_string(&mac).expect("MacAddr serialization failed.");
         assert_eq!(s, "\"12:34:56:78:9a:bc\"");
     }
+
+    #[test]
+    fn test_mac_addr_format() {
+        let mac = MacAddr::from_str("12:34:56:78:9a:bc").unwrap();
+        assert_eq!(format!("{}", mac), "12:34:56:78:9a:bc");
+        assert_eq!(format!("{:x}", mac), "12:34:56:78:9a:bc");
+        assert_eq!(format!("{:X}", mac), "12:34:56:78:9A:BC");
+
+        let mac = MacAddr::from_bytes_unchecked(&[0x0a, 0, 0xff, 0x01, 0xf0, 0x0f]);
+        assert_eq!(format!("{}", mac), "0a:00:ff:01:f0:0f");
+        assert_eq!(format!("{:x}", mac), "0a:00:ff:01:f0:0f");
+        assert_eq!(format!("{:X}", mac), "0A:00:FF:01:F0:0F");
+    }
+
+    #[test]
+    fn test_mac_addr_predicates() {
+        let broadcast = MacAddr::from_str("ff:ff:ff:ff:ff:ff").unwrap();
+        assert!(broadcast.is_broadcast());
+        assert!(broadcast.is_multicast());
+
+        let multicast = MacAddr::from_str("01:00:5e:00:00:01").unwrap();
+        assert!(!multicast.is_broadcast());
+        assert!(multicast.is_multicast());
+
+        let unicast = MacAddr::from_str("06:01:23:45:67:01").unwrap();
+        assert!(!unicast.is_broadcast());
+        assert!(!unicast.is_multicast());
+
+        // Only the first byte tells group addresses apart.
+        let unicast = MacAddr::from_str("fe:ff:ff:ff:ff:ff").unwrap();
+        assert!(!unicast.is_broadcast());
+        assert!(!unicast.is_multicast());
+    }
+
+    #[test]
+    fn test_mac_addr_ord() {
+        let mut macs: Vec<MacAddr> = [
+            "12:34:56:78:9a:bc",
+            "00:00:00:00:00:01",
+            "12:34:56:78:9a:bb",
+            "ff:00:00:00:00:00",
+            "00:00:00:00:01:00",
+        ]
+        .iter()
+        .map(|mac| MacAddr::from_str(mac).unwrap())
+        .collect();
+        macs.sort();
+        let sorted: Vec<String> = macs.iter().map(MacAddr::to_string).collect();
+        assert_eq!(
+            sorted,
+            [
+                "00:00:00:00:00:01",
+                "00:00:00:00:01:00",
+                "12:34:56:78:9a:bb",
+                "12:34:56:78:9a:bc",
+                "ff:00:00:00:00:00",
+            ]
+        );
+
+        // The ordering is consistent with equality.
+        let mac = MacAddr::from_str("12:34:56:78:9a:bc").unwrap();
+        assert_eq!(mac.cmp(&mac), std::cmp::Ordering::Equal);
+        assert_eq!(mac.partial_cmp(&mac), Some(std::cmp::Ordering::Equal));
+        assert!(
+            macs.windows(2)
+                .all(|pair| pair[0] < pair[1] && pair[0] != pair[1])
+        );
+
+        // Equal addresses are the same map key.
+        let mut set = std::collections::HashSet::new();
+        assert!(set.insert(mac));
+        assert!(!set.insert(MacAddr::from_bytes_unchecked(mac.get_bytes())));
+        let mut map = std::collections::BTreeMap::new();
+        map.insert(mac, 1);
+        map.insert(MacAddr::from_str("00:00:00:00:00:01").unwrap(), 2);
+        assert_eq!(map.keys().next(), macs.first());
+        assert_eq!(map[&mac], 1);
+    }
 }
@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 
 pub use crate::devices::virtio::balloon::BALLOON_DEV_ID;
 pub use crate::devices::virtio::balloon::device::BalloonStats;
+pub use crate::devices::virtio::balloon::policy::BalloonPolicy;
 use crate::devices::virtio::balloon::{Balloon, BalloonConfig};
 
 type MutexBalloon = Arc<Mutex<Balloon>>;
@@ -0,0 +1,42 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+
+use crate::SingleVcpuError;
+
+/// Number of hardware breakpoints of a vCPU, one per debug address register (DR0-DR3).
+pub const MAX_BREAKPOINTS: usize = 4;
+
+/// The kind of access that triggers a breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BreakpointType {
+    /// Executing the instruction at the address.
+    Execute,
+    /// Writing to the address.
+    Write,
+    /// Reading from or writing to the address.
+    Access,
+}
+
+/// This struct represents the strongly typed equivalent of a json breakpoint from breakpoint
+/// requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BreakpointConfig {
+    /// Guest virtual address of the breakpoint.
+    pub address: u64,
+    /// The kind of access that triggers the breakpoint.
+    #[serde(rename = "type")]
+    pub breakpoint_type: BreakpointType,
+}
+
+/// Errors associated with setting breakpoints.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum BreakpointsError {
+    /// Too many breakpoints: {0}. At most 4 breakpoints can be set.
+    TooManyBreakpoints(usize),
+    /// {0}
+    SingleVcpu(#[from] SingleVcpuError),
+}
@@ -0,0 +1,16 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+
+/// This struct represents the strongly typed equivalent of the json body from VM clock related
+/// requests. It pairs a reading of the KVM clock, from which the guest kvmclock is derived, with
+/// the host wall clock time at which it was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct VmClockConfig {
+    /// Host `CLOCK_REALTIME` time, in nanoseconds.
+    pub realtime_ns: u64,
+    /// KVM clock value at `realtime_ns`, in nanoseconds.
+    pub host_ns: u64,
+}
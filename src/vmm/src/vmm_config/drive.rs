@@ -10,7 +10,7 @@ use serde::{Deserialize, Serialize};
 use super::RateLimiterConfig;
 use crate::VmmError;
 use crate::devices::virtio::block::device::Block;
-pub use crate::devices::virtio::block::virtio::device::FileEngineType;
+pub use crate::devices::virtio::block::virtio::device::{DriveLifetime, FileEngineType};
 use crate::devices::virtio::block::{BlockError, CacheType};
 
 /// Errors associated with the operations allowed on a drive.
@@ -24,6 +24,8 @@ pub enum DriveError {
     DeviceUpdate(VmmError),
     /// A root block device already exists!
     RootBlockDeviceAlreadyAdded,
+    /// A root pmem device already exists!
+    RootPmemDeviceAlreadyAdded,
 }
 
 /// Use this structure to set up the Block Device before booting the kernel.
@@ -58,6 +60,11 @@ pub struct BlockDeviceConfig {
     // pub file_engine_type: FileEngineType,
     #[serde(rename = "io_engine")]
     pub file_engine_type: Option<FileEngineType>,
+    /// Wear information of the drive, which the guest can query if set.
+    pub lifetime: Option<DriveLifetime>,
+    /// If set to true, the guest can discard and zero ranges of a writable drive, which
+    /// deallocates them in the backing file.
+    pub discard: Option<bool>,
 
     // VhostUserBlock specific fields
     /// Path to the vhost-user socket.
@@ -99,7 +106,7 @@ impl BlockBuilder {
     }
 
     /// Specifies whether there is a root block device already present in the list.
-    fn has_root_device(&self) -> bool {
+    pub fn has_root_device(&self) -> bool {
         // If there is a root device, it would be at the top of the list.
         if let Some(block) = self.devices.front() {
             block.lock().expect("Poisoned lock").root_device()
@@ -202,6 +209,8 @@ mod tests {
                 path_on_host: self.path_on_host.clone(),
                 rate_limiter: self.rate_limiter,
                 file_engine_type: self.file_engine_type,
+                lifetime: self.lifetime,
+                discard: self.discard,
 
                 socket: self.socket.clone(),
             }
@@ -229,6 +238,8 @@ mod tests {
             path_on_host: Some(dummy_path),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -261,6 +272,8 @@ mod tests {
             path_on_host: Some(dummy_path),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -290,6 +303,8 @@ mod tests {
             path_on_host: Some(dummy_path_1),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -306,6 +321,8 @@ mod tests {
             path_on_host: Some(dummy_path_2),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -333,6 +350,8 @@ mod tests {
             path_on_host: Some(dummy_path_1),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -349,6 +368,8 @@ mod tests {
             path_on_host: Some(dummy_path_2),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -365,6 +386,8 @@ mod tests {
             path_on_host: Some(dummy_path_3),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -406,6 +429,8 @@ mod tests {
             path_on_host: Some(dummy_path_1),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -422,6 +447,8 @@ mod tests {
             path_on_host: Some(dummy_path_2),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -438,6 +465,8 @@ mod tests {
             path_on_host: Some(dummy_path_3),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -480,6 +509,8 @@ mod tests {
             path_on_host: Some(dummy_path_1.clone()),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -496,6 +527,8 @@ mod tests {
             path_on_host: Some(dummy_path_2.clone()),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -564,6 +597,8 @@ mod tests {
             path_on_host: Some(dummy_path_1),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -580,6 +615,8 @@ mod tests {
             path_on_host: Some(dummy_path_2),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -606,6 +643,8 @@ mod tests {
             path_on_host: Some(dummy_file.as_path().to_str().unwrap().to_string()),
             rate_limiter: None,
             file_engine_type: Some(FileEngineType::Sync),
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
@@ -634,6 +673,8 @@ mod tests {
             path_on_host: Some(backing_file.as_path().to_str().unwrap().to_string()),
             rate_limiter: None,
             file_engine_type: None,
+            lifetime: None,
+            discard: None,
 
             socket: None,
         };
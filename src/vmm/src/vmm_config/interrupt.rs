@@ -0,0 +1,88 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+
+use crate::SingleVcpuError;
+
+/// The first vector available for fixed interrupts. Vectors below are reserved for exceptions.
+pub const FIRST_FIXED_VECTOR: u8 = 32;
+
+/// The kind of interrupt injected into a vCPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterruptType {
+    /// An interrupt with the given vector, delivered through the local APIC.
+    Fixed,
+    /// A non-maskable interrupt.
+    Nmi,
+    /// A system management interrupt.
+    Smi,
+}
+
+/// This struct represents the strongly typed equivalent of the json body from interrupt
+/// injection requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct InjectInterruptConfig {
+    /// Index of the vCPU receiving the interrupt.
+    pub vcpu_id: u8,
+    /// Interrupt vector. Only used for fixed interrupts.
+    #[serde(default)]
+    pub vector: u8,
+    /// The kind of interrupt.
+    #[serde(rename = "type")]
+    pub interrupt_type: InterruptType,
+    /// Whether a fixed interrupt is level triggered rather than edge triggered.
+    #[serde(default)]
+    pub level: bool,
+}
+
+/// Errors associated with injecting interrupts into the guest.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum InjectInterruptError {
+    /// Invalid vector for fixed interrupt: {0}. Vectors must be in the range 32-255.
+    InvalidVector(u8),
+    /// Failed to signal the interrupt to the local APIC: {0}
+    SignalMsi(kvm_ioctls::Error),
+    /// {0}
+    SingleVcpu(#[from] SingleVcpuError),
+}
+
+impl InjectInterruptConfig {
+    /// Checks that the vector can be used for the requested kind of interrupt.
+    pub fn validate(&self) -> Result<(), InjectInterruptError> {
+        if self.interrupt_type == InterruptType::Fixed && self.vector < FIRST_FIXED_VECTOR {
+            return Err(InjectInterruptError::InvalidVector(self.vector));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let mut config = InjectInterruptConfig {
+            vcpu_id: 0,
+            vector: 0x50,
+            interrupt_type: InterruptType::Fixed,
+            level: false,
+        };
+        config.validate().unwrap();
+
+        config.vector = 31;
+        assert!(matches!(
+            config.validate(),
+            Err(InjectInterruptError::InvalidVector(31))
+        ));
+
+        // The vector is ignored for NMIs and SMIs.
+        config.interrupt_type = InterruptType::Nmi;
+        config.validate().unwrap();
+        config.interrupt_type = InterruptType::Smi;
+        config.validate().unwrap();
+    }
+}
@@ -5,12 +5,17 @@ use std::fmt::Debug;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::cpu_config::templates::{CpuTemplateType, CustomCpuTemplate, StaticCpuTemplate};
+use crate::vmm_config::balloon::BalloonPolicy;
 
 /// The default memory size of the VM, in MiB.
 pub const DEFAULT_MEM_SIZE_MIB: usize = 128;
 /// Firecracker aims to support small scale workloads only, so limit the maximum
 /// vCPUs supported.
 pub const MAX_SUPPORTED_VCPUS: u8 = 32;
+/// The smallest weight accepted by the cgroup v2 `cpu.weight` and `io.weight` files.
+pub const MIN_CGROUP_WEIGHT: u16 = 1;
+/// The largest weight accepted by the cgroup v2 `cpu.weight` and `io.weight` files.
+pub const MAX_CGROUP_WEIGHT: u16 = 10000;
 
 /// Errors associated with configuring the microVM.
 #[rustfmt::skip]
@@ -31,6 +36,26 @@ pub enum MachineConfigError {
     KernelVersion,
     /// Firecracker's huge pages support is incompatible with memory ballooning.
     BalloonAndHugePages,
+    /// The CPU budget must satisfy 1024 <= runtime_ns <= deadline_ns <= period_ns.
+    InvalidCpuBudget,
+    /// The memory regions add up to {0} MiB instead of the memory size of {1} MiB.
+    MemoryRegionsSizeMismatch(usize, usize),
+    /// Transparent huge pages are incompatible with hugetlbfs huge pages.
+    ThpModeIncompatible,
+    /// The balloon policy must keep at most 100% of the host memory available.
+    InvalidBalloonPolicy,
+    /// The CPU weight must be between {MIN_CGROUP_WEIGHT:} and {MAX_CGROUP_WEIGHT:}.
+    InvalidCpuWeight,
+    /// The I/O weight must be between {MIN_CGROUP_WEIGHT:} and {MAX_CGROUP_WEIGHT:}.
+    InvalidIoWeight,
+    /// The memory.high limit must be greater than 0 MiB.
+    InvalidMemoryHigh,
+    /// Blocking CPUID bits is only supported on x86_64.
+    #[cfg(target_arch = "aarch64")]
+    CpuidBlocklistNotSupported,
+    /// Memory regions with memory types are only supported on x86_64.
+    #[cfg(target_arch = "aarch64")]
+    MemoryRegionsNotSupported,
 }
 
 /// Describes the possible (huge)page configurations for a microVM's memory.
@@ -80,6 +105,20 @@ impl HugePageConfig {
     }
 }
 
+/// Transparent huge pages (THP) policy of the guest memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThpMode {
+    /// Never back guest memory with transparent huge pages (`MADV_NOHUGEPAGE`).
+    Never,
+    /// Back guest memory with transparent huge pages (`MADV_HUGEPAGE`), and collapse the memory
+    /// populated by the kernel and boot data into huge pages (`MADV_COLLAPSE`) before the vCPUs
+    /// start, if the host kernel supports it.
+    Madvise,
+    /// Back guest memory with transparent huge pages as it gets faulted in (`MADV_HUGEPAGE`).
+    Always,
+}
+
 impl From<HugePageConfig> for Option<memfd::HugetlbSize> {
     fn from(value: HugePageConfig) -> Self {
         match value {
@@ -89,6 +128,129 @@ impl From<HugePageConfig> for Option<memfd::HugetlbSize> {
     }
 }
 
+/// Memory type of a region of guest memory.
+///
+/// KVM memory slots have no flag for memory types (`KVM_MEM_READONLY` only write-protects a slot),
+/// so the memory types are given to the guest through its MTRRs only.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemoryRegionType {
+    /// Write-back cacheable memory.
+    #[default]
+    Normal,
+    /// Write-through cacheable memory.
+    WriteThrough,
+    /// Uncacheable memory whose writes are combined in write buffers.
+    WriteCombining,
+    /// Uncacheable memory.
+    Uncacheable,
+}
+
+/// Configures the memory type of a region of guest memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryRegionConfig {
+    /// Size of the region in MiB.
+    pub size_mib: usize,
+    /// Memory type of the region.
+    #[serde(default)]
+    pub region_type: MemoryRegionType,
+}
+
+/// Limit of a cgroup v2 memory interface file, given in MiB, or `"max"` for no limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CgroupMemoryLimit {
+    /// Limit in MiB.
+    Mib(u64),
+    /// No limit.
+    Max,
+}
+
+impl CgroupMemoryLimit {
+    /// Returns the value to write to the cgroup interface file, which takes bytes.
+    pub fn to_cgroup_value(self) -> String {
+        match self {
+            CgroupMemoryLimit::Mib(mib) => mib.saturating_mul(1 << 20).to_string(),
+            CgroupMemoryLimit::Max => "max".to_string(),
+        }
+    }
+}
+
+impl Serialize for CgroupMemoryLimit {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            CgroupMemoryLimit::Mib(mib) => serializer.serialize_u64(*mib),
+            CgroupMemoryLimit::Max => serializer.serialize_str("max"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CgroupMemoryLimit {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Limit {
+            Mib(u64),
+            Keyword(String),
+        }
+
+        match Limit::deserialize(deserializer)? {
+            Limit::Mib(mib) => Ok(CgroupMemoryLimit::Mib(mib)),
+            Limit::Keyword(keyword) if keyword == "max" => Ok(CgroupMemoryLimit::Max),
+            Limit::Keyword(keyword) => Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Str(&keyword),
+                &r#"a size in MiB or "max""#,
+            )),
+        }
+    }
+}
+
+/// `SCHED_DEADLINE` parameters of the vCPU threads, in nanoseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CpuBudget {
+    /// CPU time a vCPU thread can consume in each period.
+    pub runtime_ns: u64,
+    /// Time from the start of each period by which the runtime is consumed.
+    pub deadline_ns: u64,
+    /// Length of the scheduling period.
+    pub period_ns: u64,
+}
+
+impl CpuBudget {
+    // The kernel rejects runtimes below its deadline scheduling resolution.
+    const MIN_RUNTIME_NS: u64 = 1 << 10;
+
+    /// Checks that the budget is accepted by the kernel.
+    pub fn is_valid(&self) -> bool {
+        Self::MIN_RUNTIME_NS <= self.runtime_ns
+            && self.runtime_ns <= self.deadline_ns
+            && self.deadline_ns <= self.period_ns
+    }
+}
+
+/// CPUID bits hidden from the guest, in the entry of a leaf and subleaf.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CpuidBlock {
+    /// CPUID leaf.
+    pub leaf: u32,
+    /// CPUID subleaf, 0 for leaves without subleaves.
+    #[serde(default)]
+    pub subleaf: u32,
+    /// Bits of EAX read as 0 by the guest.
+    #[serde(default)]
+    pub eax_mask: u32,
+    /// Bits of EBX read as 0 by the guest.
+    #[serde(default)]
+    pub ebx_mask: u32,
+    /// Bits of ECX read as 0 by the guest.
+    #[serde(default)]
+    pub ecx_mask: u32,
+    /// Bits of EDX read as 0 by the guest.
+    #[serde(default)]
+    pub edx_mask: u32,
+}
+
 /// Struct used in PUT `/machine-config` API call.
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
@@ -115,6 +277,39 @@ pub struct MachineConfig {
     /// Configures what page size Firecracker should use to back guest memory.
     #[serde(default)]
     pub huge_pages: HugePageConfig,
+    /// Splits guest memory into regions of different memory types, laid out in order from the
+    /// start of guest memory. The regions must add up to the memory size. Memory types are set
+    /// through MTRRs, so they are only supported on x86_64.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_memory_regions: Vec<MemoryRegionConfig>,
+    /// Transparent huge pages policy of the guest memory. Left to the host defaults if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thp_mode: Option<ThpMode>,
+    /// CPU weight of the microVM cgroup, relative to the default weight of 100. Left to the
+    /// cgroup defaults if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_weight: Option<u16>,
+    /// Block I/O weight of the microVM cgroup, on the host disks backing the block devices. Left
+    /// to the cgroup defaults if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io_weight: Option<u16>,
+    /// Memory usage of the microVM cgroup, in MiB, above which its processes are throttled and
+    /// reclaimed from, without being OOM killed. Left to the cgroup defaults if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_high_mb: Option<CgroupMemoryLimit>,
+    /// Swap usage of the microVM cgroup, in MiB, above which its processes are throttled. A limit
+    /// of 0 MiB keeps the processes from swapping. Left to the cgroup defaults if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_swap_high_mb: Option<CgroupMemoryLimit>,
+    /// Runs the vCPU threads under `SCHED_DEADLINE` with the given budget.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_budget: Option<CpuBudget>,
+    /// Resizes the balloon device automatically, based on the host memory usage.
+    #[serde(default, skip_serializing_if = "BalloonPolicy::is_none")]
+    pub balloon_policy: BalloonPolicy,
+    /// CPUID bits hidden from the guest, on top of the CPU template. Only supported on x86_64.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cpuid_blocklist: Vec<CpuidBlock>,
     /// GDB socket address.
     #[cfg(feature = "gdb")]
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -157,6 +352,15 @@ impl Default for MachineConfig {
             cpu_template: None,
             track_dirty_pages: false,
             huge_pages: HugePageConfig::None,
+            additional_memory_regions: Vec::new(),
+            thp_mode: None,
+            cpu_weight: None,
+            io_weight: None,
+            memory_high_mb: None,
+            memory_swap_high_mb: None,
+            cpu_budget: None,
+            balloon_policy: BalloonPolicy::None,
+            cpuid_blocklist: Vec::new(),
             #[cfg(feature = "gdb")]
             gdb_socket_path: None,
         }
@@ -190,6 +394,34 @@ pub struct MachineConfigUpdate {
     /// Configures what page size Firecracker should use to back guest memory.
     #[serde(default)]
     pub huge_pages: Option<HugePageConfig>,
+    /// Splits guest memory into regions of different memory types, laid out in order from the
+    /// start of guest memory. The regions must add up to the memory size.
+    #[serde(default)]
+    pub additional_memory_regions: Option<Vec<MemoryRegionConfig>>,
+    /// Transparent huge pages policy of the guest memory.
+    #[serde(default)]
+    pub thp_mode: Option<ThpMode>,
+    /// CPU weight of the microVM cgroup. Can be updated after boot.
+    #[serde(default)]
+    pub cpu_weight: Option<u16>,
+    /// Block I/O weight of the microVM cgroup. Can be updated after boot.
+    #[serde(default)]
+    pub io_weight: Option<u16>,
+    /// Memory throttling limit of the microVM cgroup, in MiB. Can be updated after boot.
+    #[serde(default)]
+    pub memory_high_mb: Option<CgroupMemoryLimit>,
+    /// Swap throttling limit of the microVM cgroup, in MiB. Can be updated after boot.
+    #[serde(default)]
+    pub memory_swap_high_mb: Option<CgroupMemoryLimit>,
+    /// Runs the vCPU threads under `SCHED_DEADLINE` with the given budget.
+    #[serde(default)]
+    pub cpu_budget: Option<CpuBudget>,
+    /// Resizes the balloon device automatically, based on the host memory usage.
+    #[serde(default)]
+    pub balloon_policy: Option<BalloonPolicy>,
+    /// CPUID bits hidden from the guest, on top of the CPU template.
+    #[serde(default)]
+    pub cpuid_blocklist: Option<Vec<CpuidBlock>>,
     /// GDB socket address.
     #[cfg(feature = "gdb")]
     #[serde(default)]
@@ -203,6 +435,19 @@ impl MachineConfigUpdate {
     pub fn is_empty(&self) -> bool {
         self == &Default::default()
     }
+
+    /// Returns `true` if the update changes the cgroup resource controls and nothing else. These
+    /// are the only fields that can be updated after boot.
+    pub fn is_cgroup_only(&self) -> bool {
+        let cgroup_update = MachineConfigUpdate {
+            cpu_weight: self.cpu_weight,
+            io_weight: self.io_weight,
+            memory_high_mb: self.memory_high_mb,
+            memory_swap_high_mb: self.memory_swap_high_mb,
+            ..Default::default()
+        };
+        !self.is_empty() && self == &cgroup_update
+    }
 }
 
 impl From<MachineConfig> for MachineConfigUpdate {
@@ -214,6 +459,15 @@ impl From<MachineConfig> for MachineConfigUpdate {
             cpu_template: cfg.static_template(),
             track_dirty_pages: Some(cfg.track_dirty_pages),
             huge_pages: Some(cfg.huge_pages),
+            additional_memory_regions: Some(cfg.additional_memory_regions),
+            thp_mode: cfg.thp_mode,
+            cpu_weight: cfg.cpu_weight,
+            io_weight: cfg.io_weight,
+            memory_high_mb: cfg.memory_high_mb,
+            memory_swap_high_mb: cfg.memory_swap_high_mb,
+            cpu_budget: cfg.cpu_budget,
+            balloon_policy: Some(cfg.balloon_policy),
+            cpuid_blocklist: Some(cfg.cpuid_blocklist),
             #[cfg(feature = "gdb")]
             gdb_socket_path: cfg.gdb_socket_path,
         }
@@ -226,6 +480,14 @@ impl MachineConfig {
         self.cpu_template = Some(CpuTemplateType::Custom(cpu_template));
     }
 
+    /// Returns `true` if any resource control of the microVM cgroup is set.
+    pub fn has_cgroup_limits(&self) -> bool {
+        self.cpu_weight.is_some()
+            || self.io_weight.is_some()
+            || self.memory_high_mb.is_some()
+            || self.memory_swap_high_mb.is_some()
+    }
+
     fn static_template(&self) -> Option<StaticCpuTemplate> {
         match self.cpu_template {
             Some(CpuTemplateType::Static(template)) => Some(template),
@@ -268,6 +530,69 @@ impl MachineConfig {
             return Err(MachineConfigError::InvalidMemorySize);
         }
 
+        let additional_memory_regions = update
+            .additional_memory_regions
+            .clone()
+            .unwrap_or_else(|| self.additional_memory_regions.clone());
+        #[cfg(target_arch = "aarch64")]
+        if !additional_memory_regions.is_empty() {
+            return Err(MachineConfigError::MemoryRegionsNotSupported);
+        }
+        let regions_size_mib = additional_memory_regions
+            .iter()
+            .map(|region| region.size_mib)
+            .sum();
+        if !additional_memory_regions.is_empty() && regions_size_mib != mem_size_mib {
+            return Err(MachineConfigError::MemoryRegionsSizeMismatch(
+                regions_size_mib,
+                mem_size_mib,
+            ));
+        }
+
+        let thp_mode = update.thp_mode.or(self.thp_mode);
+        if thp_mode.is_some() && page_config != HugePageConfig::None {
+            return Err(MachineConfigError::ThpModeIncompatible);
+        }
+
+        let cpu_weight = update.cpu_weight.or(self.cpu_weight);
+        if cpu_weight
+            .is_some_and(|weight| !(MIN_CGROUP_WEIGHT..=MAX_CGROUP_WEIGHT).contains(&weight))
+        {
+            return Err(MachineConfigError::InvalidCpuWeight);
+        }
+
+        let io_weight = update.io_weight.or(self.io_weight);
+        if io_weight
+            .is_some_and(|weight| !(MIN_CGROUP_WEIGHT..=MAX_CGROUP_WEIGHT).contains(&weight))
+        {
+            return Err(MachineConfigError::InvalidIoWeight);
+        }
+
+        let memory_high_mb = update.memory_high_mb.or(self.memory_high_mb);
+        let memory_swap_high_mb = update.memory_swap_high_mb.or(self.memory_swap_high_mb);
+        if memory_high_mb == Some(CgroupMemoryLimit::Mib(0)) {
+            return Err(MachineConfigError::InvalidMemoryHigh);
+        }
+
+        let cpu_budget = update.cpu_budget.or(self.cpu_budget);
+        if cpu_budget.is_some_and(|budget| !budget.is_valid()) {
+            return Err(MachineConfigError::InvalidCpuBudget);
+        }
+
+        let balloon_policy = update.balloon_policy.unwrap_or(self.balloon_policy);
+        if !balloon_policy.is_valid() {
+            return Err(MachineConfigError::InvalidBalloonPolicy);
+        }
+
+        let cpuid_blocklist = update
+            .cpuid_blocklist
+            .clone()
+            .unwrap_or_else(|| self.cpuid_blocklist.clone());
+        #[cfg(target_arch = "aarch64")]
+        if !cpuid_blocklist.is_empty() {
+            return Err(MachineConfigError::CpuidBlocklistNotSupported);
+        }
+
         let cpu_template = match update.cpu_template {
             None => self.cpu_template.clone(),
             Some(StaticCpuTemplate::None) => None,
@@ -281,6 +606,15 @@ impl MachineConfig {
             cpu_template,
             track_dirty_pages: update.track_dirty_pages.unwrap_or(self.track_dirty_pages),
             huge_pages: page_config,
+            additional_memory_regions,
+            thp_mode,
+            cpu_weight,
+            io_weight,
+            memory_high_mb,
+            memory_swap_high_mb,
+            cpu_budget,
+            balloon_policy,
+            cpuid_blocklist,
             #[cfg(feature = "gdb")]
             gdb_socket_path: update.gdb_socket_path.clone(),
         })
@@ -290,7 +624,12 @@ impl MachineConfig {
 #[cfg(test)]
 mod tests {
     use crate::cpu_config::templates::{CpuTemplateType, CustomCpuTemplate, StaticCpuTemplate};
-    use crate::vmm_config::machine_config::MachineConfig;
+    use crate::vmm_config::balloon::BalloonPolicy;
+    use crate::vmm_config::machine_config::{
+        CgroupMemoryLimit, CpuBudget, CpuidBlock, HugePageConfig, MAX_CGROUP_WEIGHT,
+        MIN_CGROUP_WEIGHT, MachineConfig, MachineConfigError, MachineConfigUpdate,
+        MemoryRegionConfig, MemoryRegionType, ThpMode,
+    };
 
     // Ensure the special (de)serialization logic for the cpu_template field works:
     // only static cpu templates can be specified via the machine-config endpoint, but
@@ -337,4 +676,287 @@ mod tests {
 
         assert!(deserialized.cpu_template.is_none());
     }
+
+    #[test]
+    fn test_update_cpu_budget() {
+        let budget = CpuBudget {
+            runtime_ns: 1_000_000,
+            deadline_ns: 5_000_000,
+            period_ns: 10_000_000,
+        };
+        let mconfig = MachineConfig::default()
+            .update(&MachineConfigUpdate {
+                cpu_budget: Some(budget),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(mconfig.cpu_budget, Some(budget));
+
+        // Updating other fields keeps the budget.
+        let mconfig = mconfig
+            .update(&MachineConfigUpdate {
+                vcpu_count: Some(2),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(mconfig.cpu_budget, Some(budget));
+
+        let invalid_budgets = [
+            // The runtime exceeds the deadline.
+            CpuBudget {
+                runtime_ns: 6_000_000,
+                ..budget
+            },
+            // The deadline exceeds the period.
+            CpuBudget {
+                deadline_ns: 11_000_000,
+                ..budget
+            },
+            // The runtime is below the resolution of the scheduler.
+            CpuBudget {
+                runtime_ns: 1000,
+                ..budget
+            },
+        ];
+        for budget in invalid_budgets {
+            assert_eq!(
+                mconfig.update(&MachineConfigUpdate {
+                    cpu_budget: Some(budget),
+                    ..Default::default()
+                }),
+                Err(MachineConfigError::InvalidCpuBudget)
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_update_memory_regions() {
+        let regions = vec![
+            MemoryRegionConfig {
+                size_mib: 96,
+                region_type: MemoryRegionType::Normal,
+            },
+            MemoryRegionConfig {
+                size_mib: 32,
+                region_type: MemoryRegionType::WriteCombining,
+            },
+        ];
+        let mconfig = MachineConfig::default()
+            .update(&MachineConfigUpdate {
+                additional_memory_regions: Some(regions.clone()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(mconfig.additional_memory_regions, regions);
+
+        // The regions must keep adding up to the memory size.
+        assert_eq!(
+            mconfig.update(&MachineConfigUpdate {
+                mem_size_mib: Some(256),
+                ..Default::default()
+            }),
+            Err(MachineConfigError::MemoryRegionsSizeMismatch(128, 256))
+        );
+        let mconfig = mconfig
+            .update(&MachineConfigUpdate {
+                mem_size_mib: Some(256),
+                additional_memory_regions: Some(vec![]),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(mconfig.additional_memory_regions.is_empty());
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn test_update_memory_regions() {
+        assert_eq!(
+            MachineConfig::default().update(&MachineConfigUpdate {
+                additional_memory_regions: Some(vec![MemoryRegionConfig {
+                    size_mib: 128,
+                    region_type: MemoryRegionType::Normal,
+                }]),
+                ..Default::default()
+            }),
+            Err(MachineConfigError::MemoryRegionsNotSupported)
+        );
+    }
+
+    #[test]
+    fn test_update_thp_mode() {
+        let mconfig = MachineConfig::default()
+            .update(&MachineConfigUpdate {
+                thp_mode: Some(ThpMode::Madvise),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(mconfig.thp_mode, Some(ThpMode::Madvise));
+
+        assert_eq!(
+            mconfig.update(&MachineConfigUpdate {
+                huge_pages: Some(HugePageConfig::Hugetlbfs2M),
+                ..Default::default()
+            }),
+            Err(MachineConfigError::ThpModeIncompatible)
+        );
+
+        let mode: ThpMode = serde_json::from_str(r#""never""#).unwrap();
+        assert_eq!(mode, ThpMode::Never);
+    }
+
+    #[test]
+    fn test_update_cpu_weight() {
+        let update = MachineConfigUpdate {
+            cpu_weight: Some(MAX_CGROUP_WEIGHT),
+            ..Default::default()
+        };
+        assert!(update.is_cgroup_only());
+        let mconfig = MachineConfig::default().update(&update).unwrap();
+        assert_eq!(mconfig.cpu_weight, Some(MAX_CGROUP_WEIGHT));
+        assert!(mconfig.has_cgroup_limits());
+
+        for cpu_weight in [0, MAX_CGROUP_WEIGHT + 1] {
+            assert_eq!(
+                mconfig.update(&MachineConfigUpdate {
+                    cpu_weight: Some(cpu_weight),
+                    ..Default::default()
+                }),
+                Err(MachineConfigError::InvalidCpuWeight)
+            );
+        }
+
+        let update = MachineConfigUpdate {
+            vcpu_count: Some(2),
+            cpu_weight: Some(100),
+            ..Default::default()
+        };
+        assert!(!update.is_cgroup_only());
+        // An update without any field changes nothing.
+        assert!(!MachineConfigUpdate::default().is_cgroup_only());
+    }
+
+    #[test]
+    fn test_update_memory_high() {
+        let update = MachineConfigUpdate {
+            memory_high_mb: Some(CgroupMemoryLimit::Mib(64)),
+            memory_swap_high_mb: Some(CgroupMemoryLimit::Mib(32)),
+            ..Default::default()
+        };
+        assert!(update.is_cgroup_only());
+        // The limits may be below the guest memory size.
+        let mconfig = MachineConfig::default().update(&update).unwrap();
+        assert_eq!(mconfig.memory_high_mb, Some(CgroupMemoryLimit::Mib(64)));
+        assert_eq!(
+            mconfig.memory_swap_high_mb,
+            Some(CgroupMemoryLimit::Mib(32))
+        );
+        assert!(mconfig.has_cgroup_limits());
+
+        assert_eq!(
+            mconfig.update(&MachineConfigUpdate {
+                memory_high_mb: Some(CgroupMemoryLimit::Mib(0)),
+                ..Default::default()
+            }),
+            Err(MachineConfigError::InvalidMemoryHigh)
+        );
+        // Swapping can be prevented altogether.
+        let mconfig = mconfig
+            .update(&MachineConfigUpdate {
+                memory_swap_high_mb: Some(CgroupMemoryLimit::Mib(0)),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(mconfig.memory_swap_high_mb, Some(CgroupMemoryLimit::Mib(0)));
+    }
+
+    #[test]
+    fn test_cgroup_memory_limit_serde() {
+        let update: MachineConfigUpdate =
+            serde_json::from_str(r#"{"memory_high_mb": "max", "memory_swap_high_mb": 16}"#)
+                .unwrap();
+        assert_eq!(update.memory_high_mb, Some(CgroupMemoryLimit::Max));
+        assert_eq!(update.memory_swap_high_mb, Some(CgroupMemoryLimit::Mib(16)));
+        serde_json::from_str::<MachineConfigUpdate>(r#"{"memory_high_mb": "min"}"#).unwrap_err();
+        serde_json::from_str::<MachineConfigUpdate>(r#"{"memory_high_mb": -1}"#).unwrap_err();
+
+        assert_eq!(
+            serde_json::to_string(&CgroupMemoryLimit::Max).unwrap(),
+            r#""max""#
+        );
+        assert_eq!(
+            serde_json::to_string(&CgroupMemoryLimit::Mib(16)).unwrap(),
+            "16"
+        );
+        assert_eq!(CgroupMemoryLimit::Max.to_cgroup_value(), "max");
+        assert_eq!(CgroupMemoryLimit::Mib(16).to_cgroup_value(), "16777216");
+    }
+
+    #[test]
+    fn test_update_io_weight() {
+        let update = MachineConfigUpdate {
+            io_weight: Some(MIN_CGROUP_WEIGHT),
+            ..Default::default()
+        };
+        assert!(update.is_cgroup_only());
+        let mconfig = MachineConfig::default().update(&update).unwrap();
+        assert_eq!(mconfig.io_weight, Some(MIN_CGROUP_WEIGHT));
+        assert!(mconfig.has_cgroup_limits());
+
+        for io_weight in [0, MAX_CGROUP_WEIGHT + 1] {
+            assert_eq!(
+                mconfig.update(&MachineConfigUpdate {
+                    io_weight: Some(io_weight),
+                    ..Default::default()
+                }),
+                Err(MachineConfigError::InvalidIoWeight)
+            );
+        }
+    }
+
+    #[test]
+    fn test_update_balloon_policy() {
+        let policy = BalloonPolicy::ProportionalToFreeHost {
+            target_free_pct: 20,
+        };
+        let mconfig = MachineConfig::default()
+            .update(&MachineConfigUpdate {
+                balloon_policy: Some(policy),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(mconfig.balloon_policy, policy);
+
+        assert_eq!(
+            mconfig.update(&MachineConfigUpdate {
+                balloon_policy: Some(BalloonPolicy::ProportionalToFreeHost {
+                    target_free_pct: 101
+                }),
+                ..Default::default()
+            }),
+            Err(MachineConfigError::InvalidBalloonPolicy)
+        );
+    }
+
+    #[test]
+    fn test_update_cpuid_blocklist() {
+        let update: MachineConfigUpdate =
+            serde_json::from_str(r#"{"cpuid_blocklist": [{"leaf": 1, "ecx_mask": 1073741824}]}"#)
+                .unwrap();
+        let block = CpuidBlock {
+            leaf: 1,
+            subleaf: 0,
+            eax_mask: 0,
+            ebx_mask: 0,
+            ecx_mask: 1 << 30,
+            edx_mask: 0,
+        };
+        assert_eq!(update.cpuid_blocklist, Some(vec![block]));
+
+        let mconfig = MachineConfig::default().update(&update);
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(mconfig.unwrap().cpuid_blocklist, vec![block]);
+        #[cfg(target_arch = "aarch64")]
+        assert_eq!(mconfig, Err(MachineConfigError::CpuidBlocklistNotSupported));
+    }
 }
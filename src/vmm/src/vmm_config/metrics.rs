@@ -7,13 +7,16 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 
 use super::open_file_nonblock;
-use crate::logger::{FcLineWriter, METRICS};
+use crate::logger::{FcLineWriter, METRICS, MetricsFormat};
 
 /// Strongly typed structure used to describe the metrics system.
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct MetricsConfig {
     /// Named pipe or file used as output for metrics.
     pub metrics_path: PathBuf,
+    /// Format in which the metrics are written.
+    #[serde(default)]
+    pub format: MetricsFormat,
 }
 
 /// Errors associated with actions on the `MetricsConfig`.
@@ -30,7 +33,7 @@ pub fn init_metrics(metrics_cfg: MetricsConfig) -> Result<(), MetricsConfigError
             .map_err(|err| MetricsConfigError::InitializationFailure(err.to_string()))?,
     );
     METRICS
-        .init(writer)
+        .init(writer, metrics_cfg.format)
         .map_err(|err| MetricsConfigError::InitializationFailure(err.to_string()))
 }
 
@@ -45,6 +48,7 @@ mod tests {
         // Error case: initializing metrics with invalid pipe returns error.
         let desc = MetricsConfig {
             metrics_path: PathBuf::from("not_found_file_metrics"),
+            format: MetricsFormat::Json,
         };
         init_metrics(desc).unwrap_err();
 
@@ -52,6 +56,7 @@ mod tests {
         let metrics_file = TempFile::new().unwrap();
         let desc = MetricsConfig {
             metrics_path: metrics_file.as_path().to_path_buf(),
+            format: MetricsFormat::Prometheus,
         };
 
         init_metrics(desc.clone()).unwrap();
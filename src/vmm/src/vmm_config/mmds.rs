@@ -18,6 +18,12 @@ pub struct MmdsConfig {
     pub network_interfaces: Vec<String>,
     /// MMDS IPv4 configured address.
     pub ipv4_address: Option<Ipv4Addr>,
+    /// Connections per second each guest IPv4 address can open to MMDS, 0 for no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mmds_rate_limit_rps: Option<u32>,
+    /// Milliseconds the responses to guest requests are cached for, 0 to disable the cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mmds_cache_ttl_ms: Option<u64>,
 }
 
 impl MmdsConfig {
@@ -36,6 +42,18 @@ impl MmdsConfig {
     pub fn ipv4_addr(&self) -> Option<Ipv4Addr> {
         self.ipv4_address
     }
+
+    /// Returns the MMDS connection rate limit if one was configured.
+    /// Otherwise returns None.
+    pub fn rate_limit_rps(&self) -> Option<u32> {
+        self.mmds_rate_limit_rps
+    }
+
+    /// Returns the MMDS response cache TTL if one was configured.
+    /// Otherwise returns None.
+    pub fn cache_ttl_ms(&self) -> Option<u64> {
+        self.mmds_cache_ttl_ms
+    }
 }
 
 /// MMDS configuration related errors.
@@ -16,12 +16,21 @@ use crate::rate_limiter::{BucketUpdate, RateLimiter, TokenBucket};
 pub mod balloon;
 /// Wrapper for configuring the microVM boot source.
 pub mod boot_source;
+/// Wrapper for setting breakpoints on the vCPUs of the microVM.
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+pub mod breakpoints;
+/// Wrapper for synchronizing the microVM clock.
+#[cfg(target_arch = "x86_64")]
+pub mod clock;
 /// Wrapper for configuring the block devices.
 pub mod drive;
 /// Wrapper for configuring the entropy device attached to the microVM.
 pub mod entropy;
 /// Wrapper over the microVM general information attached to the microVM.
 pub mod instance_info;
+/// Wrapper for injecting interrupts into the microVM.
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+pub mod interrupt;
 /// Wrapper for configuring the memory and CPU of the microVM.
 pub mod machine_config;
 /// Wrapper for configuring the metrics.
@@ -30,8 +39,18 @@ pub mod metrics;
 pub mod mmds;
 /// Wrapper for configuring the network devices attached to the microVM.
 pub mod net;
+/// Wrapper for configuring the devices emulated by plugins.
+#[cfg(feature = "plugins")]
+pub mod plugin;
+/// Wrapper for configuring the pmem devices attached to the microVM.
+pub mod pmem;
+/// Wrapper for configuring the PMU exposed to the guest by the vCPUs.
+#[cfg(target_arch = "x86_64")]
+pub mod pmu;
 /// Wrapper for configuring microVM snapshots and the microVM state.
 pub mod snapshot;
+/// Wrapper for setting the generation ID of the microVM.
+pub mod vmgenid;
 /// Wrapper for configuring the vsock devices attached to the microVM.
 pub mod vsock;
 
@@ -9,9 +9,17 @@ use serde::{Deserialize, Serialize};
 
 use super::RateLimiterConfig;
 use crate::VmmError;
+pub use crate::devices::virtio::net::Duplex;
 use crate::devices::virtio::net::{Net, TapError};
 use crate::utils::net::mac::MacAddr;
 
+/// The smallest MTU an IPv4 host must support (RFC 791).
+const MIN_MTU: u16 = 68;
+/// The link speed reported to the guest when only the duplex mode is configured.
+const DEFAULT_LINK_SPEED_MBPS: u32 = 10000;
+/// The largest link speed the Linux driver accepts, larger values are shown as unknown.
+const MAX_LINK_SPEED_MBPS: u32 = 0x7fff_ffff;
+
 /// This struct represents the strongly typed equivalent of the json body from net iface
 /// related requests.
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
@@ -27,18 +35,31 @@ pub struct NetworkInterfaceConfig {
     pub rx_rate_limiter: Option<RateLimiterConfig>,
     /// Rate Limiter for transmitted packages.
     pub tx_rate_limiter: Option<RateLimiterConfig>,
+    /// MTU advertised to the guest driver.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u16>,
+    /// Link speed, in Mbps, reported to the guest driver.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_speed_mbps: Option<u32>,
+    /// Duplex mode of the link reported to the guest driver.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplex: Option<Duplex>,
 }
 
 impl From<&Net> for NetworkInterfaceConfig {
     fn from(net: &Net) -> Self {
         let rx_rl: RateLimiterConfig = net.rx_rate_limiter().into();
         let tx_rl: RateLimiterConfig = net.tx_rate_limiter().into();
+        let speed_duplex = net.speed_duplex();
         NetworkInterfaceConfig {
             iface_id: net.id().clone(),
             host_dev_name: net.iface_name(),
             guest_mac: net.guest_mac().copied(),
             rx_rate_limiter: rx_rl.into_option(),
             tx_rate_limiter: tx_rl.into_option(),
+            mtu: net.mtu(),
+            link_speed_mbps: speed_duplex.map(|(speed, _)| speed),
+            duplex: speed_duplex.map(|(_, duplex)| duplex),
         }
     }
 }
@@ -69,6 +90,10 @@ pub enum NetworkInterfaceError {
     DeviceUpdate(#[from] VmmError),
     /// The MAC address is already in use: {0}
     GuestMacAddressInUse(String),
+    /// The link speed is not between 1 and 2147483647 Mbps: {0}
+    InvalidLinkSpeed(u32),
+    /// The MTU is smaller than 68 bytes: {0}
+    InvalidMtu(u16),
     /// Cannot open/create the tap device: {0}
     OpenTap(#[from] TapError),
 }
@@ -143,6 +168,17 @@ impl NetBuilder {
 
     /// Creates a Net device from a NetworkInterfaceConfig.
     pub fn create_net(cfg: NetworkInterfaceConfig) -> Result<Net, NetworkInterfaceError> {
+        if let Some(mtu) = cfg.mtu {
+            if mtu < MIN_MTU {
+                return Err(NetworkInterfaceError::InvalidMtu(mtu));
+            }
+        }
+        if let Some(speed) = cfg.link_speed_mbps {
+            if !(1..=MAX_LINK_SPEED_MBPS).contains(&speed) {
+                return Err(NetworkInterfaceError::InvalidLinkSpeed(speed));
+            }
+        }
+
         let rx_rate_limiter = cfg
             .rx_rate_limiter
             .map(super::RateLimiterConfig::try_into)
@@ -155,14 +191,24 @@ impl NetBuilder {
             .map_err(NetworkInterfaceError::CreateRateLimiter)?;
 
         // Create and return the Net device
-        crate::devices::virtio::net::Net::new(
+        let mut net = crate::devices::virtio::net::Net::new(
             cfg.iface_id,
             &cfg.host_dev_name,
             cfg.guest_mac,
             rx_rate_limiter.unwrap_or_default(),
             tx_rate_limiter.unwrap_or_default(),
         )
-        .map_err(NetworkInterfaceError::CreateNetworkDevice)
+        .map_err(NetworkInterfaceError::CreateNetworkDevice)?;
+        if let Some(mtu) = cfg.mtu {
+            net.set_mtu(mtu);
+        }
+        if cfg.link_speed_mbps.is_some() || cfg.duplex.is_some() {
+            net.set_speed_duplex(
+                cfg.link_speed_mbps.unwrap_or(DEFAULT_LINK_SPEED_MBPS),
+                cfg.duplex.unwrap_or_default(),
+            );
+        }
+        Ok(net)
     }
 
     /// Returns a vec with the structures used to configure the net devices.
@@ -195,6 +241,9 @@ mod tests {
             guest_mac: Some(MacAddr::from_str(mac).unwrap()),
             rx_rate_limiter: RateLimiterConfig::default().into_option(),
             tx_rate_limiter: RateLimiterConfig::default().into_option(),
+            mtu: None,
+            link_speed_mbps: None,
+            duplex: None,
         }
     }
 
@@ -206,6 +255,9 @@ mod tests {
                 guest_mac: self.guest_mac,
                 rx_rate_limiter: None,
                 tx_rate_limiter: None,
+                mtu: self.mtu,
+                link_speed_mbps: self.link_speed_mbps,
+                duplex: self.duplex,
             }
         }
     }
@@ -325,6 +377,68 @@ mod tests {
         assert_eq!(configs.first().unwrap(), &net_if_cfg);
     }
 
+    #[test]
+    fn test_net_config_mtu() {
+        let mut net_builder = NetBuilder::new();
+
+        let mut net_if_cfg = create_netif("id", "dev6", "01:23:45:67:89:0c");
+        net_if_cfg.mtu = Some(MIN_MTU - 1);
+        assert_eq!(
+            net_builder
+                .build(net_if_cfg.clone())
+                .err()
+                .unwrap()
+                .to_string(),
+            NetworkInterfaceError::InvalidMtu(MIN_MTU - 1).to_string()
+        );
+        assert_eq!(net_builder.net_devices.len(), 0);
+
+        net_if_cfg.mtu = Some(1400);
+        let net = net_builder.build(net_if_cfg.clone()).unwrap();
+        assert_eq!(net.lock().unwrap().mtu(), Some(1400));
+        assert_eq!(net_builder.configs().first().unwrap(), &net_if_cfg);
+    }
+
+    #[test]
+    fn test_net_config_speed_duplex() {
+        let mut net_builder = NetBuilder::new();
+
+        let mut net_if_cfg = create_netif("id", "dev7", "01:23:45:67:89:0d");
+        for speed in [0, MAX_LINK_SPEED_MBPS + 1] {
+            net_if_cfg.link_speed_mbps = Some(speed);
+            assert_eq!(
+                net_builder
+                    .build(net_if_cfg.clone())
+                    .err()
+                    .unwrap()
+                    .to_string(),
+                NetworkInterfaceError::InvalidLinkSpeed(speed).to_string()
+            );
+        }
+        assert_eq!(net_builder.net_devices.len(), 0);
+
+        // The speed defaults to 10 Gbps if only the duplex mode is given.
+        net_if_cfg.link_speed_mbps = None;
+        net_if_cfg.duplex = Some(Duplex::Half);
+        let net = net_builder.build(net_if_cfg.clone()).unwrap();
+        assert_eq!(
+            net.lock().unwrap().speed_duplex(),
+            Some((DEFAULT_LINK_SPEED_MBPS, Duplex::Half))
+        );
+
+        // The duplex mode defaults to full if only the speed is given.
+        net_if_cfg.link_speed_mbps = Some(1000);
+        net_if_cfg.duplex = None;
+        let net = net_builder.build(net_if_cfg.clone()).unwrap();
+        assert_eq!(
+            net.lock().unwrap().speed_duplex(),
+            Some((1000, Duplex::Full))
+        );
+
+        net_if_cfg.duplex = Some(Duplex::Full);
+        assert_eq!(net_builder.configs().first().unwrap(), &net_if_cfg);
+    }
+
     #[test]
     fn test_add_device() {
         let mut net_builder = NetBuilder::new();
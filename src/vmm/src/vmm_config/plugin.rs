@@ -0,0 +1,36 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// This struct represents the strongly typed equivalent of the json body describing a plugin
+/// device.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Path of the shared library implementing the plugin.
+    pub path: PathBuf,
+    /// Configuration passed as is to the plugin.
+    #[serde(default)]
+    pub config: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugin_config_serde() {
+        let config: PluginConfig =
+            serde_json::from_str(r#"{"path": "/usr/lib/libhsm.so", "config": {"slots": 4}}"#)
+                .unwrap();
+        assert_eq!(config.path, PathBuf::from("/usr/lib/libhsm.so"));
+        assert_eq!(config.config, serde_json::json!({"slots": 4}));
+
+        let config: PluginConfig = serde_json::from_str(r#"{"path": "libhsm.so"}"#).unwrap();
+        assert!(config.config.is_null());
+        serde_json::from_str::<PluginConfig>(r#"{"path": "libhsm.so", "foo": 1}"#).unwrap_err();
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright 2026 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::devices::virtio::pmem::{Pmem, PmemError};
+
+/// Errors associated with the operations allowed on a pmem device.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum PmemConfigError {
+    /// Unable to create the virtio-pmem device: {0}
+    CreateDevice(#[from] PmemError),
+    /// A root pmem device already exists!
+    RootPmemDeviceAlreadyAdded,
+    /// A root block device already exists!
+    RootBlockDeviceAlreadyAdded,
+}
+
+/// Use this structure to set up a pmem device before booting the kernel.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PmemConfig {
+    /// Unique identifier of the pmem device.
+    pub drive_id: String,
+    /// Path of the file backing the device, whose size must be a multiple of 2 MiB.
+    pub path_on_host: String,
+    /// If set to true, the device is the root device, mounted from /dev/pmem0 in the guest.
+    #[serde(default)]
+    pub is_root_device: bool,
+    /// If set to true, the guest cannot write to the device.
+    #[serde(default)]
+    pub is_read_only: bool,
+}
+
+/// Wrapper for the collection that holds all the pmem devices.
+#[derive(Debug, Default)]
+pub struct PmemBuilder {
+    /// The list of pmem devices.
+    /// There can be at most one root pmem device and it is the first in the list, so that the
+    /// guest names it /dev/pmem0.
+    pub devices: VecDeque<Arc<Mutex<Pmem>>>,
+}
+
+impl PmemBuilder {
+    /// Constructor for the pmem devices. It initializes an empty list.
+    pub fn new() -> Self {
+        Self {
+            devices: Default::default(),
+        }
+    }
+
+    /// Specifies whether there is a root pmem device already present in the list.
+    pub fn has_root_device(&self) -> bool {
+        // If there is a root device, it would be at the top of the list.
+        self.devices
+            .front()
+            .is_some_and(|pmem| pmem.lock().expect("Poisoned lock").root_device())
+    }
+
+    /// Gets the index of the device with the specified `drive_id` if it exists in the list.
+    fn get_index_of_drive_id(&self, drive_id: &str) -> Option<usize> {
+        self.devices
+            .iter()
+            .position(|pmem| pmem.lock().expect("Poisoned lock").id() == drive_id)
+    }
+
+    /// Inserts a `Pmem` in the pmem devices list using the specified configuration.
+    /// If a device with the same id already exists, it will overwrite it.
+    /// Inserting a secondary root pmem device will fail.
+    pub fn insert(&mut self, config: PmemConfig) -> Result<(), PmemConfigError> {
+        let position = self.get_index_of_drive_id(&config.drive_id);
+        let configured_as_root = config.is_root_device;
+
+        // Don't allow adding a second root pmem device.
+        if configured_as_root && self.has_root_device() && position != Some(0) {
+            return Err(PmemConfigError::RootPmemDeviceAlreadyAdded);
+        }
+
+        let pmem = Arc::new(Mutex::new(Pmem::new(config)?));
+
+        match position {
+            None if configured_as_root => self.devices.push_front(pmem),
+            None => self.devices.push_back(pmem),
+            Some(index) => {
+                self.devices[index] = pmem;
+                // Make sure the root device is on the first position.
+                if index != 0 && configured_as_root {
+                    self.devices.swap(0, index);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a vec with the structures used to configure the devices.
+    pub fn configs(&self) -> Vec<PmemConfig> {
+        self.devices
+            .iter()
+            .map(|pmem| pmem.lock().expect("Poisoned lock").config())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vmm_sys_util::tempfile::TempFile;
+
+    use super::*;
+    use crate::devices::virtio::pmem::PMEM_ALIGNMENT;
+
+    fn config(drive_id: &str, is_root_device: bool) -> (PmemConfig, TempFile) {
+        let file = TempFile::new().unwrap();
+        file.as_file().set_len(PMEM_ALIGNMENT).unwrap();
+        let config = PmemConfig {
+            drive_id: drive_id.to_string(),
+            path_on_host: file.as_path().to_str().unwrap().to_string(),
+            is_root_device,
+            is_read_only: false,
+        };
+        (config, file)
+    }
+
+    fn ids(builder: &PmemBuilder) -> Vec<String> {
+        builder
+            .configs()
+            .into_iter()
+            .map(|config| config.drive_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut builder = PmemBuilder::new();
+        let (config_1, _file_1) = config("pmem1", false);
+        let (config_2, _file_2) = config("pmem2", false);
+        builder.insert(config_1.clone()).unwrap();
+        builder.insert(config_2).unwrap();
+        assert!(!builder.has_root_device());
+        assert_eq!(ids(&builder), ["pmem1", "pmem2"]);
+        assert_eq!(builder.configs()[0], config_1);
+
+        // The root device goes first.
+        let (root, _root_file) = config("root", true);
+        builder.insert(root).unwrap();
+        assert!(builder.has_root_device());
+        assert_eq!(ids(&builder), ["root", "pmem1", "pmem2"]);
+
+        // A second root device is rejected.
+        let (root_2, _root_file_2) = config("root2", true);
+        assert!(matches!(
+            builder.insert(root_2),
+            Err(PmemConfigError::RootPmemDeviceAlreadyAdded)
+        ));
+
+        // Updating a device keeps its position, unless it becomes the root device.
+        let (mut update, _update_file) = config("pmem2", false);
+        update.is_read_only = true;
+        builder.insert(update.clone()).unwrap();
+        assert_eq!(ids(&builder), ["root", "pmem1", "pmem2"]);
+        assert_eq!(builder.configs()[2], update);
+
+        let (root, _root_file) = config("root", false);
+        builder.insert(root).unwrap();
+        let (root, _root_file) = config("pmem2", true);
+        builder.insert(root).unwrap();
+        assert_eq!(ids(&builder), ["pmem2", "pmem1", "root"]);
+
+        // Devices with invalid backing files are rejected.
+        let (mut invalid, _invalid_file) = config("invalid", false);
+        invalid.path_on_host = "/invalid/path".to_string();
+        assert!(matches!(
+            builder.insert(invalid),
+            Err(PmemConfigError::CreateDevice(PmemError::BackingFile(_)))
+        ));
+        assert_eq!(builder.devices.len(), 3);
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::BTreeMap;
+
+use kvm_bindings::KVM_CAP_PMU_EVENT_FILTER;
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of events of a PMU event filter (`KVM_PMU_EVENT_FILTER_MAX_EVENTS`).
+pub const MAX_PMU_EVENTS: usize = 300;
+
+/// This struct represents the strongly typed equivalent of the json body from vCPU PMU
+/// requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct VcpuPmuConfig {
+    /// Whether the PMU of the vCPU is exposed to the guest.
+    pub enabled: bool,
+    /// Events the guest is allowed to count, encoded as event select and unit mask. All events
+    /// are allowed when empty.
+    #[serde(default)]
+    pub allowed_events: Vec<u64>,
+}
+
+/// Errors associated with configuring the PMU of vCPUs.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum VcpuPmuConfigError {
+    /// Invalid vCPU index: {0}. The microVM has {1} vCPUs.
+    InvalidVcpuIndex(u8, u8),
+    /// Too many allowed events: {0}. At most 300 events can be allowed.
+    TooManyEvents(usize),
+    /// The allowed events of vCPU {0} differ from the ones of the vCPUs sharing its filter.
+    EventsMismatch(u8),
+    /// Failed to open KVM: {0}
+    Kvm(kvm_ioctls::Error),
+    /// The host kernel does not support PMU event filters.
+    EventFilterNotSupported,
+}
+
+/// The PMU configuration of the vCPUs of a microVM. The PMU of vCPUs without configuration is
+/// hidden from the guest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PmuConfig {
+    vcpus: BTreeMap<u8, VcpuPmuConfig>,
+}
+
+impl PmuConfig {
+    /// Sets the PMU configuration of the vCPU with index `index`.
+    pub fn set_vcpu(&mut self, index: u8, config: VcpuPmuConfig) -> Result<(), VcpuPmuConfigError> {
+        if config.allowed_events.len() > MAX_PMU_EVENTS {
+            return Err(VcpuPmuConfigError::TooManyEvents(
+                config.allowed_events.len(),
+            ));
+        }
+        if config.enabled
+            && self.vcpus.iter().any(|(other_index, other)| {
+                *other_index != index
+                    && other.enabled
+                    && other.allowed_events != config.allowed_events
+            })
+        {
+            return Err(VcpuPmuConfigError::EventsMismatch(index));
+        }
+        if config.enabled && !config.allowed_events.is_empty() {
+            check_event_filter_support()?;
+        }
+        self.vcpus.insert(index, config);
+        Ok(())
+    }
+
+    /// Returns whether the PMU of the vCPU with index `index` is exposed to the guest.
+    pub fn is_enabled(&self, index: u8) -> bool {
+        self.vcpus.get(&index).is_some_and(|config| config.enabled)
+    }
+
+    /// Returns the events allowed by the event filter of the microVM, if one is needed.
+    pub fn event_filter(&self) -> Option<&[u64]> {
+        self.vcpus
+            .values()
+            .find(|config| config.enabled && !config.allowed_events.is_empty())
+            .map(|config| config.allowed_events.as_slice())
+    }
+}
+
+fn check_event_filter_support() -> Result<(), VcpuPmuConfigError> {
+    let kvm = kvm_ioctls::Kvm::new().map_err(VcpuPmuConfigError::Kvm)?;
+    if kvm.check_extension_raw(u64::from(KVM_CAP_PMU_EVENT_FILTER)) == 0 {
+        return Err(VcpuPmuConfigError::EventFilterNotSupported);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pmu_config() {
+        let mut pmu = PmuConfig::default();
+        assert!(!pmu.is_enabled(0));
+        assert_eq!(pmu.event_filter(), None);
+
+        // Enabling the PMU without allowed events does not need a filter.
+        pmu.set_vcpu(
+            0,
+            VcpuPmuConfig {
+                enabled: true,
+                allowed_events: vec![],
+            },
+        )
+        .unwrap();
+        assert!(pmu.is_enabled(0));
+        assert!(!pmu.is_enabled(1));
+        assert_eq!(pmu.event_filter(), None);
+
+        // All vCPUs with the PMU enabled share the same filter.
+        let config = VcpuPmuConfig {
+            enabled: true,
+            allowed_events: vec![0x3c, 0xc0],
+        };
+        assert!(matches!(
+            pmu.set_vcpu(1, config.clone()),
+            Err(VcpuPmuConfigError::EventsMismatch(1))
+        ));
+        pmu.set_vcpu(0, config.clone()).unwrap();
+        pmu.set_vcpu(1, config).unwrap();
+        assert_eq!(pmu.event_filter(), Some([0x3c, 0xc0].as_slice()));
+
+        // vCPUs with the PMU disabled do not need to match the filter.
+        pmu.set_vcpu(
+            2,
+            VcpuPmuConfig {
+                enabled: false,
+                allowed_events: vec![0x3c],
+            },
+        )
+        .unwrap();
+        assert!(!pmu.is_enabled(2));
+
+        assert!(matches!(
+            pmu.set_vcpu(
+                0,
+                VcpuPmuConfig {
+                    enabled: true,
+                    allowed_events: vec![0; MAX_PMU_EVENTS + 1],
+                }
+            ),
+            Err(VcpuPmuConfigError::TooManyEvents(_))
+        ));
+    }
+}
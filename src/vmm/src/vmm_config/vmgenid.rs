@@ -0,0 +1,96 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+
+use crate::devices::acpi::vmgenid::VmGenIdError;
+
+/// This struct represents the strongly typed equivalent of the json body from VM generation ID
+/// related requests.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct VmGenIdConfig {
+    /// The generation ID, as a UUID in its canonical textual form.
+    pub uuid: String,
+}
+
+/// Errors associated with setting the generation ID of the microVM.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum VmGenIdConfigError {
+    /// Invalid generation ID {0}, expected a UUID such as 1b4e28ba-2fa1-11d2-883f-0016d3cca427.
+    InvalidUuid(String),
+    /// The microVM has no VMGenID device.
+    DeviceNotFound,
+    /// {0}
+    VmGenId(#[from] VmGenIdError),
+}
+
+impl VmGenIdConfig {
+    /// Returns the generation ID, whose little endian bytes are the bytes of the UUID in the
+    /// order they are written in.
+    pub fn gen_id(&self) -> Result<u128, VmGenIdConfigError> {
+        let invalid = || VmGenIdConfigError::InvalidUuid(self.uuid.clone());
+        let groups: Vec<&str> = self.uuid.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+        let hex = groups.concat();
+        if lengths != [8, 4, 4, 4, 12] || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let mut bytes = [0u8; 16];
+        for (byte, digits) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            // The digits were checked above.
+            *byte = u8::from_str_radix(std::str::from_utf8(digits).unwrap(), 16).unwrap();
+        }
+        Ok(u128::from_le_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(uuid: &str) -> VmGenIdConfig {
+        VmGenIdConfig {
+            uuid: uuid.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_gen_id() {
+        let gen_id = config("1b4e28ba-2fa1-11d2-883f-0016d3cca427")
+            .gen_id()
+            .unwrap();
+        assert_eq!(
+            gen_id.to_le_bytes(),
+            [
+                0x1b, 0x4e, 0x28, 0xba, 0x2f, 0xa1, 0x11, 0xd2, 0x88, 0x3f, 0x00, 0x16, 0xd3, 0xcc,
+                0xa4, 0x27
+            ]
+        );
+        assert_eq!(
+            config("1B4E28BA-2FA1-11D2-883F-0016D3CCA427")
+                .gen_id()
+                .unwrap(),
+            gen_id
+        );
+
+        for uuid in [
+            "",
+            "1b4e28ba2fa111d2883f0016d3cca427",
+            "1b4e28ba-2fa1-11d2-883f-0016d3cca42",
+            "1b4e28ba-2fa1-11d2-883f-0016d3cca4277",
+            "1b4e28ba-2fa1-11d2-883f-0016d3cca42g",
+            "+b4e28ba-2fa1-11d2-883f-0016d3cca427",
+            "1b4e28ba-2fa1-11d2-883f-0016d3cca\u{e9}7",
+        ] {
+            assert!(
+                matches!(
+                    config(uuid).gen_id(),
+                    Err(VmGenIdConfigError::InvalidUuid(_))
+                ),
+                "{uuid}"
+            );
+        }
+    }
+}
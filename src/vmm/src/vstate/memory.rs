@@ -22,7 +22,10 @@ use vmm_sys_util::errno;
 
 use crate::DirtyBitmap;
 use crate::utils::{get_page_size, u64_to_usize};
-use crate::vmm_config::machine_config::HugePageConfig;
+use crate::vmm_config::machine_config::{HugePageConfig, ThpMode};
+
+// Not exported by the libc crate yet, from `include/uapi/asm-generic/mman-common.h`.
+const MADV_COLLAPSE: libc::c_int = 25;
 
 /// Type of GuestMemoryMmap.
 pub type GuestMemoryMmap = vm_memory::GuestMemoryMmap<Option<AtomicBitmap>>;
@@ -46,8 +49,19 @@ pub enum MemoryError {
     Memfd(memfd::Error),
     /// Cannot resize memfd file: {0}
     MemfdSetLen(std::io::Error),
+    /// Cannot set the transparent huge pages mode of guest memory: {0}
+    Madvise(std::io::Error),
     /// Total sum of memory regions exceeds largest possible file offset
     OffsetTooLarge,
+    /// No KVM memory slot left: {used} of {max} slots in use, {reserved} reserved for hot-plug
+    SlotExhausted {
+        /// Number of slots in use.
+        used: usize,
+        /// Number of slots reserved for memory hot-plug.
+        reserved: usize,
+        /// Maximum number of slots of the VM.
+        max: usize,
+    },
 }
 
 /// Creates a `Vec` of `GuestRegionMmap` with the given configuration
@@ -132,6 +146,36 @@ pub fn snapshot_file(
     create(regions, libc::MAP_PRIVATE, Some(file), track_dirty_pages)
 }
 
+/// Applies the transparent huge pages `mode` to the guest memory `regions`.
+pub fn apply_thp_mode(regions: &[GuestRegionMmap], mode: ThpMode) -> Result<(), MemoryError> {
+    let advice = match mode {
+        ThpMode::Never => libc::MADV_NOHUGEPAGE,
+        ThpMode::Madvise | ThpMode::Always => libc::MADV_HUGEPAGE,
+    };
+    for region in regions {
+        // SAFETY: The range is a mapping we own, and the advice does not change its contents.
+        let ret = unsafe { libc::madvise(region.as_ptr().cast(), region.len(), advice) };
+        if ret < 0 {
+            return Err(MemoryError::Madvise(std::io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+/// Collapses the populated guest memory into transparent huge pages (`MADV_COLLAPSE`).
+///
+/// Only supported from Linux 6.1. The kernel also fails the ranges that are not populated yet.
+pub fn collapse_huge_pages(guest_memory: &GuestMemoryMmap) -> std::io::Result<()> {
+    for region in guest_memory.iter() {
+        // SAFETY: The range is a mapping we own, and collapsing it keeps its contents.
+        let ret = unsafe { libc::madvise(region.as_ptr().cast(), region.len(), MADV_COLLAPSE) };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
 /// Defines the interface for snapshotting memory.
 pub trait GuestMemoryExtension
 where
@@ -340,6 +384,82 @@ fn create_memfd(
     Ok(mem_file)
 }
 
+/// Keeps track of the KVM memory slots used by a VM, against the maximum number of slots KVM
+/// supports on the architecture.
+///
+/// Slots are handed out in order, so that the slot of a guest memory region is its index in the
+/// guest memory. Some slots can be reserved for memory hot-plug, so that they cannot be used up by
+/// other memory regions.
+#[derive(Debug)]
+pub struct MemorySlotManager {
+    max_slots: usize,
+    reserved_slots: usize,
+    used_slots: usize,
+}
+
+impl MemorySlotManager {
+    /// Creates a manager for a VM supporting `max_slots` memory slots.
+    pub fn new(max_slots: usize) -> Self {
+        Self {
+            max_slots,
+            reserved_slots: 0,
+            used_slots: 0,
+        }
+    }
+
+    /// Returns the number of slots in use.
+    pub fn used_slots(&self) -> usize {
+        self.used_slots
+    }
+
+    /// Reserves `count` more slots for memory hot-plug.
+    pub fn reserve(&mut self, count: usize) -> Result<(), MemoryError> {
+        if self.max_slots - self.used_slots - self.reserved_slots < count {
+            return Err(self.exhausted());
+        }
+        self.reserved_slots += count;
+        Ok(())
+    }
+
+    /// Allocates the next slot, without using up the slots reserved for memory hot-plug.
+    pub fn allocate(&mut self) -> Result<u32, MemoryError> {
+        if self.used_slots + self.reserved_slots >= self.max_slots {
+            return Err(self.exhausted());
+        }
+        self.next_slot()
+    }
+
+    /// Allocates the next slot out of the slots reserved for memory hot-plug.
+    pub fn allocate_reserved(&mut self) -> Result<u32, MemoryError> {
+        if self.reserved_slots == 0 {
+            return Err(self.exhausted());
+        }
+        let slot = self.next_slot()?;
+        self.reserved_slots -= 1;
+        Ok(slot)
+    }
+
+    /// Releases the last allocated `slot`, when it could not be registered with KVM.
+    pub fn release(&mut self, slot: u32) {
+        debug_assert_eq!(slot as usize + 1, self.used_slots);
+        self.used_slots -= 1;
+    }
+
+    fn next_slot(&mut self) -> Result<u32, MemoryError> {
+        let slot = u32::try_from(self.used_slots).map_err(|_| self.exhausted())?;
+        self.used_slots += 1;
+        Ok(slot)
+    }
+
+    fn exhausted(&self) -> MemoryError {
+        MemoryError::SlotExhausted {
+            used: self.used_slots,
+            reserved: self.reserved_slots,
+            max: self.max_slots,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::undocumented_unsafe_blocks)]
@@ -376,6 +496,59 @@ mod tests {
         }
     }
 
+    // Returns the size of the transparent huge pages backing the mapping at `addr`, in KiB.
+    fn anon_huge_pages_kib(addr: usize) -> u64 {
+        let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+        let mut in_mapping = false;
+        for line in smaps.lines() {
+            if let Some((start, end)) = line
+                .split_whitespace()
+                .next()
+                .and_then(|range| range.split_once('-'))
+                .and_then(|(start, end)| {
+                    Some((
+                        usize::from_str_radix(start, 16).ok()?,
+                        usize::from_str_radix(end, 16).ok()?,
+                    ))
+                })
+            {
+                in_mapping = (start..end).contains(&addr);
+            } else if let Some(value) = line.strip_prefix("AnonHugePages:") {
+                if in_mapping {
+                    return value.trim().trim_end_matches("kB").trim().parse().unwrap();
+                }
+            }
+        }
+        panic!("No mapping at {addr:#x}");
+    }
+
+    #[test]
+    fn test_apply_thp_mode() {
+        let region_size = mib_to_bytes(4);
+        let regions = anonymous(
+            [(GuestAddress(0), region_size)].into_iter(),
+            false,
+            HugePageConfig::None,
+        )
+        .unwrap();
+        for mode in [ThpMode::Never, ThpMode::Always, ThpMode::Madvise] {
+            apply_thp_mode(&regions, mode).unwrap();
+        }
+        let guest_memory = GuestMemoryMmap::from_regions(regions).unwrap();
+
+        // Populate the guest memory, so that all of it can be collapsed.
+        guest_memory
+            .write_slice(&vec![1u8; region_size], GuestAddress(0))
+            .unwrap();
+        let addr = guest_memory.get_host_address(GuestAddress(0)).unwrap() as usize;
+        match collapse_huge_pages(&guest_memory) {
+            // The 4 MiB region contains at least one aligned 2 MiB huge page.
+            Ok(()) => assert!(anon_huge_pages_kib(addr) >= 2048),
+            // Kernels older than 6.1 do not support MADV_COLLAPSE.
+            Err(err) => assert_eq!(err.raw_os_error(), Some(libc::EINVAL)),
+        }
+    }
+
     #[test]
     fn test_mark_dirty() {
         let page_size = get_page_size().unwrap();
@@ -722,4 +895,41 @@ mod tests {
         seals.insert(memfd::FileSeal::SealGrow);
         memfd.add_seals(&seals).unwrap_err();
     }
+
+    #[test]
+    fn test_memory_slot_manager() {
+        let mut slots = MemorySlotManager::new(4);
+        assert_eq!(slots.allocate().unwrap(), 0);
+        slots.reserve(2).unwrap();
+        assert_eq!(slots.allocate().unwrap(), 1);
+
+        // The remaining slots are reserved for hot-plug.
+        let err = slots.allocate().unwrap_err();
+        assert!(
+            matches!(
+                err,
+                MemoryError::SlotExhausted {
+                    used: 2,
+                    reserved: 2,
+                    max: 4
+                }
+            ),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.to_string(),
+            "No KVM memory slot left: 2 of 4 slots in use, 2 reserved for hot-plug"
+        );
+        slots.reserve(1).unwrap_err();
+
+        assert_eq!(slots.allocate_reserved().unwrap(), 2);
+        assert_eq!(slots.allocate_reserved().unwrap(), 3);
+        slots.allocate_reserved().unwrap_err();
+        slots.allocate().unwrap_err();
+        assert_eq!(slots.used_slots(), 4);
+
+        // A released slot is handed out again.
+        slots.release(3);
+        assert_eq!(slots.allocate().unwrap(), 3);
+    }
 }
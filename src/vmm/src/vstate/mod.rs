@@ -7,5 +7,7 @@ pub mod kvm;
 pub mod memory;
 /// Module with Vcpu implementation.
 pub mod vcpu;
+/// Module with the pool of pre-spawned vCPU threads.
+pub mod vcpu_pool;
 /// Module with Vm implementation.
 pub mod vm;
@@ -9,21 +9,28 @@ use std::cell::Cell;
 #[cfg(feature = "gdb")]
 use std::os::fd::AsRawFd;
 use std::sync::atomic::{Ordering, fence};
-use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel, sync_channel};
 use std::sync::{Arc, Barrier};
 use std::{fmt, io, thread};
 
-use kvm_bindings::{KVM_SYSTEM_EVENT_RESET, KVM_SYSTEM_EVENT_SHUTDOWN};
+use kvm_bindings::{
+    KVM_MEMORY_EXIT_FLAG_PRIVATE, KVM_SYSTEM_EVENT_RESET, KVM_SYSTEM_EVENT_SHUTDOWN,
+};
 use kvm_ioctls::VcpuExit;
 #[cfg(feature = "gdb")]
 use kvm_ioctls::VcpuFd;
 use libc::{c_int, c_void, siginfo_t};
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
 use vmm_sys_util::errno;
 use vmm_sys_util::eventfd::EventFd;
 
 use crate::FcExitCode;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use crate::arch::PersistentDebugState;
+#[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+use crate::arch::VcpuStateSnapshot;
 pub use crate::arch::{KvmVcpu, KvmVcpuConfigureError, KvmVcpuError, Peripherals, VcpuState};
+use crate::cgroup::VcpuCgroup;
 use crate::cpu_config::templates::{CpuConfiguration, GuestConfigError};
 #[cfg(feature = "gdb")]
 use crate::gdb::target::{GdbTargetError, get_raw_tid};
@@ -31,6 +38,8 @@ use crate::logger::{IncMetric, METRICS};
 use crate::seccomp::{BpfProgram, BpfProgramRef};
 use crate::utils::signal::{Killable, register_signal_handler, sigrtmin};
 use crate::utils::sm::StateMachine;
+use crate::vmm_config::machine_config::CpuBudget;
+use crate::vstate::vcpu_pool::vcpu_pool;
 use crate::vstate::vm::Vm;
 
 /// Signal number (SIGRTMIN) used to kick Vcpus.
@@ -75,9 +84,50 @@ pub struct VcpuConfig {
 type VcpuCell = Cell<Option<*mut Vcpu>>;
 
 /// Error type for [`Vcpu::start_threaded`].
-#[derive(Debug, derive_more::From, thiserror::Error)]
-#[error("Failed to spawn vCPU thread: {0}")]
-pub struct StartThreadedError(std::io::Error);
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum StartThreadedError {
+    /// Failed to spawn vCPU thread: {0}
+    Spawn(#[from] std::io::Error),
+    /// Failed to apply the CPU budget to the vCPU thread: {0}
+    CpuBudget(std::io::Error),
+}
+
+// Scheduling policy of `sched_setattr(2)` for deadline scheduling.
+const SCHED_DEADLINE: u32 = 6;
+
+// Argument of `sched_setattr(2)`, which libc does not define.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct SchedAttr {
+    size: u32,
+    sched_policy: u32,
+    sched_flags: u64,
+    sched_nice: i32,
+    sched_priority: u32,
+    sched_runtime: u64,
+    sched_deadline: u64,
+    sched_period: u64,
+}
+
+/// Schedules the calling thread with `SCHED_DEADLINE`, so that it gets `budget.runtime_ns` of CPU
+/// time within `budget.deadline_ns` of the start of every period of `budget.period_ns`.
+fn set_sched_deadline(budget: &CpuBudget) -> io::Result<()> {
+    let attr = SchedAttr {
+        size: u32::try_from(std::mem::size_of::<SchedAttr>()).unwrap(),
+        sched_policy: SCHED_DEADLINE,
+        sched_runtime: budget.runtime_ns,
+        sched_deadline: budget.deadline_ns,
+        sched_period: budget.period_ns,
+        ..Default::default()
+    };
+    // SAFETY: `attr` is a valid `sched_attr` whose size is given in its `size` field, and the
+    // kernel does not keep a reference to it after the call.
+    let ret = unsafe { libc::syscall(libc::SYS_sched_setattr, 0, &attr as *const SchedAttr, 0) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
 
 /// Error type for [`Vcpu::copy_kvm_vcpu_fd`].
 #[cfg(feature = "gdb")]
@@ -109,6 +159,10 @@ pub struct Vcpu {
     response_receiver: Option<Receiver<VcpuResponse>>,
     /// The transmitting end of the responses channel owned by the vcpu side.
     response_sender: Sender<VcpuResponse>,
+    /// CPU time reserved to the vcpu thread through deadline scheduling.
+    pub cpu_budget: Option<CpuBudget>,
+    /// Cgroup the vcpu thread joins, so that it is frozen along with the other vcpus.
+    pub vcpu_cgroup: Option<&'static VcpuCgroup>,
 }
 
 impl Vcpu {
@@ -215,6 +269,8 @@ impl Vcpu {
             #[cfg(feature = "gdb")]
             gdb_event: None,
             kvm_vcpu,
+            cpu_budget: None,
+            vcpu_cgroup: None,
         })
     }
 
@@ -250,16 +306,53 @@ impl Vcpu {
     ) -> Result<VcpuHandle, StartThreadedError> {
         let event_sender = self.event_sender.take().expect("vCPU already started");
         let response_receiver = self.response_receiver.take().unwrap();
-        let vcpu_thread = thread::Builder::new()
-            .name(format!("fc_vcpu {}", self.kvm_vcpu.index))
-            .spawn(move || {
-                let filter = &*seccomp_filter;
-                self.init_thread_local_data()
-                    .expect("Cannot cleanly initialize vcpu TLS.");
-                // Synchronization to make sure thread local data is initialized.
-                barrier.wait();
-                self.run(filter);
-            })?;
+        let cpu_budget = self.cpu_budget;
+        let (budget_sender, budget_receiver) = sync_channel(1);
+        let name = format!("fc_vcpu {}", self.kvm_vcpu.index);
+        // The threads of the pool run under the vCPU seccomp filter already, which does not allow
+        // setting the deadline scheduling policy of a CPU budget.
+        let idle_thread = vcpu_pool()
+            .filter(|_| cpu_budget.is_none())
+            .and_then(|pool| pool.acquire(usize::from(self.kvm_vcpu.index)));
+        let pooled = idle_thread.is_some();
+        let vcpu_fn = move || {
+            // The vCPU seccomp filter does not allow installing another filter.
+            let filter: BpfProgramRef = if pooled { &[] } else { &seccomp_filter };
+            if let Some(budget) = &self.cpu_budget {
+                let result = set_sched_deadline(budget);
+                let failed = result.is_err();
+                // The receiver waits for the result, so sending cannot fail.
+                budget_sender.send(result).unwrap();
+                if failed {
+                    return;
+                }
+            }
+            if let Some(cgroup) = self.vcpu_cgroup {
+                // Pausing still works without the cgroup, only not atomically.
+                if let Err(err) = cgroup.join() {
+                    error!(
+                        "vCPU {} cannot join the vCPU cgroup: {}",
+                        self.kvm_vcpu.index, err
+                    );
+                }
+            }
+            self.init_thread_local_data()
+                .expect("Cannot cleanly initialize vcpu TLS.");
+            // Synchronization to make sure thread local data is initialized.
+            barrier.wait();
+            self.run(filter);
+        };
+        let vcpu_thread = match idle_thread {
+            Some(idle_thread) => idle_thread.run(vcpu_fn),
+            None => thread::Builder::new().name(name).spawn(vcpu_fn)?,
+        };
+
+        if cpu_budget.is_some() {
+            budget_receiver
+                .recv()
+                .expect("vCPU thread exited before applying its CPU budget")
+                .map_err(StartThreadedError::CpuBudget)?;
+        }
 
         Ok(VcpuHandle::new(
             event_sender,
@@ -369,6 +462,32 @@ impl Vcpu {
                     )))
                     .expect("vcpu channel unexpectedly closed");
             }
+            // A single Vcpu can only be resumed through the API while it is paused.
+            #[cfg(feature = "debug-api")]
+            Ok(VcpuEvent::ResumePaused) => {
+                self.response_sender
+                    .send(VcpuResponse::NotAllowed(String::from(
+                        "vcpu is already running",
+                    )))
+                    .expect("vcpu channel unexpectedly closed");
+            }
+            // The architectural state cannot be accessed on a running Vcpu.
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            Ok(VcpuEvent::CaptureState | VcpuEvent::RestoreState(_)) => {
+                self.response_sender
+                    .send(VcpuResponse::NotAllowed(String::from(
+                        "vcpu state is unavailable while running",
+                    )))
+                    .expect("vcpu channel unexpectedly closed");
+            }
+            // Interrupts are injected into running Vcpus as well.
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            Ok(event @ (VcpuEvent::InjectNmi | VcpuEvent::InjectSmi)) => {
+                self.inject_interrupt(event)
+            }
+            // Breakpoints are set on running Vcpus as well.
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            Ok(VcpuEvent::SetDebugState(debug_state)) => self.set_debug_state(debug_state),
             Ok(VcpuEvent::Finish) => return StateMachine::finish(),
             // Unhandled exit of the other end.
             Err(TryRecvError::Disconnected) => {
@@ -382,25 +501,60 @@ impl Vcpu {
         state
     }
 
+    // Injects an NMI or an SMI, which is delivered the next time the vCPU enters the guest.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    fn inject_interrupt(&self, event: VcpuEvent) {
+        let result = match event {
+            VcpuEvent::InjectNmi => self.kvm_vcpu.fd.nmi().map_err(KvmVcpuError::VcpuInjectNmi),
+            VcpuEvent::InjectSmi => self.kvm_vcpu.fd.smi().map_err(KvmVcpuError::VcpuInjectSmi),
+            _ => unreachable!("not an interrupt injection event"),
+        };
+        let response = match result {
+            Ok(()) => VcpuResponse::InjectedInterrupt,
+            Err(err) => VcpuResponse::Error(VcpuError::VcpuResponse(err)),
+        };
+        self.response_sender
+            .send(response)
+            .expect("vcpu channel unexpectedly closed");
+    }
+
+    // Replaces the breakpoints set through the API, which take effect the next time the vCPU
+    // enters the guest.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    fn set_debug_state(&mut self, debug_state: PersistentDebugState) {
+        let response = match self.kvm_vcpu.set_persistent_debug(debug_state) {
+            Ok(()) => VcpuResponse::SetDebugState,
+            Err(err) => VcpuResponse::Error(VcpuError::VcpuResponse(err)),
+        };
+        self.response_sender
+            .send(response)
+            .expect("vcpu channel unexpectedly closed");
+    }
+
+    // Transition from the `Paused` state to the `Running` state.
+    fn resume(&mut self) -> StateMachine<Self> {
+        if self.kvm_vcpu.fd.get_kvm_run().immediate_exit == 1u8 {
+            warn!(
+                "Received a VcpuEvent::Resume message with immediate_exit enabled. immediate_exit \
+                 was disabled before proceeding"
+            );
+            self.kvm_vcpu.fd.set_kvm_immediate_exit(0);
+        }
+        // Nothing special to do.
+        self.response_sender
+            .send(VcpuResponse::Resumed)
+            .expect("vcpu channel unexpectedly closed");
+        // Move to 'running' state.
+        StateMachine::next(Self::running)
+    }
+
     // This is the main loop of the `Paused` state.
     fn paused(&mut self) -> StateMachine<Self> {
         match self.event_receiver.recv() {
             // Paused ---- Resume ----> Running
-            Ok(VcpuEvent::Resume) => {
-                if self.kvm_vcpu.fd.get_kvm_run().immediate_exit == 1u8 {
-                    warn!(
-                        "Received a VcpuEvent::Resume message with immediate_exit enabled. \
-                         immediate_exit was disabled before proceeding"
-                    );
-                    self.kvm_vcpu.fd.set_kvm_immediate_exit(0);
-                }
-                // Nothing special to do.
-                self.response_sender
-                    .send(VcpuResponse::Resumed)
-                    .expect("vcpu channel unexpectedly closed");
-                // Move to 'running' state.
-                StateMachine::next(Self::running)
-            }
+            Ok(VcpuEvent::Resume) => self.resume(),
+            #[cfg(feature = "debug-api")]
+            Ok(VcpuEvent::ResumePaused) => self.resume(),
             Ok(VcpuEvent::Pause) => {
                 self.response_sender
                     .send(VcpuResponse::Paused)
@@ -440,6 +594,40 @@ impl Vcpu {
 
                 StateMachine::next(Self::paused)
             }
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            Ok(VcpuEvent::CaptureState) => {
+                let response = match VcpuStateSnapshot::capture(&self.kvm_vcpu.fd) {
+                    Ok(snapshot) => VcpuResponse::CapturedState(Box::new(snapshot)),
+                    Err(err) => VcpuResponse::Error(VcpuError::VcpuResponse(err)),
+                };
+                self.response_sender
+                    .send(response)
+                    .expect("vcpu channel unexpectedly closed");
+
+                StateMachine::next(Self::paused)
+            }
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            Ok(VcpuEvent::RestoreState(snapshot)) => {
+                let response = match self.kvm_vcpu.restore_state_snapshot(&snapshot) {
+                    Ok(()) => VcpuResponse::RestoredState,
+                    Err(err) => VcpuResponse::Error(VcpuError::VcpuResponse(err)),
+                };
+                self.response_sender
+                    .send(response)
+                    .expect("vcpu channel unexpectedly closed");
+
+                StateMachine::next(Self::paused)
+            }
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            Ok(event @ (VcpuEvent::InjectNmi | VcpuEvent::InjectSmi)) => {
+                self.inject_interrupt(event);
+                StateMachine::next(Self::paused)
+            }
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            Ok(VcpuEvent::SetDebugState(debug_state)) => {
+                self.set_debug_state(debug_state);
+                StateMachine::next(Self::paused)
+            }
             Ok(VcpuEvent::Finish) => StateMachine::finish(),
             // Unhandled exit of the other end.
             Err(_) => {
@@ -490,6 +678,7 @@ impl Vcpu {
     ///
     /// Returns error or enum specifying whether emulation was handled or interrupted.
     pub fn run_emulation(&mut self) -> Result<VcpuEmulation, VcpuError> {
+        crate::ftrace!("run_emulation");
         if self.kvm_vcpu.fd.get_kvm_run().immediate_exit == 1u8 {
             warn!("Requested a vCPU run with immediate_exit enabled. The operation was skipped");
             self.kvm_vcpu.fd.set_kvm_immediate_exit(0);
@@ -508,10 +697,27 @@ impl Vcpu {
                     gdb_event
                         .send(get_raw_tid(self.kvm_vcpu.index.into()))
                         .expect("Unable to notify gdb event");
+                } else {
+                    // Without GDB, the vCPU hit a breakpoint set through the API and stays
+                    // paused until resumed through the API.
+                    info!("vCPU {} hit a breakpoint", self.kvm_vcpu.index);
+                    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                    if let Err(err) = self.kvm_vcpu.set_resume_flag() {
+                        warn!("Failed to set the resume flag of the vCPU: {}", err);
+                    }
                 }
 
                 Ok(VcpuEmulation::Paused)
             }
+            #[cfg(target_arch = "x86_64")]
+            Ok(VcpuExit::Exception) => {
+                debug!(
+                    "Received KVM_EXIT_EXCEPTION on vcpu {}: {}",
+                    self.kvm_vcpu.index,
+                    self.kvm_vcpu.exception_info()
+                );
+                handle_kvm_exit(&mut self.kvm_vcpu.peripherals, Ok(VcpuExit::Exception))
+            }
             emulation_result => handle_kvm_exit(&mut self.kvm_vcpu.peripherals, emulation_result),
         }
     }
@@ -571,6 +777,24 @@ fn handle_kvm_exit(
                     VcpuExit::InternalError
                 )))
             }
+            VcpuExit::MemoryFault { flags, gpa, size } => {
+                // KVM could not resolve a guest access to memory it expected to be mapped.
+                // Firecracker does not emulate machine checks, so the guest cannot recover.
+                METRICS.vcpu.exit_memory_fault.inc();
+                METRICS.vcpu.failures.inc();
+                let access = if flags & u64::from(KVM_MEMORY_EXIT_FLAG_PRIVATE) != 0 {
+                    "private"
+                } else {
+                    "shared"
+                };
+                error!(
+                    "Received KVM_EXIT_MEMORY_FAULT: {} access to GPA {:#x}, size {:#x}",
+                    access, gpa, size
+                );
+                Err(VcpuError::FaultyKvmExit(format!(
+                    "Memory fault: {access} access to GPA {gpa:#x}, size {size:#x}"
+                )))
+            }
             VcpuExit::SystemEvent(event_type, event_flags) => match event_type {
                 KVM_SYSTEM_EVENT_RESET | KVM_SYSTEM_EVENT_SHUTDOWN => {
                     info!(
@@ -632,10 +856,28 @@ pub enum VcpuEvent {
     Pause,
     /// Event to resume the Vcpu.
     Resume,
+    /// Event to resume a single Vcpu, which is refused if the Vcpu is already running.
+    #[cfg(feature = "debug-api")]
+    ResumePaused,
     /// Event to save the state of a paused Vcpu.
     SaveState,
     /// Event to dump CPU configuration of a paused Vcpu.
     DumpCpuConfig,
+    /// Event to capture the architectural state of a paused Vcpu.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    CaptureState,
+    /// Event to overwrite the architectural state of a paused Vcpu.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    RestoreState(Box<VcpuStateSnapshot>),
+    /// Event to inject a non-maskable interrupt into the Vcpu.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    InjectNmi,
+    /// Event to inject a system management interrupt into the Vcpu.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    InjectSmi,
+    /// Event to replace the breakpoints of the Vcpu.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    SetDebugState(PersistentDebugState),
 }
 
 /// List of responses that the Vcpu reports.
@@ -654,6 +896,18 @@ pub enum VcpuResponse {
     SavedState(Box<VcpuState>),
     /// Vcpu is in the state where CPU config is dumped.
     DumpedCpuConfig(Box<CpuConfiguration>),
+    /// Vcpu architectural state is captured.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    CapturedState(Box<VcpuStateSnapshot>),
+    /// Vcpu architectural state is restored.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    RestoredState,
+    /// Interrupt is injected into the Vcpu.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    InjectedInterrupt,
+    /// Vcpu breakpoints are replaced.
+    #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+    SetDebugState,
 }
 
 impl fmt::Debug for VcpuResponse {
@@ -667,6 +921,14 @@ impl fmt::Debug for VcpuResponse {
             Error(err) => write!(f, "VcpuResponse::Error({:?})", err),
             NotAllowed(reason) => write!(f, "VcpuResponse::NotAllowed({})", reason),
             DumpedCpuConfig(_) => write!(f, "VcpuResponse::DumpedCpuConfig"),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            CapturedState(_) => write!(f, "VcpuResponse::CapturedState"),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            RestoredState => write!(f, "VcpuResponse::RestoredState"),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            InjectedInterrupt => write!(f, "VcpuResponse::InjectedInterrupt"),
+            #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+            SetDebugState => write!(f, "VcpuResponse::SetDebugState"),
         }
     }
 }
@@ -836,6 +1098,24 @@ pub(crate) mod tests {
             )
         );
 
+        let res = handle_kvm_exit(
+            &mut vcpu.kvm_vcpu.peripherals,
+            Ok(VcpuExit::MemoryFault {
+                flags: u64::from(KVM_MEMORY_EXIT_FLAG_PRIVATE),
+                gpa: 0x1000,
+                size: 0x1000,
+            }),
+        );
+        assert_eq!(
+            format!("{:?}", res.unwrap_err()),
+            format!(
+                "{:?}",
+                EmulationError::FaultyKvmExit(
+                    "Memory fault: private access to GPA 0x1000, size 0x1000".to_string()
+                )
+            )
+        );
+
         // Check what happens with an unhandled exit reason.
         let res = handle_kvm_exit(&mut vcpu.kvm_vcpu.peripherals, Ok(VcpuExit::Unknown));
         assert_eq!(
@@ -902,6 +1182,12 @@ pub(crate) mod tests {
             match self {
                 Paused | Resumed | Exited(_) => (),
                 Error(_) | NotAllowed(_) | SavedState(_) | DumpedCpuConfig(_) => (),
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                InjectedInterrupt => (),
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                CapturedState(_) | RestoredState => (),
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                SetDebugState => (),
             };
             match (self, other) {
                 (Paused, Paused) | (Resumed, Resumed) => true,
@@ -909,6 +1195,12 @@ pub(crate) mod tests {
                 (NotAllowed(_), NotAllowed(_))
                 | (SavedState(_), SavedState(_))
                 | (DumpedCpuConfig(_), DumpedCpuConfig(_)) => true,
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                (InjectedInterrupt, InjectedInterrupt) => true,
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                (CapturedState(_), CapturedState(_)) | (RestoredState, RestoredState) => true,
+                #[cfg(all(target_arch = "x86_64", feature = "debug-api"))]
+                (SetDebugState, SetDebugState) => true,
                 (Error(err), Error(other_err)) => {
                     format!("{:?}", err) == format!("{:?}", other_err)
                 }
@@ -1064,6 +1356,44 @@ pub(crate) mod tests {
         vcpu.init_thread_local_data().unwrap_err();
     }
 
+    #[test]
+    fn test_set_sched_deadline() {
+        let budget = CpuBudget {
+            runtime_ns: 1_000_000,
+            deadline_ns: 10_000_000,
+            period_ns: 100_000_000,
+        };
+        // Use a separate thread so that the test runner thread keeps its scheduling policy.
+        let result = std::thread::spawn(move || {
+            set_sched_deadline(&budget).map(|()| unsafe { libc::sched_getscheduler(0) })
+        })
+        .join()
+        .unwrap();
+        let policy = match result {
+            Ok(policy) => policy,
+            // Setting SCHED_DEADLINE needs CAP_SYS_NICE, which the test runner may not have.
+            Err(err) if err.raw_os_error() == Some(libc::EPERM) => return,
+            Err(err) => panic!("{err}"),
+        };
+        assert_eq!(policy, i32::try_from(SCHED_DEADLINE).unwrap());
+
+        // The kernel rejects budgets with a runtime longer than the deadline.
+        let (_, _, mut vcpu) = setup_vcpu(0x1000);
+        vcpu.cpu_budget = Some(CpuBudget {
+            runtime_ns: 10_000_000,
+            deadline_ns: 1_000_000,
+            period_ns: 100_000_000,
+        });
+        let seccomp_filters = get_empty_filters();
+        assert!(matches!(
+            vcpu.start_threaded(
+                seccomp_filters.get("vcpu").unwrap().clone(),
+                Arc::new(Barrier::new(2))
+            ),
+            Err(StartThreadedError::CpuBudget(_))
+        ));
+    }
+
     #[test]
     fn test_vcpu_kick() {
         Vcpu::register_kick_signal_handler();
@@ -1177,6 +1507,27 @@ pub(crate) mod tests {
         vcpu_handle.send_event(VcpuEvent::Finish).unwrap();
     }
 
+    #[test]
+    #[cfg(feature = "debug-api")]
+    fn test_vcpu_resume_paused() {
+        let (_vm, vcpu_handle, _vcpu_exit_evt) = vcpu_configured_for_boot();
+
+        // A paused vCPU is resumed.
+        queue_event_expect_response(&vcpu_handle, VcpuEvent::ResumePaused, VcpuResponse::Resumed);
+
+        // A running vCPU is not.
+        queue_event_expect_response(
+            &vcpu_handle,
+            VcpuEvent::ResumePaused,
+            VcpuResponse::NotAllowed(String::new()),
+        );
+
+        queue_event_expect_response(&vcpu_handle, VcpuEvent::Pause, VcpuResponse::Paused);
+        queue_event_expect_response(&vcpu_handle, VcpuEvent::ResumePaused, VcpuResponse::Resumed);
+
+        vcpu_handle.send_event(VcpuEvent::Finish).unwrap();
+    }
+
     #[test]
     fn test_vcpu_save_state_events() {
         let (_vm, vcpu_handle, _vcpu_exit_evt) = vcpu_configured_for_boot();
@@ -0,0 +1,265 @@
+// Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Sender, channel, sync_channel};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use std::{fs, io, thread};
+
+use crate::logger::{info, warn};
+use crate::seccomp::BpfProgram;
+
+/// Pool of the Firecracker process, if it was given one.
+static VCPU_POOL: OnceLock<VcpuPool> = OnceLock::new();
+
+/// How often the host load is checked while waiting for the host to be idle.
+const HOST_IDLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+type VcpuThreadFn = Box<dyn FnOnce() + Send>;
+
+/// A thread spawned ahead of time, waiting for a vCPU to run.
+#[derive(Debug)]
+pub struct IdleVcpuThread {
+    index: usize,
+    work_sender: Sender<VcpuThreadFn>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl IdleVcpuThread {
+    /// Spawns the thread of the vCPU at `index`, which installs `seccomp_filter` before waiting
+    /// for the vCPU.
+    fn spawn(index: usize, seccomp_filter: Arc<BpfProgram>) -> io::Result<Self> {
+        let (work_sender, work_receiver) = channel::<VcpuThreadFn>();
+        let (installed_sender, installed_receiver) = sync_channel(1);
+        let thread = thread::Builder::new()
+            .name(format!("fc_vcpu {index}"))
+            .spawn(move || {
+                // The thread runs under the vCPU seccomp filter before taking any work, like the
+                // vCPU threads spawned when the microVM starts.
+                let installed = crate::seccomp::apply_filter(&seccomp_filter);
+                let failed = installed.is_err();
+                // The spawner waits for the result, so sending cannot fail.
+                installed_sender.send(installed).unwrap();
+                if failed {
+                    return;
+                }
+                // The thread exits without running anything if the pool is dropped.
+                if let Ok(work) = work_receiver.recv() {
+                    work();
+                }
+            })?;
+        installed_receiver
+            .recv()
+            .expect("Idle vCPU thread exited before installing its seccomp filter")
+            .map_err(io::Error::other)?;
+        Ok(IdleVcpuThread {
+            index,
+            work_sender,
+            thread,
+        })
+    }
+
+    /// Returns the name of the thread.
+    pub fn name(&self) -> &str {
+        self.thread.thread().name().unwrap_or_default()
+    }
+
+    /// Runs `f` on the thread, returning its handle.
+    ///
+    /// `f` runs under the vCPU seccomp filter the pool was created with.
+    pub fn run(self, f: impl FnOnce() + Send + 'static) -> thread::JoinHandle<()> {
+        // The thread only stops waiting when the sender is dropped.
+        self.work_sender
+            .send(Box::new(f))
+            .expect("Idle vCPU thread exited");
+        self.thread
+    }
+}
+
+/// A pool of threads spawned while the microVM is configured, which its vCPUs run on once it
+/// starts. Spawning the threads ahead of time takes it off the boot path.
+///
+/// The pool holds one thread per vCPU index below `max_idle_vcpus`, named `fc_vcpu {index}` as
+/// the vCPU it is handed out to. The threads install the vCPU seccomp filter before waiting. Once
+/// the microVM started, the pool is closed and the threads no vCPU took exit.
+#[derive(Debug)]
+pub struct VcpuPool {
+    max_idle_vcpus: usize,
+    seccomp_filter: Arc<BpfProgram>,
+    idle: Arc<Mutex<VecDeque<IdleVcpuThread>>>,
+    // Only changed with the `idle` lock held, so that no thread is added once the pool is closed.
+    closed: AtomicBool,
+}
+
+impl VcpuPool {
+    /// Creates an empty pool holding up to `max_idle_vcpus` idle threads, which run under
+    /// `seccomp_filter`.
+    pub fn new(max_idle_vcpus: usize, seccomp_filter: Arc<BpfProgram>) -> Self {
+        VcpuPool {
+            max_idle_vcpus,
+            seccomp_filter,
+            idle: Arc::new(Mutex::new(VecDeque::with_capacity(max_idle_vcpus))),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the maximum number of idle threads of the pool.
+    pub fn max_idle_vcpus(&self) -> usize {
+        self.max_idle_vcpus
+    }
+
+    /// Returns the number of idle threads in the pool.
+    pub fn idle_vcpus(&self) -> usize {
+        self.idle.lock().expect("Poisoned lock").len()
+    }
+
+    /// Spawns the missing idle threads, until the pool holds `max_idle_vcpus` of them.
+    pub fn fill(&self) -> io::Result<()> {
+        (0..self.max_idle_vcpus).try_for_each(|index| self.spawn_idle(index))
+    }
+
+    /// Spawns the idle thread of the vCPU at `index`, unless the pool holds it already or is
+    /// closed.
+    fn spawn_idle(&self, index: usize) -> io::Result<()> {
+        // The lock is held while spawning, so that a vCPU acquiring its thread in the meantime
+        // waits for it instead of spawning another one.
+        let mut idle = self.idle.lock().expect("Poisoned lock");
+        if self.closed.load(Ordering::Relaxed) || idle.iter().any(|thread| thread.index == index) {
+            return Ok(());
+        }
+        idle.push_back(IdleVcpuThread::spawn(
+            index,
+            Arc::clone(&self.seccomp_filter),
+        )?);
+        Ok(())
+    }
+
+    /// Takes the idle thread of the vCPU at `index` out of the pool, if any.
+    pub fn acquire(&self, index: usize) -> Option<IdleVcpuThread> {
+        let mut idle = self.idle.lock().expect("Poisoned lock");
+        let pos = idle.iter().position(|thread| thread.index == index)?;
+        idle.remove(pos)
+    }
+
+    /// Stops filling the pool, and waits for the idle threads left to exit.
+    pub fn close(&self) {
+        let unused = {
+            let mut idle = self.idle.lock().expect("Poisoned lock");
+            self.closed.store(true, Ordering::Relaxed);
+            std::mem::take(&mut *idle)
+        };
+        for IdleVcpuThread {
+            work_sender,
+            thread,
+            ..
+        } in unused
+        {
+            // Dropping the sender makes the thread exit.
+            drop(work_sender);
+            if thread.join().is_err() {
+                warn!("Idle vCPU thread panicked");
+            }
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+}
+
+/// Returns whether the host has CPU time to spare, i.e. whether its load over the last minute is
+/// lower than its number of CPUs. The host is assumed idle when its load cannot be read.
+fn host_idle() -> bool {
+    let Ok(loadavg) = fs::read_to_string("/proc/loadavg") else {
+        return true;
+    };
+    let Some(load) = loadavg
+        .split_whitespace()
+        .next()
+        .and_then(|load| load.parse::<f64>().ok())
+    else {
+        return true;
+    };
+    let cpus = thread::available_parallelism().map_or(1, usize::from);
+    load < cpus as f64
+}
+
+/// Creates the vCPU pool of the Firecracker process, and fills it in the background while the
+/// host is idle. The pool threads run under `seccomp_filter`.
+pub fn init_vcpu_pool(max_idle_vcpus: usize, seccomp_filter: Arc<BpfProgram>) -> io::Result<()> {
+    if VCPU_POOL
+        .set(VcpuPool::new(max_idle_vcpus, seccomp_filter))
+        .is_err()
+    {
+        return Err(io::Error::from(io::ErrorKind::AlreadyExists));
+    }
+    // The threads are spawned while the API server waits for the configuration of the microVM,
+    // without competing for the CPU with the other workloads of the host. The vCPUs whose thread
+    // is not spawned by the time the microVM starts spawn theirs.
+    thread::Builder::new()
+        .name("fc_vcpu_pool".to_string())
+        .spawn(|| {
+            let pool = vcpu_pool().expect("vCPU pool not set");
+            for index in 0..pool.max_idle_vcpus() {
+                while !pool.is_closed() && !host_idle() {
+                    thread::sleep(HOST_IDLE_POLL_INTERVAL);
+                }
+                if let Err(err) = pool.spawn_idle(index) {
+                    warn!("Cannot spawn the idle vCPU threads: {}", err);
+                    return;
+                }
+            }
+            info!("Spawned {} idle vCPU threads", pool.idle_vcpus());
+        })?;
+    Ok(())
+}
+
+/// Returns the vCPU pool of the Firecracker process, if it was given one.
+pub fn vcpu_pool() -> Option<&'static VcpuPool> {
+    VCPU_POOL.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn test_vcpu_pool() {
+        let pool = VcpuPool::new(2, Arc::new(vec![]));
+        assert_eq!(pool.idle_vcpus(), 0);
+        assert!(pool.acquire(0).is_none());
+
+        pool.fill().unwrap();
+        assert_eq!(pool.idle_vcpus(), 2);
+
+        // The threads are handed out to the vCPU they are named after, in any order.
+        let (sender, receiver) = channel();
+        let second = pool.acquire(1).unwrap();
+        assert_eq!(second.name(), "fc_vcpu 1");
+        let handle = second.run(move || {
+            sender
+                .send(thread::current().name().map(str::to_string))
+                .unwrap();
+        });
+        assert_eq!(receiver.recv().unwrap().as_deref(), Some("fc_vcpu 1"));
+        handle.join().unwrap();
+        assert_eq!(pool.idle_vcpus(), 1);
+        assert!(pool.acquire(1).is_none());
+
+        // Refilling the pool only spawns the missing threads.
+        pool.fill().unwrap();
+        assert_eq!(pool.idle_vcpus(), 2);
+        assert_eq!(pool.acquire(1).unwrap().name(), "fc_vcpu 1");
+        assert!(pool.acquire(2).is_none());
+
+        // Closing the pool joins the idle threads, and keeps it empty.
+        pool.close();
+        assert_eq!(pool.idle_vcpus(), 0);
+        pool.fill().unwrap();
+        assert!(pool.acquire(0).is_none());
+    }
+}
@@ -11,7 +11,7 @@ use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 
-use kvm_bindings::{KVM_MEM_LOG_DIRTY_PAGES, kvm_userspace_memory_region};
+use kvm_bindings::{KVM_MEM_LOG_DIRTY_PAGES, KVM_MEM_READONLY, kvm_userspace_memory_region};
 use kvm_ioctls::VmFd;
 use vmm_sys_util::eventfd::EventFd;
 
@@ -21,7 +21,8 @@ use crate::persist::CreateSnapshotError;
 use crate::utils::u64_to_usize;
 use crate::vmm_config::snapshot::SnapshotType;
 use crate::vstate::memory::{
-    Address, GuestMemory, GuestMemoryExtension, GuestMemoryMmap, GuestMemoryRegion, GuestRegionMmap,
+    Address, GuestAddress, GuestMemory, GuestMemoryExtension, GuestMemoryMmap, GuestMemoryRegion,
+    GuestMmapRegion, GuestRegionMmap, MemoryError, MemorySlotManager,
 };
 use crate::vstate::vcpu::VcpuError;
 use crate::{DirtyBitmap, Vcpu, mem_size_mib};
@@ -31,7 +32,7 @@ use crate::{DirtyBitmap, Vcpu, mem_size_mib};
 pub struct VmCommon {
     /// The KVM file descriptor used to access this Vm.
     pub fd: VmFd,
-    max_memslots: usize,
+    memory_slots: MemorySlotManager,
     /// The guest memory of this Vm.
     pub guest_memory: GuestMemoryMmap,
 }
@@ -51,8 +52,8 @@ pub enum VmError {
     EventFd(std::io::Error),
     /// Failed to create vcpu: {0}
     CreateVcpu(VcpuError),
-    /// The number of configured slots is bigger than the maximum reported by KVM
-    NotEnoughMemorySlots,
+    /// Cannot allocate a memory slot: {0}
+    MemorySlot(MemoryError),
     /// Memory Error: {0}
     VmMemory(#[from] vm_memory::Error),
 }
@@ -99,7 +100,7 @@ impl Vm {
 
         Ok(VmCommon {
             fd,
-            max_memslots: kvm.max_nr_memslots(),
+            memory_slots: MemorySlotManager::new(kvm.max_nr_memslots()),
             guest_memory: GuestMemoryMmap::default(),
         })
     }
@@ -138,15 +139,23 @@ impl Vm {
 
     /// Register a new memory region to this [`Vm`].
     pub fn register_memory_region(&mut self, region: GuestRegionMmap) -> Result<(), VmError> {
-        let next_slot = self
-            .guest_memory()
-            .num_regions()
-            .try_into()
-            .map_err(|_| VmError::NotEnoughMemorySlots)?;
-        if next_slot as usize >= self.common.max_memslots {
-            return Err(VmError::NotEnoughMemorySlots);
-        }
+        // Check the slot limit before KVM fails with an unhelpful ENOMEM.
+        let slot = self
+            .common
+            .memory_slots
+            .allocate()
+            .map_err(VmError::MemorySlot)?;
+        self.register_memory_region_at(slot, region)
+            .inspect_err(|_| {
+                self.common.memory_slots.release(slot);
+            })
+    }
 
+    fn register_memory_region_at(
+        &mut self,
+        slot: u32,
+        region: GuestRegionMmap,
+    ) -> Result<(), VmError> {
         let flags = if region.bitmap().is_some() {
             KVM_MEM_LOG_DIRTY_PAGES
         } else {
@@ -154,7 +163,7 @@ impl Vm {
         };
 
         let memory_region = kvm_userspace_memory_region {
-            slot: next_slot,
+            slot,
             guest_phys_addr: region.start_addr().raw_value(),
             memory_size: region.len(),
             userspace_addr: region.as_ptr() as u64,
@@ -175,6 +184,39 @@ impl Vm {
         Ok(())
     }
 
+    /// Registers a region of device memory, such as the mapping of the file backing a
+    /// virtio-pmem device, at `guest_addr` of the guest physical address space.
+    ///
+    /// Device memory is not part of the guest memory of this [`Vm`], so it is neither tracked
+    /// for dirty pages nor saved in snapshots. The caller must keep `region` mapped for as long as
+    /// the [`Vm`] exists.
+    pub fn register_device_memory(
+        &mut self,
+        guest_addr: GuestAddress,
+        region: &GuestMmapRegion,
+        read_only: bool,
+    ) -> Result<(), VmError> {
+        let slot = self
+            .common
+            .memory_slots
+            .allocate()
+            .map_err(VmError::MemorySlot)?;
+
+        let memory_region = kvm_userspace_memory_region {
+            slot,
+            guest_phys_addr: guest_addr.raw_value(),
+            memory_size: region.size() as u64,
+            userspace_addr: region.as_ptr() as u64,
+            flags: if read_only { KVM_MEM_READONLY } else { 0 },
+        };
+
+        // SAFETY: Safe because the fd is a valid KVM file descriptor and the caller keeps the
+        // region mapped for as long as the Vm exists.
+        unsafe { self.fd().set_user_memory_region(memory_region) }
+            .map_err(VmError::SetUserMemoryRegion)
+            .inspect_err(|_| self.common.memory_slots.release(slot))
+    }
+
     /// Gets a reference to the kvm file descriptor owned by this VM.
     pub fn fd(&self) -> &VmFd {
         &self.common.fd
@@ -364,7 +406,10 @@ pub(crate) mod tests {
 
             if i >= max_nr_regions {
                 assert!(
-                    matches!(res, Err(VmError::NotEnoughMemorySlots)),
+                    matches!(
+                        res,
+                        Err(VmError::MemorySlot(MemoryError::SlotExhausted { .. }))
+                    ),
                     "{:?} at iteration {} - max_nr_memslots: {}",
                     res,
                     i,
@@ -382,6 +427,26 @@ pub(crate) mod tests {
         }
     }
 
+    #[test]
+    fn test_register_device_memory() {
+        let (_, mut vm) = setup_vm_with_memory(0x1000);
+        let region = MmapRegionBuilder::new_with_bitmap(0x1000, None)
+            .build()
+            .unwrap();
+
+        // Device memory overlapping guest memory is rejected by KVM.
+        vm.register_device_memory(GuestAddress(0), &region, false)
+            .unwrap_err();
+
+        vm.register_device_memory(GuestAddress(0x20_0000), &region, false)
+            .unwrap();
+        vm.register_device_memory(GuestAddress(0x40_0000), &region, true)
+            .unwrap();
+        // Device memory does not show up in guest memory.
+        assert_eq!(vm.guest_memory().num_regions(), 1);
+        assert_eq!(vm.guest_memory().last_addr(), GuestAddress(0xfff));
+    }
+
     #[test]
     fn test_create_vcpus() {
         let vcpu_count = 2;
@@ -104,6 +104,7 @@ class Api:
         self.describe = Resource(self, "/")
         self.vm = Resource(self, "/vm")
         self.vm_config = Resource(self, "/vm/config")
+        self.trace_log = Resource(self, "/vm/trace-log")
         self.actions = Resource(self, "/actions")
         self.boot = Resource(self, "/boot-source")
         self.drive = Resource(self, "/drives", "drive_id")
@@ -120,4 +121,8 @@ class Api:
         self.snapshot_create = Resource(self, "/snapshot/create")
         self.snapshot_load = Resource(self, "/snapshot/load")
         self.cpu_config = Resource(self, "/cpu-config")
+        self.config_snapshot = Resource(self, "/config-snapshot")
         self.entropy = Resource(self, "/entropy")
+        self.transaction_begin = Resource(self, "/transaction/begin")
+        self.transaction_commit = Resource(self, "/transaction/commit")
+        self.transaction_rollback = Resource(self, "/transaction/rollback")
@@ -39,6 +39,11 @@ def create_metrics_schema_objects(metrics):
             metrics.pop(special_metrics)
             metrics_schema["properties"][special_metrics] = {"type": "number"}
             metrics_schema["required"].append(special_metrics)
+        special_metrics = "instance_id"
+        if special_metrics in metrics.keys():
+            metrics.pop(special_metrics)
+            metrics_schema["properties"][special_metrics] = {"type": "string"}
+            metrics_schema["required"].append(special_metrics)
 
         for sub_metrics_name, sub_metrics_fields in metrics.items():
             obj = create_metrics_schema_objects(sub_metrics_fields)
@@ -59,6 +64,15 @@ def create_metrics_schema_objects(metrics):
 
         return metrics_schema
 
+    if isinstance(metrics, tuple):
+        # fixed size arrays of numbers, one per named element
+        return {
+            "type": "array",
+            "items": {"type": "number"},
+            "minItems": len(metrics),
+            "maxItems": len(metrics),
+        }
+
     raise Exception("Invalid schema")
 
 
@@ -73,6 +87,15 @@ def validate_fc_metrics(metrics):
         "max_us",
         "sum_us",
     ]
+
+    # LatencyHistogramMetrics are serialized as an array of bucket counts
+    latency_histogram_metrics_fields = (
+        "lt_50_us",
+        "50_to_200_us",
+        "200_to_1000_us",
+        "1000_to_10000_us",
+        "ge_10000_us",
+    )
     block_metrics = [
         "activate_fails",
         "cfg_fails",
@@ -81,6 +104,8 @@ def validate_fc_metrics(metrics):
         "execute_fails",
         "invalid_reqs_count",
         "flush_count",
+        "discard_count",
+        "write_zeroes_count",
         "queue_event_count",
         "rate_limiter_event_count",
         "update_count",
@@ -94,6 +119,8 @@ def validate_fc_metrics(metrics):
         "remaining_reqs_count",
         {"read_agg": latency_agg_metrics_fields},
         {"write_agg": latency_agg_metrics_fields},
+        {"read_latency_hist": latency_histogram_metrics_fields},
+        {"write_latency_hist": latency_histogram_metrics_fields},
     ]
     net_metrics = [
         "activate_fails",
@@ -111,12 +138,14 @@ def validate_fc_metrics(metrics):
         "rx_packets_count",
         "rx_fails",
         "rx_count",
+        "rx_drops",
         "tap_read_fails",
         "tap_write_fails",
         "tx_bytes_count",
         "tx_malformed_frames",
         "tx_fails",
         "tx_count",
+        "tx_drops",
         "tx_packets_count",
         "tx_partial_reads",
         "tx_queue_event_count",
@@ -128,6 +157,7 @@ def validate_fc_metrics(metrics):
     ]
     firecracker_metrics = {
         "utc_timestamp_ms": "",
+        "instance_id": "",
         "api_server": [
             "process_startup_time_us",
             "process_startup_time_cpu_us",
@@ -191,6 +221,7 @@ def validate_fc_metrics(metrics):
             "tx_frames",
             "connections_created",
             "connections_destroyed",
+            "connections_rate_limited",
         ],
         "net": net_metrics,
         "patch_api_requests": [
@@ -233,6 +264,7 @@ def validate_fc_metrics(metrics):
             "exit_io_out",
             "exit_mmio_read",
             "exit_mmio_write",
+            "exit_memory_fault",
             "failures",
             "kvmclock_ctrl_fails",
             {"exit_io_in_agg": latency_agg_metrics_fields},
@@ -292,6 +324,14 @@ def validate_fc_metrics(metrics):
             "entropy_rate_limiter_throttled",
             "rate_limiter_event_count",
         ],
+        "pmem": [
+            "activate_fails",
+            "cfg_fails",
+            "event_count",
+            "event_fails",
+            "flush_count",
+            "flush_fails",
+        ],
     }
 
     # validate timestamp before jsonschema validation which some more time
@@ -415,6 +455,14 @@ class FcDeviceMetrics:
                         metrics_calculated[metrics_name]["sum_us"] += metric_value[
                             "sum_us"
                         ]
+                    elif isinstance(metric_value, list):
+                        # this is for LatencyHistogramMetrics metrics type
+                        if metrics_name not in metrics_calculated:
+                            metrics_calculated[metrics_name] = [0] * len(
+                                metric_value
+                            )
+                        for i, bucket in enumerate(metric_value):
+                            metrics_calculated[metrics_name][i] += bucket
 
         assert self.num_dev == actual_num_devices
         if self.aggr_supported:
@@ -450,6 +498,8 @@ def flush_fc_metrics_to_cw(fc_metrics, metrics):
 
     # Pre-order tree traversal to convert a tree into its list of paths with dot separate segments
     def flatten_dict(node, prefix: str):
+        if isinstance(node, list):
+            return {f"{prefix}.{i}": value for i, value in enumerate(node)}
         if not isinstance(node, dict):
             return {prefix: node}
 
@@ -461,7 +511,7 @@ def flush_fc_metrics_to_cw(fc_metrics, metrics):
     flattened_metrics = flatten_dict(fc_metrics, "fc_metrics")
 
     for key, value in flattened_metrics.items():
-        if ".utc_timestamp_ms." in key:
+        if ".utc_timestamp_ms." in key or key.endswith(".instance_id"):
             continue
         metrics.put_metric(key, value, get_emf_unit_for_fc_metrics(key))
 
@@ -0,0 +1,16 @@
+# Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+# SPDX-License-Identifier: Apache-2.0
+"""A test that ensures that firecracker builds with the arp-verbose feature enabled."""
+
+import platform
+
+import host_tools.cargo_build as host
+
+MACHINE = platform.machine()
+TARGET = "{}-unknown-linux-musl".format(MACHINE)
+
+
+def test_arp_verbose_compiles():
+    """Checks that Firecracker compiles with the ARP frames logged at debug level"""
+
+    host.cargo("build", f"--features arp-verbose --target {TARGET}")
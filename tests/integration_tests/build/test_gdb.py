@@ -4,6 +4,8 @@
 
 import platform
 
+import pytest
+
 import host_tools.cargo_build as host
 
 MACHINE = platform.machine()
@@ -14,3 +16,29 @@ def test_gdb_compiles():
     """Checks that Firecracker compiles with GDB enabled"""
 
     host.cargo("build", f"--features gdb --target {TARGET}")
+
+
+def test_debug_api_compiles():
+    """Checks that Firecracker compiles with the debug API enabled"""
+
+    host.cargo("build", f"--features debug-api --target {TARGET}")
+
+
+def test_debug_api_release_compiles():
+    """
+    Checks that Firecracker compiles in release mode with the debug API enabled, which merges
+    the debug API seccomp rules into the default filters
+    """
+
+    host.cargo("build", f"--release --features debug-api --target {TARGET}")
+
+
+@pytest.mark.timeout(600)
+def test_debug_api_unittests(test_fc_session_root_path):
+    """
+    Run the unit tests of the code only built with the debug API enabled.
+    """
+
+    host.cargo_test(
+        test_fc_session_root_path, extra_args=f"--features debug-api --target {TARGET}"
+    )
@@ -0,0 +1,16 @@
+# Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+# SPDX-License-Identifier: Apache-2.0
+"""A test that ensures that firecracker builds with the perf-counters feature enabled."""
+
+import platform
+
+import host_tools.cargo_build as host
+
+MACHINE = platform.machine()
+TARGET = "{}-unknown-linux-musl".format(MACHINE)
+
+
+def test_perf_counters_compiles():
+    """Checks that Firecracker compiles with the VMM performance counters enabled"""
+
+    host.cargo("build", f"--features perf-counters --target {TARGET}")
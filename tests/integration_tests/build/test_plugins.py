@@ -0,0 +1,26 @@
+# Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+# SPDX-License-Identifier: Apache-2.0
+"""Tests ensuring that firecracker builds with the plugins feature enabled."""
+
+import platform
+
+import host_tools.cargo_build as host
+
+MACHINE = platform.machine()
+TARGET = "{}-unknown-linux-musl".format(MACHINE)
+
+
+def test_plugins_compiles():
+    """Checks that Firecracker compiles with plugin devices enabled"""
+
+    host.cargo("build", f"--features plugins --target {TARGET}")
+
+
+def test_plugins_clippy():
+    """Checks that clippy does not generate any errors/warnings with plugin devices enabled"""
+
+    host.cargo(
+        "clippy",
+        f"--features plugins --target {TARGET} --all --profile test",
+        "-D warnings",
+    )
@@ -0,0 +1,33 @@
+# Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+# SPDX-License-Identifier: Apache-2.0
+
+"""Tests for the ACPI tables of the microVM."""
+
+import platform
+
+import pytest
+
+
+@pytest.mark.skipif(
+    platform.machine() != "x86_64", reason="ACPI is only supported on x86_64"
+)
+def test_dsdt_osc(uvm_plain_any):
+    """
+    Test that the DSDT of the guest holds the platform-wide `_OSC` method.
+    """
+    test_microvm = uvm_plain_any
+    test_microvm.spawn()
+    test_microvm.basic_config()
+    test_microvm.add_net_iface()
+    test_microvm.start()
+
+    # `\_SB._OSC` is encoded with a DualNamePrefix, as `_SB__OSC`.
+    _, stdout, _ = test_microvm.ssh.check_output(
+        "grep -c -a _SB__OSC /sys/firmware/acpi/tables/DSDT"
+    )
+    assert int(stdout) == 1
+
+    # The guest negotiated its capabilities without errors.
+    _, stdout, _ = test_microvm.ssh.check_output("dmesg")
+    assert "_OSC request failed" not in stdout
+    assert "_OSC invalid" not in stdout
@@ -4,6 +4,7 @@
 
 # Disable pylint C0302: Too many lines in module
 # pylint: disable=C0302
+import copy
 import os
 import platform
 import re
@@ -770,6 +771,158 @@ def test_send_ctrl_alt_del(uvm_plain_any):
     test_microvm.mark_killed()
 
 
+def test_api_vcpu_state(uvm_plain_any):
+    """
+    Test that single vCPUs cannot be controlled in builds without the debug API.
+    """
+    test_microvm = uvm_plain_any
+    test_microvm.spawn()
+    test_microvm.basic_config(vcpu_count=2)
+    test_microvm.add_net_iface()
+    test_microvm.start()
+
+    url = test_microvm.api.endpoint + "/vm/vcpu/1"
+    res = test_microvm.api.session.patch(url, json={"state": "Paused"})
+    assert res.status_code == 400
+    assert test_microvm.api.session.get(url + "/state").status_code == 400
+    assert test_microvm.api.session.put(url + "/state", json={}).status_code == 400
+    test_microvm.ssh.check_output("true")
+
+
+@pytest.mark.skipif(
+    platform.machine() != "x86_64", reason="The VM clock is only supported on x86_64."
+)
+def test_api_vm_clock(uvm_plain_any):
+    """
+    Test re-synchronizing the guest clock after pausing the microVM.
+    """
+    test_microvm = uvm_plain_any
+    test_microvm.spawn()
+    test_microvm.basic_config()
+    test_microvm.add_net_iface()
+
+    url = test_microvm.api.endpoint + "/vm/clock"
+    # The clock is not available before boot.
+    res = test_microvm.api.session.get(url)
+    assert res.status_code == 400
+    assert NOT_SUPPORTED_BEFORE_START in res.json()["fault_message"]
+
+    test_microvm.start()
+
+    def guest_uptime():
+        _, stdout, _ = test_microvm.ssh.check_output("cat /proc/uptime")
+        return float(stdout.split()[0])
+
+    uptime = guest_uptime()
+    test_microvm.api.vm.patch(state="Paused")
+    res = test_microvm.api.session.get(url)
+    assert res.status_code == 200
+    clock = res.json()
+    time.sleep(1)
+    test_microvm.api.vm.request("PUT", "/vm/clock", **clock)
+    test_microvm.api.vm.patch(state="Resumed")
+
+    # The guest clock advanced by the time the microVM was paused.
+    elapsed = guest_uptime() - uptime
+    assert 1 <= elapsed < 3, elapsed
+
+
+def test_api_vm_gen_id(uvm_plain_any):
+    """
+    Test setting the generation ID of the microVM.
+    """
+    test_microvm = uvm_plain_any
+    test_microvm.spawn()
+    test_microvm.basic_config()
+    test_microvm.add_net_iface()
+
+    url = test_microvm.api.endpoint + "/vm/gen-id"
+    uuid = "1b4e28ba-2fa1-11d2-883f-0016d3cca427"
+    # The generation ID cannot be set before boot.
+    res = test_microvm.api.session.put(url, json={"uuid": uuid})
+    assert res.status_code == 400
+    assert NOT_SUPPORTED_BEFORE_START in res.json()["fault_message"]
+
+    test_microvm.start()
+
+    res = test_microvm.api.session.put(url, json={"uuid": "not-a-uuid"})
+    assert res.status_code == 400
+    assert "Invalid generation ID" in res.json()["fault_message"]
+
+    test_microvm.api.vm.request("PUT", "/vm/gen-id", uuid=uuid)
+    test_microvm.ssh.check_output("true")
+
+
+def test_api_inject_interrupt(uvm_plain_any):
+    """
+    Test that interrupts cannot be injected in builds without the debug API.
+    """
+    test_microvm = uvm_plain_any
+    test_microvm.spawn()
+    test_microvm.basic_config()
+    test_microvm.add_net_iface()
+    test_microvm.start()
+
+    url = test_microvm.api.endpoint + "/vm/inject-interrupt"
+    res = test_microvm.api.session.put(url, json={"vcpu_id": 0, "type": "nmi"})
+    assert res.status_code == 400
+    test_microvm.ssh.check_output("true")
+
+
+@pytest.mark.skipif(
+    platform.machine() != "x86_64"
+    or utils_cpuid.get_cpu_vendor() != utils_cpuid.CpuVendor.INTEL,
+    reason="Exposing the PMU is only supported on Intel x86_64.",
+)
+def test_api_vcpu_pmu(uvm_plain_any):
+    """
+    Test exposing the PMU to the guest.
+    """
+    test_microvm = uvm_plain_any
+    test_microvm.spawn()
+    test_microvm.basic_config(vcpu_count=2)
+    test_microvm.add_net_iface()
+
+    with pytest.raises(RuntimeError, match="Invalid vCPU index: 2"):
+        test_microvm.api.vm.request("PUT", "/vm/vcpu/2/pmu", enabled=True)
+
+    # vCPUs with the PMU enabled share the same event filter.
+    events = [0x003C, 0x00C0]
+    test_microvm.api.vm.request(
+        "PUT", "/vm/vcpu/0/pmu", enabled=True, allowed_events=events
+    )
+    with pytest.raises(RuntimeError, match="allowed events of vCPU 1 differ"):
+        test_microvm.api.vm.request(
+            "PUT", "/vm/vcpu/1/pmu", enabled=True, allowed_events=[0x003C]
+        )
+    test_microvm.api.vm.request(
+        "PUT", "/vm/vcpu/1/pmu", enabled=True, allowed_events=events
+    )
+
+    test_microvm.start()
+
+    with pytest.raises(RuntimeError, match=NOT_SUPPORTED_AFTER_START):
+        test_microvm.api.vm.request("PUT", "/vm/vcpu/0/pmu", enabled=False)
+
+    _, stdout, _ = test_microvm.ssh.check_output("grep -c arch_perfmon /proc/cpuinfo")
+    assert int(stdout) == 2
+
+
+def test_api_page_table(uvm_plain_any):
+    """
+    Test that the guest page tables cannot be walked in builds without the debug API.
+    """
+    test_microvm = uvm_plain_any
+    test_microvm.spawn()
+    test_microvm.basic_config()
+    test_microvm.add_net_iface()
+    test_microvm.start()
+
+    url = test_microvm.api.endpoint + "/vm/page-table"
+    res = test_microvm.api.session.get(url, params={"cr3": "0x1000", "cr4": "0"})
+    assert res.status_code == 400
+
+
 def _drive_patch(test_microvm, io_engine):
     """Exercise drive patch test scenarios."""
     # Patches without mandatory fields for virtio block are not allowed.
@@ -984,6 +1137,112 @@ def test_api_entropy(uvm_plain):
         test_microvm.api.entropy.put()
 
 
+def test_api_trace_log(uvm_plain):
+    """
+    Test that the trace log cannot be drained in builds without the debug API.
+    """
+    test_microvm = uvm_plain
+    test_microvm.spawn()
+    test_microvm.basic_config()
+
+    url = test_microvm.api.endpoint + "/vm/trace-log"
+    res = test_microvm.api.session.get(url)
+    assert res.status_code == 400
+
+    test_microvm.start()
+
+    res = test_microvm.api.session.get(url, params={"last_n": 1})
+    assert res.status_code == 400
+
+
+def test_api_transaction_commit(uvm_plain):
+    """
+    Test that the changes of a transaction are applied once committed.
+    """
+    test_microvm = uvm_plain
+    test_microvm.spawn()
+    test_microvm.basic_config(vcpu_count=2, mem_size_mib=256)
+
+    test_microvm.api.transaction_begin.put()
+    with pytest.raises(RuntimeError, match="A transaction is already in progress."):
+        test_microvm.api.transaction_begin.put()
+    test_microvm.api.machine_config.patch(mem_size_mib=512)
+    # The change is buffered until the transaction is committed.
+    assert test_microvm.api.machine_config.get().json()["mem_size_mib"] == 256
+    with pytest.raises(RuntimeError, match="while a transaction is in progress"):
+        test_microvm.api.actions.put(action_type="InstanceStart")
+
+    test_microvm.api.transaction_commit.put()
+    assert test_microvm.api.machine_config.get().json()["mem_size_mib"] == 512
+
+    test_microvm.start()
+    with pytest.raises(RuntimeError, match=NOT_SUPPORTED_AFTER_START):
+        test_microvm.api.transaction_begin.put()
+
+
+def test_api_transaction_rollback(uvm_plain):
+    """
+    Test that rolled back and failed transactions leave the configuration untouched.
+    """
+    test_microvm = uvm_plain
+    test_microvm.spawn()
+    test_microvm.basic_config(vcpu_count=2)
+    config = test_microvm.api.vm_config.get().json()
+
+    with pytest.raises(RuntimeError, match="No transaction is in progress."):
+        test_microvm.api.transaction_rollback.put()
+
+    test_microvm.api.transaction_begin.put()
+    test_microvm.api.machine_config.patch(vcpu_count=1)
+    fs = drive_tools.FilesystemFile(os.path.join(test_microvm.fsfiles, "scratch"))
+    test_microvm.add_drive("scratch", fs.path)
+    test_microvm.api.transaction_rollback.put()
+    assert test_microvm.api.vm_config.get().json() == config
+
+    # A failing change reverts the changes which preceded it.
+    test_microvm.api.transaction_begin.put()
+    test_microvm.api.machine_config.patch(vcpu_count=1)
+    test_microvm.api.drive.put(
+        drive_id="scratch",
+        path_on_host="/invalid/path",
+        is_root_device=False,
+        is_read_only=False,
+    )
+    with pytest.raises(RuntimeError, match="Step 1 of the transaction failed"):
+        test_microvm.api.transaction_commit.put()
+    assert test_microvm.api.vm_config.get().json() == config
+
+    test_microvm.start()
+
+
+def test_api_config_snapshot(uvm_plain):
+    """
+    Test that a saved configuration can be restored before boot.
+    """
+    test_microvm = uvm_plain
+    test_microvm.spawn()
+    test_microvm.basic_config(vcpu_count=2)
+    snapshot = test_microvm.api.config_snapshot.get().json()
+    assert snapshot["snapshot"] == test_microvm.api.vm_config.get().json()
+
+    test_microvm.api.machine_config.patch(vcpu_count=1)
+    fs = drive_tools.FilesystemFile(os.path.join(test_microvm.fsfiles, "scratch"))
+    test_microvm.add_drive("scratch", fs.path)
+    test_microvm.api.config_snapshot.put(**snapshot)
+    assert test_microvm.api.config_snapshot.get().json() == snapshot
+
+    # A configuration which cannot be applied leaves the current one untouched.
+    invalid = copy.deepcopy(snapshot)
+    invalid["snapshot"]["boot-source"]["kernel_image_path"] = "/invalid/path"
+    with pytest.raises(RuntimeError, match="Config snapshot error"):
+        test_microvm.api.config_snapshot.put(**invalid)
+    assert test_microvm.api.config_snapshot.get().json() == snapshot
+
+    test_microvm.start()
+    with pytest.raises(RuntimeError, match=NOT_SUPPORTED_AFTER_START):
+        test_microvm.api.config_snapshot.put(**snapshot)
+
+
 def test_api_balloon(uvm_nano):
     """
     Test balloon related API commands.
@@ -560,3 +560,77 @@ def test_memory_scrub(microvm_factory, guest_kernel, rootfs):
     _ = get_stable_rss_mem_by_pid(firecracker_pid)
 
     microvm.ssh.check_output("/usr/local/bin/readmem {} {}".format(60, 1))
+
+
+@retry(wait=wait_fixed(1), stop=stop_after_attempt(15), reraise=True)
+def wait_for_balloon_target(microvm, check):
+    """Wait for the balloon policy to set a target size accepted by `check`."""
+    amount_mib = microvm.api.balloon.get().json()["amount_mib"]
+    assert check(amount_mib), amount_mib
+    return amount_mib
+
+
+def test_balloon_policy_static(uvm_plain_any):
+    """
+    Check that a static balloon policy inflates the balloon without API calls.
+    """
+    test_microvm = uvm_plain_any
+    test_microvm.spawn()
+    test_microvm.basic_config(mem_size_mib=256)
+    test_microvm.api.machine_config.patch(
+        balloon_policy={"Static": {"target_mib": 64}}
+    )
+    test_microvm.add_net_iface()
+    test_microvm.api.balloon.put(
+        amount_mib=0, deflate_on_oom=False, stats_polling_interval_s=0
+    )
+    test_microvm.start()
+
+    available_mem_deflated = get_free_mem_ssh(test_microvm.ssh)
+    wait_for_balloon_target(test_microvm, lambda amount_mib: amount_mib == 64)
+    _ = get_stable_rss_mem_by_pid(test_microvm.firecracker_pid)
+
+    # Assert that ballooning reclaimed about 64 MB of memory.
+    available_mem_inflated = get_free_mem_ssh(test_microvm.ssh)
+    assert available_mem_inflated <= available_mem_deflated - 85 * 64000 / 100
+
+
+def test_balloon_policy_host_pressure(uvm_plain_any):
+    """
+    Check that the balloon inflates when the host is short of available memory.
+    """
+    test_microvm = uvm_plain_any
+    test_microvm.spawn()
+    test_microvm.basic_config(mem_size_mib=256)
+    # The host can never keep all of its memory available, so the policy sees
+    # memory pressure and keeps inflating the balloon up to the guest memory size.
+    test_microvm.api.machine_config.patch(
+        balloon_policy={"ProportionalToFreeHost": {"target_free_pct": 100}}
+    )
+    test_microvm.add_net_iface()
+    test_microvm.api.balloon.put(
+        amount_mib=0, deflate_on_oom=True, stats_polling_interval_s=0
+    )
+    test_microvm.start()
+
+    wait_for_balloon_target(test_microvm, lambda amount_mib: amount_mib == 256)
+
+
+def test_balloon_policy_requires_balloon(uvm_plain_any):
+    """
+    Check that a balloon policy cannot be used without a balloon device.
+    """
+    test_microvm = uvm_plain_any
+    test_microvm.spawn()
+    test_microvm.basic_config()
+
+    with pytest.raises(RuntimeError, match="at most 100%"):
+        test_microvm.api.machine_config.patch(
+            balloon_policy={"ProportionalToFreeHost": {"target_free_pct": 101}}
+        )
+
+    test_microvm.api.machine_config.patch(
+        balloon_policy={"Static": {"target_mib": 64}}
+    )
+    with pytest.raises(RuntimeError, match="without a balloon device"):
+        test_microvm.start()
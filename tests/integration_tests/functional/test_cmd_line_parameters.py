@@ -2,10 +2,13 @@
 # SPDX-License-Identifier: Apache-2.0
 """Tests that ensure the correctness of the command line parameters."""
 
+import json
+import re
 import subprocess
 from pathlib import Path
 
 import pytest
+from tenacity import retry, stop_after_attempt, wait_fixed
 
 from framework.utils import check_output
 from host_tools.fcmetrics import validate_fc_metrics
@@ -116,3 +119,164 @@ def test_cli_no_params(microvm_factory):
     except subprocess.TimeoutExpired:
         # The good case
         process.kill()
+
+
+@retry(wait=wait_fixed(0.1), stop=stop_after_attempt(30), reraise=True)
+def wait_for_path(path):
+    """Wait for a path to exist."""
+    assert path.exists()
+
+
+def test_cli_oom_score_adj(microvm_factory, tmp_path):
+    """
+    Test --oom-score-adj parameter
+    """
+    fc_binary = microvm_factory.fc_binary_path
+    api_sock = tmp_path / "api.sock"
+    process = subprocess.Popen(
+        [fc_binary, "--api-sock", api_sock, "--oom-score-adj", "-500"]
+    )
+    try:
+        # The value is written before the API socket is created.
+        wait_for_path(api_sock)
+        oom_score_adj = Path(f"/proc/{process.pid}/oom_score_adj").read_text()
+        assert oom_score_adj.strip() == "-500"
+    finally:
+        process.kill()
+        process.wait()
+
+    # Out of range values are rejected.
+    process = subprocess.run(
+        [fc_binary, "--api-sock", api_sock, "--oom-score-adj", "1001"],
+        capture_output=True,
+        timeout=3,
+        check=False,
+    )
+    assert process.returncode == 153
+    assert "InvalidValue" in process.stderr.decode()
+
+
+def test_cli_max_open_files(uvm_plain):
+    """
+    Test --max-open-files parameter
+    """
+    microvm = uvm_plain
+    microvm.jailer.extra_args.update({"max-open-files": 32})
+    microvm.spawn()
+
+    limits = Path(f"/proc/{microvm.firecracker_pid}/limits").read_text()
+    assert re.search(r"^Max open files\s+32\s", limits, re.MULTILINE)
+
+    # 32 files are not enough for the base VMM, 2 vCPUs and the root drive.
+    microvm.basic_config(vcpu_count=2)
+    with pytest.raises(RuntimeError, match="open files limit of 32 is below"):
+        microvm.start()
+
+
+def test_cli_lock_dir(microvm_factory, tmp_path):
+    """
+    Test that --lock-dir rejects a second instance with the same --id
+    """
+    fc_binary = microvm_factory.fc_binary_path
+    lock_dir = tmp_path / "locks"
+    lock_dir.mkdir()
+
+    def fc_args(api_sock):
+        return [
+            fc_binary,
+            "--api-sock",
+            tmp_path / api_sock,
+            "--id",
+            "dup-instance",
+            "--lock-dir",
+            lock_dir,
+        ]
+
+    process = subprocess.Popen(fc_args("api1.sock"))
+    try:
+        # The lock is taken before the API socket is created.
+        wait_for_path(tmp_path / "api1.sock")
+        assert (lock_dir / "dup-instance.lock").exists()
+
+        duplicate = subprocess.run(
+            fc_args("api2.sock"), capture_output=True, timeout=3, check=False
+        )
+        assert duplicate.returncode != 0
+        assert "Duplicate" in duplicate.stderr.decode()
+    finally:
+        process.kill()
+        process.wait()
+
+    # The lock is released when the first instance exits.
+    process = subprocess.Popen(fc_args("api3.sock"))
+    try:
+        wait_for_path(tmp_path / "api3.sock")
+    finally:
+        process.kill()
+        process.wait()
+
+
+def test_cli_instance_id_json_logs(microvm_factory, tmp_path):
+    """
+    Test that --instance-id is in every JSON log line
+    """
+    fc_binary = microvm_factory.fc_binary_path
+    log_path = tmp_path / "log.ndjson"
+    log_path.touch()
+
+    process = subprocess.Popen(
+        [
+            fc_binary,
+            "--api-sock",
+            tmp_path / "api.sock",
+            "--instance-id",
+            "my-instance",
+            "--log-path",
+            log_path,
+            "--log-json",
+            "--show-level",
+        ]
+    )
+    try:
+        wait_for_path(tmp_path / "api.sock")
+    finally:
+        process.kill()
+        process.wait()
+
+    lines = [json.loads(line) for line in log_path.read_text().splitlines()]
+    assert lines
+    for line in lines:
+        assert line["instance_id"] == "my-instance"
+        assert "level" in line
+    assert any("Running Firecracker" in line["message"] for line in lines)
+
+    # --instance-id is an alias of --id, they cannot be used together.
+    conflict = subprocess.run(
+        [fc_binary, "--id", "one", "--instance-id", "two"],
+        capture_output=True,
+        timeout=3,
+        check=False,
+    )
+    assert conflict.returncode != 0
+
+
+def test_cli_vcpu_pool_size_invalid(microvm_factory, tmp_path):
+    """
+    Test that an invalid --vcpu-pool-size is reported instead of panicking
+    """
+    result = subprocess.run(
+        [
+            microvm_factory.fc_binary_path,
+            "--api-sock",
+            tmp_path / "api.sock",
+            "--vcpu-pool-size",
+            "two",
+        ],
+        capture_output=True,
+        timeout=3,
+        check=False,
+    )
+    # FcExitCode::ArgParsing
+    assert result.returncode == 153
+    assert "InvalidVcpuPoolSize" in result.stderr.decode()
+    assert "panicked" not in result.stderr.decode()
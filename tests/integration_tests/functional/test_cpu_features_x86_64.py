@@ -134,6 +134,22 @@ def test_extended_cache_features(uvm_plain_any):
     _check_extended_cache_features(vm)
 
 
+def test_cpuid_blocklist(uvm_plain_any):
+    """
+    Check that the CPUID bits of the blocklist are hidden from the guest.
+    """
+    vm = uvm_plain_any
+    vm.spawn()
+    vm.basic_config()
+    # RDRAND is CPUID.(EAX=01H):ECX[30].
+    vm.api.machine_config.patch(cpuid_blocklist=[{"leaf": 1, "ecx_mask": 1 << 30}])
+    vm.add_net_iface()
+    vm.start()
+
+    _, stdout, _ = vm.ssh.check_output("grep -c -w rdrand /proc/cpuinfo || true")
+    assert int(stdout) == 0
+
+
 def test_brand_string(uvm_plain_any):
     """
     Ensure good formatting for the guest brand string.
@@ -594,6 +594,45 @@ def test_mmds_limit_scenario(uvm_plain, version):
     assert len(str(response.json()).replace(" ", "")) == 158
 
 
+def test_mmds_rate_limit(uvm_plain):
+    """
+    Test that the guest connections to MMDS over the rate limit are dropped.
+    """
+    test_microvm = uvm_plain
+    test_microvm.spawn()
+
+    # Attach network device.
+    test_microvm.add_net_iface()
+    test_microvm.api.mmds_config.put(
+        network_interfaces=["eth0"], version="V1", mmds_rate_limit_rps=5
+    )
+    response = test_microvm.api.vm_config.get()
+    assert response.json()["mmds-config"]["mmds_rate_limit_rps"] == 5
+
+    data_store = {"latest": {"meta-data": {"ami-id": "ami-12345678"}}}
+    populate_data_store(test_microvm, data_store)
+
+    test_microvm.basic_config(vcpu_count=1)
+    test_microvm.start()
+    ssh_connection = test_microvm.ssh
+
+    run_guest_cmd(ssh_connection, f"ip route add {DEFAULT_IPV4} dev eth0", "")
+
+    # Open more connections than the burst of 10 allows, each one at a time.
+    get_cmd = f"curl -m 1 -s http://{DEFAULT_IPV4}/latest/meta-data/ami-id"
+    _, stdout, _ = ssh_connection.run(
+        f"for i in $(seq 30); do {get_cmd}; echo; done | grep -c ami-12345678"
+    )
+    assert 0 < int(stdout) < 30
+
+    fc_metrics = test_microvm.flush_metrics()
+    assert fc_metrics["mmds"]["connections_rate_limited"] > 0
+
+    # The rate limit bucket refills over time.
+    time.sleep(2)
+    run_guest_cmd(ssh_connection, get_cmd, "ami-12345678")
+
+
 @pytest.mark.parametrize("version", MMDS_VERSIONS)
 def test_mmds_snapshot(uvm_nano, microvm_factory, version):
     """
@@ -87,6 +87,43 @@ def test_multi_queue_unsupported(uvm_plain):
     utils.run_cmd(f"{microvm.netns.cmd_prefix()} ip link del name {tapname}")
 
 
+def test_guest_mtu(uvm_plain):
+    """
+    Check that the guest interface comes up with the MTU given to the net device.
+    """
+    microvm = uvm_plain
+    microvm.spawn()
+    microvm.basic_config()
+    microvm.add_net_iface(mtu=1400)
+    microvm.start()
+
+    _, stdout, _ = microvm.ssh.check_output("cat /sys/class/net/eth0/mtu")
+    assert stdout.strip() == "1400"
+
+    config = microvm.api.vm_config.get().json()
+    assert config["network-interfaces"][0]["mtu"] == 1400
+
+
+def test_guest_link_speed_duplex(uvm_plain):
+    """
+    Check that the guest reports the link speed and duplex mode given to the net device.
+    """
+    microvm = uvm_plain
+    microvm.spawn()
+    microvm.basic_config()
+    microvm.add_net_iface(link_speed_mbps=1000, duplex="Half")
+    microvm.start()
+
+    _, stdout, _ = microvm.ssh.check_output("cat /sys/class/net/eth0/speed")
+    assert stdout.strip() == "1000"
+    _, stdout, _ = microvm.ssh.check_output("cat /sys/class/net/eth0/duplex")
+    assert stdout.strip() == "half"
+
+    config = microvm.api.vm_config.get().json()
+    assert config["network-interfaces"][0]["link_speed_mbps"] == 1000
+    assert config["network-interfaces"][0]["duplex"] == "Half"
+
+
 @pytest.fixture
 def uvm_any(microvm_factory, uvm_ctor, guest_kernel, rootfs):
     """Return booted and restored uvm with no CPU templates"""
@@ -0,0 +1,50 @@
+# Copyright 2025 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+# SPDX-License-Identifier: Apache-2.0
+
+"""Tests for steal time reporting."""
+
+import platform
+import subprocess
+import time
+
+import pytest
+
+
+def get_steal_time(vm):
+    """Returns the steal time of the guest, in USER_HZ units, read from /proc/stat."""
+    _, stdout, _ = vm.ssh.check_output("head -1 /proc/stat")
+    # cpu  user nice system idle iowait irq softirq steal guest guest_nice
+    return int(stdout.split()[8])
+
+
+@pytest.mark.skipif(
+    platform.machine() != "aarch64",
+    reason="Steal time setup is only tested on aarch64.",
+)
+def test_steal_time_aarch64(uvm_plain_any):
+    """
+    Test that the guest sees time stolen by host processes competing with its vCPU.
+    """
+    vm = uvm_plain_any
+    vm.spawn()
+    vm.basic_config(vcpu_count=1)
+    vm.add_net_iface()
+    vm.start()
+
+    _, dmesg, _ = vm.ssh.check_output("dmesg")
+    assert "using stolen time PV" in dmesg
+
+    vm.pin_vcpu(0, 0)
+    steal_before = get_steal_time(vm)
+
+    # Keep the vCPU busy while a host process competes with it for the same CPU.
+    vm.ssh.check_output(
+        "nohup timeout 5 sh -c 'while :; do :; done' >/dev/null 2>&1 &"
+    )
+    with subprocess.Popen(
+        ["taskset", "-c", "0", "timeout", "5", "sh", "-c", "while :; do :; done"]
+    ) as competitor:
+        competitor.wait()
+    time.sleep(1)
+
+    assert get_steal_time(vm) > steal_before
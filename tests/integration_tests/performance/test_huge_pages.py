@@ -2,6 +2,7 @@
 # SPDX-License-Identifier: Apache-2.0
 """Integration tests for Firecracker's huge pages support"""
 import signal
+import subprocess
 import time
 
 import pytest
@@ -221,6 +222,54 @@ def test_ept_violation_count(
     metrics.put_metric(metric, int(metric_value), "Count")
 
 
+@pytest.mark.parametrize("thp_mode", ["never", "madvise", "always"])
+def test_thp_tlb_misses(uvm_plain, metrics, thp_mode):
+    """
+    Records the data TLB misses of Firecracker while the guest touches its memory, for each
+    transparent huge page mode of the guest memory.
+    """
+    vm = uvm_plain
+    vm.memory_monitor = None
+    vm.spawn()
+    vm.basic_config(mem_size_mib=512)
+    vm.api.machine_config.patch(thp_mode=thp_mode)
+    vm.add_net_iface()
+    vm.start()
+
+    metrics.set_dimensions(
+        {
+            "performance_test": "test_thp_tlb_misses",
+            "thp_mode": thp_mode,
+            **vm.dimensions,
+        }
+    )
+
+    events = ["dTLB-load-misses", "dTLB-store-misses"]
+    # pylint: disable=consider-using-with
+    perf = subprocess.Popen(
+        [
+            "perf",
+            "stat",
+            "-x,",
+            "-e",
+            ",".join(events),
+            "-p",
+            str(vm.firecracker_pid),
+        ],
+        stderr=subprocess.PIPE,
+        text=True,
+    )
+    vm.ssh.check_output("/usr/local/bin/fillmem 256")
+    perf.send_signal(signal.SIGINT)
+    _, stderr = perf.communicate(timeout=30)
+
+    # With `-x,`, each line is `<value>,<unit>,<event>,...`.
+    for line in stderr.splitlines():
+        fields = line.split(",")
+        if len(fields) > 2 and fields[2] in events and fields[0].isdigit():
+            metrics.put_metric(fields[2], int(fields[0]), "Count")
+
+
 def test_negative_huge_pages_plus_balloon(uvm_plain):
     """Tests that huge pages and memory ballooning cannot be used together"""
     uvm_plain.memory_monitor = None